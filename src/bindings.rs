@@ -0,0 +1,390 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Working out what role bindings and cluster role bindings bind to what, so a service account
+//! can be described with the roles it has, and a role with who has it.
+
+use k8s_openapi::api::rbac::v1 as api_rbac;
+
+use crate::env::Env;
+use crate::error::ClickError;
+use crate::kobj::{KObj, ObjType};
+
+/// The parts of a RoleBinding or ClusterRoleBinding needed to match it up with subjects and roles
+#[derive(Debug, PartialEq)]
+pub struct Binding {
+    /// The namespace of a RoleBinding, None for a ClusterRoleBinding
+    pub namespace: Option<String>,
+    pub name: String,
+    pub role_ref: api_rbac::RoleRef,
+    pub subjects: Vec<api_rbac::Subject>,
+}
+
+impl Binding {
+    pub fn from_role_binding(binding: api_rbac::RoleBinding) -> Binding {
+        Binding {
+            namespace: binding.metadata.namespace,
+            name: binding.metadata.name.unwrap_or_default(),
+            role_ref: binding.role_ref,
+            subjects: binding.subjects,
+        }
+    }
+
+    pub fn from_cluster_role_binding(binding: api_rbac::ClusterRoleBinding) -> Binding {
+        Binding {
+            namespace: None,
+            name: binding.metadata.name.unwrap_or_default(),
+            role_ref: binding.role_ref,
+            subjects: binding.subjects,
+        }
+    }
+
+    /// i.e. "RoleBinding dev/read-pods" or "ClusterRoleBinding view-all"
+    pub fn description(&self) -> String {
+        match self.namespace.as_ref() {
+            Some(ns) => format!("RoleBinding {}/{}", ns, self.name),
+            None => format!("ClusterRoleBinding {}", self.name),
+        }
+    }
+
+    /// Where the bound role applies. A cluster role bound by a RoleBinding only applies in the
+    /// namespace of the binding.
+    fn scope(&self) -> String {
+        match self.namespace.as_ref() {
+            Some(ns) => format!("in namespace {}", ns),
+            None => "cluster wide".to_string(),
+        }
+    }
+
+    /// The namespace of a service account subject. Subjects of a RoleBinding can leave it out to
+    /// mean the namespace of the binding.
+    fn subject_namespace<'a>(&'a self, subject: &'a api_rbac::Subject) -> Option<&'a str> {
+        subject
+            .namespace
+            .as_deref()
+            .filter(|ns| !ns.is_empty())
+            .or(self.namespace.as_deref())
+    }
+
+    /// How subject is shown, i.e. "ServiceAccount dev/ci" or "Group developers"
+    pub fn subject_str(&self, subject: &api_rbac::Subject) -> String {
+        match (subject.kind.as_str(), self.subject_namespace(subject)) {
+            ("ServiceAccount", Some(ns)) => format!("ServiceAccount {}/{}", ns, subject.name),
+            (kind, _) => format!("{} {}", kind, subject.name),
+        }
+    }
+
+    /// The subject of this binding that binds service account ns/name, either directly or through
+    /// one of the groups all service accounts like it are in
+    pub fn service_account_subject(&self, ns: &str, name: &str) -> Option<&api_rbac::Subject> {
+        let groups = service_account_groups(ns);
+        self.subjects
+            .iter()
+            .find(|subject| match subject.kind.as_str() {
+                "ServiceAccount" => {
+                    subject.name == name && self.subject_namespace(subject) == Some(ns)
+                }
+                "Group" => groups.contains(&subject.name),
+                _ => false,
+            })
+    }
+
+    /// Does this binding bind role kind/name. A Role can only be bound by bindings in its own
+    /// namespace, a ClusterRole by any binding.
+    pub fn binds_role(&self, kind: &str, namespace: Option<&str>, name: &str) -> bool {
+        self.role_ref.kind == kind
+            && self.role_ref.name == name
+            && (kind == "ClusterRole" || self.namespace.as_deref() == namespace)
+    }
+}
+
+/// The groups every service account in namespace ns is in
+pub fn service_account_groups(ns: &str) -> Vec<String> {
+    vec![
+        "system:serviceaccounts".to_string(),
+        format!("system:serviceaccounts:{}", ns),
+        "system:authenticated".to_string(),
+    ]
+}
+
+/// A line for each role bound to service account ns/name by bindings
+pub fn service_account_roles(bindings: &[Binding], ns: &str, name: &str) -> Vec<String> {
+    bindings
+        .iter()
+        .filter_map(|binding| {
+            binding.service_account_subject(ns, name).map(|subject| {
+                let through = if subject.kind == "Group" {
+                    format!(" (through group {})", subject.name)
+                } else {
+                    String::new()
+                };
+                format!(
+                    "{} {}, {}, from {}{}",
+                    binding.role_ref.kind,
+                    binding.role_ref.name,
+                    binding.scope(),
+                    binding.description(),
+                    through
+                )
+            })
+        })
+        .collect()
+}
+
+/// A line for each subject role kind/name is bound to by bindings
+pub fn role_subjects(
+    bindings: &[Binding],
+    kind: &str,
+    namespace: Option<&str>,
+    name: &str,
+) -> Vec<String> {
+    bindings
+        .iter()
+        .filter(|binding| binding.binds_role(kind, namespace, name))
+        .flat_map(|binding| {
+            binding.subjects.iter().map(move |subject| {
+                format!(
+                    "{}, {}, from {}",
+                    binding.subject_str(subject),
+                    binding.scope(),
+                    binding.description()
+                )
+            })
+        })
+        .collect()
+}
+
+/// The role bindings in namespace, or in all namespaces if it's None
+fn role_bindings(env: &Env, namespace: Option<&str>) -> Result<Vec<Binding>, ClickError> {
+    let (request, _) = match namespace {
+        Some(ns) => api_rbac::RoleBinding::list_namespaced_role_binding(ns, Default::default())?,
+        None => api_rbac::RoleBinding::list_role_binding_for_all_namespaces(Default::default())?,
+    };
+    let list = env.run_on_context(|c| c.execute_list::<api_rbac::RoleBinding>(request))?;
+    Ok(list
+        .items
+        .into_iter()
+        .map(Binding::from_role_binding)
+        .collect())
+}
+
+fn cluster_role_bindings(env: &Env) -> Result<Vec<Binding>, ClickError> {
+    let (request, _) = api_rbac::ClusterRoleBinding::list_cluster_role_binding(Default::default())?;
+    let list = env.run_on_context(|c| c.execute_list::<api_rbac::ClusterRoleBinding>(request))?;
+    Ok(list
+        .items
+        .into_iter()
+        .map(Binding::from_cluster_role_binding)
+        .collect())
+}
+
+/// The role bindings in all namespaces. Plenty of users can't list those, so if that's forbidden
+/// the ones in fallback are returned instead, along with a note saying some may be missing.
+fn role_bindings_everywhere(
+    env: &Env,
+    fallback: Option<&str>,
+) -> Result<(Vec<Binding>, Option<String>), ClickError> {
+    match role_bindings(env, None) {
+        Ok(bindings) => Ok((bindings, None)),
+        Err(ClickError::Api { code: 403, .. }) => match fallback {
+            Some(ns) => Ok((
+                role_bindings(env, Some(ns))?,
+                Some(format!(
+                    "Not allowed to list role bindings in all namespaces, only those in {} are \
+                     included",
+                    ns
+                )),
+            )),
+            None => Ok((
+                vec![],
+                Some(
+                    "Not allowed to list role bindings in all namespaces, only cluster role \
+                     bindings are included"
+                        .to_string(),
+                ),
+            )),
+        },
+        Err(e) => Err(e),
+    }
+}
+
+fn format_section(title: &str, lines: Vec<String>, note: Option<String>) -> String {
+    let mut section = format!("{}\n", title);
+    if lines.is_empty() {
+        section.push_str("  <none>\n");
+    }
+    for line in lines.iter() {
+        section.push_str(&format!("  {}\n", line));
+    }
+    if let Some(note) = note {
+        section.push_str(&format!("  ({})\n", note));
+    }
+    section
+}
+
+/// The part of the description of a service account that lists the roles bound to it, or of a
+/// role or cluster role that lists who it's bound to. Empty for any other kind of object.
+pub fn describe_bindings(env: &Env, obj: &KObj) -> Result<String, ClickError> {
+    match obj.typ {
+        ObjType::ServiceAccount => {
+            let ns = obj.namespace.as_deref().unwrap_or("default");
+            // a RoleBinding in any namespace can bind a service account
+            let (mut bindings, note) = role_bindings_everywhere(env, Some(ns))?;
+            bindings.append(&mut cluster_role_bindings(env)?);
+            Ok(format_section(
+                "Bound Roles:",
+                service_account_roles(&bindings, ns, obj.name()),
+                note,
+            ))
+        }
+        ObjType::Role => {
+            let ns = obj.namespace.as_deref();
+            Ok(format_section(
+                "Bound To:",
+                role_subjects(&role_bindings(env, ns)?, "Role", ns, obj.name()),
+                None,
+            ))
+        }
+        ObjType::ClusterRole => {
+            let mut bindings = cluster_role_bindings(env)?;
+            let (mut role_bindings, note) =
+                role_bindings_everywhere(env, env.namespace.as_deref())?;
+            bindings.append(&mut role_bindings);
+            Ok(format_section(
+                "Bound To:",
+                role_subjects(&bindings, "ClusterRole", None, obj.name()),
+                note,
+            ))
+        }
+        _ => Ok(String::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subject(kind: &str, name: &str, namespace: Option<&str>) -> api_rbac::Subject {
+        api_rbac::Subject {
+            api_group: None,
+            kind: kind.to_string(),
+            name: name.to_string(),
+            namespace: namespace.map(|ns| ns.to_string()),
+        }
+    }
+
+    fn binding(
+        namespace: Option<&str>,
+        name: &str,
+        role_kind: &str,
+        role_name: &str,
+        subjects: Vec<api_rbac::Subject>,
+    ) -> Binding {
+        Binding {
+            namespace: namespace.map(|ns| ns.to_string()),
+            name: name.to_string(),
+            role_ref: api_rbac::RoleRef {
+                api_group: "rbac.authorization.k8s.io".to_string(),
+                kind: role_kind.to_string(),
+                name: role_name.to_string(),
+            },
+            subjects,
+        }
+    }
+
+    #[test]
+    fn test_service_account_subject() {
+        let b = binding(
+            Some("dev"),
+            "ci",
+            "Role",
+            "deployer",
+            vec![
+                subject("User", "alice", None),
+                // no namespace means the binding's namespace
+                subject("ServiceAccount", "ci", None),
+                subject("ServiceAccount", "builder", Some("build")),
+            ],
+        );
+        assert_eq!(b.service_account_subject("dev", "ci").unwrap().name, "ci");
+        assert_eq!(
+            b.service_account_subject("build", "builder").unwrap().name,
+            "builder"
+        );
+        assert!(b.service_account_subject("build", "ci").is_none());
+        assert!(b.service_account_subject("dev", "alice").is_none());
+        assert_eq!(b.subject_str(&b.subjects[1]), "ServiceAccount dev/ci");
+        assert_eq!(b.subject_str(&b.subjects[0]), "User alice");
+
+        let groups = binding(
+            None,
+            "all-sas",
+            "ClusterRole",
+            "view",
+            vec![subject("Group", "system:serviceaccounts:dev", None)],
+        );
+        assert!(groups.service_account_subject("dev", "anything").is_some());
+        assert!(groups.service_account_subject("prod", "anything").is_none());
+    }
+
+    #[test]
+    fn test_binds_role() {
+        let rb = binding(Some("dev"), "rb", "Role", "reader", vec![]);
+        assert!(rb.binds_role("Role", Some("dev"), "reader"));
+        assert!(!rb.binds_role("Role", Some("prod"), "reader"));
+        assert!(!rb.binds_role("ClusterRole", None, "reader"));
+        let rb_cr = binding(Some("dev"), "rb", "ClusterRole", "edit", vec![]);
+        assert!(rb_cr.binds_role("ClusterRole", None, "edit"));
+        assert!(!rb_cr.binds_role("ClusterRole", None, "view"));
+    }
+
+    #[test]
+    fn test_binding_lines() {
+        let bindings = vec![
+            binding(
+                None,
+                "view-all",
+                "ClusterRole",
+                "view",
+                vec![subject("Group", "system:serviceaccounts", None)],
+            ),
+            binding(
+                Some("dev"),
+                "edit-ci",
+                "ClusterRole",
+                "edit",
+                vec![
+                    subject("ServiceAccount", "ci", Some("build")),
+                    subject("Group", "developers", None),
+                ],
+            ),
+        ];
+        assert_eq!(
+            service_account_roles(&bindings, "build", "ci"),
+            vec![
+                "ClusterRole view, cluster wide, from ClusterRoleBinding view-all (through group \
+                 system:serviceaccounts)",
+                "ClusterRole edit, in namespace dev, from RoleBinding dev/edit-ci",
+            ]
+        );
+        assert_eq!(
+            role_subjects(&bindings, "ClusterRole", None, "edit"),
+            vec![
+                "ServiceAccount build/ci, in namespace dev, from RoleBinding dev/edit-ci",
+                "Group developers, in namespace dev, from RoleBinding dev/edit-ci",
+            ]
+        );
+        assert!(role_subjects(&bindings, "Role", Some("dev"), "edit").is_empty());
+    }
+}
@@ -12,40 +12,82 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use ansi_term::Colour::Yellow;
 use clap::{App, Arg};
 use rustyline::completion::Pair as RustlinePair;
 
 use crate::{
     command::command_def::{exec_match, start_clap, Cmd},
+    command_processor::is_builtin_command,
     completer, config,
     env::Env,
+    error::ClickError,
     output::ClickWriter,
+    theme::{self, Role},
 };
 
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::io::Write;
 
+/// Words that can't be aliases, because they mean something to the alias command
+const RESERVED_NAMES: &[&str] = &["add", "list", "rm", "context"];
+
+/// Define alias as expanded, refusing names that would shadow a command, or that would expand
+/// forever
+fn add_alias(
+    env: &mut Env,
+    alias: &str,
+    expanded: &str,
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    if alias.parse::<usize>().is_ok() || RESERVED_NAMES.contains(&alias) {
+        return Err(ClickError::CommandError(format!(
+            "An alias can't be a number, or any of: {}",
+            RESERVED_NAMES.join(", ")
+        )));
+    }
+    if is_builtin_command(alias) {
+        return Err(ClickError::CommandError(format!(
+            "{} is a click command, and can't be replaced by an alias",
+            alias
+        )));
+    }
+    if let Some(cycle) = env.alias_cycle(alias, expanded) {
+        return Err(ClickError::CommandError(format!(
+            "That would make {} expand forever: {}",
+            alias,
+            cycle.join(" -> ")
+        )));
+    }
+    env.add_alias(config::Alias {
+        alias: alias.to_owned(),
+        expanded: expanded.to_owned(),
+    });
+    clickwriteln!(writer, "aliased {} = '{}'", alias, expanded);
+    Ok(())
+}
+
+fn list_aliases(env: &Env, writer: &mut ClickWriter) {
+    for alias in env.click_config.aliases.iter() {
+        clickwriteln!(writer, "alias {} = '{}'", alias.alias, alias.expanded);
+    }
+    for (alias, context) in env.click_config.context_aliases.iter() {
+        clickwriteln!(writer, "alias context {} = '{}'", alias, context);
+    }
+}
+
 command!(
     Alias,
     "alias",
-    "Define or display aliases",
+    "Define, list, or remove aliases",
     |clap: App<'static, 'static>| clap
         .arg(
             Arg::with_name("alias")
                 .help(
-                    "the short version of the command.\nCannot be 'alias', 'unalias', or a number."
+                    "add, list, or rm. Or the short version of the command, as a shorter way to \
+                     add an alias."
                 )
-                .validator(|s: String| {
-                    if s == "alias" || s == "unalias" || s.parse::<usize>().is_ok() {
-                        Err("alias cannot be \"alias\", \"unalias\", or a number".to_owned())
-                    } else {
-                        Ok(())
-                    }
-                })
                 .required(false)
-                .requires("expanded")
         )
         .arg(
             Arg::with_name("expanded")
@@ -53,45 +95,106 @@ command!(
                 .required(false)
                 .requires("alias")
         )
+        .arg(
+            Arg::with_name("context_alias")
+                .help("when using 'alias context NAME ALIAS', the alias to create for context NAME")
+                .required(false)
+                .requires("expanded")
+        )
         .after_help(
             "An alias is a substitution rule.  When click encounters an alias at the start of a
-command, it will substitue the expanded version for what was typed.
+command, it will substitue the expanded version for what was typed. Anything typed after the alias
+is added to the end of the expansion, so aliases can hold flags and still take more arguments.
 
-As with Bash: The first word of the expansion is tested for aliases, but a word that is identical to
-an alias being expanded is not expanded a second time.  So one can alias logs to \"logs -e\", for
-instance, without causing infinite expansion.
+The first word of the expansion is tested for aliases too, so aliases can build on each other. An
+alias can't have the same name as a click command, and aliases that would expand forever (like p to
+q and q to p) are rejected.
 
 Examples:
   # Display current aliases
-  alias
+  alias list
 
-  # alias p to pods
-  alias p pods
+  # alias p to pods with the app=ingest label
+  alias add p \"pods -l app=ingest\"
 
-  # alias pn to get pods with nginx in the name
-  alias pn \"pods -r nginx\"
+  # alias lg to follow logs, then follow the sidecar's logs with 'lg -c sidecar'
+  alias add lg \"logs -f --tail 100\"
 
   # alias el to run logs and grep for ERROR
-  alias el \"logs | grep ERROR\""
+  alias add el \"logs | grep ERROR\"
+
+  # remove the el alias
+  alias rm el
+
+  # 'alias NAME EXPANSION' is short for 'alias add NAME EXPANSION'
+  alias pn \"pods -r nginx\"
+
+Context aliases:
+  'alias context NAME ALIAS' defines ALIAS as another name for the context NAME. The alias can be
+  used anywhere a context name is expected (i.e. 'context ALIAS'), and is shown in the prompt.
+  If a real context has the same name as an alias, the real context wins. Remove a context alias
+  with 'unalias context ALIAS'.
+
+  # make 'prod' refer to the 'prod-us-east-1' context
+  alias context prod-us-east-1 prod"
         ),
     vec!["alias", "aliases"],
-    noop_complete!(),
+    vec![
+        &completer::aliasaction_values_completer,
+        &completer::alias_completer
+    ],
     no_named_complete!(),
     |matches, env, writer| {
-        if matches.is_present("alias") {
-            let alias = matches.value_of("alias").unwrap(); // safe, checked above
-            let expanded = matches.value_of("expanded").unwrap(); // safe, required with alias
-            env.add_alias(config::Alias {
-                alias: alias.to_owned(),
-                expanded: expanded.to_owned(),
-            });
-            clickwriteln!(writer, "aliased {} = '{}'", alias, expanded);
-        } else {
-            for alias in env.click_config.aliases.iter() {
-                clickwriteln!(writer, "alias {} = '{}'", alias.alias, alias.expanded);
+        let first = matches.value_of("alias");
+        let second = matches.value_of("expanded");
+        let third = matches.value_of("context_alias");
+        match (first, second, third) {
+            (None, _, _) | (Some("list"), None, _) => {
+                list_aliases(env, writer);
+                Ok(())
+            }
+            (Some("add"), Some(alias), Some(expanded)) => add_alias(env, alias, expanded, writer),
+            (Some("add"), _, _) => Err(ClickError::CommandError(
+                "Usage: alias add NAME EXPANSION".to_string(),
+            )),
+            (Some("rm"), Some(alias), None) => {
+                if env.remove_alias(alias) {
+                    clickwriteln!(writer, "unaliased: {}", alias);
+                } else {
+                    clickwriteln!(writer, "no such alias: {}", alias);
+                }
+                Ok(())
+            }
+            (Some("context"), Some(context), Some(ctx_alias)) => {
+                if !env.add_context_alias(ctx_alias, context) {
+                    return Err(ClickError::CommandError(format!(
+                        "No such context: {}",
+                        context
+                    )));
+                }
+                if env.config.contexts.contains_key(ctx_alias) {
+                    clickwriteln!(
+                        writer,
+                        "{}",
+                        theme::style(Role::Warning).paint(format!(
+                            "[WARN] There is a context named {}, which will shadow this alias",
+                            ctx_alias
+                        ))
+                    );
+                }
+                clickwriteln!(writer, "aliased context {} = '{}'", ctx_alias, context);
+                Ok(())
             }
+            (Some(alias), Some(expanded), None) => add_alias(env, alias, expanded, writer),
+            (Some(_), Some(_), Some(_)) => Err(ClickError::CommandError(
+                "Too many arguments. Did you mean 'alias add NAME EXPANSION' or \
+                 'alias context NAME ALIAS'?"
+                    .to_string(),
+            )),
+            (Some(_), None, _) => Err(ClickError::CommandError(
+                "Missing what the alias should expand to. See 'alias --help'".to_string(),
+            )),
         }
-        Ok(())
     }
 );
 
@@ -99,17 +202,34 @@ command!(
     Unalias,
     "unalias",
     "Remove an alias",
-    |clap: App<'static, 'static>| clap.arg(
-        Arg::with_name("alias")
-            .help("Short version of alias to remove")
-            .required(true)
-    ),
+    |clap: App<'static, 'static>| clap
+        .arg(
+            Arg::with_name("alias")
+                .help("Short version of alias to remove")
+                .required(true)
+        )
+        .arg(
+            Arg::with_name("context_alias")
+                .help("when using 'unalias context ALIAS', the context alias to remove")
+                .required(false)
+        ),
     vec!["unalias"],
-    noop_complete!(),
+    vec![&completer::alias_completer],
     no_named_complete!(),
     |matches, env, writer| {
         let alias = matches.value_of("alias").unwrap(); // safe, required
-        if env.remove_alias(alias) {
+        if let Some(ctx_alias) = matches.value_of("context_alias") {
+            if alias != "context" {
+                return Err(ClickError::CommandError(
+                    "Too many arguments. Did you mean 'unalias context ALIAS'?".to_string(),
+                ));
+            }
+            if env.remove_context_alias(ctx_alias) {
+                clickwriteln!(writer, "unaliased context: {}", ctx_alias);
+            } else {
+                clickwriteln!(writer, "no such context alias: {}", ctx_alias);
+            }
+        } else if env.remove_alias(alias) {
             clickwriteln!(writer, "unaliased: {}", alias);
         } else {
             clickwriteln!(writer, "no such alias: {}", alias);
@@ -117,3 +237,83 @@ command!(
         Ok(())
     }
 );
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{get_test_config, ClickConfig, ContextConf};
+
+    fn test_env(dir: &tempdir::TempDir) -> Env {
+        let mut config = get_test_config();
+        for name in ["prod-us-east-1", "prod"].iter() {
+            config.contexts.insert(
+                name.to_string(),
+                ContextConf {
+                    cluster: "cluster".to_string(),
+                    user: "user".to_string(),
+                    namespace: None,
+                },
+            );
+        }
+        Env::new(
+            config,
+            ClickConfig::default(),
+            dir.path().join("click.config"),
+        )
+    }
+
+    fn run(cmd: &dyn Cmd, env: &mut Env, args: &[&str]) -> String {
+        let mut writer = ClickWriter::with_buffer(vec![], false);
+        cmd.exec(env, &mut args.iter().copied(), &mut writer)
+            .unwrap();
+        String::from_utf8(writer.finish_output().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn remove_aliases() {
+        let dir = tempdir::TempDir::new("click_alias_test").unwrap();
+        let mut env = test_env(&dir);
+        run(&Alias::new(), &mut env, &["add", "p", "pods"]);
+        run(&Alias::new(), &mut env, &["add", "l", "logs -f"]);
+        let out = run(&Alias::new(), &mut env, &["rm", "p"]);
+        assert_eq!(out, "unaliased: p\n");
+        let names: Vec<&str> = env
+            .click_config
+            .aliases
+            .iter()
+            .map(|a| a.alias.as_str())
+            .collect();
+        assert_eq!(names, vec!["l"]);
+        let out = run(&Unalias::new(), &mut env, &["p"]);
+        assert_eq!(out, "no such alias: p\n");
+
+        run(
+            &Alias::new(),
+            &mut env,
+            &["context", "prod-us-east-1", "east"],
+        );
+        assert_eq!(env.resolve_context_alias("east"), Some("prod-us-east-1"));
+        let out = run(&Unalias::new(), &mut env, &["context", "east"]);
+        assert_eq!(out, "unaliased context: east\n");
+        assert!(env.click_config.context_aliases.is_empty());
+        assert_eq!(env.resolve_context_alias("east"), None);
+    }
+
+    #[test]
+    fn context_alias_shadowed() {
+        let dir = tempdir::TempDir::new("click_alias_test").unwrap();
+        let mut env = test_env(&dir);
+        let out = run(
+            &Alias::new(),
+            &mut env,
+            &["context", "prod-us-east-1", "prod"],
+        );
+        assert_eq!(
+            out,
+            "[WARN] There is a context named prod, which will shadow this alias\n\
+             aliased context prod = 'prod-us-east-1'\n"
+        );
+        // the alias is still added, in case the context goes away
+        assert_eq!(env.resolve_context_alias("prod"), Some("prod-us-east-1"));
+    }
+}
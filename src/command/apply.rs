@@ -0,0 +1,216 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use clap::{App, Arg};
+use rustyline::completion::Pair as RustlinePair;
+use serde_json::Value;
+
+use crate::{
+    command::command_def::{exec_match, start_clap, Cmd},
+    command::{command_line, kubectl_command, kubectl_output_with_input},
+    completer,
+    env::Env,
+    error::ClickError,
+    kobj::KObj,
+    manifest,
+    output::ClickWriter,
+};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Files in a directory that are treated as manifests
+const MANIFEST_EXTENSIONS: &[&str] = &["yaml", "yml", "json"];
+
+/// Add the manifest files in dir to files, in name order, descending into subdirectories if
+/// recursive is set
+fn collect_dir(dir: &Path, recursive: bool, files: &mut Vec<PathBuf>) -> Result<(), ClickError> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<_, _>>()?;
+    entries.sort();
+    for path in entries.into_iter() {
+        if path.is_dir() {
+            if recursive {
+                collect_dir(&path, recursive, files)?;
+            }
+        } else if path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| MANIFEST_EXTENSIONS.contains(&ext))
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Read all the manifests from filename, which can be a file, a directory, or - for stdin
+pub fn read_manifests(filename: &str, recursive: bool) -> Result<Vec<Value>, ClickError> {
+    if filename == "-" {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text)?;
+        return manifest::parse_manifests("<stdin>", &text);
+    }
+    let path = Path::new(filename);
+    let mut files = vec![];
+    if path.is_dir() {
+        collect_dir(path, recursive, &mut files)?;
+    } else {
+        files.push(path.to_path_buf());
+    }
+    let mut manifests = vec![];
+    for file in files.iter() {
+        let text = fs::read_to_string(file).map_err(|e| {
+            ClickError::CommandError(format!("Could not read {}: {}", file.display(), e))
+        })?;
+        manifests.extend(manifest::parse_manifests(&file.to_string_lossy(), &text)?);
+    }
+    Ok(manifests)
+}
+
+/// Apply a single manifest. kubectl prints what it did, i.e. "deployment.apps/foo configured"
+fn apply_manifest(
+    env: &Env,
+    manifest: &Value,
+    namespace: Option<&str>,
+    dry_run: Option<&str>,
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    let mut command = kubectl_command(env, namespace)?;
+    command.arg("apply").arg("-f").arg("-");
+    if let Some(mode) = dry_run {
+        command.arg(format!("--dry-run={}", mode));
+    }
+    if env.dry_run {
+        clickwriteln!(
+            writer,
+            "[DRY RUN] {} < {}",
+            command_line(&command),
+            manifest
+        );
+        return Ok(());
+    }
+    let out = kubectl_output_with_input(command, manifest.to_string().as_bytes())?;
+    clickwrite!(writer, "{}", out);
+    Ok(())
+}
+
+command!(
+    Apply,
+    "apply",
+    "Create or update objects from manifest files",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("filename")
+                .short("f")
+                .long("filename")
+                .help(
+                    "A yaml or json file of objects to apply, a directory of them, or - to read \
+                     from stdin. Can be given more than once.",
+                )
+                .required(true)
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("recursive")
+                .short("R")
+                .long("recursive")
+                .help("Also apply manifests in subdirectories of directories given with -f")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("dry_run")
+                .long("dry-run")
+                .help(
+                    "Only show what would be applied. 'client' just checks the manifests, \
+                     'server' has the server process the objects without saving them.",
+                )
+                .takes_value(true)
+                .possible_values(&["client", "server"]),
+        )
+        .arg(
+            Arg::with_name("select")
+                .short("s")
+                .long("select")
+                .help("Select the applied objects, so you can describe or delete them")
+                .takes_value(false),
+        )
+        .after_help(
+            "Objects are applied in the namespace set in their manifest, or the active namespace if \
+they don't have one. Nothing is ever pruned. After applying, the applied objects can be selected \
+by number, in the order they were applied, or all at once with --select.
+
+Examples:
+  # apply a file, and select what was applied
+  apply -f deployment.yaml --select
+
+  # see what the server would do with a directory of manifests
+  apply -f manifests/ -R --dry-run server",
+        )
+    },
+    vec!["apply"],
+    noop_complete!(),
+    no_named_complete!(),
+    |matches, env, writer| {
+        // read and check everything before applying anything
+        let mut manifests = vec![];
+        for filename in matches.values_of("filename").unwrap() {
+            // safe, required
+            manifests.extend(read_manifests(filename, matches.is_present("recursive"))?);
+        }
+        if manifests.is_empty() {
+            return Err(ClickError::CommandError(
+                "No objects found to apply".to_string(),
+            ));
+        }
+        let mut targets = vec![];
+        for manifest in manifests.iter() {
+            let namespace = manifest::manifest_namespace(manifest, env.namespace.as_deref());
+            targets.push(manifest::manifest_kobj(manifest, namespace)?);
+        }
+
+        let dry_run = matches.value_of("dry_run");
+        let mut applied: Vec<KObj> = vec![];
+        let mut errors = vec![];
+        for (manifest, obj) in manifests.iter().zip(targets.into_iter()) {
+            match apply_manifest(env, manifest, obj.namespace.as_deref(), dry_run, writer) {
+                Ok(()) => applied.push(obj),
+                Err(e) => errors.push((obj.name().to_string(), e)),
+            }
+        }
+
+        if dry_run.is_none() && !env.dry_run && !applied.is_empty() {
+            env.invalidate_selection();
+            env.set_last_objs(applied.clone());
+            if matches.is_present("select") {
+                if applied.len() == 1 {
+                    env.set_current(0);
+                } else {
+                    env.set_range(applied);
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ClickError::MultiError(errors))
+        }
+    }
+);
@@ -0,0 +1,192 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use clap::{App, Arg};
+use rustyline::completion::Pair as RustlinePair;
+use serde_json::json;
+
+use crate::{
+    command::command_def::{exec_match, start_clap, Cmd},
+    command::{format_duration, valid_u32},
+    completer,
+    env::Env,
+    error::ClickError,
+    jobs::JobStatus,
+    output::ClickWriter,
+    table::CellSpec,
+    theme::{self, Role},
+};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+command!(
+    BgJobs,
+    "bgjobs",
+    "List background jobs, like commands run with '&' and the watches started with 'wait \
+     --notify'. ('jobs' lists kubernetes jobs.)",
+    |clap: App<'static, 'static>| {
+        clap.after_help(
+            "Jobs that have finished are listed once, with how they ended, and then forgotten. \
+Use 'fg N' to see what job N has printed, and 'kill N' to stop it. See 'help bg' for running \
+commands in the background.",
+        )
+    },
+    vec!["bgjobs"],
+    noop_complete!(),
+    no_named_complete!(),
+    |_matches, env, writer| {
+        let mut raw = vec![];
+        let mut specs = vec![];
+        for job in env.jobs().iter() {
+            let status = job.status();
+            let running_for = chrono::Duration::from_std(job.started.elapsed())
+                .unwrap_or_else(|_| chrono::Duration::zero());
+            raw.push(json!({
+                "id": job.id,
+                "description": job.description,
+                "status": status.to_string(),
+                "seconds": job.started.elapsed().as_secs(),
+            }));
+            let role = match status {
+                JobStatus::Running => None,
+                JobStatus::Done(_) => Some(Role::GoodStatus),
+                JobStatus::Failed(_) => Some(Role::BadStatus),
+                JobStatus::Killed => Some(Role::Warning),
+            };
+            let status_cell = match role {
+                Some(role) => CellSpec::with_role(status.to_string().into(), role),
+                None => status.to_string().into(),
+            };
+            specs.push(vec![
+                job.id.to_string().into(),
+                status_cell,
+                format_duration(running_for).into(),
+                job.description.clone().into(),
+            ]);
+        }
+        env.jobs_mut().remove_finished();
+
+        if writer.print_raw(&raw)? {
+            return Ok(());
+        }
+        if specs.is_empty() {
+            clickwriteln!(writer, "No background jobs");
+            return Ok(());
+        }
+        crate::table::print_table(row!["Job", "Status", "Age", "Command"], specs, writer);
+        Ok(())
+    }
+);
+
+command!(
+    Kill,
+    "kill",
+    "Stop a background job",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("job")
+                .help("The number of the job to stop, from 'bgjobs'")
+                .required(true)
+                .validator(valid_u32)
+                .index(1),
+        )
+    },
+    vec!["kill"],
+    noop_complete!(),
+    no_named_complete!(),
+    |matches, env, writer| {
+        let id = matches.value_of("job").unwrap().parse().unwrap(); // safe, required and validated
+        env.jobs_mut().kill(id)?;
+        clickwriteln!(writer, "Stopped job {}", id);
+        Ok(())
+    }
+);
+
+command!(
+    Fg,
+    "fg",
+    "Show the output of a background job, and follow it until the job finishes",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("job")
+                .help("The number of the job to show, from 'bgjobs'")
+                .required(true)
+                .validator(valid_u32)
+                .index(1),
+        )
+        .after_help(
+            "Everything the job has printed so far is shown first, as far back as \
+job_output_lines goes. Hit ctrl-c to stop following the job, which leaves it running in the \
+background.",
+        )
+    },
+    vec!["fg"],
+    noop_complete!(),
+    no_named_complete!(),
+    |matches, env, writer| {
+        let id = matches.value_of("job").unwrap().parse().unwrap(); // safe, required and validated
+        let job = env
+            .jobs()
+            .get(id)
+            .ok_or_else(|| ClickError::CommandError(format!("No job {}", id)))?;
+        let output = job.output().ok_or_else(|| {
+            ClickError::CommandError(format!(
+                "Job {} ({}) doesn't run a command, so has no output to show",
+                id, job.description
+            ))
+        })?;
+        writer.finish_paging();
+        env.ctrlcbool.store(false, Ordering::SeqCst);
+        let mut seen = 0;
+        loop {
+            // check before reading, so nothing printed just before the job finished is missed
+            let running = env.jobs().get(id).is_some_and(|job| job.is_running());
+            let (dropped, lines) = output.lock().unwrap().since(seen);
+            if dropped > 0 {
+                clickwriteln!(
+                    writer,
+                    "{}",
+                    theme::style(Role::Warning).paint(format!(
+                        "[{} earlier line{} dropped, job_output_lines is {}]",
+                        dropped,
+                        if dropped == 1 { "" } else { "s" },
+                        env.click_config.job_output_lines
+                    ))
+                );
+            }
+            seen += dropped + lines.len();
+            for line in lines.iter() {
+                clickwriteln!(writer, "{}", line);
+            }
+            if !running {
+                break;
+            }
+            if env.ctrlcbool.load(Ordering::SeqCst) {
+                env.ctrlcbool.store(false, Ordering::SeqCst);
+                clickwriteln!(writer, "Job {} is still running in the background", id);
+                return Ok(());
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        if let Some(job) = env.jobs().get(id) {
+            clickwriteln!(writer, "[job {}] {}", id, job.status());
+        }
+        Ok(())
+    }
+);
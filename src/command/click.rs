@@ -12,23 +12,35 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use ansi_term::Colour::Yellow;
+use atomicwrites::{AllowOverwrite, AtomicFile};
 use chrono::offset::Utc;
 use clap::{App, Arg};
 use prettytable::Table;
+use regex::Regex;
 use rustyline::completion::Pair as RustlinePair;
 
 use crate::{
     command::command_def::{exec_match, identity, start_clap, Cmd},
-    completer, config,
-    env::Env,
+    command::{confirm_action, format_duration, kubectl_get_json, resolve_fuzzy},
+    completer,
+    config::{self, is_go_duration, parse_verbose, CertExpiry, Impersonation, MAX_VERBOSE},
+    env::{self, Env, ErrorStrategy, ObjectSelection, ReloadSummary},
+    error::ClickError,
+    fuzzy,
+    kobj::KObj,
     output::ClickWriter,
+    parser::parse_index_list,
+    selection,
     table::CellSpec,
+    theme::{self, Role},
+    values::val_str_opt,
 };
 
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::fs;
 use std::io::{stderr, Write};
+use std::path::{Path, PathBuf};
 
 command!(
     Clear,
@@ -44,6 +56,54 @@ command!(
     }
 );
 
+fn print_reload_summary(summary: &ReloadSummary, writer: &mut ClickWriter) {
+    if !summary.added.is_empty() {
+        clickwriteln!(writer, "New contexts: {}", summary.added.join(", "));
+    }
+    if !summary.changed.is_empty() {
+        clickwriteln!(writer, "Changed contexts: {}", summary.changed.join(", "));
+    }
+    if !summary.removed.is_empty() {
+        clickwriteln!(writer, "Removed contexts: {}", summary.removed.join(", "));
+    }
+    if let Some(active) = summary.kept_active.as_ref() {
+        clickwriteln!(
+            writer,
+            "{}",
+            theme::style(Role::Warning).paint(format!(
+                "[WARN] The active context {} was removed from your kubeconfig. Keeping it for \
+                 this session, but you won't be able to switch back to it once you leave it.",
+                active
+            ))
+        );
+    }
+}
+
+/// If the kubeconfig has changed on disk since we loaded it, reload it and say if new contexts
+/// were found
+fn reload_if_stale(env: &mut Env, writer: &mut ClickWriter) {
+    if env.config.is_stale() {
+        match env.reload_config() {
+            Ok(summary) => {
+                if !summary.added.is_empty() {
+                    clickwriteln!(writer, "Your kubeconfig changed, found new contexts.");
+                }
+                print_reload_summary(&summary, writer);
+            }
+            Err(e) => {
+                clickwriteln!(
+                    writer,
+                    "{}",
+                    theme::style(Role::Warning).paint(format!(
+                        "[WARN] Your kubeconfig changed, but couldn't be reloaded: {}",
+                        e
+                    ))
+                );
+            }
+        }
+    }
+}
+
 fn print_contexts(env: &Env, writer: &mut ClickWriter) {
     let mut contexts: Vec<&String> = env.config.contexts.keys().collect();
     contexts.sort();
@@ -55,19 +115,91 @@ fn print_contexts(env: &Env, writer: &mut ClickWriter) {
                 Some(c) => c.server.as_str(),
                 None => "[no cluster for context]",
             };
-            row.push(CellSpec::with_style((*context).clone().into(), "FR"));
+            let aliases: Vec<&str> = env
+                .click_config
+                .context_aliases
+                .iter()
+                .filter(|(_, ctx)| ctx == context)
+                .map(|(alias, _)| alias.as_str())
+                .collect();
+            row.push(CellSpec::with_role(
+                (*context).clone().into(),
+                Role::Selection,
+            ));
+            row.push(aliases.join(", ").into());
             row.push(cluster.into());
             row
         })
         .collect();
-    crate::table::print_table(row!["Context", "Api Server Address"], ctxs, writer);
+    crate::table::print_table(
+        row!["Context", "Aliases", "Api Server Address"],
+        ctxs,
+        writer,
+    );
+}
+
+/// Figure out which context the user meant by `typed`. Real context names win over aliases,
+/// otherwise aliases are resolved, and finally we try a fuzzy match over both. Returns None if the
+/// name was ambiguous.
+fn resolve_context(env: &Env, typed: &str, writer: &mut ClickWriter) -> Option<String> {
+    if env.config.contexts.contains_key(typed) {
+        if let Some(shadowed) = env.resolve_context_alias(typed) {
+            clickwriteln!(
+                writer,
+                "{}",
+                theme::style(Role::Warning).paint(format!(
+                    "[WARN] Context alias '{}' (for {}) is shadowed by a context of the same name",
+                    typed, shadowed
+                ))
+            );
+        }
+        return Some(typed.to_string());
+    }
+    if let Some(ctx) = env.resolve_context_alias(typed) {
+        return Some(ctx.to_string());
+    }
+    let candidates = env
+        .config
+        .contexts
+        .keys()
+        .chain(env.click_config.context_aliases.keys())
+        .map(|k| k.as_str());
+    resolve_fuzzy(typed, candidates, "context", writer).map(|matched| {
+        if env.config.contexts.contains_key(matched) {
+            matched.to_string()
+        } else {
+            env.resolve_context_alias(matched)
+                .unwrap_or(matched)
+                .to_string()
+        }
+    })
+}
+
+/// Switch to the namespace that was last used in the current context, if it still exists
+fn restore_namespace(env: &mut Env, writer: &mut ClickWriter) {
+    if let Some(ns) = env.remembered_namespace().map(|ns| ns.to_string()) {
+        match completer::get_namespace_names(env) {
+            Some(names) if !names.contains(&ns) => {
+                clickwriteln!(
+                    writer,
+                    "{}",
+                    theme::style(Role::Warning).paint(format!(
+                        "[WARN] Namespace {} no longer exists, not switching to it",
+                        ns
+                    ))
+                );
+            }
+            _ => env.set_namespace(Some(&ns)),
+        }
+    }
 }
 
 command!(
     Context,
     "context",
     "Set the current context (will clear any selected pod). \
-     With no argument, lists available contexts.",
+     With no argument, lists available contexts. A partial name will switch to the matching \
+     context if only one matches.",
     |clap: App<'static, 'static>| clap.arg(
         Arg::with_name("context")
             .help("The name of the context")
@@ -75,12 +207,16 @@ command!(
             .index(1)
     ),
     vec!["ctx", "context"],
-    vec![&completer::context_complete],
+    vec![&completer::context_completer],
     no_named_complete!(),
     |matches, env, writer| {
-        if matches.is_present("context") {
-            let context = matches.value_of("context");
-            if let (&Some(ref cur), Some(c)) = (&env.context, context) {
+        if let Some(typed) = matches.value_of("context") {
+            let context = match resolve_context(env, typed, writer) {
+                Some(c) => Some(c),
+                None => return Ok(()),
+            };
+            let context = context.as_deref();
+            if let (Some(cur), Some(c)) = (&env.context, context) {
                 if cur.name == c {
                     // no-op if we're already in the specified context1
                     return Ok(());
@@ -88,23 +224,422 @@ command!(
             }
             env.set_context(context);
             env.clear_current();
+            if env.click_config.remember_namespace {
+                restore_namespace(env, writer);
+            }
         } else {
+            reload_if_stale(env, writer);
             print_contexts(env, writer);
         }
         Ok(())
     }
 );
 
+/// Check that each context can be loaded, and that any client certificates aren't expired or
+/// expiring soon
+fn check_contexts(env: &Env, writer: &mut ClickWriter) {
+    let warn_days = env.click_config.cert_warn_days;
+    let time_fmt = "%Y-%m-%d %H:%M:%S UTC";
+    let rows = env
+        .config
+        .contexts
+        .keys()
+        .map(|context| {
+            let (status, details) = match env.config.get_context(context, &env.click_config) {
+                Err(e) => (
+                    CellSpec::with_role("Error".into(), Role::BadStatus),
+                    e.to_string(),
+                ),
+                Ok(_) => match env.config.cert_expiry(context, warn_days) {
+                    Ok(Some(CertExpiry::Valid(not_after))) => (
+                        CellSpec::with_role("OK".into(), Role::GoodStatus),
+                        format!(
+                            "Client certificate valid until {}",
+                            not_after.format(time_fmt)
+                        ),
+                    ),
+                    Ok(Some(CertExpiry::ExpiresSoon(not_after))) => (
+                        CellSpec::with_role("Warning".into(), Role::Warning),
+                        format!(
+                            "Client certificate expires in {} (at {})",
+                            format_duration(not_after - Utc::now()),
+                            not_after.format(time_fmt)
+                        ),
+                    ),
+                    Ok(Some(CertExpiry::Expired(not_after))) => (
+                        CellSpec::with_role("Error".into(), Role::BadStatus),
+                        format!(
+                            "Client certificate expired at {}",
+                            not_after.format(time_fmt)
+                        ),
+                    ),
+                    Ok(None) => (
+                        CellSpec::with_role("OK".into(), Role::GoodStatus),
+                        String::new(),
+                    ),
+                    Err(e) => (
+                        CellSpec::with_role("Error".into(), Role::BadStatus),
+                        format!("Couldn't read client certificate: {}", e),
+                    ),
+                },
+            };
+            vec![
+                CellSpec::with_role(context.as_str().into(), Role::Selection),
+                status,
+                details.into(),
+            ]
+        })
+        .collect();
+    crate::table::print_table(row!["Context", "Status", "Details"], rows, writer);
+}
+
 command!(
     Contexts,
     "contexts",
     "List available contexts",
-    identity,
+    |clap: App<'static, 'static>| clap.arg(
+        Arg::with_name("check")
+            .long("check")
+            .help(
+                "Check the credentials of every context without switching to it, reporting any \
+                 that can't be loaded, or that have expired or soon to expire client certificates"
+            )
+            .takes_value(false)
+    ),
     vec!["contexts", "ctxs"],
     noop_complete!(),
     no_named_complete!(),
-    |_, env, writer| {
-        print_contexts(env, writer);
+    |matches, env, writer| {
+        reload_if_stale(env, writer);
+        if matches.is_present("check") {
+            check_contexts(env, writer);
+        } else {
+            print_contexts(env, writer);
+        }
+        Ok(())
+    }
+);
+
+/// Where the kubeconfig paths in use came from, if we can tell
+fn kubeconfig_source(env: &Env) -> Option<&'static str> {
+    let from_var = std::env::var_os("KUBECONFIG")
+        .map(|paths| {
+            std::env::split_paths(&paths)
+                .map(|p| p.to_string_lossy().to_string())
+                .collect::<Vec<String>>()
+        })
+        .is_some_and(|paths| paths == env.config.source_paths);
+    if from_var {
+        Some("from $KUBECONFIG")
+    } else if env.config.source_paths
+        == [env::expand_home("~/.kube/config")
+            .to_string_lossy()
+            .to_string()]
+    {
+        Some("the default")
+    } else {
+        None
+    }
+}
+
+/// Print each kubeconfig file in use, with how many contexts it has, marking the one the active
+/// context comes from
+fn list_kubeconfigs(env: &Env, writer: &mut ClickWriter) {
+    let active = env.context.as_ref().map(|c| c.name.as_str());
+    let mut active_found = false;
+    for path in env.config.source_paths.iter() {
+        let (marker, details) = if !Path::new(path).exists() {
+            (
+                " ",
+                theme::style(Role::Warning).paint("(missing)").to_string(),
+            )
+        } else {
+            match config::Config::from_files(std::slice::from_ref(path)) {
+                Ok(conf) => {
+                    // the first file with a context is the one it's loaded from
+                    let has_active = active.is_some_and(|a| conf.contexts.contains_key(a));
+                    let marker = if has_active && !active_found {
+                        active_found = true;
+                        "*"
+                    } else {
+                        " "
+                    };
+                    (marker, format!("({} contexts)", conf.contexts.len()))
+                }
+                Err(e) => (
+                    " ",
+                    theme::style(Role::BadStatus)
+                        .paint(format!("(error: {})", e))
+                        .to_string(),
+                ),
+            }
+        };
+        clickwriteln!(writer, "{} {} {}", marker, path, details);
+    }
+}
+
+command!(
+    Kubeconfig,
+    "kubeconfig",
+    "Show the kubernetes config file(s) in use, or switch to different ones for this session",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("use")
+                .long("use")
+                .value_name("FILENAME")
+                .help(
+                    "Switch to this config file. Several files can be given separated by ':', \
+                     and are merged like KUBECONFIG",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("add")
+                .long("add")
+                .value_name("FILENAME")
+                .help("Merge this config file in after the ones already in use")
+                .conflicts_with("use")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("list")
+                .long("list")
+                .short("l")
+                .help("List each config file in use, marking the one the active context is from")
+                .takes_value(false),
+        )
+        .after_help(
+            "Switching sets KUBECONFIG for the rest of the click session, so kubectl uses the same \
+files. When files are merged, the first one to define a context, cluster, or user wins. The active \
+context is kept if the new files have it, otherwise their current-context is used.
+
+Examples:
+  # show which config file(s) are in use
+  kubeconfig
+
+  # switch to the config for a test cluster
+  kubeconfig --use ~/.kube/test-cluster
+
+  # use the contexts from another file as well
+  kubeconfig --add ~/.kube/other",
+        )
+    },
+    vec!["kubeconfig"],
+    noop_complete!(),
+    no_named_complete!(),
+    |matches, env, writer| {
+        let paths = match (matches.value_of_os("use"), matches.value_of_os("add")) {
+            (Some(paths), _) => Some(std::env::split_paths(paths).collect::<Vec<_>>()),
+            (None, Some(path)) => {
+                let mut paths: Vec<PathBuf> =
+                    env.config.source_paths.iter().map(PathBuf::from).collect();
+                paths.extend(std::env::split_paths(path));
+                Some(paths)
+            }
+            (None, None) => None,
+        };
+        if let Some(paths) = paths {
+            let paths: Vec<String> = paths
+                .iter()
+                .map(|p| {
+                    env::expand_home(&p.to_string_lossy())
+                        .to_string_lossy()
+                        .to_string()
+                })
+                .collect();
+            if let Some(missing) = paths.iter().find(|p| !Path::new(p).exists()) {
+                return Err(ClickError::CommandError(format!(
+                    "Config file {} not found",
+                    missing
+                )));
+            }
+            if env.set_kubeconfig(paths)? {
+                env.clear_current();
+                let context = env.context.as_ref().map(|c| c.name.as_str());
+                clickwriteln!(
+                    writer,
+                    "Switched to context {}",
+                    context.unwrap_or("<none>")
+                );
+            }
+        }
+
+        if matches.is_present("list") {
+            list_kubeconfigs(env, writer);
+        } else {
+            match kubeconfig_source(env) {
+                Some(source) => {
+                    clickwriteln!(
+                        writer,
+                        "Kubeconfig: {} ({})",
+                        env.config.source_file,
+                        source
+                    )
+                }
+                None => clickwriteln!(writer, "Kubeconfig: {}", env.config.source_file),
+            }
+        }
+        Ok(())
+    }
+);
+
+/// Ask for a new name for the context name from a kubeconfig being merged, as it's already in
+/// use. Returns None if the user wants to leave it out.
+fn prompt_context_rename(
+    env: &Env,
+    name: &str,
+    incoming: &[String],
+    chosen: &HashMap<String, Option<String>>,
+    writer: &mut ClickWriter,
+) -> Result<Option<String>, ClickError> {
+    writer.finish_paging();
+    loop {
+        clickwrite!(
+            writer,
+            "Context {} already exists. New name for it (leave empty to skip it): ",
+            name
+        );
+        std::io::stdout().flush()?;
+        let mut answer = String::new();
+        if std::io::stdin().read_line(&mut answer)? == 0 {
+            clickwriteln!(writer, "");
+            return Ok(None);
+        }
+        let answer = answer.trim();
+        if answer.is_empty() {
+            return Ok(None);
+        }
+        let taken = env.config.contexts.contains_key(answer)
+            || incoming.iter().any(|n| n == answer)
+            || chosen.values().any(|n| n.as_deref() == Some(answer));
+        if taken {
+            clickwriteln!(writer, "Context {} exists too", answer);
+        } else {
+            return Ok(Some(answer.to_string()));
+        }
+    }
+}
+
+command!(
+    MergeKubeconfig,
+    "merge-kubeconfig",
+    "Merge the clusters, users, and contexts from a kubeconfig file into the current config, and \
+     optionally save them to your kubeconfig",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("file")
+                .help("The kubeconfig file to merge in")
+                .required(true)
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("into")
+                .long("into")
+                .value_name("FILENAME")
+                .help(
+                    "The kubeconfig file to save the merged config to. Defaults to the first \
+                     file in use (see 'kubeconfig --list')",
+                )
+                .takes_value(true),
+        )
+        .after_help(
+            "Contexts whose names are already in use prompt for a new name, or can be skipped. \
+Clusters and users whose names are taken by different ones are renamed with a numbered suffix. \
+The merged contexts can be used straight away, and after showing what would change, you're asked \
+if the kubeconfig file should be updated too. Comments and formatting in it aren't preserved.
+
+Example:
+  # add the contexts for a new cluster
+  merge-kubeconfig ~/Downloads/new-cluster.yaml",
+        )
+    },
+    vec!["merge-kubeconfig"],
+    noop_complete!(),
+    no_named_complete!(),
+    |matches, env, writer| {
+        let path = env::expand_home(matches.value_of("file").unwrap()); // safe, required
+        let other: serde_yaml::Value = serde_yaml::from_reader(fs::File::open(&path)?)?;
+        let incoming = config::context_names(&other)?;
+
+        let mut context_names = HashMap::new();
+        for name in incoming.iter() {
+            if env.config.contexts.contains_key(name) {
+                let new_name = prompt_context_rename(env, name, &incoming, &context_names, writer)?;
+                context_names.insert(name.clone(), new_name);
+            }
+        }
+
+        let target = match matches.value_of("into") {
+            Some(into) => env::expand_home(into),
+            None => PathBuf::from(&env.config.source_paths[0]),
+        };
+        let original = match fs::read_to_string(&target) {
+            Ok(original) => original,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let mut merged: serde_yaml::Value = if original.trim().is_empty() {
+            serde_yaml::Value::Null
+        } else {
+            serde_yaml::from_str(&original)?
+        };
+        // diff against the file as we'd write it, so the diff is only what the merge changed
+        let old_text = if merged.is_null() {
+            String::new()
+        } else {
+            serde_yaml::to_string(&merged)?
+        };
+        let taken_clusters = env.config.clusters.keys().cloned().collect();
+        let taken_users = env.config.users.keys().cloned().collect();
+        let result = config::merge_configs(
+            &mut merged,
+            &other,
+            &context_names,
+            &taken_clusters,
+            &taken_users,
+        )?;
+        if result.contexts.is_empty() {
+            clickwriteln!(writer, "No contexts to merge");
+            return Ok(());
+        }
+
+        let target_str = target.to_string_lossy().to_string();
+        env.config.merge(&target_str, &result.added)?;
+        for (kind, old, new) in result.renamed.iter() {
+            clickwriteln!(
+                writer,
+                "Renamed {} {} to {}, as a different one already exists",
+                kind,
+                old,
+                new
+            );
+        }
+        clickwriteln!(writer, "Merged contexts: {}", result.contexts.join(", "));
+
+        let new_text = serde_yaml::to_string(&merged)?;
+        let lines = crate::diff::unified_diff(&target_str, &target_str, &old_text, &new_text);
+        crate::diff::write_diff(writer, &lines);
+        if old_text != original {
+            clickwriteln!(
+                writer,
+                "(saving will also reformat the file, and drop any comments in it)"
+            );
+        }
+        if env.dry_run {
+            clickwriteln!(writer, "[DRY RUN] Not writing {}", target_str);
+        } else if confirm_action(writer, &format!("Save the merged config to {}", target_str)) {
+            AtomicFile::new(&target, AllowOverwrite)
+                .write(|f| f.write_all(new_text.as_bytes()))
+                .map_err(|e| {
+                    ClickError::ConfigFileError(format!("Failed to write kubeconfig: {}", e))
+                })?;
+            clickwriteln!(writer, "Saved {}", target_str);
+        } else {
+            clickwriteln!(
+                writer,
+                "Not saved, the merged contexts are only available until click exits"
+            );
+        }
         Ok(())
     }
 );
@@ -149,7 +684,7 @@ command!(
     |_, env, writer| {
         let mut table = Table::new();
         table.set_titles(row!["Name", "Type", "Namespace"]);
-        env.apply_to_selection(writer, None, |obj, _| {
+        env.apply_to_selection(writer, None, ErrorStrategy::CollectAll, |obj, _| {
             table.add_row(row!(
                 obj.name(),
                 obj.type_str(),
@@ -162,15 +697,376 @@ command!(
     }
 );
 
+command!(
+    Select,
+    "select",
+    "Select objects from the last list by number (see 'help ranges' for other ways to select)",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("indices")
+                .help(
+                    "The numbers of the objects to select, like 3, or 1,3,5-8. Ranges like 5.. \
+                     also work.",
+                )
+                .required(true)
+                .multiple(true),
+        )
+        .after_help(
+            "Examples:
+  # select the first pod listed
+  select 0
+
+  # select a few pods, and all the ones from 10 on
+  select 1,3,5-8,10..",
+        )
+    },
+    vec!["select"],
+    noop_complete!(),
+    no_named_complete!(),
+    |matches, env, _writer| {
+        // allow spaces, as in "select 1, 3"
+        let expr: Vec<&str> = matches.values_of("indices").unwrap().collect(); // safe, required
+        let len = env.last_objs_len().ok_or_else(|| {
+            ClickError::CommandError("Nothing to select from, list some objects first".to_string())
+        })?;
+        let indices = parse_index_list(&expr.join(","), len).map_err(ClickError::CommandError)?;
+        if indices.len() == 1 {
+            env.set_current(indices[0]);
+        } else {
+            let objs = indices
+                .iter()
+                .filter_map(|i| env.item_at(*i).cloned())
+                .collect();
+            env.set_range(objs);
+        }
+        Ok(())
+    }
+);
+
+command!(
+    Save,
+    "save",
+    "Save the current selection to a file, so it can be loaded again later",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("file")
+                .help("The file to save to. It's overwritten if it exists.")
+                .required(true)
+                .index(1),
+        )
+    },
+    vec!["save"],
+    noop_complete!(),
+    no_named_complete!(),
+    |matches, env, writer| {
+        let file = matches.value_of("file").unwrap(); // safe, required
+        let objs = match env.current_selection() {
+            ObjectSelection::Single(obj) => vec![obj.clone()],
+            ObjectSelection::Range(range) => range.clone(),
+            ObjectSelection::None => {
+                return Err(ClickError::CommandError(
+                    "Nothing selected to save".to_string(),
+                ));
+            }
+        };
+        let context = env.context.as_ref().map(|c| c.name.as_str());
+        let value = selection::selection_to_json(&objs, context);
+        fs::write(file, serde_json::to_string_pretty(&value)?)
+            .map_err(|e| ClickError::CommandError(format!("Could not write {}: {}", file, e)))?;
+        clickwriteln!(writer, "Saved {} object(s) to {}", objs.len(), file);
+        Ok(())
+    }
+);
+
+/// Check that obj still exists. Returns the object as it is now (its uid may have changed, if it
+/// was recreated), or None if it's gone.
+fn verify_obj(env: &Env, obj: &KObj) -> Result<Option<KObj>, ClickError> {
+    match kubectl_get_json(env, obj) {
+        Ok(value) => {
+            let mut current = obj.clone();
+            current.uid = val_str_opt("/metadata/uid", &value);
+            Ok(Some(current))
+        }
+        Err(ClickError::KubectlError { ref stderr, .. }) if stderr.contains("NotFound") => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+command!(
+    Load,
+    "load",
+    "Load a selection saved with the save command",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("file")
+                .help("The file to load")
+                .required(true)
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("verify")
+                .short("v")
+                .long("verify")
+                .help(
+                    "Check that each object still exists, leaving out (and warning about) any \
+                     that don't",
+                )
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("quiet")
+                .short("q")
+                .long("quiet")
+                .help("Don't say what was loaded, only print warnings")
+                .takes_value(false),
+        )
+        .after_help(
+            "The loaded objects are selected, and can also be selected by number, as if they'd just \
+been listed.",
+        )
+    },
+    vec!["load"],
+    noop_complete!(),
+    no_named_complete!(),
+    |matches, env, writer| {
+        let file = matches.value_of("file").unwrap(); // safe, required
+        let text = fs::read_to_string(file)
+            .map_err(|e| ClickError::CommandError(format!("Could not read {}: {}", file, e)))?;
+        let saved = selection::selection_from_json(file, &serde_json::from_str(&text)?)?;
+        let warn = |writer: &mut ClickWriter, msg: String| {
+            clickwriteln!(writer, "{}", theme::style(Role::Warning).paint(msg));
+        };
+        let current_context = env.context.as_ref().map(|c| c.name.clone());
+        if let Some(context) = saved.context.as_ref() {
+            if current_context.as_ref() != Some(context) {
+                warn(
+                    writer,
+                    format!(
+                        "Selection was saved in context {}, but the current context is {}",
+                        context,
+                        current_context.as_deref().unwrap_or("[none]")
+                    ),
+                );
+            }
+        }
+
+        let objs = if matches.is_present("verify") {
+            let mut found = vec![];
+            for obj in saved.objects.into_iter() {
+                match verify_obj(env, &obj)? {
+                    Some(current) => {
+                        if obj.uid.is_some() && current.uid != obj.uid {
+                            warn(
+                                writer,
+                                format!(
+                                    "{} {} has been recreated since it was saved",
+                                    obj.type_str(),
+                                    obj.name()
+                                ),
+                            );
+                        }
+                        found.push(current);
+                    }
+                    None => warn(
+                        writer,
+                        format!("{} {} no longer exists", obj.type_str(), obj.name()),
+                    ),
+                }
+            }
+            found
+        } else {
+            saved.objects
+        };
+
+        let quiet = matches.is_present("quiet");
+        if objs.is_empty() {
+            env.clear_current();
+            if !quiet {
+                clickwriteln!(writer, "Nothing to select");
+            }
+            return Ok(());
+        }
+        if !quiet {
+            clickwriteln!(writer, "Loaded {} object(s) from {}", objs.len(), file);
+        }
+        env.set_last_objs(objs.clone());
+        if objs.len() == 1 {
+            env.set_current(0);
+        } else {
+            env.set_range(objs);
+        }
+        Ok(())
+    }
+);
+
+/// The selected objects whose names match pattern, either fuzzily (as a substring, or failing
+/// that, a subsequence) or as a regex
+fn filter_objs(objs: &[KObj], pattern: &str, regex: bool) -> Result<Vec<KObj>, ClickError> {
+    if regex {
+        let regex = Regex::new(pattern)
+            .map_err(|e| ClickError::CommandError(format!("Invalid regex: {}", e)))?;
+        Ok(objs
+            .iter()
+            .filter(|obj| regex.is_match(obj.name()))
+            .cloned()
+            .collect())
+    } else {
+        let names = fuzzy::fuzzy_candidates(pattern, objs.iter().map(|obj| obj.name()));
+        Ok(objs
+            .iter()
+            .filter(|obj| names.contains(&obj.name()))
+            .cloned()
+            .collect())
+    }
+}
+
+command!(
+    Filter,
+    "filter",
+    "Narrow the current selection down to the objects whose names match a pattern",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("pattern")
+                .help(
+                    "The pattern to match names against. Names containing it are kept, or if there \
+                     are none, names with its characters in order (so 'wb1' matches 'web-1').",
+                )
+                .required_unless("clear")
+                .conflicts_with("clear")
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("regex")
+                .short("r")
+                .long("regex")
+                .help("Treat the pattern as a regex")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("clear")
+                .short("c")
+                .long("clear")
+                .help("Go back to the selection from before it was filtered")
+                .takes_value(false),
+        )
+        .after_help(
+            "Filters can be applied one after another to keep narrowing things down, and --clear \
+goes back to the selection from before the first one.
+
+Examples:
+  # keep just the api pods
+  filter api
+
+  # keep pods ending in a number, then undo that
+  filter -r '-[0-9]+$'
+  filter --clear",
+        )
+    },
+    vec!["filter"],
+    noop_complete!(),
+    no_named_complete!(),
+    |matches, env, writer| {
+        if matches.is_present("clear") {
+            if !env.clear_filter() {
+                clickwriteln!(writer, "Selection isn't filtered");
+            }
+            return Ok(());
+        }
+        let pattern = matches.value_of("pattern").unwrap(); // safe, required unless clear
+        let selected = match env.current_selection() {
+            ObjectSelection::Single(obj) => vec![obj.clone()],
+            ObjectSelection::Range(range) => range.clone(),
+            ObjectSelection::None => {
+                return Err(ClickError::CommandError(
+                    "Nothing selected to filter".to_string(),
+                ));
+            }
+        };
+        let matched = filter_objs(&selected, pattern, matches.is_present("regex"))?;
+        if matched.is_empty() {
+            return Err(ClickError::CommandError(format!(
+                "Nothing selected matches '{}', selection unchanged",
+                pattern
+            )));
+        }
+        clickwriteln!(
+            writer,
+            "{} of {} selected objects match",
+            matched.len(),
+            selected.len()
+        );
+        env.filter_selection(matched);
+        Ok(())
+    }
+);
+
 pub const SET_OPTS: &[&str] = &[
     "completion_type",
     "edit_mode",
     "editor",
     "terminal",
+    "ssh_command",
+    "container_runtime",
+    "default_timeout",
+    "pager",
+    "rc_file",
+    "history_size",
+    "history_ignore_dups",
+    "history_ignore_space",
     "range_separator",
+    "prompt_format",
     "describe_include_events",
+    "remember_namespace",
+    "auth_refresh",
+    "retries",
+    "list_page_size",
+    "wire_format",
+    "max_parallel_requests",
+    "job_output_lines",
+    "impersonate",
+    "impersonate_group",
+    "verbose",
+    "cert_warn_days",
+    "proxy_port",
+    "strip_color_on_redirect",
+    "color",
+    "syntax_highlight",
+    "dry-run",
+    "output_format",
+    "theme",
+    "theme.header",
+    "theme.good-status",
+    "theme.bad-status",
+    "theme.warning",
+    "theme.error",
+    "theme.prompt-context",
+    "theme.prompt-namespace",
+    "theme.selection",
 ];
 
+command!(
+    Reload,
+    "reload",
+    "Reload your kubernetes config file(s), picking up any added, changed, or removed contexts",
+    identity,
+    vec!["reload"],
+    noop_complete!(),
+    no_named_complete!(),
+    |_, env, writer| {
+        let summary = env.reload_config()?;
+        if summary.added.is_empty()
+            && summary.changed.is_empty()
+            && summary.removed.is_empty()
+            && summary.kept_active.is_none()
+        {
+            clickwriteln!(writer, "Reloaded config, no changes to contexts");
+        } else {
+            print_reload_summary(&summary, writer);
+        }
+        Ok(())
+    }
+);
+
 command!(
     SetCmd,
     "set",
@@ -198,7 +1094,41 @@ Example:
   set -- range_separator \"---- {name} [{namespace}] ----\"
 
   # set edit_mode
-  set edit_mode emacs",
+  set edit_mode emacs
+
+  # a shorter prompt. {context}, {namespace}, {selection}, and {selection_count} are filled in,
+  # and {selected} is \"[N objects selected]\" when something is, and nothing otherwise
+  set prompt_format \"{context}/{namespace} ({selection_count})> \"
+  set prompt_format \"{context}/{namespace} {selected}> \"
+
+  # keep the last 5000 commands in the history
+  set history_size 5000
+
+  # page long output with most (\"none\" turns paging off, \"\" goes back to $PAGER)
+  set pager most
+
+  # never color output (auto colors output to a terminal, but not to files or pipes)
+  set color off
+
+  # get lists of core and apps types as protobuf, which is smaller to send than json
+  set wire_format protobuf
+
+  # print the kubectl commands that would change things, rather than running them
+  set dry-run true
+
+  # see what the ci service account can do, then go back to being yourself
+  set impersonate system:serviceaccount:ci:deployer
+  unset impersonate
+
+  # see what jane can do as a member of the dev group (like --as jane --as-group dev)
+  set impersonate jane
+  set impersonate_group dev
+
+  # use colors that work on a light background (themes: dark, light, solarized, gruvbox, colorblind)
+  set theme light
+
+  # show bad statuses in bold red, whatever the theme (set to \"\" to use the theme's style again)
+  set theme.bad-status \"red bold\"",
         )
     },
     vec!["set"],
@@ -237,9 +1167,73 @@ Example:
             "terminal" => {
                 env.set_terminal(Some(value));
             }
+            "ssh_command" => {
+                env.set_ssh_command(value);
+            }
+            "container_runtime" => match value {
+                "" | "docker" | "containerd" | "cri-o" => env.set_container_runtime(value),
+                _ => {
+                    clickwriteln!(
+                        writer,
+                        "container_runtime must be docker, containerd, or cri-o (\"\" to detect \
+                         it from the node)"
+                    );
+                    failed = true;
+                }
+            },
+            "default_timeout" => {
+                if value.is_empty() || is_go_duration(value) {
+                    env.set_default_timeout(value);
+                } else {
+                    clickwriteln!(
+                        writer,
+                        "default_timeout must be a duration like 30s or 2m (\"\" to not time out)"
+                    );
+                    failed = true;
+                }
+            }
+            "pager" => {
+                env.set_pager(value);
+            }
+            "rc_file" => {
+                env.set_rc_file(value);
+            }
+            "history_size" => match value.parse() {
+                Ok(size) => env.set_history_size(size),
+                Err(_) => {
+                    clickwriteln!(writer, "history_size must be a non-negative number");
+                    failed = true;
+                }
+            },
+            "history_ignore_dups" => match value.parse() {
+                Ok(b) => env.set_history_ignore_dups(b),
+                Err(_) => {
+                    clickwriteln!(
+                        writer,
+                        "history_ignore_dups must be set to 'true' or 'false'"
+                    );
+                    failed = true;
+                }
+            },
+            "history_ignore_space" => match value.parse() {
+                Ok(b) => env.click_config.history_ignore_space = b,
+                Err(_) => {
+                    clickwriteln!(
+                        writer,
+                        "history_ignore_space must be set to 'true' or 'false'"
+                    );
+                    failed = true;
+                }
+            },
             "range_separator" => {
                 env.click_config.range_separator = value.to_string();
             }
+            "prompt_format" => {
+                if let Err(e) = env.set_prompt_format(value) {
+                    clickwriteln!(writer, "{}", e);
+                    failed = true;
+                }
+            }
             "describe_include_events" => match value.parse() {
                 Ok(b) => env.click_config.describe_include_events = b,
                 Err(_) => {
@@ -250,9 +1244,209 @@ Example:
                     failed = true;
                 }
             },
+            "remember_namespace" => match value.parse() {
+                Ok(b) => env.click_config.remember_namespace = b,
+                Err(_) => {
+                    clickwriteln!(
+                        writer,
+                        "remember_namespace must be set to 'true' or 'false'"
+                    );
+                    failed = true;
+                }
+            },
+            "auth_refresh" => match value.parse() {
+                Ok(b) => {
+                    env.click_config.auth_refresh = b;
+                    if let Some(context) = env.context.as_ref() {
+                        context.set_auth_refresh(b);
+                    }
+                }
+                Err(_) => {
+                    clickwriteln!(writer, "auth_refresh must be set to 'true' or 'false'");
+                    failed = true;
+                }
+            },
+            "retries" => match value.parse() {
+                Ok(retries) => {
+                    env.click_config.retries = retries;
+                    if let Some(context) = env.context.as_ref() {
+                        context.set_retries(retries);
+                    }
+                }
+                Err(_) => {
+                    clickwriteln!(writer, "retries must be a non-negative number");
+                    failed = true;
+                }
+            },
+            "verbose" => match parse_verbose(value) {
+                Some(level) => {
+                    env.click_config.verbose = level;
+                    if let Some(context) = env.context.as_ref() {
+                        context.set_verbose(level);
+                    }
+                }
+                None => {
+                    clickwriteln!(
+                        writer,
+                        "verbose must be 'on', 'off', or a level from 0 to {}",
+                        MAX_VERBOSE
+                    );
+                    failed = true;
+                }
+            },
+            "impersonate" => match value {
+                "off" | "" => env.set_impersonate(None),
+                user => {
+                    // keep impersonating the group, if one was set
+                    let groups = env
+                        .click_config
+                        .impersonate
+                        .as_ref()
+                        .map(|imp| imp.groups.clone())
+                        .unwrap_or_default();
+                    env.set_impersonate(Some(Impersonation {
+                        user: user.to_string(),
+                        groups,
+                    }));
+                }
+            },
+            "impersonate_group" => match env.click_config.impersonate.clone() {
+                Some(mut imp) => {
+                    imp.groups = match value {
+                        "off" | "" => vec![],
+                        group => vec![group.to_string()],
+                    };
+                    env.set_impersonate(Some(imp));
+                }
+                None => {
+                    clickwriteln!(
+                        writer,
+                        "A group can only be impersonated along with a user, set impersonate first"
+                    );
+                    failed = true;
+                }
+            },
+            "max_parallel_requests" => match value.parse() {
+                Ok(max) if max > 0 => env.click_config.max_parallel_requests = max,
+                _ => {
+                    clickwriteln!(writer, "max_parallel_requests must be a positive number");
+                    failed = true;
+                }
+            },
+            "job_output_lines" => match value.parse() {
+                Ok(lines) if lines > 0 => env.click_config.job_output_lines = lines,
+                _ => {
+                    clickwriteln!(writer, "job_output_lines must be a positive number");
+                    failed = true;
+                }
+            },
+            "list_page_size" => match value.parse() {
+                Ok(size) => env.click_config.list_page_size = size,
+                Err(_) => {
+                    clickwriteln!(
+                        writer,
+                        "list_page_size must be a non-negative number (0 to not page)"
+                    );
+                    failed = true;
+                }
+            },
+            "wire_format" => {
+                let wire_format = match value {
+                    "json" => Some(config::WireFormat::Json),
+                    "protobuf" => Some(config::WireFormat::Protobuf),
+                    _ => None,
+                };
+                match wire_format {
+                    Some(wire_format) => {
+                        env.click_config.wire_format = wire_format;
+                        if let Some(context) = env.context.as_ref() {
+                            context.set_wire_format(wire_format);
+                        }
+                    }
+                    None => {
+                        clickwriteln!(
+                            writer,
+                            "Invalid wire_format.  Possible values are: [json, protobuf]"
+                        );
+                        failed = true;
+                    }
+                }
+            }
+            "cert_warn_days" => match value.parse() {
+                Ok(days) => env.click_config.cert_warn_days = days,
+                Err(_) => {
+                    clickwriteln!(
+                        writer,
+                        "cert_warn_days must be a non-negative number of days"
+                    );
+                    failed = true;
+                }
+            },
+            "proxy_port" => match value.parse() {
+                Ok(port) => env.click_config.proxy_port = port,
+                Err(_) => {
+                    clickwriteln!(writer, "proxy_port must be a port number");
+                    failed = true;
+                }
+            },
+            "color" => match value {
+                "on" => env.set_color(config::ColorMode::On),
+                "off" => env.set_color(config::ColorMode::Off),
+                "auto" => env.set_color(config::ColorMode::Auto),
+                _ => {
+                    clickwriteln!(
+                        writer,
+                        "Invalid color.  Possible values are: [on, off, auto]"
+                    );
+                    failed = true;
+                }
+            },
+            "strip_color_on_redirect" => match value.parse() {
+                Ok(b) => env.click_config.strip_color_on_redirect = b,
+                Err(_) => {
+                    clickwriteln!(
+                        writer,
+                        "strip_color_on_redirect must be set to 'true' or 'false'"
+                    );
+                    failed = true;
+                }
+            },
+            "syntax_highlight" => match value.parse() {
+                Ok(b) => env.click_config.syntax_highlight = b,
+                Err(_) => {
+                    clickwriteln!(writer, "syntax_highlight must be set to 'true' or 'false'");
+                    failed = true;
+                }
+            },
+            "output_format" => match value.parse() {
+                Ok(format) => env.output_format = format,
+                Err(e) => {
+                    clickwriteln!(writer, "{}", e);
+                    failed = true;
+                }
+            },
+            "dry-run" => match value.parse() {
+                Ok(b) => env.dry_run = b,
+                Err(_) => {
+                    clickwriteln!(writer, "dry-run must be set to 'true' or 'false'");
+                    failed = true;
+                }
+            },
+            "theme" => {
+                if let Err(e) = env.set_theme(value) {
+                    clickwriteln!(writer, "{}", e);
+                    failed = true;
+                }
+            }
+            opt if opt.starts_with("theme.") => {
+                if let Err(e) = env.set_theme_override(&opt[6..], value) {
+                    clickwriteln!(writer, "{}", e);
+                    failed = true;
+                }
+            }
             _ => {
                 // this shouldn't happen
-                write!(stderr(), "Invalid option\n").unwrap_or(());
+                writeln!(stderr(), "Invalid option").unwrap_or(());
                 failed = true;
             }
         }
@@ -263,6 +1457,87 @@ Example:
     }
 );
 
+/// The options unset can go back to the default for
+pub const UNSET_OPTS: &[&str] = &[
+    "editor",
+    "terminal",
+    "ssh_command",
+    "container_runtime",
+    "default_timeout",
+    "pager",
+    "rc_file",
+    "prompt_format",
+    "impersonate",
+    "impersonate_group",
+    "theme.header",
+    "theme.good-status",
+    "theme.bad-status",
+    "theme.warning",
+    "theme.error",
+    "theme.prompt-context",
+    "theme.prompt-namespace",
+    "theme.selection",
+];
+
+command!(
+    UnsetCmd,
+    "unset",
+    "Unset a click option, going back to its default",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("option")
+                .help("The click option to unset")
+                .required(true)
+                .index(1)
+                .possible_values(UNSET_OPTS),
+        )
+        .after_help(
+            "This is the same as setting the option to \"\" (or 'off' for impersonate and \
+             impersonate_group).
+
+Example:
+  # stop impersonating
+  unset impersonate
+
+  # go back to the theme's style for bad statuses
+  unset theme.bad-status",
+        )
+    },
+    vec!["unset"],
+    vec![&completer::unsetoptions_values_completer],
+    no_named_complete!(),
+    |matches, env, writer| {
+        let option = matches.value_of("option").unwrap(); // safe, required
+        match option {
+            "editor" => env.set_editor(None),
+            "terminal" => env.set_terminal(None),
+            "ssh_command" => env.set_ssh_command(""),
+            "container_runtime" => env.set_container_runtime(""),
+            "default_timeout" => env.set_default_timeout(""),
+            "pager" => env.set_pager(""),
+            "rc_file" => env.set_rc_file(""),
+            "prompt_format" => env.set_prompt_format(&config::default_prompt_format())?,
+            "impersonate" => env.set_impersonate(None),
+            "impersonate_group" => {
+                if let Some(mut imp) = env.click_config.impersonate.clone() {
+                    imp.groups.clear();
+                    env.set_impersonate(Some(imp));
+                }
+            }
+            opt if opt.starts_with("theme.") => env.set_theme_override(&opt[6..], "")?,
+            _ => {
+                // this shouldn't happen, clap checks the option
+                return Err(ClickError::CommandError(format!(
+                    "Invalid option {}",
+                    option
+                )));
+            }
+        }
+        clickwriteln!(writer, "Unset {}", option);
+        Ok(())
+    }
+);
+
 command!(
     UtcCmd,
     "utc",
@@ -276,3 +1551,114 @@ command!(
         Ok(())
     }
 );
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{get_test_config, ClickConfig, ContextConf};
+
+    #[test]
+    fn resolve_context_aliases() {
+        let dir = tempdir::TempDir::new("click_context_test").unwrap();
+        let mut config = get_test_config();
+        for name in ["prod-us-east-1", "prod", "staging"].iter() {
+            config.contexts.insert(
+                name.to_string(),
+                ContextConf {
+                    cluster: "cluster".to_string(),
+                    user: "user".to_string(),
+                    namespace: None,
+                },
+            );
+        }
+        let mut env = Env::new(
+            config,
+            ClickConfig::default(),
+            dir.path().join("click.config"),
+        );
+        assert!(env.add_context_alias("prod", "prod-us-east-1"));
+        assert!(env.add_context_alias("east", "prod-us-east-1"));
+
+        let resolve = |env: &Env, typed: &str| {
+            let mut writer = ClickWriter::with_buffer(vec![], false);
+            let ctx = resolve_context(env, typed, &mut writer);
+            let out = String::from_utf8(writer.finish_output().unwrap()).unwrap();
+            (ctx, out)
+        };
+
+        assert_eq!(
+            resolve(&env, "east"),
+            (Some("prod-us-east-1".to_string()), String::new())
+        );
+        // the real context named prod wins over the alias, with a warning
+        assert_eq!(
+            resolve(&env, "prod"),
+            (
+                Some("prod".to_string()),
+                "[WARN] Context alias 'prod' (for prod-us-east-1) is shadowed by a context of the \
+                 same name\n"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            resolve(&env, "staging"),
+            (Some("staging".to_string()), String::new())
+        );
+
+        assert!(env.remove_context_alias("prod"));
+        assert_eq!(
+            resolve(&env, "prod"),
+            (Some("prod".to_string()), String::new())
+        );
+    }
+
+    #[test]
+    fn set_impersonate() {
+        let dir = tempdir::TempDir::new("click_set_test").unwrap();
+        let mut env = Env::new(
+            get_test_config(),
+            ClickConfig::default(),
+            dir.path().join("click.config"),
+        );
+        let set = |env: &mut Env, option: &str, value: &str| {
+            let mut writer = ClickWriter::with_buffer(vec![], false);
+            SetCmd::new()
+                .exec(env, &mut [option, value].iter().copied(), &mut writer)
+                .unwrap();
+            String::from_utf8(writer.finish_output().unwrap()).unwrap()
+        };
+        let impersonating = |env: &Env| {
+            env.click_config
+                .impersonate
+                .as_ref()
+                .map(|imp| (imp.user.clone(), imp.groups.clone()))
+        };
+
+        // a group needs a user
+        assert_eq!(
+            set(&mut env, "impersonate_group", "dev"),
+            "A group can only be impersonated along with a user, set impersonate first\n"
+        );
+        assert_eq!(impersonating(&env), None);
+
+        // users are taken whole, colons and all
+        set(&mut env, "impersonate", "https://issuer.example.com#1234");
+        assert_eq!(
+            impersonating(&env),
+            Some(("https://issuer.example.com#1234".to_string(), vec![]))
+        );
+        set(&mut env, "impersonate", "jane:dev");
+        assert_eq!(impersonating(&env), Some(("jane:dev".to_string(), vec![])));
+
+        set(&mut env, "impersonate_group", "dev");
+        set(&mut env, "impersonate", "jane");
+        assert_eq!(
+            impersonating(&env),
+            Some(("jane".to_string(), vec!["dev".to_string()]))
+        );
+        set(&mut env, "impersonate_group", "off");
+        assert_eq!(impersonating(&env), Some(("jane".to_string(), vec![])));
+        set(&mut env, "impersonate", "off");
+        assert_eq!(impersonating(&env), None);
+    }
+}
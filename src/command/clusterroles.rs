@@ -0,0 +1,118 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use clap::{App, Arg};
+use k8s_openapi::api::rbac::v1 as api_rbac;
+
+use crate::{
+    command::command_def::{exec_match, show_arg, sort_arg, start_clap, Cmd},
+    command::{run_list_command, Extractor},
+    completer,
+    env::Env,
+    kobj::{KObj, ObjType},
+    output::ClickWriter,
+    table::CellSpec,
+};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+
+lazy_static! {
+    static ref CR_EXTRACTORS: HashMap<String, Extractor<api_rbac::ClusterRole>> = {
+        let mut m: HashMap<String, Extractor<api_rbac::ClusterRole>> = HashMap::new();
+        m.insert("Rules".to_owned(), cr_rules);
+        m.insert("Aggregated".to_owned(), cr_aggregated);
+        m
+    };
+}
+const COL_MAP: &[(&str, &str)] = &[("name", "Name"), ("rules", "Rules"), ("age", "Age")];
+
+const COL_FLAGS: &[&str] = &{ extract_first!(COL_MAP) };
+
+const EXTRA_COL_MAP: &[(&str, &str)] = &[("aggregated", "Aggregated"), ("labels", "Labels")];
+
+const EXTRA_COL_FLAGS: &[&str] = &{ extract_first!(EXTRA_COL_MAP) };
+
+fn cr_to_kobj(role: &api_rbac::ClusterRole) -> KObj {
+    let meta = &role.metadata;
+    KObj {
+        name: meta.name.clone().unwrap_or_else(|| "<Unknown>".into()),
+        namespace: None,
+        uid: meta.uid.clone(),
+        context: None,
+        typ: ObjType::ClusterRole,
+    }
+}
+
+fn cr_rules(role: &api_rbac::ClusterRole) -> Option<CellSpec<'_>> {
+    Some(role.rules.len().into())
+}
+
+/// aggregated cluster roles get their rules from the roles their selectors match
+fn cr_aggregated(role: &api_rbac::ClusterRole) -> Option<CellSpec<'_>> {
+    Some(role.aggregation_rule.is_some().to_string().into())
+}
+
+list_command!(
+    ClusterRoles,
+    "clusterroles",
+    "Get cluster roles. Describing a cluster role shows who it's bound to, cluster wide or in a \
+     namespace.",
+    super::COL_FLAGS,
+    super::EXTRA_COL_FLAGS,
+    |clap: App<'static, 'static>| clap
+        .arg(
+            Arg::with_name("show_label")
+                .short("L")
+                .long("labels")
+                .help("Show cluster role labels (deprecated, use --show labels)")
+                .takes_value(false)
+        )
+        .arg(
+            Arg::with_name("regex")
+                .short("r")
+                .long("regex")
+                .help("Filter cluster roles by the specified regex")
+                .takes_value(true)
+        )
+        .arg(show_arg(EXTRA_COL_FLAGS, true))
+        .arg(sort_arg(COL_FLAGS, Some(EXTRA_COL_FLAGS)))
+        .arg(
+            Arg::with_name("reverse")
+                .short("R")
+                .long("reverse")
+                .help("Reverse the order of the returned list")
+                .takes_value(false),
+        ),
+    vec!["clusterroles"],
+    noop_complete!(),
+    [],
+    |matches, env, writer| {
+        let (request, _response_body) =
+            api_rbac::ClusterRole::list_cluster_role(Default::default())?;
+        let cols: Vec<&str> = COL_MAP.iter().map(|(_, col)| *col).collect();
+
+        run_list_command(
+            matches,
+            env,
+            writer,
+            cols,
+            request,
+            COL_MAP,
+            Some(EXTRA_COL_MAP),
+            Some(&CR_EXTRACTORS),
+            cr_to_kobj,
+        )
+    }
+);
@@ -16,6 +16,7 @@
 use clap::{App, AppSettings, Arg, ArgMatches};
 use rustyline::completion::Pair as RustlinePair;
 
+use crate::config::Impersonation;
 use crate::env::Env;
 use crate::error::ClickError;
 use crate::output::ClickWriter;
@@ -103,7 +104,45 @@ pub fn start_clap(
         .before_help(aliases)
         .setting(AppSettings::NoBinaryName)
         .setting(AppSettings::DisableVersion)
-        .setting(AppSettings::ColoredHelp);
+        .setting(AppSettings::ColoredHelp)
+        .arg(
+            Arg::with_name("kubeconfig")
+                .long("kubeconfig")
+                .value_name("PATH")
+                .help(
+                    "Run kubectl with this kubernetes config file, just for this command. Requests \
+                     click makes itself still use the config files in use.",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("as")
+                .long("as")
+                .value_name("USER")
+                .help(
+                    "Impersonate this user, just for this command, in requests and when running \
+                     kubectl",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("as_group")
+                .long("as-group")
+                .value_name("GROUP")
+                .help("The user from --as is in this group, can be repeated")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .requires("as"),
+        )
+        .arg(
+            Arg::with_name("no_truncate")
+                .long("no-truncate")
+                .help(
+                    "Print tables at their full width, rather than cutting long values short to \
+                     fit the terminal",
+                ),
+        );
     if trailing_var_arg {
         app.setting(AppSettings::TrailingVarArg)
     } else {
@@ -124,7 +163,33 @@ where
 {
     let matches = clap.borrow_mut().get_matches_from_safe_borrow(args);
     match matches {
-        Ok(matches) => func(matches, env, writer),
+        Ok(matches) => {
+            let kubeconfig = matches.value_of("kubeconfig").map(crate::env::expand_home);
+            if let Some(path) = kubeconfig.as_ref() {
+                if !path.is_file() {
+                    return Err(ClickError::CommandError(format!(
+                        "Kubeconfig {} doesn't exist",
+                        path.display()
+                    )));
+                }
+            }
+            let impersonate = matches.value_of("as").map(|user| Impersonation {
+                user: user.to_string(),
+                groups: matches
+                    .values_of("as_group")
+                    .map(|groups| groups.map(|g| g.to_string()).collect())
+                    .unwrap_or_default(),
+            });
+            if matches.is_present("no_truncate") {
+                writer.set_table_width(None);
+            }
+            env.command_kubeconfig = kubeconfig;
+            env.set_command_impersonate(impersonate);
+            let res = func(matches, env, writer);
+            env.command_kubeconfig = None;
+            env.set_command_impersonate(None);
+            res
+        }
         Err(e) => {
             if e.kind == clap::ErrorKind::HelpDisplayed
                 || e.kind == clap::ErrorKind::VersionDisplayed
@@ -206,8 +271,11 @@ macro_rules! command {
         impl $cmd_name {
             pub fn new() -> $cmd_name {
                 lazy_static! {
-                    static ref ALIASES_STR: String =
-                        format!("{}:\n    {:?}", Yellow.paint("ALIASES"), $aliases);
+                    static ref ALIASES_STR: String = format!(
+                        "{}:\n    {:?}",
+                        crate::theme::style(crate::theme::Role::Header).paint("ALIASES"),
+                        $aliases
+                    );
                 }
                 let clap = start_clap($name, $about, &ALIASES_STR, $trailing_var_arg);
                 let extra = $extra_args(clap);
@@ -355,11 +423,13 @@ macro_rules! list_command {
             $cmd_name,
             $name,
             $about,
-            $extra_args,
+            |clap: clap::App<'static, 'static>| {
+                crate::command::command_def::add_multi_list_args(($extra_args)(clap))
+            },
             $aliases,
             $cmplters,
             //$named_cmplters,
-            IntoIter::new([
+            IntoIterator::into_iter([
                 (
                     "sort".to_string(),
                     list_sort_completers::$cmd_name as fn(&str, &Env) -> Vec<RustlinePair>
@@ -367,6 +437,15 @@ macro_rules! list_command {
                 (
                     "show".to_string(),
                     list_show_completers::$cmd_name as fn(&str, &Env) -> Vec<RustlinePair>
+                ),
+                (
+                    "namespaces".to_string(),
+                    crate::completer::namespace_list_completer
+                        as fn(&str, &Env) -> Vec<RustlinePair>
+                ),
+                (
+                    "contexts".to_string(),
+                    crate::completer::context_list_completer as fn(&str, &Env) -> Vec<RustlinePair>
                 )
             ])
             .chain($named_cmplters)
@@ -432,6 +511,32 @@ pub fn sort_arg<'a>(cols: &[&'a str], extra_cols: Option<&[&'a str]>) -> Arg<'a,
 static SHOW_HELP: &str =
     "Comma separated list (case-insensitive) of extra columns to show in output. \
      Use '--show all' to show all available columns.";
+/// Add the args for listing from several namespaces or contexts at once
+pub fn add_multi_list_args(clap: App<'static, 'static>) -> App<'static, 'static> {
+    clap.arg(
+        Arg::with_name("namespaces")
+            .long("namespaces")
+            .value_name("NAMESPACES")
+            .help(
+                "List from each of these comma separated namespaces, rather than the current \
+                 one. Requests are sent in parallel, and the results merged into one table.",
+            )
+            .takes_value(true)
+            .use_delimiter(true),
+    )
+    .arg(
+        Arg::with_name("contexts")
+            .long("contexts")
+            .value_name("CONTEXTS")
+            .help(
+                "List from each of these comma separated contexts (or context aliases), rather \
+                 than the active one. Selecting an object from the list switches to its context.",
+            )
+            .takes_value(true)
+            .use_delimiter(true),
+    )
+}
+
 static SHOW_HELP_WITH_LABELS: &str =
     "Comma separated list (case-insensitive) of extra columns to show in output. \
      Use '--show all,labels' to show all available columns. (Note that 'all' doesn't \
@@ -12,7 +12,6 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use ansi_term::Colour::Yellow;
 use clap::{App, Arg};
 use k8s_openapi::api::core::v1 as api;
 
@@ -26,7 +25,6 @@ use crate::{
     table::CellSpec,
 };
 
-use std::array::IntoIter;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::io::Write;
@@ -51,6 +49,8 @@ fn cm_to_kobj(configmap: &api::ConfigMap) -> KObj {
     KObj {
         name: meta.name.clone().unwrap_or_else(|| "<Unknown>".into()),
         namespace: meta.namespace.clone(),
+        uid: meta.uid.clone(),
+        context: None,
         typ: ObjType::ConfigMap,
     }
 }
@@ -91,7 +91,7 @@ list_command!(
         ),
     vec!["cm", "configmaps"],
     noop_complete!(),
-    IntoIter::new([]),
+    [],
     |matches, env, writer| {
         let (request, _response_body) = match &env.namespace {
             Some(ns) => api::ConfigMap::list_namespaced_config_map(ns, Default::default())?,
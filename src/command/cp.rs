@@ -0,0 +1,386 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use clap::{App, Arg};
+use rustyline::completion::Pair as RustlinePair;
+
+use crate::{
+    command::command_def::{exec_match, start_clap, Cmd},
+    command::{command_line, kubectl_exit_error, kubectl_spawn_error, kubectl_streaming_command},
+    completer,
+    env::Env,
+    error::ClickError,
+    kobj::KObj,
+    output::ClickWriter,
+    tar::{self, Progress},
+    theme::{self, Role},
+};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Transfers smaller than this don't report progress
+const PROGRESS_MIN_BYTES: u64 = 1024 * 1024;
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: &[&str] = &["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Prints progress for large transfers, at most once per PROGRESS_INTERVAL
+struct ProgressReporter {
+    total: Option<u64>,
+    last: Instant,
+}
+
+impl ProgressReporter {
+    fn new(total: Option<u64>) -> ProgressReporter {
+        ProgressReporter {
+            total,
+            last: Instant::now(),
+        }
+    }
+
+    fn report(&mut self, progress: &Progress, writer: &mut ClickWriter) {
+        if progress.bytes < PROGRESS_MIN_BYTES || self.last.elapsed() < PROGRESS_INTERVAL {
+            return;
+        }
+        self.last = Instant::now();
+        match self.total {
+            Some(total) if total > 0 => clickwriteln!(
+                writer,
+                "  {} / {} ({}%)",
+                format_bytes(progress.bytes),
+                format_bytes(total),
+                progress.bytes * 100 / total
+            ),
+            _ => clickwriteln!(writer, "  {} copied", format_bytes(progress.bytes)),
+        }
+    }
+}
+
+/// Split a path in the container into the directory to run tar in, and the name in that directory
+fn split_remote(path: &str) -> Result<(String, String), ClickError> {
+    let trimmed = path.trim_end_matches('/');
+    if trimmed.is_empty() {
+        return Err(ClickError::CommandError(
+            "Need a file or directory in the container, not /".to_string(),
+        ));
+    }
+    Ok(match trimmed.rsplit_once('/') {
+        Some(("", name)) => ("/".to_string(), name.to_string()),
+        Some((dir, name)) => (dir.to_string(), name.to_string()),
+        None => (".".to_string(), trimmed.to_string()),
+    })
+}
+
+/// A kubectl command to exec args in the container
+fn exec_command(
+    env: &Env,
+    pod: &KObj,
+    container: Option<&str>,
+    stdin: bool,
+    args: &[&str],
+) -> Result<Command, ClickError> {
+    // copying a big file can take longer than a request timeout
+    let mut command = kubectl_streaming_command(env, pod.namespace.as_deref())?;
+    command.arg("exec");
+    if stdin {
+        command.arg("-i");
+    }
+    command.arg(pod.name());
+    if let Some(cont) = container {
+        command.arg("-c").arg(cont);
+    }
+    command.arg("--").args(args);
+    Ok(command)
+}
+
+/// Is path a directory in the container. If we can't tell (no `test` binary, say), it's assumed
+/// not to be.
+fn remote_is_dir(env: &Env, pod: &KObj, container: Option<&str>, path: &str) -> bool {
+    match exec_command(env, pod, container, false, &["test", "-d", path]) {
+        Ok(mut command) => command
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success()),
+        Err(_) => false,
+    }
+}
+
+/// Collect everything the child writes to stderr, so it can't block on a full pipe
+fn read_stderr(child: &mut Child) -> JoinHandle<Vec<u8>> {
+    let stderr = child.stderr.take();
+    thread::spawn(move || {
+        let mut buf = vec![];
+        if let Some(mut stderr) = stderr {
+            stderr.read_to_end(&mut buf).unwrap_or(0);
+        }
+        buf
+    })
+}
+
+/// The error for tar in the container failing, with a hint for containers that don't have tar
+fn tar_error(status: ExitStatus, stderr: &[u8], pod: &KObj, container: Option<&str>) -> ClickError {
+    let text = String::from_utf8_lossy(stderr);
+    let missing = status.code() == Some(127)
+        || text.contains("executable file not found")
+        || text.contains("tar: not found");
+    if missing {
+        ClickError::CommandError(format!(
+            "tar is not available in {} of pod {}. cp needs tar in the container. For containers \
+             without it (like distroless images), try copying through an ephemeral debug container \
+             that has tar: kubectl debug -it {} --image=busybox --target=<container>",
+            container.map_or("the default container".to_string(), |c| format!(
+                "container {}",
+                c
+            )),
+            pod.name(),
+            pod.name()
+        ))
+    } else {
+        kubectl_exit_error(status, stderr)
+    }
+}
+
+fn upload(
+    env: &Env,
+    pod: &KObj,
+    container: Option<&str>,
+    local: &Path,
+    remote: &str,
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    let local_name = match local.file_name() {
+        Some(name) => name.to_owned(),
+        None => local
+            .canonicalize()?
+            .file_name()
+            .map(|name| name.to_owned())
+            .ok_or_else(|| ClickError::CommandError("Can't copy /".to_string()))?,
+    };
+    let local_name = local_name.to_string_lossy();
+    let total = tar::total_size(local).map_err(|e| {
+        ClickError::CommandError(format!("Could not read {}: {}", local.display(), e))
+    })?;
+
+    // like cp, copying into an existing directory puts things inside it, otherwise the last part
+    // of remote is the new name
+    let (dir, name) = if remote.is_empty() {
+        (".".to_string(), local_name.to_string())
+    } else if remote.ends_with('/') || remote_is_dir(env, pod, container, remote) {
+        (remote.to_string(), local_name.to_string())
+    } else {
+        split_remote(remote)?
+    };
+
+    let mut command = exec_command(env, pod, container, true, &["tar", "-xmf", "-", "-C", &dir])?;
+    if env.dry_run {
+        clickwriteln!(
+            writer,
+            "[DRY RUN] {} < (tar of {})",
+            command_line(&command),
+            local.display()
+        );
+        return Ok(());
+    }
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(kubectl_spawn_error)?;
+    let stderr = read_stderr(&mut child);
+
+    let mut progress = Progress::default();
+    let mut reporter = ProgressReporter::new(Some(total));
+    let write_res = match child.stdin.take() {
+        Some(stdin) => {
+            let mut out = BufWriter::new(stdin);
+            tar::append_path(&mut out, local, &name, &mut progress, &mut |p| {
+                reporter.report(p, writer)
+            })
+            .and_then(|_| tar::finish(&mut out))
+        }
+        None => Ok(()),
+    };
+    // stdin is closed now, so tar sees the end of the archive
+
+    let status = child.wait()?;
+    let stderr = stderr.join().unwrap_or_default();
+    if !status.success() {
+        return Err(tar_error(status, &stderr, pod, container));
+    }
+    write_res?;
+    let dest = if dir.ends_with('/') {
+        format!("{}{}", dir, name)
+    } else {
+        format!("{}/{}", dir, name)
+    };
+    clickwriteln!(
+        writer,
+        "Copied {} file(s) ({}) to {}:{}",
+        progress.files,
+        format_bytes(progress.bytes),
+        pod.name(),
+        dest
+    );
+    Ok(())
+}
+
+fn download(
+    env: &Env,
+    pod: &KObj,
+    container: Option<&str>,
+    remote: &str,
+    local: &Path,
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    let (dir, name) = split_remote(remote)?;
+    // like cp, copying into an existing directory puts things inside it
+    let target: PathBuf = if local.is_dir() {
+        local.join(&name)
+    } else {
+        local.to_path_buf()
+    };
+
+    let mut command = exec_command(
+        env,
+        pod,
+        container,
+        false,
+        &["tar", "cf", "-", "-C", &dir, &name],
+    )?;
+    if env.dry_run {
+        clickwriteln!(
+            writer,
+            "[DRY RUN] {} > (extract to {})",
+            command_line(&command),
+            target.display()
+        );
+        return Ok(());
+    }
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(kubectl_spawn_error)?;
+    let stderr = read_stderr(&mut child);
+
+    let mut reporter = ProgressReporter::new(None);
+    let extract_res = match child.stdout.take() {
+        Some(stdout) => tar::extract(stdout, &target, &name, &mut |p| reporter.report(p, writer)),
+        None => Err(io::Error::other("no output from kubectl")),
+    };
+
+    let status = child.wait()?;
+    let stderr = stderr.join().unwrap_or_default();
+    if !status.success() {
+        return Err(tar_error(status, &stderr, pod, container));
+    }
+    let (progress, skipped) = extract_res?;
+    for skip in skipped.iter() {
+        clickwriteln!(
+            writer,
+            "{}",
+            theme::style(Role::Warning)
+                .paint(format!("Warning: skipped {}: {}", skip.path, skip.reason))
+        );
+    }
+    clickwriteln!(
+        writer,
+        "Copied {} file(s) ({}) to {}",
+        progress.files,
+        format_bytes(progress.bytes),
+        target.display()
+    );
+    Ok(())
+}
+
+command!(
+    Cp,
+    "cp",
+    "Copy files and directories to and from the active pod",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("src")
+                .help("What to copy. Paths in the container start with a ':'")
+                .required(true)
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("dest")
+                .help("Where to copy it to. Paths in the container start with a ':'")
+                .required(true)
+                .index(2),
+        )
+        .arg(
+            Arg::with_name("container")
+                .short("c")
+                .long("container")
+                .help("Copy to or from the specified container")
+                .takes_value(true),
+        )
+        .after_help(
+            "Exactly one of src and dest must be in the container. Directories are copied \
+recursively, and file modes are kept. Symlinks are copied into the container, but like kubectl, \
+not out of it. This runs tar in the container, so it must have tar installed.
+
+Examples:
+  # upload a directory into /tmp in the container
+  cp ./configs :/tmp/
+
+  # download a file from the sidecar container to the current directory
+  cp -c sidecar :/var/log/app.log .",
+        )
+    },
+    vec!["cp"],
+    noop_complete!(),
+    std::iter::once((
+        "container".to_string(),
+        completer::container_completer as fn(&str, &Env) -> Vec<RustlinePair>
+    ))
+    .collect(),
+    |matches, env, writer| {
+        let pod = env.current_pod()?;
+        let src = matches.value_of("src").unwrap(); // safe, required
+        let dest = matches.value_of("dest").unwrap(); // safe, required
+        let container = matches.value_of("container");
+        match (src.strip_prefix(':'), dest.strip_prefix(':')) {
+            (None, Some(remote)) => upload(env, pod, container, Path::new(src), remote, writer),
+            (Some(remote), None) => download(env, pod, container, remote, Path::new(dest), writer),
+            _ => Err(ClickError::CommandError(
+                "Exactly one of src and dest must be a path in the container, starting with ':'"
+                    .to_string(),
+            )),
+        }
+    }
+);
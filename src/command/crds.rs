@@ -12,7 +12,6 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use ansi_term::Colour::Yellow;
 use clap::{App, Arg};
 
 use rustyline::completion::Pair as RustlinePair;
@@ -39,15 +38,12 @@ struct CrdApiDesc {
 
 impl CrdApiDesc {
     fn url(&self, namespace: Option<&str>) -> String {
-        if self.namespaced && namespace.is_some() {
-            format!(
+        match namespace {
+            Some(ns) if self.namespaced => format!(
                 "/apis/{}/namespaces/{}/{}",
-                self.group_version,
-                namespace.as_ref().unwrap(), // safe: checked
-                self.name
-            )
-        } else {
-            format!("/apis/{}/{}", self.group_version, self.name)
+                self.group_version, ns, self.name
+            ),
+            _ => format!("/apis/{}/{}", self.group_version, self.name),
         }
     }
 }
@@ -63,8 +59,8 @@ fn find_desc_for(env: &mut Env, name: &str) -> Result<Option<CrdApiDesc>, ClickE
         };
         if let Some(group_version) = version {
             let (group_req, _) = crate::crd::get_api_group_resources(group_version)?;
-            match env.run_on_context::<_, GetAPIGroupResourcesResponse>(|c| c.read(group_req))? {
-                GetAPIGroupResourcesResponse::Ok(resp) => {
+            match env.run_on_context::<_, GetAPIGroupResourcesResponse>(|c| c.read(group_req)) {
+                Ok(GetAPIGroupResourcesResponse::Ok(resp)) => {
                     for resource in resp.resources.iter() {
                         if resource.name == name || resource.singular_name == name {
                             return Ok(Some(CrdApiDesc {
@@ -75,9 +71,10 @@ fn find_desc_for(env: &mut Env, name: &str) -> Result<Option<CrdApiDesc>, ClickE
                         }
                     }
                 }
-                GetAPIGroupResourcesResponse::Other(_) => {
-                    println!("Error"); // TODO: Print something more useful
-                }
+                // an api group that's down (like an aggregated api with no backend) shouldn't
+                // stop us finding the resource in another one
+                Ok(GetAPIGroupResourcesResponse::Other(_)) | Err(ClickError::Api { .. }) => {}
+                Err(e) => return Err(e),
             }
         }
     }
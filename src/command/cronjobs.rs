@@ -0,0 +1,342 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::Utc;
+use clap::{App, Arg, ArgGroup};
+use k8s_openapi::api::batch::v1beta1 as batch_api;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::Patch;
+use k8s_openapi::{ListOptional, PatchResponse};
+
+use crate::{
+    command::command_def::{exec_match, show_arg, sort_arg, start_clap, Cmd},
+    command::{
+        dry_run_request, kubectl_command, kubectl_mutate, run_list_command, time_since, Extractor,
+    },
+    completer,
+    cron::CronSchedule,
+    env::Env,
+    error::ClickError,
+    kobj::{KObj, ObjType},
+    output::ClickWriter,
+    table::CellSpec,
+    theme::{self, Role},
+    values::val_str,
+};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+
+lazy_static! {
+    static ref CRONJOB_EXTRACTORS: HashMap<String, Extractor<batch_api::CronJob>> = {
+        let mut m: HashMap<String, Extractor<batch_api::CronJob>> = HashMap::new();
+        m.insert("Schedule".to_owned(), cronjob_schedule);
+        m.insert("Suspend".to_owned(), cronjob_suspend);
+        m.insert("Active".to_owned(), cronjob_active);
+        m.insert("Last Schedule".to_owned(), cronjob_last_schedule);
+        m.insert("Next Schedule".to_owned(), cronjob_next_schedule);
+        m.insert("Concurrency".to_owned(), cronjob_concurrency);
+        m.insert("Images".to_owned(), cronjob_images);
+        m
+    };
+}
+const COL_MAP: &[(&str, &str)] = &[
+    ("name", "Name"),
+    ("schedule", "Schedule"),
+    ("suspend", "Suspend"),
+    ("active", "Active"),
+    ("lastschedule", "Last Schedule"),
+    ("nextschedule", "Next Schedule"),
+    ("age", "Age"),
+];
+
+const COL_FLAGS: &[&str] = &{ extract_first!(COL_MAP) };
+
+const EXTRA_COL_MAP: &[(&str, &str)] = &[
+    ("namespace", "Namespace"),
+    ("concurrency", "Concurrency"),
+    ("images", "Images"),
+    ("labels", "Labels"),
+];
+
+const EXTRA_COL_FLAGS: &[&str] = &{ extract_first!(EXTRA_COL_MAP) };
+
+/// Kubernetes limits job names to 63 chars
+const MAX_JOB_NAME_LEN: usize = 63;
+
+fn cronjob_to_kobj(cronjob: &batch_api::CronJob) -> KObj {
+    let meta = &cronjob.metadata;
+    KObj {
+        name: meta.name.clone().unwrap_or_else(|| "<Unknown>".into()),
+        namespace: meta.namespace.clone(),
+        uid: meta.uid.clone(),
+        context: None,
+        typ: ObjType::CronJob,
+    }
+}
+
+fn is_suspended(cronjob: &batch_api::CronJob) -> bool {
+    cronjob
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.suspend)
+        .unwrap_or(false)
+}
+
+fn cronjob_schedule(cronjob: &batch_api::CronJob) -> Option<CellSpec<'_>> {
+    cronjob
+        .spec
+        .as_ref()
+        .map(|spec| spec.schedule.as_str().into())
+}
+
+fn cronjob_suspend(cronjob: &batch_api::CronJob) -> Option<CellSpec<'_>> {
+    if is_suspended(cronjob) {
+        Some(CellSpec::with_role("True".into(), Role::Warning))
+    } else {
+        Some("False".into())
+    }
+}
+
+fn cronjob_active(cronjob: &batch_api::CronJob) -> Option<CellSpec<'_>> {
+    Some(
+        cronjob
+            .status
+            .as_ref()
+            .map(|stat| stat.active.len())
+            .unwrap_or(0)
+            .into(),
+    )
+}
+
+fn cronjob_last_schedule(cronjob: &batch_api::CronJob) -> Option<CellSpec<'_>> {
+    match cronjob
+        .status
+        .as_ref()
+        .and_then(|stat| stat.last_schedule_time.as_ref())
+    {
+        Some(last) => Some(time_since(last.0).into()),
+        None => Some("<none>".into()),
+    }
+}
+
+/// time until the next scheduled run. schedules are evaluated in UTC, which is what the
+/// controller-manager uses unless it's been explicitly configured otherwise.
+fn cronjob_next_schedule(cronjob: &batch_api::CronJob) -> Option<CellSpec<'_>> {
+    if is_suspended(cronjob) {
+        return Some(CellSpec::with_role("<suspended>".into(), Role::Warning));
+    }
+    cronjob.spec.as_ref().map(|spec| {
+        let now = Utc::now();
+        match CronSchedule::parse(&spec.schedule) {
+            Ok(schedule) => match schedule.next_after(now) {
+                Some(next) => next.signed_duration_since(now).into(),
+                None => CellSpec::with_role("<never>".into(), Role::BadStatus),
+            },
+            Err(_) => CellSpec::with_role("<invalid schedule>".into(), Role::BadStatus),
+        }
+    })
+}
+
+fn cronjob_concurrency(cronjob: &batch_api::CronJob) -> Option<CellSpec<'_>> {
+    cronjob
+        .spec
+        .as_ref()
+        .map(|spec| spec.concurrency_policy.as_deref().unwrap_or("Allow").into())
+}
+
+fn cronjob_images(cronjob: &batch_api::CronJob) -> Option<CellSpec<'_>> {
+    cronjob.spec.as_ref().and_then(|spec| {
+        spec.job_template
+            .spec
+            .as_ref()
+            .and_then(|job_spec| job_spec.template.spec.as_ref())
+            .map(|pod_spec| {
+                let names: Vec<&str> = pod_spec
+                    .containers
+                    .iter()
+                    .map(|cont| cont.image.as_deref().unwrap_or("<unknown>"))
+                    .collect();
+                names.join(", ").into()
+            })
+    })
+}
+
+fn need_namespace(env: &Env) -> Result<String, ClickError> {
+    env.namespace.clone().ok_or_else(|| {
+        ClickError::CommandError("Set a namespace to modify or trigger a cronjob".to_string())
+    })
+}
+
+/// Manually run a cronjob by creating a job from its template
+fn trigger_cronjob(env: &Env, writer: &mut ClickWriter, name: &str) -> Result<(), ClickError> {
+    let ns = need_namespace(env)?;
+    let suffix = format!("-manual-{}", Utc::now().timestamp());
+    let base_len = name.len().min(MAX_JOB_NAME_LEN - suffix.len());
+    let job_name = format!("{}{}", &name[..base_len], suffix);
+    let mut command = kubectl_command(env, Some(&ns))?;
+    command
+        .arg("create")
+        .arg("job")
+        .arg(format!("--from=cronjob/{}", name))
+        .arg(&job_name);
+    if kubectl_mutate(env, command, writer)?.is_none() {
+        return Ok(());
+    }
+    clickwriteln!(
+        writer,
+        "Triggered cronjob {}, created job {} (see: jobs {})",
+        name,
+        theme::style(Role::GoodStatus).paint(job_name.as_str()),
+        job_name
+    );
+    Ok(())
+}
+
+/// Set spec.suspend on a cronjob
+fn set_suspend(
+    env: &Env,
+    writer: &mut ClickWriter,
+    name: &str,
+    suspend: bool,
+) -> Result<(), ClickError> {
+    let ns = need_namespace(env)?;
+    let patch = Patch::Merge(serde_json::json!({ "spec": { "suspend": suspend } }));
+    let (request, _) =
+        batch_api::CronJob::patch_namespaced_cron_job(name, &ns, &patch, Default::default())?;
+    if dry_run_request(env, &request, writer) {
+        return Ok(());
+    }
+    match env.run_on_context(|c| c.read::<PatchResponse<batch_api::CronJob>>(request))? {
+        PatchResponse::Ok(_) => {
+            clickwriteln!(
+                writer,
+                "{} cronjob {}",
+                if suspend { "Suspended" } else { "Resumed" },
+                name
+            );
+            Ok(())
+        }
+        PatchResponse::Other(res) => {
+            let msg = match res {
+                Ok(Some(val)) => val_str("/message", &val, "<No message>").into_owned(),
+                Ok(None) => "no reason given".to_string(),
+                Err(e) => e.to_string(),
+            };
+            Err(ClickError::CommandError(format!(
+                "Could not update cronjob {}: {}",
+                name, msg
+            )))
+        }
+    }
+}
+
+list_command!(
+    CronJobs,
+    "cronjobs",
+    "Get cronjobs (in current namespace if set), and optionally trigger, suspend, or resume one",
+    super::COL_FLAGS,
+    super::EXTRA_COL_FLAGS,
+    |clap: App<'static, 'static>| clap
+        .arg(
+            Arg::with_name("name")
+                .help("Only get the cronjob with this name (required for --suspend and --resume)")
+                .required(false)
+                .index(1)
+        )
+        .arg(
+            Arg::with_name("trigger")
+                .long("trigger")
+                .help("Manually trigger a run of the specified cronjob by creating a job from it")
+                .value_name("NAME")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("suspend")
+                .long("suspend")
+                .help("Suspend the specified cronjob so it stops scheduling new jobs")
+                .takes_value(false)
+                .requires("name")
+        )
+        .arg(
+            Arg::with_name("resume")
+                .long("resume")
+                .help("Resume a suspended cronjob")
+                .takes_value(false)
+                .requires("name")
+        )
+        .group(ArgGroup::with_name("action").args(&["trigger", "suspend", "resume"]))
+        .arg(
+            Arg::with_name("regex")
+                .short("r")
+                .long("regex")
+                .help("Filter cronjobs by the specified regex")
+                .takes_value(true)
+        )
+        .arg(show_arg(EXTRA_COL_FLAGS, true))
+        .arg(sort_arg(COL_FLAGS, Some(EXTRA_COL_FLAGS)))
+        .arg(
+            Arg::with_name("reverse")
+                .short("R")
+                .long("reverse")
+                .help("Reverse the order of the returned list")
+                .takes_value(false),
+        ),
+    vec!["cronjob", "cronjobs", "cj"],
+    vec![&completer::cronjob_completer],
+    [],
+    |matches, env, writer| {
+        if let Some(name) = matches.value_of("trigger") {
+            return trigger_cronjob(env, writer, name);
+        }
+        if matches.is_present("suspend") || matches.is_present("resume") {
+            let name = matches.value_of("name").unwrap(); // safe, required by clap
+            set_suspend(env, writer, name, matches.is_present("suspend"))?;
+            if matches.is_present("suspend") {
+                clickwriteln!(
+                    writer,
+                    "{}",
+                    theme::style(Role::Warning)
+                        .paint("Note: any jobs that are already running will not be stopped")
+                );
+            }
+            return Ok(());
+        }
+
+        let field_sel = matches
+            .value_of("name")
+            .map(|name| format!("metadata.name={}", name));
+        let opts = ListOptional {
+            field_selector: field_sel.as_deref(),
+            ..Default::default()
+        };
+        let (request, _response_body) = match &env.namespace {
+            Some(ns) => batch_api::CronJob::list_namespaced_cron_job(ns, opts)?,
+            None => batch_api::CronJob::list_cron_job_for_all_namespaces(opts)?,
+        };
+        let cols: Vec<&str> = COL_MAP.iter().map(|(_, col)| *col).collect();
+
+        run_list_command(
+            matches,
+            env,
+            writer,
+            cols,
+            request,
+            COL_MAP,
+            Some(EXTRA_COL_MAP),
+            Some(&CRONJOB_EXTRACTORS),
+            cronjob_to_kobj,
+        )
+    }
+);
@@ -0,0 +1,308 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use clap::{App, Arg};
+use k8s_openapi::api::apps::v1 as apps_api;
+use k8s_openapi::ListOptional;
+
+use crate::{
+    command::command_def::{exec_match, show_arg, sort_arg, start_clap, Cmd},
+    command::pods::{get_pods_for_controller, print_pod_sub_table},
+    command::{
+        keyval_string, kubectl_stream, kubectl_streaming_command, run_filtered_list_command,
+        Extractor,
+    },
+    completer,
+    env::{Env, ObjectSelection},
+    error::ClickError,
+    kobj::{KObj, ObjType},
+    output::ClickWriter,
+    table::CellSpec,
+    theme::Role,
+};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+
+lazy_static! {
+    static ref DS_EXTRACTORS: HashMap<String, Extractor<apps_api::DaemonSet>> = {
+        let mut m: HashMap<String, Extractor<apps_api::DaemonSet>> = HashMap::new();
+        m.insert("Desired".to_owned(), ds_desired);
+        m.insert("Current".to_owned(), ds_current);
+        m.insert("Ready".to_owned(), ds_ready);
+        m.insert("Up-To-Date".to_owned(), ds_updated);
+        m.insert("Available".to_owned(), ds_available);
+        m.insert("Node Selector".to_owned(), ds_node_selector);
+        m.insert("Containers".to_owned(), ds_containers);
+        m.insert("Images".to_owned(), ds_images);
+        m
+    };
+}
+const COL_MAP: &[(&str, &str)] = &[
+    ("name", "Name"),
+    ("desired", "Desired"),
+    ("current", "Current"),
+    ("ready", "Ready"),
+    ("uptodate", "Up-To-Date"),
+    ("available", "Available"),
+    ("age", "Age"),
+];
+
+const COL_FLAGS: &[&str] = &{ extract_first!(COL_MAP) };
+
+const EXTRA_COL_MAP: &[(&str, &str)] = &[
+    ("nodeselector", "Node Selector"),
+    ("containers", "Containers"),
+    ("images", "Images"),
+    ("labels", "Labels"),
+    ("namespace", "Namespace"),
+];
+
+const EXTRA_COL_FLAGS: &[&str] = &{ extract_first!(EXTRA_COL_MAP) };
+
+fn ds_to_kobj(daemonset: &apps_api::DaemonSet) -> KObj {
+    let meta = &daemonset.metadata;
+    KObj {
+        name: meta.name.clone().unwrap_or_else(|| "<Unknown>".into()),
+        namespace: meta.namespace.clone(),
+        uid: meta.uid.clone(),
+        context: None,
+        typ: ObjType::DaemonSet,
+    }
+}
+
+fn ds_desired(daemonset: &apps_api::DaemonSet) -> Option<CellSpec<'_>> {
+    daemonset
+        .status
+        .as_ref()
+        .map(|stat| stat.desired_number_scheduled.into())
+}
+
+fn ds_current(daemonset: &apps_api::DaemonSet) -> Option<CellSpec<'_>> {
+    daemonset
+        .status
+        .as_ref()
+        .map(|stat| stat.current_number_scheduled.into())
+}
+
+/// ready count, colored yellow if not all desired pods are ready
+fn ds_ready(daemonset: &apps_api::DaemonSet) -> Option<CellSpec<'_>> {
+    daemonset.status.as_ref().map(|stat| {
+        if stat.number_ready < stat.desired_number_scheduled {
+            CellSpec::with_role(stat.number_ready.to_string().into(), Role::Warning)
+        } else {
+            stat.number_ready.into()
+        }
+    })
+}
+
+fn ds_updated(daemonset: &apps_api::DaemonSet) -> Option<CellSpec<'_>> {
+    daemonset
+        .status
+        .as_ref()
+        .map(|stat| stat.updated_number_scheduled.unwrap_or(0).into())
+}
+
+fn ds_available(daemonset: &apps_api::DaemonSet) -> Option<CellSpec<'_>> {
+    daemonset
+        .status
+        .as_ref()
+        .map(|stat| stat.number_available.unwrap_or(0).into())
+}
+
+fn ds_node_selector(daemonset: &apps_api::DaemonSet) -> Option<CellSpec<'_>> {
+    daemonset.spec.as_ref().and_then(|spec| {
+        spec.template
+            .spec
+            .as_ref()
+            .map(|pod_spec| keyval_string(&pod_spec.node_selector).into())
+    })
+}
+
+fn ds_containers(daemonset: &apps_api::DaemonSet) -> Option<CellSpec<'_>> {
+    daemonset.spec.as_ref().and_then(|spec| {
+        spec.template.spec.as_ref().map(|pod_spec| {
+            let names: Vec<&str> = pod_spec
+                .containers
+                .iter()
+                .map(|cont| cont.name.as_str())
+                .collect();
+            names.join(", ").into()
+        })
+    })
+}
+
+fn ds_images(daemonset: &apps_api::DaemonSet) -> Option<CellSpec<'_>> {
+    daemonset.spec.as_ref().and_then(|spec| {
+        spec.template.spec.as_ref().map(|pod_spec| {
+            let names: Vec<&str> = pod_spec
+                .containers
+                .iter()
+                .map(|cont| cont.image.as_deref().unwrap_or("<unknown>"))
+                .collect();
+            names.join(", ").into()
+        })
+    })
+}
+
+/// Figure out which daemonset to operate on, either the one specified, or the active one
+fn target_daemonset(matches: &clap::ArgMatches, env: &Env) -> Result<KObj, ClickError> {
+    if let Some(name) = matches.value_of("name") {
+        match env.namespace.as_ref() {
+            Some(ns) => Ok(KObj {
+                name: name.to_string(),
+                namespace: Some(ns.clone()),
+                uid: None,
+                context: None,
+                typ: ObjType::DaemonSet,
+            }),
+            None => Err(ClickError::CommandError(
+                "Set a namespace, or select a daemonset, to check rollout status".to_string(),
+            )),
+        }
+    } else {
+        match env.current_selection() {
+            ObjectSelection::Single(obj) if obj.is(ObjType::DaemonSet) => Ok(obj.clone()),
+            _ => Err(ClickError::CommandError(
+                "Specify a daemonset name, or select a daemonset, to check rollout status"
+                    .to_string(),
+            )),
+        }
+    }
+}
+
+/// Print the pods of each daemonset that was shown in the last printed table, using the fetched
+/// daemonsets for their selectors
+fn print_daemonset_pods(
+    env: &Env,
+    writer: &mut ClickWriter,
+    fetched: &[apps_api::DaemonSet],
+) -> Result<(), ClickError> {
+    let mut index = 0;
+    while let Some(shown) = env.item_at(index) {
+        index += 1;
+        let daemonset = fetched.iter().find(|ds| {
+            ds.metadata.name.as_deref() == Some(shown.name())
+                && ds.metadata.namespace == shown.namespace
+                && ds.metadata.uid == shown.uid
+        });
+        if let Some(spec) = daemonset.and_then(|ds| ds.spec.as_ref()) {
+            let pods = get_pods_for_controller(env, shown, &spec.selector)?;
+            clickwriteln!(writer, "\nPods for daemonset {}:", shown.name());
+            print_pod_sub_table(&pods, writer);
+        }
+    }
+    Ok(())
+}
+
+list_command!(
+    DaemonSets,
+    "daemonsets",
+    "Get daemonsets (in current namespace if set)",
+    super::COL_FLAGS,
+    super::EXTRA_COL_FLAGS,
+    |clap: App<'static, 'static>| clap
+        .arg(
+            Arg::with_name("name")
+                .help("Only get the daemonset with this name")
+                .required(false)
+                .index(1)
+        )
+        .arg(
+            Arg::with_name("pods")
+                .long("pods")
+                .help("Also list the pods managed by each daemonset")
+                .takes_value(false)
+        )
+        .arg(
+            Arg::with_name("rollout_status")
+                .long("rollout-status")
+                .help(
+                    "Watch the rollout status of the specified (or active) daemonset until it's \
+                     done"
+                )
+                .takes_value(false)
+                .conflicts_with("pods")
+        )
+        .arg(
+            Arg::with_name("regex")
+                .short("r")
+                .long("regex")
+                .help("Filter daemonsets by the specified regex")
+                .takes_value(true)
+        )
+        .arg(show_arg(EXTRA_COL_FLAGS, true))
+        .arg(sort_arg(COL_FLAGS, Some(EXTRA_COL_FLAGS)))
+        .arg(
+            Arg::with_name("reverse")
+                .short("R")
+                .long("reverse")
+                .help("Reverse the order of the returned list")
+                .takes_value(false),
+        ),
+    vec!["ds", "daemonsets"],
+    vec![&completer::daemonset_completer],
+    [],
+    |matches, env, writer| {
+        if matches.is_present("rollout_status") {
+            let daemonset = target_daemonset(&matches, env)?;
+            let mut command = kubectl_streaming_command(env, daemonset.namespace.as_deref())?;
+            command
+                .arg("rollout")
+                .arg("status")
+                .arg(format!("daemonset/{}", daemonset.name()));
+            return kubectl_stream(command, writer);
+        }
+
+        let field_sel = matches
+            .value_of("name")
+            .map(|name| format!("metadata.name={}", name));
+        let opts = ListOptional {
+            field_selector: field_sel.as_deref(),
+            ..Default::default()
+        };
+        let (request, _response_body) = match &env.namespace {
+            Some(ns) => apps_api::DaemonSet::list_namespaced_daemon_set(ns, opts)?,
+            None => apps_api::DaemonSet::list_daemon_set_for_all_namespaces(opts)?,
+        };
+        let show_pods = matches.is_present("pods");
+        // keep what's fetched, as --pods needs the selectors
+        let fetched = RefCell::new(vec![]);
+        let cols: Vec<&str> = COL_MAP.iter().map(|(_, col)| *col).collect();
+
+        run_filtered_list_command(
+            matches,
+            env,
+            writer,
+            cols,
+            request,
+            COL_MAP,
+            Some(EXTRA_COL_MAP),
+            Some(&DS_EXTRACTORS),
+            ds_to_kobj,
+            |ds: &apps_api::DaemonSet| {
+                if show_pods {
+                    fetched.borrow_mut().push(ds.clone());
+                }
+                true
+            },
+        )?;
+
+        if show_pods {
+            print_daemonset_pods(env, writer, &fetched.into_inner())?;
+        }
+        Ok(())
+    }
+);
@@ -0,0 +1,149 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use clap::{App, Arg};
+use rustyline::completion::Pair as RustlinePair;
+
+use crate::{
+    command::command_def::{exec_match, start_clap, Cmd},
+    command::version::{minor_version, server_version},
+    command::{command_line, kubectl_exit_error, kubectl_spawn_error, kubectl_streaming_command},
+    completer,
+    env::Env,
+    output::ClickWriter,
+    theme::{self, Role},
+};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+
+/// The first minor version of kubernetes kubectl debug works against
+const MIN_DEBUG_MINOR: u32 = 18;
+
+command!(
+    Debug,
+    "debug",
+    "Debug the active pod by adding an ephemeral container to it, and attaching to it. With \
+     --copy-to, a copy of the pod is made with the debug container added instead, leaving the \
+     original alone.",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("image")
+                .long("image")
+                .short("i")
+                .help("The image to run in the debug container")
+                .takes_value(true)
+                .default_value("busybox"),
+        )
+        .arg(
+            Arg::with_name("target")
+                .long("target")
+                .short("t")
+                .value_name("CONTAINER")
+                .help(
+                    "Share the process namespace of this container, so its processes can be \
+                     seen from the debug container",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("copyto")
+                .long("copy-to")
+                .value_name("NEW_POD_NAME")
+                .help(
+                    "Create a copy of the pod with this name to debug, rather than the pod itself",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("command")
+                .help("The command to run in the debug container, rather than the image's default")
+                .multiple(true) // required for trailing_var_arg
+                .index(1),
+        )
+        .after_help(
+            "kubectl debug needs a server running kubernetes 1.18 or newer, and ephemeral \
+containers need to be enabled on older servers (they're on by default from 1.23). A debug copy \
+keeps running after you detach, so delete it when you're done.
+
+Examples:
+  # get a busybox shell in the active pod, seeing the processes of its app container
+  debug --target app
+
+  # debug a copy of the pod with a netshoot container, running tcpdump
+  debug --image nicolaka/netshoot --copy-to web-debug -- tcpdump -i any",
+        )
+    },
+    vec!["debug"],
+    noop_complete!(),
+    IntoIterator::into_iter([(
+        "target".to_string(),
+        completer::container_completer as fn(&str, &Env) -> Vec<RustlinePair>
+    )])
+    .collect(),
+    |matches, env, writer| {
+        let pod = env.current_pod()?.clone();
+        if let Some(server) = server_version(env) {
+            if minor_version(&server).is_some_and(|minor| minor < MIN_DEBUG_MINOR) {
+                clickwriteln!(
+                    writer,
+                    "{}",
+                    theme::style(Role::Warning).paint(format!(
+                        "Warning: the server is running {}, but kubectl debug needs 1.{} or newer",
+                        server, MIN_DEBUG_MINOR
+                    ))
+                );
+            }
+        }
+
+        let mut command = kubectl_streaming_command(env, pod.namespace.as_deref())?;
+        command
+            .arg("debug")
+            .arg("-it")
+            .arg(pod.name())
+            .arg("--image")
+            .arg(matches.value_of("image").unwrap()); // safe, has a default
+        if let Some(target) = matches.value_of("target") {
+            command.arg("--target").arg(target);
+        }
+        if let Some(copy_to) = matches.value_of("copyto") {
+            command.arg("--copy-to").arg(copy_to);
+        }
+        if let Some(cmd) = matches.values_of("command") {
+            command.arg("--").args(cmd);
+        }
+
+        if env.dry_run {
+            clickwriteln!(writer, "[DRY RUN] {}", command_line(&command));
+            return Ok(());
+        }
+        // attached to the terminal like exec, so nothing's captured, but the exit code is kept
+        writer.finish_paging();
+        let status = command.status().map_err(kubectl_spawn_error)?;
+        if status.success() {
+            if let Some(copy_to) = matches.value_of("copyto") {
+                clickwriteln!(
+                    writer,
+                    "Debug copy {} is still running, delete it when you're done",
+                    copy_to
+                );
+            }
+            Ok(())
+        } else {
+            Err(kubectl_exit_error(status, &[]))
+        }
+    },
+    true // gather up everything after the options into the command
+);
@@ -12,20 +12,24 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use ansi_term::Colour::Yellow;
 use clap::{App, Arg};
 use k8s_openapi::{
-    api::apps::v1 as api_apps, api::batch::v1 as api_batch, api::core::v1 as api,
-    api::storage::v1 as api_storage, http::Request, DeleteOptional, DeleteResponse,
+    api::apps::v1 as api_apps, api::autoscaling::v2beta2 as api_autoscaling,
+    api::batch::v1 as api_batch, api::batch::v1beta1 as api_batch_beta, api::core::v1 as api,
+    api::rbac::v1 as api_rbac, api::storage::v1 as api_storage, http::Request, DeleteOptional,
+    DeleteResponse,
 };
 use rustyline::completion::Pair as RustlinePair;
 use serde::de::DeserializeOwned;
 
 use crate::{
     command::command_def::{exec_match, start_clap, Cmd},
-    command::{uppercase_first, valid_u32},
+    command::wait::wait_for_deletion,
+    command::{
+        confirm_action, dry_run_request, parse_duration, uppercase_first, valid_duration, valid_u32,
+    },
     completer,
-    env::Env,
+    env::{Env, ErrorStrategy},
     error::ClickError,
     kobj::{KObj, ObjType},
     output::ClickWriter,
@@ -36,12 +40,19 @@ use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::io::{self, stderr, Write};
+use std::time::Duration;
+
+/// How long delete --wait waits for objects to be gone, if no --timeout is given
+const DEFAULT_WAIT_TIMEOUT: Duration = Duration::from_secs(60);
 
-fn send_delete<D: DeserializeOwned + Debug>(
+pub fn send_delete<D: DeserializeOwned + Debug>(
     env: &Env,
     writer: &mut ClickWriter,
     request: Request<Vec<u8>>,
 ) -> Result<(), ClickError> {
+    if dry_run_request(env, &request, writer) {
+        return Ok(());
+    }
     let r = env.run_on_context::<_, DeleteResponse<D>>(|c| c.read(request))?;
     match r {
         DeleteResponse::OkStatus(_) | DeleteResponse::OkValue(_) => {
@@ -79,163 +90,223 @@ fn delete_obj(
     obj: &KObj,
     options: DeleteOptional,
 ) -> Result<(), ClickError> {
-    match obj.namespace.as_ref() {
-        Some(ns) => match obj.typ {
-            ObjType::ConfigMap => {
-                let req = api::ConfigMap::delete_namespaced_config_map(
-                    obj.name.as_str(),
-                    ns.as_str(),
-                    options,
-                )?
-                .0;
-                send_delete::<api::ConfigMap>(env, writer, req)
-            }
-            ObjType::Deployment => {
-                let req = api_apps::Deployment::delete_namespaced_deployment(
-                    obj.name.as_str(),
-                    ns.as_str(),
-                    options,
-                )?
-                .0;
-                send_delete::<api_apps::Deployment>(env, writer, req)
-            }
-            ObjType::Job => {
-                let req =
-                    api_batch::Job::delete_namespaced_job(obj.name.as_str(), ns.as_str(), options)?
-                        .0;
-                send_delete::<api_batch::Job>(env, writer, req)
+    if !obj.is_namespaced() {
+        return match obj.typ {
+            ObjType::Node => {
+                let req = api::Node::delete_node(obj.name.as_str(), options)?.0;
+                send_delete::<api::Node>(env, writer, req)
             }
             ObjType::Namespace => {
-                clickwriteln!(
-                    writer,
-                    "Namespace has unexpected namespace. Please file an issue on github. \
-                     Deleting anyway"
-                );
                 let req = api::Namespace::delete_namespace(obj.name.as_str(), options)?.0;
                 send_delete::<api::Namespace>(env, writer, req)
             }
-            ObjType::Node => {
-                clickwriteln!(
-                    writer,
-                    "Node has unexpected namespace. Please file an issue on github. \
-                         Deleting anyway"
-                );
-                let req = api::Node::delete_node(obj.name.as_str(), options)?.0;
-                send_delete::<api::Node>(env, writer, req)
-            }
             ObjType::PersistentVolume => {
-                clickwriteln!(
-                    writer,
-                    "PersistentVolume has unexpected namespace. Please file an issue on github. \
-                     Deleting anyway"
-                );
                 let req =
                     api::PersistentVolume::delete_persistent_volume(obj.name.as_str(), options)?.0;
                 send_delete::<api::PersistentVolume>(env, writer, req)
             }
-            ObjType::Pod { .. } => {
+            ObjType::StorageClass => {
                 let req =
-                    api::Pod::delete_namespaced_pod(obj.name.as_str(), ns.as_str(), options)?.0;
-                send_delete::<api::Pod>(env, writer, req)
+                    api_storage::StorageClass::delete_storage_class(obj.name.as_str(), options)?.0;
+                send_delete::<api_storage::StorageClass>(env, writer, req)
             }
-            ObjType::Crd { .. } => Err(ClickError::CommandError(
-                "Can't delete CRDs yet".to_string(),
-            )),
-            ObjType::ReplicaSet => {
-                let req = api_apps::ReplicaSet::delete_namespaced_replica_set(
-                    obj.name.as_str(),
-                    ns.as_str(),
-                    options,
-                )?
-                .0;
-                send_delete::<api_apps::ReplicaSet>(env, writer, req)
+            ObjType::ClusterRole => {
+                let req = api_rbac::ClusterRole::delete_cluster_role(obj.name.as_str(), options)?.0;
+                send_delete::<api_rbac::ClusterRole>(env, writer, req)
             }
-            ObjType::StatefulSet => {
-                let req = api_apps::StatefulSet::delete_namespaced_stateful_set(
+            ObjType::ClusterRoleBinding => {
+                let req = api_rbac::ClusterRoleBinding::delete_cluster_role_binding(
                     obj.name.as_str(),
-                    ns.as_str(),
                     options,
                 )?
                 .0;
-                send_delete::<api_apps::StatefulSet>(env, writer, req)
-            }
-            ObjType::Secret => {
-                let req =
-                    api::Secret::delete_namespaced_secret(obj.name.as_str(), ns.as_str(), options)?
-                        .0;
-                send_delete::<api::Secret>(env, writer, req)
+                send_delete::<api_rbac::ClusterRoleBinding>(env, writer, req)
             }
-            ObjType::Service => {
-                let req = api::Service::delete_namespaced_service(
+            _ => Err(ClickError::CommandError(format!(
+                "Can't delete cluster scoped {} objects yet",
+                obj.type_str()
+            ))),
+        };
+    }
+    let ns = match obj.namespace.as_ref() {
+        Some(ns) => ns,
+        None => {
+            let msg = format!("Object {} has no namespace. Cannot delete", obj.name());
+            return Err(ClickError::CommandError(msg));
+        }
+    };
+    match obj.typ {
+        ObjType::ConfigMap => {
+            let req = api::ConfigMap::delete_namespaced_config_map(
+                obj.name.as_str(),
+                ns.as_str(),
+                options,
+            )?
+            .0;
+            send_delete::<api::ConfigMap>(env, writer, req)
+        }
+        ObjType::DaemonSet => {
+            let req = api_apps::DaemonSet::delete_namespaced_daemon_set(
+                obj.name.as_str(),
+                ns.as_str(),
+                options,
+            )?
+            .0;
+            send_delete::<api_apps::DaemonSet>(env, writer, req)
+        }
+        ObjType::Deployment => {
+            let req = api_apps::Deployment::delete_namespaced_deployment(
+                obj.name.as_str(),
+                ns.as_str(),
+                options,
+            )?
+            .0;
+            send_delete::<api_apps::Deployment>(env, writer, req)
+        }
+        ObjType::Job => {
+            let req =
+                api_batch::Job::delete_namespaced_job(obj.name.as_str(), ns.as_str(), options)?.0;
+            send_delete::<api_batch::Job>(env, writer, req)
+        }
+        ObjType::CronJob => {
+            let req = api_batch_beta::CronJob::delete_namespaced_cron_job(
+                obj.name.as_str(),
+                ns.as_str(),
+                options,
+            )?
+            .0;
+            send_delete::<api_batch_beta::CronJob>(env, writer, req)
+        }
+        ObjType::HorizontalPodAutoscaler => {
+            let req =
+                api_autoscaling::HorizontalPodAutoscaler::delete_namespaced_horizontal_pod_autoscaler(
                     obj.name.as_str(),
                     ns.as_str(),
                     options,
                 )?
                 .0;
-                send_delete::<api::Service>(env, writer, req)
-            }
-            ObjType::StorageClass => {
-                clickwriteln!(
-                    writer,
-                    "StorageClass has unexpected namespace. Please file an issue on github. \
-                         Deleting anyway"
-                );
-                let req =
-                    api_storage::StorageClass::delete_storage_class(obj.name.as_str(), options)?.0;
-                send_delete::<api_storage::StorageClass>(env, writer, req)
-            }
-            #[cfg(feature = "argorollouts")]
-            ObjType::Rollout => Err(ClickError::CommandError(
-                "Cannot delete rollouts".to_string(),
-            )),
-        },
-        None => match obj.typ {
-            ObjType::Node => {
-                let req = api::Node::delete_node(obj.name.as_str(), options)?.0;
-                send_delete::<api::Node>(env, writer, req)
-            }
-            ObjType::Namespace => {
-                let req = api::Namespace::delete_namespace(obj.name.as_str(), options)?.0;
-                send_delete::<api::Namespace>(env, writer, req)
-            }
-            ObjType::PersistentVolume => {
-                let req =
-                    api::PersistentVolume::delete_persistent_volume(obj.name.as_str(), options)?.0;
-                send_delete::<api::PersistentVolume>(env, writer, req)
-            }
-            ObjType::StorageClass => {
-                let req =
-                    api_storage::StorageClass::delete_storage_class(obj.name.as_str(), options)?.0;
-                send_delete::<api_storage::StorageClass>(env, writer, req)
-            }
-            _ => {
-                let msg = format!("Object {} has no namespace. Cannot delete", obj.name());
-                Err(ClickError::CommandError(msg))
-            }
-        },
+            send_delete::<api_autoscaling::HorizontalPodAutoscaler>(env, writer, req)
+        }
+        ObjType::Pod { .. } => {
+            let req = api::Pod::delete_namespaced_pod(obj.name.as_str(), ns.as_str(), options)?.0;
+            send_delete::<api::Pod>(env, writer, req)
+        }
+        ObjType::Crd { .. } => Err(ClickError::CommandError(
+            "Can't delete CRDs yet".to_string(),
+        )),
+        ObjType::ReplicaSet => {
+            let req = api_apps::ReplicaSet::delete_namespaced_replica_set(
+                obj.name.as_str(),
+                ns.as_str(),
+                options,
+            )?
+            .0;
+            send_delete::<api_apps::ReplicaSet>(env, writer, req)
+        }
+        ObjType::StatefulSet => {
+            let req = api_apps::StatefulSet::delete_namespaced_stateful_set(
+                obj.name.as_str(),
+                ns.as_str(),
+                options,
+            )?
+            .0;
+            send_delete::<api_apps::StatefulSet>(env, writer, req)
+        }
+        ObjType::Secret => {
+            let req =
+                api::Secret::delete_namespaced_secret(obj.name.as_str(), ns.as_str(), options)?.0;
+            send_delete::<api::Secret>(env, writer, req)
+        }
+        ObjType::Service => {
+            let req =
+                api::Service::delete_namespaced_service(obj.name.as_str(), ns.as_str(), options)?.0;
+            send_delete::<api::Service>(env, writer, req)
+        }
+        ObjType::ServiceAccount => {
+            let req = api::ServiceAccount::delete_namespaced_service_account(
+                obj.name.as_str(),
+                ns.as_str(),
+                options,
+            )?
+            .0;
+            send_delete::<api::ServiceAccount>(env, writer, req)
+        }
+        ObjType::Role => {
+            let req =
+                api_rbac::Role::delete_namespaced_role(obj.name.as_str(), ns.as_str(), options)?.0;
+            send_delete::<api_rbac::Role>(env, writer, req)
+        }
+        ObjType::RoleBinding => {
+            let req = api_rbac::RoleBinding::delete_namespaced_role_binding(
+                obj.name.as_str(),
+                ns.as_str(),
+                options,
+            )?
+            .0;
+            send_delete::<api_rbac::RoleBinding>(env, writer, req)
+        }
+        #[cfg(feature = "argorollouts")]
+        ObjType::Rollout => Err(ClickError::CommandError(
+            "Cannot delete rollouts".to_string(),
+        )),
+        ObjType::Namespace
+        | ObjType::Node
+        | ObjType::PersistentVolume
+        | ObjType::StorageClass
+        | ObjType::ClusterRole
+        | ObjType::ClusterRoleBinding => {
+            unreachable!("cluster scoped objects are deleted above")
+        }
+    }
+}
+
+/// Ask to delete objs, which the caller has listed, and delete each of them if the answer is yes.
+/// Deletes propagate in the background, so whatever they own (like a job's pods) goes too.
+pub fn confirm_delete_all(
+    env: &mut Env,
+    writer: &mut ClickWriter,
+    objs: &[KObj],
+    prompt: &str,
+) -> Result<(), ClickError> {
+    if !confirm_action(writer, prompt) {
+        clickwriteln!(writer, "Not deleting");
+        return Ok(());
+    }
+    for obj in objs.iter() {
+        let options = DeleteOptional {
+            propagation_policy: Some("Background"),
+            ..Default::default()
+        };
+        clickwrite!(writer, "{}: ", obj.name());
+        delete_obj(env, writer, obj, options)?;
     }
+    env.clear_last_objs();
+    Ok(())
 }
 
+/// Ask, then delete obj if the answer is yes. Returns if it was deleted.
 fn confirm_delete(
     env: &Env,
     obj: &KObj,
     options: DeleteOptional,
     writer: &mut ClickWriter,
-) -> Result<(), ClickError> {
+) -> Result<bool, ClickError> {
     let name = obj.name();
+    writer.finish_paging();
     clickwrite!(writer, "Delete {} {} [y/N]? ", obj.type_str(), name);
     io::stdout().flush().expect("Could not flush stdout");
     let mut conf = String::new();
     if io::stdin().read_line(&mut conf).is_ok() {
         if conf.trim() == "y" || conf.trim() == "yes" {
             delete_obj(env, writer, obj, options)?;
+            return Ok(true);
         } else {
             clickwriteln!(writer, "Not deleting");
         }
     } else {
         writeln!(stderr(), "Could not read response, not deleting.").unwrap_or(());
     }
-    Ok(())
+    Ok(false)
 }
 
 command!(
@@ -246,7 +317,8 @@ command!(
         clap.arg(
             Arg::with_name("grace")
                 .short("g")
-                .long("gracePeriod")
+                .long("grace-period")
+                .visible_alias("gracePeriod")
                 .help("The duration in seconds before the object should be deleted.")
                 .validator(valid_u32)
                 .takes_value(true),
@@ -282,6 +354,37 @@ command!(
                 .conflicts_with("grace")
                 .conflicts_with("now"),
         )
+        .arg(
+            Arg::with_name("wait")
+                .long("wait")
+                .short("w")
+                .help(
+                    "Wait for the object(s) to actually be gone, rather than returning once the \
+                     delete is accepted",
+                )
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("timeout")
+                .long("timeout")
+                .help("With --wait, how long to wait before giving up, i.e. 30s, 10m (default 60s)")
+                .takes_value(true)
+                .validator(valid_duration)
+                .requires("wait"),
+        )
+        .after_help(
+            "An object with finalizers isn't gone until whatever owns each finalizer removes it. If \
+--wait times out, the finalizers still on the object are reported. Pods stuck terminating on a \
+node that's gone can be removed with --force, which doesn't wait for the kubelet to confirm the \
+pod has stopped.
+
+Examples:
+  # delete the active deployment and its pods, returning once they're all gone
+  delete --cascade foreground --wait
+
+  # delete the selected pods right away
+  delete --now",
+        )
     },
     vec!["delete"],
     noop_complete!(),
@@ -309,10 +412,31 @@ command!(
             ..Default::default()
         };
 
-        env.apply_to_selection(
+        let mut deleted = vec![];
+        let res = env.apply_to_selection(
             writer,
             Some(&env.click_config.range_separator),
-            |obj, writer| confirm_delete(env, obj, delete_options, writer),
-        )
+            ErrorStrategy::CollectAll,
+            |obj, writer| {
+                if confirm_delete(env, obj, delete_options, writer)? {
+                    deleted.push(obj.clone());
+                }
+                Ok(())
+            },
+        );
+        // objects that were deleted are waited for even if others failed
+        let waited = if matches.is_present("wait") && !env.dry_run && !deleted.is_empty() {
+            let timeout = match matches.value_of("timeout") {
+                Some(timeout) => parse_duration(timeout).unwrap(), // safe, validated
+                None => DEFAULT_WAIT_TIMEOUT,
+            };
+            wait_for_deletion(env, &deleted, timeout, writer)
+        } else {
+            Ok(())
+        };
+        if res.is_ok() && !env.dry_run {
+            env.invalidate_selection();
+        }
+        res.and(waited)
     }
 );
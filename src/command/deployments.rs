@@ -12,21 +12,21 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use ansi_term::Colour::Yellow;
 use clap::{App, Arg};
 use k8s_openapi::api::apps::v1 as apps_api;
+use k8s_openapi::ListOptional;
 
 use crate::{
     command::command_def::{exec_match, show_arg, sort_arg, start_clap, Cmd},
-    command::{run_list_command, Extractor},
+    command::{keyval_string, kubectl_command, kubectl_mutate, run_list_command, Extractor},
     completer,
-    env::Env,
+    env::{Env, ObjectSelection},
+    error::ClickError,
     kobj::{KObj, ObjType},
     output::ClickWriter,
     table::CellSpec,
 };
 
-use std::array::IntoIter;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::io::Write;
@@ -40,6 +40,8 @@ lazy_static! {
         m.insert("Desired".to_owned(), deployment_desired);
         m.insert("Up To Date".to_owned(), deployment_uptodate);
         m.insert("Available".to_owned(), deployment_available);
+        m.insert("Image Tag".to_owned(), deployment_image_tags);
+        m.insert("Selector".to_owned(), deployment_selector);
         m
     };
 }
@@ -49,6 +51,7 @@ const COL_MAP: &[(&str, &str)] = &[
     ("desired", "Desired"),
     ("uptodate", "Up To Date"),
     ("available", "Available"),
+    ("imagetag", "Image Tag"),
     ("age", "Age"),
 ];
 
@@ -58,6 +61,7 @@ const EXTRA_COL_MAP: &[(&str, &str)] = &[
     ("containers", "Containers"),
     ("images", "Images"),
     ("namespace", "Namespace"),
+    ("selector", "Selector"),
 ];
 
 const EXTRA_COL_FLAGS: &[&str] = &{ extract_first!(EXTRA_COL_MAP) };
@@ -67,6 +71,8 @@ fn deployment_to_kobj(deployment: &apps_api::Deployment) -> KObj {
     KObj {
         name: meta.name.clone().unwrap_or_else(|| "<Unknown>".into()),
         namespace: meta.namespace.clone(),
+        uid: meta.uid.clone(),
+        context: None,
         typ: ObjType::Deployment,
     }
 }
@@ -97,6 +103,39 @@ fn deployment_images(deployment: &apps_api::Deployment) -> Option<CellSpec<'_>>
     })
 }
 
+/// The tag (or digest) part of an image reference. A port in the registry host isn't a tag, so only
+/// look after the last '/'. Untagged images get "latest", like the container runtime would pull.
+fn image_tag(image: &str) -> &str {
+    if let Some(idx) = image.find('@') {
+        return &image[idx + 1..];
+    }
+    let last_part = &image[image.rfind('/').map(|idx| idx + 1).unwrap_or(0)..];
+    match last_part.rfind(':') {
+        Some(idx) => &last_part[idx + 1..],
+        None => "latest",
+    }
+}
+
+fn deployment_image_tags(deployment: &apps_api::Deployment) -> Option<CellSpec<'_>> {
+    deployment.spec.as_ref().and_then(|spec| {
+        spec.template.spec.as_ref().map(|pod_spec| {
+            let tags: Vec<&str> = pod_spec
+                .containers
+                .iter()
+                .map(|cont| cont.image.as_deref().map(image_tag).unwrap_or("<unknown>"))
+                .collect();
+            tags.join(", ").into()
+        })
+    })
+}
+
+fn deployment_selector(deployment: &apps_api::Deployment) -> Option<CellSpec<'_>> {
+    deployment
+        .spec
+        .as_ref()
+        .map(|spec| keyval_string(&spec.selector.match_labels).into())
+}
+
 fn deployment_desired(deployment: &apps_api::Deployment) -> Option<CellSpec<'_>> {
     deployment
         .spec
@@ -134,6 +173,31 @@ fn deployment_uptodate(deployment: &apps_api::Deployment) -> Option<CellSpec<'_>
         })
 }
 
+/// Figure out which deployment to operate on, either the one specified, or the active one
+fn target_deployment(matches: &clap::ArgMatches, env: &Env) -> Result<KObj, ClickError> {
+    if let Some(name) = matches.value_of("name") {
+        match env.namespace.as_ref() {
+            Some(ns) => Ok(KObj {
+                name: name.to_string(),
+                namespace: Some(ns.clone()),
+                uid: None,
+                context: None,
+                typ: ObjType::Deployment,
+            }),
+            None => Err(ClickError::CommandError(
+                "Set a namespace, or select a deployment, to restart it".to_string(),
+            )),
+        }
+    } else {
+        match env.current_selection() {
+            ObjectSelection::Single(obj) if obj.is(ObjType::Deployment) => Ok(obj.clone()),
+            _ => Err(ClickError::CommandError(
+                "Specify a deployment name, or select a deployment, to restart it".to_string(),
+            )),
+        }
+    }
+}
+
 list_command!(
     Deployments,
     "deployments",
@@ -141,6 +205,24 @@ list_command!(
     super::COL_FLAGS,
     super::EXTRA_COL_FLAGS,
     |clap: App<'static, 'static>| clap
+        .arg(
+            Arg::with_name("name")
+                .help("Only get the deployment with this name")
+                .required(false)
+                .index(1)
+        )
+        .arg(
+            Arg::with_name("selector")
+                .long("selector")
+                .help("Show the pod selector of each deployment")
+                .takes_value(false)
+        )
+        .arg(
+            Arg::with_name("restart")
+                .long("restart")
+                .help("Restart the specified (or active) deployment, via kubectl rollout restart")
+                .takes_value(false)
+        )
         .arg(
             Arg::with_name("show_label")
                 .short("L")
@@ -165,14 +247,38 @@ list_command!(
                 .takes_value(false),
         ),
     vec!["deps", "deployments"],
-    noop_complete!(),
-    IntoIter::new([]),
+    vec![&completer::deployment_completer],
+    [],
     |matches, env, writer| {
+        if matches.is_present("restart") {
+            let deployment = target_deployment(&matches, env)?;
+            let mut command = kubectl_command(env, deployment.namespace.as_deref())?;
+            command
+                .arg("rollout")
+                .arg("restart")
+                .arg("deployment")
+                .arg(deployment.name());
+            if let Some(out) = kubectl_mutate(env, command, writer)? {
+                clickwrite!(writer, "{}", out);
+            }
+            return Ok(());
+        }
+
+        let field_sel = matches
+            .value_of("name")
+            .map(|name| format!("metadata.name={}", name));
+        let opts = ListOptional {
+            field_selector: field_sel.as_deref(),
+            ..Default::default()
+        };
         let (request, _response_body) = match &env.namespace {
-            Some(ns) => apps_api::Deployment::list_namespaced_deployment(ns, Default::default())?,
-            None => apps_api::Deployment::list_deployment_for_all_namespaces(Default::default())?,
+            Some(ns) => apps_api::Deployment::list_namespaced_deployment(ns, opts)?,
+            None => apps_api::Deployment::list_deployment_for_all_namespaces(opts)?,
         };
-        let cols: Vec<&str> = COL_MAP.iter().map(|(_, col)| *col).collect();
+        let mut cols: Vec<&str> = COL_MAP.iter().map(|(_, col)| *col).collect();
+        if matches.is_present("selector") {
+            cols.push("Selector");
+        }
 
         run_list_command(
             matches,
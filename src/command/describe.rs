@@ -12,14 +12,14 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use ansi_term::Colour::Yellow;
 use clap::{App, Arg};
 use rustyline::completion::Pair as RustlinePair;
 
 use crate::{
     command::command_def::{exec_match, start_clap, Cmd},
     completer,
-    env::Env,
+    env::{Env, ErrorStrategy},
+    jsonpath::JsonPath,
     output::ClickWriter,
 };
 
@@ -59,6 +59,32 @@ command!(
                 .takes_value(true)
                 .possible_values(&["true", "false"]),
         )
+        .arg(
+            Arg::with_name("field")
+                .short("f")
+                .long("field")
+                .help(
+                    "Print only the value at this path in the object, i.e. .status.podIP or \
+                     '{.spec.containers[*].image}'. Array elements can be selected with [N], or \
+                     all of them with [*].",
+                )
+                .takes_value(true)
+                .conflicts_with_all(&["json", "yaml"]),
+        )
+        .arg(
+            Arg::with_name("raw")
+                .long("raw")
+                .help("Print strings selected by --field without quotes, for use in scripts")
+                .takes_value(false)
+                .requires("field"),
+        )
+        .arg(
+            Arg::with_name("with_name")
+                .long("with-name")
+                .help("Prefix each value selected by --field with the name of its object")
+                .takes_value(false)
+                .requires("field"),
+        )
     },
     vec!["describe"],
     noop_complete!(),
@@ -68,18 +94,23 @@ command!(
         if let Some(b) = matches.value_of("include_events") {
             include_events = b.parse().unwrap(); // safe, validated to be true/false
         }
-        env.apply_to_selection(
-            writer,
-            Some(&env.click_config.range_separator),
-            |obj, writer| {
-                obj.describe(&matches, env, writer)?;
-                if include_events {
-                    clickwriteln!(writer, "Events:");
-                    print_events_for_obj(obj, env, writer)
-                } else {
-                    Ok(())
-                }
-            },
-        )
+        let sepfmt = if let Some(path) = matches.value_of("field") {
+            // check the path before fetching anything. a field is printed once per object with no
+            // separators or events, so it's easy to use in scripts
+            JsonPath::parse(path)?;
+            include_events = false;
+            None
+        } else {
+            Some(env.click_config.range_separator.as_str())
+        };
+        env.apply_to_selection(writer, sepfmt, ErrorStrategy::CollectAll, |obj, writer| {
+            obj.describe(&matches, env, writer)?;
+            if include_events {
+                clickwriteln!(writer, "Events:");
+                print_events_for_obj(obj, env, writer)
+            } else {
+                Ok(())
+            }
+        })
     }
 );
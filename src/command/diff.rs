@@ -0,0 +1,184 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use clap::{App, Arg};
+use rustyline::completion::Pair as RustlinePair;
+use serde_json::Value;
+
+use crate::{
+    command::apply::read_manifests,
+    command::command_def::{exec_match, start_clap, Cmd},
+    command::kustomize::dry_run_apply,
+    command::{kubectl_get_json, kubectl_obj_arg},
+    completer, diff,
+    env::Env,
+    error::ClickError,
+    kobj::KObj,
+    manifest,
+    output::ClickWriter,
+    theme::{self, Role},
+};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+
+/// What manifest would become if it were applied. This asks the server with a dry run, so
+/// defaulting and admission webhooks show up, falling back to the manifest as written if the
+/// server can't do that.
+fn applied_version(
+    env: &Env,
+    manifest: &Value,
+    name: &str,
+    writer: &mut ClickWriter,
+) -> Result<Value, ClickError> {
+    match dry_run_apply(env, &manifest.to_string()) {
+        Ok(mut objects) if objects.len() == 1 => Ok(objects.remove(0)),
+        Ok(_) => Err(ClickError::CommandError(format!(
+            "Server dry run of {} didn't return one object",
+            name
+        ))),
+        Err(e) => {
+            clickwriteln!(
+                writer,
+                "{}",
+                theme::style(Role::Warning).paint(format!(
+                    "Couldn't do a server side dry run of {}, comparing with its manifest as \
+                     written: {}",
+                    name, e
+                ))
+            );
+            Ok(manifest.clone())
+        }
+    }
+}
+
+/// The live version of obj, or None if it doesn't exist yet
+fn live_version(env: &Env, obj: &KObj) -> Result<Option<Value>, ClickError> {
+    match kubectl_get_json(env, obj) {
+        Ok(live) => Ok(Some(live)),
+        Err(ClickError::KubectlError { ref stderr, .. }) if stderr.contains("NotFound") => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Diff what's live for manifest against what applying it would leave. Returns if there were any
+/// differences.
+fn diff_manifest(
+    env: &Env,
+    manifest: &Value,
+    obj: &KObj,
+    writer: &mut ClickWriter,
+) -> Result<bool, ClickError> {
+    let name = kubectl_obj_arg(obj);
+    let live = match live_version(env, obj)? {
+        Some(mut live) => {
+            diff::strip_server_fields(&mut live);
+            serde_yaml::to_string(&live)?
+        }
+        None => String::new(), // so everything shows as added
+    };
+    let mut applied = applied_version(env, manifest, &name, writer)?;
+    diff::strip_server_fields(&mut applied);
+    let lines = diff::unified_diff(
+        &format!("{} (live)", name),
+        &format!("{} (applied)", name),
+        &live,
+        &serde_yaml::to_string(&applied)?,
+    );
+    diff::write_diff(writer, &lines);
+    Ok(!lines.is_empty())
+}
+
+command!(
+    Diff,
+    "diff",
+    "Show how applying manifest files would change the live objects, as a unified diff",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("filename")
+                .short("f")
+                .long("filename")
+                .help(
+                    "A yaml or json file of objects to diff, a directory of them, or - to read \
+                     from stdin. Can be given more than once.",
+                )
+                .required(true)
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("recursive")
+                .short("R")
+                .long("recursive")
+                .help("Also diff manifests in subdirectories of directories given with -f")
+                .takes_value(false),
+        )
+        .after_help(
+            "Each object is run through a server side dry run of applying it, so defaults and \
+changes made by admission webhooks are part of what's compared. If the server can't do the dry \
+run, the manifest is compared as written. Objects that don't exist yet show up as all added. \
+Fields the server manages, like resourceVersion, managedFields, and status, are left out.
+
+If any objects differ this fails, and with -c click exits with code 2 (rather than 1 for other \
+errors), so scripts can check for drift.
+
+Examples:
+  # see what applying a deployment would change
+  diff -f deployment.yaml
+
+  # check a directory of manifests for drift from a script
+  click -c 'diff -f manifests/ -R' || echo drifted",
+        )
+    },
+    vec!["diff"],
+    noop_complete!(),
+    no_named_complete!(),
+    |matches, env, writer| {
+        let mut manifests = vec![];
+        for filename in matches.values_of("filename").unwrap() {
+            // safe, required
+            manifests.extend(read_manifests(filename, matches.is_present("recursive"))?);
+        }
+        if manifests.is_empty() {
+            return Err(ClickError::CommandError(
+                "No objects found to diff".to_string(),
+            ));
+        }
+        let mut targets = vec![];
+        for manifest in manifests.iter() {
+            let namespace = manifest::manifest_namespace(manifest, env.namespace.as_deref());
+            targets.push(manifest::manifest_kobj(manifest, namespace)?);
+        }
+
+        let mut differ = 0;
+        let mut errors = vec![];
+        for (manifest, obj) in manifests.iter().zip(targets.iter()) {
+            match diff_manifest(env, manifest, obj, writer) {
+                Ok(true) => differ += 1,
+                Ok(false) => {}
+                Err(e) => errors.push((obj.name().to_string(), e)),
+            }
+        }
+        if !errors.is_empty() {
+            Err(ClickError::MultiError(errors))
+        } else if differ > 0 {
+            Err(ClickError::Drift(differ))
+        } else {
+            clickwriteln!(writer, "All {} objects match what's live", manifests.len());
+            Ok(())
+        }
+    }
+);
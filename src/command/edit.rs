@@ -0,0 +1,249 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::offset::Local;
+use clap::{App, Arg};
+use rustyline::completion::Pair as RustlinePair;
+use serde_json::Value;
+
+use crate::{
+    command::command_def::{exec_match, start_clap, Cmd},
+    command::{
+        confirm_action, editor_expression, get_editor, kubectl_get_json, kubectl_mutate,
+        kubectl_obj_arg, kubectl_obj_command, strip_managed_fields,
+    },
+    completer, diff,
+    env::{Env, ErrorStrategy},
+    error::ClickError,
+    kobj::KObj,
+    output::ClickWriter,
+};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// What kubectl says when a PUT or PATCH was based on an old resourceVersion
+const CONFLICT_MSG: &str = "the object has been modified";
+
+/// Fetch obj as json, without the managedFields noise
+fn fetch(env: &Env, obj: &KObj) -> Result<Value, ClickError> {
+    let mut value = kubectl_get_json(env, obj)?;
+    strip_managed_fields(&mut value);
+    Ok(value)
+}
+
+/// Send the edits back, either replacing the whole object, or patching just what changed. Both
+/// include the resourceVersion we fetched, so they fail if the object has changed since.
+fn submit(
+    env: &Env,
+    obj: &KObj,
+    original: &Value,
+    edited: &Value,
+    patch: bool,
+    tmpdir: &Path,
+    writer: &mut ClickWriter,
+) -> Result<Option<String>, ClickError> {
+    let mut command = kubectl_obj_command(env, obj)?;
+    if patch {
+        let mut patch = diff::merge_patch(original, edited);
+        if let Some(rv) = original.pointer("/metadata/resourceVersion") {
+            let patch_obj = patch.as_object_mut().unwrap(); // safe, both sides are objects
+            let meta = patch_obj
+                .entry("metadata")
+                .or_insert_with(|| Value::Object(Default::default()));
+            if let Some(meta) = meta.as_object_mut() {
+                meta.entry("resourceVersion").or_insert_with(|| rv.clone());
+            }
+        }
+        command
+            .arg("patch")
+            .arg(kubectl_obj_arg(obj))
+            .arg("--type")
+            .arg("strategic")
+            .arg("-p")
+            .arg(patch.to_string());
+    } else {
+        let path = tmpdir.join(format!(
+            "click-edit-{}.json",
+            Local::now().timestamp_nanos()
+        ));
+        fs::write(&path, edited.to_string())?;
+        command.arg("replace").arg("-f").arg(&path);
+    }
+    command.arg("-o").arg("name");
+    kubectl_mutate(env, command, writer)
+}
+
+/// Save text somewhere other than the file we're editing, and return where it went
+fn save_edits(tmpdir: &Path, obj: &KObj, text: &str) -> Result<String, ClickError> {
+    let path = tmpdir.join(format!(
+        "{}-{}-edits-{}.yaml",
+        obj.type_str().to_lowercase(),
+        obj.name(),
+        Local::now().format("%H%M%S")
+    ));
+    fs::write(&path, text)?;
+    Ok(path.display().to_string())
+}
+
+fn edit_obj(
+    env: &Env,
+    obj: &KObj,
+    editor: &str,
+    patch: bool,
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    let tmpdir = match env.tempdir {
+        Ok(ref td) => td.path(),
+        Err(ref e) => {
+            return Err(ClickError::CommandError(format!(
+                "Failed to create tempdir: {}",
+                e
+            )));
+        }
+    };
+    let path = tmpdir.join(format!(
+        "{}-{}.yaml",
+        obj.type_str().to_lowercase(),
+        obj.name()
+    ));
+    let mut original = fetch(env, obj)?;
+    let mut original_text = serde_yaml::to_string(&original)?;
+    let mut buffer = original_text.clone();
+    loop {
+        fs::write(&path, &buffer)?;
+        writer.finish_paging();
+        editor_expression(editor, &path)
+            .run()
+            .map_err(|e| ClickError::CommandError(format!("Could not run editor: {}", e)))?;
+        let edited_text = fs::read_to_string(&path)?;
+        if edited_text.trim().is_empty() {
+            clickwriteln!(writer, "Edit cancelled");
+            return Ok(());
+        }
+        let edited: Value = match serde_yaml::from_str(&edited_text) {
+            Ok(edited) => edited,
+            Err(e) => {
+                clickwriteln!(writer, "Edited object is not valid yaml: {}", e);
+                if confirm_action(writer, "Re-open the editor") {
+                    buffer = edited_text;
+                    continue;
+                }
+                clickwriteln!(
+                    writer,
+                    "Edit cancelled, your edits are saved in {}",
+                    save_edits(tmpdir, obj, &edited_text)?
+                );
+                return Ok(());
+            }
+        };
+        if edited == original {
+            clickwriteln!(writer, "Edit cancelled");
+            return Ok(());
+        }
+
+        let name = kubectl_obj_arg(obj);
+        diff::write_diff(
+            writer,
+            &diff::unified_diff(
+                &name,
+                &format!("{} (edited)", name),
+                &original_text,
+                &edited_text,
+            ),
+        );
+        match submit(env, obj, &original, &edited, patch, tmpdir, writer) {
+            Ok(Some(out)) => {
+                clickwriteln!(writer, "{} edited", out.trim());
+                return Ok(());
+            }
+            Ok(None) => return Ok(()),
+            Err(e) => {
+                let saved = save_edits(tmpdir, obj, &edited_text)?;
+                let conflict = matches!(
+                    &e,
+                    ClickError::KubectlError { stderr, .. } if stderr.contains(CONFLICT_MSG)
+                );
+                if !conflict {
+                    clickwriteln!(writer, "Your edits are saved in {}", saved);
+                    return Err(e);
+                }
+                clickwriteln!(
+                    writer,
+                    "{} was changed since it was fetched. Your edits are saved in {}",
+                    name,
+                    saved
+                );
+                if !confirm_action(writer, "Fetch the current version and re-open the editor") {
+                    return Err(e);
+                }
+                original = fetch(env, obj)?;
+                original_text = serde_yaml::to_string(&original)?;
+                buffer = original_text.clone();
+            }
+        }
+    }
+}
+
+command!(
+    Edit,
+    "edit",
+    "Edit the active kubernetes object(s) in your editor, and then update the object with your \
+     changes",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("patch")
+                .long("patch")
+                .help(
+                    "Send only what you changed, as a strategic merge patch, rather than \
+                     replacing the whole object. Note that lists with merge keys (like \
+                     containers) are merged, so removing an element from one won't work.",
+                )
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("editor")
+                .short("e")
+                .long("editor")
+                .help(
+                    "Use this editor rather than the one from 'set editor' or $EDITOR. Can \
+                     include arguments, i.e. 'emacs -nw'",
+                )
+                .takes_value(true),
+        )
+        .after_help(
+            "The object is opened as yaml. Save and exit to apply your changes, or leave the \
+             object unchanged (or delete everything) to cancel.
+
+If the object was changed by someone else while you were editing, your edits are saved to a file \
+(whose path is printed) and you can re-open the editor on the current version of the object.",
+        )
+    },
+    vec!["edit"],
+    noop_complete!(),
+    no_named_complete!(),
+    |matches, env, writer| {
+        let editor = get_editor(env, matches.value_of("editor"))?;
+        let patch = matches.is_present("patch");
+        env.apply_to_selection(
+            writer,
+            Some(&env.click_config.range_separator),
+            ErrorStrategy::StopOnFirst,
+            |obj, writer| edit_obj(env, obj, &editor, patch, writer),
+        )
+    }
+);
@@ -12,10 +12,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use ansi_term::Colour::Yellow;
-use clap::App;
-use k8s_openapi::ListOptional;
-use k8s_openapi::{api::core::v1 as api, http::Request, List};
+use clap::{App, Arg};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::WatchEvent;
+use k8s_openapi::{api::core::v1 as api, http::Request, List, ListOptional, WatchOptional};
 use prettytable::{Cell, Row, Table};
 use rustyline::completion::Pair as RustlinePair;
 
@@ -28,116 +27,309 @@ use crate::{
     error::ClickError,
     kobj::KObj,
     output::ClickWriter,
+    table::print_filled_table,
+    theme::{self, Role},
 };
 
+use chrono::{DateTime, Utc};
 use std::cell::RefCell;
 use std::cmp;
 use std::collections::HashMap;
-use std::io::Write;
+use std::io::{BufRead, BufReader, Write};
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+/// How many events to ask for at once. Without --all only the first page is shown (the help for
+/// --all says how big that is).
+const PAGE_SIZE: i64 = 500;
+
+/// The events to fetch: those in a namespace (or all namespaces), optionally restricted by a field
+/// selector
+struct EventQuery {
+    namespace: Option<String>,
+    field_selector: Option<String>,
+}
+
+impl EventQuery {
+    /// Events in namespace, or in all namespaces if it's None
+    fn for_namespace(namespace: Option<&str>) -> EventQuery {
+        EventQuery {
+            namespace: namespace.map(|ns| ns.to_string()),
+            field_selector: namespace.map(|ns| format!("involvedObject.namespace={}", ns)),
+        }
+    }
+
+    /// Events about obj
+    fn for_obj(obj: &KObj) -> EventQuery {
+        let field_selector = match obj.namespace.as_ref() {
+            Some(ns) => format!(
+                "involvedObject.name={},involvedObject.namespace={}",
+                obj.name(),
+                ns
+            ),
+            None => format!("involvedObject.name={}", obj.name()),
+        };
+        EventQuery {
+            namespace: obj.namespace.clone(),
+            field_selector: Some(field_selector),
+        }
+    }
+
+    fn list_request(&self, continue_: Option<&str>) -> Result<Request<Vec<u8>>, ClickError> {
+        let opts = ListOptional {
+            continue_,
+            field_selector: self.field_selector.as_deref(),
+            limit: Some(PAGE_SIZE),
+            ..Default::default()
+        };
+        let (request, _body) = match self.namespace.as_ref() {
+            Some(ns) => api::Event::list_namespaced_event(ns, opts)?,
+            None => api::Event::list_event_for_all_namespaces(opts)?,
+        };
+        Ok(request)
+    }
+
+    fn watch_request(&self, resource_version: &str) -> Result<Request<Vec<u8>>, ClickError> {
+        let opts = WatchOptional {
+            field_selector: self.field_selector.as_deref(),
+            resource_version: Some(resource_version),
+            ..Default::default()
+        };
+        let (request, _body) = match self.namespace.as_ref() {
+            Some(ns) => api::Event::watch_namespaced_event(ns, opts)?,
+            None => api::Event::watch_event_for_all_namespaces(opts)?,
+        };
+        Ok(request)
+    }
+}
+
+/// The result of running an EventQuery
+struct EventPage {
+    events: Vec<api::Event>,
+    /// where to start watching from
+    resource_version: Option<String>,
+    /// are there more events than were fetched
+    more: bool,
+}
+
+/// Fetch the events for query. The server returns events a page at a time, if all is set every
+/// page is fetched, otherwise just the first.
+fn fetch_events(env: &Env, query: &EventQuery, all: bool) -> Result<EventPage, ClickError> {
+    let mut events = vec![];
+    let mut continue_: Option<String> = None;
+    loop {
+        let request = query.list_request(continue_.as_deref())?;
+        let list: List<api::Event> = env.run_on_context(|c| c.execute_list(request))?;
+        events.extend(list.items);
+        continue_ = list.metadata.continue_.filter(|c| !c.is_empty());
+        if continue_.is_none() || !all {
+            return Ok(EventPage {
+                events,
+                resource_version: list.metadata.resource_version,
+                more: continue_.is_some(),
+            });
+        }
+    }
+}
+
+/// When the event last happened. Newer events only have event_time.
+fn event_time(event: &api::Event) -> Option<DateTime<Utc>> {
+    event
+        .last_timestamp
+        .as_ref()
+        .map(|ts| ts.0)
+        .or_else(|| event.event_time.as_ref().map(|t| t.0))
+}
 
 fn event_cmp(e1: &api::Event, e2: &api::Event) -> cmp::Ordering {
-    match (e1.last_timestamp.as_ref(), e2.last_timestamp.as_ref()) {
+    match (event_time(e1), event_time(e2)) {
         (None, None) => cmp::Ordering::Equal,
         (None, Some(_)) => cmp::Ordering::Less,
         (Some(_), None) => cmp::Ordering::Greater,
-        (Some(e1ts), Some(e2ts)) => e1ts.partial_cmp(e2ts).unwrap(),
+        (Some(e1ts), Some(e2ts)) => e1ts.cmp(&e2ts),
+    }
+}
+
+fn event_count(event: &api::Event) -> i32 {
+    event
+        .count
+        .or_else(|| event.series.as_ref().and_then(|s| s.count))
+        .unwrap_or(1)
+}
+
+/// Is event one of types. No types means every event matches.
+fn type_matches(event: &api::Event, types: &[String]) -> bool {
+    types.is_empty()
+        || event
+            .type_
+            .as_deref()
+            .is_some_and(|typ| types.iter().any(|t| t.eq_ignore_ascii_case(typ)))
+}
+
+/// Which columns to show: the namespace is only needed when showing events from all namespaces,
+/// and the object isn't needed when showing the events of a single object.
+struct EventColumns {
+    namespace: bool,
+    object: bool,
+}
+
+impl EventColumns {
+    fn titles(&self) -> Row {
+        let mut titles = vec![];
+        if self.namespace {
+            titles.push(cell!("Namespace"));
+        }
+        titles.push(cell!("Type"));
+        titles.push(cell!("Reason"));
+        if self.object {
+            titles.push(cell!("Object"));
+        }
+        titles.push(cell!("Count"));
+        titles.push(cell!("Age"));
+        titles.push(cell!("Message"));
+        Row::new(titles)
+    }
+
+    /// A table row for event. Warnings are shown in the warning style.
+    fn row(&self, event: &api::Event) -> Row {
+        let mut row: Vec<Cell> = Vec::new();
+        if self.namespace {
+            row.push(Cell::new(
+                event.metadata.namespace.as_deref().unwrap_or("unknown"),
+            ));
+        }
+        row.push(Cell::new(event.type_.as_deref().unwrap_or("unknown")));
+        row.push(Cell::new(event.reason.as_deref().unwrap_or("unknown")));
+        if self.object {
+            let involved = &event.involved_object;
+            row.push(Cell::new(&format!(
+                "{}/{}",
+                involved.kind.as_deref().unwrap_or("unknown").to_lowercase(),
+                involved.name.as_deref().unwrap_or("unknown")
+            )));
+        }
+        let mut count = Cell::new(&event_count(event).to_string());
+        count.align(prettytable::format::Alignment::RIGHT);
+        row.push(count);
+        let age = event_time(event)
+            .map(|t| format_duration(time_since(t)))
+            .unwrap_or_else(|| "unknown".to_string());
+        row.push(Cell::new(&age));
+        row.push(Cell::new(event.message.as_deref().unwrap_or("<none>")));
+        if event.type_.as_deref() == Some("Warning") {
+            let attrs = theme::cell_attrs(Role::Warning);
+            row = row
+                .into_iter()
+                .map(|mut cell| {
+                    for attr in attrs.iter() {
+                        cell.style(*attr);
+                    }
+                    cell
+                })
+                .collect();
+        }
+        Row::new(row)
     }
 }
 
+fn print_event_table(events: &[api::Event], columns: &EventColumns, writer: &mut ClickWriter) {
+    if events.is_empty() {
+        clickwriteln!(writer, "No events");
+        return;
+    }
+    let mut table = Table::new();
+    table.set_titles(columns.titles());
+    for event in events.iter() {
+        table.add_row(columns.row(event));
+    }
+    print_filled_table(&mut table, writer);
+}
+
 pub fn print_events_for_obj(
     obj: &KObj,
     env: &Env,
     writer: &mut ClickWriter,
 ) -> Result<(), ClickError> {
-    let mut opts: ListOptional = Default::default();
-    let mut include_namespace = false;
-    let (request, _body) = if let Some(ns) = obj.namespace.as_ref() {
-        let fs = format!(
-            "involvedObject.name={},involvedObject.namespace={}",
-            obj.name(),
-            ns
-        );
-        opts.field_selector = Some(&fs);
-        api::Event::list_namespaced_event(ns, opts)?
-    } else {
-        include_namespace = true;
-        let fs = format!("involvedObject.name={}", obj.name(),);
-        opts.field_selector = Some(&fs);
-        api::Event::list_event_for_all_namespaces(opts)?
+    let page = fetch_events(env, &EventQuery::for_obj(obj), true)?;
+    let mut events = page.events;
+    events.sort_by(event_cmp);
+    let columns = EventColumns {
+        namespace: obj.namespace.is_none(),
+        object: false,
     };
-    print_events(request, env, writer, include_namespace, false)
+    print_event_table(&events, &columns, writer);
+    Ok(())
 }
 
-fn print_events_no_obj(env: &Env, writer: &mut ClickWriter) -> Result<(), ClickError> {
-    let mut opts: ListOptional = Default::default();
-    let mut include_namespace = false;
-    let (request, _body) = if let Some(ns) = env.namespace.as_ref() {
-        let fs = format!("involvedObject.namespace={}", ns);
-        opts.field_selector = Some(&fs);
-        api::Event::list_namespaced_event(ns, opts)?
-    } else {
-        include_namespace = true;
-        api::Event::list_event_for_all_namespaces(opts)?
-    };
-    print_events(request, env, writer, include_namespace, true)
+/// Stream events for query from resource_version on, sending them to sender until the watch ends
+/// or the receiving side goes away
+fn start_watch(
+    env: &Env,
+    query: &EventQuery,
+    resource_version: &str,
+    sender: Sender<Result<WatchEvent<api::Event>, ClickError>>,
+) -> Result<(), ClickError> {
+    let request = query.watch_request(resource_version)?;
+    let reader = env.run_on_context(|c| c.execute_reader(request, None))?;
+    thread::spawn(move || {
+        let reader = BufReader::new(reader);
+        for line in reader.lines() {
+            let event = match line {
+                Ok(line) if line.trim().is_empty() => continue,
+                Ok(line) => serde_json::from_str(&line).map_err(ClickError::from),
+                Err(e) => Err(ClickError::from(e)),
+            };
+            let failed = event.is_err();
+            if sender.send(event).is_err() || failed {
+                break;
+            }
+        }
+    });
+    Ok(())
 }
 
-fn print_events(
-    request: Request<Vec<u8>>,
+/// Print events as they happen, until ctrl-c is pressed or the server ends the watch
+fn watch_events(
     env: &Env,
+    queries: &[(EventQuery, String)],
+    columns: &EventColumns,
+    types: &[String],
     writer: &mut ClickWriter,
-    include_namespace: bool,
-    include_object: bool,
 ) -> Result<(), ClickError> {
-    let mut event_list: List<api::Event> = env.run_on_context(|c| c.execute_list(request))?;
-    if !event_list.items.is_empty() {
-        event_list.items.sort_by(event_cmp);
-        let mut table = Table::new();
-        let mut titles = if include_namespace {
-            vec![
-                cell!("Namespace"),
-                cell!("Last Seen"),
-                cell!("Type"),
-                cell!("Reason"),
-            ]
-        } else {
-            vec![cell!("Last Seen"), cell!("Type"), cell!("Reason")]
-        };
-        if include_object {
-            titles.push(cell!("Object"));
-        }
-        titles.push(cell!("Message"));
-        table.set_titles(Row::new(titles));
-        for event in event_list.items.iter() {
-            let mut row: Vec<Cell> = Vec::new();
-            if include_namespace {
-                row.push(Cell::new(
-                    event.metadata.namespace.as_deref().unwrap_or("unknown"),
-                ));
+    let (sender, receiver) = channel();
+    env.ctrlcbool.store(false, Ordering::SeqCst);
+    for (query, resource_version) in queries.iter() {
+        start_watch(env, query, resource_version, sender.clone())?;
+    }
+    // only the watches hold senders now, so the channel disconnects when they've all ended
+    drop(sender);
+    while !env.ctrlcbool.load(Ordering::SeqCst) {
+        match receiver.recv_timeout(Duration::from_secs(1)) {
+            Ok(Ok(WatchEvent::Added(event))) | Ok(Ok(WatchEvent::Modified(event))) => {
+                if type_matches(&event, types) {
+                    let mut table = Table::new();
+                    table.add_row(columns.row(&event));
+                    print_filled_table(&mut table, writer);
+                }
             }
-            let timestr = match event.last_timestamp.as_ref() {
-                Some(ts) => format_duration(time_since(ts.0)),
-                None => event
-                    .event_time
-                    .as_ref()
-                    .map(|t| format_duration(time_since(t.0)))
-                    .unwrap_or_else(|| "unknown".to_string()),
-            };
-            row.push(Cell::new(&timestr));
-            row.push(Cell::new(event.type_.as_deref().unwrap_or("unknown")));
-            row.push(Cell::new(event.reason.as_deref().unwrap_or("unknown")));
-            if include_object {
-                row.push(Cell::new(
-                    event.involved_object.name.as_deref().unwrap_or("unknown"),
-                ));
+            Ok(Ok(WatchEvent::Deleted(_))) | Ok(Ok(WatchEvent::ErrorOther(_))) => {}
+            Ok(Ok(WatchEvent::ErrorStatus(status))) => {
+                return Err(ClickError::CommandError(format!(
+                    "Watch failed: {}",
+                    status
+                        .message
+                        .unwrap_or_else(|| "unknown error".to_string())
+                )));
+            }
+            Ok(Err(e)) => return Err(e),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                clickwriteln!(writer, "Server ended the watch");
+                break;
             }
-            row.push(Cell::new(event.message.as_deref().unwrap_or("<none>")));
-            table.add_row(Row::new(row));
         }
-        table.set_format(*crate::table::TBLFMT);
-        table.print(writer).unwrap_or(0);
-    } else {
-        clickwriteln!(writer, "No events");
     }
     Ok(())
 }
@@ -145,20 +337,107 @@ fn print_events(
 command!(
     Events,
     "events",
-    "Get events for the active pod",
-    crate::command::command_def::identity,
+    "Get events in the current namespace, or for the selected object(s) with --for",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("for")
+                .long("for")
+                .help("Only show events for the selected object(s)")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("types")
+                .long("types")
+                .help("Only show events of these types (Normal or Warning), comma separated")
+                .takes_value(true)
+                .use_delimiter(true)
+                .multiple(true),
+        )
+        .arg(
+            Arg::with_name("watch")
+                .short("w")
+                .long("watch")
+                .help(
+                    "After listing events, keep printing new events as they happen (stop with ^C)",
+                )
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("all")
+                .short("a")
+                .long("all")
+                .help(
+                    "Fetch every event. Otherwise only the first 500 are fetched (per object with \
+                     --for)",
+                )
+                .takes_value(false),
+        )
+        .after_help(
+            "Events are sorted by when they last happened, most recent last. Warnings are \
+highlighted.
+
+Examples:
+  # show only warnings in the current namespace
+  events --types Warning
+
+  # watch everything that happens to the selected pod
+  events --for -w",
+        )
+    },
     vec!["events"],
     noop_complete!(),
     no_named_complete!(),
-    |_matches, env, writer| {
-        if let ObjectSelection::None = env.current_selection() {
-            print_events_no_obj(env, writer)
+    |matches, env, writer| {
+        let types: Vec<String> = matches
+            .values_of("types")
+            .map(|types| types.map(|t| t.to_string()).collect())
+            .unwrap_or_default();
+        let queries = if matches.is_present("for") {
+            match env.current_selection() {
+                ObjectSelection::Single(obj) => vec![EventQuery::for_obj(obj)],
+                ObjectSelection::Range(range) => range.iter().map(EventQuery::for_obj).collect(),
+                ObjectSelection::None => {
+                    return Err(ClickError::CommandError(
+                        "--for needs a selected object".to_string(),
+                    ));
+                }
+            }
         } else {
-            env.apply_to_selection(
+            vec![EventQuery::for_namespace(env.namespace.as_deref())]
+        };
+        let columns = EventColumns {
+            namespace: queries.iter().any(|q| q.namespace.is_none()),
+            object: true,
+        };
+
+        let watch = matches.is_present("watch");
+        if watch {
+            // new events are printed as they arrive, so nothing can be held back for the pager
+            writer.finish_paging();
+        }
+        let mut events = vec![];
+        let mut more = false;
+        let mut watches = vec![];
+        for query in queries.into_iter() {
+            let page = fetch_events(env, &query, matches.is_present("all"))?;
+            events.extend(page.events);
+            more |= page.more;
+            watches.push((query, page.resource_version.unwrap_or_default()));
+        }
+        events.retain(|event| type_matches(event, &types));
+        events.sort_by(event_cmp);
+        print_event_table(&events, &columns, writer);
+        if more {
+            clickwriteln!(
                 writer,
-                Some(&env.click_config.range_separator),
-                |obj, writer| print_events_for_obj(obj, env, writer),
-            )
+                "{}",
+                theme::style(Role::Warning)
+                    .paint("There are more events on the server, use --all to fetch them all")
+            );
+        }
+        if watch {
+            watch_events(env, &watches, &columns, &types, writer)?;
         }
+        Ok(())
     }
 );
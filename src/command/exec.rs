@@ -12,23 +12,22 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use ansi_term::Colour::Yellow;
 use clap::{App, Arg};
 use rustyline::completion::Pair as RustlinePair;
 
 use crate::{
     command::command_def::{exec_match, start_clap, Cmd},
+    command::{kubectl_exit_error, kubectl_spawn_error},
     completer,
-    env::Env,
+    env::{Env, ErrorStrategy, ObjectSelection},
     error::ClickError,
     kobj::KObj,
     output::ClickWriter,
 };
 
-use std::array::IntoIter;
 use std::cell::RefCell;
 use std::collections::HashMap;
-use std::io::{self, Write};
+use std::io::Write;
 use std::process::Command;
 
 /// a clap validator for boolean
@@ -68,6 +67,9 @@ fn do_exec(
             it_arg,
             pod.name(),
         ];
+        if let Some(kubeconfig) = env.command_kubeconfig.as_ref().and_then(|k| k.to_str()) {
+            kubectl_args.splice(1..1, ["--kubeconfig", kubeconfig]);
+        }
         targs.append(&mut kubectl_args);
         if let Some(cont) = cont_opt {
             targs.push("-c");
@@ -80,6 +82,9 @@ fn do_exec(
         Ok(())
     } else {
         let mut command = Command::new("kubectl");
+        if let Some(kubeconfig) = env.command_kubeconfig.as_ref() {
+            command.arg("--kubeconfig").arg(kubeconfig);
+        }
         command
             .arg("--namespace")
             .arg(ns)
@@ -93,25 +98,14 @@ fn do_exec(
         } else {
             command.arg("--").args(cmd)
         };
-        match command.status() {
-            Ok(s) => {
-                if s.success() {
-                    Ok(())
-                } else {
-                    Err(ClickError::CommandError(
-                        "kubectl exited abnormally".to_string(),
-                    ))
-                }
-            }
-            Err(e) => {
-                if let io::ErrorKind::NotFound = e.kind() {
-                    Err(ClickError::CommandError(
-                        "Could not find kubectl binary. Is it in your PATH?".to_string(),
-                    ))
-                } else {
-                    Err(ClickError::Io(e))
-                }
-            }
+        // stderr is left attached to the terminal so interactive sessions work, which means
+        // there's nothing captured to report, but the exit code is the command's exit code
+        writer.finish_paging();
+        let status = command.status().map_err(kubectl_spawn_error)?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(kubectl_exit_error(status, &[]))
         }
     }
 }
@@ -168,7 +162,7 @@ command!(
         ),
     vec!["exec"],
     noop_complete!(),
-    IntoIter::new([(
+    IntoIterator::into_iter([(
         "container".to_string(),
         completer::container_completer as fn(&str, &Env) -> Vec<RustlinePair>
     )])
@@ -202,9 +196,14 @@ command!(
                 (false, true) => "-i",
                 (false, false) => "",
             };
+            // ranges are checked one object at a time below
+            if !matches!(env.current_selection(), ObjectSelection::Range(_)) {
+                env.current_pod()?;
+            }
             env.apply_to_selection(
                 writer,
                 Some(&env.click_config.range_separator),
+                ErrorStrategy::StopOnFirst,
                 |obj, writer| {
                     if obj.is_pod() {
                         do_exec(
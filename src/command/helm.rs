@@ -0,0 +1,280 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use clap::{App, Arg};
+use prettytable::Row;
+use rustyline::completion::Pair as RustlinePair;
+use serde_json::Value;
+
+use crate::{
+    command::command_def::{exec_match, start_clap, Cmd},
+    command::{tool_exit_error, tool_spawn_error},
+    completer,
+    env::Env,
+    error::ClickError,
+    output::ClickWriter,
+    table::CellSpec,
+    theme::Role,
+};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::Command;
+
+const HELM_URL: &str = "https://helm.sh/docs/intro/install/";
+
+/// A helm command against the active context, and namespace if there is one, as whoever we're
+/// impersonating
+fn helm_command(env: &Env) -> Result<Command, ClickError> {
+    let context = env.context.as_ref().ok_or_else(|| {
+        ClickError::CommandError("Need an active context in order to run helm".to_string())
+    })?;
+    let mut command = Command::new("helm");
+    command.arg("--kube-context").arg(&context.name);
+    if let Some(ns) = env.namespace.as_deref() {
+        command.arg("--namespace").arg(ns);
+    }
+    if let Some(imp) = env.impersonating() {
+        command.arg("--kube-as-user").arg(&imp.user);
+        for group in imp.groups.iter() {
+            command.arg("--kube-as-group").arg(group);
+        }
+    }
+    Ok(command)
+}
+
+/// Run helm, returning what it printed
+fn helm_output(mut command: Command) -> Result<String, ClickError> {
+    let output = command
+        .output()
+        .map_err(|e| tool_spawn_error("helm", HELM_URL, e))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(tool_exit_error("helm", output.status, &output.stderr))
+    }
+}
+
+/// Parse the times helm prints, which are RFC 3339 in history, but look like
+/// "2021-06-01 12:00:00.123456 +0000 UTC" in list
+fn parse_helm_time(time: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(time)
+        .or_else(|_| {
+            let without_zone_name = time.splitn(4, ' ').take(3).collect::<Vec<&str>>().join(" ");
+            DateTime::parse_from_str(&without_zone_name, "%Y-%m-%d %H:%M:%S%.f %z")
+        })
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+fn status_cell(status: &str) -> CellSpec<'_> {
+    match status {
+        "deployed" | "superseded" => CellSpec::with_role(status.into(), Role::GoodStatus),
+        "failed" => CellSpec::with_role(status.into(), Role::BadStatus),
+        _ => status.into(),
+    }
+}
+
+fn str_cell<'a>(value: &'a Value, key: &str) -> CellSpec<'a> {
+    value.get(key).and_then(|v| v.as_str()).unwrap_or("").into()
+}
+
+/// Print helm's json for a list of releases or revisions, as is if the output format is json or
+/// yaml, otherwise as a table with cols, each made from a field of the json
+fn print_helm_table(
+    json: &str,
+    titles: Row,
+    cells: fn(&Value) -> Vec<CellSpec<'_>>,
+    writer: &mut ClickWriter,
+) -> Result<usize, ClickError> {
+    // helm prints nothing at all when there are no releases in some versions
+    let value: Value = if json.trim().is_empty() {
+        Value::Array(vec![])
+    } else {
+        serde_json::from_str(json)?
+    };
+    let items = value.as_array().map(|items| items.len()).unwrap_or(0);
+    if !writer.print_raw(&value)? {
+        let rows = value
+            .as_array()
+            .map(|items| items.iter().map(cells).collect())
+            .unwrap_or_default();
+        crate::table::print_table(titles, rows, writer);
+    }
+    Ok(items)
+}
+
+fn release_cells(release: &Value) -> Vec<CellSpec<'_>> {
+    let updated = release
+        .get("updated")
+        .and_then(|u| u.as_str())
+        .and_then(parse_helm_time);
+    vec![
+        str_cell(release, "name"),
+        str_cell(release, "namespace"),
+        release
+            .get("revision")
+            .and_then(|r| r.as_str())
+            .unwrap_or("")
+            .into(),
+        str_cell(release, "chart"),
+        str_cell(release, "app_version"),
+        status_cell(release.get("status").and_then(|s| s.as_str()).unwrap_or("")),
+        updated.into(),
+    ]
+}
+
+fn revision_cells(revision: &Value) -> Vec<CellSpec<'_>> {
+    let updated = revision
+        .get("updated")
+        .and_then(|u| u.as_str())
+        .and_then(parse_helm_time);
+    vec![
+        revision.get("revision").and_then(|r| r.as_i64()).into(),
+        updated.into(),
+        status_cell(
+            revision
+                .get("status")
+                .and_then(|s| s.as_str())
+                .unwrap_or(""),
+        ),
+        str_cell(revision, "chart"),
+        str_cell(revision, "app_version"),
+        str_cell(revision, "description"),
+    ]
+}
+
+command!(
+    Helm,
+    "helm",
+    "Look at the Helm releases in the current namespace (or all namespaces if none is set), using \
+     the helm binary",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("action")
+                .help(
+                    "list: list the releases\n\
+                     status: show the status of a release, and its notes\n\
+                     values: show a release's values, including the chart's defaults\n\
+                     history: list the revisions of a release",
+                )
+                .possible_values(&["list", "status", "values", "history"])
+                .default_value("list")
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("release")
+                .help("The release to look at, for everything but list")
+                .required_ifs(&[
+                    ("action", "status"),
+                    ("action", "values"),
+                    ("action", "history"),
+                ])
+                .index(2),
+        )
+        .arg(
+            Arg::with_name("user_supplied")
+                .long("user-supplied")
+                .short("u")
+                .help("With values, show only the values given at install or upgrade time"),
+        )
+        .after_help(
+            "helm needs to be installed. Releases are looked for in the active \
+context and namespace, like with kubectl. With -o json or yaml (or 'set output_format'), list and \
+history print what helm returns rather than a table.
+
+Examples:
+  # list releases
+  helm
+
+  # see what the ingress release was installed with
+  helm values ingress-nginx --user-supplied",
+        )
+    },
+    vec!["helm"],
+    vec![&completer::helmaction_values_completer],
+    no_named_complete!(),
+    |matches, env, writer| {
+        let action = matches.value_of("action").unwrap(); // safe, has a default
+        let mut command = helm_command(env)?;
+        match action {
+            "list" => {
+                command.arg("list").arg("--output").arg("json");
+                if env.namespace.is_none() {
+                    command.arg("--all-namespaces");
+                }
+                let titles = row![
+                    "Name",
+                    "Namespace",
+                    "Revision",
+                    "Chart",
+                    "App Version",
+                    "Status",
+                    "Updated"
+                ];
+                if print_helm_table(&helm_output(command)?, titles, release_cells, writer)? == 0 {
+                    clickwriteln!(writer, "No releases found");
+                }
+            }
+            "history" => {
+                let release = matches.value_of("release").unwrap(); // safe, required for history
+                command
+                    .arg("history")
+                    .arg(release)
+                    .arg("--output")
+                    .arg("json");
+                let titles = row![
+                    "Revision",
+                    "Updated",
+                    "Status",
+                    "Chart",
+                    "App Version",
+                    "Description"
+                ];
+                print_helm_table(&helm_output(command)?, titles, revision_cells, writer)?;
+            }
+            "values" => {
+                let release = matches.value_of("release").unwrap(); // safe, required for values
+                command
+                    .arg("get")
+                    .arg("values")
+                    .arg(release)
+                    .arg("--output")
+                    .arg("json");
+                if !matches.is_present("user_supplied") {
+                    command.arg("--all");
+                }
+                let values: Value = serde_json::from_str(&helm_output(command)?)?;
+                if !writer.print_raw(&values)? {
+                    writer.print_yaml(&values)?;
+                }
+            }
+            "status" => {
+                let release = matches.value_of("release").unwrap(); // safe, required for status
+                command.arg("status").arg(release);
+                if writer.output_format().is_raw() {
+                    command.arg("--output").arg("json");
+                    let status: Value = serde_json::from_str(&helm_output(command)?)?;
+                    writer.print_raw(&status)?;
+                } else {
+                    clickwrite!(writer, "{}", helm_output(command)?);
+                }
+            }
+            _ => unreachable!(), // clap validates
+        }
+        Ok(())
+    }
+);
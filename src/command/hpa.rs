@@ -0,0 +1,399 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use clap::{App, Arg, ArgMatches};
+use k8s_openapi::api::autoscaling::v2beta2 as autoscaling_api;
+use prettytable::Table;
+
+use crate::{
+    command::command_def::{exec_match, show_arg, sort_arg, start_clap, Cmd},
+    command::{run_list_command, Extractor},
+    completer,
+    env::Env,
+    error::ClickError,
+    kobj::{KObj, ObjType},
+    output::ClickWriter,
+    table::CellSpec,
+};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+type Hpa = autoscaling_api::HorizontalPodAutoscaler;
+
+lazy_static! {
+    static ref HPA_EXTRACTORS: HashMap<String, Extractor<Hpa>> = {
+        let mut m: HashMap<String, Extractor<Hpa>> = HashMap::new();
+        m.insert("Reference".to_owned(), hpa_reference);
+        m.insert("Current".to_owned(), hpa_current);
+        m.insert("Desired".to_owned(), hpa_desired);
+        m.insert("Min".to_owned(), hpa_min);
+        m.insert("Max".to_owned(), hpa_max);
+        m.insert("Metrics".to_owned(), hpa_metrics);
+        m
+    };
+}
+
+const COL_MAP: &[(&str, &str)] = &[
+    ("name", "Name"),
+    ("reference", "Reference"),
+    ("current", "Current"),
+    ("desired", "Desired"),
+    ("min", "Min"),
+    ("max", "Max"),
+    ("metrics", "Metrics"),
+    ("age", "Age"),
+];
+
+const COL_FLAGS: &[&str] = &{ extract_first!(COL_MAP) };
+
+const EXTRA_COL_MAP: &[(&str, &str)] = &[("namespace", "Namespace"), ("labels", "Labels")];
+
+const EXTRA_COL_FLAGS: &[&str] = &{ extract_first!(EXTRA_COL_MAP) };
+
+/// How often to refresh with --watch
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+fn hpa_to_kobj(hpa: &Hpa) -> KObj {
+    let meta = &hpa.metadata;
+    KObj {
+        name: meta.name.clone().unwrap_or_else(|| "<Unknown>".into()),
+        namespace: meta.namespace.clone(),
+        uid: meta.uid.clone(),
+        context: None,
+        typ: ObjType::HorizontalPodAutoscaler,
+    }
+}
+
+fn hpa_reference(hpa: &Hpa) -> Option<CellSpec<'_>> {
+    hpa.spec.as_ref().map(|spec| {
+        let target = &spec.scale_target_ref;
+        format!("{}/{}", target.kind, target.name).into()
+    })
+}
+
+fn hpa_current(hpa: &Hpa) -> Option<CellSpec<'_>> {
+    hpa.status.as_ref().map(|stat| stat.current_replicas.into())
+}
+
+fn hpa_desired(hpa: &Hpa) -> Option<CellSpec<'_>> {
+    hpa.status.as_ref().map(|stat| stat.desired_replicas.into())
+}
+
+fn hpa_min(hpa: &Hpa) -> Option<CellSpec<'_>> {
+    hpa.spec
+        .as_ref()
+        .map(|spec| spec.min_replicas.unwrap_or(1).into())
+}
+
+fn hpa_max(hpa: &Hpa) -> Option<CellSpec<'_>> {
+    hpa.spec.as_ref().map(|spec| spec.max_replicas.into())
+}
+
+fn hpa_metrics(hpa: &Hpa) -> Option<CellSpec<'_>> {
+    hpa.spec.as_ref().map(|spec| {
+        let metrics: Vec<String> = spec
+            .metrics
+            .iter()
+            .map(|metric| {
+                format!(
+                    "{}: {}/{}",
+                    metric_name(metric),
+                    current_value(hpa, metric),
+                    format_target(metric_target(metric))
+                )
+            })
+            .collect();
+        metrics.join("\n").into()
+    })
+}
+
+// metric helpers
+
+fn metric_name(metric: &autoscaling_api::MetricSpec) -> String {
+    if let Some(res) = metric.resource.as_ref() {
+        res.name.clone()
+    } else if let Some(pods) = metric.pods.as_ref() {
+        pods.metric.name.clone()
+    } else if let Some(obj) = metric.object.as_ref() {
+        format!(
+            "{} on {}/{}",
+            obj.metric.name, obj.described_object.kind, obj.described_object.name
+        )
+    } else if let Some(ext) = metric.external.as_ref() {
+        ext.metric.name.clone()
+    } else {
+        metric.type_.clone()
+    }
+}
+
+fn metric_target(metric: &autoscaling_api::MetricSpec) -> Option<&autoscaling_api::MetricTarget> {
+    metric
+        .resource
+        .as_ref()
+        .map(|r| &r.target)
+        .or_else(|| metric.pods.as_ref().map(|p| &p.target))
+        .or_else(|| metric.object.as_ref().map(|o| &o.target))
+        .or_else(|| metric.external.as_ref().map(|e| &e.target))
+}
+
+fn format_target(target: Option<&autoscaling_api::MetricTarget>) -> String {
+    match target {
+        Some(target) => {
+            if let Some(util) = target.average_utilization {
+                format!("{}%", util)
+            } else if let Some(avg) = target.average_value.as_ref() {
+                format!("{} (avg)", avg.0)
+            } else if let Some(val) = target.value.as_ref() {
+                val.0.clone()
+            } else {
+                "<unknown>".to_string()
+            }
+        }
+        None => "<unknown>".to_string(),
+    }
+}
+
+fn format_value_status(current: &autoscaling_api::MetricValueStatus) -> String {
+    if let Some(util) = current.average_utilization {
+        format!("{}%", util)
+    } else if let Some(avg) = current.average_value.as_ref() {
+        format!("{} (avg)", avg.0)
+    } else if let Some(val) = current.value.as_ref() {
+        val.0.clone()
+    } else {
+        "<unknown>".to_string()
+    }
+}
+
+/// find the current value for the specified metric in the hpa's status
+fn current_value(hpa: &Hpa, metric: &autoscaling_api::MetricSpec) -> String {
+    let name = metric_name(metric);
+    let found = hpa.status.as_ref().and_then(|stat| {
+        stat.current_metrics.iter().find_map(|cur| {
+            if let Some(res) = cur.resource.as_ref() {
+                Some((res.name.clone(), &res.current))
+            } else if let Some(pods) = cur.pods.as_ref() {
+                Some((pods.metric.name.clone(), &pods.current))
+            } else if let Some(obj) = cur.object.as_ref() {
+                Some((
+                    format!(
+                        "{} on {}/{}",
+                        obj.metric.name, obj.described_object.kind, obj.described_object.name
+                    ),
+                    &obj.current,
+                ))
+            } else {
+                cur.external
+                    .as_ref()
+                    .map(|ext| (ext.metric.name.clone(), &ext.current))
+            }
+            .filter(|(cur_name, _)| *cur_name == name)
+            .map(|(_, current)| format_value_status(current))
+        })
+    });
+    found.unwrap_or_else(|| "<unknown>".to_string())
+}
+
+fn print_hpa_details(hpa: &Hpa, writer: &mut ClickWriter) {
+    let name = hpa.metadata.name.as_deref().unwrap_or("<Unknown>");
+    clickwriteln!(writer, "Name:\t\t{}", name);
+    if let Some(spec) = hpa.spec.as_ref() {
+        clickwriteln!(
+            writer,
+            "Reference:\t{}/{}",
+            spec.scale_target_ref.kind,
+            spec.scale_target_ref.name
+        );
+        clickwriteln!(
+            writer,
+            "Min/Max:\t{}/{}",
+            spec.min_replicas.unwrap_or(1),
+            spec.max_replicas
+        );
+    }
+    if let Some(stat) = hpa.status.as_ref() {
+        clickwriteln!(
+            writer,
+            "Replicas:\t{} current / {} desired",
+            stat.current_replicas,
+            stat.desired_replicas
+        );
+        if let Some(last) = stat.last_scale_time.as_ref() {
+            clickwriteln!(writer, "Last Scaled:\t{}", last.0);
+        }
+    }
+    let mut table = Table::new();
+    table.set_titles(row!["Metric", "Type", "Current", "Target"]);
+    if let Some(spec) = hpa.spec.as_ref() {
+        for metric in spec.metrics.iter() {
+            table.add_row(row![
+                metric_name(metric),
+                metric.type_,
+                current_value(hpa, metric),
+                format_target(metric_target(metric))
+            ]);
+        }
+    }
+    crate::table::print_filled_table(&mut table, writer);
+    if let Some(stat) = hpa.status.as_ref() {
+        for cond in stat.conditions.iter() {
+            clickwriteln!(
+                writer,
+                "{} = {}: {}",
+                cond.type_,
+                cond.status,
+                cond.message.as_deref().unwrap_or("")
+            );
+        }
+    }
+}
+
+fn show_hpa(env: &Env, name: &str, writer: &mut ClickWriter) -> Result<(), ClickError> {
+    let ns = env.namespace.as_ref().ok_or_else(|| {
+        ClickError::CommandError("Set a namespace to show a specific hpa".to_string())
+    })?;
+    let (request, _) =
+        Hpa::read_namespaced_horizontal_pod_autoscaler(name, ns, Default::default())?;
+    match env.run_on_context(|c| {
+        c.read::<autoscaling_api::ReadNamespacedHorizontalPodAutoscalerResponse>(request)
+    })? {
+        autoscaling_api::ReadNamespacedHorizontalPodAutoscalerResponse::Ok(hpa) => {
+            print_hpa_details(&hpa, writer);
+            Ok(())
+        }
+        _ => Err(ClickError::CommandError(format!(
+            "Could not read hpa {}",
+            name
+        ))),
+    }
+}
+
+/// sleep for the watch interval, returning false if ctrl-c was pressed while we were sleeping
+fn watch_sleep(env: &Env) -> bool {
+    let step = Duration::from_millis(100);
+    let mut slept = Duration::from_secs(0);
+    while slept < WATCH_INTERVAL {
+        if env.ctrlcbool.load(Ordering::SeqCst) {
+            return false;
+        }
+        std::thread::sleep(step);
+        slept += step;
+    }
+    !env.ctrlcbool.load(Ordering::SeqCst)
+}
+
+/// Show the autoscaler named on the command line, or list them all
+fn show_hpas(
+    matches: &ArgMatches,
+    env: &mut Env,
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    if let Some(name) = matches.value_of("name") {
+        return show_hpa(env, name, writer);
+    }
+    let (request, _response_body) = match &env.namespace {
+        Some(ns) => Hpa::list_namespaced_horizontal_pod_autoscaler(ns, Default::default())?,
+        None => Hpa::list_horizontal_pod_autoscaler_for_all_namespaces(Default::default())?,
+    };
+    let cols: Vec<&str> = COL_MAP.iter().map(|(_, col)| *col).collect();
+    run_list_command(
+        matches.clone(),
+        env,
+        writer,
+        cols,
+        request,
+        COL_MAP,
+        Some(EXTRA_COL_MAP),
+        Some(&HPA_EXTRACTORS),
+        hpa_to_kobj,
+    )
+}
+
+list_command!(
+    Hpas,
+    "hpa",
+    "Get HorizontalPodAutoscalers (in current namespace if set). With a NAME, show the metrics of \
+     that autoscaler against their targets.",
+    super::COL_FLAGS,
+    super::EXTRA_COL_FLAGS,
+    |clap: App<'static, 'static>| clap
+        .arg(
+            Arg::with_name("name")
+                .help("Show details for the autoscaler with this name")
+                .required(false)
+                .index(1)
+        )
+        .arg(
+            Arg::with_name("watch")
+                .short("w")
+                .long("watch")
+                .help("Refresh the output every 5 seconds, until ctrl-c is pressed")
+                .takes_value(false)
+        )
+        .arg(
+            Arg::with_name("regex")
+                .short("r")
+                .long("regex")
+                .help("Filter autoscalers by the specified regex")
+                .takes_value(true)
+        )
+        .arg(show_arg(EXTRA_COL_FLAGS, true))
+        .arg(sort_arg(COL_FLAGS, Some(EXTRA_COL_FLAGS)))
+        .arg(
+            Arg::with_name("reverse")
+                .short("R")
+                .long("reverse")
+                .help("Reverse the order of the returned list")
+                .takes_value(false),
+        ),
+    vec!["hpa", "hpas", "horizontalpodautoscalers"],
+    vec![&completer::hpa_completer],
+    [],
+    |matches, env, writer| {
+        if !matches.is_present("watch") {
+            return show_hpas(&matches, env, writer);
+        }
+
+        let in_place = !writer.is_redirected() && io::stdout().is_terminal();
+        writer.finish_paging();
+        env.ctrlcbool.store(false, Ordering::SeqCst);
+        // how many lines the last output took, so it can be drawn over
+        let mut lines = 0;
+        loop {
+            if in_place {
+                let mut buffer = writer.buffered();
+                show_hpas(&matches, env, &mut buffer)?;
+                let output = buffer.finish_output().unwrap_or_default();
+                if lines > 0 {
+                    // move up to the start of the last output, and clear from there down
+                    clickwrite!(writer, "\x1b[{}A\x1b[J", lines);
+                }
+                writer.write_all(&output)?;
+                writer.flush()?;
+                lines = output.iter().filter(|b| **b == b'\n').count();
+            } else {
+                show_hpas(&matches, env, writer)?;
+                clickwrite!(writer, "\n");
+            }
+            if !watch_sleep(env) {
+                break;
+            }
+        }
+        Ok(())
+    }
+);
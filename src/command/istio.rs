@@ -0,0 +1,227 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use clap::{App, Arg};
+use rustyline::completion::Pair as RustlinePair;
+use serde_json::Value;
+
+use crate::{
+    command::command_def::{exec_match, start_clap, Cmd},
+    command::{tool_exit_error, tool_spawn_error},
+    completer,
+    env::Env,
+    error::ClickError,
+    output::{ClickWriter, OutputFormat},
+    theme::{self, Role},
+};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::Command;
+
+const ISTIOCTL_URL: &str = "https://istio.io/latest/docs/setup/getting-started/#download";
+
+/// The kinds of envoy config proxy-config can show
+const CONFIG_TYPES: &[&str] = &[
+    "cluster",
+    "listener",
+    "route",
+    "endpoint",
+    "bootstrap",
+    "secret",
+];
+
+/// Sync states proxy-status prints, and how to show them. NOT SENT comes before SENT so the
+/// longer one is matched first.
+const SYNC_STATES: &[(&str, Role)] = &[
+    ("SYNCED", Role::GoodStatus),
+    ("NOT SENT", Role::Warning),
+    ("STALE", Role::BadStatus),
+];
+
+/// An istioctl command against the active context
+fn istioctl_command(env: &Env) -> Result<Command, ClickError> {
+    let context = env.context.as_ref().ok_or_else(|| {
+        ClickError::CommandError("Need an active context in order to run istioctl".to_string())
+    })?;
+    let mut command = Command::new("istioctl");
+    command.arg("--context").arg(&context.name);
+    Ok(command)
+}
+
+/// Run istioctl, returning what it printed
+fn istioctl_output(mut command: Command) -> Result<String, ClickError> {
+    let output = command
+        .output()
+        .map_err(|e| tool_spawn_error("istioctl", ISTIOCTL_URL, e))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(tool_exit_error("istioctl", output.status, &output.stderr))
+    }
+}
+
+/// The pod to look at, as istioctl wants it: name.namespace. A pod given by name is looked for
+/// in the current namespace, and with no name it's the active pod.
+fn pod_arg(env: &Env, pod: Option<&str>) -> Result<String, ClickError> {
+    match pod {
+        Some(pod) if pod.contains('.') => Ok(pod.to_string()),
+        Some(pod) => Ok(match env.namespace.as_deref() {
+            Some(ns) => format!("{}.{}", pod, ns),
+            None => pod.to_string(),
+        }),
+        None => {
+            let pod = env.current_pod()?;
+            Ok(format!(
+                "{}.{}",
+                pod.name(),
+                pod.namespace.as_deref().unwrap_or("default")
+            ))
+        }
+    }
+}
+
+/// Print a table istioctl printed, with the header styled, and sync states colored if
+/// sync_states is true
+fn print_istioctl_table(output: &str, sync_states: bool, writer: &mut ClickWriter) {
+    let mut lines = output.lines();
+    if let Some(header) = lines.next() {
+        clickwriteln!(writer, "{}", theme::style(Role::Header).paint(header));
+    }
+    for line in lines {
+        if !sync_states {
+            clickwriteln!(writer, "{}", line);
+            continue;
+        }
+        let mut rest = line;
+        while let Some((pos, state, role)) = SYNC_STATES
+            .iter()
+            .filter_map(|(state, role)| rest.find(state).map(|pos| (pos, *state, *role)))
+            .min_by_key(|(pos, _, _)| *pos)
+        {
+            clickwrite!(writer, "{}", &rest[..pos]);
+            clickwrite!(writer, "{}", theme::style(role).paint(state));
+            rest = &rest[pos + state.len()..];
+        }
+        clickwriteln!(writer, "{}", rest);
+    }
+}
+
+command!(
+    Istio,
+    "istio",
+    "Look at the Envoy sidecars of an Istio service mesh, using istioctl",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("action")
+                .help(
+                    "proxy-status: show whether each sidecar is in sync with istiod\n\
+                     proxy-config: show a sidecar's envoy config",
+                )
+                .possible_values(&["proxy-status", "proxy-config"])
+                .default_value("proxy-status")
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("what")
+                .help(
+                    "With proxy-status, the pod to check, rather than all sidecars. With \
+                     proxy-config, the config to show: cluster, listener, route, endpoint, \
+                     bootstrap, or secret.",
+                )
+                .index(2),
+        )
+        .arg(
+            Arg::with_name("pod")
+                .help("With proxy-config, the pod to look at, if not the active pod")
+                .index(3),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .short("o")
+                .help("How to show the output, if not the current output format")
+                .takes_value(true)
+                .possible_values(&["json", "yaml", "table"]),
+        )
+        .after_help(
+            "istioctl needs to be installed, and is run against the active context. Pods can be \
+given as name.namespace, or just a name to look in the current namespace.
+
+Examples:
+  # check all the sidecars are in sync
+  istio
+
+  # see what upstream clusters the active pod's sidecar knows about
+  istio proxy-config cluster
+
+  # dump the listeners of a pod in another namespace, as yaml
+  istio proxy-config listener web-5d9c8.prod -o yaml",
+        )
+    },
+    vec!["istio"],
+    vec![&completer::istioaction_values_completer],
+    no_named_complete!(),
+    |matches, env, writer| {
+        if let Some(output) = matches.value_of("output") {
+            writer.set_output_format(output.parse()?);
+        }
+        let action = matches.value_of("action").unwrap(); // safe, has a default
+        let mut command = istioctl_command(env)?;
+        let sync_states = match action {
+            "proxy-status" => {
+                command.arg("proxy-status");
+                if let Some(pod) = matches.value_of("what") {
+                    command.arg(pod_arg(env, Some(pod))?);
+                }
+                true
+            }
+            "proxy-config" => {
+                let config_type = matches.value_of("what").ok_or_else(|| {
+                    ClickError::CommandError(format!(
+                        "Need the config to show, one of: {}",
+                        CONFIG_TYPES.join(", ")
+                    ))
+                })?;
+                if !CONFIG_TYPES.contains(&config_type) {
+                    return Err(ClickError::CommandError(format!(
+                        "Unknown config '{}', use one of: {}",
+                        config_type,
+                        CONFIG_TYPES.join(", ")
+                    )));
+                }
+                // istioctl only shows bootstrap config as json or yaml
+                if config_type == "bootstrap" && !writer.output_format().is_raw() {
+                    writer.set_output_format(OutputFormat::Json);
+                }
+                command
+                    .arg("proxy-config")
+                    .arg(config_type)
+                    .arg(pod_arg(env, matches.value_of("pod"))?);
+                false
+            }
+            _ => unreachable!(), // clap validates
+        };
+
+        if writer.output_format().is_raw() {
+            command.arg("--output").arg("json");
+            let value: Value = serde_json::from_str(&istioctl_output(command)?)?;
+            writer.print_raw(&value)?;
+        } else {
+            print_istioctl_table(&istioctl_output(command)?, sync_states, writer);
+        }
+        Ok(())
+    }
+);
@@ -12,21 +12,23 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use ansi_term::Colour::Yellow;
 use clap::{App, Arg};
 use k8s_openapi::api::batch::v1 as batch_api;
+use k8s_openapi::{http::Request, List, ListOptional};
 
 use crate::{
     command::command_def::{exec_match, show_arg, sort_arg, start_clap, Cmd},
-    command::{keyval_string, run_list_command, time_since, Extractor},
+    command::delete::confirm_delete_all,
+    command::{keyval_string, run_filtered_list_command, time_since, Extractor},
     completer,
     env::Env,
+    error::ClickError,
     kobj::{KObj, ObjType},
     output::ClickWriter,
     table::CellSpec,
+    theme::Role,
 };
 
-use std::array::IntoIter;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::io::Write;
@@ -35,6 +37,8 @@ lazy_static! {
     static ref JOB_EXTRACTORS: HashMap<String, Extractor<batch_api::Job>> = {
         let mut m: HashMap<String, Extractor<batch_api::Job>> = HashMap::new();
         m.insert("Completions".to_owned(), job_completions);
+        m.insert("Active".to_owned(), job_active);
+        m.insert("Status".to_owned(), job_status);
         m.insert("Duration".to_owned(), job_duration);
         m.insert("Containers".to_owned(), job_containers);
         m.insert("Images".to_owned(), job_images);
@@ -45,8 +49,10 @@ lazy_static! {
 const COL_MAP: &[(&str, &str)] = &[
     ("name", "Name"),
     ("completions", "Completions"),
+    ("active", "Active"),
     ("duration", "Duration"),
     ("age", "Age"),
+    ("status", "Status"),
 ];
 
 const COL_FLAGS: &[&str] = &{ extract_first!(COL_MAP) };
@@ -65,7 +71,9 @@ fn job_to_kobj(job: &batch_api::Job) -> KObj {
     KObj {
         name: meta.name.clone().unwrap_or_else(|| "<Unknown>".into()),
         namespace: meta.namespace.clone(),
-        typ: ObjType::StatefulSet,
+        uid: meta.uid.clone(),
+        context: None,
+        typ: ObjType::Job,
     }
 }
 
@@ -103,6 +111,53 @@ fn job_duration(job: &batch_api::Job) -> Option<CellSpec<'_>> {
     }
 }
 
+#[derive(Debug, PartialEq)]
+enum JobState {
+    Complete,
+    Failed,
+    Running,
+    Pending,
+}
+
+fn job_condition_true(job: &batch_api::Job, typ: &str) -> bool {
+    job.status
+        .iter()
+        .flat_map(|stat| stat.conditions.iter())
+        .any(|cond| cond.type_ == typ && cond.status == "True")
+}
+
+fn job_state(job: &batch_api::Job) -> JobState {
+    if job_condition_true(job, "Failed") {
+        JobState::Failed
+    } else if job_condition_true(job, "Complete") {
+        JobState::Complete
+    } else if job.status.as_ref().and_then(|s| s.active).unwrap_or(0) > 0 {
+        JobState::Running
+    } else {
+        JobState::Pending
+    }
+}
+
+fn job_status(job: &batch_api::Job) -> Option<CellSpec<'_>> {
+    Some(match job_state(job) {
+        JobState::Complete => CellSpec::with_role("Complete".into(), Role::GoodStatus),
+        JobState::Failed => CellSpec::with_role("Failed".into(), Role::BadStatus),
+        JobState::Running => CellSpec::with_role("Running".into(), Role::Warning),
+        JobState::Pending => "Pending".into(),
+    })
+}
+
+/// show the number of active pods, and how to list them
+fn job_active(job: &batch_api::Job) -> Option<CellSpec<'_>> {
+    let active = job.status.as_ref().and_then(|s| s.active).unwrap_or(0);
+    if active > 0 {
+        let name = job.metadata.name.as_deref().unwrap_or("<Unknown>");
+        Some(format!("{} (pods -l job-name={})", active, name).into())
+    } else {
+        Some(0.into())
+    }
+}
+
 fn job_containers(job: &batch_api::Job) -> Option<CellSpec<'_>> {
     job.spec.as_ref().and_then(|spec| {
         spec.template.spec.as_ref().map(|pod_spec| {
@@ -137,6 +192,40 @@ fn job_selector(job: &batch_api::Job) -> Option<CellSpec<'_>> {
     })
 }
 
+/// Delete all the failed jobs returned by request, after asking for confirmation
+fn delete_failed_jobs(
+    env: &mut Env,
+    writer: &mut ClickWriter,
+    request: Request<Vec<u8>>,
+) -> Result<(), ClickError> {
+    let list: List<batch_api::Job> = env.run_on_context(|c| c.execute_list(request))?;
+    let failed: Vec<KObj> = list
+        .items
+        .iter()
+        .filter(|job| job_state(job) == JobState::Failed)
+        .map(job_to_kobj)
+        .collect();
+    if failed.is_empty() {
+        clickwriteln!(writer, "No failed jobs");
+        return Ok(());
+    }
+    clickwriteln!(writer, "Failed jobs:");
+    for job in failed.iter() {
+        clickwriteln!(
+            writer,
+            "  {} [{}]",
+            job.name(),
+            job.namespace.as_deref().unwrap_or("")
+        );
+    }
+    confirm_delete_all(
+        env,
+        writer,
+        &failed,
+        &format!("Delete {} failed jobs", failed.len()),
+    )
+}
+
 list_command!(
     Jobs,
     "jobs",
@@ -144,6 +233,12 @@ list_command!(
     super::COL_FLAGS,
     super::EXTRA_COL_FLAGS,
     |clap: App<'static, 'static>| clap
+        .arg(
+            Arg::with_name("name")
+                .help("Only get the job with this name")
+                .required(false)
+                .index(1)
+        )
         .arg(
             Arg::with_name("show_label")
                 .short("L")
@@ -158,6 +253,19 @@ list_command!(
                 .help("Filter jobs by the specified regex")
                 .takes_value(true)
         )
+        .arg(
+            Arg::with_name("failed")
+                .long("failed")
+                .help("Only show failed jobs")
+                .takes_value(false)
+        )
+        .arg(
+            Arg::with_name("delete_failed")
+                .long("delete-failed")
+                .help("Delete all failed jobs (will ask for confirmation)")
+                .takes_value(false)
+                .conflicts_with("failed")
+        )
         .arg(show_arg(EXTRA_COL_FLAGS, true))
         .arg(sort_arg(COL_FLAGS, Some(EXTRA_COL_FLAGS)))
         .arg(
@@ -168,16 +276,28 @@ list_command!(
                 .takes_value(false),
         ),
     vec!["job", "jobs"],
-    noop_complete!(),
-    IntoIter::new([]),
+    vec![&completer::job_completer],
+    [],
     |matches, env, writer| {
+        let field_sel = matches
+            .value_of("name")
+            .map(|name| format!("metadata.name={}", name));
+        let opts = ListOptional {
+            field_selector: field_sel.as_deref(),
+            ..Default::default()
+        };
         let (request, _response_body) = match &env.namespace {
-            Some(ns) => batch_api::Job::list_namespaced_job(ns, Default::default())?,
-            None => batch_api::Job::list_job_for_all_namespaces(Default::default())?,
+            Some(ns) => batch_api::Job::list_namespaced_job(ns, opts)?,
+            None => batch_api::Job::list_job_for_all_namespaces(opts)?,
         };
-        let cols: Vec<&str> = COL_MAP.iter().map(|(_, col)| *col).collect();
 
-        run_list_command(
+        if matches.is_present("delete_failed") {
+            return delete_failed_jobs(env, writer, request);
+        }
+
+        let cols: Vec<&str> = COL_MAP.iter().map(|(_, col)| *col).collect();
+        let only_failed = matches.is_present("failed");
+        run_filtered_list_command(
             matches,
             env,
             writer,
@@ -187,6 +307,7 @@ list_command!(
             Some(EXTRA_COL_MAP),
             Some(&JOB_EXTRACTORS),
             job_to_kobj,
+            |job| !only_failed || job_state(job) == JobState::Failed,
         )
     }
 );
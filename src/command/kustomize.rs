@@ -0,0 +1,194 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use clap::{App, Arg};
+use rustyline::completion::Pair as RustlinePair;
+use serde_json::Value;
+
+use crate::{
+    command::command_def::{exec_match, start_clap, Cmd},
+    command::{
+        command_line, confirm_action, kubectl_command, kubectl_get_json, kubectl_obj_arg,
+        kubectl_output, kubectl_output_with_input, strip_managed_fields,
+    },
+    completer, diff,
+    env::Env,
+    error::ClickError,
+    manifest,
+    output::ClickWriter,
+};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::Command;
+
+/// Build the overlay at path with kubectl's built in kustomize, returning the yaml it makes
+fn build_overlay(path: &str) -> Result<String, ClickError> {
+    let mut command = Command::new("kubectl");
+    command.arg("kustomize").arg(path);
+    kubectl_output(command)
+}
+
+/// What the server would make of the objects in yaml if they were applied, from a server side dry
+/// run, so defaults and admission changes show up in the diff
+pub fn dry_run_apply(env: &Env, yaml: &str) -> Result<Vec<Value>, ClickError> {
+    let mut command = kubectl_command(env, env.namespace.as_deref())?;
+    command
+        .arg("apply")
+        .arg("-f")
+        .arg("-")
+        .arg("--dry-run=server")
+        .arg("-o")
+        .arg("json");
+    let applied: Value =
+        serde_json::from_str(&kubectl_output_with_input(command, yaml.as_bytes())?)?;
+    // several objects come back as a List
+    match applied.get("items").and_then(|items| items.as_array()) {
+        Some(items) if applied.get("kind").and_then(|k| k.as_str()) == Some("List") => {
+            Ok(items.clone())
+        }
+        _ => Ok(vec![applied]),
+    }
+}
+
+/// Show how applying the objects would change what's live. Returns the number of objects that
+/// would change.
+fn show_changes(
+    env: &Env,
+    objects: Vec<Value>,
+    writer: &mut ClickWriter,
+) -> Result<usize, ClickError> {
+    let mut changed = 0;
+    for mut object in objects.into_iter() {
+        let namespace = manifest::manifest_namespace(&object, env.namespace.as_deref());
+        let obj = manifest::manifest_kobj(&object, namespace)?;
+        let name = kubectl_obj_arg(&obj);
+        let live = match kubectl_get_json(env, &obj) {
+            Ok(mut live) => {
+                strip_managed_fields(&mut live);
+                serde_yaml::to_string(&live)?
+            }
+            Err(ClickError::KubectlError { ref stderr, .. }) if stderr.contains("NotFound") => {
+                String::new()
+            }
+            Err(e) => return Err(e),
+        };
+        strip_managed_fields(&mut object);
+        let lines = diff::unified_diff(
+            &name,
+            &format!("{} (kustomized)", name),
+            &live,
+            &serde_yaml::to_string(&object)?,
+        );
+        if !lines.is_empty() {
+            diff::write_diff(writer, &lines);
+            changed += 1;
+        }
+    }
+    Ok(changed)
+}
+
+command!(
+    Kustomize,
+    "kustomize",
+    "Preview the objects a kustomize overlay makes, or apply it after seeing how it would change \
+     what's running",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("action")
+                .help(
+                    "preview: print the objects the overlay makes\n\
+                     apply: show how the objects differ from what's live, then apply them",
+                )
+                .required(true)
+                .possible_values(&["preview", "apply"])
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("path")
+                .help("The directory with the kustomization.yaml of the overlay")
+                .required(true)
+                .index(2),
+        )
+        .arg(
+            Arg::with_name("yes")
+                .long("yes")
+                .short("y")
+                .help("Apply without asking first, after showing the changes"),
+        )
+        .after_help(
+            "The overlay is built with 'kubectl kustomize', so no separate kustomize install is \
+needed. Before applying, each object is run through a server side dry run and diffed against the \
+live object, so what's shown is what the server would actually end up with.
+
+Examples:
+  # see what the prod overlay makes
+  kustomize preview overlays/prod
+
+  # roll out the staging overlay
+  kustomize apply overlays/staging",
+        )
+    },
+    vec!["kustomize", "kz"],
+    vec![&completer::kustomizeaction_values_completer],
+    no_named_complete!(),
+    |matches, env, writer| {
+        let path = matches.value_of("path").unwrap(); // safe, required
+        let yaml = build_overlay(path)?;
+        let objects = manifest::parse_manifests(path, &yaml)?;
+        if matches.value_of("action") == Some("preview") {
+            if !writer.print_raw(&objects)? {
+                writer.print_yaml_text(&yaml);
+            }
+            return Ok(());
+        }
+
+        if objects.is_empty() {
+            return Err(ClickError::CommandError(format!(
+                "Overlay {} has no objects to apply",
+                path
+            )));
+        }
+        let total = objects.len();
+        let changed = show_changes(env, dry_run_apply(env, &yaml)?, writer)?;
+        if changed == 0 {
+            clickwriteln!(
+                writer,
+                "All {} objects are up to date, nothing to apply",
+                total
+            );
+            return Ok(());
+        }
+        let mut command = kubectl_command(env, env.namespace.as_deref())?;
+        command.arg("apply").arg("-f").arg("-");
+        if env.dry_run {
+            clickwriteln!(
+                writer,
+                "[DRY RUN] {} < (kustomize {})",
+                command_line(&command),
+                path
+            );
+            return Ok(());
+        }
+        let prompt = format!("Apply {} changed object(s) of {}", changed, total);
+        if !matches.is_present("yes") && !confirm_action(writer, &prompt) {
+            clickwriteln!(writer, "Not applying");
+            return Ok(());
+        }
+        let out = kubectl_output_with_input(command, yaml.as_bytes())?;
+        clickwrite!(writer, "{}", out);
+        Ok(())
+    }
+);
@@ -0,0 +1,178 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use clap::{App, Arg, ArgMatches};
+use rustyline::completion::Pair as RustlinePair;
+use serde_json::{Map, Value};
+
+use crate::{
+    command::command_def::{exec_match, start_clap, Cmd},
+    command::{kubectl_get_json, kubectl_mutate, kubectl_obj_arg, kubectl_obj_command},
+    completer,
+    env::{Env, ErrorStrategy},
+    error::ClickError,
+    kobj::KObj,
+    labels::{self, MetaChange, MetaKind},
+    output::ClickWriter,
+};
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+
+fn changes_arg() -> Arg<'static, 'static> {
+    Arg::with_name("changes")
+        .help("key=value to set a key, or key- to remove one")
+        .required(true)
+        .multiple(true)
+        .index(1)
+}
+
+fn overwrite_arg() -> Arg<'static, 'static> {
+    Arg::with_name("overwrite")
+        .long("overwrite")
+        .help("Allow changing the value of keys that are already set")
+        .takes_value(false)
+}
+
+/// Apply changes to the labels or annotations of obj, printing what changed
+fn update_meta(
+    env: &Env,
+    obj: &KObj,
+    kind: MetaKind,
+    changes: &[MetaChange],
+    overwrite: bool,
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    let field = match kind {
+        MetaKind::Label => "labels",
+        MetaKind::Annotation => "annotations",
+    };
+    let value = kubectl_get_json(env, obj)?;
+    let current: BTreeMap<String, String> = value
+        .pointer(&format!("/metadata/{}", field))
+        .and_then(Value::as_object)
+        .map(|map| {
+            map.iter()
+                .map(|(k, v)| (k.clone(), v.as_str().unwrap_or_default().to_string()))
+                .collect()
+        })
+        .unwrap_or_default();
+    let planned = labels::plan_changes(&current, changes, overwrite)?;
+    let name = kubectl_obj_arg(obj);
+    if planned.is_empty() {
+        clickwriteln!(writer, "{} not changed", name);
+        return Ok(());
+    }
+
+    let mut updates = Map::new();
+    for change in planned.iter() {
+        let new = match change.new {
+            Some(ref new) => Value::String(new.clone()),
+            None => Value::Null,
+        };
+        updates.insert(change.key.clone(), new);
+    }
+    let mut meta = Map::new();
+    meta.insert(field.to_string(), Value::Object(updates));
+    // so we fail, rather than overwrite, if someone else changed things since we checked
+    if let Some(rv) = value.pointer("/metadata/resourceVersion") {
+        meta.insert("resourceVersion".to_string(), rv.clone());
+    }
+    let patch = serde_json::json!({ "metadata": meta });
+
+    let mut command = kubectl_obj_command(env, obj)?;
+    command
+        .arg("patch")
+        .arg(&name)
+        .arg("--type")
+        .arg("merge")
+        .arg("-p")
+        .arg(patch.to_string());
+    if kubectl_mutate(env, command, writer)?.is_none() {
+        return Ok(());
+    }
+
+    let verb = match kind {
+        MetaKind::Label => "labeled",
+        MetaKind::Annotation => "annotated",
+    };
+    clickwriteln!(writer, "{} {}:", name, verb);
+    for change in planned.iter() {
+        clickwriteln!(
+            writer,
+            "  {}: {} → {}",
+            change.key,
+            change.old.as_deref().unwrap_or("<none>"),
+            change.new.as_deref().unwrap_or("<removed>")
+        );
+    }
+    Ok(())
+}
+
+fn run_meta_command(
+    matches: &ArgMatches,
+    env: &mut Env,
+    writer: &mut ClickWriter,
+    kind: MetaKind,
+) -> Result<(), ClickError> {
+    // validate everything before touching any objects
+    let args: Vec<&str> = matches.values_of("changes").unwrap().collect(); // safe, required
+    let changes = labels::parse_changes(kind, &args)?;
+    let overwrite = matches.is_present("overwrite");
+    let res = env.apply_to_selection(
+        writer,
+        Some(&env.click_config.range_separator),
+        ErrorStrategy::CollectAll,
+        |obj, writer| update_meta(env, obj, kind, &changes, overwrite, writer),
+    );
+    if res.is_ok() && !env.dry_run {
+        env.invalidate_selection();
+    }
+    res
+}
+
+command!(
+    Label,
+    "label",
+    "Add, change, or remove labels on the active object(s)",
+    |clap: App<'static, 'static>| {
+        clap.arg(changes_arg()).arg(overwrite_arg()).after_help(
+            "Example:
+  # set app=web and remove the tier label
+  label app=web tier-",
+        )
+    },
+    vec!["label"],
+    noop_complete!(),
+    no_named_complete!(),
+    |matches, env, writer| { run_meta_command(&matches, env, writer, MetaKind::Label) }
+);
+
+command!(
+    Annotate,
+    "annotate",
+    "Add, change, or remove annotations on the active object(s)",
+    |clap: App<'static, 'static>| {
+        clap.arg(changes_arg()).arg(overwrite_arg()).after_help(
+            "Example:
+  # change the description annotation, which is already set
+  annotate --overwrite description='my new description'",
+        )
+    },
+    vec!["annotate"],
+    noop_complete!(),
+    no_named_complete!(),
+    |matches, env, writer| { run_meta_command(&matches, env, writer, MetaKind::Annotation) }
+);
@@ -12,11 +12,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use ansi_term::Colour::Yellow;
+use ansi_term::Colour;
 use chrono::offset::{Local, Utc};
 use chrono::DateTime;
 use clap::{App, Arg};
 use k8s_openapi::api::core::v1 as api;
+use k8s_openapi::{List, ListOptional};
+use regex::Regex;
 
 use reqwest::blocking::Response;
 use rustyline::completion::Pair as RustlinePair;
@@ -24,9 +26,11 @@ use strfmt::strfmt;
 
 use crate::{
     command::command_def::{exec_match, start_clap, Cmd},
-    command::{parse_duration, valid_date, valid_duration, valid_u32},
+    command::{
+        editor_expression, get_editor, parse_duration, valid_date, valid_duration, valid_u32,
+    },
     completer,
-    env::Env,
+    env::{Env, ErrorStrategy, ObjectSelection},
     error::ClickError,
     kobj::{KObj, ObjType},
     output::ClickWriter,
@@ -39,6 +43,7 @@ use std::io::{BufRead, BufReader, Read, Write};
 use std::path::PathBuf;
 use std::sync::atomic::Ordering;
 use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
@@ -119,21 +124,7 @@ fn do_logs<'a>(
                 }
             } else if editor {
                 // We're opening in an editor, save to a temp
-                let editor = if let Some(v) = editor_opt {
-                    v.to_owned()
-                } else if let Some(ref e) = env.click_config.editor {
-                    e.clone()
-                } else {
-                    match std::env::var("EDITOR") {
-                        Ok(ed) => ed,
-                        Err(e) => {
-                            return Err(ClickError::CommandError(format!(
-                                "Could not get EDITOR environment variable: {}",
-                                e
-                            )));
-                        }
-                    }
-                };
+                let editor = get_editor(env, editor_opt)?;
                 let tmpdir = match env.tempdir {
                     Ok(ref td) => td,
                     Err(ref e) => {
@@ -152,14 +143,8 @@ fn do_logs<'a>(
                 write_logs_to_file(env, &file_path, reader)?;
 
                 clickwriteln!(writer, "Logs downloaded, starting editor");
-                let expr = if editor.contains(' ') {
-                    // split the whitespace
-                    let mut eargs: Vec<&str> = editor.split_whitespace().collect();
-                    eargs.push(file_path.to_str().unwrap());
-                    duct::cmd(eargs[0], &eargs[1..])
-                } else {
-                    cmd!(editor, file_path)
-                };
+                writer.finish_paging();
+                let expr = editor_expression(&editor, &file_path);
                 expr.start()?;
                 Ok(())
             } else {
@@ -356,9 +341,14 @@ command!(
             opts.timestamps = Some(true);
         }
 
+        // ranges are checked one object at a time below
+        if !matches!(env.current_selection(), ObjectSelection::Range(_)) {
+            env.current_pod()?;
+        }
         env.apply_to_selection(
             writer,
             Some(&env.click_config.range_separator),
+            ErrorStrategy::CollectAll,
             |obj, writer| {
                 if obj.is_pod() {
                     do_logs(
@@ -381,3 +371,217 @@ command!(
         )
     }
 );
+
+/// Colours to tell pods apart by in multitail, used in turn
+const POD_COLOURS: &[Colour] = &[
+    Colour::Cyan,
+    Colour::Green,
+    Colour::Yellow,
+    Colour::Blue,
+    Colour::Purple,
+    Colour::Red,
+];
+
+/// The container to tail in pod: the one asked for, else the pod's default container (set with
+/// the kubectl.kubernetes.io/default-container annotation), else its first
+fn multitail_container(pod: &api::Pod, container: Option<&str>) -> Option<String> {
+    if let Some(container) = container {
+        return Some(container.to_string());
+    }
+    pod.metadata
+        .annotations
+        .get("kubectl.kubernetes.io/default-container")
+        .cloned()
+        .or_else(|| {
+            pod.spec
+                .as_ref()
+                .and_then(|spec| spec.containers.first())
+                .map(|c| c.name.clone())
+        })
+}
+
+/// Should line be printed, given multitail's --include and --exclude regexes
+fn keep_line(line: &str, include: Option<&Regex>, exclude: Option<&Regex>) -> bool {
+    include.is_none_or(|re| re.is_match(line)) && !exclude.is_some_and(|re| re.is_match(line))
+}
+
+fn valid_regex(s: String) -> Result<(), String> {
+    Regex::new(&s).map(|_| ()).map_err(|e| e.to_string())
+}
+
+command!(
+    Multitail,
+    "multitail",
+    "Tail the logs of all the pods matching a label selector at once, like stern. Each line is \
+     prefixed with the name of the pod it came from, in a colour for that pod.",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("selector")
+                .short("l")
+                .long("selector")
+                .help("Tail pods matching this label selector (i.e. app=nginx,tier!=db)")
+                .required(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("namespace")
+                .short("n")
+                .long("namespace")
+                .help("Look for pods in this namespace, rather than the current one")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("container")
+                .short("c")
+                .long("container")
+                .help("Tail this container in each pod, rather than the pod's default container")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("tail")
+                .short("t")
+                .long("tail")
+                .validator(valid_u32)
+                .help("Number of lines from the end of each pod's logs to start with")
+                .takes_value(true)
+                .default_value("10"),
+        )
+        .arg(
+            Arg::with_name("include")
+                .long("include")
+                .short("i")
+                .value_name("REGEX")
+                .validator(valid_regex)
+                .help("Only print lines that match this regex")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("exclude")
+                .long("exclude")
+                .short("e")
+                .value_name("REGEX")
+                .validator(valid_regex)
+                .help("Don't print lines that match this regex")
+                .takes_value(true),
+        )
+        .after_help(
+            "Logs are followed until ^C. Pods that start after multitail does aren't picked up, \
+run it again to include them. With no namespace set, pods in all namespaces are tailed, and \
+prefixed with their namespace too.
+
+Examples:
+  # tail all the nginx pods, skipping health checks
+  multitail -l app=nginx --exclude /healthz
+
+  # look for errors in the sidecars of the web pods in the prod namespace
+  multitail -l tier=web -n prod -c envoy --include 'error|warn'",
+        )
+    },
+    vec!["multitail", "mtail"],
+    noop_complete!(),
+    IntoIterator::into_iter([
+        (
+            "selector".to_string(),
+            completer::pod_label_completer as fn(&str, &Env) -> Vec<RustlinePair>
+        ),
+        (
+            "namespace".to_string(),
+            completer::namespace_completer as fn(&str, &Env) -> Vec<RustlinePair>
+        ),
+    ])
+    .collect(),
+    |matches, env, writer| {
+        let namespace = matches.value_of("namespace").or(env.namespace.as_deref());
+        let opts = ListOptional {
+            label_selector: matches.value_of("selector"),
+            ..Default::default()
+        };
+        let (request, _) = match namespace {
+            Some(ns) => api::Pod::list_namespaced_pod(ns, opts)?,
+            None => api::Pod::list_pod_for_all_namespaces(opts)?,
+        };
+        let pods: List<api::Pod> = env.run_on_context(|c| c.execute_list(request))?;
+        if pods.items.is_empty() {
+            clickwriteln!(
+                writer,
+                "No pods match {}",
+                matches.value_of("selector").unwrap()
+            );
+            return Ok(());
+        }
+
+        // open all the streams first, so problems are reported before the logs start
+        let tail = matches.value_of("tail").unwrap().parse::<i64>().unwrap(); // safe, validated
+        let mut streams = vec![];
+        for pod in pods.items.iter() {
+            let name = pod.metadata.name.as_deref().unwrap_or("<unknown>");
+            let ns = pod.metadata.namespace.as_deref().unwrap_or("default");
+            let label = match namespace {
+                Some(_) => name.to_string(),
+                None => format!("{}/{}", ns, name),
+            };
+            let container = multitail_container(pod, matches.value_of("container"));
+            let opts = api::ReadNamespacedPodLogOptional {
+                container: container.as_deref(),
+                follow: Some(true),
+                tail_lines: Some(tail),
+                ..Default::default()
+            };
+            let (request, _) = api::Pod::read_namespaced_pod_log(name, ns, opts)?;
+            match env.run_on_context(|c| c.execute_reader(request, None)) {
+                Ok(reader) => streams.push((label, reader)),
+                Err(e) => clickwriteln!(writer, "Can't tail {}: {}", label, e),
+            }
+        }
+        let width = streams
+            .iter()
+            .map(|(label, _)| label.len())
+            .max()
+            .unwrap_or(0);
+        let include = matches
+            .value_of("include")
+            .map(|re| Regex::new(re).unwrap());
+        let exclude = matches
+            .value_of("exclude")
+            .map(|re| Regex::new(re).unwrap());
+
+        // each pod's thread writes whole lines to the shared writer. it's taken back when we're
+        // done, which tells any threads still waiting on a quiet pod to stop
+        let shared = Arc::new(Mutex::new(Some(std::mem::take(writer))));
+        env.ctrlcbool.store(false, Ordering::SeqCst);
+        let handles: Vec<thread::JoinHandle<()>> = streams
+            .into_iter()
+            .enumerate()
+            .map(|(i, (label, reader))| {
+                let prefix = POD_COLOURS[i % POD_COLOURS.len()]
+                    .paint(format!("{:width$}", label, width = width))
+                    .to_string();
+                let shared = shared.clone();
+                let include = include.clone();
+                let exclude = exclude.clone();
+                thread::spawn(move || {
+                    for line in BufReader::new(reader).lines() {
+                        let line = match line {
+                            Ok(line) => line,
+                            Err(_) => break,
+                        };
+                        if !keep_line(&line, include.as_ref(), exclude.as_ref()) {
+                            continue;
+                        }
+                        match shared.lock().unwrap().as_mut() {
+                            Some(writer) => clickwriteln!(writer, "{} {}", prefix, line),
+                            None => break,
+                        }
+                    }
+                })
+            })
+            .collect();
+        while !env.ctrlcbool.load(Ordering::SeqCst) && !handles.iter().all(|h| h.is_finished()) {
+            thread::sleep(Duration::from_millis(100));
+        }
+        if let Some(shared_writer) = shared.lock().unwrap().take() {
+            *writer = shared_writer;
+        }
+        Ok(())
+    }
+);
@@ -17,9 +17,10 @@ use chrono::{DateTime, Duration};
 use clap::ArgMatches;
 use humantime::parse_duration;
 use k8s_openapi::{
-    apimachinery::pkg::apis::meta::v1::ObjectMeta,
+    apimachinery::pkg::apis::meta::v1::{LabelSelector, ObjectMeta},
     http::{self, Request},
-    List, ListOptional, ListResponse, ListableResource, Metadata, RequestError, ResponseBody,
+    List, ListOptional, ListResponse, ListableResource, Metadata, NamespaceResourceScope,
+    RequestError, ResponseBody,
 };
 use prettytable::{Cell, Row};
 use regex::Regex;
@@ -27,39 +28,79 @@ use serde::Deserialize;
 
 use crate::env::Env;
 use crate::error::ClickError;
-use crate::kobj::KObj;
-use crate::output::ClickWriter;
+use crate::fuzzy::FuzzyMatch;
+use crate::kobj::{KObj, ObjType};
+use crate::output::{ClickWriter, OutputFormat};
 use crate::table::CellSpec;
+use crate::theme::{self, Role};
 
 use std::borrow::Cow;
 use std::collections::{BTreeMap, HashMap};
 use std::fmt::Debug;
-use std::io::{stderr, Write};
+use std::io::{self, stderr, BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::atomic::Ordering;
 
 #[macro_use]
 pub mod command_def;
 
 pub mod alias; // commands for alias/unalias
+pub mod apply; // command to apply manifests
+pub mod background; // commands for background jobs
 pub mod click; // commands internal to click (setting config values, etc)
+pub mod clusterrolebindings; // commands relating to cluster role bindings
+pub mod clusterroles; // commands relating to cluster roles
 pub mod configmaps; // commands relating to configmaps
+pub mod cp; // command to copy files to and from pods
 pub mod crds; // commands to query crd created objects
+pub mod cronjobs; // commands relating to cronjobs
+pub mod daemonsets; // commands relating to daemonsets
+pub mod debug; // command to debug pods with ephemeral containers, using kubectl debug
 pub mod delete; // command to delete objects
 pub mod deployments; // command to list deployments
 pub mod describe; // the describe command
+pub mod diff; // command to diff manifests against live objects
+pub mod edit; // command to edit objects
 pub mod events; // commands to print events
 pub mod exec; // command to exec into pods
+pub mod helm; // command to look at helm releases
+pub mod hpa; // commands relating to horizontal pod autoscalers
+pub mod istio; // command to look at istio sidecars with istioctl
 pub mod jobs; // commands relating to jobs
-pub mod logs; // command to get pod logs
+pub mod kustomize; // command to preview and apply kustomize overlays
+pub mod label; // commands to change labels and annotations
+pub mod logs; // commands to get pod logs
 pub mod namespaces; // commands relating to namespaces
+pub mod navigate; // commands to jump from an object to its node or owner
+pub mod networkpolicies; // commands relating to network policies
 pub mod nodes; // commands relating to nodes
+pub mod nsenter; // command to enter a container's namespaces on its node
+pub mod patch; // command to patch objects
 pub mod pods; //commands relating to pods
 pub mod portforwards; // commands for forwarding ports
+pub mod printenv; // command to show the environment of a container
+pub mod proxy; // command to run kubectl proxy in the background
+pub mod rbac; // commands to inspect rbac permissions
 pub mod replicasets; // commands relating to relicasets
+pub mod rolebindings; // commands relating to role bindings
+pub mod roles; // commands relating to roles
+pub mod rollout; // commands for managing rollouts of deployments, daemonsets, and statefulsets
+pub mod scale; // command to scale deployments and the like
+pub mod seal; // command to seal secrets with kubeseal
 pub mod secrets; // commands for secrets
+pub mod serviceaccounts; // commands relating to service accounts
 pub mod services; // commands for services
+pub mod ssh; // command to ssh to the nodes running pods
 pub mod statefulsets; // commands for statefulsets
 pub mod storage; // commands relating to storage objects (like storageclass)
+pub mod token; // command to get and decode service account tokens
+pub mod top; // command to show resource usage from the metrics api
+pub mod tree; // command to show what a controller manages as a tree
+pub mod velero; // command to look at velero backups and restore from them
+pub mod version; // command to print click, kubectl, and server versions
 pub mod volumes; // commands relating to volumes
+pub mod wait; // command to wait for conditions on objects, using kubectl wait
 
 #[cfg(feature = "argorollouts")]
 pub mod rollouts;
@@ -79,6 +120,43 @@ fn mapped_val(key: &str, map: &[(&'static str, &'static str)]) -> Option<&'stati
 
 #[allow(clippy::too_many_arguments)] // factoring this out into structs just makes it worse
 pub fn run_list_command<T, F>(
+    matches: ArgMatches,
+    env: &mut Env,
+    writer: &mut ClickWriter,
+    cols: Vec<&str>,
+    request: Request<Vec<u8>>,
+    col_map: &[(&'static str, &'static str)],
+    extra_col_map: Option<&[(&'static str, &'static str)]>,
+    extractors: Option<&HashMap<String, Extractor<T>>>,
+    get_kobj: F,
+) -> Result<(), ClickError>
+where
+    T: ListableResource
+        + Metadata<Ty = ObjectMeta>
+        + for<'de> Deserialize<'de>
+        + Debug
+        + Send
+        + 'static,
+    F: Fn(&T) -> KObj,
+{
+    run_filtered_list_command(
+        matches,
+        env,
+        writer,
+        cols,
+        request,
+        col_map,
+        extra_col_map,
+        extractors,
+        get_kobj,
+        |_| true,
+    )
+}
+
+/// Same as `run_list_command`, but only items for which `keep` returns true are shown. Use this
+/// when filtering can't be done server side via a label or field selector.
+#[allow(clippy::too_many_arguments)]
+pub fn run_filtered_list_command<T, F, P>(
     matches: ArgMatches,
     env: &mut Env,
     writer: &mut ClickWriter,
@@ -88,10 +166,17 @@ pub fn run_list_command<T, F>(
     extra_col_map: Option<&[(&'static str, &'static str)]>,
     extractors: Option<&HashMap<String, Extractor<T>>>,
     get_kobj: F,
+    keep: P,
 ) -> Result<(), ClickError>
 where
-    T: ListableResource + Metadata<Ty = ObjectMeta> + for<'de> Deserialize<'de> + Debug,
+    T: ListableResource
+        + Metadata<Ty = ObjectMeta>
+        + for<'de> Deserialize<'de>
+        + Debug
+        + Send
+        + 'static,
     F: Fn(&T) -> KObj,
+    P: Fn(&T) -> bool,
 {
     let regex = match crate::table::get_regex(&matches) {
         Ok(r) => r,
@@ -101,16 +186,13 @@ where
         }
     };
 
-    let list_res = env.run_on_context::<_, List<T>>(|c| c.execute_list(request));
-    if list_res.is_err() {
-        env.clear_last_objs();
-    }
-    let list = list_res?;
-
     let mut flags: Vec<&str> = match matches.values_of("show") {
         Some(v) => v.collect(),
         None => vec![],
     };
+    if writer.output_format() == OutputFormat::Wide {
+        flags.push("all");
+    }
 
     let sort = matches.value_of("sort").map(|s| {
         let colname = s.to_lowercase();
@@ -133,26 +215,528 @@ where
         }
     });
 
+    let namespaces: Option<Vec<&str>> = matches.values_of("namespaces").map(|ns| ns.collect());
+    let contexts: Option<Vec<&str>> = matches.values_of("contexts").map(|cs| cs.collect());
+
     if let Some(ecm) = extra_col_map {
-        // if we're not in a namespace, we want to add a namespace col if it's in extra_col_map
-        if env.namespace.is_none() && mapped_val("namespace", ecm).is_some() {
+        // if we're not in a namespace, or listing from more than one, we want to add a namespace
+        // col if it's in extra_col_map
+        let many_namespaces = namespaces.as_ref().is_some_and(|ns| ns.len() > 1);
+        if (env.namespace.is_none() || many_namespaces) && mapped_val("namespace", ecm).is_some() {
             flags.push("namespace");
         }
 
         command_def::add_extra_cols(&mut cols, matches.is_present("labels"), flags, ecm);
     }
 
-    handle_list_result(
-        env,
-        writer,
-        cols,
-        list,
-        extractors,
-        regex,
-        sort,
-        matches.is_present("reverse"),
-        get_kobj,
-    )
+    let reverse = matches.is_present("reverse");
+    if writer.output_format().is_raw() {
+        let targets = list_targets::<T>(env, &request, namespaces, contexts)?;
+        return print_raw_lists(env, writer, targets, get_kobj);
+    }
+    if namespaces.is_some() || contexts.is_some() {
+        let targets = list_targets::<T>(env, &request, namespaces, contexts)?;
+        let list = MultiList {
+            cols,
+            extractors,
+            regex,
+            sort,
+            reverse,
+        };
+        return list.run(env, writer, targets, get_kobj, keep);
+    }
+
+    // rows can only be printed as each page arrives if nothing needs to see all of them first
+    let mut stream = sort.is_none() && !reverse;
+    let page_size = env.click_config.list_page_size;
+
+    let mut continue_token: Option<String> = None;
+    let mut items: Vec<T> = vec![];
+    let mut kobjs: Vec<KObj> = vec![];
+    let mut printed_titles = false;
+    // if nothing is filtered out, the names are all there are, and can be used for completion
+    let mut complete = regex.is_none() && lists_current_namespace(env, &request);
+    let mut names: Vec<String> = vec![];
+    loop {
+        let page_request = list_page_request(&request, page_size, continue_token.as_deref())?;
+        let mut page = match env.run_on_context(|c| c.execute_list::<T>(page_request)) {
+            Ok(res) => res,
+            Err(ClickError::Api { code: 410, .. }) if continue_token.is_some() => {
+                // the continue token expired, so the only option is to start over. Rows that were
+                // printed can't be taken back, so rather than carrying on from them, the new list
+                // is printed as a whole once it's fetched.
+                clickwriteln!(
+                    writer,
+                    "{}",
+                    theme::style(Role::Warning).paint(
+                        "The list changed too much while it was being fetched, listing \
+                             again from the start"
+                    )
+                );
+                continue_token = None;
+                items.clear();
+                kobjs.clear();
+                names.clear();
+                stream = false;
+                continue;
+            }
+            Err(e) => {
+                // keep what was printed selectable
+                if kobjs.is_empty() {
+                    env.clear_last_objs();
+                } else {
+                    env.set_last_objs(kobjs);
+                }
+                return Err(e);
+            }
+        };
+        continue_token = page.metadata.continue_.take().filter(|t| !t.is_empty());
+        let last = continue_token.is_none();
+        let fetched = page.items.len();
+        page.items.retain(|item| keep(item));
+        complete &= page.items.len() == fetched;
+        if complete {
+            names.extend(
+                page.items
+                    .iter()
+                    .filter_map(|item| item.metadata().name.clone()),
+            );
+        }
+
+        if stream {
+            let specs = build_specs(&cols, &page, extractors, true, regex.clone(), &get_kobj);
+            if !specs.is_empty() || (last && !printed_titles) {
+                let (page_kobjs, rows): (Vec<KObj>, Vec<RowSpec>) = specs.into_iter().unzip();
+                let titles = if printed_titles {
+                    None
+                } else {
+                    Some(list_titles(&cols))
+                };
+                crate::table::print_table_page(titles, rows, kobjs.len(), last, writer);
+                printed_titles = true;
+                kobjs.extend(page_kobjs);
+            }
+        } else {
+            items.append(&mut page.items);
+        }
+
+        if last {
+            break;
+        }
+    }
+
+    if complete {
+        env.set_cached_resource_names(T::URL_PATH_SEGMENT, names);
+    }
+    if stream {
+        env.set_last_objs(kobjs);
+        Ok(())
+    } else {
+        let list = List {
+            items,
+            metadata: Default::default(),
+        };
+        handle_list_result(
+            env, writer, cols, list, extractors, regex, sort, reverse, get_kobj,
+        )
+    }
+}
+
+/// If request lists everything in the current namespace (or in all of them if none is set), rather
+/// than using a selector or listing some other namespace
+fn lists_current_namespace(env: &Env, request: &Request<Vec<u8>>) -> bool {
+    let has_selector = request.uri().query().is_some_and(|query| {
+        url::form_urlencoded::parse(query.as_bytes())
+            .any(|(key, _)| key == "labelSelector" || key == "fieldSelector")
+    });
+    let path = request.uri().path();
+    let in_namespace = match env.namespace.as_deref() {
+        Some(ns) => path.contains(&format!("/namespaces/{}/", ns)),
+        None => !path.contains("/namespaces/"),
+    };
+    !has_selector && in_namespace
+}
+
+/// A copy of the list request, fetching at most limit items, starting from where the list with
+/// continue_token left off. A limit of 0 means everything at once.
+fn list_page_request(
+    request: &Request<Vec<u8>>,
+    limit: u32,
+    continue_token: Option<&str>,
+) -> Result<Request<Vec<u8>>, ClickError> {
+    let mut uri = request.uri().to_string();
+    let mut params = url::form_urlencoded::Serializer::new(String::new());
+    if limit > 0 {
+        params.append_pair("limit", &limit.to_string());
+    }
+    if let Some(token) = continue_token {
+        params.append_pair("continue", token);
+    }
+    let params = params.finish();
+    if !params.is_empty() {
+        if !uri.contains('?') {
+            uri.push('?');
+        } else if !uri.ends_with('?') && !uri.ends_with('&') {
+            uri.push('&');
+        }
+        uri.push_str(&params);
+    }
+    copy_request(request, uri)
+}
+
+/// A copy of request, sent to uri instead
+fn copy_request(request: &Request<Vec<u8>>, uri: String) -> Result<Request<Vec<u8>>, ClickError> {
+    let mut builder = Request::builder().method(request.method().clone()).uri(uri);
+    if let Some(headers) = builder.headers_mut() {
+        headers.extend(request.headers().clone());
+    }
+    builder
+        .body(request.body().clone())
+        .map_err(|e| RequestError::Http(e).into())
+}
+
+/// A copy of the request to list T, listing from namespace, with the same query parameters
+fn namespaced_list_request<T: ListableResource>(
+    request: &Request<Vec<u8>>,
+    namespace: &str,
+) -> Result<Request<Vec<u8>>, ClickError> {
+    // type_name is the only way to compare the scope without requiring it to be 'static
+    if std::any::type_name::<T::Scope>() != std::any::type_name::<NamespaceResourceScope>() {
+        return Err(ClickError::CommandError(format!(
+            "{} aren't namespaced, so can't be listed from namespaces",
+            T::URL_PATH_SEGMENT
+        )));
+    }
+    let mut uri = if T::GROUP.is_empty() {
+        format!("/api/{}", T::VERSION)
+    } else {
+        format!("/apis/{}/{}", T::GROUP, T::VERSION)
+    };
+    uri.push_str(&format!(
+        "/namespaces/{}/{}",
+        namespace,
+        T::URL_PATH_SEGMENT
+    ));
+    if let Some(query) = request.uri().query() {
+        uri.push('?');
+        uri.push_str(query);
+    }
+    copy_request(request, uri)
+}
+
+/// One of the places a listing from several namespaces or contexts comes from
+struct ListTarget {
+    /// what to call the target in messages, i.e. "namespace kube-system in context prod"
+    label: String,
+    /// set for listings from several contexts, so the listed objects remember where they are
+    context_name: Option<String>,
+    /// None if the context couldn't be loaded, which is reported along with failed requests
+    context: Option<crate::k8s::Context>,
+    request: Request<Vec<u8>>,
+}
+
+/// The targets to list from: each of namespaces in each of contexts. No namespaces means the
+/// namespace the request is already for, and no contexts means the active context.
+fn list_targets<T: ListableResource>(
+    env: &Env,
+    request: &Request<Vec<u8>>,
+    namespaces: Option<Vec<&str>>,
+    contexts: Option<Vec<&str>>,
+) -> Result<Vec<ListTarget>, ClickError> {
+    let mut requests = vec![];
+    match namespaces {
+        Some(namespaces) => {
+            for ns in namespaces.into_iter() {
+                requests.push((Some(ns), namespaced_list_request::<T>(request, ns)?));
+            }
+        }
+        None => requests.push((None, copy_request(request, request.uri().to_string())?)),
+    }
+
+    let contexts: Vec<(Option<String>, Option<crate::k8s::Context>)> = match contexts {
+        Some(names) => names
+            .into_iter()
+            .map(|name| {
+                let name = env.resolve_context_alias(name).unwrap_or(name);
+                let context = match env.context.as_ref() {
+                    Some(active) if active.name == name => Some(active.clone()),
+                    _ => env.config.get_context(name, &env.click_config).ok(),
+                };
+                (Some(name.to_string()), context)
+            })
+            .collect(),
+        None => {
+            let active = env
+                .context
+                .as_ref()
+                .ok_or_else(|| ClickError::CommandError("No active context".to_string()))?;
+            vec![(None, Some(active.clone()))]
+        }
+    };
+
+    let mut targets = vec![];
+    for (context_name, context) in contexts.into_iter() {
+        for (ns, request) in requests.iter() {
+            let label = match (ns, context_name.as_ref()) {
+                (Some(ns), Some(context)) => format!("namespace {} in context {}", ns, context),
+                (Some(ns), None) => format!("namespace {}", ns),
+                (None, Some(context)) => format!("context {}", context),
+                (None, None) => "the current namespace".to_string(),
+            };
+            targets.push(ListTarget {
+                label,
+                context_name: context_name.clone(),
+                context: context.clone(),
+                request: copy_request(request, request.uri().to_string())?,
+            });
+        }
+    }
+    Ok(targets)
+}
+
+/// Fetch everything request lists, a page at a time like `run_filtered_list_command`, but without
+/// printing anything, so it can run on another thread. Returns the items, and if the list had to
+/// be restarted because the continue token expired.
+fn fetch_list<T>(
+    context: &crate::k8s::Context,
+    request: &Request<Vec<u8>>,
+    page_size: u32,
+) -> Result<(Vec<T>, bool), ClickError>
+where
+    T: ListableResource + for<'de> Deserialize<'de> + Debug + 'static,
+{
+    let mut items = vec![];
+    let mut restarted = false;
+    let mut continue_token: Option<String> = None;
+    loop {
+        let page_request = list_page_request(request, page_size, continue_token.as_deref())?;
+        let mut page = match context.execute_list::<T>(page_request) {
+            Ok(res) => res,
+            Err(ClickError::Api { code: 410, .. }) if continue_token.is_some() => {
+                continue_token = None;
+                items.clear();
+                restarted = true;
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        continue_token = page.metadata.continue_.take().filter(|t| !t.is_empty());
+        items.append(&mut page.items);
+        if continue_token.is_none() {
+            return Ok((items, restarted));
+        }
+    }
+}
+
+/// Print what each of the targets lists as json or yaml. The api server's response is printed
+/// unchanged, so it's fetched in one request rather than in pages, and the filtering and sorting
+/// options for tables don't apply. Listing from several targets prints one response for each.
+fn print_raw_lists<T, F>(
+    env: &mut Env,
+    writer: &mut ClickWriter,
+    targets: Vec<ListTarget>,
+    get_kobj: F,
+) -> Result<(), ClickError>
+where
+    T: ListableResource + Metadata<Ty = ObjectMeta> + for<'de> Deserialize<'de> + Debug + Send,
+    F: Fn(&T) -> KObj,
+{
+    let results = crate::parallel::run_bounded(
+        targets,
+        env.click_config.max_parallel_requests as usize,
+        |target| {
+            let result = match target.context.as_ref() {
+                Some(context) => list_page_request(&target.request, 0, None)
+                    .and_then(|request| context.execute_list_with_body::<T>(request)),
+                None => Err(ClickError::CommandError(
+                    "Couldn't load the context".to_string(),
+                )),
+            };
+            (target.label, target.context_name, result)
+        },
+    );
+
+    let mut kobjs = vec![];
+    let mut printed = 0;
+    let mut failures = vec![];
+    for (label, context_name, result) in results.into_iter() {
+        match result {
+            Ok((list, body)) => {
+                writer.print_raw_body(&body)?;
+                printed += 1;
+                kobjs.extend(list.items.iter().map(|item| {
+                    let mut kobj = get_kobj(item);
+                    kobj.context = context_name.clone();
+                    kobj
+                }));
+            }
+            Err(e) => failures.push((label, e)),
+        }
+    }
+    if printed == 0 {
+        env.clear_last_objs();
+        return match failures.len() {
+            1 => Err(failures.pop().unwrap().1),
+            _ => Err(ClickError::MultiError(failures)),
+        };
+    }
+    env.set_last_objs(kobjs);
+
+    // on stderr, so what was printed is still valid json or yaml
+    for (label, err) in failures.iter() {
+        writeln!(stderr(), "Couldn't list from {}: {}", label, err).unwrap_or(());
+    }
+    Ok(())
+}
+
+/// How to show a listing from several namespaces or contexts, which is fetched in parallel and
+/// merged into one table
+struct MultiList<'a, T> {
+    cols: Vec<&'a str>,
+    extractors: Option<&'a HashMap<String, Extractor<T>>>,
+    regex: Option<Regex>,
+    sort: Option<command_def::SortCol>,
+    reverse: bool,
+}
+
+impl<'a, T> MultiList<'a, T>
+where
+    T: ListableResource
+        + Metadata<Ty = ObjectMeta>
+        + for<'de> Deserialize<'de>
+        + Debug
+        + Send
+        + 'static,
+{
+    fn run<F, P>(
+        self,
+        env: &mut Env,
+        writer: &mut ClickWriter,
+        targets: Vec<ListTarget>,
+        get_kobj: F,
+        keep: P,
+    ) -> Result<(), ClickError>
+    where
+        F: Fn(&T) -> KObj,
+        P: Fn(&T) -> bool,
+    {
+        let page_size = env.click_config.list_page_size;
+        let show_context = targets.iter().any(|t| t.context_name.is_some());
+        let results = crate::parallel::run_bounded(
+            targets,
+            env.click_config.max_parallel_requests as usize,
+            |target| {
+                let result = match target.context.as_ref() {
+                    Some(context) => fetch_list::<T>(context, &target.request, page_size),
+                    None => Err(ClickError::CommandError(
+                        "Couldn't load the context".to_string(),
+                    )),
+                };
+                (target.label, target.context_name, result)
+            },
+        );
+
+        let mut groups = vec![];
+        let mut failures = vec![];
+        let mut restarted = vec![];
+        for (label, context_name, result) in results.into_iter() {
+            match result {
+                Ok((mut items, was_restarted)) => {
+                    items.retain(|item| keep(item));
+                    let list = List {
+                        items,
+                        metadata: Default::default(),
+                    };
+                    groups.push((context_name, list));
+                    if was_restarted {
+                        restarted.push(label);
+                    }
+                }
+                Err(e) => failures.push((label, e)),
+            }
+        }
+        if groups.is_empty() {
+            env.clear_last_objs();
+            return Err(ClickError::MultiError(failures));
+        }
+
+        let mut specs = vec![];
+        for (context_name, list) in groups.iter() {
+            let group_specs = build_specs(
+                &self.cols,
+                list,
+                self.extractors,
+                true,
+                self.regex.clone(),
+                |item| {
+                    let mut kobj = get_kobj(item);
+                    kobj.context = context_name.clone();
+                    kobj
+                },
+            );
+            for (kobj, mut row) in group_specs.into_iter() {
+                if show_context {
+                    row.insert(1, context_name.clone().into()); // after the #### col
+                }
+                specs.push((kobj, row));
+            }
+        }
+        let mut cols = self.cols.clone();
+        if show_context {
+            cols.insert(0, "Context");
+        }
+        finish_list(env, writer, &cols, specs, self.sort, self.reverse)?;
+
+        for label in restarted.iter() {
+            clickwriteln!(
+                writer,
+                "{}",
+                theme::style(Role::Warning).paint(format!(
+                    "Note: the list from {} changed too much while it was being fetched, so it \
+                     was listed again from the start",
+                    label
+                ))
+            );
+        }
+        for (label, err) in failures.iter() {
+            clickwriteln!(
+                writer,
+                "{}",
+                theme::style(Role::Warning).paint(format!("Couldn't list from {}: {}", label, err))
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Resolve a possibly partial name typed by the user against candidates. An exact match, or a
+/// single fuzzy match, is returned. If nothing matches the query is returned unchanged, so the
+/// caller can handle it as it would without fuzzy matching. If there are multiple matches, they
+/// are printed and None is returned.
+pub fn resolve_fuzzy<'a, I>(
+    query: &'a str,
+    candidates: I,
+    kind: &str,
+    writer: &mut ClickWriter,
+) -> Option<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    match crate::fuzzy::fuzzy_match(query, candidates) {
+        FuzzyMatch::Exact(name) => Some(name),
+        FuzzyMatch::Single(name) => {
+            clickwriteln!(writer, "Matched {} {}", kind, name);
+            Some(name)
+        }
+        FuzzyMatch::Multiple(names) => {
+            clickwriteln!(writer, "Multiple {}s match '{}':", kind, query);
+            for name in names.iter() {
+                clickwriteln!(writer, "  {}", name);
+            }
+            None
+        }
+        FuzzyMatch::None => Some(query),
+    }
 }
 
 /// Uppercase the first letter of the given str
@@ -210,13 +794,21 @@ where
     T: 'a + ListableResource + Metadata<Ty = ObjectMeta>,
     F: Fn(&T) -> KObj,
 {
-    let mut specs = build_specs(&cols, &list, extractors, true, regex, get_kobj);
+    let specs = build_specs(&cols, &list, extractors, true, regex, get_kobj);
+    finish_list(env, writer, &cols, specs, sort, reverse)
+}
 
-    let mut titles: Vec<Cell> = vec![Cell::new("####")];
-    titles.reserve(cols.len());
-    for col in cols.iter() {
-        titles.push(Cell::new(col));
-    }
+/// The rest of `handle_list_result`, once the row specs are built: sort them, print them, and set
+/// the listed objects as the last list in the env
+fn finish_list(
+    env: &mut Env,
+    writer: &mut ClickWriter,
+    cols: &[&str],
+    mut specs: Vec<(KObj, RowSpec)>,
+    sort: Option<command_def::SortCol>,
+    reverse: bool,
+) -> Result<(), ClickError> {
+    let titles = list_titles(cols);
 
     if let Some(command_def::SortCol(colname)) = sort {
         let index = cols.iter().position(|&c| c == colname);
@@ -239,17 +831,28 @@ where
         specs.into_iter().unzip()
     };
 
-    crate::table::print_table(Row::new(titles), rows, writer);
+    crate::table::print_table(titles, rows, writer);
     env.set_last_objs(kobjs);
     Ok(())
 }
 
+/// The title row of a list table, with the #### column for the index
+fn list_titles(cols: &[&str]) -> Row {
+    let mut titles: Vec<Cell> = vec![Cell::new("####")];
+    titles.reserve(cols.len());
+    for col in cols.iter() {
+        titles.push(Cell::new(col));
+    }
+    Row::new(titles)
+}
+
 // row building
 
 /* Build row specs and a kobj vec from data returned from k8s.
  *
  * cols is a list of names of columns to build. "Name" * and "Age" are handled, other names need to
- * be in 'extractors', and the extractor for the specified name will be used.
+ * be in 'extractors', and the extractor for the specified name will be used. If 'extractors' has an
+ * entry for one of the handled names, it is used instead.
  *
  * include_index = true will put an index (numbered) column as the first item in the row
  *
@@ -281,6 +884,11 @@ where
             vec![]
         };
         for col in cols.iter() {
+            // an extractor can override the standard handling of a column
+            if let Some(extractor) = extractors.and_then(|extractors| extractors.get(*col)) {
+                row.push(extractor(item).into());
+                continue;
+            }
             match *col {
                 "Age" => row.push(extract_age(item).into()),
                 "Labels" => row.push(extract_labels(item).into()),
@@ -392,6 +1000,46 @@ pub fn keyval_string(keyvals: &BTreeMap<String, String>) -> String {
     buf
 }
 
+// label selector utils
+
+/// Check if the given labels are selected by selector. An empty selector selects everything.
+pub fn selector_matches(selector: &LabelSelector, labels: &BTreeMap<String, String>) -> bool {
+    let labels_match = selector
+        .match_labels
+        .iter()
+        .all(|(key, val)| labels.get(key) == Some(val));
+    labels_match
+        && selector
+            .match_expressions
+            .iter()
+            .all(|expr| match expr.operator.as_str() {
+                "In" => matches!(labels.get(&expr.key), Some(v) if expr.values.contains(v)),
+                "NotIn" => !matches!(labels.get(&expr.key), Some(v) if expr.values.contains(v)),
+                "Exists" => labels.contains_key(&expr.key),
+                "DoesNotExist" => !labels.contains_key(&expr.key),
+                _ => false,
+            })
+}
+
+/// Format a selector in the string form the api server accepts for a label_selector query
+pub fn selector_string(selector: &LabelSelector) -> String {
+    let mut parts: Vec<String> = selector
+        .match_labels
+        .iter()
+        .map(|(key, val)| format!("{}={}", key, val))
+        .collect();
+    for expr in selector.match_expressions.iter() {
+        match expr.operator.as_str() {
+            "In" => parts.push(format!("{} in ({})", expr.key, expr.values.join(","))),
+            "NotIn" => parts.push(format!("{} notin ({})", expr.key, expr.values.join(","))),
+            "Exists" => parts.push(expr.key.clone()),
+            "DoesNotExist" => parts.push(format!("!{}", expr.key)),
+            _ => {}
+        }
+    }
+    parts.join(",")
+}
+
 // utils for getting custom requests
 
 /// Get a read request for a custom url
@@ -433,3 +1081,306 @@ pub fn get_list_request_for_url<T: ListableResource + for<'de> serde::Deserializ
         Err(err) => Err(RequestError::Http(err)),
     }
 }
+
+// utils for running kubectl
+
+/// Get a kubectl Command that targets the active context, and the specified namespace if one is
+/// given. It gives up waiting for the server after the --timeout or default_timeout, if set.
+pub fn kubectl_command(env: &Env, namespace: Option<&str>) -> Result<Command, ClickError> {
+    let mut command = kubectl_streaming_command(env, namespace)?;
+    if let Some(timeout) = env.kubectl_timeout() {
+        command.arg("--request-timeout").arg(timeout);
+    }
+    Ok(command)
+}
+
+/// Like `kubectl_command`, but without a request timeout, for kubectl commands that stay
+/// connected as long as they run, like attaching to a container or waiting on a watch
+pub fn kubectl_streaming_command(
+    env: &Env,
+    namespace: Option<&str>,
+) -> Result<Command, ClickError> {
+    let context = env.context.as_ref().ok_or_else(|| {
+        ClickError::CommandError("Need an active context in order to run kubectl".to_string())
+    })?;
+    let mut command = Command::new("kubectl");
+    if let Some(kubeconfig) = env.command_kubeconfig.as_ref() {
+        command.arg("--kubeconfig").arg(kubeconfig);
+    }
+    if let Some(ns) = namespace {
+        command.arg("--namespace").arg(ns);
+    }
+    command.arg("--context").arg(&context.name);
+    if let Some(imp) = env.impersonating() {
+        command.arg("--as").arg(&imp.user);
+        for group in imp.groups.iter() {
+            command.arg("--as-group").arg(group);
+        }
+    }
+    Ok(command)
+}
+
+/// Get a kubectl Command to run against obj. This targets obj's namespace if it's namespaced.
+pub fn kubectl_obj_command(env: &Env, obj: &KObj) -> Result<Command, ClickError> {
+    let namespace = if obj.is_namespaced() {
+        obj.namespace.as_deref()
+    } else {
+        None
+    };
+    kubectl_command(env, namespace)
+}
+
+/// The argument to pass kubectl to refer to obj, i.e. deployment/foo
+pub fn kubectl_obj_arg(obj: &KObj) -> String {
+    match &obj.typ {
+        // custom resources need the group too, as the same name can be in more than one
+        ObjType::Crd {
+            _type,
+            group_version,
+        } => match group_version.split_once('/') {
+            Some((group, _)) => format!("{}.{}/{}", _type, group, obj.name()),
+            None => format!("{}/{}", _type, obj.name()),
+        },
+        _ => format!("{}/{}", obj.type_str().to_lowercase(), obj.name()),
+    }
+}
+
+/// Fetch the current version of obj as json, using kubectl get
+pub fn kubectl_get_json(env: &Env, obj: &KObj) -> Result<serde_json::Value, ClickError> {
+    let mut command = kubectl_obj_command(env, obj)?;
+    command
+        .arg("get")
+        .arg(kubectl_obj_arg(obj))
+        .arg("-o")
+        .arg("json");
+    Ok(serde_json::from_str(&kubectl_output(command)?)?)
+}
+
+/// Remove metadata.managedFields from the json for an object. It's noise when showing an object to
+/// a user.
+pub fn strip_managed_fields(value: &mut serde_json::Value) {
+    if let Some(meta) = value.get_mut("metadata").and_then(|m| m.as_object_mut()) {
+        meta.remove("managedFields");
+    }
+}
+
+/// Ask the user to confirm something, like a destructive operation. Returns true only if they
+/// answer y or yes.
+pub fn confirm_action(writer: &mut ClickWriter, prompt: &str) -> bool {
+    writer.finish_paging();
+    clickwrite!(writer, "{} [y/N]? ", prompt);
+    io::stdout().flush().expect("Could not flush stdout");
+    let mut conf = String::new();
+    io::stdin().read_line(&mut conf).is_ok() && (conf.trim() == "y" || conf.trim() == "yes")
+}
+
+/// Sleep for duration, waking up early if the user hits ctrl-c. Returns false if they did.
+pub fn interruptible_sleep(env: &Env, duration: std::time::Duration) -> bool {
+    let step = std::time::Duration::from_millis(100);
+    let mut slept = std::time::Duration::from_secs(0);
+    while slept < duration {
+        if env.ctrlcbool.load(Ordering::SeqCst) {
+            return false;
+        }
+        std::thread::sleep(step);
+        slept += step;
+    }
+    !env.ctrlcbool.load(Ordering::SeqCst)
+}
+
+/// Figure out which editor to use: editor_opt if given, then the editor setting, then $EDITOR
+pub fn get_editor(env: &Env, editor_opt: Option<&str>) -> Result<String, ClickError> {
+    if let Some(v) = editor_opt {
+        Ok(v.to_owned())
+    } else if let Some(ref e) = env.click_config.editor {
+        Ok(e.clone())
+    } else {
+        std::env::var("EDITOR").map_err(|e| {
+            ClickError::CommandError(format!("Could not get EDITOR environment variable: {}", e))
+        })
+    }
+}
+
+/// An expression that runs editor on path. Editor can include arguments, i.e. "emacs -nw"
+pub fn editor_expression(editor: &str, path: &Path) -> duct::Expression {
+    if editor.contains(' ') {
+        // split the whitespace
+        let mut eargs: Vec<&str> = editor.split_whitespace().collect();
+        eargs.push(path.to_str().unwrap());
+        duct::cmd(eargs[0], &eargs[1..])
+    } else {
+        cmd!(editor, path)
+    }
+}
+
+/// Map an error from spawning kubectl into a ClickError
+pub fn kubectl_spawn_error(e: io::Error) -> ClickError {
+    if let io::ErrorKind::NotFound = e.kind() {
+        ClickError::CommandError("Could not find kubectl binary. Is it in your PATH?".to_string())
+    } else {
+        ClickError::Io(e)
+    }
+}
+
+/// Build the error for another tool, like helm, that couldn't be started. If it's not installed,
+/// the error says where to get it from.
+pub fn tool_spawn_error(tool: &str, url: &str, e: io::Error) -> ClickError {
+    if let io::ErrorKind::NotFound = e.kind() {
+        ClickError::CommandError(format!(
+            "Could not find {} binary. Is it in your PATH? See {} for how to install it.",
+            tool, url
+        ))
+    } else {
+        ClickError::Io(e)
+    }
+}
+
+/// Build the error for another tool that exited unsuccessfully, including what it printed to
+/// stderr
+pub fn tool_exit_error(tool: &str, status: ExitStatus, stderr: &[u8]) -> ClickError {
+    let stderr = String::from_utf8_lossy(stderr);
+    if stderr.trim().is_empty() {
+        ClickError::CommandError(format!(
+            "{} exited with code {}",
+            tool,
+            status.code().unwrap_or(-1)
+        ))
+    } else {
+        ClickError::CommandError(format!(
+            "{} exited with code {}: {}",
+            tool,
+            status.code().unwrap_or(-1),
+            stderr.trim()
+        ))
+    }
+}
+
+/// Build the error for a kubectl that exited unsuccessfully. If kubectl was killed by a signal
+/// there's no exit code, so -1 is used.
+pub fn kubectl_exit_error(status: ExitStatus, stderr: &[u8]) -> ClickError {
+    ClickError::KubectlError {
+        exit_code: status.code().unwrap_or(-1),
+        stderr: String::from_utf8_lossy(stderr).trim().to_string(),
+    }
+}
+
+/// Run the specified kubectl command, capturing its output. Returns stdout if kubectl exited
+/// successfully, or an error including whatever kubectl printed to stderr otherwise.
+pub fn kubectl_output(mut command: Command) -> Result<String, ClickError> {
+    let output = command.output().map_err(kubectl_spawn_error)?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(kubectl_exit_error(output.status, &output.stderr))
+    }
+}
+
+/// Like kubectl_output, but writes input to kubectl's stdin, for commands like `apply -f -`
+pub fn kubectl_output_with_input(mut command: Command, input: &[u8]) -> Result<String, ClickError> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(kubectl_spawn_error)?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input)?;
+    }
+    let output = child.wait_with_output()?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(kubectl_exit_error(output.status, &output.stderr))
+    }
+}
+
+/// Quote arg so it can be pasted into a shell, if it needs quoting
+pub fn shell_quote(arg: &str) -> Cow<'_, str> {
+    let safe = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=,@%+".contains(c));
+    if safe {
+        arg.into()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''")).into()
+    }
+}
+
+/// The command line that command runs, as it would be typed into a shell
+pub fn command_line(command: &Command) -> String {
+    let mut parts = vec![shell_quote(&command.get_program().to_string_lossy()).into_owned()];
+    parts.extend(
+        command
+            .get_args()
+            .map(|arg| shell_quote(&arg.to_string_lossy()).into_owned()),
+    );
+    parts.join(" ")
+}
+
+/// If dry-run is on, print the api request that would have been sent and return true. Commands
+/// that change things through the api call this before sending the request.
+pub fn dry_run_request(env: &Env, request: &Request<Vec<u8>>, writer: &mut ClickWriter) -> bool {
+    if !env.dry_run {
+        return false;
+    }
+    let uri = request.uri().to_string();
+    clickwriteln!(
+        writer,
+        "[DRY RUN] {} {}",
+        request.method(),
+        uri.trim_end_matches('?')
+    );
+    if !request.body().is_empty() {
+        clickwriteln!(writer, "  {}", String::from_utf8_lossy(request.body()));
+    }
+    true
+}
+
+/// Run a kubectl command that changes something, like kubectl_output. If dry-run is on, the
+/// command is printed instead of being run, and this returns None.
+pub fn kubectl_mutate(
+    env: &Env,
+    command: Command,
+    writer: &mut ClickWriter,
+) -> Result<Option<String>, ClickError> {
+    if env.dry_run {
+        clickwriteln!(writer, "[DRY RUN] {}", command_line(&command));
+        Ok(None)
+    } else {
+        kubectl_output(command).map(Some)
+    }
+}
+
+/// Run the specified kubectl command, writing its output to writer line by line as it's
+/// produced. Use this for long running commands like `rollout status`.
+pub fn kubectl_stream(mut command: Command, writer: &mut ClickWriter) -> Result<(), ClickError> {
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(kubectl_spawn_error)?;
+    // read stderr on its own thread, so kubectl can't block writing to it while we wait for
+    // stdout to end
+    let stderr = child.stderr.take().map(|mut stderr| {
+        std::thread::spawn(move || {
+            let mut buf = vec![];
+            stderr.read_to_end(&mut buf).map(|_| buf)
+        })
+    });
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).lines() {
+            clickwriteln!(writer, "{}", line?);
+        }
+    }
+    let status = child.wait()?;
+    let stderr = match stderr {
+        Some(handle) => handle.join().unwrap_or_else(|_| Ok(vec![]))?,
+        None => vec![],
+    };
+    if status.success() {
+        Ok(())
+    } else {
+        Err(kubectl_exit_error(status, &stderr))
+    }
+}
@@ -12,14 +12,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use ansi_term::Colour::Yellow;
 use clap::{App, Arg};
 use k8s_openapi::api::core::v1 as api;
 use rustyline::completion::Pair as RustlinePair;
 
 use crate::{
     command::command_def::{exec_match, sort_arg, start_clap, Cmd},
-    command::{run_list_command, Extractor},
+    command::{resolve_fuzzy, run_list_command, Extractor},
     completer,
     env::Env,
     kobj::{KObj, ObjType},
@@ -46,7 +45,8 @@ const COL_FLAGS: &[&str] = &{ extract_first!(COL_MAP) };
 command!(
     Namespace,
     "namespace",
-    "Set the current namespace (no argument to clear namespace)",
+    "Set the current namespace (no argument to clear namespace). A partial name will switch to \
+     the matching namespace if only one matches.",
     |clap: App<'static, 'static>| clap.arg(
         Arg::with_name("namespace")
             .help("The namespace to use")
@@ -56,9 +56,22 @@ command!(
     vec!["ns", "namespace"],
     vec![&completer::namespace_completer],
     no_named_complete!(),
-    |matches, env, _| {
-        let ns = matches.value_of("namespace");
-        env.set_namespace(ns);
+    |matches, env, writer| {
+        match matches.value_of("namespace") {
+            Some(typed) => {
+                let names = completer::get_namespace_names(env).unwrap_or_default();
+                if let Some(ns) =
+                    resolve_fuzzy(typed, names.iter().map(|n| n.as_str()), "namespace", writer)
+                {
+                    env.set_namespace(Some(ns));
+                    env.remember_namespace();
+                }
+            }
+            None => {
+                env.set_namespace(None);
+                env.remember_namespace();
+            }
+        }
         Ok(())
     }
 );
@@ -71,6 +84,8 @@ fn namespace_to_kobj(namespace: &api::Namespace) -> KObj {
             .clone()
             .unwrap_or_else(|| "<Unknown>".into()),
         namespace: None,
+        uid: namespace.metadata.uid.clone(),
+        context: None,
         typ: ObjType::Namespace,
     }
 }
@@ -0,0 +1,160 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use clap::App;
+use rustyline::completion::Pair as RustlinePair;
+use serde_json::Value;
+
+use crate::{
+    command::command_def::{exec_match, start_clap, Cmd},
+    command::{kubectl_command, kubectl_get_json, kubectl_output},
+    completer,
+    env::{Env, ObjectSelection},
+    error::ClickError,
+    kobj::{KObj, ObjType},
+    output::ClickWriter,
+    values::val_str_opt,
+};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+
+/// How far up ownerReferences owner will go, in case they loop
+const MAX_OWNER_DEPTH: usize = 10;
+
+/// The single selected object, for commands that jump from it to something else
+fn selected_obj(env: &Env) -> Result<KObj, ClickError> {
+    match env.current_selection() {
+        ObjectSelection::Single(obj) => Ok(obj.clone()),
+        ObjectSelection::Range(_) => Err(ClickError::CommandError(
+            "Select one object, not a range".to_string(),
+        )),
+        ObjectSelection::None => Err(ClickError::CommandError(
+            "No active object selected".to_string(),
+        )),
+    }
+}
+
+/// The owner reference of an object that's its controller, or the first one if none are marked
+/// as the controller
+fn controller_ref(value: &Value) -> Option<&Value> {
+    let owners = value.pointer("/metadata/ownerReferences")?.as_array()?;
+    owners
+        .iter()
+        .find(|owner| owner.get("controller").and_then(|c| c.as_bool()) == Some(true))
+        .or_else(|| owners.first())
+}
+
+/// The argument to pass kubectl to get the object owner refers to, i.e. replicaset.apps/web-123
+fn owner_arg(owner: &Value) -> Option<String> {
+    let kind = val_str_opt("/kind", owner)?.to_lowercase();
+    let name = val_str_opt("/name", owner)?;
+    let api_version = val_str_opt("/apiVersion", owner).unwrap_or_default();
+    Some(match api_version.split_once('/') {
+        Some((group, _)) => format!("{}.{}/{}", kind, group, name),
+        None => format!("{}/{}", kind, name),
+    })
+}
+
+/// Fetch the object owner refers to. Owners are in the same namespace as what they own, or
+/// cluster scoped.
+fn get_owner(env: &Env, namespace: Option<&str>, owner: &Value) -> Result<Value, ClickError> {
+    let arg = owner_arg(owner)
+        .ok_or_else(|| ClickError::ParseErr("Owner reference has no kind or name".to_string()))?;
+    let mut command = kubectl_command(env, namespace)?;
+    command.arg("get").arg(arg).arg("-o").arg("json");
+    Ok(serde_json::from_str(&kubectl_output(command)?)?)
+}
+
+fn obj_str(obj: &KObj) -> String {
+    format!("{} {}", obj.type_str(), obj.name())
+}
+
+command!(
+    Node,
+    "node",
+    "Select the node the active pod is scheduled on",
+    |clap: App<'static, 'static>| clap,
+    vec!["node"],
+    noop_complete!(),
+    no_named_complete!(),
+    |_matches, env, writer| {
+        let pod = selected_obj(env)?;
+        if !pod.is_pod() {
+            return Err(ClickError::CommandError(format!(
+                "{} is not a pod",
+                obj_str(&pod)
+            )));
+        }
+        let value = kubectl_get_json(env, &pod)?;
+        let node = val_str_opt("/spec/nodeName", &value).ok_or_else(|| {
+            ClickError::CommandError(format!(
+                "Pod {} hasn't been scheduled to a node",
+                pod.name()
+            ))
+        })?;
+        clickwriteln!(writer, "Pod {} is on node {}", pod.name(), node);
+        env.select_obj(KObj {
+            name: node,
+            namespace: None,
+            uid: None,
+            context: pod.context.clone(),
+            typ: ObjType::Node,
+        });
+        Ok(())
+    }
+);
+
+command!(
+    Owner,
+    "owner",
+    "Select the controller at the top of the active object's owners, like the deployment that \
+     owns a pod's replicaset, printing each owner on the way up",
+    |clap: App<'static, 'static>| clap,
+    vec!["owner"],
+    noop_complete!(),
+    no_named_complete!(),
+    |_matches, env, writer| {
+        let start = selected_obj(env)?;
+        let mut value = kubectl_get_json(env, &start)?;
+        let mut chain = vec![obj_str(&start)];
+        let mut top = None;
+        while let Some(owner) = controller_ref(&value) {
+            if chain.len() > MAX_OWNER_DEPTH {
+                return Err(ClickError::CommandError(format!(
+                    "Gave up after {} owners, they might refer to each other",
+                    MAX_OWNER_DEPTH
+                )));
+            }
+            let owner_value = get_owner(env, start.namespace.as_deref(), owner)?;
+            let mut obj = KObj::from_json(&owner_value)?;
+            obj.context = start.context.clone();
+            chain.push(obj_str(&obj));
+            top = Some(obj);
+            value = owner_value;
+        }
+        match top {
+            Some(obj) => {
+                clickwriteln!(writer, "{}", chain.join(" -> "));
+                env.select_obj(obj);
+                Ok(())
+            }
+            None => Err(ClickError::CommandError(format!(
+                "{} has no owner",
+                obj_str(&start)
+            ))),
+        }
+    }
+);
@@ -0,0 +1,247 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use clap::App;
+use k8s_openapi::api::core::v1 as api;
+use k8s_openapi::api::networking::v1 as net_api;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelector;
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+use k8s_openapi::List;
+use prettytable::Table;
+use rustyline::completion::Pair as RustlinePair;
+
+use crate::{
+    command::command_def::{exec_match, identity, start_clap, Cmd},
+    command::{selector_matches, selector_string},
+    completer,
+    env::{Env, ErrorStrategy, ObjectSelection},
+    error::ClickError,
+    kobj::KObj,
+    output::ClickWriter,
+};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+
+fn describe_selector(selector: &LabelSelector) -> String {
+    let sel = selector_string(selector);
+    if sel.is_empty() {
+        "all".to_string()
+    } else {
+        sel
+    }
+}
+
+fn describe_peer(peer: &net_api::NetworkPolicyPeer) -> String {
+    if let Some(ip_block) = peer.ip_block.as_ref() {
+        if ip_block.except.is_empty() {
+            ip_block.cidr.clone()
+        } else {
+            format!("{} except {}", ip_block.cidr, ip_block.except.join(", "))
+        }
+    } else {
+        match (peer.pod_selector.as_ref(), peer.namespace_selector.as_ref()) {
+            (Some(pods), Some(namespaces)) => format!(
+                "pods: {} in namespaces: {}",
+                describe_selector(pods),
+                describe_selector(namespaces)
+            ),
+            (Some(pods), None) => format!("pods: {}", describe_selector(pods)),
+            (None, Some(namespaces)) => format!("namespaces: {}", describe_selector(namespaces)),
+            (None, None) => "<unknown peer>".to_string(),
+        }
+    }
+}
+
+fn describe_peers(peers: &[net_api::NetworkPolicyPeer]) -> String {
+    if peers.is_empty() {
+        "anywhere".to_string()
+    } else {
+        let descs: Vec<String> = peers.iter().map(describe_peer).collect();
+        descs.join("\n")
+    }
+}
+
+fn describe_ports(ports: &[net_api::NetworkPolicyPort]) -> String {
+    if ports.is_empty() {
+        "all".to_string()
+    } else {
+        let descs: Vec<String> = ports
+            .iter()
+            .map(|port| {
+                let proto = port.protocol.as_deref().unwrap_or("TCP");
+                match port.port.as_ref() {
+                    Some(IntOrString::Int(p)) => format!("{}/{}", proto, p),
+                    Some(IntOrString::String(p)) => format!("{}/{}", proto, p),
+                    None => format!("{}/all", proto),
+                }
+            })
+            .collect();
+        descs.join(", ")
+    }
+}
+
+/// Kubernetes assumes Ingress if policyTypes isn't set, and Egress if there are any egress rules
+fn policy_types(spec: &net_api::NetworkPolicySpec) -> (bool, bool) {
+    if spec.policy_types.is_empty() {
+        (true, !spec.egress.is_empty())
+    } else {
+        (
+            spec.policy_types.iter().any(|t| t == "Ingress"),
+            spec.policy_types.iter().any(|t| t == "Egress"),
+        )
+    }
+}
+
+fn get_pod_labels(
+    env: &Env,
+    pod: &KObj,
+    ns: &str,
+) -> Result<std::collections::BTreeMap<String, String>, ClickError> {
+    let (request, _) = api::Pod::read_namespaced_pod(pod.name(), ns, Default::default())?;
+    match env.run_on_context(|c| c.read::<api::ReadNamespacedPodResponse>(request))? {
+        api::ReadNamespacedPodResponse::Ok(pod) => Ok(pod.metadata.labels),
+        _ => Err(ClickError::CommandError(format!(
+            "Could not read pod {}",
+            pod.name()
+        ))),
+    }
+}
+
+/// Print the NetworkPolicies that select pod, with what traffic each of them allows. The
+/// policies in each namespace are kept in fetched, so a range of pods only lists them once.
+fn show_pod_policies(
+    env: &Env,
+    pod: &KObj,
+    fetched: &mut HashMap<String, List<net_api::NetworkPolicy>>,
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    let ns = pod.namespace.as_ref().unwrap(); // pods are always namespaced
+    let labels = get_pod_labels(env, pod, ns)?;
+
+    if !fetched.contains_key(ns) {
+        let (request, _) =
+            net_api::NetworkPolicy::list_namespaced_network_policy(ns, Default::default())?;
+        let policies = env.run_on_context(|c| c.execute_list(request))?;
+        fetched.insert(ns.clone(), policies);
+    }
+    let policies = &fetched[ns];
+
+    let mut table = Table::new();
+    table.set_titles(row!["Policy", "Direction", "From/To", "Ports"]);
+    let mut select_all = vec![];
+    let mut applied = 0;
+    for policy in policies.items.iter() {
+        let spec = match policy.spec.as_ref() {
+            Some(spec) => spec,
+            None => continue,
+        };
+        if !selector_matches(&spec.pod_selector, &labels) {
+            continue;
+        }
+        applied += 1;
+        let name = policy.metadata.name.as_deref().unwrap_or("<Unknown>");
+        if spec.pod_selector.match_labels.is_empty()
+            && spec.pod_selector.match_expressions.is_empty()
+        {
+            select_all.push(name);
+        }
+        let (ingress, egress) = policy_types(spec);
+        if ingress {
+            if spec.ingress.is_empty() {
+                table.add_row(row![name, "Ingress", Fr->"<none, all denied>", ""]);
+            }
+            for rule in spec.ingress.iter() {
+                table.add_row(row![
+                    name,
+                    "Ingress",
+                    describe_peers(&rule.from),
+                    describe_ports(&rule.ports)
+                ]);
+            }
+        }
+        if egress {
+            if spec.egress.is_empty() {
+                table.add_row(row![name, "Egress", Fr->"<none, all denied>", ""]);
+            }
+            for rule in spec.egress.iter() {
+                table.add_row(row![
+                    name,
+                    "Egress",
+                    describe_peers(&rule.to),
+                    describe_ports(&rule.ports)
+                ]);
+            }
+        }
+    }
+
+    if applied == 0 {
+        clickwriteln!(
+            writer,
+            "No NetworkPolicies in {} select pod {}, all traffic is allowed",
+            ns,
+            pod.name()
+        );
+        return Ok(());
+    }
+    crate::table::print_filled_table(&mut table, writer);
+    if !select_all.is_empty() {
+        clickwriteln!(
+            writer,
+            "Note: {} {} an empty podSelector, and so {} to every pod in {}",
+            select_all.join(", "),
+            if select_all.len() == 1 { "has" } else { "have" },
+            if select_all.len() == 1 {
+                "applies"
+            } else {
+                "apply"
+            },
+            ns
+        );
+    }
+    Ok(())
+}
+
+command!(
+    NetworkPolicies,
+    "network-policies",
+    "Show the NetworkPolicies that apply to the active pod(s), and what traffic they allow",
+    identity,
+    vec!["network-policies", "networkpolicies", "netpol"],
+    noop_complete!(),
+    no_named_complete!(),
+    |_matches, env, writer| {
+        // ranges are checked one object at a time below
+        if !matches!(env.current_selection(), ObjectSelection::Range(_)) {
+            env.current_pod()?;
+        }
+        let mut fetched = HashMap::new();
+        env.apply_to_selection(
+            writer,
+            Some(&env.click_config.range_separator),
+            ErrorStrategy::CollectAll,
+            |obj, writer| {
+                if obj.is_pod() {
+                    show_pod_policies(env, obj, &mut fetched, writer)
+                } else {
+                    Err(ClickError::CommandError(format!(
+                        "{} is not a pod",
+                        obj.name()
+                    )))
+                }
+            },
+        )
+    }
+);
@@ -12,36 +12,65 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use ansi_term::Colour::Yellow;
 use clap::{App, Arg};
 use k8s_openapi::api::core::v1 as api;
+use k8s_openapi::api::policy::v1beta1 as policy_api;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{ObjectMeta, Patch};
+use k8s_openapi::http::StatusCode;
+use k8s_openapi::{List, ListOptional, PatchResponse};
 
 use crate::{
     command::command_def::{exec_match, show_arg, sort_arg, start_clap, Cmd},
-    command::{run_list_command, Extractor},
+    command::{
+        dry_run_request, interruptible_sleep, parse_duration, run_list_command, valid_duration,
+        Extractor,
+    },
     completer,
-    env::Env,
+    env::{Env, ErrorStrategy},
+    error::ClickError,
     kobj::{KObj, ObjType},
-    output::ClickWriter,
+    output::{ClickWriter, OutputFormat},
     table::CellSpec,
+    theme::Role,
+    values::val_str,
 };
 
-use std::array::IntoIter;
 use std::borrow::Cow;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::io::Write;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long to wait between retries of an eviction that a PodDisruptionBudget blocked, and
+/// between checks that evicted pods are gone
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Static pods show up in the api as mirror pods, which can't be evicted
+const MIRROR_ANNOTATION: &str = "kubernetes.io/config.mirror";
+
+/// Pods in these phases have stopped, so don't count against a node's capacity any more
+const TERMINATED_SELECTOR: &str = "status.phase!=Succeeded,status.phase!=Failed";
 
 lazy_static! {
+    /// The number of non-terminated pods on each node, fetched before a listing that shows the
+    /// Pods column, since extractors only get to see the node
+    static ref NODE_POD_COUNTS: Mutex<HashMap<String, usize>> = Mutex::new(HashMap::new());
+
     static ref NODE_EXTRACTORS: HashMap<String, Extractor<api::Node>> = {
         let mut m: HashMap<String, Extractor<api::Node>> = HashMap::new();
+        m.insert("Allocatable Cpu".to_owned(), node_allocatable_cpu);
+        m.insert("Allocatable Memory".to_owned(), node_allocatable_memory);
         m.insert("Container Runtime".to_owned(), node_container_runtime);
         m.insert("External Ip".to_owned(), node_external_ip);
         m.insert("Internal Ip".to_owned(), node_internal_ip);
         m.insert("Kernel Version".to_owned(), node_kernel_version);
         m.insert("Roles".to_owned(), node_roles);
         m.insert("Os Image".to_owned(), node_os_image);
+        m.insert("Pods".to_owned(), node_pod_count);
         m.insert("State".to_owned(), node_state);
+        m.insert("Taints".to_owned(), node_taints);
         m.insert("Version".to_owned(), node_version);
         m
     };
@@ -53,16 +82,19 @@ const COL_MAP: &[(&str, &str)] = &[
     ("roles", "Roles"),
     ("age", "Age"),
     ("version", "Version"),
+    ("internalip", "Internal Ip"),
+    ("osimage", "Os Image"),
 ];
 
 const COL_FLAGS: &[&str] = &{ extract_first!(COL_MAP) };
 
 const EXTRA_COL_MAP: &[(&str, &str)] = &[
-    ("internalip", "Internal Ip"),
     ("externalip", "External Ip"),
-    ("osimage", "Os Image"),
     ("kernelversion", "Kernel Version"),
     ("containerruntime", "Container Runtime"),
+    ("cpu", "Allocatable Cpu"),
+    ("memory", "Allocatable Memory"),
+    ("pods", "Pods"),
     ("labels", "Labels"),
 ];
 
@@ -76,10 +108,28 @@ fn node_to_kobj(node: &api::Node) -> KObj {
             .clone()
             .unwrap_or_else(|| "<Unknown>".into()),
         namespace: None,
+        uid: node.metadata.uid.clone(),
+        context: None,
         typ: ObjType::Node,
     }
 }
 
+fn node_allocatable<'a>(node: &'a api::Node, resource: &str) -> Option<CellSpec<'a>> {
+    node.status.as_ref().and_then(|stat| {
+        stat.allocatable
+            .get(resource)
+            .map(|quantity| quantity.0.as_str().into())
+    })
+}
+
+fn node_allocatable_cpu(node: &api::Node) -> Option<CellSpec<'_>> {
+    node_allocatable(node, "cpu")
+}
+
+fn node_allocatable_memory(node: &api::Node) -> Option<CellSpec<'_>> {
+    node_allocatable(node, "memory")
+}
+
 fn node_container_runtime(node: &api::Node) -> Option<CellSpec<'_>> {
     node.status.as_ref().and_then(|stat| {
         stat.node_info
@@ -141,33 +191,74 @@ fn node_roles(node: &api::Node) -> Option<CellSpec<'_>> {
     }
 }
 
+fn node_pod_count(node: &api::Node) -> Option<CellSpec<'_>> {
+    let name = node.metadata.name.as_deref()?;
+    let counts = NODE_POD_COUNTS.lock().unwrap();
+    Some(counts.get(name).copied().unwrap_or(0).into())
+}
+
+/// Taints as key=value:effect, one per line, like kubectl describe shows them
+fn node_taints(node: &api::Node) -> Option<CellSpec<'_>> {
+    let taints = node.spec.as_ref().map(|spec| spec.taints.as_slice());
+    match taints {
+        Some(taints) if !taints.is_empty() => {
+            let strs: Vec<String> = taints
+                .iter()
+                .map(|taint| match taint.value.as_deref() {
+                    Some(value) => format!("{}={}:{}", taint.key, value, taint.effect),
+                    None => format!("{}:{}", taint.key, taint.effect),
+                })
+                .collect();
+            Some(strs.join("\n").into())
+        }
+        _ => Some("<none>".into()),
+    }
+}
+
+/// Count the non-terminated pods on each node, for the Pods column
+fn count_node_pods(env: &Env) -> Result<HashMap<String, usize>, ClickError> {
+    let opts = ListOptional {
+        field_selector: Some(TERMINATED_SELECTOR),
+        ..Default::default()
+    };
+    let (request, _) = api::Pod::list_pod_for_all_namespaces(opts)?;
+    let pods: List<api::Pod> = env.run_on_context(|c| c.execute_list(request))?;
+    let mut counts = HashMap::new();
+    for pod in pods.items.into_iter() {
+        if let Some(node) = pod.spec.and_then(|spec| spec.node_name) {
+            *counts.entry(node).or_insert(0) += 1;
+        }
+    }
+    Ok(counts)
+}
+
 fn node_state<'a>(node: &'a api::Node) -> Option<CellSpec<'a>> {
     // scope borrows
     let readycond: Option<&api::NodeCondition> = node
         .status
         .as_ref()
         .and_then(|stat| stat.conditions.iter().find(|c| c.type_ == "Ready"));
-    let (state, state_style) = if let Some(cond) = readycond {
+    let (state, state_role) = if let Some(cond) = readycond {
         if cond.status == "True" {
-            ("Ready", "Fg")
+            ("Ready", Role::GoodStatus)
         } else {
-            ("Not Ready", "Fr")
+            ("NotReady", Role::BadStatus)
         }
     } else {
-        ("Unknown", "Fy")
+        ("Unknown", Role::Warning)
     };
 
     let state: Cow<'a, str> = match node.spec.as_ref().and_then(|spec| spec.unschedulable) {
         Some(unsched) => {
             if unsched {
-                format!("{}\nSchedulingDisabled", state).into()
+                format!("{},SchedulingDisabled", state).into()
             } else {
                 state.into()
             }
         }
         None => state.into(),
     };
-    Some(CellSpec::with_style(state, state_style))
+    Some(CellSpec::with_role(state, state_role))
 }
 
 fn node_version(node: &api::Node) -> Option<CellSpec<'_>> {
@@ -199,6 +290,12 @@ list_command!(
                 .help("Filter returned value by the specified regex")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("taints")
+                .long("show-taints")
+                .help("Show each node's taints")
+                .takes_value(false),
+        )
         .arg(show_arg(EXTRA_COL_FLAGS, true))
         .arg(sort_arg(COL_FLAGS, Some(EXTRA_COL_FLAGS)))
         .arg(
@@ -208,12 +305,30 @@ list_command!(
                 .help("Reverse the order of the returned list")
                 .takes_value(false),
         )
+        .after_help(
+            "The Pods column counts the pods scheduled on each node that haven't terminated, \
+             which takes listing every pod in the cluster, so it's only fetched when shown. Select \
+             a node and run 'pods --on-node' to list them.",
+        )
     },
     vec!["nodes"],
     noop_complete!(),
-    IntoIter::new([]),
+    [],
     |matches, env, writer| {
-        let cols: Vec<&str> = COL_MAP.iter().map(|(_, col)| *col).collect();
+        let mut cols: Vec<&str> = COL_MAP.iter().map(|(_, col)| *col).collect();
+        if matches.is_present("taints") {
+            cols.push("Taints");
+        }
+        let shows_pods = writer.output_format() == OutputFormat::Wide
+            || matches
+                .value_of("sort")
+                .is_some_and(|sort| sort.eq_ignore_ascii_case("pods"))
+            || matches.values_of("show").is_some_and(|mut show| {
+                show.any(|s| s.eq_ignore_ascii_case("pods") || s.eq_ignore_ascii_case("all"))
+            });
+        if shows_pods {
+            *NODE_POD_COUNTS.lock().unwrap() = count_node_pods(env)?;
+        }
         let (request, _response_body) = api::Node::list_node(Default::default())?;
 
         run_list_command(
@@ -229,3 +344,363 @@ list_command!(
         )
     }
 );
+
+fn check_node(obj: &KObj) -> Result<(), ClickError> {
+    if obj.is(ObjType::Node) {
+        Ok(())
+    } else {
+        Err(ClickError::CommandError(format!(
+            "{} {} is not a node",
+            obj.type_str(),
+            obj.name()
+        )))
+    }
+}
+
+/// Set spec.unschedulable on a node, i.e. cordon or uncordon it
+fn set_unschedulable(
+    env: &Env,
+    node: &str,
+    unschedulable: bool,
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    let patch = Patch::Merge(serde_json::json!({ "spec": { "unschedulable": unschedulable } }));
+    let (request, _) = api::Node::patch_node(node, &patch, Default::default())?;
+    if dry_run_request(env, &request, writer) {
+        return Ok(());
+    }
+    match env.run_on_context(|c| c.read::<PatchResponse<api::Node>>(request))? {
+        PatchResponse::Ok(_) => {
+            let action = if unschedulable {
+                "cordoned"
+            } else {
+                "uncordoned"
+            };
+            clickwriteln!(writer, "node {} {}", node, action);
+            Ok(())
+        }
+        PatchResponse::Other(res) => {
+            let msg = match res {
+                Ok(Some(val)) => val_str("/message", &val, "<No message>").into_owned(),
+                Ok(None) => "no reason given".to_string(),
+                Err(e) => e.to_string(),
+            };
+            Err(ClickError::CommandError(format!(
+                "Could not update node {}: {}",
+                node, msg
+            )))
+        }
+    }
+}
+
+fn pod_ref(pod: &api::Pod) -> String {
+    format!(
+        "{}/{}",
+        pod.metadata.namespace.as_deref().unwrap_or("default"),
+        pod.metadata.name.as_deref().unwrap_or("<Unknown>")
+    )
+}
+
+fn pods_on_node(env: &Env, node: &str) -> Result<List<api::Pod>, ClickError> {
+    let field_sel = format!("spec.nodeName={}", node);
+    let opts = ListOptional {
+        field_selector: Some(&field_sel),
+        ..Default::default()
+    };
+    let (request, _) = api::Pod::list_pod_for_all_namespaces(opts)?;
+    env.run_on_context(|c| c.execute_list(request))
+}
+
+/// What drain should do with a pod
+enum DrainAction {
+    Evict,
+    /// Leave the pod alone, and say why
+    Skip(&'static str),
+    /// Refuse to drain the node because of this pod, unless the user passes the flag
+    Refuse(&'static str),
+}
+
+/// Check a pod the same way kubectl drain does
+fn drain_action(pod: &api::Pod, force: bool, delete_emptydir: bool) -> DrainAction {
+    if pod.metadata.annotations.contains_key(MIRROR_ANNOTATION) {
+        return DrainAction::Skip("mirror pod");
+    }
+    let finished = pod
+        .status
+        .as_ref()
+        .and_then(|stat| stat.phase.as_deref())
+        .is_some_and(|phase| phase == "Succeeded" || phase == "Failed");
+    // finished pods aren't running anything, so there's nothing to lose by removing them
+    if finished {
+        return DrainAction::Evict;
+    }
+    let controller = pod
+        .metadata
+        .owner_references
+        .iter()
+        .find(|owner| owner.controller == Some(true));
+    match controller {
+        Some(owner) if owner.kind == "DaemonSet" => {
+            // the daemonset controller ignores unschedulable, so it would just come back
+            return DrainAction::Skip("managed by a DaemonSet");
+        }
+        None if !force => {
+            return DrainAction::Refuse("not managed by a controller (use --force)");
+        }
+        _ => {}
+    }
+    let has_emptydir = pod
+        .spec
+        .as_ref()
+        .is_some_and(|spec| spec.volumes.iter().any(|vol| vol.empty_dir.is_some()));
+    if has_emptydir && !delete_emptydir {
+        return DrainAction::Refuse(
+            "has emptyDir volumes, whose data will be lost (use --delete-emptydir-data)",
+        );
+    }
+    DrainAction::Evict
+}
+
+/// Try to evict a pod. Returns Ok(false) if a PodDisruptionBudget doesn't allow it right now.
+fn evict(env: &Env, pod: &api::Pod, writer: &mut ClickWriter) -> Result<bool, ClickError> {
+    let name = pod.metadata.name.as_deref().unwrap_or_default();
+    let ns = pod.metadata.namespace.as_deref().unwrap_or("default");
+    let eviction = policy_api::Eviction {
+        metadata: ObjectMeta {
+            name: Some(name.to_string()),
+            namespace: Some(ns.to_string()),
+            ..Default::default()
+        },
+        delete_options: None,
+    };
+    let (request, _) = policy_api::Eviction::create_namespaced_pod_eviction(
+        name,
+        ns,
+        &eviction,
+        Default::default(),
+    )?;
+    if dry_run_request(env, &request, writer) {
+        return Ok(true);
+    }
+    let response = env.run_on_context(|c| c.execute(request))?;
+    match response.status() {
+        // not found means it's already gone, which is what we wanted
+        StatusCode::OK | StatusCode::CREATED | StatusCode::NOT_FOUND => Ok(true),
+        StatusCode::TOO_MANY_REQUESTS => Ok(false),
+        status => {
+            let msg = serde_json::from_slice(response.body())
+                .map(|val| val_str("/message", &val, "<No message>").into_owned())
+                .unwrap_or_else(|_| status.to_string());
+            Err(ClickError::CommandError(format!(
+                "Could not evict pod {}: {}",
+                pod_ref(pod),
+                msg
+            )))
+        }
+    }
+}
+
+fn drain_node(
+    env: &Env,
+    node: &str,
+    force: bool,
+    delete_emptydir: bool,
+    timeout: Duration,
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    let deadline = Instant::now() + timeout;
+    let timed_out = || {
+        ClickError::CommandError(format!(
+            "Timed out draining node {}, it is still cordoned",
+            node
+        ))
+    };
+    env.ctrlcbool.store(false, Ordering::SeqCst);
+
+    set_unschedulable(env, node, true, writer)?;
+
+    // check every pod before evicting any, so we don't half drain a node
+    let pods = pods_on_node(env, node)?;
+    let mut to_evict = vec![];
+    let mut refused = vec![];
+    for pod in pods.items.iter() {
+        match drain_action(pod, force, delete_emptydir) {
+            DrainAction::Evict => to_evict.push(pod),
+            DrainAction::Skip(why) => {
+                clickwriteln!(writer, "ignoring pod {}: {}", pod_ref(pod), why);
+            }
+            DrainAction::Refuse(why) => refused.push(format!("  {}: {}", pod_ref(pod), why)),
+        }
+    }
+    if !refused.is_empty() {
+        return Err(ClickError::CommandError(format!(
+            "Cannot drain node {}, it is cordoned but no pods were evicted:\n{}",
+            node,
+            refused.join("\n")
+        )));
+    }
+
+    let mut pending = to_evict;
+    let mut evicted = vec![];
+    while !pending.is_empty() {
+        let mut blocked = vec![];
+        for pod in pending.into_iter() {
+            if evict(env, pod, writer)? {
+                if !env.dry_run {
+                    clickwriteln!(writer, "evicting pod {}", pod_ref(pod));
+                }
+                evicted.push(pod);
+            } else {
+                blocked.push(pod);
+            }
+        }
+        pending = blocked;
+        if pending.is_empty() {
+            break;
+        }
+        for pod in pending.iter() {
+            clickwriteln!(
+                writer,
+                "pod {} can't be evicted yet, it would violate a PodDisruptionBudget. Retrying \
+                 in {}s",
+                pod_ref(pod),
+                DRAIN_POLL_INTERVAL.as_secs()
+            );
+        }
+        if Instant::now() >= deadline {
+            return Err(timed_out());
+        }
+        if !interruptible_sleep(env, DRAIN_POLL_INTERVAL) {
+            clickwriteln!(writer, "Stopped draining, node {} is still cordoned", node);
+            return Ok(());
+        }
+    }
+
+    if env.dry_run {
+        return Ok(());
+    }
+
+    // an eviction is just a graceful delete, so wait for the pods to actually go away. a pod
+    // that's been replaced by one with the same name (statefulsets) will have a different uid.
+    let mut remaining: Vec<&api::Pod> = evicted;
+    loop {
+        let current = pods_on_node(env, node)?;
+        remaining.retain(|pod| {
+            let still_there = current
+                .items
+                .iter()
+                .any(|cur| cur.metadata.uid == pod.metadata.uid);
+            if !still_there {
+                clickwriteln!(writer, "pod {} evicted", pod_ref(pod));
+            }
+            still_there
+        });
+        if remaining.is_empty() {
+            clickwriteln!(writer, "node {} drained", node);
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            return Err(timed_out());
+        }
+        if !interruptible_sleep(env, DRAIN_POLL_INTERVAL) {
+            clickwriteln!(writer, "Stopped waiting, node {} is still cordoned", node);
+            return Ok(());
+        }
+    }
+}
+
+command!(
+    Cordon,
+    "cordon",
+    "Mark the active node(s) as unschedulable, so no new pods are scheduled on them",
+    |clap: App<'static, 'static>| clap,
+    vec!["cordon"],
+    noop_complete!(),
+    no_named_complete!(),
+    |_matches, env, writer| {
+        env.apply_to_selection(
+            writer,
+            Some(&env.click_config.range_separator),
+            ErrorStrategy::CollectAll,
+            |obj, writer| {
+                check_node(obj)?;
+                set_unschedulable(env, obj.name(), true, writer)
+            },
+        )
+    }
+);
+
+command!(
+    Uncordon,
+    "uncordon",
+    "Mark the active node(s) as schedulable again",
+    |clap: App<'static, 'static>| clap,
+    vec!["uncordon"],
+    noop_complete!(),
+    no_named_complete!(),
+    |_matches, env, writer| {
+        env.apply_to_selection(
+            writer,
+            Some(&env.click_config.range_separator),
+            ErrorStrategy::CollectAll,
+            |obj, writer| {
+                check_node(obj)?;
+                set_unschedulable(env, obj.name(), false, writer)
+            },
+        )
+    }
+);
+
+command!(
+    Drain,
+    "drain",
+    "Cordon the active node(s), and then evict all their pods",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("force")
+                .long("force")
+                .help("Also delete pods that aren't managed by a controller. They won't come back")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("delete_emptydir_data")
+                .long("delete-emptydir-data")
+                .help("Also delete pods with emptyDir volumes. The data in them will be lost")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("timeout")
+                .long("timeout")
+                .help("How long to wait for the whole drain, i.e. 30s, 5m")
+                .takes_value(true)
+                .validator(valid_duration)
+                .default_value("10m"),
+        )
+        .after_help(
+            "Pods are evicted using the eviction api, so PodDisruptionBudgets are respected, and \
+             evictions they block are retried until --timeout. DaemonSet and mirror (static) pods \
+             are skipped. If any pod can't be evicted without --force or --delete-emptydir-data, \
+             no pods are evicted.
+
+The node is left cordoned when drain finishes (or fails). Use 'uncordon' to make it schedulable \
+again.",
+        )
+    },
+    vec!["drain"],
+    noop_complete!(),
+    no_named_complete!(),
+    |matches, env, writer| {
+        let force = matches.is_present("force");
+        let delete_emptydir = matches.is_present("delete_emptydir_data");
+        let timeout = parse_duration(matches.value_of("timeout").unwrap()).unwrap(); // safe, validated with a default
+        env.apply_to_selection(
+            writer,
+            Some(&env.click_config.range_separator),
+            ErrorStrategy::StopOnFirst,
+            |obj, writer| {
+                check_node(obj)?;
+                drain_node(env, obj.name(), force, delete_emptydir, timeout, writer)
+            },
+        )
+    }
+);
@@ -0,0 +1,310 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use clap::{App, Arg};
+use k8s_openapi::api::core::v1 as api;
+use rustyline::completion::Pair as RustlinePair;
+
+use crate::{
+    command::command_def::{exec_match, start_clap, Cmd},
+    command::ssh::ssh_to,
+    command::{kubectl_exit_error, kubectl_spawn_error, kubectl_streaming_command, shell_quote},
+    completer,
+    env::Env,
+    error::ClickError,
+    output::ClickWriter,
+};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+
+/// How to find a container's pid on its node. containerd and cri-o both go through crictl.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Runtime {
+    Docker,
+    Crictl,
+}
+
+impl Runtime {
+    /// The runtime for a name like the ones in container_runtime, or the scheme of a node's
+    /// containerRuntimeVersion (i.e. containerd://1.6.2)
+    fn from_name(name: &str) -> Option<Runtime> {
+        match name {
+            "docker" => Some(Runtime::Docker),
+            "containerd" | "cri-o" => Some(Runtime::Crictl),
+            _ => None,
+        }
+    }
+
+    /// A shell command that prints the pid of container id on the node
+    fn pid_command(self, id: &str) -> String {
+        match self {
+            Runtime::Docker => format!("docker inspect --format '{{{{.State.Pid}}}}' {}", id),
+            Runtime::Crictl => format!(
+                "crictl inspect --output go-template --template '{{{{.info.pid}}}}' {}",
+                id
+            ),
+        }
+    }
+}
+
+/// The scheme of a url like containerd://abc123, and what follows it
+fn split_scheme(url: &str) -> Option<(&str, &str)> {
+    url.split_once("://")
+}
+
+fn read_pod(env: &Env, name: &str, ns: &str) -> Result<api::Pod, ClickError> {
+    let (request, _) = api::Pod::read_namespaced_pod(name, ns, Default::default())?;
+    match env.run_on_context(|c| c.read::<api::ReadNamespacedPodResponse>(request))? {
+        api::ReadNamespacedPodResponse::Ok(pod) => Ok(pod),
+        _ => Err(ClickError::CommandError(format!(
+            "Could not read pod {}",
+            name
+        ))),
+    }
+}
+
+/// The runtime to use on node: the configured one, else what the node reports it runs, else
+/// whatever the container id says it came from
+fn node_runtime(env: &Env, node: &str, container_id: &str) -> Result<Runtime, ClickError> {
+    if let Some(name) = env.click_config.container_runtime.as_deref() {
+        return Runtime::from_name(name).ok_or_else(|| {
+            ClickError::CommandError(format!("Unknown container_runtime '{}'", name))
+        });
+    }
+    let (request, _) = api::Node::read_node(node, Default::default())?;
+    let reported = match env.run_on_context(|c| c.read::<api::ReadNodeResponse>(request))? {
+        api::ReadNodeResponse::Ok(node) => node
+            .status
+            .and_then(|status| status.node_info)
+            .map(|info| info.container_runtime_version),
+        _ => None,
+    };
+    reported
+        .as_deref()
+        .and_then(split_scheme)
+        .and_then(|(scheme, _)| Runtime::from_name(scheme))
+        .or_else(|| split_scheme(container_id).and_then(|(scheme, _)| Runtime::from_name(scheme)))
+        .ok_or_else(|| {
+            ClickError::CommandError(format!(
+                "Can't tell what container runtime node {} runs ({}). Use 'set container_runtime' \
+                 to say.",
+                node,
+                reported.as_deref().unwrap_or("unknown")
+            ))
+        })
+}
+
+/// The shell script to run as root on the node: find the container's pid, then run command in
+/// its namespaces
+fn nsenter_script(runtime: Runtime, id: &str, namespaces: &[&str], command: &[&str]) -> String {
+    let mut script = format!(
+        "pid=$({}) && exec nsenter --target \"$pid\"",
+        runtime.pid_command(&shell_quote(id))
+    );
+    for ns in namespaces.iter() {
+        script.push_str(" --");
+        script.push_str(ns);
+    }
+    if !command.is_empty() {
+        script.push_str(" --");
+        for arg in command.iter() {
+            script.push(' ');
+            script.push_str(&shell_quote(arg));
+        }
+    }
+    script
+}
+
+command!(
+    Nsenter,
+    "nsenter",
+    "Run a shell or command on the active pod's node, inside the namespaces of one of its \
+     containers. This uses the node's tools, with the container's view of the network, processes, \
+     or filesystem, which helps with containers that don't have debugging tools in them.",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("net")
+                .long("net")
+                .short("n")
+                .help("Enter the container's network namespace (the default if none are given)"),
+        )
+        .arg(
+            Arg::with_name("pid")
+                .long("pid")
+                .short("p")
+                .help("Enter the container's pid namespace"),
+        )
+        .arg(Arg::with_name("mnt").long("mnt").short("m").help(
+            "Enter the container's mount namespace. The node's tools won't be there, \
+                     only the container's.",
+        ))
+        .arg(
+            Arg::with_name("container")
+                .long("container")
+                .short("c")
+                .help("The container to enter, if the pod has more than one")
+                .takes_value(true),
+        )
+        .arg(Arg::with_name("ssh").long("ssh").help(
+            "Get to the node with ssh and sudo, rather than kubectl node-shell. See \
+                     'ssh --help' for how nodes are connected to.",
+        ))
+        .arg(
+            Arg::with_name("user")
+                .long("user")
+                .short("u")
+                .help("The user to ssh in as, with --ssh")
+                .takes_value(true)
+                .requires("ssh"),
+        )
+        .arg(
+            Arg::with_name("key")
+                .long("key")
+                .short("i")
+                .value_name("KEY")
+                .help("The private key to ssh in with, with --ssh")
+                .takes_value(true)
+                .requires("ssh"),
+        )
+        .arg(
+            Arg::with_name("command")
+                .help("A command to run, rather than starting a shell")
+                .multiple(true) // required for trailing_var_arg
+                .index(1),
+        )
+        .after_help(
+            "The container's pid is found with 'docker inspect' or 'crictl inspect' on the node, \
+depending on the container runtime the node reports. 'set container_runtime' overrides that, \
+with one of docker, containerd, or cri-o. By default the node is reached with the node-shell \
+kubectl plugin, which needs to be installed. With --ssh, the user needs to be able to sudo on \
+the node.
+
+Examples:
+  # capture traffic from the active pod with the node's tcpdump
+  nsenter -- tcpdump -i any -n port 8080
+
+  # look at the processes in the istio-proxy container, going to the node over ssh
+  nsenter --pid --mnt -c istio-proxy --ssh -- ps aux",
+        )
+    },
+    vec!["nsenter"],
+    noop_complete!(),
+    no_named_complete!(),
+    |matches, env, writer| {
+        let pod = env.current_pod()?;
+        let ns = pod.namespace.as_deref().unwrap_or("default");
+        let pod_obj = read_pod(env, pod.name(), ns)?;
+        let node = pod_obj
+            .spec
+            .as_ref()
+            .and_then(|spec| spec.node_name.clone())
+            .ok_or_else(|| {
+                ClickError::CommandError(format!(
+                    "Pod {} hasn't been scheduled to a node",
+                    pod.name()
+                ))
+            })?;
+        let statuses = pod_obj
+            .status
+            .map(|status| status.container_statuses)
+            .unwrap_or_default();
+        let status = match matches.value_of("container") {
+            Some(container) => statuses
+                .iter()
+                .find(|s| s.name == container)
+                .ok_or_else(|| {
+                    ClickError::CommandError(format!(
+                        "Pod {} has no container {}",
+                        pod.name(),
+                        container
+                    ))
+                })?,
+            None => {
+                let status = statuses.first().ok_or_else(|| {
+                    ClickError::CommandError(format!("Pod {} has no containers", pod.name()))
+                })?;
+                if statuses.len() > 1 {
+                    clickwriteln!(
+                        writer,
+                        "Pod has multiple containers, using {} (use -c to pick another)",
+                        status.name
+                    );
+                }
+                status
+            }
+        };
+        let container_id = status.container_id.as_deref().ok_or_else(|| {
+            ClickError::CommandError(format!("Container {} isn't running", status.name))
+        })?;
+        let runtime = node_runtime(env, &node, container_id)?;
+        let id = split_scheme(container_id)
+            .map(|(_, id)| id)
+            .unwrap_or(container_id);
+
+        let mut namespaces: Vec<&str> = ["net", "pid", "mnt"]
+            .iter()
+            .copied()
+            .filter(|ns| matches.is_present(ns))
+            .collect();
+        if namespaces.is_empty() {
+            namespaces.push("net");
+        }
+        let command: Vec<&str> = matches
+            .values_of("command")
+            .map(|c| c.collect())
+            .unwrap_or_default();
+        let script = nsenter_script(runtime, id, &namespaces, &command);
+
+        clickwriteln!(
+            writer,
+            "Entering {} namespace{} of container {} on node {}",
+            namespaces.join(", "),
+            if namespaces.len() == 1 { "" } else { "s" },
+            status.name,
+            node
+        );
+        if matches.is_present("ssh") {
+            let sudo = format!("sudo sh -c {}", shell_quote(&script));
+            ssh_to(
+                env,
+                &node,
+                matches.value_of("user"),
+                matches.value_of("key"),
+                &[&sudo],
+                true,
+                writer,
+            )
+        } else {
+            // node-shell runs a privileged pod on the node that's already root in its namespaces
+            let mut kubectl = kubectl_streaming_command(env, None)?;
+            kubectl
+                .arg("node-shell")
+                .arg(&node)
+                .arg("--")
+                .arg("sh")
+                .arg("-c")
+                .arg(&script);
+            writer.finish_paging();
+            let status = kubectl.status().map_err(kubectl_spawn_error)?;
+            if status.success() {
+                Ok(())
+            } else {
+                Err(kubectl_exit_error(status, &[]))
+            }
+        }
+    },
+    true // gather up everything after the options into the command
+);
@@ -0,0 +1,201 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use clap::{App, Arg};
+use rustyline::completion::Pair as RustlinePair;
+use serde_json::Value;
+
+use crate::{
+    command::command_def::{exec_match, start_clap, Cmd},
+    command::{
+        kubectl_get_json, kubectl_mutate, kubectl_obj_arg, kubectl_obj_command, kubectl_output,
+        strip_managed_fields,
+    },
+    completer, diff,
+    env::{Env, ErrorStrategy},
+    error::ClickError,
+    kobj::KObj,
+    output::ClickWriter,
+};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::process::Command;
+
+/// Parse the patch, which can be yaml or json (json is yaml), and check it has the right shape for
+/// patch_type
+fn parse_patch(text: &str, patch_type: &str) -> Result<Value, ClickError> {
+    let patch: Value = serde_yaml::from_str(text)
+        .map_err(|e| ClickError::ParseErr(format!("Patch is not valid json or yaml: {}", e)))?;
+    match (patch_type, &patch) {
+        ("json", Value::Array(_)) => Ok(patch),
+        ("json", _) => Err(ClickError::ParseErr(
+            "A json patch must be a list of operations, i.e. [{\"op\": \"replace\", \"path\": \
+             \"/spec/replicas\", \"value\": 3}]"
+                .to_string(),
+        )),
+        (_, Value::Object(_)) => Ok(patch),
+        (_, _) => Err(ClickError::ParseErr(format!(
+            "A {} patch must be an object, i.e. {{\"spec\": {{\"replicas\": 3}}}}",
+            patch_type
+        ))),
+    }
+}
+
+fn patch_command(
+    env: &Env,
+    obj: &KObj,
+    patch: &Value,
+    patch_type: &str,
+) -> Result<Command, ClickError> {
+    let mut command = kubectl_obj_command(env, obj)?;
+    command
+        .arg("patch")
+        .arg(kubectl_obj_arg(obj))
+        .arg("--type")
+        .arg(patch_type)
+        .arg("-p")
+        .arg(patch.to_string());
+    Ok(command)
+}
+
+/// Have the server apply the patch without saving the result, and show what would change
+fn server_dry_run(
+    env: &Env,
+    obj: &KObj,
+    patch: &Value,
+    patch_type: &str,
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    let mut current = kubectl_get_json(env, obj)?;
+    strip_managed_fields(&mut current);
+    let mut command = patch_command(env, obj, patch, patch_type)?;
+    command.arg("--dry-run=server").arg("-o").arg("json");
+    let mut patched: Value = serde_json::from_str(&kubectl_output(command)?)?;
+    strip_managed_fields(&mut patched);
+
+    let name = kubectl_obj_arg(obj);
+    let lines = diff::unified_diff(
+        &name,
+        &format!("{} (patched)", name),
+        &serde_yaml::to_string(&current)?,
+        &serde_yaml::to_string(&patched)?,
+    );
+    if lines.is_empty() {
+        clickwriteln!(writer, "{} would not be changed", name);
+    } else {
+        diff::write_diff(writer, &lines);
+    }
+    Ok(())
+}
+
+fn patch_obj(
+    env: &Env,
+    obj: &KObj,
+    patch: &Value,
+    patch_type: &str,
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    // kubectl prints "<name> patched", or "<name> patched (no change)"
+    let command = patch_command(env, obj, patch, patch_type)?;
+    if let Some(out) = kubectl_mutate(env, command, writer)? {
+        clickwrite!(writer, "{}", out);
+    }
+    Ok(())
+}
+
+command!(
+    Patch,
+    "patch",
+    "Apply a patch to the active object(s)",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("patch")
+                .help("The patch to apply, as json or yaml")
+                .required_unless("file")
+                .conflicts_with("file")
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("file")
+                .short("f")
+                .long("file")
+                .help("Read the patch from this file (json or yaml)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("type")
+                .short("t")
+                .long("type")
+                .help(
+                    "The type of patch: json (RFC 6902), merge (RFC 7386), or strategic (a \
+                     merge that knows how to merge lists, like containers, by name)",
+                )
+                .takes_value(true)
+                .possible_values(&["json", "merge", "strategic"])
+                .default_value("strategic"),
+        )
+        .arg(
+            Arg::with_name("dry_run")
+                .long("dry-run")
+                .help("Have the server apply the patch without saving it, and show what would change")
+                .takes_value(true)
+                .possible_values(&["server"]),
+        )
+        .after_help(
+            "Examples:
+  # scale to 3 replicas
+  patch '{\"spec\":{\"replicas\":3}}'
+
+  # change the first container's image
+  patch --type json '[{\"op\":\"replace\",\"path\":\"/spec/template/spec/containers/0/image\",\"value\":\"nginx:1.21\"}]'
+
+  # see what a patch from a file would change, without changing anything
+  patch -f patch.yaml --dry-run server",
+        )
+    },
+    vec!["patch"],
+    noop_complete!(),
+    no_named_complete!(),
+    |matches, env, writer| {
+        let patch_type = matches.value_of("type").unwrap(); // safe, has a default
+        let text = match matches.value_of("file") {
+            Some(file) => fs::read_to_string(file).map_err(|e| {
+                ClickError::CommandError(format!("Could not read patch file {}: {}", file, e))
+            })?,
+            None => matches.value_of("patch").unwrap().to_string(), // safe, required unless file
+        };
+        let patch = parse_patch(&text, patch_type)?;
+        let server_dry_run_mode = matches.is_present("dry_run");
+
+        let res = env.apply_to_selection(
+            writer,
+            Some(&env.click_config.range_separator),
+            ErrorStrategy::CollectAll,
+            |obj, writer| {
+                if server_dry_run_mode {
+                    server_dry_run(env, obj, &patch, patch_type, writer)
+                } else {
+                    patch_obj(env, obj, &patch, patch_type, writer)
+                }
+            },
+        );
+        if res.is_ok() && !server_dry_run_mode && !env.dry_run {
+            env.invalidate_selection();
+        }
+        res
+    }
+);
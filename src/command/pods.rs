@@ -12,27 +12,29 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use ansi_term::{
-    Colour::{Green, Red, Yellow},
-    Style,
-};
+use ansi_term::Style;
 use clap::{App, Arg};
 use k8s_openapi::api::core::v1 as api;
-use k8s_openapi::ListOptional;
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelector;
+use k8s_openapi::{http::Request, List, ListOptional};
+use prettytable::{Cell, Row};
+use serde_json::Value;
 
 use crate::{
     command::command_def::{exec_match, show_arg, sort_arg, start_clap, Cmd},
-    command::{run_list_command, Extractor},
+    command::{build_specs, run_list_command, selector_string, Extractor},
     completer,
-    env::{Env, ObjectSelection},
+    env::{Env, ErrorStrategy, ObjectSelection},
     error::ClickError,
     kobj::{KObj, ObjType},
     output::ClickWriter,
     table::CellSpec,
+    theme::{self, Role},
 };
 
-use std::array::IntoIter;
 use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::io::Write;
 
@@ -71,7 +73,7 @@ const EXTRA_COL_MAP: &[(&str, &str)] = &[
 
 const EXTRA_COL_FLAGS: &[&str] = &{ extract_first!(EXTRA_COL_MAP) };
 
-fn pod_to_kobj(pod: &api::Pod) -> KObj {
+pub fn pod_to_kobj(pod: &api::Pod) -> KObj {
     let containers = match &pod.spec {
         Some(spec) => spec
             .containers
@@ -84,6 +86,8 @@ fn pod_to_kobj(pod: &api::Pod) -> KObj {
     KObj {
         name: meta.name.clone().unwrap_or_else(|| "<Unknown>".into()),
         namespace: meta.namespace.clone(),
+        uid: meta.uid.clone(),
+        context: None,
         typ: ObjType::Pod { containers },
     }
 }
@@ -109,15 +113,12 @@ fn has_waiting(pod: &api::Pod) -> bool {
     }
 }
 
-fn phase_style_str(phase: &str) -> &'static str {
+pub fn phase_role(phase: &str) -> Role {
     match phase {
-        "Running" | "Active" => "Fg",
-        "Terminated" | "Terminating" => "Fr",
-        "Pending" | "ContainerCreating" => "Fy",
-        "Succeeded" => "Fb",
-        "Failed" => "Fr",
-        "Unknown" => "Fr",
-        _ => "Fr",
+        "Running" | "Active" | "Succeeded" => Role::GoodStatus,
+        "Pending" | "ContainerCreating" => Role::Warning,
+        // Terminated, Failed, Unknown, etc.
+        _ => Role::BadStatus,
     }
 }
 
@@ -182,8 +183,10 @@ fn restart_count(pod: &api::Pod) -> Option<CellSpec<'_>> {
     })
 }
 
-fn pod_status(pod: &api::Pod) -> Option<CellSpec<'_>> {
-    let status = if pod.metadata.deletion_timestamp.is_some() {
+/// The status to show for a pod, like kubectl does: its phase, unless it's being deleted or
+/// waiting for containers to start
+pub fn pod_status_str(pod: &api::Pod) -> &str {
+    if pod.metadata.deletion_timestamp.is_some() {
         // Was deleted
         "Terminating"
     } else if has_waiting(pod) {
@@ -193,9 +196,12 @@ fn pod_status(pod: &api::Pod) -> Option<CellSpec<'_>> {
             .as_ref()
             .and_then(|stat| stat.phase.as_deref())
             .unwrap_or("Unknown")
-    };
-    let style = phase_style_str(status);
-    Some(CellSpec::with_style(status.into(), style))
+    }
+}
+
+fn pod_status(pod: &api::Pod) -> Option<CellSpec<'_>> {
+    let status = pod_status_str(pod);
+    Some(CellSpec::with_role(status.into(), phase_role(status)))
 }
 
 list_command!(
@@ -212,12 +218,26 @@ list_command!(
                 .help("include labels in output (deprecated, use --show labels)")
                 .takes_value(false),
         )
+        .arg(
+            Arg::with_name("label")
+                .short("l")
+                .long("selector")
+                .help("Only fetch pods matching this label selector (i.e. app=nginx,tier!=db)")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("node")
                 .short("n")
                 .long("node")
-                .help("Only fetch pods on the specified node.")
-                .takes_value(true),
+                .visible_alias("on-node")
+                .value_name("NODE")
+                .help(
+                    "Only fetch pods on the specified node. With no node, use the active node \
+                     (which is also the default when a node is selected).",
+                )
+                .takes_value(true)
+                .min_values(0)
+                .max_values(1),
         )
         .arg(
             Arg::with_name("regex")
@@ -238,7 +258,10 @@ list_command!(
     },
     vec!["pods"],
     noop_complete!(),
-    IntoIter::new([]),
+    [(
+        "label".to_string(),
+        completer::pod_label_completer as fn(&str, &Env) -> Vec<RustlinePair>
+    )],
     |matches, env, writer| {
         let mut opts: ListOptional = ListOptional::<'_> {
             label_selector: matches.value_of("label"),
@@ -249,13 +272,17 @@ list_command!(
             Some(nodeval) => {
                 field_sel = Some(format!("spec.nodeName={}", nodeval));
             }
-            None => {
-                if let ObjectSelection::Single(obj) = env.current_selection() {
-                    if obj.is(ObjType::Node) {
-                        field_sel = Some(format!("spec.nodeName={}", obj.name()));
-                    }
+            None => match env.current_selection() {
+                ObjectSelection::Single(obj) if obj.is(ObjType::Node) => {
+                    field_sel = Some(format!("spec.nodeName={}", obj.name()));
                 }
-            }
+                _ if matches.is_present("node") => {
+                    return Err(ClickError::CommandError(
+                        "No node given, and the active object isn't a node".to_string(),
+                    ));
+                }
+                _ => {}
+            },
         }
         opts.field_selector = field_sel.as_deref();
 
@@ -280,6 +307,66 @@ list_command!(
     }
 );
 
+/// columns for the pod tables printed under other objects (i.e. `daemonsets --pods`)
+const SUB_TABLE_COLS: &[&str] = &["Name", "Ready", "Status", "Restarts", "Node", "Age"];
+
+/// Get the pods in namespace matched by selector
+pub fn get_pods_for_selector(
+    env: &Env,
+    namespace: &str,
+    selector: &LabelSelector,
+) -> Result<List<api::Pod>, ClickError> {
+    let request = pods_for_selector_request(namespace, selector)?;
+    env.run_on_context(|c| c.execute_list(request))
+}
+
+/// Get the pods matched by the selector of controller, from the namespace and context it's in
+pub fn get_pods_for_controller(
+    env: &Env,
+    controller: &KObj,
+    selector: &LabelSelector,
+) -> Result<List<api::Pod>, ClickError> {
+    let namespace = controller.namespace.as_deref().unwrap_or("default");
+    let request = pods_for_selector_request(namespace, selector)?;
+    env.run_on_obj_context(controller, |c| c.execute_list(request))
+}
+
+fn pods_for_selector_request(
+    namespace: &str,
+    selector: &LabelSelector,
+) -> Result<Request<Vec<u8>>, ClickError> {
+    let sel = selector_string(selector);
+    let opts = ListOptional {
+        label_selector: Some(sel.as_str()),
+        ..Default::default()
+    };
+    let (request, _) = api::Pod::list_namespaced_pod(namespace, opts)?;
+    Ok(request)
+}
+
+/// Print a table of the pods in list. This is used to show the pods managed by a controller like
+/// a daemonset. The printed pods are not numbered and don't change the current list.
+pub fn print_pod_sub_table(list: &List<api::Pod>, writer: &mut ClickWriter) {
+    if list.items.is_empty() {
+        clickwriteln!(writer, "  No pods");
+        return;
+    }
+    let specs = build_specs(
+        SUB_TABLE_COLS,
+        list,
+        Some(&POD_EXTRACTORS),
+        false,
+        None,
+        pod_to_kobj,
+    );
+    let titles = Row::new(SUB_TABLE_COLS.iter().map(|col| Cell::new(col)).collect());
+    crate::table::print_table(
+        titles,
+        specs.into_iter().map(|(_, row)| row).collect(),
+        writer,
+    );
+}
+
 // also add a command to print all the containers of a pod
 command!(
     Containers,
@@ -287,12 +374,24 @@ command!(
     "Print information about the containers of the active pod",
     |clap: App<'static, 'static>| {
         clap.arg(
+            Arg::with_name("details")
+                .short("d")
+                .long("details")
+                .help("Show each container in detail, rather than as a table")
+                .takes_value(false),
+        )
+        .arg(
             Arg::with_name("volumes")
                 .short("v")
                 .long("volumes")
-                .help("show information about each containers volume mounts")
+                .help("show information about each containers volume mounts (implies --details)")
                 .takes_value(false),
         )
+        .after_help(
+            "The table lists init, regular, and ephemeral containers, with the reason and \
+             exit code of the last time each one terminated. The names are remembered for \
+             completing -c in exec, logs, and other commands that take a container.",
+        )
     },
     vec!["conts", "containers"],
     noop_complete!(),
@@ -301,19 +400,239 @@ command!(
         env.apply_to_selection(
             writer,
             Some(&env.click_config.range_separator),
+            ErrorStrategy::CollectAll,
             |obj, writer| {
-                if obj.is_pod() {
-                    print_containers(obj, env, matches.is_present("volumes"), writer)
-                } else {
+                if !obj.is_pod() {
                     Err(ClickError::CommandError(
                         "containers only possible on a Pod".to_string(),
                     ))
+                } else if matches.is_present("details") || matches.is_present("volumes") {
+                    print_containers(obj, env, matches.is_present("volumes"), writer)
+                } else {
+                    print_container_table(obj, env, writer)
                 }
             },
         )
     }
 );
 
+/// Where a container is in its pod's spec
+#[derive(Clone, Copy)]
+enum ContainerKind {
+    Init,
+    Regular,
+    Ephemeral,
+}
+
+impl ContainerKind {
+    /// The fields of the pod's spec and status that list containers of this kind
+    fn fields(self) -> (&'static str, &'static str) {
+        match self {
+            ContainerKind::Init => ("/spec/initContainers", "/status/initContainerStatuses"),
+            ContainerKind::Regular => ("/spec/containers", "/status/containerStatuses"),
+            ContainerKind::Ephemeral => (
+                "/spec/ephemeralContainers",
+                "/status/ephemeralContainerStatuses",
+            ),
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            ContainerKind::Init => "init",
+            ContainerKind::Regular => "regular",
+            ContainerKind::Ephemeral => "ephemeral",
+        }
+    }
+}
+
+/// Read pod as json. Ephemeral containers aren't in the pod type for the api version we build
+/// against, so they're only in the json.
+pub fn read_pod_json(env: &Env, obj: &KObj) -> Result<Value, ClickError> {
+    let (request, _) = api::Pod::read_namespaced_pod(
+        obj.name(),
+        obj.namespace.as_deref().unwrap_or("default"),
+        Default::default(),
+    )?;
+    let response = env.run_on_context(|c| c.execute(request))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(
+            ClickError::from_status(status.as_u16(), response.body()).unwrap_or_else(|| {
+                ClickError::CommandError(format!("Error getting pod info: {}", status))
+            }),
+        );
+    }
+    Ok(serde_json::from_slice(response.body())?)
+}
+
+/// The containers (specs and statuses) of kind in a pod's json. Ephemeral containers have the
+/// same fields we look at as regular ones.
+fn containers_of(
+    pod: &Value,
+    kind: ContainerKind,
+) -> Result<Vec<(api::Container, Option<api::ContainerStatus>)>, ClickError> {
+    let (spec_field, status_field) = kind.fields();
+    let specs: Vec<api::Container> = match pod.pointer(spec_field) {
+        Some(specs) => serde_json::from_value(specs.clone())?,
+        None => vec![],
+    };
+    let mut statuses: Vec<api::ContainerStatus> = match pod.pointer(status_field) {
+        Some(statuses) => serde_json::from_value(statuses.clone())?,
+        None => vec![],
+    };
+    Ok(specs
+        .into_iter()
+        .map(|spec| {
+            let status = statuses
+                .iter()
+                .position(|status| status.name == spec.name)
+                .map(|i| statuses.swap_remove(i));
+            (spec, status)
+        })
+        .collect())
+}
+
+fn state_cell(status: Option<&api::ContainerStatus>) -> CellSpec<'static> {
+    let state = status.and_then(|status| status.state.as_ref());
+    match state {
+        Some(state) => {
+            if state.running.is_some() {
+                CellSpec::with_role("Running".into(), Role::GoodStatus)
+            } else if let Some(terminated) = state.terminated.as_ref() {
+                let role = if terminated.exit_code == 0 {
+                    Role::GoodStatus
+                } else {
+                    Role::BadStatus
+                };
+                let text = match terminated.reason.as_deref() {
+                    Some(reason) => format!("Terminated: {}", reason),
+                    None => "Terminated".to_string(),
+                };
+                CellSpec::with_role(text.into(), role)
+            } else if let Some(waiting) = state.waiting.as_ref() {
+                let text = match waiting.reason.as_deref() {
+                    Some(reason) => format!("Waiting: {}", reason),
+                    None => "Waiting".to_string(),
+                };
+                CellSpec::with_role(text.into(), Role::Warning)
+            } else {
+                "Unknown".into()
+            }
+        }
+        None => "Unknown".into(),
+    }
+}
+
+/// The last time a container terminated: the last state if it's been restarted, else its current
+/// state, i.e. for an init container that's finished
+fn last_termination(
+    status: Option<&api::ContainerStatus>,
+) -> Option<&api::ContainerStateTerminated> {
+    status.and_then(|status| {
+        status
+            .last_state
+            .as_ref()
+            .and_then(|state| state.terminated.as_ref())
+            .or_else(|| {
+                status
+                    .state
+                    .as_ref()
+                    .and_then(|state| state.terminated.as_ref())
+            })
+    })
+}
+
+fn resources_str(resources: &BTreeMap<String, Quantity>) -> String {
+    if resources.is_empty() {
+        "<none>".to_string()
+    } else {
+        let strs: Vec<String> = resources
+            .iter()
+            .map(|(resource, quantity)| format!("{}={}", resource, quantity.0))
+            .collect();
+        strs.join(", ")
+    }
+}
+
+fn container_row(
+    kind: ContainerKind,
+    spec: &api::Container,
+    status: Option<&api::ContainerStatus>,
+) -> Vec<CellSpec<'static>> {
+    let terminated = last_termination(status);
+    let reason = terminated.and_then(|t| t.reason.clone());
+    let reason_cell = match reason {
+        Some(reason) if reason == "OOMKilled" => {
+            CellSpec::with_role(reason.into(), Role::BadStatus)
+        }
+        Some(reason) => reason.into(),
+        None => "<none>".into(),
+    };
+    let exit_cell = match terminated.map(|t| t.exit_code) {
+        Some(0) => 0.into(),
+        Some(code) => CellSpec::with_role(code.to_string().into(), Role::BadStatus),
+        None => "".into(),
+    };
+    let (requests, limits) = match spec.resources.as_ref() {
+        Some(resources) => (
+            resources_str(&resources.requests),
+            resources_str(&resources.limits),
+        ),
+        None => ("<none>".to_string(), "<none>".to_string()),
+    };
+    vec![
+        spec.name.clone().into(),
+        kind.as_str().into(),
+        spec.image.clone().unwrap_or_default().into(),
+        status.is_some_and(|s| s.ready).to_string().into(),
+        state_cell(status),
+        status.map(|s| s.restart_count).unwrap_or(0).into(),
+        reason_cell,
+        exit_cell,
+        requests.into(),
+        limits.into(),
+    ]
+}
+
+/// Print the containers of the pod obj as a table, and remember their names for completion
+fn print_container_table(
+    obj: &KObj,
+    env: &Env,
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    let pod = read_pod_json(env, obj)?;
+    let mut rows = vec![];
+    let mut names = vec![];
+    for kind in [
+        ContainerKind::Init,
+        ContainerKind::Regular,
+        ContainerKind::Ephemeral,
+    ] {
+        for (spec, status) in containers_of(&pod, kind)?.iter() {
+            names.push(spec.name.clone());
+            rows.push(container_row(kind, spec, status.as_ref()));
+        }
+    }
+    env.set_cached_container_names(obj, names);
+    if !writer.print_raw(&pod)? {
+        let titles = row![
+            "Name",
+            "Kind",
+            "Image",
+            "Ready",
+            "State",
+            "Restarts",
+            "Last Reason",
+            "Exit Code",
+            "Requests",
+            "Limits"
+        ];
+        crate::table::print_table(titles, rows, writer);
+    }
+    Ok(())
+}
+
 // conainer helper commands
 fn print_containers(
     obj: &KObj,
@@ -326,10 +645,7 @@ fn print_containers(
         obj.namespace.as_ref().unwrap(),
         Default::default(),
     )?;
-    match env
-        .run_on_context(|c| c.read::<api::ReadNamespacedPodResponse>(request))
-        .unwrap()
-    {
+    match env.run_on_context(|c| c.read::<api::ReadNamespacedPodResponse>(request))? {
         api::ReadNamespacedPodResponse::Ok(pod) => match pod.status {
             Some(status) => {
                 for cont in status.container_statuses.iter() {
@@ -417,7 +733,11 @@ fn print_state_string(state: &Option<api::ContainerState>, writer: &mut ClickWri
     match state {
         Some(state) => {
             if let Some(running) = state.running.as_ref() {
-                clickwrite!(writer, "{}\n", Green.paint("Running"));
+                clickwrite!(
+                    writer,
+                    "{}\n",
+                    theme::style(Role::GoodStatus).paint("Running")
+                );
                 match &running.started_at {
                     Some(start) => clickwrite!(writer, "\t\t  started at: {}\n", start.0),
                     None => clickwrite!(writer, "\t\t  since unknown\n"),
@@ -430,7 +750,11 @@ fn print_state_string(state: &Option<api::ContainerState>, writer: &mut ClickWri
                     .as_ref()
                     .map(|fa| fa.0.to_string())
                     .unwrap_or_else(|| "<unknown>".to_string());
-                clickwrite!(writer, "{}\n", Red.paint("Terminated"));
+                clickwrite!(
+                    writer,
+                    "{}\n",
+                    theme::style(Role::BadStatus).paint("Terminated")
+                );
                 clickwrite!(writer, "\t\t  at: {}\n", tsr);
                 clickwrite!(writer, "\t\t  code: {}\n", terminated.exit_code);
                 clickwrite!(writer, "\t\t  message: {}\n", message);
@@ -438,14 +762,17 @@ fn print_state_string(state: &Option<api::ContainerState>, writer: &mut ClickWri
             } else if let Some(waiting) = state.waiting.as_ref() {
                 let message = waiting.message.as_deref().unwrap_or("no message");
                 let reason = waiting.reason.as_deref().unwrap_or("no reason");
-                clickwrite!(writer, "{}\n", Yellow.paint("Waiting"));
+                clickwrite!(writer, "{}\n", theme::style(Role::Warning).paint("Waiting"));
                 clickwrite!(writer, "\t\t  message: {}\n", message);
                 clickwrite!(writer, "\t\t  reason: {}\n", reason);
             } else {
                 clickwrite!(
                     writer,
                     "{}",
-                    format!("{} (reason unknown)\n", Yellow.paint("Waiting"))
+                    format!(
+                        "{} (reason unknown)\n",
+                        theme::style(Role::Warning).paint("Waiting")
+                    )
                 );
             }
         }
@@ -12,7 +12,6 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use ansi_term::Colour::Yellow;
 use clap::{App, Arg};
 use prettytable::{format, Cell, Row, Table};
 use rustyline::completion::Pair as RustlinePair;
@@ -84,17 +83,11 @@ Examples:
         let ports: Vec<_> = matches.values_of("ports").unwrap().collect(); // unwrap safe, required
 
         let (pod, ns) = {
-            let epod = env.current_pod();
-            match epod {
-                Some(p) => (
-                    p.name().to_string(),
-                    p.namespace.as_ref().unwrap().to_string(),
-                ),
-                None => {
-                    write!(stderr(), "No active pod").unwrap_or(());
-                    return Ok(()); // TODO: Return error
-                }
-            }
+            let pod = env.current_pod()?;
+            (
+                pod.name().to_string(),
+                pod.namespace.as_ref().unwrap().to_string(),
+            )
         };
 
         let context = if let Some(ref context) = env.context {
@@ -103,7 +96,11 @@ Examples:
             return Err(ClickError::CommandError("No active context".to_string()));
         };
 
-        match Command::new("kubectl")
+        let mut command = Command::new("kubectl");
+        if let Some(kubeconfig) = env.command_kubeconfig.as_ref() {
+            command.arg("--kubeconfig").arg(kubeconfig);
+        }
+        match command
             .arg("--namespace")
             .arg(ns)
             .arg("--context")
@@ -127,7 +124,7 @@ Examples:
                                 if read > 0 {
                                     let readstr = String::from_utf8_lossy(&buffer[0..read]);
                                     let mut res = output_clone.lock().unwrap();
-                                    res.push_str(&*readstr);
+                                    res.push_str(&readstr);
                                 } else {
                                     break;
                                 }
@@ -259,6 +256,7 @@ command!(
             }
 
             if stop {
+                writer.finish_paging();
                 clickwrite!(writer, "  [y/N]? ");
                 io::stdout().flush().expect("Could not flush stdout");
                 let mut conf = String::new();
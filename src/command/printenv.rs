@@ -0,0 +1,443 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use ansi_term::Style;
+use clap::{App, Arg};
+use k8s_openapi::api::core::v1 as api;
+use rustyline::completion::Pair as RustlinePair;
+use serde_json::json;
+
+use crate::{
+    command::command_def::{exec_match, start_clap, Cmd},
+    completer,
+    env::Env,
+    error::ClickError,
+    output::ClickWriter,
+};
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+
+/// The annotation kubectl uses to pick the container to exec into when none is given
+const DEFAULT_CONTAINER_ANNOTATION: &str = "kubectl.kubernetes.io/default-container";
+
+const MASK: &str = "********";
+
+/// One variable in a container's environment
+struct EnvEntry {
+    name: String,
+    /// The value, if it's literal or has been resolved
+    value: Option<String>,
+    /// Where a value that isn't literal comes from, like "secret db, key password"
+    from: Option<String>,
+}
+
+/// The container to look at: the one asked for, else the one kubectl would exec into by default,
+/// else the first
+fn pick_container<'a>(
+    pod: &'a api::Pod,
+    name: Option<&str>,
+    writer: &mut ClickWriter,
+) -> Result<&'a api::Container, ClickError> {
+    let spec = pod
+        .spec
+        .as_ref()
+        .ok_or_else(|| ClickError::CommandError("Pod has no spec".to_string()))?;
+    let all = || spec.containers.iter().chain(spec.init_containers.iter());
+    let name = name.or_else(|| {
+        pod.metadata
+            .annotations
+            .get(DEFAULT_CONTAINER_ANNOTATION)
+            .map(|n| n.as_str())
+    });
+    match name {
+        Some(name) => all()
+            .find(|c| c.name == name)
+            .ok_or_else(|| ClickError::CommandError(format!("Pod has no container {}", name))),
+        None => {
+            let container = spec
+                .containers
+                .first()
+                .ok_or_else(|| ClickError::CommandError("Pod has no containers".to_string()))?;
+            if spec.containers.len() > 1 {
+                clickwriteln!(
+                    writer,
+                    "Pod has multiple containers, using {} (use -c to pick another)",
+                    container.name
+                );
+            }
+            Ok(container)
+        }
+    }
+}
+
+/// The value of a fieldRef, for the fields the downward api allows in env vars
+fn field_value(pod: &api::Pod, path: &str) -> Option<String> {
+    let meta = &pod.metadata;
+    let spec = pod.spec.as_ref();
+    let status = pod.status.as_ref();
+    // metadata.labels['app'] and metadata.annotations['x']
+    let keyed = |prefix: &str, map: &BTreeMap<String, String>| {
+        path.strip_prefix(prefix)
+            .and_then(|rest| rest.strip_suffix("']"))
+            .and_then(|key| map.get(key).cloned())
+    };
+    match path {
+        "metadata.name" => meta.name.clone(),
+        "metadata.namespace" => meta.namespace.clone(),
+        "metadata.uid" => meta.uid.clone(),
+        "spec.nodeName" => spec.and_then(|s| s.node_name.clone()),
+        "spec.serviceAccountName" => spec.and_then(|s| s.service_account_name.clone()),
+        "status.hostIP" => status.and_then(|s| s.host_ip.clone()),
+        "status.podIP" => status.and_then(|s| s.pod_ip.clone()),
+        _ => keyed("metadata.labels['", &meta.labels)
+            .or_else(|| keyed("metadata.annotations['", &meta.annotations)),
+    }
+}
+
+/// The value of a resourceFieldRef, like limits.cpu, as the quantity in the container spec. The
+/// divisor isn't applied, that's left to the kubelet.
+fn resource_value(container: &api::Container, resource: &str) -> Option<String> {
+    let resources = container.resources.as_ref()?;
+    let (kind, name) = resource.split_once('.')?;
+    let quantities = match kind {
+        "limits" => &resources.limits,
+        "requests" => &resources.requests,
+        _ => return None,
+    };
+    quantities.get(name).map(|q| q.0.clone())
+}
+
+/// Fetches the configmaps and secrets env vars refer to, each one once, for --resolve
+struct Resolver<'a> {
+    env: &'a Env,
+    namespace: &'a str,
+    show_secrets: bool,
+    config_maps: HashMap<String, Option<api::ConfigMap>>,
+    secrets: HashMap<String, Option<api::Secret>>,
+}
+
+impl<'a> Resolver<'a> {
+    fn config_map(&mut self, name: &str) -> Result<Option<&api::ConfigMap>, ClickError> {
+        if !self.config_maps.contains_key(name) {
+            let (request, _) = api::ConfigMap::read_namespaced_config_map(
+                name,
+                self.namespace,
+                Default::default(),
+            )?;
+            let config_map = match self
+                .env
+                .run_on_context(|c| c.read::<api::ReadNamespacedConfigMapResponse>(request))
+            {
+                Ok(api::ReadNamespacedConfigMapResponse::Ok(config_map)) => Some(config_map),
+                Ok(_) | Err(ClickError::Api { code: 404, .. }) => None,
+                Err(e) => return Err(e),
+            };
+            self.config_maps.insert(name.to_string(), config_map);
+        }
+        Ok(self.config_maps[name].as_ref())
+    }
+
+    fn secret(&mut self, name: &str) -> Result<Option<&api::Secret>, ClickError> {
+        if !self.secrets.contains_key(name) {
+            let (request, _) =
+                api::Secret::read_namespaced_secret(name, self.namespace, Default::default())?;
+            let secret = match self
+                .env
+                .run_on_context(|c| c.read::<api::ReadNamespacedSecretResponse>(request))
+            {
+                Ok(api::ReadNamespacedSecretResponse::Ok(secret)) => Some(secret),
+                Ok(_) | Err(ClickError::Api { code: 404, .. }) => None,
+                Err(e) => return Err(e),
+            };
+            self.secrets.insert(name.to_string(), secret);
+        }
+        Ok(self.secrets[name].as_ref())
+    }
+
+    fn config_map_key(&mut self, name: &str, key: &str) -> Result<String, ClickError> {
+        Ok(match self.config_map(name)? {
+            Some(config_map) => match config_map.data.get(key) {
+                Some(value) => value.clone(),
+                None => format!("<configmap {} has no key {}>", name, key),
+            },
+            None => format!("<configmap {} not found>", name),
+        })
+    }
+
+    fn secret_key(&mut self, name: &str, key: &str) -> Result<String, ClickError> {
+        let show_secrets = self.show_secrets;
+        Ok(match self.secret(name)? {
+            Some(secret) => match secret.data.get(key) {
+                Some(_) if !show_secrets => MASK.to_string(),
+                Some(value) => String::from_utf8_lossy(&value.0).into_owned(),
+                None => format!("<secret {} has no key {}>", name, key),
+            },
+            None => format!("<secret {} not found>", name),
+        })
+    }
+
+    /// All the variables an envFrom source adds. Nothing if what it refers to is missing.
+    fn env_from(&mut self, source: &api::EnvFromSource) -> Result<Vec<EnvEntry>, ClickError> {
+        let prefix = source.prefix.as_deref().unwrap_or("");
+        let mut entries = vec![];
+        if let Some(name) = source
+            .config_map_ref
+            .as_ref()
+            .and_then(|r| r.name.as_deref())
+        {
+            if let Some(config_map) = self.config_map(name)? {
+                for (key, value) in config_map.data.iter() {
+                    entries.push(EnvEntry {
+                        name: format!("{}{}", prefix, key),
+                        value: Some(value.clone()),
+                        from: Some(format!("configmap {}", name)),
+                    });
+                }
+            }
+        }
+        if let Some(name) = source.secret_ref.as_ref().and_then(|r| r.name.as_deref()) {
+            let show_secrets = self.show_secrets;
+            if let Some(secret) = self.secret(name)? {
+                for (key, value) in secret.data.iter() {
+                    let value = if show_secrets {
+                        String::from_utf8_lossy(&value.0).into_owned()
+                    } else {
+                        MASK.to_string()
+                    };
+                    entries.push(EnvEntry {
+                        name: format!("{}{}", prefix, key),
+                        value: Some(value),
+                        from: Some(format!("secret {}", name)),
+                    });
+                }
+            }
+        }
+        Ok(entries)
+    }
+}
+
+/// Describe an envFrom source, when it's not being resolved
+fn env_from_str(source: &api::EnvFromSource) -> String {
+    let mut from = vec![];
+    if let Some(name) = source
+        .config_map_ref
+        .as_ref()
+        .and_then(|r| r.name.as_deref())
+    {
+        from.push(format!("configmap {}", name));
+    }
+    if let Some(name) = source.secret_ref.as_ref().and_then(|r| r.name.as_deref()) {
+        from.push(format!("secret {}", name));
+    }
+    let mut desc = format!("all keys of {}", from.join(" and "));
+    if let Some(prefix) = source.prefix.as_deref() {
+        desc.push_str(&format!(", prefixed with {}", prefix));
+    }
+    desc
+}
+
+/// The environment of container. With a resolver, references are looked up, otherwise they're
+/// just described.
+fn container_env(
+    pod: &api::Pod,
+    container: &api::Container,
+    mut resolver: Option<&mut Resolver>,
+) -> Result<Vec<EnvEntry>, ClickError> {
+    let mut entries = vec![];
+    // envFrom goes first, and anything in env with the same name replaces it
+    for source in container.env_from.iter() {
+        match resolver.as_mut() {
+            Some(resolver) => {
+                for entry in resolver.env_from(source)? {
+                    if !container.env.iter().any(|var| var.name == entry.name) {
+                        entries.push(entry);
+                    }
+                }
+            }
+            None => entries.push(EnvEntry {
+                name: "*".to_string(),
+                value: None,
+                from: Some(env_from_str(source)),
+            }),
+        }
+    }
+    for var in container.env.iter() {
+        let source = match var.value_from.as_ref() {
+            Some(source) => source,
+            None => {
+                entries.push(EnvEntry {
+                    name: var.name.clone(),
+                    value: Some(var.value.clone().unwrap_or_default()),
+                    from: None,
+                });
+                continue;
+            }
+        };
+        let (from, value) = if let Some(sel) = source.config_map_key_ref.as_ref() {
+            let name = sel.name.as_deref().unwrap_or("");
+            let value = match resolver.as_mut() {
+                Some(resolver) => Some(resolver.config_map_key(name, &sel.key)?),
+                None => None,
+            };
+            (format!("configmap {}, key {}", name, sel.key), value)
+        } else if let Some(sel) = source.secret_key_ref.as_ref() {
+            let name = sel.name.as_deref().unwrap_or("");
+            let value = match resolver.as_mut() {
+                Some(resolver) => Some(resolver.secret_key(name, &sel.key)?),
+                None => None,
+            };
+            (format!("secret {}, key {}", name, sel.key), value)
+        } else if let Some(sel) = source.field_ref.as_ref() {
+            let value = resolver
+                .as_ref()
+                .map(|_| field_value(pod, &sel.field_path).unwrap_or_default());
+            (format!("field {}", sel.field_path), value)
+        } else if let Some(sel) = source.resource_field_ref.as_ref() {
+            let value = resolver
+                .as_ref()
+                .map(|_| resource_value(container, &sel.resource).unwrap_or_default());
+            (format!("resource {}", sel.resource), value)
+        } else {
+            ("unknown source".to_string(), None)
+        };
+        entries.push(EnvEntry {
+            name: var.name.clone(),
+            value,
+            from: Some(from),
+        });
+    }
+    Ok(entries)
+}
+
+fn read_pod(env: &Env, name: &str, namespace: &str) -> Result<api::Pod, ClickError> {
+    let (request, _) = api::Pod::read_namespaced_pod(name, namespace, Default::default())?;
+    match env.run_on_context(|c| c.read::<api::ReadNamespacedPodResponse>(request))? {
+        api::ReadNamespacedPodResponse::Ok(pod) => Ok(pod),
+        _ => Err(ClickError::CommandError(format!(
+            "Could not read pod {}",
+            name
+        ))),
+    }
+}
+
+command!(
+    PrintEnv,
+    "printenv",
+    "Show the environment variables of a container in the active pod, and where the ones that \
+     aren't set directly come from",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("container")
+                .short("c")
+                .long("container")
+                .help("The container to look at, if not the one exec would use")
+                .takes_value(true),
+        )
+        .arg(Arg::with_name("resolve").short("r").long("resolve").help(
+            "Look up values from configmaps, secrets, and the pod's fields, and expand \
+                     envFrom",
+        ))
+        .arg(
+            Arg::with_name("show_secrets")
+                .long("show-secrets")
+                .help("With --resolve, show the values from secrets, rather than masking them")
+                .requires("resolve"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .short("o")
+                .help("How to show the variables, if not the current output format")
+                .takes_value(true)
+                .possible_values(&["json", "yaml", "table"]),
+        )
+        .after_help(
+            "This shows what the pod spec says the environment is, not what the running process \
+sees, so it doesn't include variables set by the image or kubernetes (like service addresses). \
+Values from resource fields are shown as the container's request or limit, without a divisor \
+applied.
+
+Examples:
+  # see where the app container's settings come from
+  printenv -c app
+
+  # get the values, secrets included, for a script
+  printenv --resolve --show-secrets -o json",
+        )
+    },
+    vec!["printenv"],
+    noop_complete!(),
+    IntoIterator::into_iter([(
+        "container".to_string(),
+        completer::container_completer as fn(&str, &Env) -> Vec<RustlinePair>
+    )])
+    .collect(),
+    |matches, env, writer| {
+        if let Some(output) = matches.value_of("output") {
+            writer.set_output_format(output.parse()?);
+        }
+        let pod_obj = env.current_pod()?;
+        let namespace = pod_obj.namespace.as_deref().unwrap_or("default");
+        let pod = read_pod(env, pod_obj.name(), namespace)?;
+        let container = pick_container(&pod, matches.value_of("container"), writer)?;
+        let resolve = matches.is_present("resolve");
+        let mut resolver = Resolver {
+            env,
+            namespace,
+            show_secrets: matches.is_present("show_secrets"),
+            config_maps: HashMap::new(),
+            secrets: HashMap::new(),
+        };
+        let entries = container_env(&pod, container, resolve.then_some(&mut resolver))?;
+
+        let value = json!(entries
+            .iter()
+            .map(|entry| json!({
+                "name": entry.name,
+                "value": entry.value,
+                "from": entry.from,
+            }))
+            .collect::<Vec<_>>());
+        if writer.print_raw(&value)? {
+            return Ok(());
+        }
+        if entries.is_empty() {
+            clickwriteln!(
+                writer,
+                "Container {} has no environment variables",
+                container.name
+            );
+            return Ok(());
+        }
+        let from_style = Style::new().dimmed();
+        let specs = entries
+            .iter()
+            .map(|entry| {
+                let value = match (entry.value.as_deref(), entry.from.as_deref()) {
+                    (Some(value), Some(from)) if resolve => {
+                        format!("{} {}", value, from_style.paint(format!("(from {})", from)))
+                    }
+                    (Some(value), _) => value.to_string(),
+                    (None, Some(from)) => from_style.paint(format!("<from {}>", from)).to_string(),
+                    (None, None) => String::new(),
+                };
+                vec![entry.name.clone().into(), value.into()]
+            })
+            .collect();
+        crate::table::print_table(row!["Name", "Value"], specs, writer);
+        Ok(())
+    }
+);
@@ -0,0 +1,172 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use clap::{App, Arg};
+use rustyline::completion::Pair as RustlinePair;
+
+use crate::{
+    command::command_def::{exec_match, start_clap, Cmd},
+    command::kubectl_spawn_error,
+    completer,
+    env::{Env, Proxy},
+    error::ClickError,
+    output::ClickWriter,
+};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long to watch a new proxy for failing to start (i.e. because the port is in use)
+const STARTUP_WAIT: Duration = Duration::from_millis(750);
+
+fn start_proxy(
+    env: &mut Env,
+    port: u16,
+    address: &str,
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    if let Some(proxy) = env.proxy() {
+        return Err(ClickError::CommandError(format!(
+            "A proxy is already running at {} (for context {}), stop it with 'proxy stop' first",
+            proxy.url, proxy.context
+        )));
+    }
+    let context = match env.context {
+        Some(ref context) => context.name.clone(),
+        None => return Err(ClickError::CommandError("No active context".to_string())),
+    };
+    let port_arg = format!("--port={}", port);
+    let address_arg = format!("--address={}", address);
+    let mut args = vec!["--context", &context, "proxy", &port_arg, &address_arg];
+    if let Some(kubeconfig) = env.command_kubeconfig.as_ref().and_then(|k| k.to_str()) {
+        args.splice(0..0, ["--kubeconfig", kubeconfig]);
+    }
+    let handle = duct::cmd("kubectl", &args)
+        .stdin_null()
+        .stdout_null()
+        .stderr_capture()
+        .unchecked()
+        .start()
+        .map_err(kubectl_spawn_error)?;
+
+    // kubectl exits right away if it can't listen, so give it a moment to fail
+    let start = Instant::now();
+    while start.elapsed() < STARTUP_WAIT {
+        if let Some(output) = handle.try_wait()? {
+            return Err(ClickError::KubectlError {
+                exit_code: output.status.code().unwrap_or(-1),
+                stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    let host = if address == "0.0.0.0" {
+        "localhost"
+    } else {
+        address
+    };
+    let url = format!("http://{}:{}/", host, port);
+    clickwriteln!(
+        writer,
+        "Proxying the api server for {} at {}  (stop with 'proxy stop')",
+        context,
+        url
+    );
+    env.set_proxy(Proxy {
+        handle,
+        url,
+        context,
+    });
+    Ok(())
+}
+
+command!(
+    ProxyCmd,
+    "proxy",
+    "Run kubectl proxy in the background, to access the api server with local tools",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("action")
+                .help(
+                    "start: start a proxy (the default)\n\
+                     stop: stop the running proxy\n\
+                     status: show the running proxy, if there is one",
+                )
+                .possible_values(&["start", "stop", "status"])
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("port")
+                .short("p")
+                .long("port")
+                .help("The local port to listen on (default is the proxy_port option, 8001 unless set)")
+                .takes_value(true)
+                .validator(|s: String| s.parse::<u16>().map(|_| ()).map_err(|e| e.to_string())),
+        )
+        .arg(
+            Arg::with_name("address")
+                .long("address")
+                .help("The local address to listen on")
+                .takes_value(true)
+                .default_value("127.0.0.1"),
+        )
+        .after_help(
+            "Only one proxy runs at a time. It keeps talking to the context that was active when it \
+was started, even if you switch contexts. It's stopped when click exits.
+
+Examples:
+  # start a proxy, then use it from another terminal with: curl localhost:8001/api/v1/namespaces
+  proxy
+
+  # proxy on port 9000 instead
+  proxy -p 9000",
+        )
+    },
+    vec!["proxy"],
+    vec![&completer::proxyaction_values_completer],
+    no_named_complete!(),
+    |matches, env, writer| {
+        match matches.value_of("action").unwrap_or("start") {
+            "stop" => match env.take_proxy() {
+                Some(proxy) => {
+                    proxy.handle.kill()?;
+                    clickwriteln!(writer, "Stopped proxy at {}", proxy.url);
+                }
+                None => clickwriteln!(writer, "No proxy is running"),
+            },
+            "status" => match env.proxy() {
+                Some(proxy) => clickwriteln!(
+                    writer,
+                    "Proxy running at {} (for context {})",
+                    proxy.url,
+                    proxy.context
+                ),
+                None => clickwriteln!(writer, "No proxy is running"),
+            },
+            _ => {
+                let port = match matches.value_of("port") {
+                    Some(port) => port.parse().unwrap(), // safe, validated
+                    None => env.click_config.proxy_port,
+                };
+                let address = matches.value_of("address").unwrap(); // safe, has a default
+                start_proxy(env, port, address, writer)?;
+            }
+        }
+        Ok(())
+    }
+);
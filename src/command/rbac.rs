@@ -0,0 +1,551 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use clap::{App, Arg, ArgGroup};
+use k8s_openapi::api::authorization::v1 as authz;
+use k8s_openapi::api::core::v1 as api;
+use k8s_openapi::CreateResponse;
+use prettytable::Table;
+use rustyline::completion::Pair as RustlinePair;
+
+use crate::{
+    command::command_def::{exec_match, start_clap, Cmd},
+    command::{kubectl_command, kubectl_exit_error, kubectl_output, kubectl_spawn_error},
+    completer,
+    config::Impersonation,
+    env::Env,
+    error::ClickError,
+    output::ClickWriter,
+    theme::{self, Role},
+};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::Command;
+
+/// Who we're asking kubectl about
+enum Subject {
+    ServiceAccount(String, String),
+    User(String),
+    Group(String),
+}
+
+impl Subject {
+    fn add_args(&self, command: &mut Command) {
+        match self {
+            Subject::ServiceAccount(ns, name) => {
+                command.arg(format!("--as=system:serviceaccount:{}:{}", ns, name));
+            }
+            Subject::User(name) => {
+                command.arg(format!("--as={}", name));
+            }
+            Subject::Group(name) => {
+                // kubectl requires a user when impersonating a group, so use a placeholder one
+                command
+                    .arg("--as=click-rbac-check")
+                    .arg(format!("--as-group={}", name));
+            }
+        }
+    }
+
+    fn description(&self) -> String {
+        match self {
+            Subject::ServiceAccount(ns, name) => format!("serviceaccount {}/{}", ns, name),
+            Subject::User(name) => format!("user {}", name),
+            Subject::Group(name) => format!("group {}", name),
+        }
+    }
+}
+
+/// Figure out the service account of the currently selected pod
+fn current_pod_subject(env: &Env) -> Result<Subject, ClickError> {
+    if env.current_pod().is_err() {
+        return Err(ClickError::CommandError(
+            "No pod selected. Select a pod, or specify --serviceaccount, --user, or --group"
+                .to_string(),
+        ));
+    }
+    let (ns, sa) = pod_service_account(env)?;
+    Ok(Subject::ServiceAccount(ns, sa))
+}
+
+/// The namespace and name of the service account the currently selected pod runs as
+pub fn pod_service_account(env: &Env) -> Result<(String, String), ClickError> {
+    let pod = env.current_pod()?;
+    let ns = pod.namespace.as_ref().unwrap(); // pods are always namespaced
+    let (request, _) = api::Pod::read_namespaced_pod(pod.name(), ns, Default::default())?;
+    match env.run_on_context(|c| c.read::<api::ReadNamespacedPodResponse>(request))? {
+        api::ReadNamespacedPodResponse::Ok(pod) => {
+            let sa = pod
+                .spec
+                .and_then(|spec| spec.service_account_name)
+                .unwrap_or_else(|| "default".to_string());
+            Ok((ns.clone(), sa))
+        }
+        _ => Err(ClickError::CommandError(format!(
+            "Could not read pod {}",
+            pod.name()
+        ))),
+    }
+}
+
+/// The namespace and name of a service account given as NAMESPACE/NAME, or just NAME for one in the
+/// current namespace
+pub fn service_account_arg(env: &Env, sa: &str) -> Result<(String, String), ClickError> {
+    match sa.split_once('/') {
+        Some((ns, name)) => Ok((ns.to_string(), name.to_string())),
+        None => match env.namespace.as_ref() {
+            Some(ns) => Ok((ns.clone(), sa.to_string())),
+            None => Err(ClickError::CommandError(
+                "Specify service account as NAMESPACE/NAME, or set a namespace".to_string(),
+            )),
+        },
+    }
+}
+
+fn get_subject(matches: &clap::ArgMatches, env: &Env) -> Result<Subject, ClickError> {
+    if let Some(sa) = matches.value_of("serviceaccount") {
+        let (ns, name) = service_account_arg(env, sa)?;
+        Ok(Subject::ServiceAccount(ns, name))
+    } else if let Some(user) = matches.value_of("user") {
+        Ok(Subject::User(user.to_string()))
+    } else if let Some(group) = matches.value_of("group") {
+        Ok(Subject::Group(group.to_string()))
+    } else {
+        current_pod_subject(env)
+    }
+}
+
+/// get the byte offset that each named column starts at in the header of kubectl's output
+fn column_starts(header: &str, names: &[&str]) -> Option<Vec<usize>> {
+    names.iter().map(|name| header.find(name)).collect()
+}
+
+fn column_at<'a>(line: &'a str, starts: &[usize], idx: usize) -> &'a str {
+    let start = starts[idx].min(line.len());
+    let end = starts
+        .get(idx + 1)
+        .map_or(line.len(), |e| (*e).min(line.len()));
+    line.get(start..end).unwrap_or("").trim()
+}
+
+/// format a kubectl list column like "[get list watch]" into "get, list, watch"
+fn unbracket(s: &str) -> String {
+    s.trim_start_matches('[')
+        .trim_end_matches(']')
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(", ")
+}
+
+/// parse the output of 'kubectl auth can-i --list' into a table of resource, api group, resource
+/// names, and verbs
+fn build_rules_table(output: &str) -> Result<Table, ClickError> {
+    let mut lines = output.lines();
+    let header = lines.next().unwrap_or("");
+    let starts = column_starts(
+        header,
+        &["Resources", "Non-Resource URLs", "Resource Names", "Verbs"],
+    )
+    .ok_or_else(|| ClickError::ParseErr(format!("Unexpected output from kubectl: {}", header)))?;
+    let mut table = Table::new();
+    table.set_titles(row!["Resource", "API Group", "Resource Names", "Verbs"]);
+    for line in lines.filter(|l| !l.trim().is_empty()) {
+        let resource = column_at(line, &starts, 0);
+        let urls = unbracket(column_at(line, &starts, 1));
+        let names = unbracket(column_at(line, &starts, 2));
+        let verbs = unbracket(column_at(line, &starts, 3));
+        if resource.is_empty() {
+            // a non-resource url rule
+            table.add_row(row![urls, "", names, verbs]);
+        } else {
+            let (res, group) = match resource.find('.') {
+                Some(idx) => (&resource[..idx], &resource[idx + 1..]),
+                None => (resource, "core"),
+            };
+            table.add_row(row![res, group, names, verbs]);
+        }
+    }
+    Ok(table)
+}
+
+/// Split a resource like deployments.apps/scale into its resource, group, and subresource
+fn split_resource(resource: &str) -> (&str, &str, Option<&str>) {
+    let (resource, subresource) = match resource.split_once('/') {
+        Some((resource, sub)) => (resource, Some(sub)),
+        None => (resource, None),
+    };
+    match resource.split_once('.') {
+        Some((resource, group)) => (resource, group, subresource),
+        None => (resource, "", subresource),
+    }
+}
+
+/// The groups the api server puts a user in, which a SubjectAccessReview needs to be told about.
+/// Service accounts are in groups for all service accounts, and those in their namespace.
+fn implicit_groups(user: &str) -> Vec<String> {
+    let mut groups = vec![];
+    if let Some(sa) = user.strip_prefix("system:serviceaccount:") {
+        if let Some((ns, _)) = sa.split_once(':') {
+            groups.push("system:serviceaccounts".to_string());
+            groups.push(format!("system:serviceaccounts:{}", ns));
+        }
+    }
+    groups.push("system:authenticated".to_string());
+    groups
+}
+
+/// Ask the api server if verb can be done to resource, as ourselves, or as_user if given.
+/// Returns the status of the review.
+fn access_review(
+    env: &Env,
+    verb: &str,
+    resource: &str,
+    name: Option<&str>,
+    namespace: Option<&str>,
+    as_user: Option<&Impersonation>,
+) -> Result<authz::SubjectAccessReviewStatus, ClickError> {
+    // like kubectl, a resource starting with / is a url, like /healthz
+    let (resource_attributes, non_resource_attributes) = if resource.starts_with('/') {
+        let attrs = authz::NonResourceAttributes {
+            path: Some(resource.to_string()),
+            verb: Some(verb.to_string()),
+        };
+        (None, Some(attrs))
+    } else {
+        let (resource, group, subresource) = split_resource(resource);
+        let attrs = authz::ResourceAttributes {
+            group: Some(group.to_string()),
+            name: name.map(|n| n.to_string()),
+            namespace: namespace.map(|ns| ns.to_string()),
+            resource: Some(resource.to_string()),
+            subresource: subresource.map(|s| s.to_string()),
+            verb: Some(verb.to_string()),
+            version: None,
+        };
+        (Some(attrs), None)
+    };
+
+    let status = match as_user {
+        Some(imp) => {
+            let mut groups = imp.groups.clone();
+            groups.extend(implicit_groups(&imp.user));
+            let review = authz::SubjectAccessReview {
+                spec: authz::SubjectAccessReviewSpec {
+                    groups,
+                    non_resource_attributes,
+                    resource_attributes,
+                    user: Some(imp.user.clone()),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let (request, _) = authz::SubjectAccessReview::create_subject_access_review(
+                &review,
+                Default::default(),
+            )?;
+            match env
+                .run_on_context(|c| c.read::<CreateResponse<authz::SubjectAccessReview>>(request))?
+            {
+                CreateResponse::Ok(review) | CreateResponse::Created(review) => review.status,
+                _ => None,
+            }
+        }
+        None => {
+            let review = authz::SelfSubjectAccessReview {
+                spec: authz::SelfSubjectAccessReviewSpec {
+                    non_resource_attributes,
+                    resource_attributes,
+                },
+                ..Default::default()
+            };
+            let (request, _) = authz::SelfSubjectAccessReview::create_self_subject_access_review(
+                &review,
+                Default::default(),
+            )?;
+            match env.run_on_context(|c| {
+                c.read::<CreateResponse<authz::SelfSubjectAccessReview>>(request)
+            })? {
+                CreateResponse::Ok(review) | CreateResponse::Created(review) => review.status,
+                _ => None,
+            }
+        }
+    };
+    status.ok_or_else(|| {
+        ClickError::CommandError("The api server didn't say if that's allowed".to_string())
+    })
+}
+
+/// Ask the api server for everything we (or as_user, by impersonating them) can do in namespace
+fn rules_review(
+    env: &Env,
+    namespace: &str,
+    as_user: Option<&Impersonation>,
+) -> Result<authz::SubjectRulesReviewStatus, ClickError> {
+    // a copy, so only this request impersonates
+    let context = env
+        .context
+        .clone()
+        .ok_or_else(|| ClickError::CommandError("No active context".to_string()))?;
+    if let Some(imp) = as_user {
+        context.set_impersonate(Some(imp.clone()));
+    }
+    let review = authz::SelfSubjectRulesReview {
+        spec: authz::SelfSubjectRulesReviewSpec {
+            namespace: Some(namespace.to_string()),
+        },
+        ..Default::default()
+    };
+    let (request, _) = authz::SelfSubjectRulesReview::create_self_subject_rules_review(
+        &review,
+        Default::default(),
+    )?;
+    let status = match context.read::<CreateResponse<authz::SelfSubjectRulesReview>>(request)? {
+        CreateResponse::Ok(review) | CreateResponse::Created(review) => review.status,
+        _ => None,
+    };
+    status.ok_or_else(|| {
+        ClickError::CommandError("The api server didn't return any rules".to_string())
+    })
+}
+
+/// A table of resource, api group, resource names, and verbs for rules, like the one rbac prints
+fn rules_table(status: &authz::SubjectRulesReviewStatus) -> Table {
+    let mut table = Table::new();
+    table.set_titles(row!["Resource", "API Group", "Resource Names", "Verbs"]);
+    for rule in status.resource_rules.iter() {
+        let groups = if rule.api_groups.is_empty() {
+            vec![String::new()]
+        } else {
+            rule.api_groups.clone()
+        };
+        for resource in rule.resources.iter() {
+            for group in groups.iter() {
+                table.add_row(row![
+                    resource,
+                    if group.is_empty() { "core" } else { group },
+                    rule.resource_names.join(", "),
+                    rule.verbs.join(", ")
+                ]);
+            }
+        }
+    }
+    for rule in status.non_resource_rules.iter() {
+        table.add_row(row![
+            rule.non_resource_urls.join(", "),
+            "",
+            "",
+            rule.verbs.join(", ")
+        ]);
+    }
+    table
+}
+
+command!(
+    CanI,
+    "can-i",
+    "Check if you (or another user with --as) can do VERB to RESOURCE, by asking the api server. \
+     With --list, show everything you can do in the current namespace.",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("verb")
+                .help("The verb to check, like get, list, create, or delete")
+                .required_unless("list")
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("resource")
+                .help(
+                    "The resource to check, like pods, deployments.apps, pods/log, or a url \
+                     like /healthz",
+                )
+                .required_unless("list")
+                .index(2),
+        )
+        .arg(
+            Arg::with_name("name")
+                .help("Check for only the object with this name")
+                .index(3),
+        )
+        .arg(
+            Arg::with_name("all_namespaces")
+                .long("all-namespaces")
+                .short("A")
+                .help("Check in all namespaces, rather than the current one")
+                .conflicts_with("list"),
+        )
+        .arg(
+            Arg::with_name("list")
+                .long("list")
+                .short("l")
+                .help("List everything that can be done in the current namespace")
+                .conflicts_with_all(&["verb", "resource", "name"]),
+        )
+        .after_help(
+            "Checking as another user needs permission to create subjectaccessreviews, and \
+listing as another user needs permission to impersonate them.
+
+Examples:
+  # can I delete pods here?
+  can-i delete pods
+
+  # can the ci service account update the web deployment?
+  can-i update deployments.apps web --as system:serviceaccount:ci:deployer
+
+  # what can I do in this namespace?
+  can-i --list",
+        )
+    },
+    vec!["can-i", "cani"],
+    noop_complete!(),
+    no_named_complete!(),
+    |matches, env, writer| {
+        // --as here means who to check for, which is done by asking about them rather than
+        // asking as them, so this command's requests don't impersonate them
+        env.set_command_impersonate(None);
+        let as_user = matches.value_of("as").map(|user| Impersonation {
+            user: user.to_string(),
+            groups: matches
+                .values_of("as_group")
+                .map(|groups| groups.map(|g| g.to_string()).collect())
+                .unwrap_or_default(),
+        });
+        if matches.is_present("list") {
+            let ns = env.namespace.as_deref().unwrap_or("default");
+            let status = rules_review(env, ns, as_user.as_ref())?;
+            clickwriteln!(
+                writer,
+                "Permissions for {} in namespace {}:",
+                as_user
+                    .as_ref()
+                    .map(|imp| format!("user {}", imp.user))
+                    .unwrap_or_else(|| "you".to_string()),
+                ns
+            );
+            crate::table::print_filled_table(&mut rules_table(&status), writer);
+            if status.incomplete {
+                clickwriteln!(
+                    writer,
+                    "{}",
+                    theme::style(Role::Warning).paint(
+                        "The list is incomplete, as some of the authorizers can't list rules"
+                    )
+                );
+            }
+            if let Some(err) = status.evaluation_error.as_ref() {
+                clickwriteln!(writer, "Evaluation error: {}", err);
+            }
+            return Ok(());
+        }
+
+        let verb = matches.value_of("verb").unwrap(); // safe, required without --list
+        let resource = matches.value_of("resource").unwrap(); // safe, required without --list
+        let namespace = if matches.is_present("all_namespaces") {
+            None
+        } else {
+            env.namespace.as_deref()
+        };
+        let status = access_review(
+            env,
+            verb,
+            resource,
+            matches.value_of("name"),
+            namespace,
+            as_user.as_ref(),
+        )?;
+        if status.allowed {
+            clickwriteln!(writer, "{}", theme::style(Role::GoodStatus).paint("yes"));
+        } else {
+            clickwriteln!(writer, "{}", theme::style(Role::BadStatus).paint("no"));
+        }
+        if let Some(reason) = status.reason.as_deref().filter(|r| !r.is_empty()) {
+            clickwriteln!(writer, "Reason: {}", reason);
+        }
+        if let Some(err) = status.evaluation_error.as_deref().filter(|e| !e.is_empty()) {
+            clickwriteln!(writer, "Evaluation error: {}", err);
+        }
+        Ok(())
+    }
+);
+
+command!(
+    Rbac,
+    "rbac",
+    "Show what a service account, user, or group is allowed to do. With no target specified, \
+     shows the permissions of the service account of the currently selected pod.",
+    |clap: App<'static, 'static>| clap
+        .arg(
+            Arg::with_name("serviceaccount")
+                .long("serviceaccount")
+                .visible_alias("sa")
+                .help(
+                    "Show permissions for this service account. Specify as NAMESPACE/NAME, or \
+                     just NAME to use the current namespace"
+                )
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("user")
+                .long("user")
+                .short("u")
+                .help("Show permissions for this user")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("group")
+                .long("group")
+                .short("g")
+                .help("Show permissions for this group")
+                .takes_value(true)
+        )
+        .group(ArgGroup::with_name("target").args(&["serviceaccount", "user", "group"]))
+        .arg(
+            Arg::with_name("check")
+                .long("check")
+                .help("Only check if VERB can be performed on RESOURCE, and print yes or no")
+                .takes_value(true)
+                .number_of_values(2)
+                .value_names(&["VERB", "RESOURCE"])
+        ),
+    vec!["rbac"],
+    noop_complete!(),
+    no_named_complete!(),
+    |matches, env, writer| {
+        let subject = get_subject(&matches, env)?;
+        let mut command = kubectl_command(env, env.namespace.as_deref())?;
+        command.arg("auth").arg("can-i");
+        subject.add_args(&mut command);
+        if let Some(mut check) = matches.values_of("check") {
+            let verb = check.next().unwrap(); // safe, two values required
+            let resource = check.next().unwrap();
+            command.arg(verb).arg(resource);
+            // can-i exits non-zero for "no", so we can't just use kubectl_output here
+            let output = command.output().map_err(kubectl_spawn_error)?;
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            match stdout.trim() {
+                "yes" => clickwriteln!(writer, "{}", theme::style(Role::GoodStatus).paint("yes")),
+                "no" => clickwriteln!(writer, "{}", theme::style(Role::BadStatus).paint("no")),
+                _ => return Err(kubectl_exit_error(output.status, &output.stderr)),
+            }
+        } else {
+            command.arg("--list");
+            let output = kubectl_output(command)?;
+            clickwriteln!(writer, "Permissions for {}:", subject.description());
+            let mut table = build_rules_table(&output)?;
+            crate::table::print_filled_table(&mut table, writer);
+        }
+        Ok(())
+    }
+);
@@ -12,21 +12,23 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use ansi_term::Colour::Yellow;
 use clap::{App, Arg};
 use k8s_openapi::api::apps::v1 as apps_api;
+use k8s_openapi::{http::Request, List, ListOptional};
 
 use crate::{
     command::command_def::{exec_match, show_arg, sort_arg, start_clap, Cmd},
-    command::{keyval_string, run_list_command, Extractor},
+    command::delete::confirm_delete_all,
+    command::{extract_age, keyval_string, run_list_command, Extractor},
     completer,
     env::Env,
+    error::ClickError,
     kobj::{KObj, ObjType},
     output::ClickWriter,
     table::CellSpec,
+    theme::{self, Role},
 };
 
-use std::array::IntoIter;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::io::Write;
@@ -34,8 +36,11 @@ use std::io::Write;
 lazy_static! {
     static ref RS_EXTRACTORS: HashMap<String, Extractor<apps_api::ReplicaSet>> = {
         let mut m: HashMap<String, Extractor<apps_api::ReplicaSet>> = HashMap::new();
+        m.insert("Name".to_owned(), rs_name);
+        m.insert("Age".to_owned(), rs_age);
         m.insert("Current".to_owned(), rs_current);
         m.insert("Containers".to_owned(), rs_containers);
+        m.insert("Deployment".to_owned(), rs_deployment);
         m.insert("Desired".to_owned(), rs_desired);
         m.insert("Images".to_owned(), rs_images);
         m.insert("Ready".to_owned(), rs_ready);
@@ -48,6 +53,7 @@ const COL_MAP: &[(&str, &str)] = &[
     ("desired", "Desired"),
     ("current", "Current"),
     ("ready", "Ready"),
+    ("deployment", "Deployment"),
     ("age", "Age"),
 ];
 
@@ -63,15 +69,55 @@ const EXTRA_COL_MAP: &[(&str, &str)] = &[
 
 const EXTRA_COL_FLAGS: &[&str] = &{ extract_first!(EXTRA_COL_MAP) };
 
+/// style used for replicasets that have been scaled to zero, usually old revisions of a deployment
+const INACTIVE_STYLE: &str = "FD";
+
 fn rs_to_kobj(replicaset: &apps_api::ReplicaSet) -> KObj {
     let meta = &replicaset.metadata;
     KObj {
         name: meta.name.clone().unwrap_or_else(|| "<Unknown>".into()),
         namespace: meta.namespace.clone(),
+        uid: meta.uid.clone(),
+        context: None,
         typ: ObjType::ReplicaSet,
     }
 }
 
+/// A replicaset is inactive if it wants, and has, zero replicas
+fn is_inactive(replicaset: &apps_api::ReplicaSet) -> bool {
+    let desired = replicaset
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.replicas)
+        .unwrap_or(1);
+    let current = replicaset
+        .status
+        .as_ref()
+        .map(|stat| stat.replicas)
+        .unwrap_or(0);
+    desired == 0 && current == 0
+}
+
+/// Dim the cell if the replicaset is inactive
+fn dim_inactive<'a>(replicaset: &apps_api::ReplicaSet, mut cell: CellSpec<'a>) -> CellSpec<'a> {
+    if is_inactive(replicaset) {
+        cell.style = Some(INACTIVE_STYLE);
+    }
+    cell
+}
+
+fn rs_name(replicaset: &apps_api::ReplicaSet) -> Option<CellSpec<'_>> {
+    replicaset
+        .metadata
+        .name
+        .as_deref()
+        .map(|name| dim_inactive(replicaset, name.into()))
+}
+
+fn rs_age(replicaset: &apps_api::ReplicaSet) -> Option<CellSpec<'_>> {
+    extract_age(replicaset).map(|age| dim_inactive(replicaset, age))
+}
+
 fn rs_containers(replicaset: &apps_api::ReplicaSet) -> Option<CellSpec<'_>> {
     replicaset.spec.as_ref().and_then(|spec| {
         spec.template.as_ref().and_then(|template| {
@@ -103,12 +149,15 @@ fn rs_images(replicaset: &apps_api::ReplicaSet) -> Option<CellSpec<'_>> {
 }
 
 fn rs_current(replicaset: &apps_api::ReplicaSet) -> Option<CellSpec<'_>> {
-    replicaset.status.as_ref().map(|stat| stat.replicas.into())
+    replicaset
+        .status
+        .as_ref()
+        .map(|stat| dim_inactive(replicaset, stat.replicas.into()))
 }
 
 fn rs_desired(replicaset: &apps_api::ReplicaSet) -> Option<CellSpec<'_>> {
     replicaset.spec.as_ref().map(|spec| match spec.replicas {
-        Some(desired) => desired.into(),
+        Some(desired) => dim_inactive(replicaset, desired.into()),
         None => "Unspecified".into(),
     })
 }
@@ -117,10 +166,20 @@ fn rs_ready(replicaset: &apps_api::ReplicaSet) -> Option<CellSpec<'_>> {
     replicaset
         .status
         .as_ref()
-        .map(|stat| match stat.ready_replicas {
-            Some(ready) => ready.into(),
-            None => 0.into(),
-        })
+        .map(|stat| dim_inactive(replicaset, stat.ready_replicas.unwrap_or(0).into()))
+}
+
+/// The name of the deployment that owns this replicaset, if any
+fn rs_deployment(replicaset: &apps_api::ReplicaSet) -> Option<CellSpec<'_>> {
+    let owner = replicaset
+        .metadata
+        .owner_references
+        .iter()
+        .find(|owner| owner.kind == "Deployment");
+    Some(match owner {
+        Some(owner) => dim_inactive(replicaset, owner.name.as_str().into()),
+        None => "<none>".into(),
+    })
 }
 
 fn rs_selector(replicaset: &apps_api::ReplicaSet) -> Option<CellSpec<'_>> {
@@ -130,13 +189,59 @@ fn rs_selector(replicaset: &apps_api::ReplicaSet) -> Option<CellSpec<'_>> {
         .map(|spec| keyval_string(&spec.selector.match_labels).into())
 }
 
+/// Delete all the zero-replica replicasets returned by request, after asking for confirmation
+fn trim_replicasets(
+    env: &mut Env,
+    writer: &mut ClickWriter,
+    request: Request<Vec<u8>>,
+) -> Result<(), ClickError> {
+    let list: List<apps_api::ReplicaSet> = env.run_on_context(|c| c.execute_list(request))?;
+    let inactive: Vec<KObj> = list
+        .items
+        .iter()
+        .filter(|rs| is_inactive(rs))
+        .map(rs_to_kobj)
+        .collect();
+    if inactive.is_empty() {
+        clickwriteln!(writer, "No replicasets with zero replicas");
+        return Ok(());
+    }
+    clickwriteln!(writer, "Replicasets with zero replicas:");
+    for rs in inactive.iter() {
+        clickwriteln!(
+            writer,
+            "  {} [{}]",
+            rs.name(),
+            rs.namespace.as_deref().unwrap_or("")
+        );
+    }
+    clickwriteln!(
+        writer,
+        "{}",
+        theme::style(Role::Warning)
+            .paint("Note: deployments can't be rolled back to revisions that are deleted")
+    );
+    confirm_delete_all(
+        env,
+        writer,
+        &inactive,
+        &format!("Delete {} replicasets", inactive.len()),
+    )
+}
+
 list_command!(
     ReplicaSets,
     "replicasets",
-    "Get replicasets (in current namespace if set)",
+    "Get replicasets (in current namespace if set). Replicasets scaled to zero are dimmed",
     super::COL_FLAGS,
     super::EXTRA_COL_FLAGS,
     |clap: App<'static, 'static>| clap
+        .arg(
+            Arg::with_name("name")
+                .help("Only get the replicaset with this name")
+                .required(false)
+                .index(1)
+        )
         .arg(
             Arg::with_name("show_label")
                 .short("L")
@@ -151,6 +256,12 @@ list_command!(
                 .help("Filter replicasets by the specified regex")
                 .takes_value(true)
         )
+        .arg(
+            Arg::with_name("trim")
+                .long("trim")
+                .help("Delete all replicasets with zero replicas (will ask for confirmation)")
+                .takes_value(false)
+        )
         .arg(show_arg(EXTRA_COL_FLAGS, true))
         .arg(sort_arg(COL_FLAGS, Some(EXTRA_COL_FLAGS)))
         .arg(
@@ -161,13 +272,25 @@ list_command!(
                 .takes_value(false),
         ),
     vec!["rs", "replicasets"],
-    noop_complete!(),
-    IntoIter::new([]),
+    vec![&completer::replicaset_completer],
+    [],
     |matches, env, writer| {
+        let field_sel = matches
+            .value_of("name")
+            .map(|name| format!("metadata.name={}", name));
+        let opts = ListOptional {
+            field_selector: field_sel.as_deref(),
+            ..Default::default()
+        };
         let (request, _response_body) = match &env.namespace {
-            Some(ns) => apps_api::ReplicaSet::list_namespaced_replica_set(ns, Default::default())?,
-            None => apps_api::ReplicaSet::list_replica_set_for_all_namespaces(Default::default())?,
+            Some(ns) => apps_api::ReplicaSet::list_namespaced_replica_set(ns, opts)?,
+            None => apps_api::ReplicaSet::list_replica_set_for_all_namespaces(opts)?,
         };
+
+        if matches.is_present("trim") {
+            return trim_replicasets(env, writer, request);
+        }
+
         let cols: Vec<&str> = COL_MAP.iter().map(|(_, col)| *col).collect();
 
         run_list_command(
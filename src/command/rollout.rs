@@ -0,0 +1,400 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use clap::{App, Arg};
+use k8s_openapi::api::apps::v1 as apps_api;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use k8s_openapi::List;
+use rustyline::completion::Pair as RustlinePair;
+use serde_json::Value;
+
+use crate::{
+    command::command_def::{exec_match, start_clap, Cmd},
+    command::{
+        interruptible_sleep, kubectl_get_json, kubectl_mutate, kubectl_obj_arg,
+        kubectl_obj_command, parse_duration, valid_duration, valid_u32,
+    },
+    completer,
+    env::{Env, ErrorStrategy},
+    error::ClickError,
+    kobj::{KObj, ObjType},
+    output::ClickWriter,
+    table::CellSpec,
+    theme::Role,
+};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+const REVISION_ANNOTATION: &str = "deployment.kubernetes.io/revision";
+const CHANGE_CAUSE_ANNOTATION: &str = "kubernetes.io/change-cause";
+const RESTARTED_AT_ANNOTATION: &str = "kubectl.kubernetes.io/restartedAt";
+
+fn count(value: &Value, pointer: &str) -> i64 {
+    value.pointer(pointer).and_then(Value::as_i64).unwrap_or(0)
+}
+
+/// How far along a rollout is
+#[derive(PartialEq)]
+struct Progress {
+    desired: i64,
+    updated: i64,
+    ready: i64,
+    available: i64,
+    done: bool,
+}
+
+/// Figure out the progress of the rollout of value, which is the json for obj. Returns an error if
+/// the rollout has failed.
+fn rollout_progress(obj: &KObj, value: &Value) -> Result<Progress, ClickError> {
+    // until the controller has seen the latest spec, the status is for the previous one
+    let observed =
+        count(value, "/status/observedGeneration") >= count(value, "/metadata/generation");
+    match obj.typ {
+        ObjType::Deployment => {
+            let failed = value
+                .pointer("/status/conditions")
+                .and_then(Value::as_array)
+                .is_some_and(|conds| {
+                    conds.iter().any(|cond| {
+                        cond.get("type").and_then(Value::as_str) == Some("Progressing")
+                            && cond.get("reason").and_then(Value::as_str)
+                                == Some("ProgressDeadlineExceeded")
+                    })
+                });
+            if failed {
+                return Err(ClickError::CommandError(format!(
+                    "Rollout of {} failed: progress deadline exceeded",
+                    kubectl_obj_arg(obj)
+                )));
+            }
+            let desired = value
+                .pointer("/spec/replicas")
+                .and_then(Value::as_i64)
+                .unwrap_or(1);
+            let updated = count(value, "/status/updatedReplicas");
+            let available = count(value, "/status/availableReplicas");
+            Ok(Progress {
+                desired,
+                updated,
+                ready: count(value, "/status/readyReplicas"),
+                available,
+                // old replicas are still around until replicas == updated
+                done: observed
+                    && updated == desired
+                    && count(value, "/status/replicas") == updated
+                    && available == desired,
+            })
+        }
+        ObjType::DaemonSet => {
+            let desired = count(value, "/status/desiredNumberScheduled");
+            let updated = count(value, "/status/updatedNumberScheduled");
+            let available = count(value, "/status/numberAvailable");
+            Ok(Progress {
+                desired,
+                updated,
+                ready: count(value, "/status/numberReady"),
+                available,
+                done: observed && updated == desired && available == desired,
+            })
+        }
+        ObjType::StatefulSet => {
+            let desired = value
+                .pointer("/spec/replicas")
+                .and_then(Value::as_i64)
+                .unwrap_or(1);
+            let updated = count(value, "/status/updatedReplicas");
+            let ready = count(value, "/status/readyReplicas");
+            Ok(Progress {
+                desired,
+                updated,
+                ready,
+                // statefulsets only gained availableReplicas recently
+                available: value
+                    .pointer("/status/availableReplicas")
+                    .and_then(Value::as_i64)
+                    .unwrap_or(ready),
+                done: observed
+                    && updated == desired
+                    && ready == desired
+                    && value.pointer("/status/currentRevision")
+                        == value.pointer("/status/updateRevision"),
+            })
+        }
+        _ => unreachable!("rollout only works on deployments, daemonsets, and statefulsets"),
+    }
+}
+
+fn rollout_status(
+    env: &Env,
+    obj: &KObj,
+    timeout: Duration,
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    let start = Instant::now();
+    let name = kubectl_obj_arg(obj);
+    let mut last = None;
+    env.ctrlcbool.store(false, Ordering::SeqCst);
+    loop {
+        let progress = rollout_progress(obj, &kubectl_get_json(env, obj)?)?;
+        if progress.done {
+            clickwriteln!(writer, "{} successfully rolled out", name);
+            return Ok(());
+        }
+        // only print when something changed, so this is a live view rather than a wall of lines
+        let counts = (progress.updated, progress.ready, progress.available);
+        if last != Some(counts) {
+            clickwriteln!(
+                writer,
+                "{}: {}/{d} updated, {}/{d} ready, {}/{d} available",
+                name,
+                progress.updated,
+                progress.ready,
+                progress.available,
+                d = progress.desired
+            );
+            last = Some(counts);
+        }
+        if start.elapsed() >= timeout {
+            return Err(ClickError::CommandError(format!(
+                "Timed out waiting for the rollout of {} to finish",
+                name
+            )));
+        }
+        if !interruptible_sleep(env, POLL_INTERVAL) {
+            clickwriteln!(writer, "Stopped watching rollout");
+            return Ok(());
+        }
+    }
+}
+
+fn rollout_restart(env: &Env, obj: &KObj, writer: &mut ClickWriter) -> Result<(), ClickError> {
+    // this is what kubectl rollout restart does: changing the pod template causes a new rollout
+    let patch = serde_json::json!({
+        "spec": { "template": { "metadata": { "annotations": {
+            RESTARTED_AT_ANNOTATION: Utc::now().to_rfc3339(),
+        }}}}
+    });
+    let mut command = kubectl_obj_command(env, obj)?;
+    command
+        .arg("patch")
+        .arg(kubectl_obj_arg(obj))
+        .arg("--type")
+        .arg("strategic")
+        .arg("-p")
+        .arg(patch.to_string())
+        .arg("-o")
+        .arg("name");
+    if let Some(out) = kubectl_mutate(env, command, writer)? {
+        clickwriteln!(writer, "{} restarted", out.trim());
+    }
+    Ok(())
+}
+
+struct Revision {
+    number: i64,
+    name: String,
+    change_cause: Option<String>,
+    created: Option<DateTime<Utc>>,
+}
+
+impl Revision {
+    fn new(number: i64, meta: &ObjectMeta) -> Revision {
+        Revision {
+            number,
+            name: meta.name.clone().unwrap_or_default(),
+            change_cause: meta.annotations.get(CHANGE_CAUSE_ANNOTATION).cloned(),
+            created: meta.creation_timestamp.as_ref().map(|ts| ts.0),
+        }
+    }
+}
+
+fn owned_by(meta: &ObjectMeta, uid: &str) -> bool {
+    meta.owner_references.iter().any(|owner| owner.uid == uid)
+}
+
+/// Get the revisions of obj. Deployments keep their revisions as replicasets, and daemonsets and
+/// statefulsets keep them as controllerrevisions.
+fn get_revisions(env: &Env, obj: &KObj) -> Result<Vec<Revision>, ClickError> {
+    let ns = obj.namespace.as_deref().ok_or_else(|| {
+        ClickError::CommandError(format!("{} has no namespace", kubectl_obj_arg(obj)))
+    })?;
+    let uid = match obj.uid.clone() {
+        Some(uid) => uid,
+        None => kubectl_get_json(env, obj)?
+            .pointer("/metadata/uid")
+            .and_then(Value::as_str)
+            .map(|uid| uid.to_string())
+            .ok_or_else(|| {
+                ClickError::CommandError(format!("Could not get uid of {}", kubectl_obj_arg(obj)))
+            })?,
+    };
+    let mut revisions: Vec<Revision> = if obj.is(ObjType::Deployment) {
+        let (request, _) =
+            apps_api::ReplicaSet::list_namespaced_replica_set(ns, Default::default())?;
+        let list: List<apps_api::ReplicaSet> = env.run_on_context(|c| c.execute_list(request))?;
+        list.items
+            .iter()
+            .filter(|rs| owned_by(&rs.metadata, &uid))
+            .filter_map(|rs| {
+                let number = rs
+                    .metadata
+                    .annotations
+                    .get(REVISION_ANNOTATION)?
+                    .parse()
+                    .ok()?;
+                Some(Revision::new(number, &rs.metadata))
+            })
+            .collect()
+    } else {
+        let (request, _) = apps_api::ControllerRevision::list_namespaced_controller_revision(
+            ns,
+            Default::default(),
+        )?;
+        let list: List<apps_api::ControllerRevision> =
+            env.run_on_context(|c| c.execute_list(request))?;
+        list.items
+            .iter()
+            .filter(|cr| owned_by(&cr.metadata, &uid))
+            .map(|cr| Revision::new(cr.revision, &cr.metadata))
+            .collect()
+    };
+    revisions.sort_by_key(|rev| rev.number);
+    Ok(revisions)
+}
+
+fn rollout_history(env: &Env, obj: &KObj, writer: &mut ClickWriter) -> Result<(), ClickError> {
+    let revisions = get_revisions(env, obj)?;
+    if revisions.is_empty() {
+        clickwriteln!(writer, "No revisions found for {}", kubectl_obj_arg(obj));
+        return Ok(());
+    }
+    let current = revisions.last().map(|rev| rev.number);
+    let rows = revisions
+        .iter()
+        .map(|rev| {
+            let number = if Some(rev.number) == current {
+                CellSpec::with_role(format!("{} (current)", rev.number).into(), Role::Selection)
+            } else {
+                rev.number.into()
+            };
+            vec![
+                number,
+                rev.name.as_str().into(),
+                rev.change_cause.as_deref().unwrap_or("<none>").into(),
+                rev.created.into(),
+            ]
+        })
+        .collect();
+    crate::table::print_table(
+        row!["Revision", "Name", "Change-Cause", "Age"],
+        rows,
+        writer,
+    );
+    Ok(())
+}
+
+fn rollout_undo(
+    env: &Env,
+    obj: &KObj,
+    to_revision: Option<&str>,
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    let mut command = kubectl_obj_command(env, obj)?;
+    command.arg("rollout").arg("undo").arg(kubectl_obj_arg(obj));
+    if let Some(revision) = to_revision {
+        command.arg(format!("--to-revision={}", revision));
+    }
+    if let Some(out) = kubectl_mutate(env, command, writer)? {
+        clickwrite!(writer, "{}", out);
+    }
+    Ok(())
+}
+
+command!(
+    Rollout,
+    "rollout",
+    "Manage the rollout of the active deployment(s), daemonset(s), or statefulset(s)",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("action")
+                .help(
+                    "status: watch the rollout until it's done\n\
+                     restart: restart all the pods, by starting a new rollout\n\
+                     history: list the revisions that can be rolled back to\n\
+                     undo: roll back to the previous revision (or --to-revision)",
+                )
+                .required(true)
+                .possible_values(&["status", "restart", "history", "undo"])
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("timeout")
+                .long("timeout")
+                .help("How long status waits for the rollout to finish, i.e. 30s, 10m")
+                .takes_value(true)
+                .validator(valid_duration)
+                .default_value("10m"),
+        )
+        .arg(
+            Arg::with_name("to_revision")
+                .long("to-revision")
+                .help("The revision to undo to, as shown by 'rollout history'")
+                .takes_value(true)
+                .validator(valid_u32),
+        )
+    },
+    vec!["rollout"],
+    vec![&completer::rolloutaction_values_completer],
+    no_named_complete!(),
+    |matches, env, writer| {
+        let action = matches.value_of("action").unwrap(); // safe, required
+        if matches.is_present("to_revision") && action != "undo" {
+            return Err(ClickError::CommandError(
+                "--to-revision can only be used with undo".to_string(),
+            ));
+        }
+        let timeout = parse_duration(matches.value_of("timeout").unwrap()).unwrap(); // safe, validated with a default
+        env.apply_to_selection(
+            writer,
+            Some(&env.click_config.range_separator),
+            ErrorStrategy::CollectAll,
+            |obj, writer| {
+                if !matches!(
+                    obj.typ,
+                    ObjType::Deployment | ObjType::DaemonSet | ObjType::StatefulSet
+                ) {
+                    return Err(ClickError::CommandError(format!(
+                        "{} {} has no rollout, only deployments, daemonsets, and statefulsets do",
+                        obj.type_str(),
+                        obj.name()
+                    )));
+                }
+                match action {
+                    "status" => rollout_status(env, obj, timeout, writer),
+                    "restart" => rollout_restart(env, obj, writer),
+                    "history" => rollout_history(env, obj, writer),
+                    "undo" => rollout_undo(env, obj, matches.value_of("to_revision"), writer),
+                    _ => unreachable!(), // clap validates
+                }
+            },
+        )
+    }
+);
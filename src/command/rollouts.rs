@@ -13,7 +13,6 @@
 // limitations under the License.
 
 /// Support for argo rollouts https://argoproj.github.io/argo-rollouts/
-use ansi_term::Colour::Yellow;
 use clap::{App, Arg};
 use k8s_openapi::{
     apimachinery::pkg::apis::meta::v1::ObjectMeta, http, ListOptional, ListResponse,
@@ -33,7 +32,6 @@ use crate::{
     values::val_num,
 };
 
-use std::array::IntoIter;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::io::Write;
@@ -68,6 +66,8 @@ fn ro_to_kobj(rollout: &RolloutValue) -> KObj {
     KObj {
         name: meta.name.clone().unwrap_or_else(|| "<Unknown>".into()),
         namespace: meta.namespace.clone(),
+        uid: meta.uid.clone(),
+        context: None,
         typ: ObjType::Rollout,
     }
 }
@@ -120,7 +120,7 @@ list_command!(
         ),
     vec!["rollouts"],
     noop_complete!(),
-    IntoIter::new([]),
+    [],
     |matches, env, writer| {
         let (request, _response_body) = match &env.namespace {
             Some(ns) => RolloutValue::list_namespaced_rollout(ns, Default::default())?,
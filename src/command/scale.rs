@@ -0,0 +1,245 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use clap::{App, Arg};
+use k8s_openapi::api::autoscaling::v2beta2 as autoscaling_api;
+use k8s_openapi::List;
+use rustyline::completion::Pair as RustlinePair;
+use serde_json::Value;
+
+use crate::{
+    command::command_def::{exec_match, start_clap, Cmd},
+    command::{
+        confirm_action, interruptible_sleep, kubectl_get_json, kubectl_mutate, kubectl_obj_arg,
+        kubectl_obj_command, parse_duration, valid_duration, valid_u32,
+    },
+    completer,
+    env::{Env, ErrorStrategy, ObjectSelection},
+    error::ClickError,
+    kobj::{KObj, ObjType},
+    output::ClickWriter,
+    theme::{self, Role},
+};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Only things with a scale subresource can be scaled
+fn can_scale(obj: &KObj) -> bool {
+    match obj.typ {
+        ObjType::Deployment | ObjType::StatefulSet | ObjType::ReplicaSet => true,
+        #[cfg(feature = "argorollouts")]
+        ObjType::Rollout => true,
+        _ => false,
+    }
+}
+
+/// The name of the hpa that manages obj, if there is one. This is best effort, if we can't list
+/// hpas we just don't warn.
+fn managing_hpa(env: &Env, obj: &KObj) -> Option<String> {
+    let ns = obj.namespace.as_deref()?;
+    let (request, _) =
+        autoscaling_api::HorizontalPodAutoscaler::list_namespaced_horizontal_pod_autoscaler(
+            ns,
+            Default::default(),
+        )
+        .ok()?;
+    let list: List<autoscaling_api::HorizontalPodAutoscaler> =
+        env.run_on_context(|c| c.execute_list(request)).ok()?;
+    list.items
+        .into_iter()
+        .find(|hpa| {
+            hpa.spec.as_ref().is_some_and(|spec| {
+                spec.scale_target_ref.kind == obj.type_str()
+                    && spec.scale_target_ref.name == obj.name()
+            })
+        })
+        .and_then(|hpa| hpa.metadata.name)
+}
+
+fn wait_for_ready(
+    env: &Env,
+    obj: &KObj,
+    replicas: i64,
+    timeout: Duration,
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    let start = Instant::now();
+    let mut last_ready = None;
+    env.ctrlcbool.store(false, Ordering::SeqCst);
+    loop {
+        let value = kubectl_get_json(env, obj)?;
+        let ready = value
+            .pointer("/status/readyReplicas")
+            .and_then(Value::as_i64)
+            .unwrap_or(0);
+        if last_ready != Some(ready) {
+            clickwriteln!(writer, "  {}/{} replicas ready", ready, replicas);
+            last_ready = Some(ready);
+        }
+        if ready == replicas {
+            return Ok(());
+        }
+        if start.elapsed() >= timeout {
+            return Err(ClickError::CommandError(format!(
+                "Timed out waiting for {} to have {} ready replicas",
+                kubectl_obj_arg(obj),
+                replicas
+            )));
+        }
+        if !interruptible_sleep(env, POLL_INTERVAL) {
+            clickwriteln!(writer, "Stopped waiting");
+            return Ok(());
+        }
+    }
+}
+
+fn scale_obj(
+    env: &Env,
+    obj: &KObj,
+    replicas: i64,
+    wait: Option<Duration>,
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    let current = kubectl_get_json(env, obj)?;
+    // replicas defaults to 1 when unset
+    let old = current
+        .pointer("/spec/replicas")
+        .and_then(Value::as_i64)
+        .unwrap_or(1);
+    if let Some(hpa) = managing_hpa(env, obj) {
+        clickwriteln!(
+            writer,
+            "{}",
+            theme::style(Role::Warning).paint(format!(
+                "Warning: {} is managed by horizontal pod autoscaler {}, which may change the \
+                 replica count back",
+                kubectl_obj_arg(obj),
+                hpa
+            ))
+        );
+    }
+
+    let mut command = kubectl_obj_command(env, obj)?;
+    command
+        .arg("patch")
+        .arg(kubectl_obj_arg(obj))
+        .arg("--type")
+        .arg("merge")
+        .arg("-p")
+        .arg(serde_json::json!({ "spec": { "replicas": replicas } }).to_string())
+        .arg("-o")
+        .arg("name");
+    let name = match kubectl_mutate(env, command, writer)? {
+        Some(name) => name,
+        None => return Ok(()),
+    };
+    clickwriteln!(writer, "{} scaled: {} → {}", name.trim(), old, replicas);
+
+    match wait {
+        Some(timeout) => wait_for_ready(env, obj, replicas, timeout, writer),
+        None => Ok(()),
+    }
+}
+
+command!(
+    Scale,
+    "scale",
+    "Scale the active deployment(s), statefulset(s), or replicaset(s) to the specified number of \
+     replicas",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("replicas")
+                .help("The number of replicas to scale to")
+                .required(true)
+                .validator(valid_u32)
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("wait")
+                .short("w")
+                .long("wait")
+                .help("Wait until the object has the specified number of ready replicas")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("timeout")
+                .long("timeout")
+                .help("How long to --wait before giving up, i.e. 30s, 5m")
+                .takes_value(true)
+                .requires("wait")
+                .validator(valid_duration)
+                .default_value("5m"),
+        )
+        .after_help(
+            "Scaling more than one object to zero will ask for confirmation.
+
+Example:
+  # scale the active deployment to 3 replicas and wait for them to be ready
+  scale 3 --wait",
+        )
+    },
+    vec!["scale"],
+    noop_complete!(),
+    no_named_complete!(),
+    |matches, env, writer| {
+        let replicas: i64 = matches.value_of("replicas").unwrap().parse().unwrap(); // safe, validated
+        let wait = if matches.is_present("wait") {
+            // safe, has a default and is validated
+            Some(parse_duration(matches.value_of("timeout").unwrap()).unwrap())
+        } else {
+            None
+        };
+
+        // check everything before scaling anything, so a range isn't half scaled
+        let objs: Vec<&KObj> = match env.current_selection() {
+            ObjectSelection::Single(obj) => vec![obj],
+            ObjectSelection::Range(range) => range.iter().collect(),
+            ObjectSelection::None => vec![],
+        };
+        if let Some(obj) = objs.iter().find(|obj| !can_scale(obj)) {
+            return Err(ClickError::CommandError(format!(
+                "{} {} can't be scaled, only deployments, statefulsets, and replicasets can",
+                obj.type_str(),
+                obj.name()
+            )));
+        }
+        if replicas == 0 && objs.len() > 1 {
+            for obj in objs.iter() {
+                clickwriteln!(writer, "  {}", kubectl_obj_arg(obj));
+            }
+            let prompt = format!("Scale {} objects to zero", objs.len());
+            if !confirm_action(writer, &prompt) {
+                clickwriteln!(writer, "Not scaling");
+                return Ok(());
+            }
+        }
+
+        let res = env.apply_to_selection(
+            writer,
+            Some(&env.click_config.range_separator),
+            ErrorStrategy::CollectAll,
+            |obj, writer| scale_obj(env, obj, replicas, wait, writer),
+        );
+        if res.is_ok() && !env.dry_run {
+            env.invalidate_selection();
+        }
+        res
+    }
+);
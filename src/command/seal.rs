@@ -0,0 +1,249 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use clap::{App, Arg};
+use k8s_openapi::api::core::v1 as api;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use k8s_openapi::ByteString;
+use rustyline::completion::Pair as RustlinePair;
+
+use crate::{
+    command::command_def::{exec_match, start_clap, Cmd},
+    command::{tool_exit_error, tool_spawn_error},
+    completer,
+    env::Env,
+    error::ClickError,
+    output::ClickWriter,
+};
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const KUBESEAL_URL: &str = "https://github.com/bitnami-labs/sealed-secrets";
+
+/// Run kubeseal with the secret on its stdin, returning the SealedSecret it prints
+fn run_kubeseal(mut command: Command, secret: &api::Secret) -> Result<String, ClickError> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| tool_spawn_error("kubeseal", KUBESEAL_URL, e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(&serde_json::to_vec(secret)?)?;
+    }
+    let output = child.wait_with_output()?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(tool_exit_error("kubeseal", output.status, &output.stderr))
+    }
+}
+
+/// The secret to seal, copied from an existing one. Only what's needed to recreate it is kept, so
+/// server set fields like the uid don't end up in the SealedSecret.
+fn read_secret(env: &Env, name: &str, ns: &str) -> Result<api::Secret, ClickError> {
+    let (request, _) = api::Secret::read_namespaced_secret(name, ns, Default::default())?;
+    let secret =
+        match env.run_on_context(|c| c.read::<api::ReadNamespacedSecretResponse>(request))? {
+            api::ReadNamespacedSecretResponse::Ok(secret) => secret,
+            _ => {
+                return Err(ClickError::CommandError(format!(
+                    "Could not read secret {}",
+                    name
+                )))
+            }
+        };
+    Ok(api::Secret {
+        metadata: ObjectMeta {
+            name: secret.metadata.name,
+            namespace: secret.metadata.namespace,
+            labels: secret.metadata.labels,
+            ..Default::default()
+        },
+        data: secret.data,
+        type_: secret.type_,
+        ..Default::default()
+    })
+}
+
+command!(
+    Seal,
+    "seal",
+    "Seal a secret with kubeseal, printing the SealedSecret it makes, which is safe to commit. The \
+     secret is made from --key/--value pairs, or copied from an existing secret with --from-secret.",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("name")
+                .long("name")
+                .help("The name of the secret the SealedSecret unseals to")
+                .takes_value(true)
+                .required_unless("from_secret"),
+        )
+        .arg(
+            Arg::with_name("namespace")
+                .long("namespace")
+                .short("n")
+                .help("The namespace the secret will be in, rather than the current one")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("key")
+                .long("key")
+                .short("k")
+                .help("A key to put in the secret, can be repeated, with a --value for each")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .requires("value"),
+        )
+        .arg(
+            Arg::with_name("value")
+                .long("value")
+                .short("v")
+                .help("The value for the --key in the same position")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .requires("key"),
+        )
+        .arg(
+            Arg::with_name("from_secret")
+                .long("from-secret")
+                .value_name("SECRET_NAME")
+                .help("Seal all the keys of this existing secret")
+                .takes_value(true)
+                .conflicts_with_all(&["key", "value"]),
+        )
+        .arg(
+            Arg::with_name("scope")
+                .long("scope")
+                .help(
+                    "Where the SealedSecret can be unsealed: only with this name and namespace \
+                     (strict), with any name in the namespace, or anywhere",
+                )
+                .takes_value(true)
+                .possible_values(&["strict", "namespace-wide", "cluster-wide"]),
+        )
+        .arg(
+            Arg::with_name("controller_namespace")
+                .long("controller-namespace")
+                .help("The namespace the sealed-secrets controller runs in, if not kube-system")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .short("o")
+                .value_name("FILENAME")
+                .help("Write the SealedSecret to this file, rather than printing it")
+                .takes_value(true),
+        )
+        .after_help(
+            "kubeseal needs to be installed, and is run against the active context to get the \
+controller's public key.
+
+Examples:
+  # seal a database password
+  seal --name db-creds --key password --value hunter2 -o db-creds.yaml
+
+  # seal a copy of an existing secret, to move it into git
+  seal --from-secret api-tokens",
+        )
+    },
+    vec!["seal", "kubeseal"],
+    noop_complete!(),
+    IntoIterator::into_iter([
+        (
+            "from_secret".to_string(),
+            completer::secret_completer as fn(&str, &Env) -> Vec<RustlinePair>
+        ),
+        (
+            "namespace".to_string(),
+            completer::namespace_completer as fn(&str, &Env) -> Vec<RustlinePair>
+        ),
+    ])
+    .collect(),
+    |matches, env, writer| {
+        let context = env.context.as_ref().ok_or_else(|| {
+            ClickError::CommandError("Need an active context to seal secrets".to_string())
+        })?;
+        let ns = matches
+            .value_of("namespace")
+            .or(env.namespace.as_deref())
+            .unwrap_or("default");
+        let mut secret = match matches.value_of("from_secret") {
+            Some(from) => read_secret(env, from, ns)?,
+            None => {
+                let keys: Vec<&str> = matches.values_of("key").into_iter().flatten().collect();
+                let values: Vec<&str> = matches.values_of("value").into_iter().flatten().collect();
+                if keys.len() != values.len() {
+                    return Err(ClickError::CommandError(format!(
+                        "Got {} keys but {} values, each --key needs a --value",
+                        keys.len(),
+                        values.len()
+                    )));
+                }
+                let data: BTreeMap<String, ByteString> = keys
+                    .into_iter()
+                    .zip(values)
+                    .map(|(k, v)| (k.to_string(), ByteString(v.as_bytes().to_vec())))
+                    .collect();
+                api::Secret {
+                    metadata: ObjectMeta {
+                        namespace: Some(ns.to_string()),
+                        ..Default::default()
+                    },
+                    data,
+                    ..Default::default()
+                }
+            }
+        };
+        if let Some(name) = matches.value_of("name") {
+            secret.metadata.name = Some(name.to_string());
+        }
+        if secret.data.is_empty() {
+            clickwriteln!(writer, "Sealing a secret with no keys, add some with --key");
+        }
+
+        let mut command = Command::new("kubeseal");
+        command
+            .arg("--context")
+            .arg(&context.name)
+            .arg("--format")
+            .arg("yaml");
+        if let Some(scope) = matches.value_of("scope") {
+            command.arg("--scope").arg(scope);
+        }
+        if let Some(controller_ns) = matches.value_of("controller_namespace") {
+            command.arg("--controller-namespace").arg(controller_ns);
+        }
+        let sealed = run_kubeseal(command, &secret)?;
+        match matches.value_of("output") {
+            Some(file) => {
+                std::fs::write(file, &sealed)?;
+                clickwriteln!(
+                    writer,
+                    "Wrote SealedSecret {} to {}",
+                    secret.metadata.name.as_deref().unwrap_or(""),
+                    file
+                );
+            }
+            None => clickwrite!(writer, "{}", sealed),
+        }
+        Ok(())
+    }
+);
@@ -0,0 +1,118 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use clap::{App, Arg};
+use k8s_openapi::api::core::v1 as api;
+
+use crate::{
+    command::command_def::{exec_match, show_arg, sort_arg, start_clap, Cmd},
+    command::{run_list_command, Extractor},
+    completer,
+    env::Env,
+    kobj::{KObj, ObjType},
+    output::ClickWriter,
+    table::CellSpec,
+};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+
+lazy_static! {
+    static ref SA_EXTRACTORS: HashMap<String, Extractor<api::ServiceAccount>> = {
+        let mut m: HashMap<String, Extractor<api::ServiceAccount>> = HashMap::new();
+        m.insert("Secrets".to_owned(), sa_secrets);
+        m
+    };
+}
+const COL_MAP: &[(&str, &str)] = &[("name", "Name"), ("secrets", "Secrets"), ("age", "Age")];
+
+const COL_FLAGS: &[&str] = &{ extract_first!(COL_MAP) };
+
+const EXTRA_COL_MAP: &[(&str, &str)] = &[("namespace", "Namespace"), ("labels", "Labels")];
+
+const EXTRA_COL_FLAGS: &[&str] = &{ extract_first!(EXTRA_COL_MAP) };
+
+fn sa_to_kobj(sa: &api::ServiceAccount) -> KObj {
+    let meta = &sa.metadata;
+    KObj {
+        name: meta.name.clone().unwrap_or_else(|| "<Unknown>".into()),
+        namespace: meta.namespace.clone(),
+        uid: meta.uid.clone(),
+        context: None,
+        typ: ObjType::ServiceAccount,
+    }
+}
+
+fn sa_secrets(sa: &api::ServiceAccount) -> Option<CellSpec<'_>> {
+    Some(sa.secrets.len().into())
+}
+
+list_command!(
+    ServiceAccounts,
+    "serviceaccounts",
+    "Get service accounts (in current namespace if set). Describing a service account shows the \
+     roles bound to it, and 'token' gets a token for the selected one.",
+    super::COL_FLAGS,
+    super::EXTRA_COL_FLAGS,
+    |clap: App<'static, 'static>| clap
+        .arg(
+            Arg::with_name("show_label")
+                .short("L")
+                .long("labels")
+                .help("Show service account labels (deprecated, use --show labels)")
+                .takes_value(false)
+        )
+        .arg(
+            Arg::with_name("regex")
+                .short("r")
+                .long("regex")
+                .help("Filter service accounts by the specified regex")
+                .takes_value(true)
+        )
+        .arg(show_arg(EXTRA_COL_FLAGS, true))
+        .arg(sort_arg(COL_FLAGS, Some(EXTRA_COL_FLAGS)))
+        .arg(
+            Arg::with_name("reverse")
+                .short("R")
+                .long("reverse")
+                .help("Reverse the order of the returned list")
+                .takes_value(false),
+        ),
+    vec!["sa", "serviceaccounts"],
+    noop_complete!(),
+    [],
+    |matches, env, writer| {
+        let (request, _response_body) = match &env.namespace {
+            Some(ns) => {
+                api::ServiceAccount::list_namespaced_service_account(ns, Default::default())?
+            }
+            None => {
+                api::ServiceAccount::list_service_account_for_all_namespaces(Default::default())?
+            }
+        };
+        let cols: Vec<&str> = COL_MAP.iter().map(|(_, col)| *col).collect();
+
+        run_list_command(
+            matches,
+            env,
+            writer,
+            cols,
+            request,
+            COL_MAP,
+            Some(EXTRA_COL_MAP),
+            Some(&SA_EXTRACTORS),
+            sa_to_kobj,
+        )
+    }
+);
@@ -12,7 +12,6 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use ansi_term::Colour::Yellow;
 use clap::{App, Arg};
 use k8s_openapi::api::core::v1 as api;
 
@@ -26,7 +25,6 @@ use crate::{
     table::CellSpec,
 };
 
-use std::array::IntoIter;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::io::Write;
@@ -63,6 +61,8 @@ fn service_to_kobj(service: &api::Service) -> KObj {
     KObj {
         name: meta.name.clone().unwrap_or_else(|| "<Unknown>".into()),
         namespace: meta.namespace.clone(),
+        uid: meta.uid.clone(),
+        context: None,
         typ: ObjType::Service,
     }
 }
@@ -93,10 +93,8 @@ fn service_external_ip(service: &api::Service) -> Option<CellSpec<'_>> {
                     .map(|ingress| {
                         if let Some(hv) = ingress.hostname.as_deref() {
                             hv
-                        } else if let Some(ipv) = ingress.ip.as_deref() {
-                            ipv
                         } else {
-                            ""
+                            ingress.ip.as_deref().unwrap_or_default()
                         }
                     })
                     .collect::<Vec<&str>>()
@@ -168,7 +166,7 @@ list_command!(
     },
     vec!["services"],
     noop_complete!(),
-    IntoIter::new([]),
+    [],
     |matches, env, writer| {
         let cols: Vec<&str> = COL_MAP.iter().map(|(_, col)| *col).collect();
         let (request, _response_body) = match &env.namespace {
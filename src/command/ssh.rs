@@ -0,0 +1,259 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use clap::{App, Arg};
+use k8s_openapi::api::core::v1 as api;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelector;
+use rustyline::completion::Pair as RustlinePair;
+
+use crate::{
+    command::command_def::{exec_match, start_clap, Cmd},
+    command::kubectl_get_json,
+    command::pods::get_pods_for_selector,
+    completer,
+    env::{Env, ErrorStrategy, ObjectSelection},
+    error::ClickError,
+    kobj::{KObj, ObjType},
+    output::ClickWriter,
+};
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Write;
+
+/// Node address types to ssh to, most preferred first. Internal addresses are a last resort, as
+/// they're only reachable from inside the cluster's network (or over a VPN).
+const ADDRESS_TYPES: &[&str] = &[
+    "ExternalIP",
+    "ExternalDNS",
+    "Hostname",
+    "InternalDNS",
+    "InternalIP",
+];
+
+/// The names of the nodes obj runs on. A pod runs on one node, and controllers and services run
+/// on the nodes of the pods they select.
+fn nodes_for(env: &Env, obj: &KObj) -> Result<Vec<String>, ClickError> {
+    match obj.typ {
+        ObjType::Node => return Ok(vec![obj.name().to_string()]),
+        ObjType::Pod { .. } => {
+            let ns = obj.namespace.as_deref().unwrap_or("default");
+            let (request, _) = api::Pod::read_namespaced_pod(obj.name(), ns, Default::default())?;
+            return match env
+                .run_on_context(|c| c.read::<api::ReadNamespacedPodResponse>(request))?
+            {
+                api::ReadNamespacedPodResponse::Ok(pod) => pod
+                    .spec
+                    .and_then(|spec| spec.node_name)
+                    .map(|node| vec![node])
+                    .ok_or_else(|| {
+                        ClickError::CommandError(format!(
+                            "Pod {} hasn't been scheduled to a node",
+                            obj.name()
+                        ))
+                    }),
+                _ => Err(ClickError::CommandError(format!(
+                    "Could not read pod {}",
+                    obj.name()
+                ))),
+            };
+        }
+        _ => {}
+    }
+
+    let value = kubectl_get_json(env, obj)?;
+    let selector = value.pointer("/spec/selector").ok_or_else(|| {
+        ClickError::CommandError(format!(
+            "Can't ssh to {}, it's not a pod or node, and doesn't select any pods",
+            obj.name()
+        ))
+    })?;
+    // services have a plain map of labels, everything else a LabelSelector
+    let selector: LabelSelector =
+        if selector.get("matchLabels").is_some() || selector.get("matchExpressions").is_some() {
+            serde_json::from_value(selector.clone())?
+        } else {
+            LabelSelector {
+                match_labels: serde_json::from_value::<BTreeMap<String, String>>(selector.clone())?,
+                ..Default::default()
+            }
+        };
+    let ns = obj.namespace.as_deref().unwrap_or("default");
+    let pods = get_pods_for_selector(env, ns, &selector)?;
+    let mut nodes: Vec<String> = pods
+        .items
+        .into_iter()
+        .filter_map(|pod| pod.spec.and_then(|spec| spec.node_name))
+        .collect();
+    nodes.sort();
+    nodes.dedup();
+    if nodes.is_empty() {
+        return Err(ClickError::CommandError(format!(
+            "None of the pods of {} are running on a node",
+            obj.name()
+        )));
+    }
+    Ok(nodes)
+}
+
+/// The address to ssh to for node
+fn node_address(env: &Env, node: &str) -> Result<String, ClickError> {
+    let (request, _) = api::Node::read_node(node, Default::default())?;
+    let node_obj = match env.run_on_context(|c| c.read::<api::ReadNodeResponse>(request))? {
+        api::ReadNodeResponse::Ok(node) => node,
+        _ => {
+            return Err(ClickError::CommandError(format!(
+                "Could not read node {}",
+                node
+            )))
+        }
+    };
+    let addresses = node_obj
+        .status
+        .map(|status| status.addresses)
+        .unwrap_or_default();
+    ADDRESS_TYPES
+        .iter()
+        .find_map(|typ| {
+            addresses
+                .iter()
+                .find(|addr| addr.type_ == *typ && !addr.address.is_empty())
+        })
+        .map(|addr| addr.address.clone())
+        .ok_or_else(|| ClickError::CommandError(format!("Node {} has no addresses", node)))
+}
+
+/// Run an ssh session to node. This takes over the terminal until it exits. tty forces ssh to
+/// allocate a terminal even though there's a command, for commands that are interactive.
+pub fn ssh_to(
+    env: &Env,
+    node: &str,
+    user: Option<&str>,
+    key: Option<&str>,
+    command: &[&str],
+    tty: bool,
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    let address = node_address(env, node)?;
+    let ssh_command = env.click_config.ssh_command.as_deref().unwrap_or("ssh");
+    let mut words = ssh_command.split_whitespace();
+    let program = words.next().unwrap_or("ssh");
+    let mut args: Vec<String> = words.map(|w| w.to_string()).collect();
+    if let Some(key) = key {
+        args.push("-i".to_string());
+        args.push(key.to_string());
+    }
+    if tty {
+        args.push("-t".to_string());
+    }
+    args.push(match user {
+        Some(user) => format!("{}@{}", user, address),
+        None => address.clone(),
+    });
+    args.extend(command.iter().map(|c| c.to_string()));
+
+    clickwriteln!(writer, "Connecting to node {} ({})", node, address);
+    writer.finish_paging();
+    let status = duct::cmd(program, &args)
+        .unchecked()
+        .run()
+        .map_err(|e| ClickError::CommandError(format!("Could not run '{}': {}", program, e)))?
+        .status;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(ClickError::CommandError(format!(
+            "{} to {} exited with code {}",
+            program,
+            node,
+            status.code().unwrap_or(-1)
+        )))
+    }
+}
+
+command!(
+    Ssh,
+    "ssh",
+    "ssh to the node the active pod is running on. For a controller like a deployment, or a \
+     service, ssh to each node its pods are on, one after another.",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("user")
+                .long("user")
+                .short("u")
+                .help("The user to log in as")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("key")
+                .long("key")
+                .short("i")
+                .value_name("KEY")
+                .help("The private key to log in with, passed to ssh as -i")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("command")
+                .help("A command to run on the node, rather than starting a shell")
+                .multiple(true) // required for trailing_var_arg
+                .index(1),
+        )
+        .after_help(
+            "The node's external IP is used if it has one, then its DNS name or hostname, and \
+its internal IP as a last resort. 'set ssh_command' changes the command run, i.e. to add options \
+or use a wrapper. The user and key are added, then the address, then any command.
+
+Examples:
+  # ssh to the active pod's node
+  ssh
+
+  # check disk space on each node running the active deployment's pods
+  ssh --user admin -- df -h",
+        )
+    },
+    vec!["ssh"],
+    noop_complete!(),
+    no_named_complete!(),
+    |matches, env, writer| {
+        if matches!(env.current_selection(), ObjectSelection::None) {
+            return Err(ClickError::CommandError(
+                "No active object selected".to_string(),
+            ));
+        }
+        let user = matches.value_of("user");
+        let key = matches.value_of("key");
+        let command: Vec<&str> = matches
+            .values_of("command")
+            .map(|c| c.collect())
+            .unwrap_or_default();
+        // pods in a range are often on the same node, which only needs one session
+        let mut visited = HashSet::new();
+        env.apply_to_selection(
+            writer,
+            Some(&env.click_config.range_separator),
+            ErrorStrategy::StopOnFirst,
+            |obj, writer| {
+                for node in nodes_for(env, obj)? {
+                    if visited.insert(node.clone()) {
+                        ssh_to(env, &node, user, key, &command, false, writer)?;
+                    } else {
+                        clickwriteln!(writer, "Already connected to node {}, skipping", node);
+                    }
+                }
+                Ok(())
+            },
+        )
+    },
+    true // gather up everything after the options into the command
+);
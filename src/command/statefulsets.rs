@@ -12,21 +12,24 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use ansi_term::Colour::Yellow;
 use clap::{App, Arg};
 use k8s_openapi::api::apps::v1 as apps_api;
+use k8s_openapi::api::core::v1 as api;
+use k8s_openapi::{List, ListOptional};
+use prettytable::Table;
 
 use crate::{
     command::command_def::{exec_match, show_arg, sort_arg, start_clap, Cmd},
-    command::{run_list_command, Extractor},
+    command::pods::{get_pods_for_controller, print_pod_sub_table},
+    command::{run_filtered_list_command, Extractor},
     completer,
     env::Env,
+    error::ClickError,
     kobj::{KObj, ObjType},
     output::ClickWriter,
     table::CellSpec,
 };
 
-use std::array::IntoIter;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::io::Write;
@@ -39,6 +42,7 @@ lazy_static! {
         m.insert("Desired".to_owned(), ss_desired);
         m.insert("Images".to_owned(), ss_images);
         m.insert("Ready".to_owned(), ss_ready);
+        m.insert("Updated".to_owned(), ss_updated);
         m
     };
 }
@@ -46,6 +50,8 @@ const COL_MAP: &[(&str, &str)] = &[
     ("name", "Name"),
     ("desired", "Desired"),
     ("current", "Current"),
+    ("ready", "Ready"),
+    ("updated", "Updated"),
     ("age", "Age"),
 ];
 
@@ -65,6 +71,8 @@ fn ss_to_kobj(statefulset: &apps_api::StatefulSet) -> KObj {
     KObj {
         name: meta.name.clone().unwrap_or_else(|| "<Unknown>".into()),
         namespace: meta.namespace.clone(),
+        uid: meta.uid.clone(),
+        context: None,
         typ: ObjType::StatefulSet,
     }
 }
@@ -119,6 +127,166 @@ fn ss_ready(statefulset: &apps_api::StatefulSet) -> Option<CellSpec<'_>> {
         })
 }
 
+fn ss_updated(statefulset: &apps_api::StatefulSet) -> Option<CellSpec<'_>> {
+    statefulset
+        .status
+        .as_ref()
+        .map(|stat| stat.updated_replicas.unwrap_or(0).into())
+}
+
+/// Get the ordinal of a pod or pvc belonging to a statefulset, from the -N suffix on its name
+fn ordinal_of(name: &str, prefix: &str) -> Option<u32> {
+    name.strip_prefix(prefix)
+        .and_then(|rest| rest.strip_prefix('-'))
+        .and_then(|ordinal| ordinal.parse().ok())
+}
+
+fn print_ordered_pods(
+    env: &Env,
+    writer: &mut ClickWriter,
+    statefulset: &apps_api::StatefulSet,
+    shown: &KObj,
+) -> Result<(), ClickError> {
+    if let Some(spec) = statefulset.spec.as_ref() {
+        let name = shown.name();
+        let mut pods = get_pods_for_controller(env, shown, &spec.selector)?;
+        // pods sort as ss-0, ss-1, ss-10, ss-2 by name, so order by the ordinal instead
+        pods.items.sort_by_key(|pod| {
+            pod.metadata
+                .name
+                .as_deref()
+                .and_then(|pod_name| ordinal_of(pod_name, name))
+                .unwrap_or(u32::MAX)
+        });
+        clickwriteln!(writer, "\nPods for statefulset {}:", name);
+        print_pod_sub_table(&pods, writer);
+    }
+    Ok(())
+}
+
+fn pvc_phase_cell(pvc: Option<&api::PersistentVolumeClaim>) -> prettytable::Cell {
+    let phase = pvc
+        .and_then(|pvc| pvc.status.as_ref())
+        .and_then(|stat| stat.phase.as_deref());
+    match (pvc, phase) {
+        (None, _) => cell!(Fr->"<missing>"),
+        (Some(_), Some("Bound")) => cell!(Fg->"Bound"),
+        (Some(_), Some("Lost")) => cell!(Fr->"Lost"),
+        (Some(_), Some(phase)) => cell!(Fy->phase),
+        (Some(_), None) => cell!("Unknown"),
+    }
+}
+
+/// Print the PVCs created from each volume claim template of the statefulset. PVCs are named
+/// TEMPLATE-STATEFULSET-ORDINAL. Any beyond the current replica count are kept by kubernetes after
+/// a scale down, so we show those too.
+fn print_pvcs(
+    writer: &mut ClickWriter,
+    statefulset: &apps_api::StatefulSet,
+    name: &str,
+    pvcs: &List<api::PersistentVolumeClaim>,
+) {
+    let spec = match statefulset.spec.as_ref() {
+        Some(spec) => spec,
+        None => return,
+    };
+    clickwriteln!(writer, "\nPVCs for statefulset {}:", name);
+    if spec.volume_claim_templates.is_empty() {
+        clickwriteln!(writer, "  No volume claim templates");
+        return;
+    }
+    let replicas = spec.replicas.unwrap_or(1).max(0) as u32;
+    let mut table = Table::new();
+    table.set_titles(row![
+        "PVC", "Template", "Ordinal", "Status", "Volume", "Capacity"
+    ]);
+    for template in spec.volume_claim_templates.iter() {
+        let template_name = template.metadata.name.as_deref().unwrap_or("<Unknown>");
+        let prefix = format!("{}-{}", template_name, name);
+        let mut ordinals: Vec<u32> = (0..replicas).collect();
+        for pvc in pvcs.items.iter() {
+            if let Some(ordinal) = pvc
+                .metadata
+                .name
+                .as_deref()
+                .and_then(|pvc_name| ordinal_of(pvc_name, &prefix))
+            {
+                if ordinal >= replicas {
+                    ordinals.push(ordinal);
+                }
+            }
+        }
+        ordinals.sort_unstable();
+        for ordinal in ordinals {
+            let pvc_name = format!("{}-{}", prefix, ordinal);
+            let pvc = pvcs
+                .items
+                .iter()
+                .find(|pvc| pvc.metadata.name.as_deref() == Some(pvc_name.as_str()));
+            let volume = pvc
+                .and_then(|pvc| pvc.spec.as_ref())
+                .and_then(|spec| spec.volume_name.as_deref())
+                .unwrap_or("");
+            let capacity = pvc
+                .and_then(|pvc| pvc.status.as_ref())
+                .and_then(|stat| stat.capacity.get("storage"))
+                .map(|q| q.0.as_str())
+                .unwrap_or("");
+            let ordinal_str = if ordinal >= replicas {
+                format!("{} (scaled down)", ordinal)
+            } else {
+                ordinal.to_string()
+            };
+            table.add_row(prettytable::Row::new(vec![
+                cell!(pvc_name),
+                cell!(template_name),
+                cell!(ordinal_str),
+                pvc_phase_cell(pvc),
+                cell!(volume),
+                cell!(capacity),
+            ]));
+        }
+    }
+    crate::table::print_filled_table(&mut table, writer);
+}
+
+/// Print pods and/or pvcs for each statefulset that was shown in the last printed table, using the
+/// fetched statefulsets for their specs
+fn print_details(
+    env: &Env,
+    writer: &mut ClickWriter,
+    fetched: &[apps_api::StatefulSet],
+    show_pods: bool,
+    show_pvcs: bool,
+) -> Result<(), ClickError> {
+    let mut index = 0;
+    while let Some(shown) = env.item_at(index) {
+        index += 1;
+        let statefulset = match fetched.iter().find(|ss| {
+            ss.metadata.name.as_deref() == Some(shown.name())
+                && ss.metadata.namespace == shown.namespace
+                && ss.metadata.uid == shown.uid
+        }) {
+            Some(ss) => ss,
+            None => continue,
+        };
+        if show_pods {
+            print_ordered_pods(env, writer, statefulset, shown)?;
+        }
+        if show_pvcs {
+            let ns = shown.namespace.as_deref().unwrap_or("default");
+            let (request, _) = api::PersistentVolumeClaim::list_namespaced_persistent_volume_claim(
+                ns,
+                Default::default(),
+            )?;
+            let pvcs: List<api::PersistentVolumeClaim> =
+                env.run_on_obj_context(shown, |c| c.execute_list(request))?;
+            print_pvcs(writer, statefulset, shown.name(), &pvcs);
+        }
+    }
+    Ok(())
+}
+
 list_command!(
     StatefulSets,
     "statefulsets",
@@ -126,6 +294,24 @@ list_command!(
     super::COL_FLAGS,
     super::EXTRA_COL_FLAGS,
     |clap: App<'static, 'static>| clap
+        .arg(
+            Arg::with_name("name")
+                .help("Only get the statefulset with this name")
+                .required(false)
+                .index(1)
+        )
+        .arg(
+            Arg::with_name("pods")
+                .long("pods")
+                .help("Also list the pods of each statefulset, in ordinal order")
+                .takes_value(false)
+        )
+        .arg(
+            Arg::with_name("pvc")
+                .long("pvc")
+                .help("Also list the PVCs created from each statefulset's volume claim templates")
+                .takes_value(false)
+        )
         .arg(
             Arg::with_name("show_label")
                 .short("L")
@@ -150,20 +336,27 @@ list_command!(
                 .takes_value(false),
         ),
     vec!["ss", "statefulsets"],
-    noop_complete!(),
-    IntoIter::new([]),
+    vec![&completer::statefulset_completer],
+    [],
     |matches, env, writer| {
+        let field_sel = matches
+            .value_of("name")
+            .map(|name| format!("metadata.name={}", name));
+        let opts = ListOptional {
+            field_selector: field_sel.as_deref(),
+            ..Default::default()
+        };
         let (request, _response_body) = match &env.namespace {
-            Some(ns) => {
-                apps_api::StatefulSet::list_namespaced_stateful_set(ns, Default::default())?
-            }
-            None => {
-                apps_api::StatefulSet::list_stateful_set_for_all_namespaces(Default::default())?
-            }
+            Some(ns) => apps_api::StatefulSet::list_namespaced_stateful_set(ns, opts)?,
+            None => apps_api::StatefulSet::list_stateful_set_for_all_namespaces(opts)?,
         };
+        let show_pods = matches.is_present("pods");
+        let show_pvcs = matches.is_present("pvc");
+        // keep what's fetched, as --pods and --pvc need the full objects
+        let fetched = RefCell::new(vec![]);
         let cols: Vec<&str> = COL_MAP.iter().map(|(_, col)| *col).collect();
 
-        run_list_command(
+        run_filtered_list_command(
             matches,
             env,
             writer,
@@ -173,6 +366,17 @@ list_command!(
             Some(EXTRA_COL_MAP),
             Some(&SS_EXTRACTORS),
             ss_to_kobj,
-        )
+            |ss: &apps_api::StatefulSet| {
+                if show_pods || show_pvcs {
+                    fetched.borrow_mut().push(ss.clone());
+                }
+                true
+            },
+        )?;
+
+        if show_pods || show_pvcs {
+            print_details(env, writer, &fetched.into_inner(), show_pods, show_pvcs)?;
+        }
+        Ok(())
     }
 );
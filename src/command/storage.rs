@@ -12,7 +12,6 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use ansi_term::Colour::Yellow;
 use clap::{App, Arg};
 use k8s_openapi::api::storage::v1 as api_storage;
 
@@ -26,7 +25,6 @@ use crate::{
     table::CellSpec,
 };
 
-use std::array::IntoIter;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::io::Write;
@@ -59,6 +57,8 @@ fn storageclass_to_kobj(node: &api_storage::StorageClass) -> KObj {
             .clone()
             .unwrap_or_else(|| "<Unknown>".into()),
         namespace: None,
+        uid: node.metadata.uid.clone(),
+        context: None,
         typ: ObjType::StorageClass,
     }
 }
@@ -100,7 +100,7 @@ list_command!(
     },
     vec!["storageclass", "storageclasses"],
     noop_complete!(),
-    IntoIter::new([]),
+    [],
     |matches, env, writer| {
         let cols: Vec<&str> = COL_MAP.iter().map(|(_, col)| *col).collect();
         let (request, _response_body) =
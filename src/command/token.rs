@@ -0,0 +1,298 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{TimeZone, Utc};
+use clap::{App, Arg};
+use k8s_openapi::http::{header::CONTENT_TYPE, Request};
+use k8s_openapi::RequestError;
+use rustyline::completion::Pair as RustlinePair;
+use serde_json::{json, Value};
+
+use crate::{
+    command::command_def::{exec_match, start_clap, Cmd},
+    command::rbac::{pod_service_account, service_account_arg},
+    command::{
+        dry_run_request, format_duration, kubectl_command, kubectl_output, parse_duration,
+        time_since, valid_duration,
+    },
+    completer,
+    env::{Env, ObjectSelection},
+    error::ClickError,
+    kobj::ObjType,
+    output::ClickWriter,
+    theme::{self, Role},
+    values::val_str_opt,
+};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+use std::time::Duration;
+
+const SA_TOKEN_SECRET_TYPE: &str = "kubernetes.io/service-account-token";
+const SA_NAME_ANNOTATION: &str = "kubernetes.io/service-account.name";
+
+/// Does a failed token request mean the cluster is too old for the TokenRequest api, as opposed
+/// to something like the service account not existing
+fn create_token_unsupported(err: &ClickError) -> bool {
+    match err {
+        ClickError::Api { code, message, .. } => {
+            *code == 404 && message.contains("the server could not find the requested resource")
+        }
+        _ => false,
+    }
+}
+
+/// Create a token for service account ns/sa that's valid for duration, with the TokenRequest api.
+/// Returns None if this is a dry run.
+fn request_token(
+    env: &Env,
+    ns: &str,
+    sa: &str,
+    duration: Duration,
+    writer: &mut ClickWriter,
+) -> Result<Option<String>, ClickError> {
+    let body = json!({
+        "apiVersion": "authentication.k8s.io/v1",
+        "kind": "TokenRequest",
+        "spec": {
+            "expirationSeconds": duration.as_secs(),
+        },
+    });
+    let request = Request::post(format!(
+        "/api/v1/namespaces/{}/serviceaccounts/{}/token",
+        ns, sa
+    ))
+    .header(CONTENT_TYPE, "application/json")
+    .body(serde_json::to_vec(&body)?)
+    .map_err(RequestError::Http)?;
+    if dry_run_request(env, &request, writer) {
+        return Ok(None);
+    }
+    let response = env.run_on_context(|c| c.execute(request))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(
+            ClickError::from_status(status.as_u16(), response.body()).unwrap_or_else(|| {
+                ClickError::CommandError(format!("Token request failed with status {}", status))
+            }),
+        );
+    }
+    let value: Value = serde_json::from_slice(response.body())?;
+    val_str_opt("/status/token", &value)
+        .map(Some)
+        .ok_or_else(|| ClickError::CommandError("Token request returned no token".to_string()))
+}
+
+/// The namespace and name of the service account to get a token for: the one given with
+/// --serviceaccount, else the selected service account, else the selected pod's
+fn token_service_account(
+    matches: &clap::ArgMatches,
+    env: &Env,
+) -> Result<(String, String), ClickError> {
+    if let Some(sa) = matches.value_of("serviceaccount") {
+        return service_account_arg(env, sa);
+    }
+    match env.current_selection() {
+        ObjectSelection::Single(obj) if obj.is(ObjType::ServiceAccount) => Ok((
+            obj.namespace
+                .clone()
+                .unwrap_or_else(|| "default".to_string()),
+            obj.name().to_string(),
+        )),
+        ObjectSelection::Single(obj) if obj.is_pod() => pod_service_account(env),
+        _ => Err(ClickError::CommandError(
+            "No service account or pod selected. Select one, or specify --serviceaccount"
+                .to_string(),
+        )),
+    }
+}
+
+/// Get a token for the service account from one of its token secrets, for clusters that are too
+/// old for `kubectl create token`. Returns the token and the name of the secret it came from.
+fn secret_token(env: &Env, ns: &str, sa: &str) -> Result<(String, String), ClickError> {
+    let mut command = kubectl_command(env, Some(ns))?;
+    command
+        .arg("get")
+        .arg("secrets")
+        .arg(format!("--field-selector=type={}", SA_TOKEN_SECRET_TYPE))
+        .arg("-o")
+        .arg("json");
+    let secrets: Value = serde_json::from_str(&kubectl_output(command)?)?;
+    let secret = secrets
+        .pointer("/items")
+        .and_then(|items| items.as_array())
+        .and_then(|items| {
+            items.iter().find(|secret| {
+                secret
+                    .pointer("/metadata/annotations")
+                    .and_then(|annotations| annotations.get(SA_NAME_ANNOTATION))
+                    .and_then(|name| name.as_str())
+                    == Some(sa)
+            })
+        })
+        .ok_or_else(|| {
+            ClickError::CommandError(format!(
+                "This cluster doesn't support creating tokens, and serviceaccount {}/{} has no \
+                 token secret",
+                ns, sa
+            ))
+        })?;
+    let name = secret
+        .pointer("/metadata/name")
+        .and_then(|name| name.as_str())
+        .unwrap_or("<unknown>")
+        .to_string();
+    let token = secret
+        .pointer("/data/token")
+        .and_then(|token| token.as_str())
+        .ok_or_else(|| ClickError::CommandError(format!("Secret {} has no token", name)))?;
+    let token = String::from_utf8(base64::decode(token)?)
+        .map_err(|_| ClickError::CommandError(format!("Secret {} has an invalid token", name)))?;
+    Ok((token, name))
+}
+
+/// Decode one of the dot separated parts of a JWT into json
+fn decode_jwt_part(part: &str) -> Result<Value, ClickError> {
+    // tokens shouldn't be padded, but accept ones that are
+    let decoded = base64::decode_config(part.trim_end_matches('='), base64::URL_SAFE_NO_PAD)?;
+    serde_json::from_slice(&decoded).map_err(ClickError::from)
+}
+
+/// Decode the header and payload of a JWT. The signature isn't checked, this is only for looking
+/// at what's in the token.
+fn decode_jwt(token: &str) -> Result<(Value, Value), ClickError> {
+    let parts: Vec<&str> = token.split('.').collect();
+    if parts.len() != 3 {
+        return Err(ClickError::ParseErr(
+            "Token is not a JWT (it doesn't have three parts)".to_string(),
+        ));
+    }
+    Ok((decode_jwt_part(parts[0])?, decode_jwt_part(parts[1])?))
+}
+
+/// The time in the numeric claim of payload, with how long ago or until it is
+fn claim_time(payload: &Value, claim: &str) -> Option<String> {
+    let date = Utc
+        .timestamp_opt(payload.get(claim)?.as_i64()?, 0)
+        .single()?;
+    let since = time_since(date);
+    Some(if since.num_seconds() < 0 {
+        format!("{} (in {})", date, format_duration(-since))
+    } else {
+        format!("{} ({} ago)", date, format_duration(since))
+    })
+}
+
+command!(
+    Token,
+    "token",
+    "Get a token for a service account, and show what's in it. With no service account \
+     specified, uses the selected service account, or the service account of the selected pod.",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("serviceaccount")
+                .long("serviceaccount")
+                .visible_alias("sa")
+                .short("s")
+                .help(
+                    "Get a token for this service account. Specify as NAMESPACE/NAME, or just \
+                     NAME to use the current namespace",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("duration")
+                .long("duration")
+                .visible_alias("ttl")
+                .short("d")
+                .help(
+                    "How long the token should be valid for, i.e. 10m or 2h. The server may \
+                     limit this.",
+                )
+                .takes_value(true)
+                .validator(valid_duration)
+                .default_value("1h"),
+        )
+        .arg(
+            Arg::with_name("raw")
+                .long("raw")
+                .help("Print just the token, rather than what's in it"),
+        )
+        .after_help(
+            "Tokens are created with the TokenRequest api. On clusters too old for that, the \
+long lived token from the service account's token secret is used instead. The token itself is \
+only printed with --raw, so it doesn't end up in your scrollback by accident. The signature isn't \
+checked, this is only to see what's in the token.
+
+Examples:
+  # see the claims of a token for the active pod's service account
+  token
+
+  # get a token for a service account picked from a list, valid for a day
+  serviceaccounts
+  1
+  token --ttl 24h
+
+  # save a 10 minute token for the ci service account in the build namespace
+  token --sa build/ci --duration 10m --raw > /tmp/token",
+        )
+    },
+    vec!["token"],
+    noop_complete!(),
+    no_named_complete!(),
+    |matches, env, writer| {
+        let (ns, sa) = token_service_account(&matches, env)?;
+        let duration = parse_duration(matches.value_of("duration").unwrap()).unwrap(); // safe, validated with a default
+
+        let (token, secret) = match request_token(env, &ns, &sa, duration, writer) {
+            Ok(Some(token)) => (token, None),
+            Ok(None) => return Ok(()), // dry run
+            Err(ref e) if create_token_unsupported(e) => {
+                let (token, secret) = secret_token(env, &ns, &sa)?;
+                (token, Some(secret))
+            }
+            Err(e) => return Err(e),
+        };
+
+        if matches.is_present("raw") {
+            clickwriteln!(writer, "{}", token);
+            return Ok(());
+        }
+        let (header, payload) = decode_jwt(&token)?;
+        clickwriteln!(writer, "Token for serviceaccount {}/{}", ns, sa);
+        if let Some(secret) = secret {
+            clickwriteln!(
+                writer,
+                "{}",
+                theme::style(Role::Warning).paint(format!(
+                    "Note: this cluster can't create tokens, so this is the long lived token \
+                     from secret {}",
+                    secret
+                ))
+            );
+        }
+        for (claim, label) in [("iat", "Issued"), ("exp", "Expires")].iter() {
+            if let Some(time) = claim_time(&payload, claim) {
+                clickwriteln!(writer, "{}: {}", label, time);
+            }
+        }
+        clickwriteln!(writer, "\nHeader:");
+        writer.pretty_color_json(&header)?;
+        clickwriteln!(writer, "\n\nPayload:");
+        writer.pretty_color_json(&payload)?;
+        clickwriteln!(writer, "");
+        Ok(())
+    }
+);
@@ -0,0 +1,437 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use clap::{App, Arg};
+use k8s_openapi::api::core::v1 as api;
+use k8s_openapi::List;
+use prettytable::{Cell, Row};
+use rustyline::completion::Pair as RustlinePair;
+
+use crate::{
+    command::command_def::{exec_match, start_clap, Cmd},
+    command::pods::pod_to_kobj,
+    command::{interruptible_sleep, parse_duration, valid_duration},
+    completer,
+    env::Env,
+    error::ClickError,
+    kobj::{KObj, ObjType},
+    metrics::{self, format_cpu, format_memory},
+    output::ClickWriter,
+    table::{print_table, raw_quantity, CellSpec},
+    theme::Role,
+};
+
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, IsTerminal, Write};
+
+/// Requests and limits, cpu in cores and memory in bytes. Anything not set is None.
+#[derive(Clone, Copy, Default)]
+struct Resources {
+    cpu_request: Option<f64>,
+    cpu_limit: Option<f64>,
+    memory_request: Option<f64>,
+    memory_limit: Option<f64>,
+}
+
+/// Add two optional amounts, which is only None if neither is set
+fn add_opt(a: Option<f64>, b: Option<f64>) -> Option<f64> {
+    match (a, b) {
+        (None, None) => None,
+        (a, b) => Some(a.unwrap_or(0.0) + b.unwrap_or(0.0)),
+    }
+}
+
+impl Resources {
+    fn of_container(container: &api::Container) -> Resources {
+        match container.resources.as_ref() {
+            Some(resources) => Resources {
+                cpu_request: resources.requests.get("cpu").map(raw_quantity),
+                cpu_limit: resources.limits.get("cpu").map(raw_quantity),
+                memory_request: resources.requests.get("memory").map(raw_quantity),
+                memory_limit: resources.limits.get("memory").map(raw_quantity),
+            },
+            None => Resources::default(),
+        }
+    }
+
+    fn add(&self, other: &Resources) -> Resources {
+        Resources {
+            cpu_request: add_opt(self.cpu_request, other.cpu_request),
+            cpu_limit: add_opt(self.cpu_limit, other.cpu_limit),
+            memory_request: add_opt(self.memory_request, other.memory_request),
+            memory_limit: add_opt(self.memory_limit, other.memory_limit),
+        }
+    }
+}
+
+/// One row of the pods table, for a whole pod or a single container of it
+struct PodUsage {
+    obj: KObj,
+    container: Option<String>,
+    cpu: f64,
+    memory: f64,
+    resources: Resources,
+}
+
+/// A cell showing usage, in the bad status style if it's more than was requested
+fn usage_cell<'a>(usage: f64, request: Option<f64>, text: String) -> CellSpec<'a> {
+    match request {
+        Some(request) if usage > request => CellSpec::with_role(text.into(), Role::BadStatus),
+        _ => text.into(),
+    }
+}
+
+fn opt_cell<'a>(amount: Option<f64>, format: fn(f64) -> String) -> CellSpec<'a> {
+    amount.map(format).unwrap_or_else(|| "-".to_string()).into()
+}
+
+/// Sort rows the way --sort asks: by name, or by the most cpu or memory used
+fn sort_usage<T, F>(rows: &mut [T], sort: Option<&str>, usage: F)
+where
+    F: Fn(&T) -> (f64, f64),
+{
+    let by = |a: f64, b: f64| b.partial_cmp(&a).unwrap_or(Ordering::Equal);
+    match sort {
+        Some("cpu") => rows.sort_by(|a, b| by(usage(a).0, usage(b).0)),
+        Some("memory") => rows.sort_by(|a, b| by(usage(a).1, usage(b).1)),
+        _ => {}
+    }
+}
+
+fn top_pods(
+    env: &mut Env,
+    name: Option<&str>,
+    containers: bool,
+    sort: Option<&str>,
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    let namespace = env.namespace.clone();
+    let mut pod_metrics = metrics::pod_metrics(env, namespace.as_deref())?;
+    if let Some(name) = name {
+        pod_metrics.retain(|metric| metric.metadata.name.as_deref() == Some(name));
+        if pod_metrics.is_empty() {
+            return Err(no_metrics_error("pod", name));
+        }
+    }
+    let (request, _) = match namespace.as_ref() {
+        Some(ns) => api::Pod::list_namespaced_pod(ns, Default::default())?,
+        None => api::Pod::list_pod_for_all_namespaces(Default::default())?,
+    };
+    let pod_list: List<api::Pod> = env.run_on_context(|c| c.execute_list(request))?;
+    // each pod, and the requests and limits of its containers, keyed by namespace and name
+    let mut pods: HashMap<(String, String), (KObj, BTreeMap<String, Resources>)> = HashMap::new();
+    for pod in pod_list.items.iter() {
+        let resources = pod
+            .spec
+            .iter()
+            .flat_map(|spec| spec.containers.iter())
+            .map(|c| (c.name.clone(), Resources::of_container(c)))
+            .collect();
+        pods.insert(
+            (
+                pod.metadata.namespace.clone().unwrap_or_default(),
+                pod.metadata.name.clone().unwrap_or_default(),
+            ),
+            (pod_to_kobj(pod), resources),
+        );
+    }
+
+    let mut rows = vec![];
+    for metric in pod_metrics.iter() {
+        let name = metric.metadata.name.clone().unwrap_or_default();
+        let ns = metric.metadata.namespace.clone().unwrap_or_default();
+        let (obj, resources) = match pods.remove(&(ns.clone(), name.clone())) {
+            Some(pod) => pod,
+            // the pod is gone since the metrics were collected
+            None => (
+                KObj {
+                    name,
+                    namespace: Some(ns),
+                    uid: None,
+                    context: None,
+                    typ: ObjType::Pod {
+                        containers: metric.containers.iter().map(|c| c.name.clone()).collect(),
+                    },
+                },
+                BTreeMap::new(),
+            ),
+        };
+        if containers {
+            for container in metric.containers.iter() {
+                rows.push(PodUsage {
+                    obj: obj.clone(),
+                    container: Some(container.name.clone()),
+                    cpu: container.usage.cpu_cores(),
+                    memory: container.usage.memory_bytes(),
+                    resources: resources.get(&container.name).copied().unwrap_or_default(),
+                });
+            }
+        } else {
+            rows.push(PodUsage {
+                obj,
+                container: None,
+                cpu: metric.total_cpu_cores(),
+                memory: metric.total_memory_bytes(),
+                resources: resources
+                    .values()
+                    .fold(Resources::default(), |total, r| total.add(r)),
+            });
+        }
+    }
+    sort_usage(&mut rows, sort, |row| (row.cpu, row.memory));
+
+    let mut titles = vec![Cell::new("####")];
+    if namespace.is_none() {
+        titles.push(Cell::new("Namespace"));
+    }
+    titles.push(Cell::new("Name"));
+    if containers {
+        titles.push(Cell::new("Container"));
+    }
+    for title in ["CPU", "CPU Req", "CPU Lim", "Memory", "Mem Req", "Mem Lim"].iter() {
+        titles.push(Cell::new(title));
+    }
+    let specs = rows
+        .iter()
+        .map(|row| {
+            let mut spec = vec![CellSpec::new_index()];
+            if namespace.is_none() {
+                spec.push(row.obj.namespace.clone().unwrap_or_default().into());
+            }
+            spec.push(row.obj.name().to_string().into());
+            if let Some(container) = row.container.as_ref() {
+                spec.push(container.clone().into());
+            }
+            let res = &row.resources;
+            spec.push(usage_cell(row.cpu, res.cpu_request, format_cpu(row.cpu)));
+            spec.push(opt_cell(res.cpu_request, format_cpu));
+            spec.push(opt_cell(res.cpu_limit, format_cpu));
+            spec.push(usage_cell(
+                row.memory,
+                res.memory_request,
+                format_memory(row.memory),
+            ));
+            spec.push(opt_cell(res.memory_request, format_memory));
+            spec.push(opt_cell(res.memory_limit, format_memory));
+            spec
+        })
+        .collect();
+    print_table(Row::new(titles), specs, writer);
+    env.set_last_objs(rows.into_iter().map(|row| row.obj).collect::<Vec<KObj>>());
+    Ok(())
+}
+
+fn no_metrics_error(kind: &str, name: &str) -> ClickError {
+    ClickError::CommandError(format!(
+        "No metrics for {} {}. It may not exist, or be too new to have been measured yet.",
+        kind, name
+    ))
+}
+
+/// Show usage once, into writer
+fn show_top(
+    matches: &clap::ArgMatches,
+    env: &mut Env,
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    let name = matches.value_of("name");
+    let sort = matches.value_of("sort");
+    match matches.value_of("kind") {
+        Some("nodes") | Some("node") => top_nodes(env, name, sort, writer),
+        _ => top_pods(env, name, matches.is_present("containers"), sort, writer),
+    }
+}
+
+/// Usage as a percent of what's allocatable
+fn percent(usage: f64, allocatable: Option<f64>) -> String {
+    match allocatable {
+        Some(total) if total > 0.0 => format!("{}%", (usage * 100.0 / total).round() as i64),
+        _ => "-".to_string(),
+    }
+}
+
+fn top_nodes(
+    env: &mut Env,
+    name: Option<&str>,
+    sort: Option<&str>,
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    let mut node_metrics = metrics::node_metrics(env)?;
+    if let Some(name) = name {
+        node_metrics.retain(|metric| metric.metadata.name.as_deref() == Some(name));
+        if node_metrics.is_empty() {
+            return Err(no_metrics_error("node", name));
+        }
+    }
+    let (request, _) = api::Node::list_node(Default::default())?;
+    let node_list: List<api::Node> = env.run_on_context(|c| c.execute_list(request))?;
+    let nodes: HashMap<&str, &api::Node> = node_list
+        .items
+        .iter()
+        .filter_map(|node| node.metadata.name.as_deref().map(|name| (name, node)))
+        .collect();
+    sort_usage(&mut node_metrics, sort, |node| {
+        (node.usage.cpu_cores(), node.usage.memory_bytes())
+    });
+
+    let mut objs = vec![];
+    let specs = node_metrics
+        .iter()
+        .map(|metric| {
+            let name = metric.metadata.name.clone().unwrap_or_default();
+            let node = nodes.get(name.as_str());
+            let allocatable = |resource: &str| {
+                node.and_then(|n| n.status.as_ref())
+                    .and_then(|status| status.allocatable.get(resource))
+                    .map(raw_quantity)
+            };
+            let cpu = metric.usage.cpu_cores();
+            let memory = metric.usage.memory_bytes();
+            let spec = vec![
+                CellSpec::new_index(),
+                name.clone().into(),
+                format_cpu(cpu).into(),
+                percent(cpu, allocatable("cpu")).into(),
+                format_memory(memory).into(),
+                percent(memory, allocatable("memory")).into(),
+            ];
+            objs.push(KObj {
+                name,
+                namespace: None,
+                uid: node.and_then(|n| n.metadata.uid.clone()),
+                context: None,
+                typ: ObjType::Node,
+            });
+            spec
+        })
+        .collect();
+    print_table(
+        row!["####", "Name", "CPU", "CPU %", "Memory", "Memory %"],
+        specs,
+        writer,
+    );
+    env.set_last_objs(objs);
+    Ok(())
+}
+
+command!(
+    Top,
+    "top",
+    "Show the cpu and memory used by pods (in current namespace if set) or nodes, from the \
+     metrics api served by metrics-server",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("kind")
+                .help("What to show usage for")
+                .required(true)
+                .possible_values(&["pods", "pod", "nodes", "node"])
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("name")
+                .help("Only show usage for the pod or node with this name")
+                .index(2),
+        )
+        .arg(
+            Arg::with_name("containers")
+                .long("containers")
+                .help("Show the usage of each container in the pods")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("sort")
+                .short("s")
+                .long("sort")
+                .help("Sort by the most cpu or memory used")
+                .takes_value(true)
+                .possible_values(&["cpu", "memory"]),
+        )
+        .arg(
+            Arg::with_name("watch")
+                .short("w")
+                .long("watch")
+                .help("Refresh the usage every interval, until ctrl-c is pressed")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("interval")
+                .short("i")
+                .long("interval")
+                .help("How often to refresh with --watch, i.e. 2s or 1m")
+                .takes_value(true)
+                .validator(valid_duration)
+                .default_value("5s"),
+        )
+        .after_help(
+            "This needs the metrics api, which is provided by metrics-server. Pods using more than \
+their request are highlighted. Listed objects can be selected by number, like other lists. With \
+--watch, the table is redrawn in place on a terminal, and printed again each time otherwise.
+
+Examples:
+  # which pods are using the most memory
+  top pods --sort memory
+
+  # keep an eye on one node, every 2 seconds
+  metrics-server nodes worker-1 --watch --interval 2s
+
+  # usage of each container in each pod
+  top pods --containers",
+        )
+    },
+    vec!["top", "metrics-server"],
+    noop_complete!(),
+    no_named_complete!(),
+    |matches, env, writer| {
+        let nodes = matches!(matches.value_of("kind"), Some("nodes") | Some("node"));
+        if nodes && matches.is_present("containers") {
+            return Err(ClickError::CommandError(
+                "--containers only applies to pods".to_string(),
+            ));
+        }
+        if !matches.is_present("watch") {
+            return show_top(&matches, env, writer);
+        }
+
+        let interval = parse_duration(matches.value_of("interval").unwrap()).unwrap(); // safe, validated with a default
+        let in_place = !writer.is_redirected() && io::stdout().is_terminal();
+        writer.finish_paging();
+        env.ctrlcbool
+            .store(false, std::sync::atomic::Ordering::SeqCst);
+        // how many lines the last table took, so it can be drawn over
+        let mut lines = 0;
+        loop {
+            if in_place {
+                let mut buffer = writer.buffered();
+                show_top(&matches, env, &mut buffer)?;
+                let output = buffer.finish_output().unwrap_or_default();
+                if lines > 0 {
+                    // move up to the start of the last table, and clear from there down
+                    clickwrite!(writer, "\x1b[{}A\x1b[J", lines);
+                }
+                writer.write_all(&output)?;
+                writer.flush()?;
+                lines = output.iter().filter(|b| **b == b'\n').count();
+            } else {
+                show_top(&matches, env, writer)?;
+                clickwrite!(writer, "\n");
+            }
+            if !interruptible_sleep(env, interval) {
+                break;
+            }
+        }
+        Ok(())
+    }
+);
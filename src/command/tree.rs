@@ -0,0 +1,335 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use ansi_term::Style;
+use clap::{App, Arg};
+use k8s_openapi::api::apps::v1 as apps_api;
+use k8s_openapi::api::core::v1 as api;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{LabelSelector, ObjectMeta};
+use k8s_openapi::{List, ListOptional};
+use rustyline::completion::Pair as RustlinePair;
+use serde_json::Value;
+
+use crate::{
+    command::command_def::{exec_match, start_clap, Cmd},
+    command::pods::{get_pods_for_selector, phase_role, pod_status_str, pod_to_kobj},
+    command::{kubectl_get_json, selector_string},
+    completer,
+    env::{Env, ObjectSelection},
+    error::ClickError,
+    kobj::{KObj, ObjType},
+    output::{ClickWriter, OutputFormat},
+    theme,
+    values::val_str_opt,
+};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+
+const REVISION_ANNOTATION: &str = "deployment.kubernetes.io/revision";
+
+/// A level of the tree: what goes before a child, and before the children of that child
+fn branch(last: bool) -> (&'static str, &'static str) {
+    if last {
+        ("└── ", "    ")
+    } else {
+        ("├── ", "│   ")
+    }
+}
+
+fn val_i64(pointer: &str, value: &Value) -> i64 {
+    value.pointer(pointer).and_then(|v| v.as_i64()).unwrap_or(0)
+}
+
+/// How the top of the tree is doing, like "3/3 ready"
+fn controller_summary(obj: &KObj, value: &Value) -> String {
+    match obj.typ {
+        ObjType::DaemonSet => format!(
+            "{}/{} ready",
+            val_i64("/status/numberReady", value),
+            val_i64("/status/desiredNumberScheduled", value)
+        ),
+        ObjType::Job => format!(
+            "{}/{} succeeded",
+            val_i64("/status/succeeded", value),
+            value
+                .pointer("/spec/completions")
+                .and_then(|c| c.as_i64())
+                .unwrap_or(1)
+        ),
+        _ => {
+            let summary = format!(
+                "{}/{} ready",
+                val_i64("/status/readyReplicas", value),
+                val_i64("/spec/replicas", value)
+            );
+            let annotation = format!(
+                "/metadata/annotations/{}",
+                REVISION_ANNOTATION.replace('/', "~1")
+            );
+            match val_str_opt(&annotation, value) {
+                Some(revision) => format!("{}, revision {}", summary, revision),
+                None => summary,
+            }
+        }
+    }
+}
+
+fn rs_revision(replicaset: &apps_api::ReplicaSet) -> Option<i64> {
+    replicaset
+        .metadata
+        .annotations
+        .get(REVISION_ANNOTATION)
+        .and_then(|rev| rev.parse().ok())
+}
+
+/// A replicaset is inactive if it wants, and has, zero replicas
+fn rs_inactive(replicaset: &apps_api::ReplicaSet) -> bool {
+    let desired = replicaset
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.replicas)
+        .unwrap_or(1);
+    let current = replicaset
+        .status
+        .as_ref()
+        .map(|stat| stat.replicas)
+        .unwrap_or(0);
+    desired == 0 && current == 0
+}
+
+fn rs_line(replicaset: &apps_api::ReplicaSet) -> String {
+    let name = replicaset.metadata.name.as_deref().unwrap_or("<Unknown>");
+    let desired = replicaset
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.replicas)
+        .unwrap_or(1);
+    let ready = replicaset
+        .status
+        .as_ref()
+        .and_then(|stat| stat.ready_replicas)
+        .unwrap_or(0);
+    match rs_revision(replicaset) {
+        Some(revision) => format!(
+            "ReplicaSet {} (revision {}, {}/{} ready)",
+            name, revision, ready, desired
+        ),
+        None => format!("ReplicaSet {} ({}/{} ready)", name, ready, desired),
+    }
+}
+
+/// Does the object with meta have an owner with uid as its controller
+fn controlled_by(meta: &ObjectMeta, uid: &str) -> bool {
+    meta.owner_references
+        .iter()
+        .any(|owner| owner.controller == Some(true) && owner.uid == uid)
+}
+
+/// Print a pod, numbered like a list so it can be selected from, and remember it in pods
+fn print_pod(
+    pod: &api::Pod,
+    prefix: &str,
+    last: bool,
+    name_width: usize,
+    wide: bool,
+    pods: &mut Vec<KObj>,
+    writer: &mut ClickWriter,
+) {
+    let (branch, _) = branch(last);
+    let name = pod.metadata.name.as_deref().unwrap_or("<Unknown>");
+    let status = pod_status_str(pod);
+    let restarts: i32 = pod
+        .status
+        .as_ref()
+        .map(|stat| {
+            stat.container_statuses
+                .iter()
+                .map(|cs| cs.restart_count)
+                .sum()
+        })
+        .unwrap_or(0);
+    clickwrite!(
+        writer,
+        "{}{}{:<3} {:<width$}  {}",
+        prefix,
+        branch,
+        pods.len(),
+        name,
+        theme::style(phase_role(status)).paint(status),
+        width = name_width
+    );
+    if restarts > 0 {
+        clickwrite!(
+            writer,
+            "  {} restart{}",
+            restarts,
+            if restarts == 1 { "" } else { "s" }
+        );
+    }
+    if wide {
+        let node = pod
+            .spec
+            .as_ref()
+            .and_then(|spec| spec.node_name.as_deref())
+            .unwrap_or("<none>");
+        let ip = pod
+            .status
+            .as_ref()
+            .and_then(|stat| stat.pod_ip.as_deref())
+            .unwrap_or("<none>");
+        clickwrite!(writer, "  node {}, ip {}", node, ip);
+    }
+    clickwriteln!(writer, "");
+    pods.push(pod_to_kobj(pod));
+}
+
+fn print_pods(
+    children: &[&api::Pod],
+    prefix: &str,
+    wide: bool,
+    pods: &mut Vec<KObj>,
+    writer: &mut ClickWriter,
+) {
+    let name_width = children
+        .iter()
+        .map(|pod| pod.metadata.name.as_deref().map(|n| n.len()).unwrap_or(0))
+        .max()
+        .unwrap_or(0);
+    for (i, pod) in children.iter().enumerate() {
+        let last = i + 1 == children.len();
+        print_pod(pod, prefix, last, name_width, wide, pods, writer);
+    }
+}
+
+command!(
+    Tree,
+    "tree",
+    "Show the active deployment, statefulset, daemonset or job as a tree of what it manages: a \
+     deployment's replicasets, and their pods. The pods become the active selection.",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("wide")
+                .long("wide")
+                .short("w")
+                .help("Show the node and ip of each pod"),
+        )
+        .after_help(
+            "Replicasets are shown newest revision first, and old ones that have been scaled down \
+to zero are dimmed. The pods are numbered, so 'select' picks from them like after a list, and \
+they're all selected, so logs or exec work on them straight away.",
+        )
+    },
+    vec!["tree"],
+    noop_complete!(),
+    no_named_complete!(),
+    |matches, env, writer| {
+        let obj = match env.current_selection() {
+            ObjectSelection::Single(obj) => obj.clone(),
+            _ => {
+                return Err(ClickError::CommandError(
+                    "Select a single deployment, statefulset, daemonset, or job".to_string(),
+                ))
+            }
+        };
+        if !matches!(
+            obj.typ,
+            ObjType::Deployment | ObjType::StatefulSet | ObjType::DaemonSet | ObjType::Job
+        ) {
+            return Err(ClickError::CommandError(format!(
+                "Can't show a tree for {} {}, only for deployments, statefulsets, daemonsets, and \
+                 jobs",
+                obj.type_str(),
+                obj.name()
+            )));
+        }
+        let wide = matches.is_present("wide") || writer.output_format() == OutputFormat::Wide;
+        let value = kubectl_get_json(env, &obj)?;
+        let uid = val_str_opt("/metadata/uid", &value).unwrap_or_default();
+        let ns = obj.namespace.as_deref().unwrap_or("default");
+        let selector: LabelSelector = match value.pointer("/spec/selector") {
+            Some(selector) => serde_json::from_value(selector.clone())?,
+            None => {
+                return Err(ClickError::CommandError(format!(
+                    "{} {} has no selector",
+                    obj.type_str(),
+                    obj.name()
+                )))
+            }
+        };
+        let pod_list = get_pods_for_selector(env, ns, &selector)?;
+
+        clickwriteln!(
+            writer,
+            "{} {} ({})",
+            obj.type_str(),
+            obj.name(),
+            controller_summary(&obj, &value)
+        );
+        let mut pods = vec![];
+        if obj.is(ObjType::Deployment) {
+            let sel = selector_string(&selector);
+            let opts = ListOptional {
+                label_selector: Some(sel.as_str()),
+                ..Default::default()
+            };
+            let (request, _) = apps_api::ReplicaSet::list_namespaced_replica_set(ns, opts)?;
+            let rs_list: List<apps_api::ReplicaSet> =
+                env.run_on_context(|c| c.execute_list(request))?;
+            let mut replicasets: Vec<&apps_api::ReplicaSet> = rs_list
+                .items
+                .iter()
+                .filter(|rs| controlled_by(&rs.metadata, &uid))
+                .collect();
+            replicasets.sort_by_key(|rs| std::cmp::Reverse(rs_revision(rs)));
+            for (i, rs) in replicasets.iter().enumerate() {
+                let (branch, indent) = branch(i + 1 == replicasets.len());
+                let line = rs_line(rs);
+                if rs_inactive(rs) {
+                    clickwriteln!(writer, "{}{}", branch, Style::new().dimmed().paint(line));
+                } else {
+                    clickwriteln!(writer, "{}{}", branch, line);
+                }
+                let rs_uid = rs.metadata.uid.as_deref().unwrap_or_default();
+                let children: Vec<&api::Pod> = pod_list
+                    .items
+                    .iter()
+                    .filter(|pod| controlled_by(&pod.metadata, rs_uid))
+                    .collect();
+                print_pods(&children, indent, wide, &mut pods, writer);
+            }
+        } else {
+            let children: Vec<&api::Pod> = pod_list
+                .items
+                .iter()
+                .filter(|pod| controlled_by(&pod.metadata, &uid))
+                .collect();
+            print_pods(&children, "", wide, &mut pods, writer);
+        }
+
+        match pods.len() {
+            0 => env.clear_current(),
+            1 => {
+                env.set_last_objs(pods);
+                env.set_current(0);
+            }
+            _ => {
+                env.set_last_objs(pods.clone());
+                env.set_range(pods);
+            }
+        }
+        Ok(())
+    }
+);
@@ -0,0 +1,326 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use clap::{App, Arg};
+use prettytable::Row;
+use rustyline::completion::Pair as RustlinePair;
+use serde_json::Value;
+
+use crate::{
+    command::command_def::{exec_match, start_clap, Cmd},
+    command::{command_line, confirm_action, tool_exit_error, tool_spawn_error},
+    completer,
+    env::Env,
+    error::ClickError,
+    output::ClickWriter,
+    table::CellSpec,
+    theme::Role,
+};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::Command;
+
+const VELERO_URL: &str = "https://velero.io/docs/main/basic-install/";
+
+/// Where velero is usually installed, used when there's no active namespace
+const DEFAULT_NAMESPACE: &str = "velero";
+
+/// A velero command against the active context, and namespace (or velero's default)
+fn velero_command(env: &Env) -> Result<Command, ClickError> {
+    let context = env.context.as_ref().ok_or_else(|| {
+        ClickError::CommandError("Need an active context in order to run velero".to_string())
+    })?;
+    let mut command = Command::new("velero");
+    command.arg("--kubecontext").arg(&context.name);
+    command
+        .arg("--namespace")
+        .arg(env.namespace.as_deref().unwrap_or(DEFAULT_NAMESPACE));
+    Ok(command)
+}
+
+/// Run velero, returning what it printed
+fn velero_output(mut command: Command) -> Result<String, ClickError> {
+    let output = command
+        .output()
+        .map_err(|e| tool_spawn_error("velero", VELERO_URL, e))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(tool_exit_error("velero", output.status, &output.stderr))
+    }
+}
+
+fn str_cell<'a>(value: &'a Value, pointer: &str) -> CellSpec<'a> {
+    value
+        .pointer(pointer)
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .into()
+}
+
+fn count_cell<'a>(value: &Value, pointer: &str) -> CellSpec<'a> {
+    value
+        .pointer(pointer)
+        .and_then(|v| v.as_i64())
+        .unwrap_or(0)
+        .into()
+}
+
+fn time_cell<'a>(value: &Value, pointer: &str) -> CellSpec<'a> {
+    value
+        .pointer(pointer)
+        .and_then(|v| v.as_str())
+        .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+        .map(|time| time.with_timezone(&Utc))
+        .into()
+}
+
+fn phase_cell(value: &Value) -> CellSpec<'_> {
+    let phase = value
+        .pointer("/status/phase")
+        .and_then(|p| p.as_str())
+        .unwrap_or("Unknown");
+    match phase {
+        "Completed" | "Enabled" => CellSpec::with_role(phase.into(), Role::GoodStatus),
+        "PartiallyFailed" => CellSpec::with_role(phase.into(), Role::Warning),
+        "Failed" | "FailedValidation" => CellSpec::with_role(phase.into(), Role::BadStatus),
+        _ => phase.into(),
+    }
+}
+
+fn backup_cells(backup: &Value) -> Vec<CellSpec<'_>> {
+    vec![
+        str_cell(backup, "/metadata/name"),
+        phase_cell(backup),
+        count_cell(backup, "/status/errors"),
+        count_cell(backup, "/status/warnings"),
+        time_cell(backup, "/status/startTimestamp"),
+        time_cell(backup, "/status/expiration"),
+        str_cell(backup, "/spec/storageLocation"),
+    ]
+}
+
+fn restore_cells(restore: &Value) -> Vec<CellSpec<'_>> {
+    vec![
+        str_cell(restore, "/metadata/name"),
+        str_cell(restore, "/spec/backupName"),
+        phase_cell(restore),
+        time_cell(restore, "/status/startTimestamp"),
+        time_cell(restore, "/status/completionTimestamp"),
+        count_cell(restore, "/status/errors"),
+        count_cell(restore, "/status/warnings"),
+    ]
+}
+
+fn schedule_cells(schedule: &Value) -> Vec<CellSpec<'_>> {
+    let paused = schedule
+        .pointer("/spec/paused")
+        .and_then(|p| p.as_bool())
+        .unwrap_or(false);
+    vec![
+        str_cell(schedule, "/metadata/name"),
+        phase_cell(schedule),
+        str_cell(schedule, "/spec/schedule"),
+        time_cell(schedule, "/status/lastBackup"),
+        if paused { "true" } else { "false" }.into(),
+    ]
+}
+
+/// Print the json velero gets, as is if the output format is json or yaml, otherwise as a table.
+/// velero prints a List when there's more than one item, and just the item when there's one.
+fn print_velero_table(
+    json: &str,
+    titles: Row,
+    cells: fn(&Value) -> Vec<CellSpec<'_>>,
+    writer: &mut ClickWriter,
+) -> Result<usize, ClickError> {
+    let value: Value = if json.trim().is_empty() {
+        Value::Array(vec![])
+    } else {
+        serde_json::from_str(json)?
+    };
+    let items: Vec<&Value> = match value.get("items").and_then(|items| items.as_array()) {
+        Some(items) => items.iter().collect(),
+        None if value.is_object() => vec![&value],
+        None => vec![],
+    };
+    if !writer.print_raw(&value)? {
+        let rows = items.iter().map(|item| cells(item)).collect();
+        crate::table::print_table(titles, rows, writer);
+    }
+    Ok(items.len())
+}
+
+/// Ask velero to restore from backup, after checking with the user
+fn create_restore(
+    env: &Env,
+    backup: &str,
+    name: Option<&str>,
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    let mut command = velero_command(env)?;
+    command.arg("restore").arg("create");
+    if let Some(name) = name {
+        command.arg(name);
+    }
+    command.arg("--from-backup").arg(backup);
+    if env.dry_run {
+        clickwriteln!(writer, "[DRY RUN] {}", command_line(&command));
+        return Ok(());
+    }
+    let context = env.context.as_ref().map(|c| c.name.as_str()).unwrap_or("");
+    let prompt = format!(
+        "Restore from backup {} in context {}? Objects that don't exist will be recreated",
+        backup, context
+    );
+    if !confirm_action(writer, &prompt) {
+        clickwriteln!(writer, "Not restoring");
+        return Ok(());
+    }
+    clickwrite!(writer, "{}", velero_output(command)?);
+    Ok(())
+}
+
+command!(
+    Velero,
+    "velero",
+    "Look at Velero backups, restores, and schedules, or restore from a backup, using the velero \
+     binary",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("kind")
+                .help(
+                    "backups: list backups, or describe one\n\
+                     restores: list restores, or describe one\n\
+                     schedules: list backup schedules, or describe one\n\
+                     restore: 'restore create' to restore from a backup",
+                )
+                .possible_values(&["backups", "restores", "schedules", "restore"])
+                .default_value("backups")
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("name")
+                .help("The backup, restore, or schedule to describe, or 'create' for restore")
+                .required_if("kind", "restore")
+                .index(2),
+        )
+        .arg(
+            Arg::with_name("restore_name")
+                .help("With restore create, the name of the new restore (velero picks one if not given)")
+                .index(3),
+        )
+        .arg(
+            Arg::with_name("from_backup")
+                .long("from-backup")
+                .short("b")
+                .value_name("BACKUP_NAME")
+                .help("With restore create, the backup to restore from")
+                .takes_value(true),
+        )
+        .after_help(
+            "velero needs to be installed. Commands run against the active context and \
+namespace, or the velero namespace if there isn't one, which needs to be where velero is \
+installed. With -o json or yaml (or 'set output_format'), lists print what velero returns rather \
+than a table.
+
+Examples:
+  # list backups
+  velero
+
+  # see what went wrong with a backup
+  velero backups nightly-20210601
+
+  # restore everything in a backup
+  velero restore create --from-backup nightly-20210601",
+        )
+    },
+    vec!["velero"],
+    vec![&completer::velerokind_values_completer],
+    no_named_complete!(),
+    |matches, env, writer| {
+        let kind = matches.value_of("kind").unwrap(); // safe, has a default
+        if kind == "restore" {
+            if matches.value_of("name") != Some("create") {
+                return Err(ClickError::CommandError(
+                    "Use 'velero restore create --from-backup BACKUP_NAME' to restore, or \
+                     'velero restores' to list restores"
+                        .to_string(),
+                ));
+            }
+            let backup = matches.value_of("from_backup").ok_or_else(|| {
+                ClickError::CommandError(
+                    "Need a backup to restore from, use --from-backup".to_string(),
+                )
+            })?;
+            return create_restore(env, backup, matches.value_of("restore_name"), writer);
+        }
+
+        // velero's commands are singular, i.e. velero backup get
+        let velero_kind = kind.trim_end_matches('s');
+        let mut command = velero_command(env)?;
+        command.arg(velero_kind);
+        match matches.value_of("name") {
+            Some(name) if !writer.output_format().is_raw() => {
+                command.arg("describe").arg(name);
+                clickwrite!(writer, "{}", velero_output(command)?);
+            }
+            name => {
+                command.arg("get");
+                if let Some(name) = name {
+                    command.arg(name);
+                }
+                command.arg("--output").arg("json");
+                let (titles, cells): (Row, fn(&Value) -> Vec<CellSpec<'_>>) = match kind {
+                    "backups" => (
+                        row![
+                            "Name",
+                            "Status",
+                            "Errors",
+                            "Warnings",
+                            "Created",
+                            "Expires",
+                            "Storage Location"
+                        ],
+                        backup_cells,
+                    ),
+                    "restores" => (
+                        row![
+                            "Name",
+                            "Backup",
+                            "Status",
+                            "Started",
+                            "Completed",
+                            "Errors",
+                            "Warnings"
+                        ],
+                        restore_cells,
+                    ),
+                    "schedules" => (
+                        row!["Name", "Status", "Schedule", "Last Backup", "Paused"],
+                        schedule_cells,
+                    ),
+                    _ => unreachable!(), // clap validates
+                };
+                if print_velero_table(&velero_output(command)?, titles, cells, writer)? == 0 {
+                    clickwriteln!(writer, "No {} found", kind);
+                }
+            }
+        }
+        Ok(())
+    }
+);
@@ -0,0 +1,168 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use clap::{App, Arg};
+use rustyline::completion::Pair as RustlinePair;
+use serde_json::Value;
+
+use crate::{
+    command::command_def::{exec_match, start_clap, Cmd},
+    command::{kubectl_command, kubectl_exit_error, kubectl_spawn_error},
+    completer,
+    env::Env,
+    error::ClickError,
+    output::ClickWriter,
+    theme::{self, Role},
+};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::Command;
+
+/// The minor version from a version like v1.27.3, or v1.26.5-gke.100
+pub fn minor_version(version: &str) -> Option<u32> {
+    let minor = version.trim_start_matches('v').split('.').nth(1)?;
+    let digits: String = minor.chars().take_while(|c| c.is_ascii_digit()).collect();
+    digits.parse().ok()
+}
+
+/// Run `kubectl version -o json`, returning the parsed output. kubectl exits non-zero if it can't
+/// reach the server, but still prints the client version, so that's returned along with the error.
+fn kubectl_version(mut command: Command) -> Result<(Value, Option<ClickError>), ClickError> {
+    let output = command
+        .arg("version")
+        .arg("-o")
+        .arg("json")
+        .output()
+        .map_err(kubectl_spawn_error)?;
+    let error = if output.status.success() {
+        None
+    } else {
+        Some(kubectl_exit_error(output.status, &output.stderr))
+    };
+    match serde_json::from_slice(&output.stdout) {
+        Ok(value) => Ok((value, error)),
+        Err(e) => Err(error.unwrap_or_else(|| e.into())),
+    }
+}
+
+fn git_version(output: &Value, key: &str) -> Option<String> {
+    output
+        .pointer(&format!("/{}/gitVersion", key))
+        .and_then(|v| v.as_str())
+        .map(|v| v.to_string())
+}
+
+/// The server version of the active context. It's fetched if it hasn't been already, and None is
+/// returned if it can't be.
+pub fn server_version(env: &mut Env) -> Option<String> {
+    if let Some(server) = env.server_version() {
+        return Some(server.clone());
+    }
+    let (output, _) = kubectl_version(kubectl_command(env, None).ok()?).ok()?;
+    let server = git_version(&output, "serverVersion")?;
+    env.set_server_version(server.clone());
+    Some(server)
+}
+
+command!(
+    Version,
+    "version",
+    "Print the versions of click, kubectl, and the kubernetes server of the current context",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("refresh")
+                .long("refresh")
+                .help("Fetch the server version again, rather than using the one from last time")
+                .takes_value(false),
+        )
+        .after_help(
+            "The server version is only fetched the first time for each context. kubectl \
+supports servers one minor version older or newer than it, and versions are highlighted if \
+kubectl and the server don't match.",
+        )
+    },
+    vec!["version"],
+    noop_complete!(),
+    no_named_complete!(),
+    |matches, env, writer| {
+        clickwriteln!(writer, "Click:   {}", env!("CARGO_PKG_VERSION"));
+
+        let cached = if matches.is_present("refresh") {
+            None
+        } else {
+            env.server_version().cloned()
+        };
+        // the client version is local, so it's cheap to get every time
+        let mut command = kubectl_command(env, None)?;
+        if cached.is_some() {
+            command.arg("--client");
+        }
+        let (output, server_error) = kubectl_version(command)?;
+        let client = git_version(&output, "clientVersion")
+            .ok_or_else(|| ClickError::ParseErr("kubectl didn't print its version".to_string()))?;
+        let server = match cached {
+            Some(server) => Some(server),
+            None => {
+                let server = git_version(&output, "serverVersion");
+                if let Some(ref server) = server {
+                    env.set_server_version(server.clone());
+                }
+                server
+            }
+        };
+
+        let skew = match (
+            minor_version(&client),
+            server.as_deref().and_then(minor_version),
+        ) {
+            (Some(client), Some(server)) => (client as i64 - server as i64).abs(),
+            _ => 0,
+        };
+        let paint = |version: &str| {
+            if skew > 0 {
+                theme::style(Role::Warning).paint(version).to_string()
+            } else {
+                version.to_string()
+            }
+        };
+        clickwriteln!(writer, "kubectl: {}", paint(&client));
+        let context = env.context.as_ref().map(|c| c.name.as_str()).unwrap_or("");
+        match server {
+            Some(server) => {
+                clickwriteln!(writer, "Server:  {} (context {})", paint(&server), context);
+                if skew > 1 {
+                    clickwriteln!(
+                        writer,
+                        "{}",
+                        theme::style(Role::Warning).paint(format!(
+                            "kubectl is {} minor versions away from the server, but only \
+                             supports a skew of one",
+                            skew
+                        ))
+                    );
+                }
+                Ok(())
+            }
+            None => {
+                clickwriteln!(writer, "Server:  unknown (context {})", context);
+                match server_error {
+                    Some(e) => Err(e),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+);
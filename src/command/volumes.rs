@@ -12,21 +12,24 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use ansi_term::Colour::Yellow;
 use clap::{App, Arg};
 use k8s_openapi::{api::core::v1 as api, apimachinery::pkg::api::resource::Quantity};
 
+use serde_json::{json, Value};
+
 use crate::{
     command::command_def::{exec_match, show_arg, sort_arg, start_clap, Cmd},
+    command::pods::read_pod_json,
     command::{run_list_command, Extractor},
     completer,
     env::Env,
+    error::ClickError,
     kobj::{KObj, ObjType},
     output::ClickWriter,
     table::CellSpec,
+    theme::Role,
 };
 
-use std::array::IntoIter;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::io::Write;
@@ -69,6 +72,8 @@ fn pv_to_kobj(volume: &api::PersistentVolume) -> KObj {
     KObj {
         name: meta.name.clone().unwrap_or_else(|| "<Unknown>".into()),
         namespace: meta.namespace.clone(),
+        uid: meta.uid.clone(),
+        context: None,
         typ: ObjType::PersistentVolume,
     }
 }
@@ -198,3 +203,265 @@ list_command!(
         )
     }
 );
+
+/// Where in a container a volume is mounted
+struct Mount {
+    container: String,
+    path: String,
+    sub_path: Option<String>,
+    read_only: bool,
+}
+
+/// The type of a volume in a pod spec, which is the one key besides its name, i.e. configMap
+fn pod_volume_type(volume: &Value) -> &str {
+    volume
+        .as_object()
+        .and_then(|fields| fields.keys().find(|key| *key != "name"))
+        .map(|key| key.as_str())
+        .unwrap_or("unknown")
+}
+
+/// What backs a volume: the object it comes from, or the path or medium for the types that don't
+/// have one
+fn pod_volume_source(typ: &str, source: &Value) -> String {
+    let field = |name: &str| source.get(name).and_then(|v| v.as_str()).unwrap_or("");
+    match typ {
+        "configMap" => field("name").to_string(),
+        "secret" => field("secretName").to_string(),
+        "persistentVolumeClaim" => field("claimName").to_string(),
+        "hostPath" => match source.get("type").and_then(|t| t.as_str()) {
+            Some(host_type) if !host_type.is_empty() => {
+                format!("{} ({})", field("path"), host_type)
+            }
+            _ => field("path").to_string(),
+        },
+        "emptyDir" => {
+            let mut desc = match field("medium") {
+                "" => "node disk".to_string(),
+                medium => medium.to_lowercase(),
+            };
+            if let Some(limit) = source.get("sizeLimit").and_then(|l| l.as_str()) {
+                desc.push_str(&format!(", limit {}", limit));
+            }
+            desc
+        }
+        "projected" => source
+            .get("sources")
+            .and_then(|s| s.as_array())
+            .map(|sources| {
+                sources
+                    .iter()
+                    .map(|s| {
+                        let name = |ptr: &str| s.pointer(ptr).and_then(|n| n.as_str());
+                        if let Some(name) = name("/configMap/name") {
+                            format!("configmap {}", name)
+                        } else if let Some(name) = name("/secret/name") {
+                            format!("secret {}", name)
+                        } else if s.get("serviceAccountToken").is_some() {
+                            "service account token".to_string()
+                        } else if s.get("downwardAPI").is_some() {
+                            "downward api".to_string()
+                        } else {
+                            pod_volume_type(s).to_string()
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })
+            .unwrap_or_default(),
+        "nfs" => format!("{}:{}", field("server"), field("path")),
+        "csi" => field("driver").to_string(),
+        _ => String::new(),
+    }
+}
+
+/// The mounts of the volume called name in any of the pod's containers
+fn pod_volume_mounts(pod: &Value, name: &str) -> Vec<Mount> {
+    let mut mounts = vec![];
+    for kind in ["initContainers", "containers", "ephemeralContainers"] {
+        let containers = pod
+            .pointer(&format!("/spec/{}", kind))
+            .and_then(|c| c.as_array());
+        for container in containers.into_iter().flatten() {
+            let volume_mounts = container.get("volumeMounts").and_then(|m| m.as_array());
+            for mount in volume_mounts.into_iter().flatten() {
+                if mount.get("name").and_then(|n| n.as_str()) != Some(name) {
+                    continue;
+                }
+                let field = |f: &str| mount.get(f).and_then(|v| v.as_str()).map(|v| v.to_string());
+                mounts.push(Mount {
+                    container: container
+                        .get("name")
+                        .and_then(|n| n.as_str())
+                        .unwrap_or("")
+                        .to_string(),
+                    path: field("mountPath").unwrap_or_default(),
+                    sub_path: field("subPath").filter(|p| !p.is_empty()),
+                    read_only: mount
+                        .get("readOnly")
+                        .and_then(|r| r.as_bool())
+                        .unwrap_or(false),
+                });
+            }
+        }
+    }
+    mounts
+}
+
+fn read_pvc(
+    env: &Env,
+    name: &str,
+    namespace: &str,
+) -> Result<Option<api::PersistentVolumeClaim>, ClickError> {
+    let (request, _) = api::PersistentVolumeClaim::read_namespaced_persistent_volume_claim(
+        name,
+        namespace,
+        Default::default(),
+    )?;
+    match env
+        .run_on_context(|c| c.read::<api::ReadNamespacedPersistentVolumeClaimResponse>(request))
+    {
+        Ok(api::ReadNamespacedPersistentVolumeClaimResponse::Ok(pvc)) => Ok(Some(pvc)),
+        Ok(_) | Err(ClickError::Api { code: 404, .. }) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+command!(
+    PodVolumes,
+    "volumes",
+    "List the volumes of the active pod: what type each is, what backs it, and where each of the \
+     pod's containers mounts it",
+    |clap: App<'static, 'static>| {
+        clap.after_help(
+            "hostPath volumes are highlighted, as they give the pod access to the node's \
+filesystem. For volumes from a persistent volume claim, the claim is looked up to show if it's \
+bound and its storage class.",
+        )
+    },
+    vec!["volumes", "vols"],
+    noop_complete!(),
+    no_named_complete!(),
+    |_matches, env, writer| {
+        let pod_obj = env.current_pod()?;
+        let namespace = pod_obj.namespace.as_deref().unwrap_or("default");
+        let pod = read_pod_json(env, pod_obj)?;
+        let volumes = pod
+            .pointer("/spec/volumes")
+            .and_then(|v| v.as_array())
+            .cloned()
+            .unwrap_or_default();
+
+        let mut raw = vec![];
+        let mut specs = vec![];
+        for volume in volumes.iter() {
+            let name = volume.get("name").and_then(|n| n.as_str()).unwrap_or("");
+            let typ = pod_volume_type(volume);
+            let source = volume.get(typ).cloned().unwrap_or(Value::Null);
+            let source_str = pod_volume_source(typ, &source);
+            let mounts = pod_volume_mounts(&pod, name);
+            let pvc = match source.get("claimName").and_then(|c| c.as_str()) {
+                Some(claim) if typ == "persistentVolumeClaim" => {
+                    Some((claim, read_pvc(env, claim, namespace)?))
+                }
+                _ => None,
+            };
+            let phase = pvc.as_ref().map(|(_, pvc)| {
+                pvc.as_ref()
+                    .and_then(|pvc| pvc.status.as_ref())
+                    .and_then(|status| status.phase.clone())
+            });
+            let storage_class = pvc.as_ref().and_then(|(_, pvc)| {
+                pvc.as_ref()
+                    .and_then(|pvc| pvc.spec.as_ref())
+                    .and_then(|spec| spec.storage_class_name.clone())
+            });
+            // a claim can be mounted read only for the whole pod, not just in a container
+            let volume_read_only = source
+                .get("readOnly")
+                .and_then(|r| r.as_bool())
+                .unwrap_or(false);
+
+            raw.push(json!({
+                "name": name,
+                "type": typ,
+                "source": source_str,
+                "mounts": mounts.iter().map(|m| json!({
+                    "container": m.container,
+                    "path": m.path,
+                    "subPath": m.sub_path,
+                    "readOnly": m.read_only || volume_read_only,
+                })).collect::<Vec<_>>(),
+                "claimPhase": phase.as_ref().map(|p| p.as_deref().unwrap_or("<missing>")),
+                "storageClass": storage_class,
+            }));
+
+            let mounts_str = if mounts.is_empty() {
+                "<not mounted>".to_string()
+            } else {
+                mounts
+                    .iter()
+                    .map(|m| {
+                        let mut desc = format!("{}: {}", m.container, m.path);
+                        if let Some(sub_path) = m.sub_path.as_ref() {
+                            desc.push_str(&format!(" (subPath {})", sub_path));
+                        }
+                        if m.read_only || volume_read_only {
+                            desc.push_str(" (read-only)");
+                        }
+                        desc
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            };
+            let (type_cell, source_cell) = if typ == "hostPath" {
+                (
+                    CellSpec::with_role(typ.to_string().into(), Role::Warning),
+                    CellSpec::with_role(source_str.into(), Role::Warning),
+                )
+            } else {
+                (typ.to_string().into(), source_str.into())
+            };
+            let phase_cell = match phase {
+                None => "".into(),
+                Some(None) => CellSpec::with_role("<missing>".into(), Role::BadStatus),
+                Some(Some(phase)) if phase == "Bound" => {
+                    CellSpec::with_role(phase.into(), Role::GoodStatus)
+                }
+                Some(Some(phase)) if phase == "Lost" => {
+                    CellSpec::with_role(phase.into(), Role::BadStatus)
+                }
+                Some(Some(phase)) => CellSpec::with_role(phase.into(), Role::Warning),
+            };
+            specs.push(vec![
+                name.to_string().into(),
+                type_cell,
+                source_cell,
+                mounts_str.into(),
+                phase_cell,
+                storage_class.into(),
+            ]);
+        }
+
+        if writer.print_raw(&Value::Array(raw))? {
+            return Ok(());
+        }
+        if specs.is_empty() {
+            clickwriteln!(writer, "Pod {} has no volumes", pod_obj.name());
+            return Ok(());
+        }
+        crate::table::print_table(
+            row![
+                "Name",
+                "Type",
+                "Source",
+                "Mounts",
+                "Claim Status",
+                "Storage Class"
+            ],
+            specs,
+            writer,
+        );
+        Ok(())
+    }
+);
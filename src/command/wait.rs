@@ -0,0 +1,604 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use clap::{App, Arg};
+use rustyline::completion::Pair as RustlinePair;
+use serde_json::Value;
+
+use crate::{
+    command::command_def::{exec_match, start_clap, Cmd},
+    command::{
+        kubectl_exit_error, kubectl_obj_arg, kubectl_spawn_error, kubectl_streaming_command,
+        parse_duration, valid_duration,
+    },
+    completer,
+    env::{Env, ObjectSelection},
+    error::ClickError,
+    jobs,
+    jsonpath::{format_value, JsonPath},
+    kobj::KObj,
+    output::ClickWriter,
+    theme::{self, Role},
+};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often to check for ctrl-c while nothing's happening
+const CHECK_INTERVAL: Duration = Duration::from_millis(200);
+
+/// What to wait for, from --for
+enum WaitFor {
+    Delete,
+    /// a status condition of this type to have this status (True unless given)
+    Condition {
+        typ: String,
+        status: String,
+    },
+    /// a field to have a value, or just to be set if there's no value
+    JsonPath {
+        path: JsonPath,
+        text: String,
+        value: Option<String>,
+    },
+}
+
+impl WaitFor {
+    /// Parse --for the way kubectl wait does: delete, condition=TYPE[=STATUS], or
+    /// jsonpath=PATH[=VALUE], where the path can be wrapped in {}
+    fn parse(spec: &str) -> Result<WaitFor, ClickError> {
+        if spec == "delete" {
+            return Ok(WaitFor::Delete);
+        }
+        if let Some(cond) = spec.strip_prefix("condition=") {
+            let (typ, status) = cond.split_once('=').unwrap_or((cond, "True"));
+            if typ.is_empty() {
+                return Err(ClickError::CommandError(
+                    "condition= needs a condition type, i.e. condition=Ready".to_string(),
+                ));
+            }
+            return Ok(WaitFor::Condition {
+                typ: typ.to_string(),
+                status: status.to_string(),
+            });
+        }
+        if let Some(rest) = spec.strip_prefix("jsonpath=") {
+            let (text, value) = if rest.starts_with('{') {
+                let end = rest
+                    .find('}')
+                    .ok_or_else(|| ClickError::CommandError(format!("Unmatched {{ in {}", spec)))?;
+                let value = rest[end + 1..].strip_prefix('=');
+                (&rest[..=end], value)
+            } else {
+                match rest.split_once('=') {
+                    Some((path, value)) => (path, Some(value)),
+                    None => (rest, None),
+                }
+            };
+            return Ok(WaitFor::JsonPath {
+                path: JsonPath::parse(text)?,
+                text: text.to_string(),
+                value: value.map(|v| v.to_string()),
+            });
+        }
+        Err(ClickError::CommandError(format!(
+            "Can't wait for '{}', use delete, condition=TYPE[=STATUS], or jsonpath=PATH[=VALUE]",
+            spec
+        )))
+    }
+
+    /// If obj meets the condition, and a short description of its state to show progress
+    fn check(&self, obj: &Value) -> (bool, String) {
+        match self {
+            WaitFor::Delete => {
+                let meta = obj.get("metadata");
+                if meta.and_then(|m| m.get("deletionTimestamp")).is_none() {
+                    return (false, "exists".to_string());
+                }
+                // an object being deleted only goes once its finalizers are all done
+                let finalizers: Vec<&str> = meta
+                    .and_then(|m| m.get("finalizers"))
+                    .and_then(|f| f.as_array())
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|f| f.as_str())
+                    .collect();
+                if finalizers.is_empty() {
+                    (false, "terminating".to_string())
+                } else {
+                    (
+                        false,
+                        format!(
+                            "terminating, waiting on finalizers {}",
+                            finalizers.join(", ")
+                        ),
+                    )
+                }
+            }
+            WaitFor::Condition { typ, status } => {
+                let conditions = obj.pointer("/status/conditions").and_then(|c| c.as_array());
+                // kubectl matches condition types and statuses ignoring case, so we do too
+                let found = conditions.into_iter().flatten().find(|c| {
+                    c.get("type")
+                        .and_then(|t| t.as_str())
+                        .is_some_and(|t| t.eq_ignore_ascii_case(typ))
+                });
+                match found {
+                    Some(cond) => {
+                        let current = cond.get("status").and_then(|s| s.as_str()).unwrap_or("");
+                        let mut state = format!("{}={}", typ, current);
+                        if let Some(reason) = cond.get("reason").and_then(|r| r.as_str()) {
+                            state.push_str(&format!(" ({})", reason));
+                        }
+                        (current.eq_ignore_ascii_case(status), state)
+                    }
+                    None => (false, format!("no {} condition", typ)),
+                }
+            }
+            WaitFor::JsonPath { path, text, value } => match path.find(obj) {
+                Ok(found) if !found.is_empty() => {
+                    let current: Vec<String> =
+                        found.iter().map(|v| format_value(v, true)).collect();
+                    let met = match value {
+                        Some(value) => current.iter().all(|c| c == value),
+                        None => true,
+                    };
+                    (met, format!("{}={}", text, current.join(",")))
+                }
+                _ => (false, format!("{} not set", text)),
+            },
+        }
+    }
+}
+
+/// What the thread reading a watch sends back
+enum Update {
+    Event(Value),
+    /// kubectl exited, with whatever it wrote to stderr
+    Ended(Vec<u8>),
+}
+
+/// One of the objects being waited for
+struct Watched {
+    name: String,
+    /// the kubectl command that watches it, kept to start the watch again if it ends
+    command: Command,
+    child: Option<Child>,
+    /// the latest state seen, to report transitions and where it got to on a timeout
+    state: Option<String>,
+    result: Option<Result<(), ClickError>>,
+}
+
+/// The kubectl command to watch obj with
+fn watch_command(env: &Env, obj: &KObj, background: bool) -> Result<Command, ClickError> {
+    let namespace = obj.namespace.as_deref().filter(|_| obj.is_namespaced());
+    // a watch stays open as long as it takes, so it mustn't get a request timeout
+    let mut command = kubectl_streaming_command(env, namespace)?;
+    command
+        .arg("get")
+        .arg(kubectl_obj_arg(obj))
+        .arg("--watch")
+        .arg("--output-watch-events")
+        .arg("-o")
+        .arg("json");
+    if background {
+        jobs::detach(&mut command);
+    }
+    Ok(command)
+}
+
+/// Start a kubectl watch with command, sending each watch event to sender tagged with index. The
+/// first event is the object as it is now, so nothing can be missed between looking at it and
+/// starting the watch.
+fn start_watch(
+    command: &mut Command,
+    index: usize,
+    sender: Sender<(usize, Update)>,
+) -> Result<Child, ClickError> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(kubectl_spawn_error)?;
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    thread::spawn(move || {
+        if let Some(stdout) = stdout {
+            // kubectl prints each event as a json object, one after another
+            let events = serde_json::Deserializer::from_reader(stdout).into_iter::<Value>();
+            for event in events {
+                match event {
+                    Ok(event) => {
+                        if sender.send((index, Update::Event(event))).is_err() {
+                            return;
+                        }
+                    }
+                    Err(_) => break,
+                }
+            }
+        }
+        let mut buf = vec![];
+        if let Some(mut stderr) = stderr {
+            stderr.read_to_end(&mut buf).unwrap_or(0);
+        }
+        sender.send((index, Update::Ended(buf))).unwrap_or(());
+    });
+    Ok(child)
+}
+
+fn stop_watch(watched: &mut Watched) {
+    if let Some(mut child) = watched.child.take() {
+        child.kill().unwrap_or(());
+        child.wait().map(|_| ()).unwrap_or(());
+    }
+}
+
+/// The objects being waited for, with their watches started
+struct Watches {
+    watched: Vec<Watched>,
+    sender: Sender<(usize, Update)>,
+    receiver: Receiver<(usize, Update)>,
+}
+
+impl Watches {
+    fn start(env: &Env, objs: &[KObj], background: bool) -> Result<Watches, ClickError> {
+        let (sender, receiver) = channel();
+        let mut watched = vec![];
+        for (index, obj) in objs.iter().enumerate() {
+            let mut command = watch_command(env, obj, background)?;
+            let child = start_watch(&mut command, index, sender.clone())?;
+            watched.push(Watched {
+                name: kubectl_obj_arg(obj),
+                command,
+                child: Some(child),
+                state: None,
+                result: None,
+            });
+        }
+        Ok(Watches {
+            watched,
+            sender,
+            receiver,
+        })
+    }
+
+    /// Follow the watches until every object has met for_, or failed, or until deadline. Each
+    /// change in an object's state is passed to progress. Returns true if stop was set.
+    fn follow(
+        &mut self,
+        spec: &str,
+        for_: &WaitFor,
+        deadline: Option<Instant>,
+        stop: &AtomicBool,
+        progress: &mut dyn FnMut(String),
+    ) -> Result<bool, ClickError> {
+        let start = Instant::now();
+        while self.watched.iter().any(|w| w.result.is_none()) {
+            if stop.load(Ordering::SeqCst) {
+                return Ok(true);
+            }
+            let wait = match deadline {
+                Some(deadline) => deadline.saturating_duration_since(Instant::now()),
+                None => CHECK_INTERVAL,
+            };
+            if wait.is_zero() {
+                break;
+            }
+            let (index, update) = match self.receiver.recv_timeout(wait.min(CHECK_INTERVAL)) {
+                Ok(received) => received,
+                Err(RecvTimeoutError::Timeout) => continue,
+                // can't happen while we hold a sender
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            let watch = &mut self.watched[index];
+            if watch.result.is_some() {
+                continue;
+            }
+            let elapsed = start.elapsed().as_secs_f64();
+            match update {
+                Update::Event(event) => {
+                    let typ = event.get("type").and_then(|t| t.as_str()).unwrap_or("");
+                    let (met, state) = match (typ, event.get("object")) {
+                        ("DELETED", _) => (matches!(for_, WaitFor::Delete), "deleted".to_string()),
+                        ("ERROR", obj) => {
+                            let message = obj
+                                .and_then(|o| o.get("message"))
+                                .and_then(|m| m.as_str())
+                                .unwrap_or("unknown error");
+                            watch.result = Some(Err(ClickError::CommandError(format!(
+                                "Watch failed: {}",
+                                message
+                            ))));
+                            stop_watch(watch);
+                            continue;
+                        }
+                        (_, Some(obj)) => for_.check(obj),
+                        (_, None) => continue,
+                    };
+                    if watch.state.as_ref() != Some(&state) {
+                        progress(format!("{}: {} ({:.1}s)", watch.name, state, elapsed));
+                        watch.state = Some(state.clone());
+                    }
+                    if met {
+                        progress(format!("{}: {} after {:.1}s", watch.name, spec, elapsed));
+                        watch.result = Some(Ok(()));
+                        stop_watch(watch);
+                    } else if state == "deleted" {
+                        watch.result = Some(Err(ClickError::CommandError(format!(
+                            "{} was deleted before {}",
+                            watch.name, spec
+                        ))));
+                        stop_watch(watch);
+                    }
+                }
+                Update::Ended(stderr) => {
+                    // kubectl gets the ctrl-c too, which is handled at the top of the loop
+                    if stop.load(Ordering::SeqCst) {
+                        continue;
+                    }
+                    let status = match watch.child.take() {
+                        Some(mut child) => child.wait()?,
+                        None => continue,
+                    };
+                    let stderr_str = String::from_utf8_lossy(&stderr);
+                    if matches!(for_, WaitFor::Delete) && stderr_str.contains("NotFound") {
+                        progress(format!("{}: already deleted", watch.name));
+                        watch.result = Some(Ok(()));
+                    } else if status.success() {
+                        // the server closed the watch, which it does after a while, so start
+                        // again
+                        watch.child =
+                            Some(start_watch(&mut watch.command, index, self.sender.clone())?);
+                    } else {
+                        watch.result = Some(Err(kubectl_exit_error(status, &stderr)));
+                    }
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    fn stop(&mut self) {
+        for watch in self.watched.iter_mut() {
+            stop_watch(watch);
+        }
+    }
+
+    /// Ok if every object met for_, otherwise an error for each one that didn't. The ones still
+    /// waiting timed out after timeout.
+    fn outcome(self, spec: &str, timeout: Option<Duration>) -> Result<(), ClickError> {
+        let count = self.watched.len();
+        let mut failures: Vec<(String, ClickError)> = self
+            .watched
+            .into_iter()
+            .filter_map(
+                |Watched {
+                     name,
+                     state,
+                     result,
+                     ..
+                 }| {
+                    let result = result.unwrap_or_else(|| {
+                        Err(ClickError::CommandError(format!(
+                            "Timed out after {:.0}s waiting for {}, last state: {}",
+                            timeout.unwrap_or_default().as_secs_f64(),
+                            spec,
+                            state.as_deref().unwrap_or("unknown")
+                        )))
+                    });
+                    result.err().map(|e| (name, e))
+                },
+            )
+            .collect();
+        match failures.len() {
+            0 => Ok(()),
+            1 if count == 1 => Err(failures.remove(0).1),
+            _ => Err(ClickError::MultiError(failures)),
+        }
+    }
+}
+
+/// Wait for objs to meet for_, all at once, until timeout. Returns Ok if they all did, or if the
+/// user stopped waiting with ctrl-c, otherwise an error for each one that didn't.
+fn wait_for(
+    env: &Env,
+    objs: &[KObj],
+    spec: &str,
+    for_: &WaitFor,
+    timeout: Duration,
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    let mut watches = Watches::start(env, objs, false)?;
+    let followed = watches.follow(
+        spec,
+        for_,
+        Some(Instant::now() + timeout),
+        &env.ctrlcbool,
+        &mut |line| clickwriteln!(writer, "{}", line),
+    );
+    watches.stop();
+    if followed? {
+        clickwriteln!(writer, "Stopped waiting");
+        return Ok(());
+    }
+    watches.outcome(spec, Some(timeout))
+}
+
+/// Watch objs in a background job until they meet for_, then ring the bell and say so. There's
+/// no deadline unless a timeout is given.
+fn notify_when(
+    env: &mut Env,
+    objs: &[KObj],
+    spec: &str,
+    for_: WaitFor,
+    timeout: Option<Duration>,
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    let mut watches = Watches::start(env, objs, true)?;
+    let names: Vec<&str> = watches.watched.iter().map(|w| w.name.as_str()).collect();
+    let description = format!("wait --for {} ({})", spec, names.join(", "));
+    let color = env.use_color(false);
+    let spec = spec.to_string();
+    let job_description = description.clone();
+    let id = env.jobs_mut().start(&description, move |id, stop| {
+        let start = Instant::now();
+        let followed = watches.follow(&spec, &for_, timeout.map(|t| start + t), stop, &mut |_| {});
+        watches.stop();
+        if followed? {
+            return Ok("stopped".to_string());
+        }
+        let count = watches.watched.len();
+        let outcome = watches.outcome(&spec, timeout);
+        let (role, message) = match outcome.as_ref() {
+            Ok(()) if count == 1 => (
+                Role::GoodStatus,
+                format!("{} after {:.1}s", spec, start.elapsed().as_secs_f64()),
+            ),
+            Ok(()) => (
+                Role::GoodStatus,
+                format!(
+                    "all {} objects {} after {:.1}s",
+                    count,
+                    spec,
+                    start.elapsed().as_secs_f64()
+                ),
+            ),
+            Err(e) => (Role::BadStatus, e.to_string()),
+        };
+        let line = format!("[job {}] {}: {}", id, job_description, message);
+        // on its own line, since the prompt is probably showing
+        if color {
+            println!("\u{7}\n{}", theme::style(role).paint(line));
+        } else {
+            println!("\u{7}\n{}", line);
+        }
+        outcome.map(|()| message)
+    });
+    clickwriteln!(
+        writer,
+        "Watching in the background as job {}. 'bgjobs' lists jobs, and 'kill {}' stops it.",
+        id,
+        id
+    );
+    Ok(())
+}
+
+/// Wait for objs to be gone, after deleting them. The error for any that aren't by the timeout
+/// says what they're stuck on.
+pub fn wait_for_deletion(
+    env: &Env,
+    objs: &[KObj],
+    timeout: Duration,
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    env.ctrlcbool.store(false, Ordering::SeqCst);
+    wait_for(env, objs, "delete", &WaitFor::Delete, timeout, writer)
+}
+
+command!(
+    Wait,
+    "wait",
+    "Wait for a condition on the active object(s), watching them until they meet it",
+    |clap: App<'static, 'static>| {
+        clap.arg(
+            Arg::with_name("for")
+                .long("for")
+                .value_name("CONDITION")
+                .help(
+                    "What to wait for: condition=TYPE[=STATUS] (i.e. condition=Ready), \
+                     jsonpath=PATH[=VALUE] (i.e. jsonpath=.status.phase=Running), or delete",
+                )
+                .required(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("timeout")
+                .long("timeout")
+                .help("How long to wait before giving up, i.e. 30s, 10m")
+                .takes_value(true)
+                .validator(valid_duration)
+                .default_value("60s"),
+        )
+        .arg(
+            Arg::with_name("notify")
+                .long("notify")
+                .short("n")
+                .help(
+                    "Watch in the background, ringing the bell and printing a line once the \
+                     condition is met, so you can keep using click meanwhile",
+                ),
+        )
+        .after_help(
+            "Each object is watched with kubectl get --watch, and changes to its state are printed \
+as they happen. A condition's status defaults to True, and a jsonpath without a value waits for \
+the field to be set. For a range, all the objects are waited for at once, and the ones that \
+didn't get there in time are reported with the last state they were in. Hit ctrl-c to stop \
+waiting early.
+
+With --notify the watch runs as a background job, and there's no time limit unless --timeout is \
+given. 'bgjobs' lists background jobs, and 'kill N' stops job N. Jobs are stopped when click exits.
+
+Examples:
+  # wait for the active pod to be ready
+  wait --for condition=Ready
+
+  # wait up to 10 minutes for the active job to finish
+  wait --for condition=Complete --timeout 10m
+
+  # wait for the selected pods to be running
+  wait --for jsonpath=.status.phase=Running
+
+  # wait for the active pod to go away
+  wait --for delete
+
+  # say when the active deployment is available, without waiting around for it
+  wait --for condition=Available --notify",
+        )
+    },
+    vec!["wait"],
+    noop_complete!(),
+    std::iter::once((
+        "for".to_string(),
+        completer::waitfor_values_completer as fn(&str, &Env) -> Vec<RustlinePair>
+    ))
+    .collect(),
+    |matches, env, writer| {
+        let spec = matches.value_of("for").unwrap(); // safe, required
+        let for_ = WaitFor::parse(spec)?;
+        let timeout = parse_duration(matches.value_of("timeout").unwrap()).unwrap(); // safe, validated with a default
+        let objs = match env.current_selection() {
+            ObjectSelection::Single(obj) => vec![obj.clone()],
+            ObjectSelection::Range(range) => range.clone(),
+            ObjectSelection::None => {
+                return Err(ClickError::CommandError(
+                    "No active object selected".to_string(),
+                ));
+            }
+        };
+        if matches.is_present("notify") {
+            let timeout = Some(timeout).filter(|_| matches.occurrences_of("timeout") > 0);
+            return notify_when(env, &objs, spec, for_, timeout, writer);
+        }
+        writer.finish_paging();
+        env.ctrlcbool.store(false, Ordering::SeqCst);
+        wait_for(env, &objs, spec, &for_, timeout, writer)
+    }
+);
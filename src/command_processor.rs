@@ -13,24 +13,72 @@
 // limitations under the License.
 
 use crate::command::command_def::Cmd;
+use crate::command::shell_quote;
 use crate::completer::ClickHelper;
 use crate::error::ClickError;
 use crate::kobj::KObj;
 use crate::output::ClickWriter;
-use crate::parser::{try_parse_csl, try_parse_range, Parser};
+use crate::parser::{
+    background_command, split_commands, try_parse_csl, try_parse_range, ChainOp, Parser,
+};
+use crate::theme::{self, Role};
 use crate::values::val_str;
 
 use rustyline::config as rustyconfig;
 use rustyline::error::ReadlineError;
+use rustyline::history::History;
 use rustyline::Editor;
 
-use crate::env::Env;
+use crate::env::{expand_home, Env, ExpandedAlias, ObjectSelection};
 
-use std::fs::{File, OpenOptions};
-use std::io::Write;
+use mlua::{Lua, Value, Variadic};
+
+use std::cell::RefCell;
+use std::fs;
+use std::io::{self, Write};
 use std::ops::Range;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+
+/// What running commands with -c or from stdin exits with when the only thing that failed was diff
+/// finding objects that differ from what's live, so scripts can tell drift from errors
+pub const DRIFT_EXIT_CODE: i32 = 2;
+
+/// Fill in the {name}, {namespace}, {kind}, and {uid} of obj in template. Missing values are
+/// empty. Any other braces, like json passed to patch, are left as they are.
+fn substitute_obj(template: &str, obj: &KObj) -> String {
+    template
+        .replace("{name}", obj.name())
+        .replace("{namespace}", obj.namespace.as_deref().unwrap_or(""))
+        .replace("{kind}", obj.type_str())
+        .replace("{uid}", obj.uid.as_deref().unwrap_or(""))
+}
+
+/// If line starts with a history reference, `!N` for entry N (as numbered by the history
+/// command) or `!!` for the last one, return the line with the reference replaced by the entry.
+/// Returns None if there's no reference.
+fn expand_history_ref(line: &str, history: &History) -> Result<Option<String>, ClickError> {
+    let (reference, rest) = match line.find(char::is_whitespace) {
+        Some(i) => line.split_at(i),
+        None => (line, ""),
+    };
+    let num = match reference.strip_prefix('!') {
+        Some("!") => history.len(),
+        Some(num) if !num.is_empty() && num.chars().all(|c| c.is_ascii_digit()) => {
+            num.parse().unwrap_or(0)
+        }
+        _ => return Ok(None),
+    };
+    match num.checked_sub(1).and_then(|i| history.get(i)) {
+        Some(entry) => Ok(Some(format!("{}{}", entry, rest))),
+        None => Err(ClickError::CommandError(format!(
+            "{}: no such history entry",
+            reference
+        ))),
+    }
+}
 
 /// Things the can come after a | or > char in input
 #[derive(Debug, PartialEq)]
@@ -94,7 +142,22 @@ pub fn alias_expand_line(env: &Env, line: &str) -> String {
         let expa = match alias_stack.last().unwrap().expansion {
             Some(prev) => {
                 // previous thing expanded an alias, so try and expand that too
-                env.try_expand_alias(prev.expanded.as_str(), Some(prev.alias.as_str()))
+                let expa = env.try_expand_alias(prev.expanded.as_str(), Some(prev.alias.as_str()));
+                match expa.expansion {
+                    // the alias command won't create loops, but a config file could have them. stop
+                    // rather than expanding forever.
+                    Some(next)
+                        if alias_stack
+                            .iter()
+                            .any(|ea| ea.expansion.is_some_and(|a| a.alias == next.alias)) =>
+                    {
+                        ExpandedAlias {
+                            expansion: None,
+                            rest: prev.expanded.as_str(),
+                        }
+                    }
+                    _ => expa,
+                }
             }
             None => break,
         };
@@ -106,11 +169,45 @@ pub fn alias_expand_line(env: &Env, line: &str) -> String {
     rests.concat()
 }
 
-fn parse_line(line: &str) -> Result<(&str, RightExpr), ClickError> {
+/// Is name one of click's commands (or another name for one, like "po" for pods)
+pub fn is_builtin_command(name: &str) -> bool {
+    name == "help"
+        || name == "source"
+        || name == "foreach"
+        || name == "history"
+        || name == "bg"
+        || name == "script"
+        || CommandProcessor::get_command_vec()
+            .iter()
+            .any(|cmd| cmd.is(name))
+}
+
+/// Run line for a script, returning the table its command functions return, and update click.env
+fn script_run(
+    lua: &Lua,
+    processor: &RefCell<&mut CommandProcessor>,
+    line: &str,
+) -> mlua::Result<mlua::Table> {
+    let mut processor = processor
+        .try_borrow_mut()
+        .map_err(|_| mlua::Error::RecursiveMutCallback)?;
+    let output = processor
+        .process_line(line, ClickWriter::with_buffer(vec![], false))
+        .unwrap_or_default();
+    let result = lua.create_table()?;
+    result.set("ok", !processor.last_failed)?;
+    result.set("output", String::from_utf8_lossy(&output))?;
+    let click: mlua::Table = lua.globals().get("click")?;
+    click.set("env", crate::script::env_table(lua, &processor.env)?)?;
+    Ok(result)
+}
+
+fn parse_line(line: &str) -> Result<(&str, RightExpr<'_>), ClickError> {
     let parser = Parser::new(line);
     for (range, sep, _) in parser {
         match sep {
-            '|' | '>' => return build_parser_expr(line, range),
+            // the range is of the argument before the separator, which ends at the separator
+            '|' | '>' => return build_parser_expr(line, range.end..line.len()),
             _ => {}
         }
     }
@@ -129,6 +226,11 @@ fn get_editor(config: rustyconfig::Config, hist_path: &Path) -> Editor<ClickHelp
             "pipes",
             "redirection",
             "ranges",
+            "source",
+            "foreach",
+            "history",
+            "bg",
+            "script",
         ],
     )));
     rl.load_history(hist_path).unwrap_or_default();
@@ -137,21 +239,35 @@ fn get_editor(config: rustyconfig::Config, hist_path: &Path) -> Editor<ClickHelp
 
 pub struct CommandProcessor {
     env: Rc<Env>,
-    rl: Editor<ClickHelper>,
+    /// None when running commands without the repl
+    rl: Option<Editor<ClickHelper>>,
     hist_path: PathBuf,
     commands: Vec<Box<dyn Cmd>>,
+    /// files being run by source or script, innermost last. Used to stop a file sourcing itself.
+    sourcing: Vec<PathBuf>,
+    /// did the last line processed fail
+    last_failed: bool,
+    /// did the last line fail only because diff found objects that differ from what's live
+    last_drift: bool,
+    /// lines added to the history by this session, which are merged into the history file when
+    /// it's saved
+    session_history: Vec<String>,
 }
 
 impl CommandProcessor {
     pub fn new(env: Env, hist_path: PathBuf) -> CommandProcessor {
         let commands = CommandProcessor::get_command_vec();
         let env = Rc::new(env);
-        let rl = get_editor(env.get_rustyline_conf(), &hist_path);
+        let rl = Some(get_editor(env.get_rustyline_conf(), &hist_path));
         CommandProcessor {
             env,
             rl,
             hist_path,
             commands,
+            sourcing: vec![],
+            last_failed: false,
+            last_drift: false,
+            session_history: vec![],
         }
     }
 
@@ -162,15 +278,38 @@ impl CommandProcessor {
         commands: Vec<Box<dyn Cmd>>,
     ) -> CommandProcessor {
         let env = Rc::new(env);
-        let rl = get_editor(env.get_rustyline_conf(), &hist_path);
+        let rl = Some(get_editor(env.get_rustyline_conf(), &hist_path));
         CommandProcessor {
             env,
             rl,
             hist_path,
             commands,
+            sourcing: vec![],
+            last_failed: false,
+            last_drift: false,
+            session_history: vec![],
+        }
+    }
+
+    /// A processor for running commands without the repl (see run_commands). There's no line
+    /// editor, so no history is loaded or saved.
+    pub fn new_batch(env: Env) -> CommandProcessor {
+        CommandProcessor {
+            env: Rc::new(env),
+            rl: None,
+            hist_path: PathBuf::new(),
+            commands: CommandProcessor::get_command_vec(),
+            sourcing: vec![],
+            last_failed: false,
+            last_drift: false,
+            session_history: vec![],
         }
     }
 
+    pub fn env_mut(&mut self) -> &mut Env {
+        Rc::get_mut(&mut self.env).unwrap()
+    }
+
     fn get_command_vec() -> Vec<Box<dyn Cmd>> {
         let commands: Vec<Box<dyn Cmd>> = vec![
             Box::new(crate::command::alias::Alias::new()),
@@ -179,32 +318,85 @@ impl CommandProcessor {
             Box::new(crate::command::click::Context::new()),
             Box::new(crate::command::click::Contexts::new()),
             Box::new(crate::command::click::EnvCmd::new()),
+            Box::new(crate::command::click::Kubeconfig::new()),
+            Box::new(crate::command::click::MergeKubeconfig::new()),
+            Box::new(crate::command::click::Filter::new()),
             Box::new(crate::command::click::Quit::new()),
             Box::new(crate::command::click::Range::new()),
+            Box::new(crate::command::click::Load::new()),
+            Box::new(crate::command::click::Reload::new()),
+            Box::new(crate::command::click::Save::new()),
+            Box::new(crate::command::click::Select::new()),
             Box::new(crate::command::click::SetCmd::new()),
+            Box::new(crate::command::click::UnsetCmd::new()),
             Box::new(crate::command::click::UtcCmd::new()),
+            Box::new(crate::command::clusterroles::ClusterRoles::new()),
+            Box::new(crate::command::clusterrolebindings::ClusterRoleBindings::new()),
             Box::new(crate::command::configmaps::ConfigMaps::new()),
             Box::new(crate::command::crds::Crd::new()),
+            Box::new(crate::command::cronjobs::CronJobs::new()),
+            Box::new(crate::command::daemonsets::DaemonSets::new()),
+            Box::new(crate::command::debug::Debug::new()),
             Box::new(crate::command::delete::Delete::new()),
             Box::new(crate::command::deployments::Deployments::new()),
             Box::new(crate::command::describe::Describe::new()),
+            Box::new(crate::command::edit::Edit::new()),
+            Box::new(crate::command::label::Label::new()),
+            Box::new(crate::command::label::Annotate::new()),
+            Box::new(crate::command::patch::Patch::new()),
+            Box::new(crate::command::apply::Apply::new()),
+            Box::new(crate::command::diff::Diff::new()),
+            Box::new(crate::command::background::BgJobs::new()),
+            Box::new(crate::command::background::Fg::new()),
+            Box::new(crate::command::background::Kill::new()),
             Box::new(crate::command::events::Events::new()),
             Box::new(crate::command::exec::Exec::new()),
+            Box::new(crate::command::cp::Cp::new()),
+            Box::new(crate::command::helm::Helm::new()),
+            Box::new(crate::command::hpa::Hpas::new()),
+            Box::new(crate::command::istio::Istio::new()),
             Box::new(crate::command::jobs::Jobs::new()),
+            Box::new(crate::command::kustomize::Kustomize::new()),
             Box::new(crate::command::logs::Logs::new()),
+            Box::new(crate::command::logs::Multitail::new()),
             Box::new(crate::command::namespaces::Namespace::new()),
             Box::new(crate::command::namespaces::Namespaces::new()),
+            Box::new(crate::command::navigate::Node::new()),
+            Box::new(crate::command::navigate::Owner::new()),
+            Box::new(crate::command::networkpolicies::NetworkPolicies::new()),
             Box::new(crate::command::nodes::Nodes::new()),
+            Box::new(crate::command::nodes::Cordon::new()),
+            Box::new(crate::command::nodes::Uncordon::new()),
+            Box::new(crate::command::nodes::Drain::new()),
+            Box::new(crate::command::nsenter::Nsenter::new()),
             Box::new(crate::command::pods::Containers::new()),
             Box::new(crate::command::pods::Pods::new()),
             Box::new(crate::command::portforwards::PortForward::new()),
             Box::new(crate::command::portforwards::PortForwards::new()),
+            Box::new(crate::command::printenv::PrintEnv::new()),
+            Box::new(crate::command::proxy::ProxyCmd::new()),
+            Box::new(crate::command::rbac::CanI::new()),
+            Box::new(crate::command::rbac::Rbac::new()),
             Box::new(crate::command::replicasets::ReplicaSets::new()),
+            Box::new(crate::command::rolebindings::RoleBindings::new()),
+            Box::new(crate::command::roles::Roles::new()),
+            Box::new(crate::command::rollout::Rollout::new()),
+            Box::new(crate::command::scale::Scale::new()),
+            Box::new(crate::command::seal::Seal::new()),
             Box::new(crate::command::secrets::Secrets::new()),
+            Box::new(crate::command::serviceaccounts::ServiceAccounts::new()),
             Box::new(crate::command::services::Services::new()),
+            Box::new(crate::command::ssh::Ssh::new()),
             Box::new(crate::command::statefulsets::StatefulSets::new()),
             Box::new(crate::command::storage::StorageClasses::new()),
+            Box::new(crate::command::token::Token::new()),
+            Box::new(crate::command::top::Top::new()),
+            Box::new(crate::command::tree::Tree::new()),
+            Box::new(crate::command::velero::Velero::new()),
+            Box::new(crate::command::version::Version::new()),
             Box::new(crate::command::volumes::PersistentVolumes::new()),
+            Box::new(crate::command::volumes::PodVolumes::new()),
+            Box::new(crate::command::wait::Wait::new()),
             #[cfg(feature = "argorollouts")]
             Box::new(crate::command::rollouts::Rollouts::new()),
         ];
@@ -214,28 +406,36 @@ impl CommandProcessor {
     pub fn run_repl(&mut self) {
         while !self.env.quit {
             let mut writer = ClickWriter::new();
-            if self.env.need_new_editor {
-                self.rl = get_editor(self.env.get_rustyline_conf(), &self.hist_path);
+            if self.rl.is_none() || self.env.need_new_editor {
+                // the new editor loads the history from the file, so get this session's into it
+                self.save_history();
+                self.rl = Some(get_editor(self.env.get_rustyline_conf(), &self.hist_path));
                 Rc::get_mut(&mut self.env).unwrap().need_new_editor = false;
             }
+            let rl = self.rl.as_mut().unwrap(); // safe, set above
 
             // we set and unset the pointer to the env in the helper here so the get_mut below works
             let helper_env = Some(self.env.clone());
-            if let Some(h) = self.rl.helper_mut() {
+            if let Some(h) = rl.helper_mut() {
                 h.set_env(helper_env)
             }
-            let readline = self.rl.readline(self.env.prompt.as_str());
-            if let Some(h) = self.rl.helper_mut() {
+            let readline = rl.readline(self.env.prompt.as_str());
+            if let Some(h) = rl.helper_mut() {
                 h.set_env(None)
             }
             match readline {
                 Ok(line) => {
                     self.process_line(line.as_str(), writer);
+                    if self.env.quit && !self.confirm_exit() {
+                        Rc::get_mut(&mut self.env).unwrap().quit = false;
+                    }
                 }
                 Err(ReadlineError::Interrupted) => {} // don't exit on Ctrl-C
                 Err(ReadlineError::Eof) => {
                     // Ctrl-D
-                    break;
+                    if self.confirm_exit() {
+                        break;
+                    }
                 }
                 Err(e) => {
                     clickwriteln!(writer, "Error reading input: {}", e);
@@ -245,14 +445,241 @@ impl CommandProcessor {
         }
         let env = Rc::get_mut(&mut self.env).unwrap();
         env.save_click_config();
-        if let Err(e) = self.rl.save_history(self.hist_path.as_path()) {
+        env.stop_all_forwards();
+        env.stop_all_jobs();
+        self.save_history();
+    }
+
+    /// Check with the user before exiting while background jobs are running, as exiting stops
+    /// them. Returns true if it's ok to exit.
+    fn confirm_exit(&self) -> bool {
+        let running = self.env.jobs().running_count();
+        running == 0
+            || crate::command::confirm_action(
+                &mut ClickWriter::new(),
+                &format!(
+                    "{} background job{} still running, exit and stop {}",
+                    running,
+                    if running == 1 { " is" } else { "s are" },
+                    if running == 1 { "it" } else { "them" }
+                ),
+            )
+    }
+
+    /// Add line to the history, unless the history config says to skip it
+    fn add_history(&mut self, line: &str) {
+        if let Some(rl) = self.rl.as_mut() {
+            if rl.add_history_entry(line) {
+                self.session_history.push(line.to_string());
+            }
+        }
+    }
+
+    /// Save the lines added to the history in this session to the history file. They're added to
+    /// what's in the file now, rather than what was there when we started, so sessions running at
+    /// the same time don't lose each other's history. The file is written to a temporary file
+    /// that's then renamed over it, so it's never left half written.
+    fn save_history(&mut self) {
+        if self.rl.is_none() || self.session_history.is_empty() {
+            return;
+        }
+        let mut history = History::with_config(self.env.get_rustyline_conf());
+        match history.load(&self.hist_path) {
+            Ok(()) => {}
+            Err(ReadlineError::Io(ref e)) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => {
+                // don't replace a history file we couldn't read
+                println!("Couldn't save command history: {}", e);
+                return;
+            }
+        }
+        for line in self.session_history.drain(..) {
+            history.add(line);
+        }
+        let mut tmp_name = self
+            .hist_path
+            .file_name()
+            .unwrap_or_default()
+            .to_os_string();
+        tmp_name.push(format!(".tmp.{}", std::process::id()));
+        let tmp_path = self.hist_path.with_file_name(tmp_name);
+        let res = history
+            .save(&tmp_path)
+            .map_err(|e| e.to_string())
+            .and_then(|_| fs::rename(&tmp_path, &self.hist_path).map_err(|e| e.to_string()));
+        if let Err(e) = res {
+            fs::remove_file(&tmp_path).unwrap_or(());
             println!("Couldn't save command history: {}", e);
         }
-        env.stop_all_forwards();
+    }
+
+    /// Run commands without the repl, as for -c or commands piped to stdin. Each line can hold
+    /// several commands, chained with ';' or '&&'. Blank lines and lines starting with '#' are skipped. A
+    /// failing command doesn't stop the rest from running. Returns the code to exit with: 0 if
+    /// everything worked, DRIFT_EXIT_CODE if the only failures were diffs finding differences, and
+    /// 1 if anything else failed.
+    pub fn run_commands<I>(&mut self, lines: I) -> i32
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut code = 0;
+        for line in lines.into_iter() {
+            let line = line.trim();
+            if line.starts_with('#') {
+                continue;
+            }
+            self.process_line(line, ClickWriter::new());
+            if self.last_failed {
+                code = if self.last_drift && code != 1 {
+                    DRIFT_EXIT_CODE
+                } else {
+                    1
+                };
+            }
+            if self.env.quit {
+                break;
+            }
+        }
+        self.env_mut().stop_all_forwards();
+        self.env_mut().stop_all_jobs();
+        code
+    }
+
+    /// Run the rc file of commands, if there is one. A missing file is only reported if the
+    /// rc_file option was set.
+    pub fn run_rc_file(&mut self) {
+        if let Some((path, explicit)) = self.env.rc_file() {
+            if explicit || path.exists() {
+                if let Err(e) = self.source_file(&path) {
+                    println!("{}", e);
+                }
+            }
+        }
+    }
+
+    /// Run each line of the file at path as a click command. Blank lines and lines starting with
+    /// '#' are skipped. A failing line is reported with its line number, and the rest of the file
+    /// is still run.
+    pub fn source_file(&mut self, path: &Path) -> Result<(), ClickError> {
+        if self.sourcing.iter().any(|p| p == path) {
+            return Err(ClickError::CommandError(format!(
+                "{} is already being sourced, not sourcing it again",
+                path.display()
+            )));
+        }
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            ClickError::CommandError(format!("Could not read {}: {}", path.display(), e))
+        })?;
+        self.sourcing.push(path.to_path_buf());
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            self.process_line(line, ClickWriter::new());
+            if self.last_failed {
+                println!("(at line {} of {}: {})", i + 1, path.display(), line);
+            }
+            if self.env.quit {
+                break;
+            }
+        }
+        self.sourcing.pop();
+        self.last_failed = false;
+        Ok(())
+    }
+
+    /// Run the Lua script at path, with args as its arg table. See SCRIPTHELP.
+    pub fn run_script(
+        &mut self,
+        path: &Path,
+        args: &[&str],
+        writer: &mut ClickWriter,
+    ) -> Result<(), ClickError> {
+        if self.sourcing.iter().any(|p| p == path) {
+            return Err(ClickError::CommandError(format!(
+                "{} is already running, not running it again",
+                path.display()
+            )));
+        }
+        let code = std::fs::read_to_string(path).map_err(|e| {
+            ClickError::CommandError(format!("Could not read {}: {}", path.display(), e))
+        })?;
+        self.sourcing.push(path.to_path_buf());
+        let res = self.exec_script(path, &code, args, writer);
+        self.sourcing.pop();
+        // the script sees if its commands fail, and decides what to do about it
+        self.last_failed = false;
+        res.map_err(|e| ClickError::CommandError(format!("Lua error: {}", e)))
+    }
+
+    fn exec_script(
+        &mut self,
+        path: &Path,
+        code: &str,
+        args: &[&str],
+        writer: &mut ClickWriter,
+    ) -> mlua::Result<()> {
+        let lua = Lua::new();
+        let names: Vec<&'static str> = self.commands.iter().map(|c| c.get_name()).collect();
+        let click = lua.create_table()?;
+        click.set("env", crate::script::env_table(&lua, &self.env)?)?;
+        let script_args = lua.create_table()?;
+        script_args.set(0, path.to_string_lossy())?;
+        for (i, arg) in args.iter().enumerate() {
+            script_args.set(i + 1, *arg)?;
+        }
+        lua.globals().set("arg", script_args)?;
+
+        let processor = &RefCell::new(self);
+        let writer = &RefCell::new(writer);
+        lua.scope(|scope| {
+            for name in names.into_iter() {
+                let func = scope.create_function(move |lua, args: Variadic<Value>| {
+                    let words = crate::script::command_words(args)?;
+                    script_run(lua, processor, &crate::script::script_line(name, &words))
+                })?;
+                click.set(crate::script::lua_name(name), func)?;
+            }
+            // click.get{"pods", ...} is the same as click.pods{...}
+            let get = scope.create_function(move |lua, args: Variadic<Value>| {
+                let words = crate::script::command_words(args)?;
+                match words.split_first() {
+                    Some((command, rest)) => {
+                        script_run(lua, processor, &crate::script::script_line(command, rest))
+                    }
+                    None => Err(mlua::Error::RuntimeError(
+                        "get needs something to get, i.e. get{\"pods\"}".to_string(),
+                    )),
+                }
+            })?;
+            click.set("get", get)?;
+            let run = scope
+                .create_function(move |lua, line: String| script_run(lua, processor, &line))?;
+            click.set("run", run)?;
+            lua.globals().set("click", click)?;
+
+            // print goes where the script command's output does, i.e. a file it's redirected to
+            let print = scope.create_function(move |_, values: Variadic<Value>| {
+                let mut strs = vec![];
+                for value in values.iter() {
+                    strs.push(value.to_string()?);
+                }
+                let mut writer = writer.borrow_mut();
+                clickwriteln!(writer, "{}", strs.join("\t"));
+                Ok(())
+            })?;
+            lua.globals().set("print", print)?;
+
+            lua.load(code)
+                .set_name(format!("@{}", path.display()))
+                .exec()
+        })
     }
 
     /// Process the line.  Returns the result of finish_output on the writer
     pub fn process_line(&mut self, line: &str, mut writer: ClickWriter) -> Option<Vec<u8>> {
+        self.last_failed = false;
         if line.is_empty() {
             return writer.finish_output();
         }
@@ -263,100 +690,165 @@ impl CommandProcessor {
             }
             first_non_whitespace += 1;
         }
-        let lstr = if first_non_whitespace == 0 {
-            // bash semantics: don't add to history if start with space
-            self.rl.add_history_entry(line);
-            line
-        } else {
-            &line[first_non_whitespace..]
+        let lstr = &line[first_non_whitespace..];
+        let expanded = match self.rl.as_ref() {
+            Some(rl) => match expand_history_ref(lstr, rl.history()) {
+                Ok(expanded) => expanded,
+                Err(e) => {
+                    println!("{}", e);
+                    self.last_failed = true;
+                    return writer.finish_output();
+                }
+            },
+            None => None,
         };
-        let expanded_line = alias_expand_line(&self.env, lstr);
+        let lstr = match expanded.as_ref() {
+            Some(expanded) => {
+                // like bash, show what's being run
+                println!("{}", expanded);
+                expanded.as_str()
+            }
+            None => lstr,
+        };
+        // bash semantics: don't add to history if start with space (unless history_ignore_space
+        // is off). sourced lines aren't added either.
+        if self.sourcing.is_empty()
+            && (first_non_whitespace == 0 || !self.env.click_config.history_ignore_space)
+        {
+            self.add_history(lstr);
+        }
+        if let Some(background) = background_command(lstr) {
+            self.start_background(background, &mut writer);
+            return writer.finish_output();
+        }
+        let commands = split_commands(lstr);
+        match commands.len() {
+            0 => writer.finish_output(),
+            1 => self.process_command(commands[0].1, writer),
+            _ => self.process_chain(&commands, writer),
+        }
+    }
+
+    /// Run line in the background, in another click that starts where this session is. Its output
+    /// is kept for 'fg'.
+    fn start_background(&mut self, line: &str, writer: &mut ClickWriter) {
+        if line.is_empty() {
+            clickwriteln!(writer, "Nothing to run in the background");
+            self.last_failed = true;
+            return;
+        }
+        let env = Rc::get_mut(&mut self.env).unwrap();
+        let max_lines = env.click_config.job_output_lines as usize;
+        let started = crate::jobs::click_command(env, line, env.jobs().next_id())
+            .and_then(|command| env.jobs_mut().start_command(line, command, max_lines));
+        match started {
+            Ok(id) => clickwriteln!(
+                writer,
+                "[job {}] {} (see its output with 'fg {}')",
+                id,
+                line,
+                id
+            ),
+            Err(e) => self.report_error(e, writer),
+        }
+    }
+
+    /// Run the commands chained together on one line. A command after '&&' is skipped if the one
+    /// before it failed (or was skipped), like in a shell.
+    fn process_chain(
+        &mut self,
+        commands: &[(ChainOp, &str)],
+        mut writer: ClickWriter,
+    ) -> Option<Vec<u8>> {
+        let mut any_failed = false;
+        let mut all_drift = true;
+        let mut prev_ok = true;
+        for (i, (op, command)) in commands.iter().enumerate() {
+            if *op == ChainOp::IfOk && !prev_ok {
+                continue;
+            }
+            if let Some(out) = self.process_command(command, writer.sibling()) {
+                writer.write_all(&out).unwrap_or(());
+            }
+            prev_ok = !self.last_failed;
+            if self.last_failed {
+                any_failed = true;
+                all_drift &= self.last_drift;
+                clickwriteln!(
+                    writer,
+                    "(command {} of {} failed: {})",
+                    i + 1,
+                    commands.len(),
+                    command
+                );
+            }
+            if self.env.quit {
+                break;
+            }
+        }
+        self.last_failed = any_failed;
+        self.last_drift = any_failed && all_drift;
+        writer.finish_output()
+    }
+
+    /// Process a single command, with any pipe or redirect and aliases, but not chained to others
+    fn process_command(&mut self, command: &str, mut writer: ClickWriter) -> Option<Vec<u8>> {
+        self.last_failed = false;
+        self.last_drift = false;
+        let expanded_line = alias_expand_line(&self.env, command);
         match parse_line(&expanded_line) {
             Ok((left, right)) => {
                 // set up output
-                match right {
-                    RightExpr::None => {} // do nothing
-                    RightExpr::Pipe(cmd) => {
-                        if let Err(e) = writer.setup_pipe(cmd) {
-                            println!("{}", e);
-                            return writer.finish_output();
-                        }
-                    }
-                    RightExpr::Redir(filename) => match File::create(filename) {
-                        Ok(out_file) => {
-                            writer.set_output_file(out_file);
-                        }
-                        Err(ref e) => {
-                            println!("Can't open output file: {}", e);
-                            return writer.finish_output();
-                        }
-                    },
-                    RightExpr::Append(filename) => {
-                        match OpenOptions::new().append(true).create(true).open(filename) {
-                            Ok(out_file) => {
-                                writer.set_output_file(out_file);
-                            }
-                            Err(ref e) => {
-                                println!("Can't open output file: {}", e);
-                                return writer.finish_output();
-                            }
-                        }
-                    }
+                writer.set_output_format(self.env.output_format);
+                writer.set_syntax_highlight(self.env.click_config.syntax_highlight);
+                if !self.env.use_color(false) {
+                    writer.set_color(false);
                 }
-                let parts_vec: Vec<String> = Parser::new(left).map(|x| x.2).collect();
-                let mut parts = parts_vec.iter().map(|s| &**s);
-                let env = Rc::get_mut(&mut self.env).unwrap();
-                if let Some(cmdstr) = parts.next() {
-                    // There was something typed
-                    if let Ok(num) = (cmdstr as &str).parse::<usize>() {
-                        env.set_current(num);
-                    } else if let Some(range) = try_parse_range(cmdstr) {
-                        // Switch to this when map_while is stable
-                        // let objs: Vec<KObj> =
-                        //     range.map_while(|i| env.item_at(*i).clone()).collect();
-                        let mut objs = vec![];
-                        for i in range {
-                            match env.item_at(i) {
-                                Some(obj) => objs.push(obj.clone()),
-                                None => break,
+                let strip_color = !self.env.use_color(true);
+                let setup = match right {
+                    RightExpr::None => {
+                        // only page and fit tables to the screen when talking to a terminal,
+                        // dimensions is None otherwise
+                        if let Some((cols, rows)) = self.rl.as_mut().and_then(|rl| rl.dimensions())
+                        {
+                            if cols > 0 {
+                                writer.set_table_width(Some(cols));
                             }
-                        }
-                        if objs.is_empty() {
-                            env.clear_current();
-                        } else {
-                            env.set_range(objs);
-                        }
-                    } else if let Some(range) = try_parse_csl(left) {
-                        // parse whole thing before sep since we might type "1, 2, 3" with spaces
-                        let objs: Vec<KObj> =
-                            range.filter_map(|i| env.item_at(i).cloned()).collect();
-                        if objs.is_empty() {
-                            env.clear_current();
-                        } else {
-                            env.set_range(objs);
-                        }
-                    } else if let Some(cmd) = self.commands.iter().find(|&c| c.is(cmdstr)) {
-                        // found a matching command
-                        if let Err(e) = cmd.exec(env, &mut parts, &mut writer) {
-                            match e {
-                                ClickError::Reqwest(_, Some(val)) => {
-                                    let reason = val_str("/reason", &val, "no reason given");
-                                    let msg = val_str("/message", &val, "no message returned");
-                                    clickwriteln!(
-                                        writer,
-                                        "Error executing request. Reason: {}, Message: {}",
-                                        reason,
-                                        msg
-                                    )
+                            if let Some(pager) = self.env.pager_command() {
+                                if rows > 0 && cols > 0 {
+                                    writer.set_pager(pager, rows, cols);
                                 }
-                                _ => clickwriteln!(writer, "{}", e),
-                            };
+                            }
                         }
-                    } else if cmdstr == "help" {
-                        self.show_help(&mut parts, &mut writer);
-                    } else {
-                        clickwriteln!(writer, "Unknown command");
+                        Ok(())
+                    }
+                    RightExpr::Pipe(cmd) => writer.setup_pipe(cmd, strip_color),
+                    RightExpr::Redir(filename) => {
+                        writer.set_output_file(filename, false, strip_color)
                     }
+                    RightExpr::Append(filename) => {
+                        writer.set_output_file(filename, true, strip_color)
+                    }
+                };
+                if let Err(e) = setup {
+                    println!("{}", e);
+                    self.last_failed = true;
+                    return writer.finish_output();
+                }
+                let start = Instant::now();
+                crate::k8s::take_request_totals(); // only count this command's requests
+                if let Err(e) = self.run_command(left, &mut writer) {
+                    self.report_error(e, &mut writer);
+                }
+                let (count, elapsed) = crate::k8s::take_request_totals();
+                if self.env.click_config.verbose > 0 && count > 0 {
+                    crate::k8s::print_verbose(&format!(
+                        "{} request{} in {}ms, command took {}ms",
+                        count,
+                        if count == 1 { "" } else { "s" },
+                        elapsed.as_millis(),
+                        start.elapsed().as_millis()
+                    ));
                 }
 
                 // reset output
@@ -364,11 +856,250 @@ impl CommandProcessor {
             }
             Err(err) => {
                 println!("{}", err);
+                self.last_failed = true;
                 None
             }
         }
     }
 
+    /// Run a click command, with no pipe or redirect, writing its output to writer. Errors from
+    /// commands are returned, for the caller to report.
+    fn run_command(&mut self, line: &str, writer: &mut ClickWriter) -> Result<(), ClickError> {
+        let parts_vec: Vec<String> = Parser::new(line).map(|x| x.2).collect();
+        let mut parts = parts_vec.iter().map(|s| &**s);
+        let env = Rc::get_mut(&mut self.env).unwrap();
+        if let Some(cmdstr) = parts.next() {
+            // There was something typed
+            if let Ok(num) = (cmdstr as &str).parse::<usize>() {
+                env.set_current(num);
+            } else if let Some(range) = try_parse_range(cmdstr) {
+                // Switch to this when map_while is stable
+                // let objs: Vec<KObj> =
+                //     range.map_while(|i| env.item_at(*i).clone()).collect();
+                let mut objs = vec![];
+                for i in range {
+                    match env.item_at(i) {
+                        Some(obj) => objs.push(obj.clone()),
+                        None => break,
+                    }
+                }
+                if objs.is_empty() {
+                    env.clear_current();
+                } else {
+                    env.set_range(objs);
+                }
+            } else if let Some(range) = try_parse_csl(line) {
+                // parse whole thing before sep since we might type "1, 2, 3" with spaces
+                let objs: Vec<KObj> = range.filter_map(|i| env.item_at(i).cloned()).collect();
+                if objs.is_empty() {
+                    env.clear_current();
+                } else {
+                    env.set_range(objs);
+                }
+            } else if let Some(cmd) = self.commands.iter().find(|&c| c.is(cmdstr)) {
+                // found a matching command
+                cmd.exec(env, &mut parts, writer)?;
+            } else if cmdstr == "help" {
+                self.show_help(&mut parts, writer);
+            } else if cmdstr == "source" {
+                // source isn't a command as it runs other commands
+                match (parts.next(), parts.next()) {
+                    (Some(file), None) => self.source_file(&expand_home(file))?,
+                    _ => return Err(ClickError::CommandError("Usage: source FILE".to_string())),
+                }
+            } else if cmdstr == "foreach" {
+                self.foreach(&mut parts, writer)?;
+            } else if cmdstr == "script" {
+                // script isn't a command as it runs other commands
+                match parts.next() {
+                    Some(file) => {
+                        let args: Vec<&str> = parts.collect();
+                        self.run_script(&expand_home(file), &args, writer)?
+                    }
+                    None => {
+                        return Err(ClickError::CommandError(
+                            "Usage: script FILE [ARGS]".to_string(),
+                        ))
+                    }
+                }
+            } else if cmdstr == "history" {
+                self.show_history(&mut parts, writer)?;
+            } else if let Some(path) = crate::plugins::find_plugin(cmdstr) {
+                let args: Vec<&str> = parts.collect();
+                crate::plugins::run_plugin(env, cmdstr, &path, &args, writer)?;
+            } else {
+                clickwriteln!(writer, "Unknown command");
+                self.last_failed = true;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn report_error(&mut self, e: ClickError, writer: &mut ClickWriter) {
+        self.last_drift = matches!(e, ClickError::Drift(_));
+        let msg = match e {
+            ClickError::Reqwest(_, Some(val)) => {
+                let reason = val_str("/reason", &val, "no reason given");
+                let msg = val_str("/message", &val, "no message returned");
+                format!(
+                    "Error executing request. Reason: {}, Message: {}",
+                    reason, msg
+                )
+            }
+            _ => e.to_string(),
+        };
+        writer.set_failed();
+        self.last_failed = true;
+        // errors shouldn't end up in a file or pipe
+        if writer.is_redirected() {
+            eprintln!("{}", msg);
+        } else {
+            clickwriteln!(writer, "{}", theme::style(Role::Error).paint(msg));
+        }
+    }
+
+    /// Run a command template once for each selected object, with that object selected and its
+    /// fields substituted into the template. See FOREACHHELP.
+    fn foreach(
+        &mut self,
+        parts: &mut dyn Iterator<Item = &str>,
+        writer: &mut ClickWriter,
+    ) -> Result<(), ClickError> {
+        let mut stop_on_error = false;
+        let mut words = vec![];
+        for part in parts {
+            match part {
+                "-s" | "--stop-on-error" if words.is_empty() => stop_on_error = true,
+                _ => words.push(part),
+            }
+        }
+        if words.is_empty() {
+            return Err(ClickError::CommandError(
+                "Usage: foreach [--stop-on-error] COMMAND_TEMPLATE".to_string(),
+            ));
+        }
+        // one word is the whole template, quoted as it has spaces. Several words are quoted again
+        // before joining them, so arguments like json for patch keep their quotes
+        let template = match words.as_slice() {
+            [template] => template.to_string(),
+            _ => words
+                .iter()
+                .map(|word| shell_quote(word))
+                .collect::<Vec<_>>()
+                .join(" "),
+        };
+        let objs = match self.env.current_selection() {
+            ObjectSelection::Single(obj) => vec![obj.clone()],
+            ObjectSelection::Range(range) => range.clone(),
+            ObjectSelection::None => {
+                return Err(ClickError::CommandError(
+                    "Nothing selected, select some objects to run the command for".to_string(),
+                ))
+            }
+        };
+        // check the template up front, rather than failing once per object
+        let expanded = alias_expand_line(&self.env, &template);
+        let (left, right) = parse_line(&expanded)?;
+        if right != RightExpr::None {
+            return Err(ClickError::CommandError(
+                "The command can't have a pipe or redirect, put it after the whole foreach instead"
+                    .to_string(),
+            ));
+        }
+        let cmdstr = left.split_whitespace().next().unwrap_or("");
+        if !self.commands.iter().any(|c| c.is(cmdstr))
+            && !is_builtin_command(cmdstr)
+            && crate::plugins::find_plugin(cmdstr).is_none()
+        {
+            return Err(ClickError::CommandError(format!(
+                "Unknown command {}",
+                cmdstr
+            )));
+        }
+
+        let snapshot = self.env.selection_snapshot();
+        // if any command changes the selection (i.e. delete clears it), it's left as it is
+        let mut selection_changed = false;
+        let mut errors = vec![];
+        // a line that doesn't parse stops everything, but only once the selection is put back
+        let mut parse_error = None;
+        self.env.ctrlcbool.store(false, Ordering::SeqCst);
+        let separate = objs.len() > 1;
+        for obj in objs.into_iter() {
+            if self.env.ctrlcbool.load(Ordering::SeqCst) {
+                clickwriteln!(writer, "Interrupted, not running for the remaining objects");
+                break;
+            }
+            let line = alias_expand_line(&self.env, &substitute_obj(&template, &obj));
+            let left = match parse_line(&line) {
+                Ok((left, _)) => left,
+                Err(e) => {
+                    parse_error = Some(e);
+                    break;
+                }
+            };
+            if separate {
+                let sep = substitute_obj(&self.env.click_config.range_separator, &obj);
+                clickwriteln!(writer, "{}", sep);
+            }
+            self.env_mut().select_obj(obj.clone());
+            let res = self.run_command(left, writer);
+            if self.env.current_selection() != &ObjectSelection::Single(obj.clone()) {
+                selection_changed = true;
+            }
+            if let Err(e) = res {
+                errors.push((obj.name, e));
+                if stop_on_error {
+                    break;
+                }
+            }
+        }
+        if !selection_changed {
+            self.env_mut().restore_selection(snapshot);
+        }
+        if let Some(e) = parse_error {
+            return Err(e);
+        }
+        match errors.len() {
+            0 => Ok(()),
+            1 if stop_on_error => Err(errors.remove(0).1),
+            _ => Err(ClickError::MultiError(errors)),
+        }
+    }
+
+    /// List the last entries in the history, numbered for use with !N. See HISTORYHELP.
+    fn show_history(
+        &mut self,
+        parts: &mut dyn Iterator<Item = &str>,
+        writer: &mut ClickWriter,
+    ) -> Result<(), ClickError> {
+        let count = match (parts.next(), parts.next()) {
+            (None, _) => DEFAULT_HISTORY_COUNT,
+            (Some(count), None) => count
+                .parse()
+                .map_err(|_| ClickError::CommandError(format!("Invalid count: {}", count)))?,
+            _ => {
+                return Err(ClickError::CommandError(
+                    "Usage: history [COUNT]".to_string(),
+                ))
+            }
+        };
+        let history = match self.rl.as_ref() {
+            Some(rl) => rl.history(),
+            None => {
+                return Err(ClickError::CommandError(
+                    "There's no history outside the repl".to_string(),
+                ))
+            }
+        };
+        let start = history.len().saturating_sub(count);
+        for (i, entry) in history.iter().enumerate().skip(start) {
+            clickwriteln!(writer, "{:>5}  {}", i + 1, entry);
+        }
+        Ok(())
+    }
+
     fn show_help(&mut self, parts: &mut dyn Iterator<Item = &str>, writer: &mut ClickWriter) {
         // help isn't a command as it needs access to the commands vec
         if let Some(hcmd) = parts.next() {
@@ -389,9 +1120,32 @@ impl CommandProcessor {
                     "ranges" => {
                         clickwriteln!(writer, "{}", RANGEHELP);
                     }
+                    "source" => {
+                        clickwriteln!(writer, "{}", SOURCEHELP);
+                    }
+                    "foreach" => {
+                        clickwriteln!(writer, "{}", FOREACHHELP);
+                    }
+                    "history" => {
+                        clickwriteln!(writer, "{}", HISTORYHELP);
+                    }
+                    "bg" => {
+                        clickwriteln!(writer, "{}", BGHELP);
+                    }
+                    "script" => {
+                        clickwriteln!(writer, "{}", SCRIPTHELP);
+                    }
                     _ => {
                         if let Some(alias) = self.env.get_alias(hcmd) {
                             clickwriteln!(writer, "{} is an alias for '{}'", hcmd, alias.expanded);
+                        } else if let Some(path) = crate::plugins::find_plugin(hcmd) {
+                            clickwriteln!(
+                                writer,
+                                "{} is a plugin, run from {}. Try '{} --help' for its own help.",
+                                hcmd,
+                                path.display(),
+                                hcmd
+                            );
                         } else {
                             clickwriteln!(writer, "I don't know anything about {}, sorry", hcmd);
                         }
@@ -413,6 +1167,22 @@ impl CommandProcessor {
                     c.about()
                 );
             }
+            let plugins = crate::plugins::plugins();
+            if !plugins.is_empty() {
+                clickwriteln!(writer, "\nPlugins (click-* executables in your PATH):");
+                for (name, path) in plugins.iter() {
+                    if self.commands.iter().any(|c| c.is(name)) || is_builtin_command(name) {
+                        clickwriteln!(
+                            writer,
+                            "  {:<20}{} (not used, click has a command with this name)",
+                            name,
+                            path.display()
+                        );
+                    } else {
+                        clickwriteln!(writer, "  {:<20}{}", name, path.display());
+                    }
+                }
+            }
             clickwriteln!(
                 writer,
                 "\nOther help topics (type 'help [TOPIC]' for details)"
@@ -422,21 +1192,46 @@ impl CommandProcessor {
                 "  completion          Available completion_type values \
                  for the 'set' command, and what they mean"
             );
+            clickwriteln!(
+                writer,
+                "  foreach             Running a command for each \
+                 selected object"
+            );
             clickwriteln!(
                 writer,
                 "  edit_mode           Available edit_mode values for \
                  the 'set' command, and what they mean"
             );
+            clickwriteln!(
+                writer,
+                "  history             Listing and re-running commands \
+                 from the history"
+            );
             clickwriteln!(
                 writer,
                 "  ranges              Selecting and operating on multiple \
                  objects at once"
             );
+            clickwriteln!(
+                writer,
+                "  script              Running Lua scripts that drive \
+                 click commands"
+            );
             clickwriteln!(
                 writer,
                 "  shell               Redirecting and piping click \
                  output to shell commands"
             );
+            clickwriteln!(
+                writer,
+                "  source              Running click commands from a \
+                 file, and the rc file run at startup"
+            );
+            clickwriteln!(
+                writer,
+                "  bg                  Running commands in the background, \
+                 and following their output"
+            );
         }
     }
 }
@@ -448,10 +1243,129 @@ Examples:\n\
  logs my-cont | grep ERROR\n\n\
  # pass output of describe -j to jq, then grep for foo \n\
  describe -j | jq . | grep foo\n\n\
+ # count pods that aren't running (colors are stripped from piped output):\n\
+ pods | grep -v Running | wc -l\n\n\
+ # a quoted | is passed to the command, not treated as a pipe:\n\
+ exec -- sh -c \"ps aux | grep java\"\n\n\
  # Save logs to logs.txt:\n\
  logs my-cont > /tmp/logs.txt\n\n\
  # Append log lines that contain \"foo bar\" to logs.txt\n\
- logs the-cont | grep \"foo bar\" >> /tmp/logs.txt";
+ logs the-cont | grep \"foo bar\" >> /tmp/logs.txt\n\n\
+ # redirection composes with pipes:\n\
+ pods | grep foo > /tmp/broken.txt\n\n\
+ # several commands can go on one line. ; always runs the next one, && only if the last worked:\n\
+ namespace prod && pods -l app=api && logs 1 --tail 50\n\n\
+Colors are stripped from redirected output too, unless 'set strip_color_on_redirect false' or \
+'set color on' is used (i.e. to view the file with 'less -R'). If a command fails before printing anything, the file \
+isn't touched, and errors are printed to the terminal rather than the file.";
+
+static FOREACHHELP: &str =
+    "'foreach [--stop-on-error] COMMAND_TEMPLATE' runs a click command once \
+for each selected object. While it runs, just that object is selected, so commands that act on the \
+selection act on it. {name}, {namespace}, {kind}, and {uid} in the template are replaced with the \
+object's values.
+
+If the command fails for some objects, it's still run for the rest, and the errors are printed at \
+the end. With --stop-on-error (or -s), nothing more is run after the first failure. A pipe or \
+redirect applies to the whole foreach, so quote the template if it's more than one word.
+
+Examples:
+  # the last 10 log lines of each selected pod
+  foreach \"logs --tail 10\"
+
+  # look for errors in the logs of all of them (the range_separator says which pod is which)
+  foreach \"logs --tail 100\" | grep -e ERROR -e ---
+
+  # leave a marker file named after each pod
+  foreach \"exec touch /tmp/{name}.{namespace}\"
+
+  # scale each selected deployment to 2 (braces other than the ones above are left alone)
+  foreach patch '{\"spec\":{\"replicas\":2}}'
+
+  # describe each selected deployment, stopping if one fails
+  foreach -s describe";
+
+static SCRIPTHELP: &str = "'script FILE [ARGS]' runs FILE as a Lua script, for workflows that \
+need more than a line of commands: loops, conditions, or using what one command found in the next.
+
+Scripts get a 'click' table with a function for each command, named like the command with - \
+changed to _ (i.e. click.logs, click.exec, click.network_policies). click.get{\"pods\"} runs the \
+command named by its first argument, and click.run(LINE) runs a line as if it was typed, with \
+pipes, chains, and aliases.
+
+A function's arguments are strings, numbers, or a table. In a table, the list part is the \
+positional arguments, and other keys are options: one letter ones are short options, true is a \
+flag, and a list repeats the option. So click.logs{\"app\", tail=10, f=true} runs \
+'logs --tail 10 -f app'. Each function returns a table: ok says if the command worked, and output \
+is what it printed.
+
+click.env has the context, namespace, selection (a list of objects), and last (the objects the \
+last list printed, so last[1] is what '0' selects). Objects have name, namespace, kind, uid, and \
+context. click.env is updated after each command. The script's arguments are in arg, with its \
+path as arg[0], and print writes to the script command's output, so it can be piped or \
+redirected.
+
+Example:
+  -- restart.lua: delete the crash looping pods in a namespace
+  click.namespace(arg[1])
+  click.pods()
+  for i, pod in ipairs(click.env.last) do
+    click.run(tostring(i - 1))
+    local res = click.describe()
+    if res.ok and res.output:find(\"CrashLoopBackOff\") then
+      print(\"deleting \" .. pod.name)
+      click.delete{now = true}
+    end
+  end
+
+  script restart.lua staging";
+
+/// How many entries history lists if it's not given a count
+const DEFAULT_HISTORY_COUNT: usize = 25;
+
+static HISTORYHELP: &str = "'history [COUNT]' lists the last COUNT (default 25) commands in the \
+history, numbered. '!N' runs command number N again, and '!!' runs the last command. Anything \
+after them is added on, so '!12 | grep foo' works.
+
+The history is kept in click.history in the config dir, unless the history_file option is set in \
+the config file. These options control what's kept (set them with 'set', i.e. \
+'set history_size 5000'):
+  history_size          the most commands to keep (default 1000)
+  history_ignore_dups   don't add a command that's the same as the one before it (default true)
+  history_ignore_space  don't add commands typed with a space at the start, i.e. ones with \
+secrets in them (default true)
+
+Several click sessions can run at once, each one adds its commands to the file when it exits.";
+
+static BGHELP: &str = "'bg COMMAND', or 'COMMAND &', runs COMMAND in the background, so the \
+repl can be used while it runs, i.e. for 'logs -f' or 'rollout status'. It runs in another click, \
+in the same context and namespace, with the same selection. Several commands can be run \
+together, as in 'bg pods && logs -f'.
+
+Background commands are numbered as jobs. 'bgjobs' lists them, 'fg N' shows what job N has \
+printed and follows its output until it finishes (ctrl-c stops following, leaving it running), \
+and 'kill N' stops it. The last job_output_lines lines (default 10000) of each job's output are \
+kept, older ones are dropped. Jobs are stopped when click exits, which asks first if any are \
+still running.
+
+Examples:
+  # follow the active pod's logs in the background, and look at them later
+  logs -f &
+  fg 1";
+
+static SOURCEHELP: &str = "'source FILE' runs each line of FILE as a click command, as if you'd \
+typed it. Blank lines, and lines starting with #, are skipped. If a line fails, the error is \
+printed along with the line number, and the rest of the file still runs.
+
+When the repl starts, the rc file is sourced. This is ~/.config/click/clickrc, unless the rc_file \
+option is set (do: 'set rc_file ~/my/clickrc'). Pass --no-rc to click to skip it, i.e. to check \
+if a broken rc file is the problem.
+
+Example clickrc:
+  # start where I usually work
+  context staging
+  namespace ingest
+  set editor nvim";
 
 static COMPLETIONHELP: &str = "There are two completion types: list or circular.
 - list: complete the next full match (like in Vim by default) (do: set completion list)
@@ -499,6 +1413,14 @@ Note that if you want to include spaces, you'll need to quote the string like:
 The 'range' command will print out a table of objects in the current range. This is useful
 to verify your commands will operate on the objects you expect.
 
+\u{001b}[33;1mTHE SELECT COMMAND\u{001b}[0m
+'select' takes any mix of the above, like 'select 1,3,5-8' (where 5-8 includes 8), and reports an
+error if a number is past the end of the last list.
+
+\u{001b}[33;1mFILTERING THE CURRENT RANGE\u{001b}[0m
+The 'filter' command narrows the selection down to the objects whose names match a pattern, like
+'filter api', or 'filter -r' with a regex. 'filter --clear' goes back to the unfiltered selection.
+
 \u{001b}[33;1mCOMMANDS ON RANGES\u{001b}[0m
 Once you have selected a range, you can run any of the following commands which will operate on each
 item in the range in turn:
@@ -537,8 +1459,10 @@ mod tests {
     use crate::error::ClickError;
     use crate::kobj::{KObj, ObjType};
 
+    use ansi_term::Colour::Red;
     use rustyline::completion::Pair as RustlinePair;
 
+    use std::fs::File;
     use std::io::Read;
     use std::path::PathBuf;
 
@@ -551,6 +1475,9 @@ mod tests {
             writer: &mut ClickWriter,
         ) -> Result<(), ClickError> {
             match args.next() {
+                Some("fail") => return Err(ClickError::CommandError("failed".to_string())),
+                Some("drift") => return Err(ClickError::Drift(1)),
+                Some("color") => clickwrite!(writer, "Called with {}", Red.paint("color")),
                 Some(arg) => clickwrite!(writer, "Called with {}", arg),
                 None => clickwrite!(writer, "Called with no args"),
             }
@@ -593,8 +1520,7 @@ mod tests {
     }
 
     fn get_processor() -> CommandProcessor {
-        let mut commands: Vec<Box<dyn Cmd>> = Vec::new();
-        commands.push(Box::new(TestCmd));
+        let commands: Vec<Box<dyn Cmd>> = vec![Box::new(TestCmd)];
         CommandProcessor::new_with_commands(
             Env::new(
                 get_test_config(),
@@ -610,6 +1536,8 @@ mod tests {
         KObj {
             name: name.to_string(),
             namespace: None,
+            uid: None,
+            context: None,
             typ: ObjType::Node,
         }
     }
@@ -641,9 +1569,14 @@ mod tests {
 
 Other help topics (type 'help [TOPIC]' for details)
   completion          Available completion_type values for the 'set' command, and what they mean
+  foreach             Running a command for each selected object
   edit_mode           Available edit_mode values for the 'set' command, and what they mean
+  history             Listing and re-running commands from the history
   ranges              Selecting and operating on multiple objects at once
-  shell               Redirecting and piping click output to shell commands\n"
+  script              Running Lua scripts that drive click commands
+  shell               Redirecting and piping click output to shell commands
+  source              Running click commands from a file, and the rc file run at startup
+  bg                  Running commands in the background, and following their output\n"
                 .as_bytes()
         );
     }
@@ -672,6 +1605,139 @@ Other help topics (type 'help [TOPIC]' for details)
         assert_eq!(res, "Called with arg1".as_bytes());
     }
 
+    #[test]
+    fn chained_commands() {
+        let mut p = get_processor();
+        let writer = ClickWriter::with_buffer(vec![], false);
+        let res = p
+            .process_line("testcmd a; testcmd \"b; c\" && testcmd d", writer)
+            .unwrap();
+        assert_eq!(res, "Called with aCalled with b; cCalled with d".as_bytes());
+        assert!(!p.last_failed);
+
+        // && stops at a failure, ; keeps going
+        let writer = ClickWriter::with_buffer(vec![], false);
+        let res = p
+            .process_line(
+                "testcmd fail && testcmd skipped && testcmd skipped; testcmd e",
+                writer,
+            )
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(res).unwrap(),
+            "Error running command: failed\n\
+             (command 1 of 4 failed: testcmd fail)\n\
+             Called with e"
+        );
+        assert!(p.last_failed);
+    }
+
+    #[test]
+    fn run_commands() {
+        let mut p = get_processor();
+        assert_eq!(
+            p.run_commands(vec![
+                "testcmd a; testcmd b".to_string(),
+                "# testcmd fail".to_string()
+            ]),
+            0
+        );
+        assert_eq!(
+            p.run_commands(vec!["testcmd fail; testcmd a".to_string()]),
+            1
+        );
+        assert_eq!(
+            p.run_commands(vec!["nope".to_string(), "testcmd".to_string()]),
+            1
+        );
+
+        // drift gets its own exit code, unless something else failed too
+        assert_eq!(
+            p.run_commands(vec!["testcmd drift; testcmd a".to_string()]),
+            DRIFT_EXIT_CODE
+        );
+        assert_eq!(
+            p.run_commands(vec![
+                "testcmd drift".to_string(),
+                "testcmd fail".to_string()
+            ]),
+            1
+        );
+        assert_eq!(
+            p.run_commands(vec![
+                "testcmd fail".to_string(),
+                "testcmd drift".to_string()
+            ]),
+            1
+        );
+    }
+
+    #[test]
+    fn foreach() {
+        let mut p = get_processor();
+        let nodes = vec![make_node_kobj("ns1"), make_node_kobj("ns2")];
+        p.env_mut().set_range(nodes.clone());
+
+        let writer = ClickWriter::with_buffer(vec![], false);
+        let res = p
+            .process_line("foreach \"testcmd {name}\"", writer)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(res).unwrap(),
+            "--- ns1 ---\nCalled with ns1--- ns2 ---\nCalled with ns2"
+        );
+        // the selection is put back afterwards
+        assert_eq!(
+            p.env.current_selection(),
+            &ObjectSelection::Range(nodes.clone())
+        );
+
+        // braces other than the placeholders, like json for patch, are passed through
+        let writer = ClickWriter::with_buffer(vec![], false);
+        let res = p
+            .process_line(
+                "foreach testcmd '{\"metadata\":{\"name\":\"{name}\"}}'",
+                writer,
+            )
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(res).unwrap(),
+            "--- ns1 ---\nCalled with {\"metadata\":{\"name\":\"ns1\"}}\
+             --- ns2 ---\nCalled with {\"metadata\":{\"name\":\"ns2\"}}"
+        );
+
+        let writer = ClickWriter::with_buffer(vec![], false);
+        let res = p.process_line("foreach testcmd fail", writer).unwrap();
+        assert!(p.last_failed);
+        assert_eq!(
+            String::from_utf8(res).unwrap(),
+            "--- ns1 ---\n--- ns2 ---\nOperation failed for 2 objects:\n  \
+             ns1: Error running command: failed\n  \
+             ns2: Error running command: failed\n"
+        );
+
+        let writer = ClickWriter::with_buffer(vec![], false);
+        let res = p
+            .process_line("foreach --stop-on-error testcmd fail", writer)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(res).unwrap(),
+            "--- ns1 ---\nError running command: failed\n"
+        );
+
+        let writer = ClickWriter::with_buffer(vec![], false);
+        let res = p.process_line("foreach nope", writer).unwrap();
+        assert_eq!(
+            String::from_utf8(res).unwrap(),
+            "Error running command: Unknown command nope\n"
+        );
+
+        p.env_mut().clear_current();
+        let writer = ClickWriter::with_buffer(vec![], false);
+        p.process_line("foreach testcmd", writer);
+        assert!(p.last_failed);
+    }
+
     #[test]
     fn number_selection() {
         let commands: Vec<Box<dyn Cmd>> = Vec::new();
@@ -693,6 +1759,8 @@ Other help topics (type 'help [TOPIC]' for details)
             &ObjectSelection::Single(KObj {
                 name: "ns1".to_string(),
                 namespace: None,
+                uid: None,
+                context: None,
                 typ: ObjType::Node,
             })
         );
@@ -701,6 +1769,79 @@ Other help topics (type 'help [TOPIC]' for details)
         assert_eq!(p.env.current_selection(), &ObjectSelection::None);
     }
 
+    #[test]
+    fn source_file() {
+        let commands: Vec<Box<dyn Cmd>> = Vec::new();
+        let mut env = Env::new(
+            get_test_config(),
+            ClickConfig::default(),
+            PathBuf::from("/tmp/click.conf"),
+        );
+        env.set_last_objs(vec![make_node_kobj("ns1"), make_node_kobj("ns2")]);
+        let mut p = CommandProcessor::new_with_commands(
+            env,
+            PathBuf::from("/tmp/click.test.hist"),
+            commands,
+        );
+        let dir = tempdir::TempDir::new("click_test_dir").unwrap();
+        let rc = dir.path().join("clickrc");
+        let nested = dir.path().join("nested");
+        std::fs::write(
+            &rc,
+            format!(
+                "# a comment\n\nnot-a-command\n  0\nsource {}\nsource {}\n",
+                nested.display(),
+                rc.display()
+            ),
+        )
+        .unwrap();
+        std::fs::write(&nested, "1\n").unwrap();
+
+        // the unknown command and the loop are reported, but don't stop the later lines
+        p.source_file(&rc).unwrap();
+        assert_eq!(
+            p.env.current_selection(),
+            &ObjectSelection::Single(make_node_kobj("ns2"))
+        );
+        assert!(p.sourcing.is_empty());
+        assert!(p.source_file(&dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn script() {
+        let mut p = get_processor();
+        p.env_mut()
+            .set_last_objs(vec![make_node_kobj("ns1"), make_node_kobj("ns2")]);
+        let dir = tempdir::TempDir::new("click_test_dir").unwrap();
+        let script = dir.path().join("test.lua");
+        std::fs::write(
+            &script,
+            r#"
+local res = click.testcmd("hi")
+print(res.ok, res.output)
+print(click.testcmd{"fail"}.ok)
+click.run("1")
+print(click.env.selection[1].name, #click.env.last, arg[1])
+"#,
+        )
+        .unwrap();
+
+        let writer = ClickWriter::with_buffer(vec![], false);
+        let line = format!("script {} an-arg", script.display());
+        let res = p.process_line(&line, writer).unwrap();
+        assert_eq!(
+            String::from_utf8(res).unwrap(),
+            "true\tCalled with hi\nfalse\nns2\t2\tan-arg\n"
+        );
+        assert!(!p.last_failed);
+        assert!(p.sourcing.is_empty());
+
+        std::fs::write(&script, "click.nope()").unwrap();
+        let writer = ClickWriter::with_buffer(vec![], false);
+        p.process_line(&line, writer);
+        assert!(p.last_failed);
+    }
+
     #[test]
     fn range_selection() {
         let commands: Vec<Box<dyn Cmd>> = Vec::new();
@@ -806,6 +1947,68 @@ Other help topics (type 'help [TOPIC]' for details)
         dir.close().unwrap();
     }
 
+    #[test]
+    fn redirect_strips_color() {
+        let dir = tempdir::TempDir::new("click_test_dir").unwrap();
+        let file_path_buf = dir.path().join("color.txt");
+        let cmd = format!("testcmd color > {}", file_path_buf.to_str().unwrap());
+
+        let mut p = get_processor();
+        p.process_line(&cmd, ClickWriter::new());
+        let mut contents = String::new();
+        File::open(&file_path_buf)
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "Called with color");
+
+        Rc::get_mut(&mut p.env)
+            .unwrap()
+            .click_config
+            .strip_color_on_redirect = false;
+        p.process_line(&cmd, ClickWriter::new());
+        let mut contents = String::new();
+        File::open(&file_path_buf)
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, format!("Called with {}", Red.paint("color")));
+
+        dir.close().unwrap();
+    }
+
+    #[test]
+    fn redirect_errors() {
+        let dir = tempdir::TempDir::new("click_test_dir").unwrap();
+        let mut p = get_processor();
+
+        // missing directory is an error, and nothing is created
+        let missing = dir.path().join("nodir").join("out.txt");
+        let res = ClickWriter::new().set_output_file(missing.to_str().unwrap(), false, true);
+        assert!(res.is_err());
+        p.process_line(
+            &format!("testcmd > {}", missing.to_str().unwrap()),
+            ClickWriter::new(),
+        );
+        assert!(!missing.exists());
+
+        // a command that fails without output leaves the file alone
+        let file_path_buf = dir.path().join("out.txt");
+        let path = file_path_buf.to_str().unwrap();
+        p.process_line(&format!("testcmd fail > {}", path), ClickWriter::new());
+        assert!(!file_path_buf.exists());
+        p.process_line(&format!("testcmd > {}", path), ClickWriter::new());
+        p.process_line(&format!("testcmd fail > {}", path), ClickWriter::new());
+        let mut contents = String::new();
+        File::open(&file_path_buf)
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "Called with no args");
+
+        dir.close().unwrap();
+    }
+
     #[test]
     #[ignore] // we ignore this since we can't guarantee a system has grep
     fn pipeline() {
@@ -828,6 +2031,22 @@ Other help topics (type 'help [TOPIC]' for details)
         dir.close().unwrap();
     }
 
+    #[test]
+    fn parse_pipes() {
+        let (left, right) = parse_line("pods|grep -v Running | wc -l").unwrap();
+        assert_eq!(left, "pods");
+        assert_eq!(right, RightExpr::Pipe("grep -v Running | wc -l"));
+
+        let (left, right) = parse_line("pods | grep 'a b' > /tmp/out").unwrap();
+        assert_eq!(left, "pods ");
+        assert_eq!(right, RightExpr::Pipe(" grep 'a b' > /tmp/out"));
+
+        let line = r#"exec -- sh -c "a | b" 'c > d' e\|f"#;
+        let (left, right) = parse_line(line).unwrap();
+        assert_eq!(left, line);
+        assert_eq!(right, RightExpr::None);
+    }
+
     #[test]
     fn unexpected_chars() {
         let p = parse_line("test || this");
@@ -886,7 +2105,97 @@ Other help topics (type 'help [TOPIC]' for details)
         let buf = vec![];
         let writer = ClickWriter::with_buffer(buf, false);
         p.process_line(" testcmd", writer);
-        assert_eq!(p.rl.history().len(), 0);
+        assert_eq!(p.rl.as_ref().unwrap().history().len(), 0);
+    }
+
+    #[test]
+    fn hist_ignore_off() {
+        let mut p = get_processor();
+        p.env_mut().click_config.history_ignore_space = false;
+        p.process_line(" testcmd", ClickWriter::with_buffer(vec![], false));
+        p.process_line("testcmd", ClickWriter::with_buffer(vec![], false));
+        let history = p.rl.as_ref().unwrap().history();
+        // leading space is trimmed, and the dup isn't added
+        assert_eq!(history.len(), 1);
+        assert_eq!(history.get(0).unwrap(), "testcmd");
+    }
+
+    #[test]
+    fn test_expand_history_ref() {
+        let mut history = History::new();
+        history.add("pods");
+        history.add("logs -f");
+        assert_eq!(
+            expand_history_ref("!1", &history).unwrap(),
+            Some("pods".to_string())
+        );
+        assert_eq!(
+            expand_history_ref("!! | grep x", &history).unwrap(),
+            Some("logs -f | grep x".to_string())
+        );
+        assert_eq!(expand_history_ref("pods", &history).unwrap(), None);
+        assert_eq!(expand_history_ref("!foo", &history).unwrap(), None);
+        assert!(expand_history_ref("!0", &history).is_err());
+        assert!(expand_history_ref("!3", &history).is_err());
+    }
+
+    #[test]
+    fn history_command() {
+        let mut p = get_processor();
+        p.process_line("testcmd", ClickWriter::with_buffer(vec![], false));
+        p.process_line("help testcmd", ClickWriter::with_buffer(vec![], false));
+        let res = p
+            .process_line("history", ClickWriter::with_buffer(vec![], false))
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(res).unwrap(),
+            "    1  testcmd\n    2  help testcmd\n    3  history\n"
+        );
+        let res = p
+            .process_line("history 1", ClickWriter::with_buffer(vec![], false))
+            .unwrap();
+        assert_eq!(String::from_utf8(res).unwrap(), "    4  history 1\n");
+
+        // the expanded command is what's run and added to the history
+        let res = p
+            .process_line("!2", ClickWriter::with_buffer(vec![], false))
+            .unwrap();
+        assert_eq!(res, "HELP\n".as_bytes());
+        assert!(!p.last_failed);
+        let history = p.rl.as_ref().unwrap().history();
+        assert_eq!(history.get(history.len() - 1).unwrap(), "help testcmd");
+
+        p.process_line("!99", ClickWriter::with_buffer(vec![], false));
+        assert!(p.last_failed);
+    }
+
+    #[test]
+    fn save_history_merges() {
+        let dir = tempdir::TempDir::new("click_test_dir").unwrap();
+        let hist_path = dir.path().join("click.history");
+        let new_processor = || {
+            CommandProcessor::new_with_commands(
+                Env::new(
+                    get_test_config(),
+                    ClickConfig::default(),
+                    PathBuf::from("/tmp/click.conf"),
+                ),
+                hist_path.clone(),
+                vec![Box::new(TestCmd)],
+            )
+        };
+        let mut first = new_processor();
+        let mut second = new_processor();
+        first.process_line("testcmd one", ClickWriter::with_buffer(vec![], false));
+        second.process_line("testcmd two", ClickWriter::with_buffer(vec![], false));
+        first.save_history();
+        second.save_history();
+        let mut history = History::new();
+        history.load(&hist_path).unwrap();
+        let entries: Vec<&String> = history.iter().collect();
+        assert_eq!(entries, vec!["testcmd one", "testcmd two"]);
+        // nothing left behind
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
     }
 
     #[test]
@@ -944,4 +2253,27 @@ Other help topics (type 'help [TOPIC]' for details)
 
         assert_eq!(alias_expand_line(&env, "z x"), "xpand arg x");
     }
+
+    #[test]
+    fn test_alias_expand_loop() {
+        let mut cc = ClickConfig::default();
+        cc.aliases.push(Alias {
+            alias: "a".to_string(),
+            expanded: "b one".to_string(),
+        });
+        cc.aliases.push(Alias {
+            alias: "b".to_string(),
+            expanded: "a two".to_string(),
+        });
+        let env = Env::new(get_test_config(), cc, PathBuf::from("/tmp/click.config"));
+        assert_eq!(alias_expand_line(&env, "a three"), "a two one three");
+    }
+
+    #[test]
+    fn test_is_builtin_command() {
+        assert!(is_builtin_command("pods"));
+        assert!(is_builtin_command("ctx"));
+        assert!(is_builtin_command("help"));
+        assert!(!is_builtin_command("p"));
+    }
 }
@@ -22,9 +22,12 @@ use rustyline::{
 };
 
 use crate::command::command_def::Cmd;
+use crate::command::{kubectl_command, kubectl_obj_command};
 use crate::env::Env;
-use crate::kobj::ObjType;
+use crate::kobj::{KObj, ObjType};
 
+use std::collections::BTreeMap;
+use std::process::Command;
 use std::rc::Rc;
 
 pub struct ClickHelper {
@@ -49,7 +52,13 @@ impl Hinter for ClickHelper {
 
 // get a vec with strings that could complete commands or aliases
 fn get_command_completion_strings(commands: &[Box<dyn Cmd>], env: Option<&Rc<Env>>) -> Vec<String> {
-    let mut v = vec!["help".to_string()];
+    let mut v = vec![
+        "help".to_string(),
+        "source".to_string(),
+        "foreach".to_string(),
+        "history".to_string(),
+        "bg".to_string(),
+    ];
     for cmd in commands.iter() {
         v.push(cmd.get_name().to_string());
     }
@@ -57,8 +66,10 @@ fn get_command_completion_strings(commands: &[Box<dyn Cmd>], env: Option<&Rc<Env
         for alias in env.click_config.aliases.iter() {
             v.push(alias.alias.to_string());
         }
+        v.extend(crate::plugins::plugins().into_keys());
     }
     v.sort_unstable();
+    v.dedup();
     v
 }
 
@@ -83,12 +94,7 @@ impl ClickHelper {
 
     #[allow(clippy::borrowed_box)]
     fn get_exact_command(&self, line: &str) -> Option<&Box<dyn Cmd>> {
-        for cmd in self.commands.iter() {
-            if cmd.is(line) {
-                return Some(cmd);
-            }
-        }
-        None
+        self.commands.iter().find(|cmd| cmd.is(line))
     }
 
     /// complete a line that starts with a full command. This should only be called when we know
@@ -156,16 +162,11 @@ impl ClickHelper {
             // here the last thing typed wasn't a '-' option, so we ask the command to
             // do completion
             if let Some(ref env) = self.env {
-                match last_opt {
-                    Some(opt) => {
-                        let opts = cmd.try_completed_named(pos, opt, prefix, &*env);
-                        (cmd_len, opts)
-                    }
-                    None => {
-                        let opts = cmd.try_complete(pos, prefix, &*env);
-                        (cmd_len, opts)
-                    }
-                }
+                let opts = match last_opt {
+                    Some(opt) => cmd.try_completed_named(pos, opt, prefix, env),
+                    None => cmd.try_complete(pos, prefix, env),
+                };
+                fuzzy_adjust(cmd_len, prefix, opts)
             } else {
                 (0, vec![])
             }
@@ -223,6 +224,24 @@ impl ClickHelper {
     }
 }
 
+/// Fuzzy completers can return candidates that don't start with what the user typed. In that case
+/// we need to replace the whole word, so move the start position back to the beginning of the
+/// prefix and make every replacement a full word
+fn fuzzy_adjust(cmd_len: usize, prefix: &str, opts: Vec<Pair>) -> (usize, Vec<Pair>) {
+    if opts.iter().all(|opt| opt.display.starts_with(prefix)) {
+        (cmd_len, opts)
+    } else {
+        let opts = opts
+            .into_iter()
+            .map(|opt| Pair {
+                replacement: opt.display.clone(),
+                display: opt.display,
+            })
+            .collect();
+        (cmd_len - prefix.len(), opts)
+    }
+}
+
 /// Does the short option (an Option<char>) from clap match
 pub fn long_matches(long: &Option<&str>, prefix: &str) -> bool {
     match long {
@@ -256,54 +275,252 @@ impl Completer for ClickHelper {
 }
 
 // Individual completers are below
-pub fn context_complete(prefix: &str, env: &Env) -> Vec<Pair> {
-    let mut v = Vec::new();
-    for context in env.config.contexts.keys() {
-        if let Some(rest) = context.strip_prefix(prefix) {
-            v.push(Pair {
-                display: context.to_string(),
-                replacement: rest.to_string(),
-            })
-        }
+
+/// Build completions for prefix out of candidates. Candidates that start with prefix are preferred,
+/// and just complete the rest of the word. If there are none, this falls back to the same fuzzy
+/// matching as the commands that take these values.
+fn fuzzy_pairs<'a, I>(prefix: &str, candidates: I) -> Vec<Pair>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    crate::fuzzy::completion_candidates(prefix, candidates)
+        .into_iter()
+        .map(|candidate| Pair {
+            display: candidate.to_string(),
+            replacement: candidate
+                .strip_prefix(prefix)
+                .unwrap_or(candidate)
+                .to_string(),
+        })
+        .collect()
+}
+
+/// Complete context names from the loaded kubeconfig(s), along with any context aliases
+pub fn context_completer(prefix: &str, env: &Env) -> Vec<Pair> {
+    let contexts = env.config.contexts.keys();
+    let aliases = env
+        .click_config
+        .context_aliases
+        .keys()
+        .filter(|alias| !env.config.contexts.contains_key(*alias));
+    fuzzy_pairs(prefix, contexts.chain(aliases).map(|k| k.as_str()))
+}
+
+/// Complete the last item of a comma separated list with complete, keeping the items before it
+fn complete_list_item(prefix: &str, env: &Env, complete: fn(&str, &Env) -> Vec<Pair>) -> Vec<Pair> {
+    let (done, last) = match prefix.rfind(',') {
+        Some(pos) => prefix.split_at(pos + 1),
+        None => ("", prefix),
+    };
+    complete(last, env)
+        .into_iter()
+        .map(|pair| Pair {
+            display: pair.display,
+            replacement: format!("{}{}", done, pair.replacement),
+        })
+        .collect()
+}
+
+/// Complete a comma separated list of contexts, like for `pods --contexts`
+pub fn context_list_completer(prefix: &str, env: &Env) -> Vec<Pair> {
+    complete_list_item(prefix, env, context_completer)
+}
+
+/// Complete a comma separated list of namespaces, like for `pods --namespaces`
+pub fn namespace_list_completer(prefix: &str, env: &Env) -> Vec<Pair> {
+    complete_list_item(prefix, env, namespace_completer)
+}
+
+/// Complete the names of the user's aliases
+pub fn alias_completer(prefix: &str, env: &Env) -> Vec<Pair> {
+    fuzzy_pairs(
+        prefix,
+        env.click_config.aliases.iter().map(|a| a.alias.as_str()),
+    )
+}
+
+/// Get the names of all namespaces in the current context
+pub fn get_namespace_names(env: &Env) -> Option<Vec<String>> {
+    let (request, _response_body) = api::Namespace::list_namespace(Default::default()).ok()?;
+    env.run_on_context::<_, List<api::Namespace>>(|c| c.execute_list(request))
+        .ok()
+        .map(|nslist| {
+            nslist
+                .items
+                .into_iter()
+                .filter_map(|ns| ns.metadata.name)
+                .collect()
+        })
+}
+
+/// The kubectl command to get the names of all objects of kind (anything `kubectl get` accepts,
+/// i.e. "pods") in the current namespace, or in all namespaces if none is set. Its output is
+/// parsed with parse_resource_names.
+pub fn resource_names_command(env: &Env, kind: &str) -> Option<Command> {
+    list_command(env, kind, "name")
+}
+
+/// A kubectl command to list objects of kind in the current namespace, or all of them if none is
+/// set, with output as the -o format
+fn list_command(env: &Env, kind: &str, output: &str) -> Option<Command> {
+    let mut command = kubectl_command(env, env.namespace.as_deref()).ok()?;
+    command.arg("get").arg(kind).arg("-o").arg(output);
+    if env.namespace.is_none() {
+        command.arg("--all-namespaces");
     }
-    v
+    Some(command)
 }
 
-pub fn namespace_completer(prefix: &str, env: &Env) -> Vec<Pair> {
-    let (request, _response_body) = api::Namespace::list_namespace(Default::default()).unwrap();
-    match env.run_on_context::<_, List<api::Namespace>>(|c| c.execute_list(request)) {
-        Ok(nslist) => nslist
-            .items
-            .into_iter()
-            .filter_map(|ns| {
-                ns.metadata.name.and_then(|name| {
-                    if let Some(rep) = name.strip_prefix(prefix) {
-                        let replacement = rep.to_string();
-                        Some(Pair {
-                            display: name,
-                            replacement,
-                        })
+/// Get the names from the output of `kubectl get -o name`
+pub fn parse_resource_names(output: &str) -> Vec<String> {
+    // names come back as kind/name, i.e. deployment.apps/foo
+    let mut names: Vec<String> = output
+        .lines()
+        .filter_map(|line| line.split_once('/').map(|(_, name)| name.to_string()))
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    names
+}
+
+/// The kubectl command to get the names of all the containers in pod, parsed with
+/// parse_container_names
+pub fn container_names_command(env: &Env, pod: &KObj) -> Option<Command> {
+    let mut command = kubectl_obj_command(env, pod).ok()?;
+    command.arg("get").arg("pod").arg(pod.name()).arg(
+        "-o=jsonpath={.spec.containers[*].name} {.spec.initContainers[*].name} \
+         {.spec.ephemeralContainers[*].name}",
+    );
+    Some(command)
+}
+
+pub fn parse_container_names(output: &str) -> Vec<String> {
+    output
+        .split_whitespace()
+        .map(|name| name.to_string())
+        .collect()
+}
+
+/// The kubectl command to get the labels of all objects of kind, as a json object per line. Its
+/// output is parsed with parse_label_pairs.
+pub fn labels_command(env: &Env, kind: &str) -> Option<Command> {
+    list_command(
+        env,
+        kind,
+        "jsonpath={range .items[*]}{.metadata.labels}{\"\\n\"}{end}",
+    )
+}
+
+/// Get key=value strings for all the labels in the output of labels_command
+pub fn parse_label_pairs(output: &str) -> Vec<String> {
+    let mut pairs: Vec<String> = output
+        .lines()
+        .filter_map(|line| serde_json::from_str::<BTreeMap<String, String>>(line).ok())
+        .flat_map(|labels| {
+            labels
+                .into_iter()
+                .map(|(key, value)| format!("{}={}", key, value))
+        })
+        .collect();
+    pairs.sort_unstable();
+    pairs.dedup();
+    pairs
+}
+
+/// Complete the names of objects of kind. Use `resource_name_completer!` to make a completer for
+/// a particular kind.
+pub fn resource_name_completer(kind: &str, prefix: &str, env: &Env) -> Vec<Pair> {
+    match env.cached_resource_names(kind) {
+        Some(names) => fuzzy_pairs(prefix, names.iter().map(|n| n.as_str())),
+        None => vec![],
+    }
+}
+
+/// Complete a label selector (like app=web,tier!=db) using the labels on objects of kind. The last
+/// term of the selector is completed, with label keys, or with the values seen for the key once
+/// there's an = (or !=). Use `label_selector_completer!` to make a completer for a particular kind.
+pub fn label_selector_completer(kind: &str, prefix: &str, env: &Env) -> Vec<Pair> {
+    match env.cached_label_pairs(kind) {
+        Some(pairs) => complete_label_selector(prefix, &pairs),
+        None => vec![],
+    }
+}
+
+/// Complete the selector in prefix from the key=value label pairs
+fn complete_label_selector(prefix: &str, pairs: &[String]) -> Vec<Pair> {
+    let term = prefix.rsplit(',').next().unwrap_or(prefix);
+    let candidates: Vec<String> = match term.find('=') {
+        Some(eq) => {
+            // key=, key==, and key!= all complete values
+            let key = term[..eq].trim_end_matches('!');
+            let op_end = if term[eq + 1..].starts_with('=') {
+                eq + 2
+            } else {
+                eq + 1
+            };
+            let typed = &term[..op_end];
+            pairs
+                .iter()
+                .filter_map(|pair| pair.split_once('='))
+                .filter(|(k, _)| *k == key)
+                .map(|(_, value)| format!("{}{}", typed, value))
+                .collect()
+        }
+        None => {
+            // a leading ! selects objects without the key
+            let not = term.starts_with('!');
+            let mut keys: Vec<String> = pairs
+                .iter()
+                .filter_map(|pair| pair.split_once('='))
+                .map(|(key, _)| {
+                    if not {
+                        format!("!{}", key)
                     } else {
-                        None
+                        format!("{}=", key)
                     }
                 })
+                .collect();
+            keys.dedup();
+            keys
+        }
+    };
+    candidates
+        .iter()
+        .filter_map(|candidate| {
+            candidate.strip_prefix(term).map(|rest| Pair {
+                display: candidate.clone(),
+                replacement: rest.to_string(),
             })
-            .collect(),
-        Err(_) => vec![],
+        })
+        .collect()
+}
+
+/// Complete namespace names. Names are cached in the env for a short time, so this is cheap enough
+/// to register for any argument that takes a namespace
+pub fn namespace_completer(prefix: &str, env: &Env) -> Vec<Pair> {
+    match env.cached_namespace_names() {
+        Some(names) => fuzzy_pairs(prefix, names.iter().map(|n| n.as_str())),
+        None => vec![],
     }
 }
 
+/// Complete the names of the containers in the active pod. The live names are fetched (and cached
+/// like other names), so this includes init and debug containers. Until they're fetched, the
+/// containers the pod had when it was listed are used.
 pub fn container_completer(prefix: &str, env: &Env) -> Vec<Pair> {
     let mut v = vec![];
-    if let Some(pod) = env.current_pod() {
-        if let ObjType::Pod { ref containers } = pod.typ {
-            for cont in containers.iter() {
-                if let Some(rest) = cont.strip_prefix(prefix) {
-                    v.push(Pair {
-                        display: cont.clone(),
-                        replacement: rest.to_string(),
-                    });
-                }
+    if let Ok(pod) = env.current_pod() {
+        let containers = match (env.cached_container_names(pod), &pod.typ) {
+            (Some(names), _) => names,
+            (None, ObjType::Pod { containers }) => containers.clone(),
+            (None, _) => vec![],
+        };
+        for cont in containers.iter() {
+            if let Some(rest) = cont.strip_prefix(prefix) {
+                v.push(Pair {
+                    display: cont.clone(),
+                    replacement: rest.to_string(),
+                });
             }
         }
     }
@@ -327,8 +544,78 @@ macro_rules! possible_values_completer {
     };
 }
 
+macro_rules! resource_name_completer {
+    ($name: ident, $kind: expr) => {
+        pub fn $name(prefix: &str, env: &Env) -> Vec<Pair> {
+            resource_name_completer($kind, prefix, env)
+        }
+    };
+}
+
+macro_rules! label_selector_completer {
+    ($name: ident, $kind: expr) => {
+        pub fn $name(prefix: &str, env: &Env) -> Vec<Pair> {
+            label_selector_completer($kind, prefix, env)
+        }
+    };
+}
+
+resource_name_completer!(cronjob_completer, "cronjobs");
+resource_name_completer!(daemonset_completer, "daemonsets");
+resource_name_completer!(deployment_completer, "deployments");
+resource_name_completer!(hpa_completer, "horizontalpodautoscalers");
+resource_name_completer!(job_completer, "jobs");
+resource_name_completer!(replicaset_completer, "replicasets");
+resource_name_completer!(secret_completer, "secrets");
+resource_name_completer!(statefulset_completer, "statefulsets");
+
+label_selector_completer!(pod_label_completer, "pods");
+
 possible_values_completer!(setoptions_values_completer, crate::command::click::SET_OPTS);
 
+possible_values_completer!(
+    unsetoptions_values_completer,
+    crate::command::click::UNSET_OPTS
+);
+
+possible_values_completer!(
+    rolloutaction_values_completer,
+    ["status", "restart", "history", "undo"]
+);
+
+possible_values_completer!(aliasaction_values_completer, ["add", "list", "rm"]);
+
+possible_values_completer!(kustomizeaction_values_completer, ["preview", "apply"]);
+
+possible_values_completer!(
+    helmaction_values_completer,
+    ["list", "status", "values", "history"]
+);
+
+possible_values_completer!(
+    istioaction_values_completer,
+    ["proxy-status", "proxy-config"]
+);
+
+possible_values_completer!(
+    velerokind_values_completer,
+    ["backups", "restores", "schedules", "restore"]
+);
+
+possible_values_completer!(
+    waitfor_values_completer,
+    [
+        "condition=Ready",
+        "condition=Available",
+        "condition=Complete",
+        "condition=Failed",
+        "delete",
+        "jsonpath=",
+    ]
+);
+
+possible_values_completer!(proxyaction_values_completer, ["start", "stop", "status"]);
+
 possible_values_completer!(
     portforwardaction_values_completer,
     ["list", "output", "stop"]
@@ -14,8 +14,10 @@
 
 /// Click config
 use atomicwrites::{AllowOverwrite, AtomicFile};
+use regex::Regex;
 use rustyline::config as rustyconfig;
 
+use std::collections::BTreeMap;
 use std::fmt;
 use std::fs::File;
 use std::io::Read;
@@ -28,18 +30,13 @@ pub struct Alias {
     pub expanded: String,
 }
 
-#[derive(PartialEq, Debug, Deserialize, Serialize)]
+#[derive(Clone, Default, PartialEq, Debug, Deserialize, Serialize)]
 pub enum EditMode {
+    #[default]
     Emacs,
     Vi,
 }
 
-impl Default for EditMode {
-    fn default() -> Self {
-        EditMode::Emacs
-    }
-}
-
 impl fmt::Display for EditMode {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -59,18 +56,13 @@ impl From<&EditMode> for String {
     }
 }
 
-#[derive(PartialEq, Debug, Deserialize, Serialize)]
+#[derive(Clone, Default, PartialEq, Debug, Deserialize, Serialize)]
 pub enum CompletionType {
+    #[default]
     Circular,
     List,
 }
 
-impl Default for CompletionType {
-    fn default() -> Self {
-        CompletionType::Circular
-    }
-}
-
 impl fmt::Display for CompletionType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -90,10 +82,92 @@ impl From<&CompletionType> for String {
     }
 }
 
+/// When to color output
+#[derive(Clone, Copy, Default, PartialEq, Debug, Deserialize, Serialize)]
+pub enum ColorMode {
+    On,
+    Off,
+    /// color output to a terminal, but not output that's piped, redirected, or going to a file
+    #[default]
+    Auto,
+}
+
+impl fmt::Display for ColorMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ColorMode::On => "On",
+                ColorMode::Off => "Off",
+                ColorMode::Auto => "Auto",
+            }
+        )
+    }
+}
+
+/// What to ask the api server to send lists as
+#[derive(Clone, Copy, Default, PartialEq, Debug, Deserialize, Serialize)]
+pub enum WireFormat {
+    #[default]
+    Json,
+    /// application/vnd.kubernetes.protobuf, which is smaller to send. Only used for the core and
+    /// apps types click can decode it for, everything else is still json.
+    Protobuf,
+}
+
+impl fmt::Display for WireFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                WireFormat::Json => "Json",
+                WireFormat::Protobuf => "Protobuf",
+            }
+        )
+    }
+}
+
+/// Who to send requests as, from --as/--as-group or 'set impersonate' and 'set impersonate_group'
+#[derive(Clone, Debug, PartialEq)]
+pub struct Impersonation {
+    pub user: String,
+    pub groups: Vec<String>,
+}
+
+/// The verbose level for a value like the ones 'set verbose' and -v take: on or off, or a level
+/// from 0 (off) to MAX_VERBOSE
+pub fn parse_verbose(value: &str) -> Option<u32> {
+    match value {
+        "on" | "true" => Some(1),
+        "off" | "false" => Some(0),
+        _ => value.parse().ok().filter(|level| *level <= MAX_VERBOSE),
+    }
+}
+
+/// The highest verbose level, which shows request and response headers
+pub const MAX_VERBOSE: u32 = 2;
+
+lazy_static! {
+    // what go's time.ParseDuration takes, i.e. 30s or 1m30s, and kubectl's plain 0 for no timeout
+    static ref GO_DURATION: Regex =
+        Regex::new(r"^(0|(([0-9]+(\.[0-9]*)?|\.[0-9]+)(ns|us|µs|μs|ms|s|m|h))+)$").unwrap();
+}
+
+/// If value is a duration kubectl takes, like the ones --timeout and 'set default_timeout' take
+pub fn is_go_duration(value: &str) -> bool {
+    GO_DURATION.is_match(value)
+}
+
 fn default_range_sep() -> String {
     "--- {name} ---".to_string()
 }
 
+pub fn default_prompt_format() -> String {
+    "[{context}] [{namespace}] [{selection}] > ".to_string()
+}
+
 fn default_connect_timeout() -> u32 {
     10
 }
@@ -106,28 +180,184 @@ fn default_describe_include_events() -> bool {
     true
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+fn default_remember_namespace() -> bool {
+    true
+}
+
+fn default_auth_refresh() -> bool {
+    true
+}
+
+fn default_retries() -> u32 {
+    3
+}
+
+fn default_list_page_size() -> u32 {
+    500
+}
+
+fn default_max_parallel_requests() -> u32 {
+    8
+}
+
+fn default_job_output_lines() -> u32 {
+    10000
+}
+
+fn default_cert_warn_days() -> u32 {
+    14
+}
+
+fn default_proxy_port() -> u16 {
+    8001
+}
+
+fn default_history_size() -> usize {
+    1000
+}
+
+fn default_history_ignore_dups() -> bool {
+    true
+}
+
+fn default_history_ignore_space() -> bool {
+    true
+}
+
+fn default_strip_color_on_redirect() -> bool {
+    true
+}
+
+fn default_syntax_highlight() -> bool {
+    true
+}
+
+fn default_theme() -> String {
+    "dark".to_string()
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ClickConfig {
     pub namespace: Option<String>,
     pub context: Option<String>,
     pub editor: Option<String>,
     pub terminal: Option<String>,
+    /// command the ssh command runs to connect to nodes. Unset means `ssh`
+    pub ssh_command: Option<String>,
+    /// container runtime on the nodes (docker, containerd, or cri-o), used by nsenter to find
+    /// container pids. Unset means detect it from the node
+    pub container_runtime: Option<String>,
+    /// command to page long output through. Unset means $PAGER, or `less -R`
+    pub pager: Option<String>,
+    /// file of click commands to run when the repl starts. Unset means ~/.config/click/clickrc
+    pub rc_file: Option<String>,
+    /// file to keep command history in. Unset means click.history in the config dir
+    pub history_file: Option<String>,
     #[serde(default = "EditMode::default")]
     pub editmode: EditMode,
     #[serde(default = "CompletionType::default")]
     pub completiontype: CompletionType,
     #[serde(default = "Vec::new")]
     pub aliases: Vec<Alias>,
+    /// map of alias -> real context name
+    #[serde(default = "BTreeMap::new")]
+    pub context_aliases: BTreeMap<String, String>,
     #[serde(default = "default_range_sep")]
     pub range_separator: String,
+    /// what the prompt looks like, with {context}, {namespace}, {selection}, {selection_count},
+    /// and {selected} ("[N objects selected]", or nothing if there's no selection) filled in
+    #[serde(default = "default_prompt_format")]
+    pub prompt_format: String,
+
+    /// most commands to keep in the history
+    #[serde(default = "default_history_size")]
+    pub history_size: usize,
+    /// don't add a command to the history if it's the same as the one before it
+    #[serde(default = "default_history_ignore_dups")]
+    pub history_ignore_dups: bool,
+    /// don't add commands that start with a space to the history
+    #[serde(default = "default_history_ignore_space")]
+    pub history_ignore_space: bool,
 
     #[serde(default = "default_connect_timeout")]
     pub connect_timeout_secs: u32,
     #[serde(default = "default_read_timeout")]
     pub read_timeout_secs: u32,
+    /// how long kubectl waits for the server, passed to it as --request-timeout (a go duration
+    /// like 30s). Unset means kubectl's default, which is to wait as long as it takes
+    pub default_timeout: Option<String>,
 
     #[serde(default = "default_describe_include_events")]
     pub describe_include_events: bool,
+
+    /// remember the last namespace used in each context, and restore it when switching back
+    #[serde(default = "default_remember_namespace")]
+    pub remember_namespace: bool,
+    /// map of context name -> last namespace used in that context
+    #[serde(default = "BTreeMap::new")]
+    pub context_namespaces: BTreeMap<String, String>,
+
+    /// refresh expired auth-provider tokens, saving them back to the kubeconfig like kubectl does
+    #[serde(default = "default_auth_refresh")]
+    pub auth_refresh: bool,
+
+    /// how many times to retry reads from the api server that fail with a connection error,
+    /// timeout, 429, or 5xx
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+
+    /// how many items to ask the api server for at a time when listing, 0 to get everything in
+    /// one request
+    #[serde(default = "default_list_page_size")]
+    pub list_page_size: u32,
+
+    /// ask for lists as json or protobuf
+    #[serde(default = "WireFormat::default")]
+    pub wire_format: WireFormat,
+
+    /// most requests to send at once when listing from several namespaces or contexts
+    #[serde(default = "default_max_parallel_requests")]
+    pub max_parallel_requests: u32,
+
+    /// most lines of output to keep from each command run in the background, dropping the oldest
+    #[serde(default = "default_job_output_lines")]
+    pub job_output_lines: u32,
+
+    /// print each api request made to stderr with how long it took (1), and its headers too (2).
+    /// this is for debugging a session, so like impersonate it's never saved
+    #[serde(skip)]
+    pub verbose: u32,
+
+    /// who to impersonate in requests. this is only for the session, so it's never saved
+    #[serde(skip)]
+    pub impersonate: Option<Impersonation>,
+
+    /// warn when switching to a context whose client certificate expires within this many days
+    #[serde(default = "default_cert_warn_days")]
+    pub cert_warn_days: u32,
+
+    /// local port the proxy command listens on, unless --port is given
+    #[serde(default = "default_proxy_port")]
+    pub proxy_port: u16,
+
+    /// strip colors from output that's redirected to a file or piped to a command (when color
+    /// is Auto)
+    #[serde(default = "default_strip_color_on_redirect")]
+    pub strip_color_on_redirect: bool,
+
+    #[serde(default = "ColorMode::default")]
+    pub color: ColorMode,
+
+    /// color keys and values in json and yaml output
+    #[serde(default = "default_syntax_highlight")]
+    pub syntax_highlight: bool,
+
+    /// name of the built-in color theme to use
+    #[serde(default = "default_theme")]
+    pub theme: String,
+    /// map of theme role (i.e. "bad-status") -> style to use instead of the theme's
+    #[serde(default = "BTreeMap::new")]
+    pub theme_overrides: BTreeMap<String, String>,
 }
 
 impl Default for ClickConfig {
@@ -137,13 +367,41 @@ impl Default for ClickConfig {
             context: None,
             editor: None,
             terminal: None,
+            ssh_command: None,
+            container_runtime: None,
+            default_timeout: None,
+            pager: None,
+            rc_file: None,
+            history_file: None,
             editmode: EditMode::default(),
             completiontype: CompletionType::default(),
             aliases: vec![],
+            context_aliases: BTreeMap::new(),
             range_separator: default_range_sep(),
+            prompt_format: default_prompt_format(),
+            history_size: default_history_size(),
+            history_ignore_dups: default_history_ignore_dups(),
+            history_ignore_space: default_history_ignore_space(),
             connect_timeout_secs: default_connect_timeout(),
             read_timeout_secs: default_read_timeout(),
             describe_include_events: true,
+            remember_namespace: default_remember_namespace(),
+            context_namespaces: BTreeMap::new(),
+            auth_refresh: default_auth_refresh(),
+            retries: default_retries(),
+            list_page_size: default_list_page_size(),
+            wire_format: WireFormat::default(),
+            max_parallel_requests: default_max_parallel_requests(),
+            job_output_lines: default_job_output_lines(),
+            verbose: 0,
+            impersonate: None,
+            cert_warn_days: default_cert_warn_days(),
+            proxy_port: default_proxy_port(),
+            strip_color_on_redirect: default_strip_color_on_redirect(),
+            color: ColorMode::default(),
+            syntax_highlight: default_syntax_highlight(),
+            theme: default_theme(),
+            theme_overrides: BTreeMap::new(),
         }
     }
 }
@@ -173,17 +431,20 @@ impl ClickConfig {
             }
             CompletionType::List => config.completion_type(rustyconfig::CompletionType::List),
         };
-        config.build()
+        // leading spaces are dealt with when adding to the history, since they're trimmed off
+        config
+            .max_history_size(self.history_size)
+            .history_ignore_dups(self.history_ignore_dups)
+            .build()
     }
 
     /// Save this config to specified path.  It's safe to call this from multiple running instances
     /// of Click, since we use an AtomicFile
     pub fn save_to_file(&self, path: &str) -> Result<(), ClickError> {
         let af = AtomicFile::new(path, AllowOverwrite);
-        af.write(|mut f| serde_yaml::to_writer(&mut f, &self))
-            .map_err(|e| {
-                ClickError::ConfigFileError(format!("Failed to write config file: {}", e))
-            })?;
+        af.write(|f| serde_yaml::to_writer(f, self)).map_err(|e| {
+            ClickError::ConfigFileError(format!("Failed to write config file: {}", e))
+        })?;
         Ok(())
     }
 }
@@ -197,11 +458,22 @@ namespace: ns
 context: ctx
 editor: emacs
 terminal: alacritty -e
+ssh_command: ssh -o ServerAliveInterval=30
+container_runtime: containerd
+default_timeout: 1m30s
+pager: less -FRX
+rc_file: ~/click/rc
 editmode: Vi
 completiontype: List
+history_file: /tmp/click.hist
+history_size: 5000
+history_ignore_space: false
+prompt_format: '{context}/{namespace}> '
 aliases:
   - alias: pn
-    expanded: pods --sort node";
+    expanded: pods --sort node
+context_aliases:
+  prod: prod-us-east-1";
 
     #[test]
     fn test_parse_config() {
@@ -212,13 +484,30 @@ aliases:
         assert_eq!(config.context, Some("ctx".to_owned()));
         assert_eq!(config.editor, Some("emacs".to_owned()));
         assert_eq!(config.terminal, Some("alacritty -e".to_owned()));
+        assert_eq!(
+            config.ssh_command,
+            Some("ssh -o ServerAliveInterval=30".to_owned())
+        );
+        assert_eq!(config.container_runtime, Some("containerd".to_owned()));
+        assert_eq!(config.default_timeout, Some("1m30s".to_owned()));
+        assert_eq!(config.pager, Some("less -FRX".to_owned()));
+        assert_eq!(config.rc_file, Some("~/click/rc".to_owned()));
         assert_eq!(config.editmode, EditMode::Vi);
         assert_eq!(config.completiontype, CompletionType::List);
+        assert_eq!(config.history_file, Some("/tmp/click.hist".to_owned()));
+        assert_eq!(config.history_size, 5000);
+        assert!(config.history_ignore_dups);
+        assert!(!config.history_ignore_space);
         assert_eq!(config.aliases.len(), 1);
         assert_eq!(config.range_separator, default_range_sep());
-        let a = config.aliases.get(0).unwrap();
+        assert_eq!(config.prompt_format, "{context}/{namespace}> ");
+        let a = config.aliases.first().unwrap();
         assert_eq!(a.alias, "pn");
         assert_eq!(a.expanded, "pods --sort node");
+        assert_eq!(
+            config.context_aliases.get("prod").map(|s| s.as_str()),
+            Some("prod-us-east-1")
+        );
         assert_eq!(config.connect_timeout_secs, default_connect_timeout());
         assert_eq!(config.read_timeout_secs, default_read_timeout());
     }
@@ -227,11 +516,35 @@ aliases:
     fn test_default_config() {
         let config = ClickConfig::default();
         assert_eq!(config.namespace, None);
+        assert_eq!(config.rc_file, None);
+        assert_eq!(config.history_file, None);
+        assert_eq!(config.history_size, 1000);
+        assert!(config.history_ignore_dups);
+        assert!(config.history_ignore_space);
         assert_eq!(config.editmode, EditMode::Emacs);
         assert_eq!(config.completiontype, CompletionType::Circular);
         assert_eq!(config.read_timeout_secs, default_read_timeout());
         assert_eq!(config.connect_timeout_secs, default_connect_timeout());
+        assert_eq!(config.default_timeout, None);
         assert_eq!(config.range_separator, default_range_sep());
+        assert_eq!(config.prompt_format, default_prompt_format());
+        assert!(config.remember_namespace);
+        assert!(config.context_namespaces.is_empty());
+        assert!(config.auth_refresh);
+        assert_eq!(config.retries, 3);
+        assert_eq!(config.list_page_size, 500);
+        assert_eq!(config.wire_format, WireFormat::Json);
+        assert_eq!(config.max_parallel_requests, 8);
+        assert_eq!(config.job_output_lines, 10000);
+        assert_eq!(config.verbose, 0);
+        assert_eq!(config.impersonate, None);
+        assert_eq!(config.cert_warn_days, default_cert_warn_days());
+        assert_eq!(config.proxy_port, 8001);
+        assert!(config.strip_color_on_redirect);
+        assert!(config.syntax_highlight);
+        assert_eq!(config.color, ColorMode::Auto);
+        assert_eq!(config.theme, "dark");
+        assert!(config.theme_overrides.is_empty());
     }
 
     #[test]
@@ -250,6 +563,7 @@ aliases:
             rustyline::config::CompletionType::List
         );
         assert_eq!(rlconf.edit_mode(), rustyline::config::EditMode::Vi);
+        assert_eq!(rlconf.max_history_size(), 5000);
         let rlconf = ClickConfig::default().get_rustyline_conf();
         assert_eq!(
             rlconf.completion_type(),
@@ -257,4 +571,24 @@ aliases:
         );
         assert_eq!(rlconf.edit_mode(), rustyline::config::EditMode::Emacs);
     }
+
+    #[test]
+    fn test_parse_verbose() {
+        assert_eq!(parse_verbose("on"), Some(1));
+        assert_eq!(parse_verbose("off"), Some(0));
+        assert_eq!(parse_verbose("true"), Some(1));
+        assert_eq!(parse_verbose("2"), Some(2));
+        assert_eq!(parse_verbose("3"), None);
+        assert_eq!(parse_verbose("loud"), None);
+    }
+
+    #[test]
+    fn test_is_go_duration() {
+        for ok in ["30s", "2m", "1m30s", "1.5h", "250ms", "0", "10us"] {
+            assert!(is_go_duration(ok), "{} should be a duration", ok);
+        }
+        for bad in ["", "30", "s", "1d", "-1s", "1m 30s", "ten seconds"] {
+            assert!(!is_go_duration(bad), "{} shouldn't be a duration", bad);
+        }
+    }
 }
@@ -15,15 +15,20 @@
 //! Code to represent the data found in .kube/config files after it's deserialized, validated, and
 //! so on.  Data in here is what gets passed around to the rest of Click.
 
+use chrono::{DateTime, Duration, Utc};
+use yasna::Tag;
+
 use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::convert::From;
 use std::env;
 use std::fs::File;
-use std::io::{BufReader, Read};
+use std::io::{self, BufReader, Read};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 
 //use crate::certs::{get_cert, get_cert_from_pem, get_key_from_str, get_private_key};
-use super::kubefile::{AuthProvider, ExecProvider};
+use super::kubefile::{AuthProvider, AuthSource, ExecProvider, LoadedMtime};
 use crate::config::ClickConfig;
 use crate::error::{ClickErrNo, ClickError};
 use crate::k8s::UserAuth as K8SUserAuth;
@@ -105,12 +110,24 @@ impl From<super::kubefile::UserConf> for UserConf {
 #[derive(Debug)]
 pub struct Config {
     pub source_file: String,
+    pub source_paths: Vec<String>,
+    /// current-context from the first file that sets it
+    pub current_context: Option<String>,
+    // modification times of source_paths when we loaded them
+    mtimes: Vec<LoadedMtime>,
     pub clusters: HashMap<String, ClusterConf>,
     pub contexts: BTreeMap<String, super::kubefile::ContextConf>,
     pub users: HashMap<String, UserConf>,
 }
 
 // some utility functions
+fn modified_times(paths: &[String]) -> Vec<Option<SystemTime>> {
+    paths
+        .iter()
+        .map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+        .collect()
+}
+
 fn get_full_path(path: String) -> Result<String, ClickError> {
     if path.is_empty() {
         return Err(ClickError::ConfigFileError(
@@ -132,17 +149,101 @@ fn get_full_path(path: String) -> Result<String, ClickError> {
     }
 }
 
+/// When a client certificate expires, relative to the warning window
+#[derive(Debug, PartialEq)]
+pub enum CertExpiry {
+    Valid(DateTime<Utc>),
+    ExpiresSoon(DateTime<Utc>),
+    Expired(DateTime<Utc>),
+}
+
+/// Read a UTCTime or GeneralizedTime, which are the two ways x509 can encode validity times
+fn read_x509_time(reader: yasna::BERReader) -> yasna::ASN1Result<DateTime<Utc>> {
+    if reader.lookahead_tag()? == yasna::tags::TAG_UTCTIME {
+        reader.read_utctime().map(|t| *t.datetime())
+    } else {
+        reader.read_generalized_time().map(|t| *t.datetime())
+    }
+}
+
+/// Get the notAfter time out of a pem encoded x509 certificate
+fn cert_not_after(cert_pem: &[u8]) -> Result<DateTime<Utc>, ClickError> {
+    let pem = pem::parse(cert_pem)?;
+    yasna::parse_der(&pem.contents, |reader| {
+        reader.read_sequence(|reader| {
+            let not_after = reader.next().read_sequence(|reader| {
+                // version is optional, and explicitly tagged
+                reader.read_optional(|reader| {
+                    reader.read_tagged(Tag::context(0), |reader| reader.read_der())
+                })?;
+                reader.next().read_der()?; // serial number
+                reader.next().read_der()?; // signature algorithm
+                reader.next().read_der()?; // issuer
+                let not_after = reader.next().read_sequence(|reader| {
+                    reader.next().read_der()?; // not before
+                    read_x509_time(reader.next())
+                })?;
+                // skip the subject, key, and any extensions
+                while reader.read_optional(|reader| reader.read_der())?.is_some() {}
+                Ok(not_after)
+            })?;
+            reader.next().read_der()?; // signature algorithm
+            reader.next().read_der()?; // signature
+            Ok(not_after)
+        })
+    })
+    .map_err(|e| ClickError::ParseErr(format!("Could not parse client certificate: {}", e)))
+}
+
 impl Config {
+    /// Load and merge the specified config files. This follows kubectl's semantics: files that
+    /// don't exist are skipped, and if a cluster, context, or user is defined in more than one
+    /// file, the first definition wins.
     pub fn from_files(paths: &[String]) -> Result<Config, ClickError> {
-        let iconfs = paths
-            .iter()
-            .map(|config_path| super::kubefile::Config::from_file(config_path))
-            .collect::<Result<Vec<_>, _>>()?;
+        let mtimes = modified_times(paths);
+        let mut iconfs = vec![];
+        let mut first_err = None;
+        for config_path in paths.iter().filter(|p| !p.is_empty()) {
+            match super::kubefile::Config::from_file(config_path) {
+                Ok(iconf) => iconfs.push((config_path, iconf)),
+                Err(ClickError::Io(ref e)) if e.kind() == io::ErrorKind::NotFound => {
+                    if first_err.is_none() {
+                        first_err = Some(ClickError::ConfigFileError(format!(
+                            "Config file {} not found",
+                            config_path
+                        )));
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        if iconfs.is_empty() {
+            return Err(first_err.unwrap_or_else(|| {
+                ClickError::ConfigFileError("No kubernetes config files specified".to_string())
+            }));
+        }
+        Config::from_kubefiles(paths, mtimes, iconfs)
+    }
+
+    /// Build a config from parsed kubeconfig files, each with the path it came from. paths is all
+    /// the paths that were asked for, including any that were skipped.
+    fn from_kubefiles(
+        paths: &[String],
+        mtimes: Vec<Option<SystemTime>>,
+        iconfs: Vec<(&String, super::kubefile::Config)>,
+    ) -> Result<Config, ClickError> {
+        let mtimes: Vec<LoadedMtime> = mtimes
+            .into_iter()
+            .map(|mtime| Arc::new(Mutex::new(mtime)))
+            .collect();
 
         // copy over clusters
         let mut cluster_map = HashMap::new();
-        for iconf in iconfs.iter() {
+        for (_, iconf) in iconfs.iter() {
             for cluster in iconf.clusters.iter() {
+                if cluster_map.contains_key(&cluster.name) {
+                    continue; // first definition wins
+                }
                 // make sure we've specified one of:
                 //  - a cert file
                 //  - cert data
@@ -209,17 +310,35 @@ impl Config {
 
         // copy over contexts
         let mut context_map = BTreeMap::new();
-        for iconf in iconfs.iter() {
+        for (_, iconf) in iconfs.iter() {
             for context in iconf.contexts.iter() {
-                context_map.insert(context.name.clone(), context.conf.clone());
+                context_map
+                    .entry(context.name.clone())
+                    .or_insert_with(|| context.conf.clone());
             }
         }
 
         // copy over users
         let mut user_map = HashMap::new();
-        for iconf in iconfs.iter() {
+        for (path, iconf) in iconfs.iter() {
             for user in iconf.users.iter() {
-                user_map.insert(user.name.clone(), user.conf.clone().into());
+                user_map.entry(user.name.clone()).or_insert_with(|| {
+                    let mut conf = user.conf.clone();
+                    // remember where this came from, so refreshed tokens can be saved
+                    if let Some(provider) = conf.auth_provider.as_mut() {
+                        let mtime = paths
+                            .iter()
+                            .position(|p| p == *path)
+                            .map(|i| mtimes[i].clone())
+                            .unwrap_or_default();
+                        provider.source = Some(AuthSource {
+                            path: path.to_string(),
+                            user: user.name.clone(),
+                            mtime,
+                        });
+                    }
+                    conf.into()
+                });
             }
         }
 
@@ -230,12 +349,96 @@ impl Config {
 
         Ok(Config {
             source_file: sources,
+            source_paths: paths.to_vec(),
+            current_context: iconfs
+                .iter()
+                .filter_map(|(_, iconf)| iconf.current_context.clone())
+                .find(|ctx| !ctx.is_empty()),
+            mtimes,
             clusters: cluster_map,
             contexts: context_map,
             users: user_map,
         })
     }
 
+    /// Add the clusters, users, and contexts from the kubeconfig added (as yaml) that aren't
+    /// already in this config, as though they were loaded from source_path. Returns the names of
+    /// the contexts added.
+    pub fn merge(
+        &mut self,
+        source_path: &str,
+        added: &serde_yaml::Value,
+    ) -> Result<Vec<String>, ClickError> {
+        let iconf: super::kubefile::Config = serde_yaml::from_value(added.clone())?;
+        let source_path = source_path.to_string();
+        let other = Config::from_kubefiles(&[], vec![], vec![(&source_path, iconf)])?;
+        for (name, cluster) in other.clusters.into_iter() {
+            self.clusters.entry(name).or_insert(cluster);
+        }
+        for (name, user) in other.users.into_iter() {
+            self.users.entry(name).or_insert(user);
+        }
+        let mut added = vec![];
+        for (name, context) in other.contexts.into_iter() {
+            if !self.contexts.contains_key(&name) {
+                self.contexts.insert(name.clone(), context);
+                added.push(name);
+            }
+        }
+        Ok(added)
+    }
+
+    /// Check if any of the files this config was loaded from have changed since it was loaded
+    pub fn is_stale(&self) -> bool {
+        let loaded: Vec<Option<SystemTime>> =
+            self.mtimes.iter().map(|m| *m.lock().unwrap()).collect();
+        modified_times(&self.source_paths) != loaded
+    }
+
+    /// Read the files this config was loaded from again, returning a fresh config
+    pub fn reload(&self) -> Result<Config, ClickError> {
+        Config::from_files(&self.source_paths)
+    }
+
+    /// Check when the client certificate for the user of context_name expires, if it has one.
+    /// Certificates expiring within warn_days are reported as `ExpiresSoon`.
+    pub fn cert_expiry(
+        &self,
+        context_name: &str,
+        warn_days: u32,
+    ) -> Result<Option<CertExpiry>, ClickError> {
+        let user = self
+            .contexts
+            .get(context_name)
+            .and_then(|context| self.users.get(&context.user))
+            .ok_or(ClickError::Kube(ClickErrNo::InvalidUser))?;
+        let mut cert_pem = None;
+        for user_auth in user.auths.iter() {
+            match user_auth {
+                UserAuth::KeyCertData(cert_data, _) => {
+                    cert_pem = Some(base64::decode(cert_data)?);
+                }
+                UserAuth::KeyCertPath(cert_path, _) => {
+                    cert_pem = Some(std::fs::read(get_full_path(cert_path.clone())?)?);
+                }
+                _ => {}
+            }
+        }
+        cert_pem
+            .map(|pem| {
+                let not_after = cert_not_after(&pem)?;
+                let now = Utc::now();
+                Ok(if not_after < now {
+                    CertExpiry::Expired(not_after)
+                } else if not_after < now + Duration::days(warn_days.into()) {
+                    CertExpiry::ExpiresSoon(not_after)
+                } else {
+                    CertExpiry::Valid(not_after)
+                })
+            })
+            .transpose()
+    }
+
     pub fn get_context(
         &self,
         context_name: &str,
@@ -297,14 +500,20 @@ impl Config {
         }
 
         k8suser.map(|user| {
-            crate::k8s::Context::new(
+            let context = crate::k8s::Context::new(
                 context_name,
                 endpoint,
                 ca_cert,
                 Some(user),
                 click_conf.connect_timeout_secs,
                 click_conf.read_timeout_secs,
-            )
+            );
+            context.set_auth_refresh(click_conf.auth_refresh);
+            context.set_retries(click_conf.retries);
+            context.set_verbose(click_conf.verbose);
+            context.set_impersonate(click_conf.impersonate.clone());
+            context.set_wire_format(click_conf.wire_format);
+            context
         })
     }
 }
@@ -321,9 +530,135 @@ pub mod tests {
     pub fn get_test_config() -> Config {
         Config {
             source_file: "/tmp/test.conf".to_string(),
+            source_paths: vec!["/tmp/test.conf".to_string()],
+            current_context: None,
+            mtimes: vec![LoadedMtime::default()],
             clusters: HashMap::new(),
             contexts: BTreeMap::new(),
             users: HashMap::new(),
         }
     }
+
+    static FIRST_CONFIG: &str = r"apiVersion: v1
+clusters:
+- cluster:
+    insecure-skip-tls-verify: true
+    server: https://first.test
+  name: shared
+contexts:
+- context:
+    cluster: shared
+    user: shareduser
+  name: sharedctx
+current-context: sharedctx
+users:
+- name: shareduser
+  user:
+    token: FIRST
+";
+
+    static SECOND_CONFIG: &str = r"apiVersion: v1
+clusters:
+- cluster:
+    insecure-skip-tls-verify: true
+    server: https://second.test
+  name: shared
+- cluster:
+    insecure-skip-tls-verify: true
+    server: https://other.test
+  name: other
+contexts:
+- context:
+    cluster: other
+    user: shareduser
+  name: sharedctx
+- context:
+    cluster: other
+    user: shareduser
+  name: otherctx
+current-context: otherctx
+users:
+- name: shareduser
+  user:
+    token: SECOND
+";
+
+    #[test]
+    fn test_merge_first_wins() {
+        let dir = tempdir::TempDir::new("click_kube_test").unwrap();
+        let first = dir.path().join("first");
+        let second = dir.path().join("second");
+        std::fs::write(&first, FIRST_CONFIG).unwrap();
+        std::fs::write(&second, SECOND_CONFIG).unwrap();
+        let paths = vec![
+            first.to_str().unwrap().to_string(),
+            dir.path().join("missing").to_str().unwrap().to_string(),
+            second.to_str().unwrap().to_string(),
+        ];
+        let config = Config::from_files(&paths).unwrap();
+        assert_eq!(config.current_context.as_deref(), Some("sharedctx"));
+        assert_eq!(config.contexts.len(), 2);
+        assert_eq!(config.contexts["sharedctx"].cluster, "shared");
+        assert_eq!(config.contexts["otherctx"].cluster, "other");
+        assert_eq!(config.clusters["shared"].server, "https://first.test");
+        assert_eq!(config.clusters["other"].server, "https://other.test");
+        assert!(matches!(
+            config.users["shareduser"].auths.as_slice(),
+            [UserAuth::Token(token)] if token == "FIRST"
+        ));
+
+        // no files that exist is an error
+        assert!(Config::from_files(&paths[1..2]).is_err());
+    }
+
+    static TEST_CERT: &str = "-----BEGIN CERTIFICATE-----
+MIIBfjCCASWgAwIBAgIUY0uGd9ly60zZlJLg2tNxfa0buk8wCgYIKoZIzj0EAwIw
+FTETMBEGA1UEAwwKY2xpY2stdGVzdDAeFw0yNjEwMTQxNDIxMTZaFw0zNjEwMTEx
+NDIxMTZaMBUxEzARBgNVBAMMCmNsaWNrLXRlc3QwWTATBgcqhkjOPQIBBggqhkjO
+PQMBBwNCAARBWHremz7S5qoHoCa/GifcpltA2JBW9Tkw14dY8xIHYKVDs+xZk2BZ
+zcAyVpj00JonToz/ibx7wV8peAbFTFlmo1MwUTAdBgNVHQ4EFgQUmQsTNyGqGtJC
+Spo+tqChceb809cwHwYDVR0jBBgwFoAUmQsTNyGqGtJCSpo+tqChceb809cwDwYD
+VR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNHADBEAiAScR5b0HROtPifW0Oou0+/
+twURfoDcMXd6rDGdgU914gIgYniukaFqeLQcuF/s2MTmz+KHnQDWpmM4l1v+PBDo
+RIQ=
+-----END CERTIFICATE-----
+";
+
+    #[test]
+    fn test_cert_expiry() {
+        use chrono::TimeZone;
+
+        let not_after = Utc.ymd(2036, 10, 11).and_hms(14, 21, 16);
+        assert_eq!(cert_not_after(TEST_CERT.as_bytes()).unwrap(), not_after);
+        assert!(cert_not_after(b"not a cert").is_err());
+
+        let mut config = get_test_config();
+        config.contexts.insert(
+            "certctx".to_string(),
+            crate::config::ContextConf {
+                cluster: "cluster".to_string(),
+                user: "certuser".to_string(),
+                namespace: None,
+            },
+        );
+        config.users.insert(
+            "certuser".to_string(),
+            UserConf {
+                auths: vec![UserAuth::KeyCertData(
+                    base64::encode(TEST_CERT),
+                    "KEY".to_string(),
+                )],
+            },
+        );
+        assert_eq!(
+            config.cert_expiry("certctx", 14).unwrap(),
+            Some(CertExpiry::Valid(not_after))
+        );
+        let days_left = (not_after - Utc::now()).num_days() as u32;
+        assert_eq!(
+            config.cert_expiry("certctx", days_left + 1).unwrap(),
+            Some(CertExpiry::ExpiresSoon(not_after))
+        );
+        assert!(config.cert_expiry("nosuchctx", 14).is_err());
+    }
 }
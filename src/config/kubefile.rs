@@ -18,8 +18,14 @@ use chrono::{DateTime, Local, TimeZone};
 use serde_json::{self, Value};
 
 use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
-use std::io::Read;
+use std::io::{IsTerminal, Read};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use atomicwrites::{AllowOverwrite, AtomicFile};
 
 use crate::error::ClickError;
 
@@ -35,6 +41,8 @@ pub struct Config {
     pub clusters: Vec<Cluster>,
     pub contexts: Vec<Context>,
     pub users: Vec<User>,
+    #[serde(rename = "current-context", default)]
+    pub current_context: Option<String>,
 }
 
 impl Config {
@@ -113,7 +121,7 @@ pub struct UserConf {
     pub exec: Option<ExecConfig>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct ContextConf {
     pub cluster: String,
     pub namespace: Option<String>,
@@ -127,14 +135,56 @@ pub struct AuthProvider {
     pub token: RefCell<Option<String>>,
     pub expiry: RefCell<Option<DateTime<Local>>>,
     pub config: AuthProviderConfig,
+    /// where this provider was defined, so refreshed tokens can be written back
+    #[serde(skip)]
+    pub source: Option<AuthSource>,
 }
 
-#[derive(PartialEq, Debug, Deserialize, Clone)]
+/// The modification time of a kubeconfig when it was loaded, shared between the loaded config and
+/// the auth providers that can write to the file
+pub type LoadedMtime = Arc<Mutex<Option<SystemTime>>>;
+
+/// The kubeconfig file and user an auth provider came from
+#[derive(Debug, Clone)]
+pub struct AuthSource {
+    pub path: String,
+    pub user: String,
+    /// updated when a token is written back, so the write isn't taken as the file changing
+    pub mtime: LoadedMtime,
+}
+
+impl PartialEq for AuthSource {
+    fn eq(&self, other: &AuthSource) -> bool {
+        self.path == other.path && self.user == other.user
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[derive(PartialEq, Debug, Default, Deserialize, Clone)]
 pub struct AuthProviderConfig {
     #[serde(rename = "access-token")]
     pub access_token: Option<String>,
     expiry: Option<String>,
 
+    // fields used by the oidc provider
+    #[serde(rename = "id-token")]
+    id_token: Option<String>,
+    #[serde(rename = "refresh-token")]
+    refresh_token: Option<String>,
+    #[serde(rename = "client-id")]
+    client_id: Option<String>,
+    #[serde(rename = "client-secret")]
+    client_secret: Option<String>,
+    #[serde(rename = "idp-issuer-url")]
+    idp_issuer_url: Option<String>,
+    #[serde(rename = "idp-certificate-authority")]
+    idp_certificate_authority: Option<String>,
+    #[serde(rename = "idp-certificate-authority-data")]
+    idp_certificate_authority_data: Option<String>,
+
     #[serde(rename = "cmd-args")]
     cmd_args: Option<String>,
     #[serde(rename = "cmd-path")]
@@ -145,12 +195,257 @@ pub struct AuthProviderConfig {
     token_key: Option<String>,
 }
 
+/// Get the expiry out of the exp claim of a JWT, without validating it. That's the server's job,
+/// we just want to know when to refresh.
+fn jwt_expiry(token: &str) -> Option<DateTime<Local>> {
+    let payload = token.split('.').nth(1)?;
+    let decoded = base64::decode_config(payload, base64::URL_SAFE_NO_PAD).ok()?;
+    let claims: Value = serde_json::from_slice(&decoded).ok()?;
+    claims
+        .get("exp")
+        .and_then(|exp| exp.as_i64())
+        .map(|exp| Local.timestamp(exp, 0))
+}
+
+/// Write updated auth-provider config values for user back to the kubeconfig at path, like kubectl
+/// does. Note that this doesn't preserve comments or formatting in the file.
+fn persist_auth_provider_config(
+    source: &AuthSource,
+    updates: &[(&str, &str)],
+) -> Result<(), ClickError> {
+    // write to the file a symlink points at, rather than replacing the symlink
+    let path = std::fs::canonicalize(&source.path)?;
+    let unchanged = modified_time(&path) == *source.mtime.lock().unwrap();
+    let mut config: serde_yaml::Value = serde_yaml::from_reader(File::open(&path)?)?;
+    let provider_config = config
+        .get_mut("users")
+        .and_then(|users| users.as_sequence_mut())
+        .and_then(|users| {
+            users
+                .iter_mut()
+                .find(|user| user.get("name").and_then(|n| n.as_str()) == Some(&source.user))
+        })
+        .and_then(|user| user.get_mut("user"))
+        .and_then(|user| user.get_mut("auth-provider"))
+        .and_then(|provider| provider.get_mut("config"))
+        .and_then(|config| config.as_mapping_mut())
+        .ok_or_else(|| {
+            ClickError::ConfigFileError(format!(
+                "Could not find auth-provider config for user {} in {}",
+                source.user, source.path
+            ))
+        })?;
+    for (key, val) in updates.iter() {
+        provider_config.insert((*key).into(), (*val).into());
+    }
+    let af = AtomicFile::new(&path, AllowOverwrite);
+    af.write(|f| serde_yaml::to_writer(f, &config))
+        .map_err(|e| ClickError::ConfigFileError(format!("Failed to write kubeconfig: {}", e)))?;
+    // if nothing else changed the file since it was loaded, it's still up to date
+    if unchanged {
+        *source.mtime.lock().unwrap() = modified_time(&path);
+    }
+    Ok(())
+}
+
+/// What merging one kubeconfig into another added
+#[derive(Debug, Default, PartialEq)]
+pub struct MergeResult {
+    /// the names of the contexts that were added
+    pub contexts: Vec<String>,
+    /// clusters and users renamed because the name was taken by a different one, as (kind, old
+    /// name, new name)
+    pub renamed: Vec<(&'static str, String, String)>,
+    /// a kubeconfig with just the clusters, users, and contexts that were added
+    pub added: serde_yaml::Value,
+}
+
+/// The entries with a name in the clusters, users, or contexts list of a kubeconfig
+fn named_entries<'a>(
+    config: &'a serde_yaml::Value,
+    section: &str,
+) -> impl Iterator<Item = (&'a str, &'a serde_yaml::Value)> {
+    config
+        .get(section)
+        .and_then(|entries| entries.as_sequence())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| Some((entry.get("name")?.as_str()?, entry)))
+}
+
+/// Add the clusters or users in other that contexts refer to through ref_key to added_entries,
+/// renaming any whose name is taken by a different definition in base, or is in taken (names
+/// defined in another file). Definitions identical to one in base are skipped. Returns a map of
+/// old name to new name.
+fn merge_section(
+    base: &serde_yaml::Value,
+    other: &serde_yaml::Value,
+    section: &'static str,
+    referenced: &[&str],
+    taken: &HashSet<String>,
+    result: &mut MergeResult,
+    added_entries: &mut Vec<serde_yaml::Value>,
+) -> HashMap<String, String> {
+    let kind = section.trim_end_matches('s');
+    let base_entries: HashMap<&str, &serde_yaml::Value> = named_entries(base, section).collect();
+    let mut names = HashMap::new();
+    for (name, entry) in named_entries(other, section) {
+        if !referenced.contains(&name) || names.contains_key(name) {
+            continue;
+        }
+        let new_name = match base_entries.get(name) {
+            Some(existing) if *existing == entry => {
+                names.insert(name.to_string(), name.to_string());
+                continue;
+            }
+            None if !taken.contains(name) => name.to_string(),
+            _ => {
+                let in_use = |n: &str| {
+                    base_entries.contains_key(n)
+                        || taken.contains(n)
+                        || names.values().any(|v: &String| v == n)
+                        || named_entries(other, section).any(|(other, _)| other == n)
+                };
+                let new_name = (2..)
+                    .map(|i| format!("{}-{}", name, i))
+                    .find(|n| !in_use(n))
+                    .unwrap(); // safe, the range is unbounded
+                result
+                    .renamed
+                    .push((kind, name.to_string(), new_name.clone()));
+                new_name
+            }
+        };
+        let mut entry = entry.clone();
+        entry["name"] = new_name.clone().into();
+        added_entries.push(entry);
+        names.insert(name.to_string(), new_name);
+    }
+    names
+}
+
+/// Check that config is a valid kubeconfig, returning the names of its contexts
+pub fn context_names(config: &serde_yaml::Value) -> Result<Vec<String>, ClickError> {
+    let config: Config = serde_yaml::from_value(config.clone())?;
+    Ok(config.contexts.into_iter().map(|c| c.name).collect())
+}
+
+/// Merge the clusters, users, and contexts of the kubeconfig other into base, both as yaml.
+/// context_names gives the name to use for contexts in other, or None to leave one out, and
+/// contexts not in it keep their name. Clusters and users that clash with a different one in base,
+/// or with a name in taken_clusters or taken_users (defined in other files), are renamed, and the
+/// contexts using them updated. base's current-context isn't changed.
+pub fn merge_configs(
+    base: &mut serde_yaml::Value,
+    other: &serde_yaml::Value,
+    context_names: &HashMap<String, Option<String>>,
+    taken_clusters: &HashSet<String>,
+    taken_users: &HashSet<String>,
+) -> Result<MergeResult, ClickError> {
+    if !base.is_mapping() {
+        if base.is_null() {
+            *base = serde_yaml::Value::Mapping(serde_yaml::Mapping::new());
+            base["apiVersion"] = "v1".into();
+            base["kind"] = "Config".into();
+        } else {
+            return Err(ClickError::ConfigFileError(
+                "Kubeconfig to merge into is not a mapping".to_string(),
+            ));
+        }
+    }
+
+    let contexts: Vec<(String, &serde_yaml::Value)> = named_entries(other, "contexts")
+        .filter_map(|(name, entry)| match context_names.get(name) {
+            Some(Some(new_name)) => Some((new_name.clone(), entry)),
+            Some(None) => None,
+            None => Some((name.to_string(), entry)),
+        })
+        .collect();
+    let referenced = |key: &str| -> Vec<&str> {
+        contexts
+            .iter()
+            .filter_map(|(_, entry)| entry.get("context")?.get(key)?.as_str())
+            .collect()
+    };
+
+    let mut result = MergeResult::default();
+    let mut clusters = vec![];
+    let cluster_names = merge_section(
+        base,
+        other,
+        "clusters",
+        &referenced("cluster"),
+        taken_clusters,
+        &mut result,
+        &mut clusters,
+    );
+    let mut users = vec![];
+    let user_names = merge_section(
+        base,
+        other,
+        "users",
+        &referenced("user"),
+        taken_users,
+        &mut result,
+        &mut users,
+    );
+    let contexts: Vec<serde_yaml::Value> = contexts
+        .into_iter()
+        .map(|(name, entry)| {
+            let mut entry = entry.clone();
+            entry["name"] = name.clone().into();
+            for (key, names) in [("cluster", &cluster_names), ("user", &user_names)].iter() {
+                let renamed = entry["context"][*key]
+                    .as_str()
+                    .and_then(|old| names.get(old));
+                if let Some(renamed) = renamed {
+                    entry["context"][*key] = renamed.clone().into();
+                }
+            }
+            result.contexts.push(name);
+            entry
+        })
+        .collect();
+
+    let mut added = serde_yaml::Mapping::new();
+    for (section, entries) in [
+        ("clusters", clusters),
+        ("users", users),
+        ("contexts", contexts),
+    ]
+    .iter()
+    {
+        if !base[*section].is_sequence() {
+            base[*section] = serde_yaml::Value::Sequence(vec![]);
+        }
+        if let Some(seq) = base[*section].as_sequence_mut() {
+            seq.extend(entries.iter().cloned());
+        }
+        added.insert(
+            (*section).into(),
+            serde_yaml::Value::Sequence(entries.clone()),
+        );
+    }
+    result.added = serde_yaml::Value::Mapping(added);
+    Ok(result)
+}
+
 impl AuthProvider {
+    fn is_oidc(&self) -> bool {
+        self.name == "oidc"
+    }
+
     // Copy the token and expiry out of the config into the refcells
     pub fn copy_up(&self) {
         let mut token = self.token.borrow_mut();
-        *token = self.config.access_token.clone();
         let mut expiry = self.expiry.borrow_mut();
+        if self.is_oidc() {
+            // oidc sends the id token, and its expiry is only in the token itself
+            *token = self.config.id_token.clone();
+            *expiry = token.as_deref().and_then(jwt_expiry);
+            return;
+        }
+        *token = self.config.access_token.clone();
         if let Some(expiry_str) = &self.config.expiry {
             match AuthProvider::parse_expiry(expiry_str.as_str()) {
                 Ok(e) => *expiry = Some(e),
@@ -252,18 +547,126 @@ impl AuthProvider {
         }
     }
 
-    fn update_token(&self, token: &mut Option<String>, expiry: &mut Option<DateTime<Local>>) {
+    /// Use the refresh token to get a new id token from the issuer
+    fn refresh_oidc(&self) -> Result<(String, Option<String>), ClickError> {
+        let missing = |what: &str| {
+            ClickError::ConfigFileError(format!(
+                "oidc auth-provider has no {}, can't refresh",
+                what
+            ))
+        };
+        let issuer = self
+            .config
+            .idp_issuer_url
+            .as_deref()
+            .ok_or_else(|| missing("idp-issuer-url"))?;
+        let refresh_token = self
+            .config
+            .refresh_token
+            .as_deref()
+            .ok_or_else(|| missing("refresh-token"))?;
+        let client_id = self
+            .config
+            .client_id
+            .as_deref()
+            .ok_or_else(|| missing("client-id"))?;
+
+        let mut builder = reqwest::blocking::Client::builder();
+        let ca_pem = match (
+            &self.config.idp_certificate_authority_data,
+            &self.config.idp_certificate_authority,
+        ) {
+            (Some(data), _) => Some(base64::decode(data)?),
+            (None, Some(path)) => Some(std::fs::read(path)?),
+            (None, None) => None,
+        };
+        if let Some(pem) = ca_pem {
+            builder = builder.add_root_certificate(reqwest::Certificate::from_pem(&pem)?);
+        }
+        let client = builder.build()?;
+
+        let discovery: Value = client
+            .get(format!(
+                "{}/.well-known/openid-configuration",
+                issuer.trim_end_matches('/')
+            ))
+            .send()?
+            .error_for_status()?
+            .json()?;
+        let token_endpoint = discovery
+            .get("token_endpoint")
+            .and_then(|e| e.as_str())
+            .ok_or_else(|| {
+                ClickError::ParseErr(format!("Issuer {} has no token_endpoint", issuer))
+            })?;
+
+        let mut form = vec![
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+            ("client_id", client_id),
+        ];
+        if let Some(secret) = self.config.client_secret.as_deref() {
+            form.push(("client_secret", secret));
+        }
+        let resp: Value = client
+            .post(token_endpoint)
+            .form(&form)
+            .send()?
+            .error_for_status()?
+            .json()?;
+        let id_token = resp
+            .get("id_token")
+            .and_then(|t| t.as_str())
+            .ok_or_else(|| ClickError::ParseErr("Token response had no id_token".to_string()))?;
+        // issuers may or may not rotate the refresh token
+        let new_refresh = resp
+            .get("refresh_token")
+            .and_then(|t| t.as_str())
+            .map(|t| t.to_string());
+        Ok((id_token.to_string(), new_refresh))
+    }
+
+    fn update_oidc_token(&self, token: &mut Option<String>, expiry: &mut Option<DateTime<Local>>) {
+        match self.refresh_oidc() {
+            Ok((id_token, refresh_token)) => {
+                *expiry = jwt_expiry(&id_token);
+                if let Some(source) = self.source.as_ref() {
+                    let mut updates = vec![("id-token", id_token.as_str())];
+                    if let Some(refresh) = refresh_token.as_deref() {
+                        updates.push(("refresh-token", refresh));
+                    }
+                    if let Err(e) = persist_auth_provider_config(source, &updates) {
+                        eprintln!("Refreshed token, but couldn't save it: {}", e);
+                    }
+                }
+                *token = Some(id_token);
+            }
+            Err(e) => {
+                eprintln!("Failed to refresh oidc token: {}", e);
+            }
+        }
+    }
+
+    fn update_token(
+        &self,
+        token: &mut Option<String>,
+        expiry: &mut Option<DateTime<Local>>,
+        persist: bool,
+    ) {
         match self.config.cmd_path {
             Some(ref conf_cmd) => {
-                let args = self
+                let args: Vec<&str> = self
                     .config
                     .cmd_args
                     .as_ref()
                     .map(|argstr| argstr.split_whitespace().collect())
-                    .unwrap_or_else(Vec::new);
+                    .unwrap_or_default();
                 match ductcmd(conf_cmd, &args).read() {
                     Ok(output) => {
                         self.parse_output_and_update(output.as_str(), token, expiry);
+                        if persist {
+                            self.persist_access_token(token, expiry);
+                        }
                     }
                     Err(e) => {
                         println!("Failed to run update command: {}", e);
@@ -276,17 +679,50 @@ impl AuthProvider {
         }
     }
 
-    /// Checks that we have a valid token, and if not, attempts to update it based on the config
-    pub fn ensure_token(&self) -> Option<String> {
+    /// Save a token we got from cmd-path back to the kubeconfig, so future sessions (and kubectl)
+    /// don't need to run the command again
+    fn persist_access_token(&self, token: &Option<String>, expiry: &Option<DateTime<Local>>) {
+        if let (Some(source), Some(token), Some(expiry)) = (self.source.as_ref(), token, expiry) {
+            let expiry = expiry.to_rfc3339();
+            let updates = [
+                ("access-token", token.as_str()),
+                ("expiry", expiry.as_str()),
+            ];
+            if let Err(e) = persist_auth_provider_config(source, &updates) {
+                eprintln!("Refreshed token, but couldn't save it: {}", e);
+            }
+        }
+    }
+
+    /// Checks that we have a valid token, and if not, attempts to update it based on the config.
+    /// If refresh is false, expired oidc tokens are not refreshed, and nothing is written back to
+    /// the kubeconfig.
+    pub fn ensure_token(&self, refresh: bool) -> Option<String> {
         let mut token = self.token.borrow_mut();
+        if self.is_oidc() && !refresh {
+            return token.clone();
+        }
         if token.is_none() || self.is_expired() {
             // update
             let mut expiry = self.expiry.borrow_mut();
-            *token = None;
-            self.update_token(&mut token, &mut expiry)
+            if self.is_oidc() {
+                self.update_oidc_token(&mut token, &mut expiry);
+            } else {
+                *token = None;
+                self.update_token(&mut token, &mut expiry, refresh);
+            }
         }
         token.clone()
     }
+
+    /// Forget the current token, so the next call to ensure_token fetches a new one. Used when the
+    /// server rejects a token before its expiry
+    pub fn invalidate(&self) {
+        *self.expiry.borrow_mut() = None;
+        if !self.is_oidc() {
+            *self.token.borrow_mut() = None;
+        }
+    }
 }
 
 #[derive(PartialEq, Debug, Deserialize, Clone)]
@@ -304,6 +740,9 @@ pub struct ExecConfig {
     env: Option<Vec<NameValue>>,
     #[serde(rename = "apiVersion")]
     api_version: Option<String>,
+    /// one of Never, IfAvailable, or Always. Defaults to IfAvailable
+    #[serde(rename = "interactiveMode")]
+    interactive_mode: Option<String>,
 }
 
 /// Result of executing above. Schema defined here:
@@ -329,6 +768,25 @@ struct ExecResultStatus {
 }
 
 impl ExecConfig {
+    /// If the plugin may interact with the user. If so, its stdin and stderr are connected to ours
+    /// so it can prompt, otherwise stderr is captured so we can report it on failure.
+    fn is_interactive(&self) -> bool {
+        self.interactive_mode.as_deref() != Some("Never") && std::io::stdin().is_terminal()
+    }
+
+    /// The ExecCredential plugins expect in the KUBERNETES_EXEC_INFO env var
+    fn exec_info(&self, interactive: bool) -> String {
+        serde_json::json!({
+            "apiVersion": self
+                .api_version
+                .as_deref()
+                .unwrap_or("client.authentication.k8s.io/v1beta1"),
+            "kind": "ExecCredential",
+            "spec": { "interactive": interactive },
+        })
+        .to_string()
+    }
+
     fn exec(&self) -> Result<ExecResult, ClickError> {
         match self.command {
             Some(ref command) => {
@@ -338,14 +796,49 @@ impl ExecConfig {
                     let args: Vec<String> = vec![];
                     ductcmd(command, args)
                 };
-                let expr = if let Some(env) = &self.env {
-                    let env = env.iter().map(|nv| (&nv.name, &nv.value));
-                    expr.full_env(env)
-                } else {
+                // the specified env is in addition to our own, not a replacement for it
+                let mut expr = expr;
+                if let Some(env) = &self.env {
+                    for nv in env.iter() {
+                        expr = expr.env(&nv.name, &nv.value);
+                    }
+                }
+                let interactive = self.is_interactive();
+                let expr = expr
+                    .env("KUBERNETES_EXEC_INFO", self.exec_info(interactive))
+                    .stdout_capture()
+                    .unchecked();
+                let expr = if interactive {
                     expr
+                } else {
+                    expr.stderr_capture()
                 };
 
-                serde_json::from_reader(expr.reader()?).map_err(ClickError::from)
+                let output = expr.run().map_err(|e| {
+                    ClickError::CommandError(format!(
+                        "Could not run exec credential plugin '{}': {}",
+                        command, e
+                    ))
+                })?;
+                if !output.status.success() {
+                    let stderr = String::from_utf8_lossy(&output.stderr);
+                    return Err(ClickError::CommandError(format!(
+                        "Exec credential plugin '{}' failed ({}): {}",
+                        command,
+                        output.status,
+                        if stderr.trim().is_empty() {
+                            "see its output above"
+                        } else {
+                            stderr.trim()
+                        }
+                    )));
+                }
+                serde_json::from_slice(&output.stdout).map_err(|e| {
+                    ClickError::CommandError(format!(
+                        "Exec credential plugin '{}' returned invalid output: {}",
+                        command, e
+                    ))
+                })
             }
             None => Err(ClickError::ConfigFileError(
                 "No command specified in exec config".to_string(),
@@ -393,55 +886,59 @@ impl ExecProvider {
         }
     }
 
-    fn update_auth(&self) {
-        match self.config.exec() {
-            Ok(result) => match result.status {
-                Some(status) => {
-                    if status.expiration.is_none() {
-                        eprintln!(
-                                "exec command returned no expiration. future commands will refetch token."
-                            );
-                    }
-                    if let Some(token) = status.token {
-                        *self.auth.borrow_mut() = Some(ExecAuth::Token(token));
-                    } else if let Some(cert_data) = status.client_certificate_data {
-                        let key_data = match status.client_key_data {
-                            Some(data) => data,
-                            None => {
-                                eprintln!("exec returned certificate but no key, can't auth.");
-                                return;
-                            }
-                        };
-                        *self.auth.borrow_mut() = Some(ExecAuth::ClientCertKey {
-                            cert_data,
-                            key_data,
-                        });
-                    }
-                    *self.expiry.borrow_mut() = status.expiration;
-                }
-                None => {
-                    eprintln!("No status block returned by exec, can't update auth");
-                }
-            },
-            Err(e) => {
-                println!("Error running specified exec command: {}", e);
-            }
+    fn update_auth(&self) -> Result<(), ClickError> {
+        let status = self.config.exec()?.status.ok_or_else(|| {
+            ClickError::CommandError(
+                "No status block returned by exec credential plugin, can't update auth".to_string(),
+            )
+        })?;
+        if status.expiration.is_none() {
+            eprintln!("exec command returned no expiration. future commands will refetch token.");
         }
+        if let Some(token) = status.token {
+            *self.auth.borrow_mut() = Some(ExecAuth::Token(token));
+        } else if let Some(cert_data) = status.client_certificate_data {
+            let key_data = status.client_key_data.ok_or_else(|| {
+                ClickError::CommandError(
+                    "Exec credential plugin returned a certificate but no key, can't auth"
+                        .to_string(),
+                )
+            })?;
+            *self.auth.borrow_mut() = Some(ExecAuth::ClientCertKey {
+                cert_data,
+                key_data,
+            });
+        } else {
+            return Err(ClickError::CommandError(
+                "Exec credential plugin returned neither a token nor a client certificate"
+                    .to_string(),
+            ));
+        }
+        *self.expiry.borrow_mut() = status.expiration;
+        Ok(())
     }
 
-    pub fn get_auth(&self) -> (ExecAuth, bool) {
+    /// Get the auth to use, re-running the plugin if what we have cached has expired. The bool
+    /// returned indicates if the plugin was re-run.
+    pub fn get_auth(&self) -> Result<(ExecAuth, bool), ClickError> {
         let was_expired = if self.is_expired() {
-            self.update_auth();
+            self.update_auth()?;
             true
         } else {
             false
         };
         // TODO: Fix kube.rs to be able to handle an option here
         match &*self.auth.borrow() {
-            Some(auth) => (auth.clone(), was_expired),
-            None => (ExecAuth::default(), was_expired),
+            Some(auth) => Ok((auth.clone(), was_expired)),
+            None => Ok((ExecAuth::default(), was_expired)),
         }
     }
+
+    /// Forget any cached auth, so the next call to get_auth runs the plugin again. Used when the
+    /// server rejects our credentials before they were meant to expire.
+    pub fn invalidate(&self) {
+        *self.expiry.borrow_mut() = None;
+    }
 }
 
 #[cfg(test)]
@@ -519,50 +1016,47 @@ users:
 
     fn contains_cluster(config: &Config, cluster: Cluster) -> bool {
         for c in config.clusters.iter() {
-            if c.name == cluster.name {
-                if c.conf.cert == cluster.conf.cert
-                    && c.conf.cert_data == cluster.conf.cert_data
-                    && c.conf.skip_tls == cluster.conf.skip_tls
-                    && c.conf.server == cluster.conf.server
-                {
-                    return true;
-                }
+            if c.name == cluster.name
+                && c.conf.cert == cluster.conf.cert
+                && c.conf.cert_data == cluster.conf.cert_data
+                && c.conf.skip_tls == cluster.conf.skip_tls
+                && c.conf.server == cluster.conf.server
+            {
+                return true;
             }
         }
-        return false;
+        false
     }
 
     fn contains_context(config: &Config, context: Context) -> bool {
         for c in config.contexts.iter() {
-            if c.name == context.name {
-                if c.conf.cluster == context.conf.cluster
-                    && c.conf.user == context.conf.user
-                    && c.conf.namespace == context.conf.namespace
-                {
-                    return true;
-                }
+            if c.name == context.name
+                && c.conf.cluster == context.conf.cluster
+                && c.conf.user == context.conf.user
+                && c.conf.namespace == context.conf.namespace
+            {
+                return true;
             }
         }
-        return false;
+        false
     }
 
     fn contains_user(config: &Config, user: User) -> bool {
         for u in config.users.iter() {
-            if u.name == user.name {
-                if u.conf.token == user.conf.token
-                    && u.conf.client_cert == user.conf.client_cert
-                    && u.conf.client_key == user.conf.client_key
-                    && u.conf.client_cert_data == user.conf.client_cert_data
-                    && u.conf.client_key_data == user.conf.client_key_data
-                    && u.conf.username == user.conf.username
-                    && u.conf.password == user.conf.password
-                    && u.conf.auth_provider == user.conf.auth_provider
-                {
-                    return true;
-                }
+            if u.name == user.name
+                && u.conf.token == user.conf.token
+                && u.conf.client_cert == user.conf.client_cert
+                && u.conf.client_key == user.conf.client_key
+                && u.conf.client_cert_data == user.conf.client_cert_data
+                && u.conf.client_key_data == user.conf.client_key_data
+                && u.conf.username == user.conf.username
+                && u.conf.password == user.conf.password
+                && u.conf.auth_provider == user.conf.auth_provider
+            {
+                return true;
             }
         }
-        return false;
+        false
     }
 
     #[test]
@@ -573,6 +1067,7 @@ users:
             assert!(config.is_ok()); // will always fail
         }
         let config = config.unwrap();
+        assert_eq!(config.current_context.as_deref(), Some("c1ctx"));
         assert!(contains_cluster(
             &config,
             Cluster {
@@ -747,7 +1242,9 @@ users:
                 cmd_path: None,
                 expiry_key: Some("{.credential.token_expiry}".to_string()),
                 token_key: Some("{.credential.access_token}".to_string()),
+                ..Default::default()
             },
+            source: None,
         };
         {
             // scope for token/expiry borrow
@@ -801,7 +1298,9 @@ users:
                 cmd_path: None,
                 expiry_key: None,
                 token_key: None,
+                ..Default::default()
             },
+            source: None,
         };
         ap.copy_up();
         assert_eq!(ap.token, RefCell::new(Some("CTOKEN".to_string())));
@@ -848,7 +1347,9 @@ users:
                 cmd_path: None,
                 expiry_key: None,
                 token_key: None,
+                ..Default::default()
             },
+            source: None,
         };
         assert!(ap.is_expired());
 
@@ -863,7 +1364,9 @@ users:
                 cmd_path: None,
                 expiry_key: None,
                 token_key: None,
+                ..Default::default()
             },
+            source: None,
         };
         assert!(!ap.is_expired());
     }
@@ -879,9 +1382,16 @@ users:
             config: exec_config.unwrap().conf.exec.as_ref().unwrap().clone(),
         };
 
-        let (auth, was_expired) = provider.get_auth();
+        let (auth, was_expired) = provider.get_auth().unwrap();
         assert!(was_expired);
         assert_eq!(auth, ExecAuth::Token("testtoken".to_string()));
+
+        // cached until it expires or is invalidated
+        let (_, was_expired) = provider.get_auth().unwrap();
+        assert!(!was_expired);
+        provider.invalidate();
+        let (_, was_expired) = provider.get_auth().unwrap();
+        assert!(was_expired);
     }
 
     #[test]
@@ -895,8 +1405,198 @@ users:
             config: exec_config.unwrap().conf.exec.as_ref().unwrap().clone(),
         };
 
-        let (auth, was_expired) = provider.get_auth();
+        let (auth, was_expired) = provider.get_auth().unwrap();
         assert!(was_expired);
         assert_eq!(auth, ExecAuth::Token("testtoken".to_string()));
     }
+
+    #[test]
+    fn exec_failure() {
+        let config = Config::from_reader(TEST_CONFIG.as_bytes()).unwrap();
+        let mut exec_config = config
+            .users
+            .iter()
+            .find(|u| u.name == "exec")
+            .unwrap()
+            .conf
+            .exec
+            .clone()
+            .unwrap();
+        exec_config.command = Some("failing-plugin".to_string());
+        let provider = ExecProvider::new(exec_config);
+        let err = provider.get_auth().unwrap_err().to_string();
+        assert!(err.contains("failing-plugin"));
+        assert!(err.contains("token expired, please log in again"));
+    }
+
+    fn test_jwt(exp: i64) -> String {
+        let claims = base64::encode_config(
+            format!(r#"{{"iss":"https://issuer","exp":{}}}"#, exp),
+            base64::URL_SAFE_NO_PAD,
+        );
+        format!("eyJhbGciOiJSUzI1NiJ9.{}.c2lnbmF0dXJl", claims)
+    }
+
+    #[test]
+    fn oidc_copy_up() {
+        let ap = AuthProvider {
+            name: "oidc".to_string(),
+            token: RefCell::new(None),
+            expiry: RefCell::new(None),
+            config: AuthProviderConfig {
+                id_token: Some(test_jwt(1_600_000_000)),
+                ..Default::default()
+            },
+            source: None,
+        };
+        ap.copy_up();
+        assert_eq!(*ap.token.borrow(), Some(test_jwt(1_600_000_000)));
+        assert_eq!(*ap.expiry.borrow(), Some(Local.timestamp(1_600_000_000, 0)));
+        assert!(ap.is_expired());
+        // with refresh off, we keep using the token we have
+        assert_eq!(ap.ensure_token(false), Some(test_jwt(1_600_000_000)));
+
+        assert_eq!(jwt_expiry("not-a-jwt"), None);
+        assert_eq!(jwt_expiry("a.bm90IGpzb24.c"), None);
+    }
+
+    #[test]
+    fn persist_auth_provider() {
+        let dir = tempdir::TempDir::new("click_kubefile_test").unwrap();
+        let path = dir.path().join("config");
+        std::fs::write(&path, TEST_CONFIG).unwrap();
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink(&path, &link).unwrap();
+        let source = AuthSource {
+            path: link.to_str().unwrap().to_string(),
+            user: "gke".to_string(),
+            mtime: Arc::new(Mutex::new(modified_time(&path))),
+        };
+        // make sure the write changes the mtime
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        persist_auth_provider_config(&source, &[("access-token", "NEWTOKEN")]).unwrap();
+        // the symlink is written through, and the write doesn't make the file look changed
+        assert!(std::fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(*source.mtime.lock().unwrap(), modified_time(&path));
+
+        let config = Config::from_file(path.to_str().unwrap()).unwrap();
+        let gke = config.users.iter().find(|u| u.name == "gke").unwrap();
+        let provider = gke.conf.auth_provider.as_ref().unwrap();
+        assert_eq!(provider.config.access_token.as_deref(), Some("NEWTOKEN"));
+        assert_eq!(provider.config.cmd_path.as_deref(), Some("/bin/gcloud"));
+
+        let missing = AuthSource {
+            path: source.path,
+            user: "nosuchuser".to_string(),
+            mtime: source.mtime,
+        };
+        assert!(persist_auth_provider_config(&missing, &[("access-token", "X")]).is_err());
+    }
+
+    static MERGE_CONFIG: &str = r"apiVersion: v1
+clusters:
+- cluster:
+    insecure-skip-tls-verify: true
+    server: https://cluster1.test:443
+  name: cluster1
+- cluster:
+    server: https://new.test
+  name: newcluster
+- cluster:
+    server: https://unused.test
+  name: unused
+contexts:
+- context:
+    cluster: cluster1
+    user: token
+  name: c1ctx
+- context:
+    cluster: newcluster
+    user: token
+  name: newctx
+- context:
+    cluster: unused
+    user: token
+  name: skipped
+current-context: newctx
+users:
+- name: token
+  user:
+    token: NEWTOKEN
+";
+
+    #[test]
+    fn merge_configs_renames() {
+        let mut base: serde_yaml::Value = serde_yaml::from_str(TEST_CONFIG).unwrap();
+        let other: serde_yaml::Value = serde_yaml::from_str(MERGE_CONFIG).unwrap();
+        assert_eq!(
+            context_names(&other).unwrap(),
+            vec!["c1ctx", "newctx", "skipped"]
+        );
+        let mut context_names = HashMap::new();
+        context_names.insert("c1ctx".to_string(), Some("c1ctx-new".to_string()));
+        context_names.insert("skipped".to_string(), None);
+        let taken = ["newcluster".to_string()].iter().cloned().collect();
+        let result =
+            merge_configs(&mut base, &other, &context_names, &taken, &HashSet::new()).unwrap();
+
+        assert_eq!(result.contexts, vec!["c1ctx-new", "newctx"]);
+        assert_eq!(
+            result.renamed,
+            vec![
+                ("cluster", "cluster1".to_string(), "cluster1-2".to_string()),
+                (
+                    "cluster",
+                    "newcluster".to_string(),
+                    "newcluster-2".to_string()
+                ),
+                ("user", "token".to_string(), "token-2".to_string()),
+            ]
+        );
+
+        let merged = Config::from_reader(serde_yaml::to_string(&base).unwrap().as_bytes()).unwrap();
+        assert_eq!(merged.current_context.as_deref(), Some("c1ctx"));
+        assert_eq!(merged.clusters.len(), 6);
+        assert!(!merged.clusters.iter().any(|c| c.name == "unused"));
+        let ctx = merged
+            .contexts
+            .iter()
+            .find(|c| c.name == "c1ctx-new")
+            .unwrap();
+        assert_eq!(ctx.conf.cluster, "cluster1-2");
+        assert_eq!(ctx.conf.user, "token-2");
+        let user = merged.users.iter().find(|u| u.name == "token-2").unwrap();
+        assert_eq!(user.conf.token.as_deref(), Some("NEWTOKEN"));
+
+        let added: Config = serde_yaml::from_value(result.added).unwrap();
+        assert_eq!(added.contexts.len(), 2);
+        assert_eq!(added.clusters.len(), 2);
+        assert_eq!(added.users.len(), 1);
+    }
+
+    #[test]
+    fn merge_configs_identical_and_empty() {
+        let other: serde_yaml::Value = serde_yaml::from_str(TEST_CONFIG).unwrap();
+        let mut base = other.clone();
+        let mut context_names = HashMap::new();
+        context_names.insert("c1ctx".to_string(), Some("copy".to_string()));
+        context_names.insert("c2ctx".to_string(), None);
+        let none = HashSet::new();
+        let result = merge_configs(&mut base, &other, &context_names, &none, &none).unwrap();
+        // the cluster and user are the same, so only the context is new
+        assert_eq!(result.contexts, vec!["copy"]);
+        assert!(result.renamed.is_empty());
+        let merged = Config::from_reader(serde_yaml::to_string(&base).unwrap().as_bytes()).unwrap();
+        assert_eq!(merged.contexts.len(), 3);
+        assert_eq!(merged.clusters.len(), 4);
+
+        let mut empty = serde_yaml::Value::Null;
+        let result = merge_configs(&mut empty, &other, &HashMap::new(), &none, &none).unwrap();
+        assert_eq!(result.contexts, vec!["c1ctx", "c2ctx"]);
+        assert_eq!(empty["kind"].as_str(), Some("Config"));
+        assert_eq!(empty["users"].as_sequence().unwrap().len(), 1);
+    }
 }
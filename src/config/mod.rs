@@ -18,13 +18,18 @@ mod kubefile;
 
 pub use self::click::Alias;
 pub use self::click::ClickConfig;
+pub use self::click::ColorMode;
 pub use self::click::CompletionType;
 pub use self::click::EditMode;
+pub use self::click::Impersonation;
+pub use self::click::WireFormat;
+pub use self::click::{default_prompt_format, is_go_duration, parse_verbose, MAX_VERBOSE};
 
 #[cfg(test)]
 pub use self::kube::tests::get_test_config;
-pub use self::kube::Config;
+pub use self::kube::{CertExpiry, Config};
 
 pub use self::kubefile::AuthProvider;
 pub use self::kubefile::ContextConf;
+pub use self::kubefile::{context_names, merge_configs};
 pub use self::kubefile::{ExecAuth, ExecProvider};
@@ -54,6 +54,7 @@ pub fn get_api_group_resources(
 #[derive(Debug)]
 pub enum GetAPIGroupResourcesResponse {
     Ok(APIResourceList),
+    #[allow(dead_code)] // only read via Debug
     Other(Result<Option<serde_json::Value>, serde_json::Error>),
 }
 
@@ -85,9 +86,10 @@ impl Response for GetAPIGroupResourcesResponse {
 }
 
 #[allow(clippy::type_complexity)] // type from k8s_openapi
-pub fn read_namespaced_resource(
+/// Read a custom resource. Pass None for namespace for cluster scoped resources
+pub fn read_resource(
     name: &str,
-    namespace: &str,
+    namespace: Option<&str>,
     _type: &str,
     group_version: &str,
 ) -> Result<
@@ -97,10 +99,13 @@ pub fn read_namespaced_resource(
     ),
     RequestError,
 > {
-    let url = format!(
-        "/apis/{}/namespaces/{}/{}/{}",
-        group_version, namespace, _type, name
-    );
+    let url = match namespace {
+        Some(namespace) => format!(
+            "/apis/{}/namespaces/{}/{}/{}",
+            group_version, namespace, _type, name
+        ),
+        None => format!("/apis/{}/{}/{}", group_version, _type, name),
+    };
     let request = Request::get(url);
     let body = vec![];
     match request.body(body) {
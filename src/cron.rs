@@ -0,0 +1,218 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parsing of cron schedules as used by Kubernetes CronJobs, so we can figure out when they will
+//! next run
+
+use chrono::{DateTime, Datelike, Duration, TimeZone, Timelike, Utc};
+
+use crate::error::ClickError;
+
+const MONTH_NAMES: &[&str] = &[
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const DAY_NAMES: &[&str] = &["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// How many steps to take looking for the next run before giving up. Each step advances by at
+/// least a minute, and usually by much more, so this covers any schedule that can ever run.
+const MAX_STEPS: usize = 100_000;
+
+/// A parsed cron schedule. Each field is a bitmask of the values that match.
+#[derive(Debug, PartialEq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    // cron matches on either day field if both are restricted, so we need to know which are '*'
+    dom_star: bool,
+    dow_star: bool,
+}
+
+fn parse_value(s: &str, names: Option<(&[&str], u32)>) -> Result<u32, ClickError> {
+    if let Some((names, offset)) = names {
+        let lower = s.to_lowercase();
+        if let Some(idx) = names.iter().position(|n| *n == lower) {
+            return Ok(idx as u32 + offset);
+        }
+    }
+    s.parse::<u32>()
+        .map_err(|_| ClickError::ParseErr(format!("Invalid cron value: {}", s)))
+}
+
+/// parse one field of a cron expression into a bitmask. returns the mask and if the field was '*'
+fn parse_field(
+    field: &str,
+    min: u32,
+    max: u32,
+    names: Option<(&[&str], u32)>,
+) -> Result<(u64, bool), ClickError> {
+    let mut mask = 0;
+    for item in field.split(',') {
+        let (range, step) = match item.find('/') {
+            Some(idx) => {
+                let step = item[idx + 1..]
+                    .parse::<u32>()
+                    .map_err(|_| ClickError::ParseErr(format!("Invalid cron step in: {}", item)))?;
+                if step == 0 {
+                    return Err(ClickError::ParseErr(format!(
+                        "Cron step can't be zero in: {}",
+                        item
+                    )));
+                }
+                (&item[..idx], Some(step))
+            }
+            None => (item, None),
+        };
+        let (start, end) = if range == "*" || range == "?" {
+            (min, max)
+        } else if let Some(idx) = range.find('-') {
+            (
+                parse_value(&range[..idx], names)?,
+                parse_value(&range[idx + 1..], names)?,
+            )
+        } else {
+            let start = parse_value(range, names)?;
+            // "5/15" means starting at 5, every 15
+            (start, if step.is_some() { max } else { start })
+        };
+        if start < min || end > max || start > end {
+            return Err(ClickError::ParseErr(format!(
+                "Cron value out of range in: {}",
+                item
+            )));
+        }
+        let mut val = start;
+        while val <= end {
+            mask |= 1 << val;
+            val += step.unwrap_or(1);
+        }
+    }
+    Ok((mask, field == "*" || field == "?"))
+}
+
+impl CronSchedule {
+    /// Parse a standard five field cron expression, or one of the @hourly style macros
+    pub fn parse(expr: &str) -> Result<CronSchedule, ClickError> {
+        let expr = match expr.trim() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(ClickError::ParseErr(format!(
+                "Cron expression should have 5 fields: {}",
+                expr
+            )));
+        }
+        let (minutes, _) = parse_field(fields[0], 0, 59, None)?;
+        let (hours, _) = parse_field(fields[1], 0, 23, None)?;
+        let (days_of_month, dom_star) = parse_field(fields[2], 1, 31, None)?;
+        let (months, _) = parse_field(fields[3], 1, 12, Some((MONTH_NAMES, 1)))?;
+        // 7 is also sunday
+        let (mut days_of_week, dow_star) = parse_field(fields[4], 0, 7, Some((DAY_NAMES, 0)))?;
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week = (days_of_week | 1) & !(1 << 7);
+        }
+        Ok(CronSchedule {
+            minutes,
+            hours,
+            days_of_month,
+            months,
+            days_of_week,
+            dom_star,
+            dow_star,
+        })
+    }
+
+    fn day_matches(&self, time: &DateTime<Utc>) -> bool {
+        let dom = self.days_of_month & (1 << time.day()) != 0;
+        let dow = self.days_of_week & (1 << time.weekday().num_days_from_sunday()) != 0;
+        if self.dom_star || self.dow_star {
+            dom && dow
+        } else {
+            dom || dow
+        }
+    }
+
+    /// Find the first time strictly after `after` that this schedule fires, or None if it never
+    /// does (i.e. "0 0 30 2 *")
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut time = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        for _ in 0..MAX_STEPS {
+            if self.months & (1 << time.month()) == 0 {
+                let (year, month) = if time.month() == 12 {
+                    (time.year() + 1, 1)
+                } else {
+                    (time.year(), time.month() + 1)
+                };
+                time = Utc.ymd(year, month, 1).and_hms(0, 0, 0);
+            } else if !self.day_matches(&time) {
+                time = time.date().and_hms(0, 0, 0) + Duration::days(1);
+            } else if self.hours & (1 << time.hour()) == 0 {
+                time = time.with_minute(0)? + Duration::hours(1);
+            } else if self.minutes & (1 << time.minute()) == 0 {
+                time = time + Duration::minutes(1);
+            } else {
+                return Some(time);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Utc> {
+        Utc.ymd(y, mo, d).and_hms(h, mi, 0)
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!(CronSchedule::parse("*/5 * * * *").is_ok());
+        assert!(CronSchedule::parse("0 9-17 * jan-jun MON-FRI").is_ok());
+        assert!(CronSchedule::parse("@daily").is_ok());
+        assert_eq!(
+            CronSchedule::parse("0 0 * * 7").unwrap(),
+            CronSchedule::parse("0 0 * * 0").unwrap()
+        );
+        assert!(CronSchedule::parse("* * * *").is_err());
+        assert!(CronSchedule::parse("60 * * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        assert!(CronSchedule::parse("0 0 * foo *").is_err());
+    }
+
+    #[test]
+    fn test_next_after() {
+        let now = at(2021, 3, 10, 12, 7); // a wednesday
+        let next = |expr: &str| CronSchedule::parse(expr).unwrap().next_after(now);
+        assert_eq!(next("*/5 * * * *"), Some(at(2021, 3, 10, 12, 10)));
+        assert_eq!(next("7 12 * * *"), Some(at(2021, 3, 11, 12, 7)));
+        assert_eq!(next("@hourly"), Some(at(2021, 3, 10, 13, 0)));
+        assert_eq!(next("30 2 * * mon"), Some(at(2021, 3, 15, 2, 30)));
+        assert_eq!(next("0 0 1 * *"), Some(at(2021, 4, 1, 0, 0)));
+        assert_eq!(next("0 0 1 1 *"), Some(at(2022, 1, 1, 0, 0)));
+        // both day fields restricted means either can match
+        assert_eq!(next("0 0 20 * thu"), Some(at(2021, 3, 11, 0, 0)));
+        assert_eq!(next("0 0 29 2 *"), Some(at(2024, 2, 29, 0, 0)));
+        assert_eq!(next("0 0 30 2 *"), None);
+    }
+}
@@ -15,9 +15,9 @@
 //!  Utility functions for the Describe command, used to output
 //!  information for supported kubernetes object types
 
+use crate::theme::{self, Role};
 use crate::values::{val_str, val_str_opt, val_u64};
 
-use ansi_term::Colour;
 use chrono::offset::Local;
 use chrono::offset::Utc;
 use chrono::DateTime;
@@ -47,7 +47,7 @@ pub enum DescItem<'a> {
     ObjectCreated,
     CustomFunc {
         path: Option<&'a str>,
-        func: &'a (dyn Fn(&Value) -> Cow<str>),
+        func: &'a dyn Fn(&Value) -> Cow<str>,
         default: &'a str,
     },
     StaticStr(Cow<'a, str>),
@@ -220,7 +220,7 @@ pub fn describe_format_pod(v: Value) -> String {
 }
 
 /// Get volume info out of volume array
-fn get_volume_str(v: &Value) -> Cow<str> {
+fn get_volume_str(v: &Value) -> Cow<'_, str> {
     let mut buf = String::new();
     if let Some(vol_arry) = v.as_array() {
         for vol in vol_arry.iter() {
@@ -282,15 +282,15 @@ fn get_volume_str(v: &Value) -> Cow<str> {
     buf.into()
 }
 
-fn pod_phase(v: &Value) -> Cow<str> {
+fn pod_phase(v: &Value) -> Cow<'_, str> {
     let phase_str = val_str("/status/phase", v, "<No Phase>");
-    let colour = match &*phase_str {
-        "Pending" | "Unknown" => Colour::Yellow,
-        "Running" | "Succeeded" => Colour::Green,
-        "Failed" => Colour::Red,
-        _ => Colour::Yellow,
+    let role = match &*phase_str {
+        "Pending" | "Unknown" => Role::Warning,
+        "Running" | "Succeeded" => Role::GoodStatus,
+        "Failed" => Role::BadStatus,
+        _ => Role::Warning,
     };
-    colour.paint(phase_str).to_string().into()
+    theme::style(role).paint(phase_str).to_string().into()
 }
 
 /// Utility function for describe to print out value
@@ -344,7 +344,7 @@ pub fn describe_format_node(v: Value) -> String {
     describe_object(&v, fields.into_iter())
 }
 
-fn node_access_url(v: &Value) -> Cow<str> {
+fn node_access_url(v: &Value) -> Cow<'_, str> {
     match val_str_opt("/spec/providerID", v) {
         Some(provider) => {
             if provider.starts_with("aws://") {
@@ -353,9 +353,8 @@ fn node_access_url(v: &Value) -> Cow<str> {
                         addr_vec
                             .iter()
                             .find(|&aval| {
-                                aval.as_object().map_or(false, |addr| {
-                                    addr["type"].as_str().map_or(false, |t| t == "ExternalIP")
-                                })
+                                aval.as_object()
+                                    .is_some_and(|addr| addr["type"].as_str() == Some("ExternalIP"))
                             })
                             .and_then(|v| v.pointer("/address").and_then(|a| a.as_str()))
                     })
@@ -447,7 +446,7 @@ pub fn describe_format_service(v: Value, endpoint_val: Option<Value>) -> String
 }
 
 /// Get ports info out of ports array
-fn get_ports_str(v: Option<&Value>, endpoint_val: Option<Value>) -> Cow<str> {
+fn get_ports_str(v: Option<&Value>, endpoint_val: Option<Value>) -> Cow<'_, str> {
     if v.is_none() {
         return "<none>".into();
     }
@@ -592,7 +591,7 @@ pub fn describe_format_secret(v: Value) -> String {
 }
 
 /// Get container info out of container array
-fn get_container_str(v: &Value) -> Cow<str> {
+fn get_container_str(v: &Value) -> Cow<'_, str> {
     let mut buf = String::new();
     if let Some(container_array) = v.as_array() {
         for container in container_array.iter() {
@@ -612,16 +611,16 @@ fn get_container_str(v: &Value) -> Cow<str> {
 }
 
 /// Get status messages out of 'conditions' array
-fn get_message_str(v: &Value) -> Cow<str> {
+fn get_message_str(v: &Value) -> Cow<'_, str> {
     let mut buf = String::new();
     if let Some(condition_array) = v.as_array() {
         for condition in condition_array.iter() {
             let msg = val_str("/message", condition, "<No Message>");
-            let colour = match &*msg {
-                "Deployment has minimum availability." => Colour::Green,
-                _ => Colour::Yellow,
+            let role = match &*msg {
+                "Deployment has minimum availability." => Role::GoodStatus,
+                _ => Role::Warning,
             };
-            buf.push_str(format!("  Message: {}\n", colour.paint(msg).to_string()).as_str());
+            buf.push_str(format!("  Message: {}\n", theme::style(role).paint(msg)).as_str());
         }
     }
     buf.into()
@@ -787,3 +786,268 @@ pub fn describe_format_rollout(v: Value) -> String {
     ];
     describe_object(&v, fields.into_iter())
 }
+
+/// The strings in the array at path in v
+fn str_list<'a>(v: &'a Value, path: &str) -> Vec<&'a str> {
+    v.pointer(path)
+        .and_then(|list| list.as_array())
+        .map(|list| list.iter().filter_map(|s| s.as_str()).collect())
+        .unwrap_or_default()
+}
+
+/// Comma separated names of the objects in a list of references, like a service account's secrets
+fn get_ref_names_str(v: &Value) -> Cow<'_, str> {
+    let names: Vec<&str> = v
+        .as_array()
+        .map(|refs| {
+            refs.iter()
+                .filter_map(|r| r.get("name").and_then(|name| name.as_str()))
+                .collect()
+        })
+        .unwrap_or_default();
+    if names.is_empty() {
+        "<none>".into()
+    } else {
+        names.join(", ").into()
+    }
+}
+
+fn get_automount_str(v: &Value) -> Cow<'_, str> {
+    match v.as_bool() {
+        Some(true) => "true".into(),
+        Some(false) => "false".into(),
+        None => "<unset> (defaults to true)".into(),
+    }
+}
+
+/// Utility function to describe a service account
+pub fn describe_format_service_account(v: Value) -> String {
+    let fields = vec![
+        (
+            "Name:\t\t",
+            DescItem::MetadataValStr {
+                path: "/name",
+                default: "<No Name>",
+            },
+        ),
+        (
+            "Namespace:\t",
+            DescItem::MetadataValStr {
+                path: "/namespace",
+                default: "<No Name>",
+            },
+        ),
+        (
+            "Labels:\t",
+            DescItem::KeyValStr {
+                parent: "/metadata/labels",
+                secret_vals: false,
+            },
+        ),
+        (
+            "Annotations:",
+            DescItem::KeyValStr {
+                parent: "/metadata/annotations",
+                secret_vals: false,
+            },
+        ),
+        ("Created at:\t", DescItem::ObjectCreated),
+        (
+            "Secrets:\t",
+            DescItem::CustomFunc {
+                path: Some("/secrets"),
+                func: &get_ref_names_str,
+                default: "<none>",
+            },
+        ),
+        (
+            "Image Pull Secrets:\t",
+            DescItem::CustomFunc {
+                path: Some("/imagePullSecrets"),
+                func: &get_ref_names_str,
+                default: "<none>",
+            },
+        ),
+        (
+            "Automount Token:\t",
+            DescItem::CustomFunc {
+                path: Some("/automountServiceAccountToken"),
+                func: &get_automount_str,
+                default: "<unset> (defaults to true)",
+            },
+        ),
+    ];
+    describe_object(&v, fields.into_iter())
+}
+
+/// One line per rule of a role, i.e. "  get, list: pods, deployments.apps"
+fn get_rules_str(v: &Value) -> Cow<'_, str> {
+    let mut lines = vec![];
+    if let Some(rules) = v.as_array() {
+        for rule in rules.iter() {
+            let verbs = str_list(rule, "/verbs").join(", ");
+            let urls = str_list(rule, "/nonResourceURLs");
+            let targets = if urls.is_empty() {
+                let groups = str_list(rule, "/apiGroups");
+                let mut targets = vec![];
+                for resource in str_list(rule, "/resources").into_iter() {
+                    for group in groups.iter() {
+                        if group.is_empty() {
+                            targets.push(resource.to_string());
+                        } else {
+                            targets.push(format!("{}.{}", resource, group));
+                        }
+                    }
+                }
+                targets.join(", ")
+            } else {
+                urls.join(", ")
+            };
+            let mut line = format!("  {}: {}", verbs, targets);
+            let names = str_list(rule, "/resourceNames");
+            if !names.is_empty() {
+                line.push_str(&format!(" (only {})", names.join(", ")));
+            }
+            lines.push(line);
+        }
+    }
+    if lines.is_empty() {
+        "  <none>".into()
+    } else {
+        lines.join("\n").into()
+    }
+}
+
+/// Utility function to describe a role or cluster role
+pub fn describe_format_role(v: Value) -> String {
+    let mut fields = vec![(
+        "Name:\t\t",
+        DescItem::MetadataValStr {
+            path: "/name",
+            default: "<No Name>",
+        },
+    )];
+    if v.pointer("/metadata/namespace").is_some() {
+        fields.push((
+            "Namespace:\t",
+            DescItem::MetadataValStr {
+                path: "/namespace",
+                default: "<No Name>",
+            },
+        ));
+    }
+    fields.extend(vec![
+        (
+            "Labels:\t",
+            DescItem::KeyValStr {
+                parent: "/metadata/labels",
+                secret_vals: false,
+            },
+        ),
+        (
+            "Annotations:",
+            DescItem::KeyValStr {
+                parent: "/metadata/annotations",
+                secret_vals: false,
+            },
+        ),
+        ("Created at:\t", DescItem::ObjectCreated),
+        (
+            "\nRules:\n",
+            DescItem::CustomFunc {
+                path: Some("/rules"),
+                func: &get_rules_str,
+                default: "  <none>",
+            },
+        ),
+    ]);
+    describe_object(&v, fields.into_iter())
+}
+
+fn get_role_ref_str(v: &Value) -> Cow<'_, str> {
+    format!(
+        "{} {}",
+        val_str("/kind", v, "<No Kind>"),
+        val_str("/name", v, "<No Name>")
+    )
+    .into()
+}
+
+/// One line per subject of a binding. Service accounts without a namespace are in the namespace
+/// of the binding.
+fn get_subjects_str(v: &Value) -> Cow<'_, str> {
+    let binding_ns = val_str_opt("/metadata/namespace", v);
+    let mut lines = vec![];
+    if let Some(subjects) = v.get("subjects").and_then(|s| s.as_array()) {
+        for subject in subjects.iter() {
+            let kind = val_str("/kind", subject, "<No Kind>");
+            let name = val_str("/name", subject, "<No Name>");
+            let ns = val_str_opt("/namespace", subject).or_else(|| binding_ns.clone());
+            match ns {
+                Some(ns) if kind == "ServiceAccount" => {
+                    lines.push(format!("  {} {}/{}", kind, ns, name))
+                }
+                _ => lines.push(format!("  {} {}", kind, name)),
+            }
+        }
+    }
+    if lines.is_empty() {
+        "  <none>".into()
+    } else {
+        lines.join("\n").into()
+    }
+}
+
+/// Utility function to describe a role binding or cluster role binding
+pub fn describe_format_role_binding(v: Value) -> String {
+    let mut fields = vec![(
+        "Name:\t\t",
+        DescItem::MetadataValStr {
+            path: "/name",
+            default: "<No Name>",
+        },
+    )];
+    if v.pointer("/metadata/namespace").is_some() {
+        fields.push((
+            "Namespace:\t",
+            DescItem::MetadataValStr {
+                path: "/namespace",
+                default: "<No Name>",
+            },
+        ));
+    }
+    fields.extend(vec![
+        (
+            "Labels:\t",
+            DescItem::KeyValStr {
+                parent: "/metadata/labels",
+                secret_vals: false,
+            },
+        ),
+        (
+            "Annotations:",
+            DescItem::KeyValStr {
+                parent: "/metadata/annotations",
+                secret_vals: false,
+            },
+        ),
+        ("Created at:\t", DescItem::ObjectCreated),
+        (
+            "Role:\t\t",
+            DescItem::CustomFunc {
+                path: Some("/roleRef"),
+                func: &get_role_ref_str,
+                default: "<No Role>",
+            },
+        ),
+        (
+            "\nSubjects:\n",
+            DescItem::CustomFunc {
+                path: None,
+                func: &get_subjects_str,
+                default: "  <none>",
+            },
+        ),
+    ]);
+    describe_object(&v, fields.into_iter())
+}
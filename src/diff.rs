@@ -0,0 +1,363 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Figuring out what changed between two versions of an object, either as a unified diff to show
+//! to the user, or as a merge patch to send to the api server.
+
+use ansi_term::Style;
+use serde_json::{Map, Value};
+
+use crate::output::ClickWriter;
+use crate::theme::{self, Role};
+
+use std::io::Write;
+
+/// Lines of unchanged context around each change
+const CONTEXT: usize = 3;
+
+/// Metadata the server fills in, which never comes from a manifest
+const SERVER_METADATA: &[&str] = &[
+    "managedFields",
+    "resourceVersion",
+    "uid",
+    "creationTimestamp",
+    "generation",
+    "selfLink",
+];
+
+/// The annotation kubectl apply keeps the last applied manifest in
+const LAST_APPLIED: &str = "kubectl.kubernetes.io/last-applied-configuration";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Op {
+    Same,
+    Added,
+    Removed,
+}
+
+/// Diff old and new line by line
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(Op, &'a str)> {
+    // skip the common prefix and suffix, so the (quadratic) LCS only runs over the part that
+    // changed, which for an edited object is usually tiny
+    let prefix = old
+        .iter()
+        .zip(new.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    // lcs[i][j] is the length of the longest common subsequence of old_mid[i..] and new_mid[j..]
+    let (n, m) = (old_mid.len(), new_mid.len());
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old_mid[i] == new_mid[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops: Vec<(Op, &str)> = old[..prefix].iter().map(|l| (Op::Same, *l)).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old_mid[i] == new_mid[j] {
+            ops.push((Op::Same, old_mid[i]));
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push((Op::Removed, old_mid[i]));
+            i += 1;
+        } else {
+            ops.push((Op::Added, new_mid[j]));
+            j += 1;
+        }
+    }
+    ops.extend(old[old.len() - suffix..].iter().map(|l| (Op::Same, *l)));
+    ops
+}
+
+/// Build a unified diff (like `diff -u`) of old and new. Returns no lines at all if they're the
+/// same.
+pub fn unified_diff(old_label: &str, new_label: &str, old: &str, new: &str) -> Vec<String> {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let ops = diff_lines(&old_lines, &new_lines);
+    let changes: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, (op, _))| *op != Op::Same)
+        .map(|(i, _)| i)
+        .collect();
+    if changes.is_empty() {
+        return vec![];
+    }
+
+    // line number in old/new (zero based) that each op starts at
+    let mut old_pos = Vec::with_capacity(ops.len() + 1);
+    let mut new_pos = Vec::with_capacity(ops.len() + 1);
+    let (mut o, mut n) = (0, 0);
+    for (op, _) in ops.iter() {
+        old_pos.push(o);
+        new_pos.push(n);
+        if *op != Op::Added {
+            o += 1;
+        }
+        if *op != Op::Removed {
+            n += 1;
+        }
+    }
+    old_pos.push(o);
+    new_pos.push(n);
+
+    let mut out = vec![format!("--- {}", old_label), format!("+++ {}", new_label)];
+    let mut k = 0;
+    while k < changes.len() {
+        let start = changes[k].saturating_sub(CONTEXT);
+        let mut last = changes[k];
+        // changes close enough that their context would touch go in the same hunk
+        while k + 1 < changes.len() && changes[k + 1] - last - 1 <= 2 * CONTEXT {
+            k += 1;
+            last = changes[k];
+        }
+        let end = (last + CONTEXT + 1).min(ops.len());
+        let old_count = old_pos[end] - old_pos[start];
+        let new_count = new_pos[end] - new_pos[start];
+        // an empty range is given as the line before it
+        let hunk_start = |pos: usize, count: usize| if count == 0 { pos } else { pos + 1 };
+        out.push(format!(
+            "@@ -{},{} +{},{} @@",
+            hunk_start(old_pos[start], old_count),
+            old_count,
+            hunk_start(new_pos[start], new_count),
+            new_count
+        ));
+        for (op, line) in ops[start..end].iter() {
+            let mark = match op {
+                Op::Same => ' ',
+                Op::Added => '+',
+                Op::Removed => '-',
+            };
+            out.push(format!("{}{}", mark, line));
+        }
+        k += 1;
+    }
+    out
+}
+
+/// Write the lines of a diff from unified_diff, colored by the theme
+pub fn write_diff(writer: &mut ClickWriter, lines: &[String]) {
+    for line in lines.iter() {
+        let style = if line.starts_with("---") || line.starts_with("+++") {
+            Style::new().bold()
+        } else if line.starts_with("@@") {
+            theme::style(Role::Header)
+        } else if line.starts_with('+') {
+            theme::style(Role::GoodStatus)
+        } else if line.starts_with('-') {
+            theme::style(Role::BadStatus)
+        } else {
+            Style::new()
+        };
+        clickwriteln!(writer, "{}", style.paint(line.as_str()));
+    }
+}
+
+/// Build a json merge patch (RFC 7386) that turns original into edited. Fields that are gone from
+/// edited are set to null, and arrays are always replaced whole.
+pub fn merge_patch(original: &Value, edited: &Value) -> Value {
+    match (original, edited) {
+        (Value::Object(orig), Value::Object(new)) => {
+            let mut patch = Map::new();
+            for (key, val) in new.iter() {
+                match orig.get(key) {
+                    Some(o) if o == val => {}
+                    Some(o) => {
+                        patch.insert(key.clone(), merge_patch(o, val));
+                    }
+                    None => {
+                        patch.insert(key.clone(), val.clone());
+                    }
+                }
+            }
+            for key in orig.keys() {
+                if !new.contains_key(key) {
+                    patch.insert(key.clone(), Value::Null);
+                }
+            }
+            Value::Object(patch)
+        }
+        _ => edited.clone(),
+    }
+}
+
+/// Remove the fields of an object that the server manages and applying a manifest never changes,
+/// like resourceVersion, managedFields, and status, so diffing the live object against what
+/// would be applied only shows real changes
+pub fn strip_server_fields(value: &mut Value) {
+    if let Some(obj) = value.as_object_mut() {
+        obj.remove("status");
+    }
+    if let Some(meta) = value.get_mut("metadata").and_then(|m| m.as_object_mut()) {
+        for field in SERVER_METADATA.iter() {
+            meta.remove(*field);
+        }
+        let no_annotations = match meta.get_mut("annotations").and_then(|a| a.as_object_mut()) {
+            Some(annotations) => {
+                annotations.remove(LAST_APPLIED);
+                annotations.is_empty()
+            }
+            None => false,
+        };
+        if no_annotations {
+            meta.remove("annotations");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_no_diff() {
+        assert!(unified_diff("a", "b", "x\ny\n", "x\ny\n").is_empty());
+        assert!(unified_diff("a", "b", "", "").is_empty());
+    }
+
+    #[test]
+    fn test_unified_diff() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm\nn\n";
+        assert_eq!(
+            unified_diff("old", "new", old, new),
+            vec![
+                "--- old",
+                "+++ new",
+                "@@ -1,5 +1,5 @@",
+                " a",
+                "-b",
+                "+B",
+                " c",
+                " d",
+                " e",
+                "@@ -11,3 +11,4 @@",
+                " k",
+                " l",
+                " m",
+                "+n",
+            ]
+        );
+
+        // close changes share a hunk
+        let new = "a\nb\nC\nd\ne\nF\ng\nh\ni\nj\nk\nl\nm\n";
+        assert_eq!(
+            unified_diff("old", "new", old, new),
+            vec![
+                "--- old",
+                "+++ new",
+                "@@ -1,9 +1,9 @@",
+                " a",
+                " b",
+                "-c",
+                "+C",
+                " d",
+                " e",
+                "-f",
+                "+F",
+                " g",
+                " h",
+                " i",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_added_and_removed() {
+        assert_eq!(
+            unified_diff("old", "new", "", "x\ny\n"),
+            vec!["--- old", "+++ new", "@@ -0,0 +1,2 @@", "+x", "+y"]
+        );
+        assert_eq!(
+            unified_diff("old", "new", "x\ny\n", "x\n"),
+            vec!["--- old", "+++ new", "@@ -1,2 +1,1 @@", " x", "-y"]
+        );
+    }
+
+    #[test]
+    fn test_strip_server_fields() {
+        let mut live = json!({
+            "kind": "ConfigMap",
+            "metadata": {
+                "name": "x",
+                "resourceVersion": "123",
+                "uid": "abc",
+                "managedFields": [{ "manager": "kubectl" }],
+                "creationTimestamp": "2021-01-01T00:00:00Z",
+                "annotations": { LAST_APPLIED: "{}" },
+                "labels": { "a": "1" },
+            },
+            "data": { "k": "v" },
+            "status": { "phase": "Active" },
+        });
+        strip_server_fields(&mut live);
+        assert_eq!(
+            live,
+            json!({
+                "kind": "ConfigMap",
+                "metadata": { "name": "x", "labels": { "a": "1" } },
+                "data": { "k": "v" },
+            })
+        );
+
+        // other annotations are kept
+        let mut live = json!({
+            "metadata": { "annotations": { LAST_APPLIED: "{}", "note": "hi" } },
+        });
+        strip_server_fields(&mut live);
+        assert_eq!(
+            live,
+            json!({ "metadata": { "annotations": { "note": "hi" } } })
+        );
+    }
+
+    #[test]
+    fn test_merge_patch() {
+        let original = json!({
+            "metadata": { "name": "x", "labels": { "a": "1", "b": "2" } },
+            "spec": { "replicas": 1, "ports": [80] },
+        });
+        let edited = json!({
+            "metadata": { "name": "x", "labels": { "a": "1", "c": "3" } },
+            "spec": { "replicas": 3, "ports": [80, 443] },
+        });
+        assert_eq!(
+            merge_patch(&original, &edited),
+            json!({
+                "metadata": { "labels": { "b": null, "c": "3" } },
+                "spec": { "replicas": 3, "ports": [80, 443] },
+            })
+        );
+        assert_eq!(merge_patch(&original, &original), json!({}));
+    }
+}
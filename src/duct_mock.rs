@@ -22,58 +22,65 @@ use std::io::Result;
 #[derive(Clone)]
 pub struct MockExpression {
     cmd: String,
+    #[allow(dead_code)] // kept for debugging mocks
     args: Vec<String>,
 }
 
-pub struct MockReader {
-    read_from: String,
-    pos: usize,
+/// Stands in for std::process::ExitStatus
+pub struct MockStatus {
+    code: i32,
 }
 
-impl MockReader {
-    fn new(read_from: String) -> MockReader {
-        MockReader { read_from, pos: 0 }
+impl MockStatus {
+    pub fn success(&self) -> bool {
+        self.code == 0
     }
 }
 
-impl std::io::Read for MockReader {
-    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
-        let left = self.read_from.len() - self.pos;
-        if left == 0 {
-            Ok(0)
-        } else if left < buf.len() {
-            // copy remaining
-            buf[0..left].copy_from_slice(&self.read_from.as_bytes()[self.pos..]);
-            self.pos += left;
-            Ok(left)
-        } else {
-            // copy what we can
-            let lim = self.pos + buf.len();
-            buf.copy_from_slice(&self.read_from.as_bytes()[self.pos..lim]);
-            self.pos += buf.len();
-            Ok(buf.len())
-        }
+impl std::fmt::Display for MockStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "exit status: {}", self.code)
     }
 }
 
+/// Stands in for std::process::Output
+pub struct MockOutput {
+    pub status: MockStatus,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
 impl MockExpression {
-    pub fn full_env<T, U, V>(&self, _name_vals: T) -> MockExpression
+    pub fn env<T, U>(&self, _name: T, _val: U) -> MockExpression
     where
-        T: IntoIterator<Item = (U, V)>,
+        T: Into<OsString>,
         U: Into<OsString>,
-        V: Into<OsString>,
     {
         self.clone()
     }
 
+    pub fn stdout_capture(&self) -> MockExpression {
+        self.clone()
+    }
+
+    pub fn stderr_capture(&self) -> MockExpression {
+        self.clone()
+    }
+
+    pub fn unchecked(&self) -> MockExpression {
+        self.clone()
+    }
+
     pub fn read(&self) -> Result<String> {
         Ok("".to_string())
     }
 
-    pub fn reader(&self) -> Result<MockReader> {
+    pub fn run(&self) -> Result<MockOutput> {
         match self.cmd.as_str() {
-            "aws" => Ok(MockReader::new(format!(
-                r#"{{
+            "aws" => Ok(MockOutput {
+                status: MockStatus { code: 0 },
+                stdout: format!(
+                    r#"{{
                   "kind": "ExecCredential",
                   "apiVersion": "client.authentication.k8s.io/v1alpha1",
                   "spec": {{}},
@@ -82,9 +89,21 @@ impl MockExpression {
                     "token": "testtoken"
                   }}
                 }}"#,
-                (Utc::now() + chrono::Duration::hours(1)).format("%Y-%m-%dT%H:%M:%SZ")
-            ))),
-            _ => Ok(MockReader::new("not found".to_string())),
+                    (Utc::now() + chrono::Duration::hours(1)).format("%Y-%m-%dT%H:%M:%SZ")
+                )
+                .into_bytes(),
+                stderr: vec![],
+            }),
+            "failing-plugin" => Ok(MockOutput {
+                status: MockStatus { code: 1 },
+                stdout: vec![],
+                stderr: b"token expired, please log in again\n".to_vec(),
+            }),
+            _ => Ok(MockOutput {
+                status: MockStatus { code: 0 },
+                stdout: b"not found".to_vec(),
+                stderr: vec![],
+            }),
         }
     }
 }
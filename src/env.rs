@@ -12,23 +12,31 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::config::{self, Alias, ClickConfig, Config};
+use crate::config::{self, Alias, CertExpiry, ClickConfig, ColorMode, Config, Impersonation};
 use crate::error::ClickError;
-use crate::kobj::{KObj, ObjType};
-use crate::output::ClickWriter;
+use crate::jobs::Jobs;
+use crate::kobj::KObj;
+use crate::output::{strip_ansi, ClickWriter, OutputFormat};
+use crate::theme::{self, Role, Theme};
 
-use ansi_term::Colour::{Blue, Green, Red, Yellow};
+use ansi_term::Colour::Green;
+use ansi_term::{ANSIString, Style};
+use chrono::Utc;
 use rustyline::config as rustyconfig;
 use strfmt::strfmt;
 use tempdir::TempDir;
 
+use std::cell::RefCell;
 use std::collections::{BTreeMap, HashMap};
 use std::fmt;
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Write};
 use std::path::PathBuf;
-use std::process::Child;
+use std::process::{Child, Command, Stdio};
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
 use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
 // TODO: Maybe make less of this pub
 
@@ -40,19 +48,103 @@ pub struct PortForward {
     pub output: Arc<Mutex<String>>,
 }
 
+/// A running kubectl proxy
+pub struct Proxy {
+    pub handle: duct::Handle,
+    pub url: String,
+    /// the context the proxy talks to
+    pub context: String,
+}
+
 #[derive(Debug)]
 pub struct ExpandedAlias<'a> {
     pub expansion: Option<&'a Alias>,
     pub rest: &'a str,
 }
 
-#[derive(Debug, PartialEq)]
+/// What changed when reloading the kubernetes config
+#[derive(Debug, Default)]
+pub struct ReloadSummary {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+    /// set if the active context was removed from the config, but kept around so the session
+    /// keeps working
+    pub kept_active: Option<String>,
+}
+
+/// What to do when applying a function to a range of objects fails for one of them
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorStrategy {
+    /// Stop at the first object that fails, and return its error
+    StopOnFirst,
+    /// Apply to every object, and return all the errors together at the end as a MultiError
+    CollectAll,
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum ObjectSelection {
     Single(KObj),
     Range(Vec<KObj>),
     None,
 }
 
+/// The whole selection state, so it can be put back after being changed temporarily
+pub struct SelectionSnapshot {
+    current: ObjectSelection,
+    unfiltered: Option<ObjectSelection>,
+    range_str: Option<String>,
+}
+
+/// How long names fetched for completion are reused before being fetched again
+const NAME_CACHE_TTL: Duration = Duration::from_secs(30);
+/// How long completion waits for names to be fetched before completing with what it has
+const NAME_FETCH_WAIT: Duration = Duration::from_millis(400);
+
+/// Pager used when neither the pager option nor $PAGER is set
+const DEFAULT_PAGER: &str = "less -R";
+
+/// Replace a leading ~ in path with the home directory
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ if path == "~" => dirs::home_dir().unwrap_or_else(|| PathBuf::from(path)),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Names fetched for completion, so we don't query the cluster on every tab press
+struct NameCache {
+    /// when names were last fetched, None if they never have been
+    fetched: Option<Instant>,
+    names: Vec<String>,
+    /// a fetch running in the background, which sends the names (or None if it failed) when done
+    pending: Option<Receiver<Option<Vec<String>>>>,
+}
+
+/// Run command in a background thread, sending the names parse gets from its output when it's
+/// done
+fn fetch_names(
+    mut command: Command,
+    parse: fn(&str) -> Vec<String>,
+) -> Receiver<Option<Vec<String>>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let names = command
+            .stdin(Stdio::null())
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| parse(&String::from_utf8_lossy(&output.stdout)));
+        // the receiver is gone if the cache was cleared, and then nobody wants the names
+        sender.send(names).unwrap_or(());
+    });
+    receiver
+}
+
+/// Names are cached by (context, kind, namespace)
+type NameCacheKey = (String, String, Option<String>);
+
 /// Keep track of our repl environment
 pub struct Env {
     pub config: Config,
@@ -63,12 +155,37 @@ pub struct Env {
     pub context: Option<super::k8s::Context>,
     pub namespace: Option<String>,
     current_selection: ObjectSelection,
+    /// the selection before it was narrowed with the filter command
+    unfiltered_selection: Option<ObjectSelection>,
     last_objs: Option<Vec<KObj>>,
     pub ctrlcbool: Arc<AtomicBool>,
     port_forwards: Vec<PortForward>,
+    proxy: Option<Proxy>,
+    /// background jobs, like wait --notify
+    jobs: Jobs,
     pub prompt: String,
     range_str: Option<String>,
     pub tempdir: std::io::Result<TempDir>,
+    name_cache: RefCell<HashMap<NameCacheKey, NameCache>>,
+    /// map of context name -> the kubernetes version its server reported, for the version command
+    server_versions: HashMap<String, String>,
+    /// colors are off for this whole session (because of NO_COLOR or --no-color), whatever the
+    /// color setting is
+    no_color: bool,
+    /// print the kubectl commands (and api requests) that would change things, rather than
+    /// running them
+    pub dry_run: bool,
+    /// how commands format their output, from -o or 'set output_format'
+    pub output_format: OutputFormat,
+    /// the --timeout click was started with, which is used rather than default_timeout for the
+    /// session
+    pub request_timeout: Option<String>,
+    /// the --kubeconfig given to the command that's running, which is passed on to kubectl. This
+    /// is only set while the command runs.
+    pub command_kubeconfig: Option<PathBuf>,
+    /// who the command that's running was told to impersonate with --as, instead of whoever the
+    /// session impersonates. This is only set while the command runs.
+    command_impersonate: Option<Impersonation>,
 }
 
 lazy_static! {
@@ -83,10 +200,32 @@ lazy_static! {
     };
 }
 
+/// Fill in the placeholders of a prompt_format
+fn format_prompt(
+    format: &str,
+    context: &str,
+    namespace: &str,
+    selection: &str,
+    selection_count: &str,
+    selected: &str,
+) -> Result<String, strfmt::FmtError> {
+    let mut vars = HashMap::new();
+    vars.insert("context".to_string(), context);
+    vars.insert("namespace".to_string(), namespace);
+    vars.insert("selection".to_string(), selection);
+    vars.insert("selection_count".to_string(), selection_count);
+    vars.insert("selected".to_string(), selected);
+    strfmt(format, &vars)
+}
+
 impl Env {
     pub fn new(config: Config, click_config: ClickConfig, click_config_path: PathBuf) -> Env {
         let namespace = click_config.namespace.clone();
-        let context = click_config.context.clone();
+        // prefer the context click was last using, falling back to the kubeconfig's current-context
+        let context = click_config
+            .context
+            .clone()
+            .or_else(|| config.current_context.clone());
         let mut env = Env {
             config,
             click_config,
@@ -96,19 +235,33 @@ impl Env {
             context: None,
             namespace,
             current_selection: ObjectSelection::None,
+            unfiltered_selection: None,
             last_objs: None,
             ctrlcbool: CTC_BOOL.clone(),
             port_forwards: Vec::new(),
-            prompt: format!(
-                "[{}] [{}] [{}] > ",
-                Red.paint("none"),
-                Green.paint("none"),
-                Yellow.paint("none")
-            ),
+            proxy: None,
+            jobs: Jobs::default(),
+            prompt: String::new(), // set below
             range_str: None,
             tempdir: TempDir::new("click"),
+            name_cache: RefCell::new(HashMap::new()),
+            server_versions: HashMap::new(),
+            // see https://no-color.org
+            no_color: std::env::var_os("NO_COLOR").is_some_and(|val| !val.is_empty()),
+            dry_run: false,
+            output_format: OutputFormat::Table,
+            request_timeout: None,
+            command_kubeconfig: None,
+            command_impersonate: None,
         };
+        if let Err(e) = env.apply_theme() {
+            println!(
+                "[WARN] Invalid theme settings, using the default theme: {}",
+                e
+            );
+        }
         env.set_context(context.as_deref());
+        env.set_prompt();
         env
     }
 
@@ -126,24 +279,176 @@ impl Env {
 
     // sets the prompt string based on current settings
     fn set_prompt(&mut self) {
-        self.prompt = format!(
-            "[{}] [{}] [{}] > ",
-            if let Some(ref c) = self.context {
-                Red.bold().paint(c.name.as_str())
-            } else {
-                Red.paint("none")
-            },
-            if let Some(ref n) = self.namespace {
-                Green.bold().paint(n.as_str())
+        let context = theme::style(Role::PromptContext)
+            .paint(match self.context {
+                Some(ref c) => self.context_alias_for(&c.name).unwrap_or(c.name.as_str()),
+                None => "none",
+            })
+            .to_string();
+        let namespace = theme::style(Role::PromptNamespace)
+            .paint(self.namespace.as_deref().unwrap_or("none"))
+            .to_string();
+        let (selection, count) = match self.current_selection {
+            ObjectSelection::Single(ref obj) => (obj.name(), 1),
+            ObjectSelection::Range(ref range) => (self.range_str.as_deref().unwrap(), range.len()),
+            ObjectSelection::None => ("none", 0),
+        };
+        let selection = theme::style(Role::Selection).paint(selection).to_string();
+        let selected = match count {
+            0 => String::new(),
+            1 => format!(
+                "[{}]",
+                theme::style(Role::Selection).paint("1 object selected")
+            ),
+            n => format!(
+                "[{}]",
+                theme::style(Role::Selection).paint(format!("{} objects selected", n))
+            ),
+        };
+        let count = count.to_string();
+        self.prompt = format_prompt(
+            &self.click_config.prompt_format,
+            &context,
+            &namespace,
+            &selection,
+            &count,
+            &selected,
+        )
+        .unwrap_or_else(|_| {
+            // the format is checked when it's set, but could be broken in the config file
+            format_prompt(
+                &config::default_prompt_format(),
+                &context,
+                &namespace,
+                &selection,
+                &count,
+                &selected,
+            )
+            .unwrap() // safe, the default format is fine
+        });
+        // so it's hard to forget requests are going out as someone else
+        if let Some(imp) = self.click_config.impersonate.as_ref() {
+            let insert_at = if self.prompt.ends_with("> ") {
+                self.prompt.len() - 2 // before the "> "
             } else {
-                Green.paint("none")
-            },
-            match self.current_selection {
-                ObjectSelection::Single(ref obj) => obj.prompt_str(),
-                ObjectSelection::Range(_) => Blue.paint(self.range_str.as_ref().unwrap()),
-                ObjectSelection::None => Yellow.paint("none"),
-            }
-        );
+                0
+            };
+            self.prompt.insert_str(
+                insert_at,
+                &format!("[as {}] ", theme::style(Role::Warning).paint(&imp.user)),
+            );
+        }
+        if !self.use_color(false) {
+            self.prompt = strip_ansi(&self.prompt);
+        }
+    }
+
+    /// Set what the prompt looks like. This fails, leaving the prompt alone, if format has
+    /// placeholders other than the ones the prompt fills in.
+    pub fn set_prompt_format(&mut self, format: &str) -> Result<(), ClickError> {
+        format_prompt(format, "", "", "", "", "").map_err(|e| {
+            ClickError::CommandError(format!("Invalid prompt_format '{}': {}", format, e))
+        })?;
+        self.click_config.prompt_format = format.to_string();
+        self.set_prompt();
+        Ok(())
+    }
+
+    /// Should output be colored. Pass redirected as true if the output is going to a file or
+    /// being piped to a command, rather than to stdout
+    pub fn use_color(&self, redirected: bool) -> bool {
+        if self.no_color {
+            return false;
+        }
+        match self.click_config.color {
+            ColorMode::On => true,
+            ColorMode::Off => false,
+            ColorMode::Auto if redirected => !self.click_config.strip_color_on_redirect,
+            ColorMode::Auto => io::stdout().is_terminal(),
+        }
+    }
+
+    /// Build the theme from the config and make it the active one
+    fn apply_theme(&mut self) -> Result<(), ClickError> {
+        let theme = Theme::new(&self.click_config.theme, &self.click_config.theme_overrides)?;
+        theme::set_active(theme);
+        self.set_prompt();
+        Ok(())
+    }
+
+    pub fn set_theme(&mut self, name: &str) -> Result<(), ClickError> {
+        // check it's valid before changing anything
+        Theme::new(name, &self.click_config.theme_overrides)?;
+        self.click_config.theme = name.to_string();
+        self.apply_theme()
+    }
+
+    /// Override the style the theme uses for role. An empty style removes the override.
+    pub fn set_theme_override(&mut self, role: &str, style: &str) -> Result<(), ClickError> {
+        let mut overrides = self.click_config.theme_overrides.clone();
+        if style.is_empty() {
+            overrides.remove(role);
+        } else {
+            overrides.insert(role.to_string(), style.to_string());
+        }
+        Theme::new(&self.click_config.theme, &overrides)?;
+        self.click_config.theme_overrides = overrides;
+        self.apply_theme()
+    }
+
+    pub fn set_color(&mut self, mode: ColorMode) {
+        self.click_config.color = mode;
+        self.set_prompt();
+    }
+
+    /// Send requests (and run kubectl) as someone else for the rest of the session, or stop, with
+    /// None
+    pub fn set_impersonate(&mut self, impersonate: Option<Impersonation>) {
+        if let Some(context) = self.context.as_ref() {
+            context.set_impersonate(impersonate.clone());
+        }
+        self.click_config.impersonate = impersonate;
+        self.set_prompt();
+    }
+
+    /// Impersonate someone just for the command that's running, or go back to whoever the session
+    /// impersonates, with None
+    pub fn set_command_impersonate(&mut self, impersonate: Option<Impersonation>) {
+        if let Some(context) = self.context.as_ref() {
+            context.set_impersonate(
+                impersonate
+                    .clone()
+                    .or_else(|| self.click_config.impersonate.clone()),
+            );
+        }
+        self.command_impersonate = impersonate;
+    }
+
+    /// Who requests and kubectl are impersonating right now, if anyone
+    pub fn impersonating(&self) -> Option<&Impersonation> {
+        self.command_impersonate
+            .as_ref()
+            .or(self.click_config.impersonate.as_ref())
+    }
+
+    /// Turn colors off for this session, without changing the color setting
+    pub fn disable_color(&mut self) {
+        self.no_color = true;
+        self.set_prompt();
+    }
+
+    /// Paint text in style if we're using color. Use this for anything printed directly to stdout,
+    /// rather than through a ClickWriter (which strips colors itself).
+    pub fn paint<'a, S, T>(&self, style: S, text: T) -> ANSIString<'a>
+    where
+        S: Into<Style>,
+        T: Into<std::borrow::Cow<'a, str>>,
+    {
+        if self.use_color(false) {
+            style.into().paint(text)
+        } else {
+            Style::default().paint(text)
+        }
     }
 
     pub fn get_rustyline_conf(&self) -> rustyconfig::Config {
@@ -154,10 +459,76 @@ impl Env {
         &self.config.contexts
     }
 
+    /// Re-read the kubernetes config files and replace our config with the result. If the active
+    /// context has been removed it's kept in the config so the current session stays usable. If it
+    /// changed, it's reloaded.
+    pub fn reload_config(&mut self) -> Result<ReloadSummary, ClickError> {
+        let mut new_config = self.config.reload()?;
+        let mut summary = ReloadSummary::default();
+        for (name, conf) in new_config.contexts.iter() {
+            match self.config.contexts.get(name) {
+                Some(old_conf) if old_conf == conf => {}
+                Some(_) => summary.changed.push(name.clone()),
+                None => summary.added.push(name.clone()),
+            }
+        }
+        for (name, conf) in self.config.contexts.iter() {
+            if !new_config.contexts.contains_key(name) {
+                if self.context.as_ref().map(|c| &c.name) == Some(name) {
+                    new_config.contexts.insert(name.clone(), conf.clone());
+                    summary.kept_active = Some(name.clone());
+                } else {
+                    summary.removed.push(name.clone());
+                }
+            }
+        }
+        self.config = new_config;
+        // a changed context may point at a different cluster now
+        for name in summary.changed.iter().chain(summary.removed.iter()) {
+            self.server_versions.remove(name);
+        }
+        let active = self.context.as_ref().map(|c| c.name.clone());
+        if let Some(active) = active {
+            if summary.changed.contains(&active) {
+                self.set_context(Some(&active));
+            }
+        }
+        Ok(summary)
+    }
+
+    /// Switch to the kubernetes config file(s) in paths for the rest of the session. KUBECONFIG is
+    /// set to match, so kubectl uses them too. The active context is kept if the new files have
+    /// it, otherwise the current-context of the new files is used. Returns true if the active
+    /// context changed.
+    pub fn set_kubeconfig(&mut self, paths: Vec<String>) -> Result<bool, ClickError> {
+        let config = Config::from_files(&paths)?;
+        std::env::set_var("KUBECONFIG", std::env::join_paths(paths.iter())?);
+        let active = self.context.as_ref().map(|c| c.name.clone());
+        let context = active
+            .clone()
+            .filter(|name| config.contexts.contains_key(name))
+            .or_else(|| config.current_context.clone());
+        self.config = config;
+        // the same context name may be a different cluster in the new files
+        self.server_versions.clear();
+        match context {
+            Some(ref context) => self.set_context(Some(context)),
+            None => {
+                self.context = None;
+                self.name_cache.borrow_mut().clear();
+                self.set_prompt();
+            }
+        }
+        Ok(context != active)
+    }
+
     pub fn set_context(&mut self, ctx: Option<&str>) {
         if let Some(cname) = ctx {
             self.context = match self.config.get_context(cname, &self.click_config) {
-                Ok(context) => Some(context),
+                Ok(context) => {
+                    self.warn_cert_expiry(cname);
+                    Some(context)
+                }
                 Err(e) => {
                     println!(
                         "[WARN] Couldn't find/load context {}, now no current context. \
@@ -167,14 +538,216 @@ impl Env {
                     None
                 }
             };
+            // as in set_namespace, cached names are for the old context
+            self.name_cache.borrow_mut().clear();
             self.save_click_config();
             self.set_prompt();
         }
     }
 
+    /// Return the cached names for key if they are recent enough. Otherwise run the kubectl
+    /// command to fetch them in the background, and wait up to NAME_FETCH_WAIT for it. If it
+    /// doesn't finish in time, the old names are returned (None if there aren't any), so a slow
+    /// cluster doesn't freeze the prompt. The fetch keeps running, and a later call picks up its
+    /// result.
+    fn cached_names<C>(
+        &self,
+        key: NameCacheKey,
+        command: C,
+        parse: fn(&str) -> Vec<String>,
+    ) -> Option<Vec<String>>
+    where
+        C: FnOnce() -> Option<Command>,
+    {
+        let mut caches = self.name_cache.borrow_mut();
+        let cache = caches.entry(key).or_insert_with(|| NameCache {
+            fetched: None,
+            names: vec![],
+            pending: None,
+        });
+        if cache
+            .fetched
+            .is_some_and(|fetched| fetched.elapsed() < NAME_CACHE_TTL)
+        {
+            return Some(cache.names.clone());
+        }
+        if cache.pending.is_none() {
+            cache.pending = command().map(|command| fetch_names(command, parse));
+        }
+        if let Some(pending) = cache.pending.as_ref() {
+            match pending.recv_timeout(NAME_FETCH_WAIT) {
+                Ok(names) => {
+                    cache.pending = None;
+                    // if the fetch failed, keep the old names, and try again next time
+                    if let Some(names) = names {
+                        cache.names = names;
+                        cache.fetched = Some(Instant::now());
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => cache.pending = None,
+            }
+        }
+        cache.fetched.map(|_| cache.names.clone())
+    }
+
+    /// Get the names of the namespaces in the current context, reusing the last fetched names if
+    /// they are recent enough. Use this for completion, where being slightly out of date is better
+    /// than being slow.
+    pub fn cached_namespace_names(&self) -> Option<Vec<String>> {
+        let context = self.context.as_ref()?;
+        let key = (context.name.clone(), "namespaces".to_string(), None);
+        self.cached_names(
+            key,
+            || crate::completer::resource_names_command(self, "namespaces"),
+            crate::completer::parse_resource_names,
+        )
+    }
+
+    /// Get the names of objects of kind (i.e. "pods") in the current namespace, or in all
+    /// namespaces if none is set. Like `cached_namespace_names`, these are reused for a while.
+    pub fn cached_resource_names(&self, kind: &str) -> Option<Vec<String>> {
+        let context = self.context.as_ref()?;
+        let key = (
+            context.name.clone(),
+            kind.to_string(),
+            self.namespace.clone(),
+        );
+        self.cached_names(
+            key,
+            || crate::completer::resource_names_command(self, kind),
+            crate::completer::parse_resource_names,
+        )
+    }
+
+    /// Remember the names of objects of kind in the current namespace (or all of them), when a
+    /// list command has just fetched every one of them anyway, so completing them doesn't need
+    /// another fetch
+    pub fn set_cached_resource_names(&self, kind: &str, mut names: Vec<String>) {
+        if let Some(context) = self.context.as_ref() {
+            names.sort_unstable();
+            names.dedup();
+            let key = (
+                context.name.clone(),
+                kind.to_string(),
+                self.namespace.clone(),
+            );
+            self.name_cache.borrow_mut().insert(
+                key,
+                NameCache {
+                    fetched: Some(Instant::now()),
+                    names,
+                    pending: None,
+                },
+            );
+        }
+    }
+
+    /// Get the labels on objects of kind in the current namespace (or all of them), as key=value
+    /// strings. These are cached like the names in `cached_resource_names`.
+    pub fn cached_label_pairs(&self, kind: &str) -> Option<Vec<String>> {
+        let context = self.context.as_ref()?;
+        let key = (
+            context.name.clone(),
+            format!("labels/{}", kind),
+            self.namespace.clone(),
+        );
+        self.cached_names(
+            key,
+            || crate::completer::labels_command(self, kind),
+            crate::completer::parse_label_pairs,
+        )
+    }
+
+    /// Get the names of the containers (including init and ephemeral ones) in pod, reusing the
+    /// last fetched names like `cached_namespace_names`
+    pub fn cached_container_names(&self, pod: &KObj) -> Option<Vec<String>> {
+        let key = self.container_names_key(pod)?;
+        self.cached_names(
+            key,
+            || crate::completer::container_names_command(self, pod),
+            crate::completer::parse_container_names,
+        )
+    }
+
+    /// Remember the names of the containers in pod, when a command has just fetched them anyway,
+    /// so completing them doesn't need another fetch
+    pub fn set_cached_container_names(&self, pod: &KObj, names: Vec<String>) {
+        if let Some(key) = self.container_names_key(pod) {
+            self.name_cache.borrow_mut().insert(
+                key,
+                NameCache {
+                    fetched: Some(Instant::now()),
+                    names,
+                    pending: None,
+                },
+            );
+        }
+    }
+
+    fn container_names_key(&self, pod: &KObj) -> Option<NameCacheKey> {
+        let context = self.context.as_ref()?;
+        Some((
+            context.name.clone(),
+            format!("containers/{}", pod.name()),
+            pod.namespace.clone(),
+        ))
+    }
+
+    /// The server version fetched for the current context, if it has been
+    pub fn server_version(&self) -> Option<&String> {
+        let context = self.context.as_ref()?;
+        self.server_versions.get(&context.name)
+    }
+
+    /// Remember the server version for the current context, so it doesn't need fetching again
+    pub fn set_server_version(&mut self, version: String) {
+        if let Some(context) = self.context.as_ref() {
+            self.server_versions.insert(context.name.clone(), version);
+        }
+    }
+
+    /// Print a warning if the client certificate for context is expired or about to expire
+    fn warn_cert_expiry(&self, context: &str) {
+        match self
+            .config
+            .cert_expiry(context, self.click_config.cert_warn_days)
+        {
+            Ok(Some(CertExpiry::ExpiresSoon(not_after))) => println!(
+                "{}",
+                self.paint(
+                    theme::style(Role::Warning),
+                    format!(
+                        "[WARN] The client certificate for context {} expires in {} (at {})",
+                        context,
+                        crate::command::format_duration(not_after - Utc::now()),
+                        not_after.format("%Y-%m-%d %H:%M:%S UTC")
+                    )
+                )
+            ),
+            Ok(Some(CertExpiry::Expired(not_after))) => println!(
+                "{}",
+                self.paint(
+                    theme::style(Role::BadStatus),
+                    format!(
+                        "[ERROR] The client certificate for context {} expired at {}. Requests to \
+                     this cluster will fail until it is renewed.",
+                        context,
+                        not_after.format("%Y-%m-%d %H:%M:%S UTC")
+                    )
+                )
+            ),
+            Ok(_) => {}
+            Err(e) => println!(
+                "[WARN] Couldn't check client certificate for context {}: {}",
+                context, e
+            ),
+        }
+    }
+
     pub fn set_namespace(&mut self, namespace: Option<&str>) {
         let mut do_clear = false;
-        if let (&Some(ref my_ns), Some(new_ns)) = (&self.namespace, namespace) {
+        if let (Some(my_ns), Some(new_ns)) = (&self.namespace, namespace) {
             if my_ns.as_str() != new_ns {
                 do_clear = true; // need to use bool since self is borrowed here
             }
@@ -183,9 +756,35 @@ impl Env {
             self.clear_current();
         }
         self.namespace = namespace.map(|n| n.to_owned());
+        // names completed in the old namespace don't apply any more
+        self.name_cache.borrow_mut().clear();
         self.set_prompt();
     }
 
+    /// Remember the current namespace as the one to use for the current context
+    pub fn remember_namespace(&mut self) {
+        if let Some(ctx) = self.context.as_ref() {
+            match self.namespace.as_ref() {
+                Some(ns) => self
+                    .click_config
+                    .context_namespaces
+                    .insert(ctx.name.clone(), ns.clone()),
+                None => self.click_config.context_namespaces.remove(&ctx.name),
+            };
+            self.save_click_config();
+        }
+    }
+
+    /// Get the namespace that was last used in the current context, if any
+    pub fn remembered_namespace(&self) -> Option<&str> {
+        self.context.as_ref().and_then(|ctx| {
+            self.click_config
+                .context_namespaces
+                .get(&ctx.name)
+                .map(|ns| ns.as_str())
+        })
+    }
+
     pub fn set_editor(&mut self, editor: Option<&str>) {
         self.click_config.editor = editor.map(|s| s.to_string());
     }
@@ -194,6 +793,84 @@ impl Env {
         self.click_config.terminal = terminal.map(|s| s.to_string());
     }
 
+    /// Set the command used to ssh to nodes. An empty command goes back to plain `ssh`.
+    pub fn set_ssh_command(&mut self, ssh_command: &str) {
+        self.click_config.ssh_command = if ssh_command.is_empty() {
+            None
+        } else {
+            Some(ssh_command.to_string())
+        };
+    }
+
+    /// Set the container runtime nsenter expects on nodes. An empty runtime goes back to detecting
+    /// it.
+    pub fn set_container_runtime(&mut self, runtime: &str) {
+        self.click_config.container_runtime = if runtime.is_empty() {
+            None
+        } else {
+            Some(runtime.to_string())
+        };
+    }
+
+    /// Set the request timeout kubectl is run with. An empty timeout goes back to kubectl's
+    /// default.
+    pub fn set_default_timeout(&mut self, timeout: &str) {
+        self.click_config.default_timeout = if timeout.is_empty() {
+            None
+        } else {
+            Some(timeout.to_string())
+        };
+    }
+
+    /// The --request-timeout to run kubectl with, if any
+    pub fn kubectl_timeout(&self) -> Option<&str> {
+        self.request_timeout
+            .as_deref()
+            .or(self.click_config.default_timeout.as_deref())
+    }
+
+    /// Set the pager command. An empty command goes back to using $PAGER.
+    pub fn set_pager(&mut self, pager: &str) {
+        self.click_config.pager = if pager.is_empty() {
+            None
+        } else {
+            Some(pager.to_string())
+        };
+    }
+
+    /// The command to page long output through, or None if paging is off (pager set to "none")
+    pub fn pager_command(&self) -> Option<String> {
+        let pager = match self.click_config.pager {
+            Some(ref pager) => pager.clone(),
+            None => std::env::var("PAGER")
+                .ok()
+                .filter(|pager| !pager.trim().is_empty())
+                .unwrap_or_else(|| DEFAULT_PAGER.to_string()),
+        };
+        if pager == "none" {
+            None
+        } else {
+            Some(pager)
+        }
+    }
+
+    pub fn set_rc_file(&mut self, rc_file: &str) {
+        self.click_config.rc_file = if rc_file.is_empty() {
+            None
+        } else {
+            Some(rc_file.to_string())
+        };
+    }
+
+    /// The file of commands to run at startup, and if it was set explicitly (rather than being the
+    /// default, which is fine to be missing)
+    pub fn rc_file(&self) -> Option<(PathBuf, bool)> {
+        match self.click_config.rc_file {
+            Some(ref path) => Some((expand_home(path), true)),
+            None => dirs::home_dir().map(|home| (home.join(".config/click/clickrc"), false)),
+        }
+    }
+
     pub fn set_completion_type(&mut self, comptype: config::CompletionType) {
         self.click_config.completiontype = comptype;
         self.need_new_editor = true;
@@ -204,6 +881,16 @@ impl Env {
         self.need_new_editor = true;
     }
 
+    pub fn set_history_size(&mut self, size: usize) {
+        self.click_config.history_size = size;
+        self.need_new_editor = true;
+    }
+
+    pub fn set_history_ignore_dups(&mut self, ignore: bool) {
+        self.click_config.history_ignore_dups = ignore;
+        self.need_new_editor = true;
+    }
+
     // Return the current position of the specified alias in the Vec, or None if it's not there
     fn alias_position(&self, alias: &str) -> Option<usize> {
         self.click_config
@@ -224,6 +911,32 @@ impl Env {
         self.save_click_config();
     }
 
+    /// If defining alias as expanded would make expansion go round in a loop (like p -> q -> p),
+    /// return the aliases in the loop, starting and ending with alias
+    pub fn alias_cycle(&self, alias: &str, expanded: &str) -> Option<Vec<String>> {
+        let first_word = |s: &str| s.split_whitespace().next().unwrap_or("").to_string();
+        let mut chain = vec![alias.to_string()];
+        let mut next = first_word(expanded);
+        loop {
+            if next == alias {
+                chain.push(next);
+                return Some(chain);
+            }
+            if chain.contains(&next) {
+                // a loop that doesn't go through alias, expansion stops there
+                return None;
+            }
+            match self.get_alias(&next) {
+                Some(a) => {
+                    let expanded = first_word(&a.expanded);
+                    chain.push(next);
+                    next = expanded;
+                }
+                None => return None,
+            }
+        }
+    }
+
     pub fn remove_alias(&mut self, alias: &str) -> bool {
         match self.alias_position(alias) {
             Some(p) => {
@@ -235,6 +948,46 @@ impl Env {
         }
     }
 
+    /// Add an alias for a context. Returns false if there's no context named `context`
+    pub fn add_context_alias(&mut self, alias: &str, context: &str) -> bool {
+        if !self.config.contexts.contains_key(context) {
+            return false;
+        }
+        self.click_config
+            .context_aliases
+            .insert(alias.to_string(), context.to_string());
+        self.save_click_config();
+        self.set_prompt();
+        true
+    }
+
+    pub fn remove_context_alias(&mut self, alias: &str) -> bool {
+        if self.click_config.context_aliases.remove(alias).is_some() {
+            self.save_click_config();
+            self.set_prompt();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Get the context an alias points to
+    pub fn resolve_context_alias(&self, alias: &str) -> Option<&str> {
+        self.click_config
+            .context_aliases
+            .get(alias)
+            .map(|s| s.as_str())
+    }
+
+    /// Get the (first) alias defined for the specified context, if any
+    pub fn context_alias_for(&self, context: &str) -> Option<&str> {
+        self.click_config
+            .context_aliases
+            .iter()
+            .find(|(_, ctx)| ctx.as_str() == context)
+            .map(|(alias, _)| alias.as_str())
+    }
+
     pub fn set_last_objs<T: Into<Vec<KObj>>>(&mut self, objs: T) {
         self.last_objs = Some(objs.into());
     }
@@ -245,10 +998,25 @@ impl Env {
 
     pub fn clear_current(&mut self) {
         self.current_selection = ObjectSelection::None;
+        self.unfiltered_selection = None;
         self.range_str = None;
         self.set_prompt();
     }
 
+    /// Forget the selection, the objects from the last list, and any cached object names. Commands
+    /// that change or delete objects call this when they succeed, so nothing goes on to operate on
+    /// stale objects.
+    pub fn invalidate_selection(&mut self) {
+        self.clear_current();
+        self.clear_last_objs();
+        self.name_cache.borrow_mut().clear();
+    }
+
+    /// How many objects the last list had, or None if nothing has been listed
+    pub fn last_objs_len(&self) -> Option<usize> {
+        self.last_objs.as_ref().map(|lo| lo.len())
+    }
+
     /// get the item from the last list at the specified index
     pub fn item_at(&self, index: usize) -> Option<&KObj> {
         self.last_objs.as_ref().and_then(|lo| lo.get(index))
@@ -259,15 +1027,67 @@ impl Env {
             Some(obj) => ObjectSelection::Single(obj.clone()),
             None => ObjectSelection::None,
         };
+        if let ObjectSelection::Single(obj) = &self.current_selection {
+            let obj = obj.clone();
+            self.switch_to_context_of(std::slice::from_ref(&obj));
+        }
+        self.unfiltered_selection = None;
         self.range_str = None;
         self.set_prompt();
     }
 
+    /// Select just obj. Unlike set_current, it doesn't need to be one of the last listed objects.
+    pub fn select_obj(&mut self, obj: KObj) {
+        self.current_selection = ObjectSelection::Single(obj);
+        self.unfiltered_selection = None;
+        self.range_str = None;
+        self.set_prompt();
+    }
+
+    pub fn selection_snapshot(&self) -> SelectionSnapshot {
+        SelectionSnapshot {
+            current: self.current_selection.clone(),
+            unfiltered: self.unfiltered_selection.clone(),
+            range_str: self.range_str.clone(),
+        }
+    }
+
+    pub fn restore_selection(&mut self, snapshot: SelectionSnapshot) {
+        self.current_selection = snapshot.current;
+        self.unfiltered_selection = snapshot.unfiltered;
+        self.range_str = snapshot.range_str;
+        self.set_prompt();
+    }
+
+    /// Objects listed from several contexts (i.e. with `pods --contexts`) remember which one they
+    /// came from. When they're selected, switch to it, so commands on them go to the right
+    /// cluster. Commands run against one context, so a range across contexts can't be handled.
+    fn switch_to_context_of(&mut self, objs: &[KObj]) {
+        let mut contexts: Vec<&str> = objs.iter().filter_map(|o| o.context.as_deref()).collect();
+        contexts.sort_unstable();
+        contexts.dedup();
+        match contexts.as_slice() {
+            [context] => {
+                if self.context.as_ref().map(|c| c.name.as_str()) != Some(*context) {
+                    let context = context.to_string();
+                    self.set_context(Some(&context));
+                }
+            }
+            [] => {}
+            _ => println!(
+                "[WARN] The selection is from {} different contexts, but commands will only run \
+                 against the current one",
+                contexts.len()
+            ),
+        }
+    }
+
     pub fn set_range(&mut self, range: Vec<KObj>) {
+        self.switch_to_context_of(&range);
         let range_str = if range.is_empty() {
             "Empty range".to_string()
         } else {
-            let mut r = format!("{} {}", range.len(), range.get(0).unwrap().type_str());
+            let mut r = format!("{} {}", range.len(), range.first().unwrap().type_str());
             if range.len() > 1 {
                 r.push('s');
             }
@@ -275,65 +1095,63 @@ impl Env {
             r
         };
         self.current_selection = ObjectSelection::Range(range);
+        self.unfiltered_selection = None;
         self.range_str = Some(range_str);
         self.set_prompt();
     }
 
-    pub fn current_pod(&self) -> Option<&KObj> {
-        match self.current_selection {
-            ObjectSelection::Single(ref obj) => match obj.typ {
-                ObjType::Pod { .. } => Some(obj),
-                _ => None,
-            },
-            _ => None,
+    /// Narrow the selection down to objs, which should be some of the selected objects. The
+    /// selection from before the first filter is kept, so clear_filter can restore it. Selecting
+    /// anything else forgets it.
+    pub fn filter_selection(&mut self, mut objs: Vec<KObj>) {
+        let unfiltered = self
+            .unfiltered_selection
+            .take()
+            .unwrap_or_else(|| self.current_selection.clone());
+        if objs.len() == 1 {
+            self.current_selection = ObjectSelection::Single(objs.remove(0));
+            self.range_str = None;
+            self.set_prompt();
+        } else {
+            self.set_range(objs);
         }
+        self.unfiltered_selection = Some(unfiltered);
     }
 
-    // the function. print its error if an error happens. return true if the loop should continue,
-    // false if it should stop
-    fn call_selection_func<F>(
-        obj: &KObj,
-        writer: &mut ClickWriter,
-        f: &mut F,
-        continue_all: &mut bool,
-    ) -> bool
-    where
-        F: FnMut(&KObj, &mut ClickWriter) -> Result<(), ClickError>,
-    {
-        if let Err(e) = f(obj, writer) {
-            clickwriteln!(writer, "Error applying operation to {}: {}", obj.name, e);
-            if *continue_all {
-                return true;
+    /// Undo filter_selection, returning false if the selection wasn't filtered
+    pub fn clear_filter(&mut self) -> bool {
+        match self.unfiltered_selection.take() {
+            Some(ObjectSelection::Range(range)) => self.set_range(range),
+            Some(selection) => {
+                self.current_selection = selection;
+                self.range_str = None;
+                self.set_prompt();
             }
-            clickwriteln!(writer, "  o = once: continue this time, ask again on error");
-            clickwriteln!(writer, "  a = all: continue over all future errors");
-            clickwriteln!(writer, "  n/N = no: abort range operation (default)");
-            clickwrite!(writer, "Continue? [o/a/N]? ");
-            io::stdout().flush().expect("Could not flush stdout");
-            let mut conf = String::new();
-            if io::stdin().read_line(&mut conf).is_ok() {
-                match conf.trim() {
-                    "o" | "once" => true,
-                    "a" | "all" => {
-                        *continue_all = true;
-                        true
-                    }
-                    _ => false,
-                }
-            } else {
-                clickwriteln!(writer, "Could not read response, stopping");
-                false
-            }
-        } else {
-            true
+            None => return false,
         }
+        true
     }
 
-    // apply a function to each selected object.
+    /// The selected pod, or an error saying why there isn't one
+    pub fn current_pod(&self) -> Result<&KObj, ClickError> {
+        match self.current_selection {
+            ObjectSelection::Single(ref obj) if obj.is_pod() => Ok(obj),
+            ObjectSelection::None => Err(ClickError::CommandError(
+                "No active pod selected".to_string(),
+            )),
+            _ => Err(ClickError::CommandError(
+                "Selected object is not a pod".to_string(),
+            )),
+        }
+    }
+
+    // apply a function to each selected object. for a range, on_error says what to do if applying
+    // to one of the objects fails.
     pub fn apply_to_selection<F>(
         &self,
         writer: &mut ClickWriter,
         sepfmt: Option<&str>,
+        on_error: ErrorStrategy,
         mut f: F,
     ) -> Result<(), ClickError>
     where
@@ -342,14 +1160,8 @@ impl Env {
         match self.current_selection() {
             ObjectSelection::Single(obj) => f(obj, writer),
             ObjectSelection::Range(range) => {
-                let mut continue_all = false;
-                let mut go = true;
+                let mut errors = vec![];
                 for obj in range.iter() {
-                    if !go {
-                        return Err(ClickError::CommandError(
-                            "Aborting range action".to_string(),
-                        ));
-                    }
                     if let Some(fmt) = sepfmt {
                         let mut fmtvars = HashMap::new();
                         fmtvars.insert("name".to_string(), obj.name());
@@ -358,35 +1170,27 @@ impl Env {
                             obj.namespace.as_deref().unwrap_or("[none]"),
                         );
                         match strfmt(fmt, &fmtvars) {
-                            Ok(sep) => {
-                                clickwriteln!(writer, "{}", sep);
-                                go = Env::call_selection_func(
-                                    obj,
-                                    writer,
-                                    &mut f,
-                                    &mut continue_all,
-                                );
-                            }
-                            Err(e) => {
-                                clickwriteln!(
-                                    writer,
-                                    "-- format of separater for {} failed: {} --",
-                                    obj.name(),
-                                    e
-                                );
-                                go = Env::call_selection_func(
-                                    obj,
-                                    writer,
-                                    &mut f,
-                                    &mut continue_all,
-                                );
-                            }
+                            Ok(sep) => clickwriteln!(writer, "{}", sep),
+                            Err(e) => clickwriteln!(
+                                writer,
+                                "-- format of separater for {} failed: {} --",
+                                obj.name(),
+                                e
+                            ),
+                        }
+                    }
+                    if let Err(e) = f(obj, writer) {
+                        match on_error {
+                            ErrorStrategy::StopOnFirst => return Err(e),
+                            ErrorStrategy::CollectAll => errors.push((obj.name.clone(), e)),
                         }
-                    } else {
-                        go = Env::call_selection_func(obj, writer, &mut f, &mut continue_all);
                     }
                 }
-                Ok(())
+                if errors.is_empty() {
+                    Ok(())
+                } else {
+                    Err(ClickError::MultiError(errors))
+                }
             }
             ObjectSelection::None => Err(ClickError::CommandError(
                 "No objects currently active".to_string(),
@@ -404,12 +1208,27 @@ impl Env {
         }
     }
 
+    /// Like `run_on_context`, but runs f on the context obj was listed from. That's the active
+    /// context, unless obj came from a listing across several contexts.
+    pub fn run_on_obj_context<F, R>(&self, obj: &KObj, f: F) -> Result<R, ClickError>
+    where
+        F: FnOnce(&crate::k8s::Context) -> Result<R, ClickError>,
+    {
+        match (obj.context.as_deref(), self.context.as_ref()) {
+            (Some(name), Some(active)) if name != active.name => {
+                f(&self.config.get_context(name, &self.click_config)?)
+            }
+            (Some(name), None) => f(&self.config.get_context(name, &self.click_config)?),
+            _ => self.run_on_context(f),
+        }
+    }
+
     /// Add a new task for the env to keep track of
     pub fn add_port_forward(&mut self, pf: PortForward) {
         self.port_forwards.push(pf);
     }
 
-    pub fn get_port_forwards(&mut self) -> std::slice::IterMut<PortForward> {
+    pub fn get_port_forwards(&mut self) -> std::slice::IterMut<'_, PortForward> {
         self.port_forwards.iter_mut()
     }
 
@@ -431,6 +1250,42 @@ impl Env {
             pf.child.kill().unwrap();
         }
         self.port_forwards = Vec::new();
+        if let Some(proxy) = self.proxy.take() {
+            proxy.handle.kill().unwrap_or(());
+        }
+    }
+
+    pub fn jobs(&self) -> &Jobs {
+        &self.jobs
+    }
+
+    pub fn jobs_mut(&mut self) -> &mut Jobs {
+        &mut self.jobs
+    }
+
+    /// Stop all the background jobs, as when click exits
+    pub fn stop_all_jobs(&mut self) {
+        self.jobs.stop_all();
+    }
+
+    pub fn set_proxy(&mut self, proxy: Proxy) {
+        self.proxy = Some(proxy);
+    }
+
+    /// The running proxy, if there is one. A proxy that has exited is forgotten.
+    pub fn proxy(&mut self) -> Option<&Proxy> {
+        let exited = match self.proxy {
+            Some(ref proxy) => !matches!(proxy.handle.try_wait(), Ok(None)),
+            None => false,
+        };
+        if exited {
+            self.proxy = None;
+        }
+        self.proxy.as_ref()
+    }
+
+    pub fn take_proxy(&mut self) -> Option<Proxy> {
+        self.proxy.take()
     }
 
     /// Try and expand alias.
@@ -443,7 +1298,7 @@ impl Env {
         line: &'a str,
         prev_word: Option<&'a str>,
     ) -> ExpandedAlias<'a> {
-        let pos = line.find(char::is_whitespace).unwrap_or_else(|| line.len());
+        let pos = line.find(char::is_whitespace).unwrap_or(line.len());
         let word = &line[0..pos];
         // don't expand if prev_word is Some, and is equal to my word
         // this means an alias maps to itself, and we want to stop expanding
@@ -477,8 +1332,36 @@ impl fmt::Display for Env {
   Edit Mode: {}
   Editor: {}
   Terminal: {}
+  SSH Command: {}
+  Container Runtime: {}
+  Default Timeout: {}
+  Pager: {}
+  Rc File: {}
+  History File: {}
+  History Size: {}
+  History Ignores Dups: {}
+  History Ignores Space: {}
   Range Separator: {}
+  Prompt Format: {}
   Describe Shows Events: {}
+  Remember Namespace: {}
+  Auth Refresh: {}
+  Retries: {}
+  List Page Size: {}
+  Wire Format: {}
+  Max Parallel Requests: {}
+  Job Output Lines: {}
+  Verbose: {}
+  Cert Warn Days: {}
+  Proxy Port: {}
+  Strip Color On Redirect: {}
+  Color: {}
+  Syntax Highlight: {}
+  Theme: {}
+  Impersonating: {}
+  Impersonating Group: {}
+  Dry Run: {}
+  Output Format: {}
 }}",
             if let Some(ref c) = self.context {
                 Green.bold().paint(c.name.as_str())
@@ -507,8 +1390,87 @@ impl fmt::Display for Env {
                     .as_ref()
                     .unwrap_or(&"<unset, will use xterm>".to_owned())
             ),
+            Green.paint(
+                self.click_config
+                    .ssh_command
+                    .as_ref()
+                    .unwrap_or(&"<unset, will use ssh>".to_owned())
+            ),
+            Green.paint(
+                self.click_config
+                    .container_runtime
+                    .as_ref()
+                    .unwrap_or(&"<unset, will detect from the node>".to_owned())
+            ),
+            Green.paint(
+                match (self.request_timeout.as_ref(), self.kubectl_timeout()) {
+                    (Some(timeout), _) => format!("{} (from --timeout)", timeout),
+                    (None, Some(timeout)) => timeout.to_string(),
+                    (None, None) => "<unset, kubectl will wait as long as it takes>".to_string(),
+                }
+            ),
+            Green.paint(
+                self.click_config
+                    .pager
+                    .as_ref()
+                    .unwrap_or(&format!("<unset, will use $PAGER or {}>", DEFAULT_PAGER))
+            ),
+            Green.paint(
+                self.click_config
+                    .rc_file
+                    .as_ref()
+                    .unwrap_or(&"<unset, will use ~/.config/click/clickrc>".to_owned())
+            ),
+            Green.paint(
+                self.click_config
+                    .history_file
+                    .as_ref()
+                    .unwrap_or(&"<unset, will use click.history in the config dir>".to_owned())
+            ),
+            Green.paint(self.click_config.history_size.to_string()),
+            Green.paint(self.click_config.history_ignore_dups.to_string()),
+            Green.paint(self.click_config.history_ignore_space.to_string()),
             Green.paint(&self.click_config.range_separator),
-            Green.paint(&self.click_config.describe_include_events.to_string()),
+            Green.paint(&self.click_config.prompt_format),
+            Green.paint(self.click_config.describe_include_events.to_string()),
+            Green.paint(self.click_config.remember_namespace.to_string()),
+            Green.paint(self.click_config.auth_refresh.to_string()),
+            Green.paint(self.click_config.retries.to_string()),
+            Green.paint(self.click_config.list_page_size.to_string()),
+            Green.paint(self.click_config.wire_format.to_string()),
+            Green.paint(self.click_config.max_parallel_requests.to_string()),
+            Green.paint(self.click_config.job_output_lines.to_string()),
+            Green.paint(self.click_config.verbose.to_string()),
+            Green.paint(self.click_config.cert_warn_days.to_string()),
+            Green.paint(self.click_config.proxy_port.to_string()),
+            Green.paint(self.click_config.strip_color_on_redirect.to_string()),
+            Green.paint(if self.no_color {
+                "Off (NO_COLOR or --no-color)".to_string()
+            } else {
+                self.click_config.color.to_string()
+            }),
+            Green.paint(self.click_config.syntax_highlight.to_string()),
+            Green.paint(if self.click_config.theme_overrides.is_empty() {
+                self.click_config.theme.clone()
+            } else {
+                let overrides: Vec<String> = self
+                    .click_config
+                    .theme_overrides
+                    .iter()
+                    .map(|(role, style)| format!("{}=\"{}\"", role, style))
+                    .collect();
+                format!("{} ({})", self.click_config.theme, overrides.join(", "))
+            }),
+            Green.paint(match self.click_config.impersonate.as_ref() {
+                Some(imp) => imp.user.as_str(),
+                None => "<nobody>",
+            }),
+            Green.paint(match self.click_config.impersonate.as_ref() {
+                Some(imp) if !imp.groups.is_empty() => imp.groups.join(", "),
+                _ => "<none>".to_string(),
+            }),
+            Green.paint(self.dry_run.to_string()),
+            Green.paint(self.output_format.to_string()),
         )
     }
 }
@@ -517,6 +1479,82 @@ impl fmt::Display for Env {
 mod tests {
     use super::*;
     use crate::config::get_test_config;
+    use crate::kobj::ObjType;
+
+    #[test]
+    fn test_expand_home() {
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(expand_home("~/a/b"), home.join("a/b"));
+        assert_eq!(expand_home("~"), home);
+        assert_eq!(expand_home("/a/~/b"), PathBuf::from("/a/~/b"));
+        assert_eq!(expand_home("~a"), PathBuf::from("~a"));
+    }
+
+    fn sh_command(script: &str) -> Option<Command> {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(script);
+        Some(command)
+    }
+
+    #[test]
+    fn cached_names() {
+        let env = Env::new(
+            get_test_config(),
+            ClickConfig::default(),
+            PathBuf::from("/tmp/click.config"),
+        );
+        let key = || ("ctx".to_string(), "pods".to_string(), None);
+        let parse = crate::completer::parse_resource_names;
+        assert_eq!(
+            env.cached_names(key(), || sh_command("echo pod/b; echo pod/a"), parse),
+            Some(vec!["a".to_string(), "b".to_string()])
+        );
+        // fresh names are reused without running anything
+        assert_eq!(
+            env.cached_names(key(), || panic!("shouldn't fetch"), parse),
+            Some(vec!["a".to_string(), "b".to_string()])
+        );
+        // a failed fetch isn't cached
+        let other = || ("ctx".to_string(), "jobs".to_string(), None);
+        assert_eq!(
+            env.cached_names(other(), || sh_command("exit 1"), parse),
+            None
+        );
+        assert_eq!(
+            env.cached_names(other(), || sh_command("echo job/j"), parse),
+            Some(vec!["j".to_string()])
+        );
+    }
+
+    #[test]
+    fn cached_names_slow_fetch() {
+        let mut env = Env::new(
+            get_test_config(),
+            ClickConfig::default(),
+            PathBuf::from("/tmp/click.config"),
+        );
+        let key = || ("ctx".to_string(), "pods".to_string(), None);
+        let parse = crate::completer::parse_resource_names;
+        // completion doesn't wait for a slow fetch, but gets the names once it's done
+        let start = Instant::now();
+        assert_eq!(
+            env.cached_names(key(), || sh_command("sleep 1; echo pod/slow"), parse),
+            None
+        );
+        assert!(start.elapsed() < Duration::from_millis(900));
+        thread::sleep(Duration::from_millis(1200));
+        assert_eq!(
+            env.cached_names(key(), || panic!("already fetching"), parse),
+            Some(vec!["slow".to_string()])
+        );
+
+        // switching namespace throws the names away
+        env.set_namespace(Some("other"));
+        assert_eq!(
+            env.cached_names(key(), || sh_command("echo pod/new"), parse),
+            Some(vec!["new".to_string()])
+        );
+    }
 
     #[test]
     fn try_expand_alias() {
@@ -553,4 +1591,260 @@ mod tests {
         assert_eq!(exp4.expansion, None);
         assert_eq!(exp4.rest, "x");
     }
+
+    #[test]
+    fn alias_cycle() {
+        let mut cc = ClickConfig::default();
+        for (alias, expanded) in [("p", "pods"), ("q", "p -a"), ("r", "q")].iter() {
+            cc.aliases.push(Alias {
+                alias: alias.to_string(),
+                expanded: expanded.to_string(),
+            });
+        }
+        let env = Env::new(get_test_config(), cc, PathBuf::from("/tmp/click.config"));
+        assert_eq!(env.alias_cycle("x", "logs -f"), None);
+        assert_eq!(env.alias_cycle("x", "r"), None);
+        assert_eq!(
+            env.alias_cycle("x", "x --all"),
+            Some(vec!["x".to_string(), "x".to_string()])
+        );
+        assert_eq!(
+            env.alias_cycle("p", "r -w"),
+            Some(vec![
+                "p".to_string(),
+                "r".to_string(),
+                "q".to_string(),
+                "p".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn use_color() {
+        let mut env = Env::new(
+            get_test_config(),
+            ClickConfig::default(),
+            PathBuf::from("/tmp/click.config"),
+        );
+        env.no_color = false; // in case NO_COLOR is set where the tests run
+        assert!(!env.use_color(true));
+        env.click_config.strip_color_on_redirect = false;
+        assert!(env.use_color(true));
+
+        env.set_color(ColorMode::On);
+        assert!(env.use_color(false));
+        env.set_color(ColorMode::Off);
+        assert!(!env.use_color(false));
+        assert!(!env.use_color(true));
+        assert_eq!(
+            env.paint(theme::style(Role::BadStatus), "x").to_string(),
+            "x"
+        );
+
+        env.set_color(ColorMode::On);
+        env.disable_color();
+        assert!(!env.use_color(false));
+        assert!(!env.prompt.contains('\u{1b}'));
+    }
+
+    #[test]
+    fn impersonate_prompt() {
+        let mut env = Env::new(
+            get_test_config(),
+            ClickConfig::default(),
+            PathBuf::from("/tmp/click.config"),
+        );
+        env.disable_color();
+        env.set_impersonate(Some(Impersonation {
+            user: "jane".to_string(),
+            groups: vec!["dev".to_string()],
+        }));
+        assert!(env.prompt.ends_with("[as jane] > "));
+        env.set_impersonate(None);
+        assert!(!env.prompt.contains("[as "));
+        assert!(env.prompt.ends_with("] > "));
+    }
+
+    #[test]
+    fn prompt_format() {
+        let mut env = Env::new(
+            get_test_config(),
+            ClickConfig::default(),
+            PathBuf::from("/tmp/click.config"),
+        );
+        env.disable_color();
+        env.set_namespace(Some("kube-system"));
+        let context = env
+            .context
+            .as_ref()
+            .map(|c| c.name.clone())
+            .unwrap_or_else(|| "none".to_string());
+        assert_eq!(env.prompt, format!("[{}] [kube-system] [none] > ", context));
+
+        env.set_prompt_format("{context}/{namespace} ({selection_count})> ")
+            .unwrap();
+        assert_eq!(env.prompt, format!("{}/kube-system (0)> ", context));
+        let node = |name: &str| KObj {
+            name: name.to_string(),
+            namespace: None,
+            uid: None,
+            context: None,
+            typ: ObjType::Node,
+        };
+        env.set_range(vec![node("node1"), node("node2")]);
+        assert_eq!(env.prompt, format!("{}/kube-system (2)> ", context));
+        env.set_impersonate(Some(Impersonation {
+            user: "jane".to_string(),
+            groups: vec![],
+        }));
+        assert_eq!(
+            env.prompt,
+            format!("{}/kube-system (2)[as jane] > ", context)
+        );
+
+        // bad formats are rejected, keeping the one there was
+        assert!(env.set_prompt_format("{context} {nope}> ").is_err());
+        assert!(env.set_prompt_format("{context").is_err());
+        assert_eq!(
+            env.click_config.prompt_format,
+            "{context}/{namespace} ({selection_count})> "
+        );
+        env.set_impersonate(None);
+        env.set_prompt_format("<{selection}> ").unwrap();
+        assert_eq!(env.prompt, "<2 Nodes selected> ");
+
+        // {selected} is only there when something is
+        env.set_prompt_format("{namespace} {selected}> ").unwrap();
+        assert_eq!(env.prompt, "kube-system [2 objects selected]> ");
+        env.set_range(vec![node("node1")]);
+        assert_eq!(env.prompt, "kube-system [1 object selected]> ");
+        env.clear_current();
+        assert_eq!(env.prompt, "kube-system > ");
+    }
+
+    #[test]
+    fn invalidate_selection() {
+        let mut env = Env::new(
+            get_test_config(),
+            ClickConfig::default(),
+            PathBuf::from("/tmp/click.config"),
+        );
+        env.set_last_objs(vec![KObj {
+            name: "node1".to_string(),
+            namespace: None,
+            uid: None,
+            context: None,
+            typ: ObjType::Node,
+        }]);
+        env.set_current(0);
+        assert!(matches!(
+            env.current_selection(),
+            ObjectSelection::Single(_)
+        ));
+
+        env.invalidate_selection();
+        assert!(matches!(env.current_selection(), ObjectSelection::None));
+        assert!(env.item_at(0).is_none());
+    }
+
+    #[test]
+    fn filter_selection() {
+        let mut env = Env::new(
+            get_test_config(),
+            ClickConfig::default(),
+            PathBuf::from("/tmp/click.config"),
+        );
+        let node = |name: &str| KObj {
+            name: name.to_string(),
+            namespace: None,
+            uid: None,
+            context: None,
+            typ: ObjType::Node,
+        };
+        assert!(!env.clear_filter());
+        let all = vec![node("node1"), node("node2"), node("node3")];
+        env.set_range(all.clone());
+
+        env.filter_selection(vec![node("node1"), node("node3")]);
+        assert_eq!(
+            env.current_selection(),
+            &ObjectSelection::Range(vec![node("node1"), node("node3")])
+        );
+        // filtering again still remembers the original selection
+        env.filter_selection(vec![node("node3")]);
+        assert_eq!(
+            env.current_selection(),
+            &ObjectSelection::Single(node("node3"))
+        );
+        assert!(env.clear_filter());
+        assert_eq!(
+            env.current_selection(),
+            &ObjectSelection::Range(all.clone())
+        );
+        assert!(!env.clear_filter());
+
+        // a new selection forgets the filter
+        env.filter_selection(vec![node("node2")]);
+        env.set_range(all);
+        assert!(!env.clear_filter());
+    }
+
+    #[test]
+    fn apply_to_selection_errors() {
+        let mut env = Env::new(
+            get_test_config(),
+            ClickConfig::default(),
+            PathBuf::from("/tmp/click.config"),
+        );
+        let node = |name: &str| KObj {
+            name: name.to_string(),
+            namespace: None,
+            uid: None,
+            context: None,
+            typ: ObjType::Node,
+        };
+        env.set_range(vec![node("node1"), node("node2"), node("node3")]);
+        let fail_odd = |obj: &KObj, _: &mut ClickWriter| {
+            if obj.name() == "node2" {
+                Ok(())
+            } else {
+                Err(ClickError::CommandError(format!("{} failed", obj.name())))
+            }
+        };
+        let mut writer = ClickWriter::with_buffer(vec![], false);
+
+        let mut applied = vec![];
+        let res =
+            env.apply_to_selection(&mut writer, None, ErrorStrategy::StopOnFirst, |obj, w| {
+                applied.push(obj.name().to_string());
+                fail_odd(obj, w)
+            });
+        assert!(matches!(res, Err(ClickError::CommandError(_))));
+        assert_eq!(applied, vec!["node1"]);
+
+        let mut applied = vec![];
+        let res = env.apply_to_selection(&mut writer, None, ErrorStrategy::CollectAll, |obj, w| {
+            applied.push(obj.name().to_string());
+            fail_odd(obj, w)
+        });
+        assert_eq!(applied, vec!["node1", "node2", "node3"]);
+        match res {
+            Err(ClickError::MultiError(errors)) => {
+                let names: Vec<&str> = errors.iter().map(|(name, _)| name.as_str()).collect();
+                assert_eq!(names, vec!["node1", "node3"]);
+                assert_eq!(
+                    ClickError::MultiError(errors).to_string(),
+                    "Operation failed for 2 objects:\n  \
+                     node1: Error running command: node1 failed\n  \
+                     node3: Error running command: node3 failed"
+                );
+            }
+            _ => panic!("expected a MultiError"),
+        }
+
+        env.set_range(vec![node("node2")]);
+        assert!(env
+            .apply_to_selection(&mut writer, None, ErrorStrategy::CollectAll, fail_odd)
+            .is_ok());
+    }
 }
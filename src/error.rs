@@ -62,13 +62,37 @@ impl error::Error for ClickErrNo {
 #[derive(Debug)]
 pub enum ClickError {
     CommandError(String),
+    /// kubectl ran, but exited with a non-zero exit code. stderr is whatever it printed there, if
+    /// that was captured.
+    KubectlError {
+        exit_code: i32,
+        stderr: String,
+    },
+    /// Errors from applying an operation to a range of objects, with the name of the object each
+    /// one was for
+    MultiError(Vec<(String, ClickError)>),
+    /// The api server returned a Status object describing why a request failed
+    Api {
+        code: u16,
+        reason: String,
+        message: String,
+    },
     ParseErr(String),
+    /// The diff command found this many objects that differ from what's live. This is an error so
+    /// scripts can tell drift apart from everything matching.
+    Drift(usize),
+    /// A request that was retried, but failed every time. error is from the last attempt.
+    RetriesExhausted {
+        attempts: u32,
+        error: Box<ClickError>,
+    },
     Kube(ClickErrNo),
     ConfigFileError(String),
     DecodeError(base64::DecodeError),
     Io(io::Error),
     SerdeJson(serde_json::Error),
     SerdeYaml(serde_yaml::Error),
+    Protobuf(prost::DecodeError),
     RequestError(k8s_openapi::RequestError),
     ResponseError(k8s_openapi::ResponseError),
     Clap(clap::Error),
@@ -82,13 +106,55 @@ impl fmt::Display for ClickError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             ClickError::CommandError(ref s) => write!(f, "Error running command: {}", s),
+            ClickError::KubectlError {
+                exit_code,
+                ref stderr,
+            } => {
+                if stderr.is_empty() {
+                    write!(f, "kubectl exited with code {}", exit_code)
+                } else {
+                    write!(f, "kubectl exited with code {}: {}", exit_code, stderr)?;
+                    write_hint(f, kubectl_reason(stderr))
+                }
+            }
+            ClickError::Api {
+                code,
+                ref reason,
+                ref message,
+            } => {
+                if message.is_empty() {
+                    write!(f, "Api server returned {} {}", code, reason)?;
+                } else {
+                    write!(f, "{}", message)?;
+                }
+                write_hint(f, Some(reason))
+            }
+            ClickError::MultiError(ref errors) => {
+                write!(f, "Operation failed for {} objects:", errors.len())?;
+                for (name, err) in errors.iter() {
+                    write!(f, "\n  {}: {}", name, err)?;
+                }
+                Ok(())
+            }
             ClickError::ParseErr(ref s) => write!(f, "Parse Error: {}", s),
+            ClickError::Drift(count) => write!(
+                f,
+                "{} object{} differ{} from what's live",
+                count,
+                if count == 1 { "" } else { "s" },
+                if count == 1 { "s" } else { "" }
+            ),
+            ClickError::RetriesExhausted {
+                attempts,
+                ref error,
+            } => write!(f, "{} (gave up after {} attempts)", error, attempts),
             ClickError::Kube(ref err) => write!(f, "Kube Error: {}", err),
             ClickError::ConfigFileError(ref s) => write!(f, "Failed to get config: {}", s),
             ClickError::DecodeError(ref err) => write!(f, "Base64 decode error: {}", err),
             ClickError::Io(ref err) => write!(f, "IO error: {}", err),
             ClickError::SerdeJson(ref err) => write!(f, "Serde json error: {}", err),
             ClickError::SerdeYaml(ref err) => write!(f, "Serde yaml error: {}", err),
+            ClickError::Protobuf(ref err) => write!(f, "Protobuf decode error: {}", err),
             ClickError::RequestError(ref err) => match err {
                 k8s_openapi::RequestError::Http(e) => {
                     write!(f, "Error preparing HTTP request: {}", e)
@@ -123,13 +189,19 @@ impl error::Error for ClickError {
     fn cause(&self) -> Option<&dyn error::Error> {
         match *self {
             ClickError::CommandError(_) => None,
+            ClickError::KubectlError { .. } => None,
+            ClickError::MultiError(_) => None,
+            ClickError::Api { .. } => None,
             ClickError::ParseErr(_) => None,
+            ClickError::Drift(_) => None,
+            ClickError::RetriesExhausted { ref error, .. } => Some(error.as_ref()),
             ClickError::Kube(ref err) => Some(err),
             ClickError::ConfigFileError(_) => None,
             ClickError::DecodeError(ref err) => Some(err),
             ClickError::Io(ref err) => Some(err),
             ClickError::SerdeJson(ref err) => Some(err),
             ClickError::SerdeYaml(ref err) => Some(err),
+            ClickError::Protobuf(ref err) => Some(err),
             ClickError::RequestError(ref err) => Some(err),
             ClickError::ResponseError(ref err) => Some(err),
             ClickError::Clap(ref err) => Some(err),
@@ -141,6 +213,68 @@ impl error::Error for ClickError {
     }
 }
 
+/// Something to try for an api error with reason (from a Status, i.e. NotFound), if we have an
+/// idea
+fn reason_hint(reason: &str) -> Option<&'static str> {
+    match reason {
+        "NotFound" => Some(
+            "Check the name, and that it's in the current namespace (or pass --namespace/-n to \
+             look in another)",
+        ),
+        "Forbidden" => Some(
+            "Your user isn't allowed to do this. 'can-i VERB RESOURCE' checks what you can do, and \
+             'can-i --list' lists it",
+        ),
+        "Conflict" => {
+            Some("The object changed since it was read. Fetch it again, and retry the change")
+        }
+        "Unauthorized" => Some("The api server didn't accept your credentials for this context"),
+        _ => None,
+    }
+}
+
+fn write_hint(f: &mut fmt::Formatter, reason: Option<&str>) -> fmt::Result {
+    match reason.and_then(reason_hint) {
+        Some(hint) => write!(f, "\n  Hint: {}", hint),
+        None => Ok(()),
+    }
+}
+
+/// The reason from kubectl's "Error from server (Reason): message" output
+fn kubectl_reason(stderr: &str) -> Option<&str> {
+    let (_, rest) = stderr.split_once("Error from server (")?;
+    rest.split_once(')').map(|(reason, _)| reason)
+}
+
+impl ClickError {
+    /// If body is a Status object for a failed request, the Api error for it. code is the http
+    /// status, used if the Status doesn't have one.
+    pub fn from_status(code: u16, body: &[u8]) -> Option<ClickError> {
+        let status: Value = serde_json::from_slice(body).ok()?;
+        if status.get("kind")?.as_str()? != "Status"
+            || status.get("status").and_then(|s| s.as_str()) == Some("Success")
+        {
+            return None;
+        }
+        let field = |name: &str| {
+            status
+                .get(name)
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string()
+        };
+        Some(ClickError::Api {
+            code: status
+                .get("code")
+                .and_then(|c| c.as_u64())
+                .map(|c| c as u16)
+                .unwrap_or(code),
+            reason: field("reason"),
+            message: field("message"),
+        })
+    }
+}
+
 // TODO: Macro all below
 
 impl From<io::Error> for ClickError {
@@ -161,6 +295,12 @@ impl From<serde_yaml::Error> for ClickError {
     }
 }
 
+impl From<prost::DecodeError> for ClickError {
+    fn from(err: prost::DecodeError) -> ClickError {
+        ClickError::Protobuf(err)
+    }
+}
+
 impl From<base64::DecodeError> for ClickError {
     fn from(err: base64::DecodeError) -> ClickError {
         ClickError::DecodeError(err)
@@ -0,0 +1,159 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Simple fuzzy matching of names, used to resolve partially typed contexts, namespaces, and so on
+
+/// The result of trying to match a query against a set of candidates
+#[derive(Debug, PartialEq)]
+pub enum FuzzyMatch<'a> {
+    /// The query exactly matched this candidate
+    Exact(&'a str),
+    /// The query matched only this candidate
+    Single(&'a str),
+    /// The query matched all of these candidates
+    Multiple(Vec<&'a str>),
+    /// Nothing matched
+    None,
+}
+
+/// Check if all the chars in query appear in candidate, in order (case-insensitive)
+pub fn is_subsequence(query: &str, candidate: &str) -> bool {
+    let mut cand_chars = candidate.chars().flat_map(char::to_lowercase);
+    query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|qc| cand_chars.any(|cc| cc == qc))
+}
+
+/// Return all candidates that match query. If any candidates contain query as a (case-insensitive)
+/// substring only those are returned, otherwise any candidates that contain query as a
+/// subsequence are returned.
+pub fn fuzzy_candidates<'a, I>(query: &str, candidates: I) -> Vec<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let lower_query = query.to_lowercase();
+    let mut substrings = vec![];
+    let mut subsequences = vec![];
+    for candidate in candidates.into_iter() {
+        if candidate.to_lowercase().contains(&lower_query) {
+            substrings.push(candidate);
+        } else if is_subsequence(query, candidate) {
+            subsequences.push(candidate);
+        }
+    }
+    if substrings.is_empty() {
+        subsequences
+    } else {
+        substrings
+    }
+}
+
+/// Return the candidates to offer when tab completing query. If any candidates start with query
+/// only those are returned, so typing the start of a name narrows things down like a normal shell
+/// completion. Otherwise this falls back to `fuzzy_candidates`.
+pub fn completion_candidates<'a, I>(query: &str, candidates: I) -> Vec<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let candidates: Vec<&'a str> = candidates.into_iter().collect();
+    let prefixed: Vec<&'a str> = candidates
+        .iter()
+        .copied()
+        .filter(|c| c.starts_with(query))
+        .collect();
+    if prefixed.is_empty() {
+        fuzzy_candidates(query, candidates)
+    } else {
+        prefixed
+    }
+}
+
+/// Match query against candidates. An exact match always wins, otherwise see `fuzzy_candidates`
+pub fn fuzzy_match<'a, I>(query: &str, candidates: I) -> FuzzyMatch<'a>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let candidates: Vec<&'a str> = candidates.into_iter().collect();
+    if let Some(exact) = candidates.iter().find(|c| **c == query) {
+        return FuzzyMatch::Exact(exact);
+    }
+    let mut matches = fuzzy_candidates(query, candidates);
+    match matches.len() {
+        0 => FuzzyMatch::None,
+        1 => FuzzyMatch::Single(matches.remove(0)),
+        _ => FuzzyMatch::Multiple(matches),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAMES: &[&str] = &["prod-us-east", "prod-eu-west", "staging", "dev", "Predev"];
+
+    #[test]
+    fn test_subsequence() {
+        assert!(is_subsequence("pue", "prod-us-east"));
+        assert!(is_subsequence("PUE", "prod-us-east"));
+        assert!(is_subsequence("", "dev"));
+        assert!(!is_subsequence("eup", "prod-us-east"));
+        assert!(!is_subsequence("devs", "dev"));
+    }
+
+    #[test]
+    fn test_fuzzy_match() {
+        assert_eq!(
+            fuzzy_match("dev", NAMES.iter().copied()),
+            FuzzyMatch::Exact("dev")
+        );
+        assert_eq!(
+            fuzzy_match("stag", NAMES.iter().copied()),
+            FuzzyMatch::Single("staging")
+        );
+        assert_eq!(
+            fuzzy_match("EU", NAMES.iter().copied()),
+            FuzzyMatch::Single("prod-eu-west")
+        );
+        assert_eq!(
+            fuzzy_match("prod", NAMES.iter().copied()),
+            FuzzyMatch::Multiple(vec!["prod-us-east", "prod-eu-west"])
+        );
+        // substring matches take precedence over subsequence matches
+        assert_eq!(
+            fuzzy_match("pre", NAMES.iter().copied()),
+            FuzzyMatch::Single("Predev")
+        );
+        assert_eq!(
+            fuzzy_match("pwst", NAMES.iter().copied()),
+            FuzzyMatch::Single("prod-eu-west")
+        );
+        assert_eq!(fuzzy_match("xyz", NAMES.iter().copied()), FuzzyMatch::None);
+    }
+
+    #[test]
+    fn test_completion_candidates() {
+        assert_eq!(
+            completion_candidates("pr", NAMES.iter().copied()),
+            vec!["prod-us-east", "prod-eu-west"]
+        );
+        assert_eq!(completion_candidates("", NAMES.iter().copied()), NAMES);
+        // no prefix matches, so fall back to fuzzy matching
+        assert_eq!(
+            completion_candidates("west", NAMES.iter().copied()),
+            vec!["prod-eu-west"]
+        );
+        assert!(completion_candidates("xyz", NAMES.iter().copied()).is_empty());
+    }
+}
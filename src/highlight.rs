@@ -0,0 +1,252 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Syntax highlighting for yaml and json output. Keys are cyan, strings green, numbers yellow,
+//! and booleans magenta. Json is highlighted as it's serialized (see PrettyColorFormatter in
+//! output.rs), yaml is highlighted a line at a time here, which is enough for the yaml we print.
+
+use ansi_term::Colour::{Cyan, Green, Purple, Yellow};
+use ansi_term::Style;
+use regex::Regex;
+
+lazy_static! {
+    /// yaml 1.2 ints and floats (and the hex, octal, infinity and nan forms)
+    static ref NUMBER_RE: Regex = Regex::new(concat!(
+        r"^[-+]?(\d[\d_]*(\.\d*)?([eE][-+]?\d+)?|\.\d+([eE][-+]?\d+)?",
+        r"|0x[0-9a-fA-F]+|0o[0-7]+|\.inf|\.Inf|\.nan|\.NaN)$"
+    ))
+    .unwrap();
+}
+
+pub fn key_style() -> Style {
+    Cyan.normal()
+}
+
+pub fn string_style() -> Style {
+    Green.normal()
+}
+
+pub fn number_style() -> Style {
+    Yellow.normal()
+}
+
+/// ansi_term calls magenta purple
+pub fn bool_style() -> Style {
+    Purple.normal()
+}
+
+/// The style for a yaml scalar value, or None if it shouldn't be colored (null, or an empty
+/// collection)
+fn value_style(value: &str) -> Option<Style> {
+    match value {
+        "" | "~" | "null" | "Null" | "NULL" | "{}" | "[]" => None,
+        "true" | "True" | "TRUE" | "false" | "False" | "FALSE" => Some(bool_style()),
+        _ if NUMBER_RE.is_match(value) => Some(number_style()),
+        _ => Some(string_style()),
+    }
+}
+
+fn paint(style: Option<Style>, text: &str) -> String {
+    match style {
+        Some(style) if !text.is_empty() => style.paint(text).to_string(),
+        _ => text.to_string(),
+    }
+}
+
+/// If line (with indentation and any "- " removed) starts with a mapping key, return the key and
+/// the rest of the line after the ':'
+fn split_key(line: &str) -> Option<(&str, &str)> {
+    let key_end = match line.chars().next()? {
+        quote @ '"' | quote @ '\'' => {
+            // find the closing quote, skipping escaped ones
+            let mut escaped = false;
+            let mut end = None;
+            for (i, c) in line.char_indices().skip(1) {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' && quote == '"' {
+                    escaped = true;
+                } else if c == quote {
+                    end = Some(i + 1);
+                    break;
+                }
+            }
+            let end = end?;
+            if !line[end..].starts_with(':') {
+                return None;
+            }
+            end
+        }
+        // flow collections and comments aren't keys
+        '{' | '[' | '#' => return None,
+        _ => match line.find(": ") {
+            Some(i) => i,
+            None if line.ends_with(':') => line.len() - 1,
+            None => return None,
+        },
+    };
+    let rest = &line[key_end + 1..];
+    if rest.is_empty() || rest.starts_with(' ') {
+        Some((&line[..key_end], rest))
+    } else {
+        None
+    }
+}
+
+/// Is value the start of a block scalar, like `|` or `>-`
+fn is_block_indicator(value: &str) -> bool {
+    let mut chars = value.chars();
+    matches!(chars.next(), Some('|') | Some('>'))
+        && chars.all(|c| c == '-' || c == '+' || c.is_ascii_digit())
+}
+
+/// Highlight yaml text. Anything that doesn't look like yaml we understand is left alone.
+pub fn highlight_yaml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() * 2);
+    // when inside a block scalar, the indentation its lines must be deeper than
+    let mut block_indent: Option<usize> = None;
+    for line in text.split_inclusive('\n') {
+        let (line, newline) = match line.strip_suffix('\n') {
+            Some(line) => (line, "\n"),
+            None => (line, ""),
+        };
+        let indent = line.len() - line.trim_start_matches(' ').len();
+        if let Some(block) = block_indent {
+            if line.trim().is_empty() || indent > block {
+                out.push_str(&line[..indent]);
+                out.push_str(&paint(Some(string_style()), &line[indent..]));
+                out.push_str(newline);
+                continue;
+            }
+            block_indent = None;
+        }
+
+        // sequence markers, possibly more than one, as in "- - a"
+        let mut content_start = indent;
+        while line[content_start..].starts_with("- ") || &line[content_start..] == "-" {
+            content_start = (content_start + 2).min(line.len());
+        }
+        out.push_str(&line[..content_start]);
+        let content = &line[content_start..];
+
+        if content.starts_with('#') || (indent == 0 && (content == "---" || content == "...")) {
+            out.push_str(content);
+        } else if let Some((key, rest)) = split_key(content) {
+            out.push_str(&paint(Some(key_style()), key));
+            out.push(':');
+            let value = rest.trim_start();
+            out.push_str(&rest[..rest.len() - value.len()]);
+            if is_block_indicator(value) {
+                out.push_str(value);
+                block_indent = Some(content_start);
+            } else {
+                out.push_str(&paint(value_style(value), value));
+            }
+        } else if is_block_indicator(content) {
+            out.push_str(content);
+            block_indent = Some(indent);
+        } else {
+            out.push_str(&paint(value_style(content), content));
+        }
+        out.push_str(newline);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::strip_ansi;
+
+    fn key(s: &str) -> String {
+        key_style().paint(s).to_string()
+    }
+
+    fn string(s: &str) -> String {
+        string_style().paint(s).to_string()
+    }
+
+    #[test]
+    fn test_value_style() {
+        assert_eq!(value_style("true"), Some(bool_style()));
+        assert_eq!(value_style("False"), Some(bool_style()));
+        assert_eq!(value_style("3"), Some(number_style()));
+        assert_eq!(value_style("-1.5e3"), Some(number_style()));
+        assert_eq!(value_style("nginx"), Some(string_style()));
+        assert_eq!(value_style("\"3\""), Some(string_style()));
+        assert_eq!(value_style("1.2.3"), Some(string_style()));
+        assert_eq!(value_style("~"), None);
+        assert_eq!(value_style("{}"), None);
+    }
+
+    #[test]
+    fn test_split_key() {
+        assert_eq!(split_key("name: web"), Some(("name", " web")));
+        assert_eq!(split_key("metadata:"), Some(("metadata", "")));
+        assert_eq!(split_key("\"a: b\": c"), Some(("\"a: b\"", " c")));
+        assert_eq!(split_key("http://example.com"), None);
+        assert_eq!(split_key("just a string"), None);
+        assert_eq!(split_key("'quoted'"), None);
+    }
+
+    #[test]
+    fn test_highlight_yaml() {
+        let yaml = "---
+apiVersion: v1
+kind: Pod
+metadata:
+  name: web
+  labels:
+    app: \"web\"
+spec:
+  replicas: 3
+  paused: false
+  args:
+    - --verbose
+    - port: 80
+  script: |
+    echo hi
+    exit: 0
+  after: ~
+";
+        let highlighted = highlight_yaml(yaml);
+        // highlighting only adds escape sequences
+        assert_eq!(strip_ansi(&highlighted), yaml);
+        let lines: Vec<&str> = highlighted.lines().collect();
+        assert_eq!(lines[0], "---");
+        assert_eq!(lines[1], format!("{}: {}", key("apiVersion"), string("v1")));
+        assert_eq!(lines[3], format!("{}:", key("metadata")));
+        assert_eq!(
+            lines[6],
+            format!("    {}: {}", key("app"), string("\"web\""))
+        );
+        assert_eq!(
+            lines[8],
+            format!("  {}: {}", key("replicas"), number_style().paint("3"))
+        );
+        assert_eq!(
+            lines[9],
+            format!("  {}: {}", key("paused"), bool_style().paint("false"))
+        );
+        assert_eq!(lines[11], format!("    - {}", string("--verbose")));
+        assert_eq!(
+            lines[12],
+            format!("    - {}: {}", key("port"), number_style().paint("80"))
+        );
+        // block scalar contents are strings, even if they look like keys
+        assert_eq!(lines[13], format!("  {}: |", key("script")));
+        assert_eq!(lines[15], format!("    {}", string("exit: 0")));
+        assert_eq!(lines[16], format!("  {}: ~", key("after")));
+    }
+}
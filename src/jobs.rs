@@ -0,0 +1,495 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Jobs that run in the background while the repl carries on, like a 'wait --notify' watching for
+//! a condition, or a command run with '&'. Each job runs on its own thread, and is asked to stop
+//! with a flag it checks. Commands run in the background are run by another click, whose output
+//! is kept so 'fg' can show it.
+
+use crate::env::{Env, ObjectSelection};
+use crate::error::ClickError;
+use crate::selection;
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum JobStatus {
+    Running,
+    /// finished, with a summary of what happened
+    Done(String),
+    Failed(String),
+    Killed,
+}
+
+impl fmt::Display for JobStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JobStatus::Running => write!(f, "Running"),
+            JobStatus::Done(summary) => write!(f, "Done: {}", summary),
+            JobStatus::Failed(err) => write!(f, "Failed: {}", err),
+            JobStatus::Killed => write!(f, "Killed"),
+        }
+    }
+}
+
+/// The last lines a background command printed. Once there are more than max_lines, the oldest
+/// are dropped.
+pub struct OutputBuffer {
+    lines: VecDeque<String>,
+    max_lines: usize,
+    /// how many lines have been added, including the dropped ones
+    total: usize,
+}
+
+impl OutputBuffer {
+    pub fn new(max_lines: usize) -> OutputBuffer {
+        OutputBuffer {
+            lines: VecDeque::new(),
+            max_lines,
+            total: 0,
+        }
+    }
+
+    pub fn push(&mut self, line: String) {
+        self.lines.push_back(line);
+        self.total += 1;
+        while self.lines.len() > self.max_lines {
+            self.lines.pop_front();
+        }
+    }
+
+    /// The lines added after the first seen lines, and how many of those were dropped before
+    /// they could be returned
+    pub fn since(&self, seen: usize) -> (usize, Vec<String>) {
+        let first = self.total - self.lines.len();
+        let dropped = first.saturating_sub(seen);
+        let skip = seen.max(first) - first;
+        (dropped, self.lines.iter().skip(skip).cloned().collect())
+    }
+}
+
+pub struct Job {
+    pub id: usize,
+    /// what the job is doing, i.e. the command that started it
+    pub description: String,
+    pub started: Instant,
+    status: Arc<Mutex<JobStatus>>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+    /// what the job's command has printed, for jobs running a command
+    output: Option<Arc<Mutex<OutputBuffer>>>,
+}
+
+impl Job {
+    pub fn status(&self) -> JobStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    pub fn output(&self) -> Option<Arc<Mutex<OutputBuffer>>> {
+        self.output.clone()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.status() == JobStatus::Running
+    }
+
+    /// Ask the job to stop, and wait for it to
+    fn stop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            handle.join().unwrap_or(());
+        }
+    }
+}
+
+/// The background jobs of a session. Ids start at 1 and aren't reused.
+#[derive(Default)]
+pub struct Jobs {
+    last_id: usize,
+    jobs: Vec<Job>,
+}
+
+impl Jobs {
+    /// Start a job running f on its own thread, returning its id. f is passed the id and the flag
+    /// that's set when the job should stop, which it should check every so often. What f returns
+    /// is the job's status once it's done, unless it was stopped, which makes it Killed.
+    pub fn start<F>(&mut self, description: &str, f: F) -> usize
+    where
+        F: FnOnce(usize, &AtomicBool) -> Result<String, ClickError> + Send + 'static,
+    {
+        self.start_job(description, None, f)
+    }
+
+    /// Start a job running command, keeping the last max_lines lines of its output (stdout and
+    /// stderr together). The command runs in its own process group, and it and anything it
+    /// started are killed if the job is stopped.
+    pub fn start_command(
+        &mut self,
+        description: &str,
+        mut command: Command,
+        max_lines: usize,
+    ) -> Result<usize, ClickError> {
+        detach(&mut command);
+        let mut child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                ClickError::CommandError(format!("Could not start background job: {}", e))
+            })?;
+        let output = Arc::new(Mutex::new(OutputBuffer::new(max_lines)));
+        let mut readers = vec![];
+        if let Some(stdout) = child.stdout.take() {
+            readers.push(read_lines(stdout, output.clone()));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            readers.push(read_lines(stderr, output.clone()));
+        }
+        Ok(self.start_job(description, Some(output), move |_, stop| {
+            let status = loop {
+                if stop.load(Ordering::SeqCst) {
+                    kill_group(&mut child);
+                    // the readers finish once everything holding the pipes is gone
+                    return Ok(String::new());
+                }
+                match child.try_wait()? {
+                    Some(status) => break status,
+                    None => thread::sleep(Duration::from_millis(100)),
+                }
+            };
+            for reader in readers.into_iter() {
+                reader.join().unwrap_or(());
+            }
+            match status.code() {
+                Some(0) => Ok("finished".to_string()),
+                Some(code) => Err(ClickError::CommandError(format!(
+                    "exited with code {}",
+                    code
+                ))),
+                None => Err(ClickError::CommandError("was killed".to_string())),
+            }
+        }))
+    }
+
+    fn start_job<F>(
+        &mut self,
+        description: &str,
+        output: Option<Arc<Mutex<OutputBuffer>>>,
+        f: F,
+    ) -> usize
+    where
+        F: FnOnce(usize, &AtomicBool) -> Result<String, ClickError> + Send + 'static,
+    {
+        self.last_id += 1;
+        let id = self.last_id;
+        let status = Arc::new(Mutex::new(JobStatus::Running));
+        let stop = Arc::new(AtomicBool::new(false));
+        let (job_status, job_stop) = (status.clone(), stop.clone());
+        let handle = thread::spawn(move || {
+            let result = f(id, &job_stop);
+            *job_status.lock().unwrap() = if job_stop.load(Ordering::SeqCst) {
+                JobStatus::Killed
+            } else {
+                match result {
+                    Ok(summary) => JobStatus::Done(summary),
+                    Err(e) => JobStatus::Failed(e.to_string()),
+                }
+            };
+        });
+        self.jobs.push(Job {
+            id,
+            description: description.to_string(),
+            started: Instant::now(),
+            status,
+            stop,
+            handle: Some(handle),
+            output,
+        });
+        id
+    }
+
+    /// The id the next job started will get
+    pub fn next_id(&self) -> usize {
+        self.last_id + 1
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Job> {
+        self.jobs.iter().find(|job| job.id == id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Job> {
+        self.jobs.iter()
+    }
+
+    pub fn running_count(&self) -> usize {
+        self.jobs.iter().filter(|job| job.is_running()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    /// Stop the job with id, waiting for it to finish
+    pub fn kill(&mut self, id: usize) -> Result<(), ClickError> {
+        let job = self
+            .jobs
+            .iter_mut()
+            .find(|job| job.id == id)
+            .ok_or_else(|| ClickError::CommandError(format!("No job {}", id)))?;
+        if !job.is_running() {
+            return Err(ClickError::CommandError(format!(
+                "Job {} isn't running ({})",
+                id,
+                job.status()
+            )));
+        }
+        job.stop();
+        Ok(())
+    }
+
+    /// Forget about the jobs that have finished, once they've been reported
+    pub fn remove_finished(&mut self) {
+        self.jobs.retain(|job| job.is_running());
+    }
+
+    /// Stop all the jobs, as when click exits
+    pub fn stop_all(&mut self) {
+        for job in self.jobs.iter_mut() {
+            job.stop();
+        }
+        self.jobs.clear();
+    }
+}
+
+/// Run command in its own process group, so the ctrl-c that stops a command in the foreground
+/// doesn't also kill the processes of background jobs
+pub fn detach(command: &mut Command) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+}
+
+/// Add each line read from pipe to output, on its own thread, until the pipe closes
+fn read_lines<R: Read + Send + 'static>(
+    pipe: R,
+    output: Arc<Mutex<OutputBuffer>>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        for line in BufReader::new(pipe).lines().map_while(Result::ok) {
+            output.lock().unwrap().push(line);
+        }
+    })
+}
+
+/// Stop child, and on unix everything else in its process group, which is everything it started
+/// if it was detached
+fn kill_group(child: &mut Child) {
+    #[cfg(unix)]
+    {
+        Command::new("kill")
+            .arg("-TERM")
+            .arg("--")
+            .arg(format!("-{}", child.id()))
+            .stderr(Stdio::null())
+            .status()
+            .map(|_| ())
+            .unwrap_or(());
+    }
+    child.kill().unwrap_or(());
+    child.wait().map(|_| ()).unwrap_or(());
+}
+
+/// A command that runs line in another click, with the session's context, namespace, selection
+/// and settings, for running line in the background. Files it needs are put in env's tempdir,
+/// named for job id.
+pub fn click_command(env: &Env, line: &str, id: usize) -> Result<Command, ClickError> {
+    let tempdir = env.tempdir.as_ref().map_err(|e| {
+        ClickError::CommandError(format!("No temporary directory for background jobs: {}", e))
+    })?;
+    // the settings as they are now, rather than as they were last saved
+    let config_dir = tempdir.path().join(format!("job-{}", id));
+    std::fs::create_dir_all(&config_dir)?;
+    let mut click_config = env.click_config.clone();
+    click_config.namespace = env.namespace.clone();
+    click_config.context = env.context.as_ref().map(|c| c.name.clone());
+    click_config.save_to_file(&config_dir.join("click.config").to_string_lossy())?;
+
+    let objs = match env.current_selection() {
+        ObjectSelection::Single(obj) => vec![obj.clone()],
+        ObjectSelection::Range(range) => range.clone(),
+        ObjectSelection::None => vec![],
+    };
+    let commands = if objs.is_empty() {
+        line.to_string()
+    } else {
+        let context = env.context.as_ref().map(|c| c.name.as_str());
+        let file = config_dir.join("selection.json");
+        std::fs::write(
+            &file,
+            serde_json::to_string(&selection::selection_to_json(&objs, context))?,
+        )?;
+        format!(
+            "load --quiet {}; {}",
+            crate::command::shell_quote(&file.to_string_lossy()),
+            line
+        )
+    };
+
+    let exe = std::env::current_exe()?;
+    let mut command = Command::new(exe);
+    command.env(
+        "KUBECONFIG",
+        std::env::join_paths(&env.config.source_paths)?,
+    );
+    command.arg("--config_dir").arg(&config_dir);
+    if let Some(context) = env.context.as_ref() {
+        command.arg("--context").arg(&context.name);
+    }
+    if let Some(namespace) = env.namespace.as_ref() {
+        command.arg("--namespace").arg(namespace);
+    }
+    if let Some(imp) = env.impersonating() {
+        command.arg("--as").arg(&imp.user);
+        for group in imp.groups.iter() {
+            command.arg("--as-group").arg(group);
+        }
+    }
+    if let Some(timeout) = env.request_timeout.as_ref() {
+        command.arg("--timeout").arg(timeout);
+    }
+    if env.click_config.verbose > 0 {
+        command.arg(format!("--v={}", env.click_config.verbose));
+    }
+    if env.dry_run {
+        command.arg("--dry-run");
+    }
+    if !env.use_color(false) {
+        command.arg("--no-color");
+    }
+    command
+        .arg("--output")
+        .arg(env.output_format.to_string())
+        .arg("-c")
+        .arg(commands);
+    Ok(command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    fn wait_until_done(jobs: &Jobs, id: usize) -> JobStatus {
+        for _ in 0..100 {
+            let status = jobs.iter().find(|job| job.id == id).unwrap().status();
+            if status != JobStatus::Running {
+                return status;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("job {} didn't finish", id);
+    }
+
+    #[test]
+    fn test_jobs() {
+        let mut jobs = Jobs::default();
+        let done = jobs.start("done", |id, _| Ok(format!("job {}", id)));
+        let failed = jobs.start("failed", |_, _| {
+            Err(ClickError::CommandError("nope".to_string()))
+        });
+        let forever = jobs.start("forever", |_, stop| {
+            while !stop.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_millis(5));
+            }
+            Ok("stopped".to_string())
+        });
+        assert_eq!((done, failed, forever), (1, 2, 3));
+        assert_eq!(
+            wait_until_done(&jobs, done),
+            JobStatus::Done("job 1".to_string())
+        );
+        assert_eq!(
+            wait_until_done(&jobs, failed),
+            JobStatus::Failed("Error running command: nope".to_string())
+        );
+
+        assert!(jobs
+            .iter()
+            .find(|job| job.id == forever)
+            .unwrap()
+            .is_running());
+        assert!(jobs.kill(done).is_err()); // already done
+        assert!(jobs.kill(7).is_err());
+        jobs.kill(forever).unwrap();
+        assert_eq!(wait_until_done(&jobs, forever), JobStatus::Killed);
+
+        jobs.remove_finished();
+        assert!(jobs.is_empty());
+        // ids aren't reused
+        assert_eq!(jobs.start("next", |_, _| Ok(String::new())), 4);
+        jobs.stop_all();
+        assert!(jobs.is_empty());
+    }
+
+    #[test]
+    fn test_output_buffer() {
+        let mut output = OutputBuffer::new(3);
+        assert_eq!(output.since(0), (0, vec![]));
+        output.push("a".to_string());
+        output.push("b".to_string());
+        assert_eq!(output.since(0), (0, vec!["a".to_string(), "b".to_string()]));
+        assert_eq!(output.since(1), (0, vec!["b".to_string()]));
+        for line in ["c", "d", "e"].iter() {
+            output.push(line.to_string());
+        }
+        // a and b were dropped to make room
+        let kept: Vec<String> = ["c", "d", "e"].iter().map(|l| l.to_string()).collect();
+        assert_eq!(output.since(0), (2, kept.clone()));
+        assert_eq!(output.since(1), (1, kept));
+        assert_eq!(output.since(3), (0, vec!["d".to_string(), "e".to_string()]));
+        assert_eq!(output.since(5), (0, vec![]));
+    }
+
+    #[test]
+    fn test_start_command() {
+        let mut jobs = Jobs::default();
+        let mut command = Command::new("sh");
+        command.arg("-c").arg("echo one; echo two >&2; echo three");
+        let id = jobs.start_command("sh", command, 2).unwrap();
+        assert_eq!(
+            wait_until_done(&jobs, id),
+            JobStatus::Done("finished".to_string())
+        );
+        let output = jobs.get(id).unwrap().output().unwrap();
+        let (dropped, lines) = output.lock().unwrap().since(0);
+        assert_eq!((dropped, lines.len()), (1, 2));
+
+        let mut command = Command::new("sh");
+        command.arg("-c").arg("sleep 30");
+        let id = jobs.start_command("sleep", command, 10).unwrap();
+        jobs.kill(id).unwrap();
+        assert_eq!(wait_until_done(&jobs, id), JobStatus::Killed);
+    }
+}
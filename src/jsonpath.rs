@@ -0,0 +1,255 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A small subset of JSONPath, enough to pull single fields out of kubernetes objects. Paths look
+//! like `.status.podIP`, `.spec.containers[0].image`, or `.spec.containers[*].name`, and can be
+//! wrapped in `{}` like kubectl's `-o jsonpath` templates.
+
+use serde_json::Value;
+
+use crate::error::ClickError;
+
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Key(String),
+    /// An array index. Negative indexes count from the end.
+    Index(i64),
+    /// `[*]`, all elements of an array (or all values of an object)
+    All,
+}
+
+impl std::fmt::Display for Segment {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Segment::Key(key) if key.contains('.') => write!(f, "['{}']", key),
+            Segment::Key(key) => write!(f, ".{}", key),
+            Segment::Index(idx) => write!(f, "[{}]", idx),
+            Segment::All => write!(f, "[*]"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+}
+
+fn parse_err(path: &str, msg: &str) -> ClickError {
+    ClickError::ParseErr(format!("Invalid path '{}': {}", path, msg))
+}
+
+impl JsonPath {
+    pub fn parse(path: &str) -> Result<JsonPath, ClickError> {
+        let mut rest = path.trim();
+        if rest.starts_with('{') {
+            rest = rest
+                .strip_prefix('{')
+                .and_then(|r| r.strip_suffix('}'))
+                .ok_or_else(|| parse_err(path, "unmatched {"))?;
+        }
+        // allow "$.foo" and "foo" as well as ".foo"
+        rest = rest.strip_prefix('$').unwrap_or(rest);
+        let mut segments = vec![];
+        let mut first = true;
+        while !rest.is_empty() {
+            if let Some(r) = rest.strip_prefix('[') {
+                let end = r
+                    .find(']')
+                    .ok_or_else(|| parse_err(path, "missing closing ]"))?;
+                let inner = r[..end].trim();
+                segments.push(if inner == "*" {
+                    Segment::All
+                } else if let Some(key) = inner
+                    .strip_prefix('\'')
+                    .and_then(|k| k.strip_suffix('\''))
+                    .or_else(|| inner.strip_prefix('"').and_then(|k| k.strip_suffix('"')))
+                {
+                    Segment::Key(key.to_string())
+                } else {
+                    Segment::Index(inner.parse().map_err(|_| {
+                        parse_err(path, &format!("invalid array index [{}]", inner))
+                    })?)
+                });
+                rest = &r[end + 1..];
+            } else {
+                let r = match rest.strip_prefix('.') {
+                    Some(r) => r,
+                    None if first => rest,
+                    None => return Err(parse_err(path, "expected . or [")),
+                };
+                let end = r.find(['.', '[']).unwrap_or(r.len());
+                if end == 0 {
+                    if r.is_empty() && segments.is_empty() {
+                        // just "." means the whole object
+                        break;
+                    }
+                    return Err(parse_err(path, "empty field name"));
+                }
+                segments.push(if &r[..end] == "*" {
+                    Segment::All
+                } else {
+                    Segment::Key(r[..end].to_string())
+                });
+                rest = &r[end..];
+            }
+            first = false;
+        }
+        Ok(JsonPath { segments })
+    }
+
+    /// Find all the values this path selects in value. Elements of a `[*]` that don't have the
+    /// rest of the path are skipped, but it's an error if a segment doesn't match anything at all.
+    pub fn find<'a>(&self, value: &'a Value) -> Result<Vec<&'a Value>, ClickError> {
+        let mut current = vec![value];
+        for (i, segment) in self.segments.iter().enumerate() {
+            let mut next = vec![];
+            for val in current.iter() {
+                match segment {
+                    Segment::Key(key) => next.extend(val.get(key.as_str())),
+                    Segment::Index(idx) => {
+                        if let Some(arr) = val.as_array() {
+                            let idx = if *idx < 0 {
+                                arr.len() as i64 + idx
+                            } else {
+                                *idx
+                            };
+                            if idx >= 0 {
+                                next.extend(arr.get(idx as usize));
+                            }
+                        }
+                    }
+                    Segment::All => match val {
+                        Value::Array(arr) => next.extend(arr.iter()),
+                        Value::Object(map) => next.extend(map.values()),
+                        _ => {}
+                    },
+                }
+            }
+            if next.is_empty() && !current.is_empty() && *segment != Segment::All {
+                let resolved: String = self.segments[..i].iter().map(|s| s.to_string()).collect();
+                return Err(ClickError::CommandError(format!(
+                    "Could not resolve '{}' under {}",
+                    segment,
+                    if resolved.is_empty() { "." } else { &resolved }
+                )));
+            }
+            current = next;
+        }
+        Ok(current)
+    }
+}
+
+/// Format a value found by a path for printing. With raw, strings are printed without quotes
+pub fn format_value(value: &Value, raw: bool) -> String {
+    match value {
+        Value::String(s) if raw => s.clone(),
+        _ => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pod() -> Value {
+        serde_json::json!({
+            "metadata": { "name": "p1", "labels": { "app.kubernetes.io/name": "web" } },
+            "spec": {
+                "containers": [
+                    { "name": "a", "image": "nginx", "ports": [{ "containerPort": 80 }] },
+                    { "name": "b", "image": "envoy" },
+                ]
+            },
+            "status": { "podIP": "10.0.0.1", "ready": true },
+        })
+    }
+
+    fn find(path: &str) -> Result<Vec<String>, ClickError> {
+        let pod = pod();
+        JsonPath::parse(path)?
+            .find(&pod)
+            .map(|vals| vals.iter().map(|v| format_value(v, true)).collect())
+    }
+
+    #[test]
+    fn test_parse() {
+        let expected = JsonPath {
+            segments: vec![
+                Segment::Key("spec".to_string()),
+                Segment::Key("containers".to_string()),
+                Segment::Index(0),
+                Segment::Key("image".to_string()),
+            ],
+        };
+        assert_eq!(
+            JsonPath::parse(".spec.containers[0].image").unwrap(),
+            expected
+        );
+        assert_eq!(
+            JsonPath::parse("{.spec.containers[0].image}").unwrap(),
+            expected
+        );
+        assert_eq!(
+            JsonPath::parse("$.spec.containers[0].image").unwrap(),
+            expected
+        );
+        assert_eq!(
+            JsonPath::parse("spec.containers[0].image").unwrap(),
+            expected
+        );
+        assert!(JsonPath::parse(".spec[").is_err());
+        assert!(JsonPath::parse(".spec[x]").is_err());
+        assert!(JsonPath::parse(".spec..name").is_err());
+        assert!(JsonPath::parse("{.spec").is_err());
+    }
+
+    #[test]
+    fn test_find() {
+        assert_eq!(find(".status.podIP").unwrap(), vec!["10.0.0.1"]);
+        assert_eq!(find(".status.ready").unwrap(), vec!["true"]);
+        assert_eq!(find(".spec.containers[1].name").unwrap(), vec!["b"]);
+        assert_eq!(find(".spec.containers[-1].name").unwrap(), vec!["b"]);
+        assert_eq!(
+            find(".spec.containers[*].image").unwrap(),
+            vec!["nginx", "envoy"]
+        );
+        assert_eq!(
+            find(".spec.containers[*].ports[*].containerPort").unwrap(),
+            vec!["80"]
+        );
+        assert_eq!(
+            find(".metadata.labels['app.kubernetes.io/name']").unwrap(),
+            vec!["web"]
+        );
+        assert_eq!(
+            find(".metadata.labels").unwrap(),
+            vec![r#"{"app.kubernetes.io/name":"web"}"#]
+        );
+        assert_eq!(find(".").unwrap().len(), 1);
+
+        let err = find(".status.podIp").unwrap_err().to_string();
+        assert!(err.contains("'.podIp'"), "{}", err);
+        let err = find(".spec.containers[5].name").unwrap_err().to_string();
+        assert!(err.contains("'[5]'"), "{}", err);
+        assert!(err.contains(".spec.containers"), "{}", err);
+    }
+
+    #[test]
+    fn test_format_value() {
+        let val = Value::String("x".to_string());
+        assert_eq!(format_value(&val, false), "\"x\"");
+        assert_eq!(format_value(&val, true), "x");
+        assert_eq!(format_value(&serde_json::json!(3), false), "3");
+    }
+}
@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use bytes::Bytes;
+use chrono::Utc;
 use k8s_openapi::{http, List, ListableResource};
 use reqwest::blocking::Client;
 use reqwest::{Certificate, Identity, Url};
@@ -20,16 +21,19 @@ use serde::Deserialize;
 use url::Host;
 use yasna::models::ObjectIdentifier;
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::fmt::Debug;
 use std::fs::File;
-use std::io::Read;
+use std::io::{IsTerminal, Read};
 use std::path::PathBuf;
-use std::time::Duration;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
 
 use crate::{
-    config::{AuthProvider, ExecAuth, ExecProvider},
+    config::{AuthProvider, ExecAuth, ExecProvider, Impersonation, WireFormat},
     error::{ClickErrNo, ClickError},
+    protobuf, retry,
 };
 
 #[derive(Clone)]
@@ -172,6 +176,81 @@ fn get_id_from_data(
     }
 }
 
+lazy_static! {
+    /// How many api requests have been made, and how long they took, since the totals were last
+    /// taken. This covers every context, and requests made from other threads.
+    static ref REQUEST_TOTALS: Mutex<(u32, Duration)> = Mutex::new((0, Duration::ZERO));
+}
+
+/// The number of requests made and the time spent on them since this was last called, so the
+/// totals for a command can be shown when it's done
+pub fn take_request_totals() -> (u32, Duration) {
+    std::mem::take(&mut *REQUEST_TOTALS.lock().unwrap())
+}
+
+/// Print a line of verbose output to stderr, dimmed so it stands apart from the command's output
+pub fn print_verbose(msg: &str) {
+    if std::io::stderr().is_terminal() {
+        eprintln!("{}", ansi_term::Style::new().dimmed().paint(msg));
+    } else {
+        eprintln!("{}", msg);
+    }
+}
+
+/// Print headers for verbose level 2, without giving away any credentials
+fn print_headers(prefix: &str, headers: &http::HeaderMap) {
+    for (name, value) in headers.iter() {
+        let value = if name == http::header::AUTHORIZATION {
+            "<redacted>"
+        } else {
+            value.to_str().unwrap_or("<binary>")
+        };
+        print_verbose(&format!("{} {}: {}", prefix, name, value));
+    }
+}
+
+/// If response is a failure with a Status body, return the Api error for it
+fn check_status(response: &http::Response<Bytes>) -> Result<(), ClickError> {
+    if response.status().is_success() {
+        return Ok(());
+    }
+    let status = response.status().as_u16();
+    // a request that asked for protobuf gets its errors as protobuf too
+    let err = match protobuf::status_json(response.body()) {
+        Some(json) => ClickError::from_status(status, &json),
+        None => ClickError::from_status(status, response.body()),
+    };
+    match err {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+/// Parse a json list response
+fn list_from_json<T: ListableResource + for<'de> Deserialize<'de> + Debug>(
+    response: &http::Response<Bytes>,
+) -> Result<List<T>, ClickError> {
+    let status_code: http::StatusCode = response.status();
+    match k8s_openapi::Response::try_from_parts(status_code, response.body()) {
+        // Successful response (HTTP 200 and parsed successfully)
+        Ok((k8s_openapi::ListResponse::Ok(res_list), _)) => Ok(res_list),
+
+        // Some unexpected response
+        // (not HTTP 200, but still parsed successfully)
+        Ok(other) => {
+            if status_code == http::StatusCode::UNAUTHORIZED {
+                Err(ClickError::Kube(ClickErrNo::Unauthorized))
+            } else {
+                Err(ClickError::ParseErr(
+                    // TODO maybe a special error type for this
+                    format!("Got unexpected status {} {:?}", status_code, other),
+                ))
+            }
+        }
+        Err(e) => Err(ClickError::ResponseError(e)),
+    }
+}
+
 pub struct Context {
     pub name: String,
     endpoint: Url,
@@ -181,6 +260,37 @@ pub struct Context {
     auth: RefCell<Option<UserAuth>>,
     connect_timeout_secs: u32,
     read_timeout_secs: u32,
+    auth_refresh: Cell<bool>,
+    /// how many times to retry a read that fails for a reason that might go away
+    retries: Cell<u32>,
+    /// how much to print about each request, see ClickConfig::verbose
+    verbose: Cell<u32>,
+    /// who to send requests as, if anyone
+    impersonate: RefCell<Option<Impersonation>>,
+    /// what to ask for lists in, see ClickConfig::wire_format
+    wire_format: Cell<WireFormat>,
+}
+
+/// A copy has its own handles to the same clients, so it can make requests from another thread.
+/// Auth that's refreshed through the copy isn't seen by the original.
+impl Clone for Context {
+    fn clone(&self) -> Context {
+        Context {
+            name: self.name.clone(),
+            endpoint: self.endpoint.clone(),
+            client: RefCell::new(self.client.borrow().clone()),
+            log_client: RefCell::new(self.log_client.borrow().clone()),
+            root_ca: self.root_ca.clone(),
+            auth: RefCell::new(self.auth.borrow().clone()),
+            connect_timeout_secs: self.connect_timeout_secs,
+            read_timeout_secs: self.read_timeout_secs,
+            auth_refresh: Cell::new(self.auth_refresh.get()),
+            retries: Cell::new(self.retries.get()),
+            verbose: Cell::new(self.verbose.get()),
+            impersonate: RefCell::new(self.impersonate.borrow().clone()),
+            wire_format: Cell::new(self.wire_format.get()),
+        }
+    }
 }
 
 impl Context {
@@ -217,9 +327,84 @@ impl Context {
             auth: client_auth,
             connect_timeout_secs,
             read_timeout_secs,
+            auth_refresh: Cell::new(true),
+            retries: Cell::new(0),
+            verbose: Cell::new(0),
+            impersonate: RefCell::new(None),
+            wire_format: Cell::new(WireFormat::default()),
         }
     }
 
+    /// Set if expired auth-provider tokens should be refreshed (and saved back to the kubeconfig)
+    pub fn set_auth_refresh(&self, auth_refresh: bool) {
+        self.auth_refresh.set(auth_refresh);
+    }
+
+    /// Set how many times GET requests are retried on connection errors, timeouts, 429s and 5xx
+    pub fn set_retries(&self, retries: u32) {
+        self.retries.set(retries);
+    }
+
+    /// Set how much to print about each request: nothing (0), timings (1), or headers too (2)
+    pub fn set_verbose(&self, verbose: u32) {
+        self.verbose.set(verbose);
+    }
+
+    /// Set who to impersonate in requests, None to stop impersonating
+    pub fn set_impersonate(&self, impersonate: Option<Impersonation>) {
+        *self.impersonate.borrow_mut() = impersonate;
+    }
+
+    /// Set whether lists are asked for as json or protobuf
+    pub fn set_wire_format(&self, wire_format: WireFormat) {
+        self.wire_format.set(wire_format);
+    }
+
+    /// Send req with client, timing it, and printing what happened if we're verbose
+    fn timed_send(
+        &self,
+        client: &Client,
+        req: reqwest::blocking::Request,
+    ) -> Result<reqwest::blocking::Response, ClickError> {
+        let verbose = self.verbose.get();
+        let method = req.method().clone();
+        let path = match req.url().query().filter(|q| !q.is_empty()) {
+            Some(query) => format!("{}?{}", req.url().path(), query),
+            None => req.url().path().to_string(),
+        };
+        if verbose >= 2 {
+            print_verbose(&format!("> {} {}", method, path));
+            print_headers(">", req.headers());
+        }
+        let start = Instant::now();
+        let result = client.execute(req);
+        let elapsed = start.elapsed();
+        {
+            let mut totals = REQUEST_TOTALS.lock().unwrap();
+            totals.0 += 1;
+            totals.1 += elapsed;
+        }
+        if verbose >= 1 {
+            let outcome = match result {
+                Ok(ref resp) => resp.status().to_string(),
+                Err(ref e) => format!("failed: {}", e),
+            };
+            print_verbose(&format!(
+                "{} {} {} in {}ms",
+                method,
+                path,
+                outcome,
+                elapsed.as_millis()
+            ));
+        }
+        if verbose >= 2 {
+            if let Ok(ref resp) = result {
+                print_headers("<", resp.headers());
+            }
+        }
+        result.map_err(ClickError::from)
+    }
+
     fn get_client(
         endpoint: &Url,
         root_ca: Option<Certificate>,
@@ -263,79 +448,76 @@ impl Context {
         !matches!(host, Host::Domain(_))
     }
 
-    fn handle_exec_provider(&self, exec_provider: &ExecProvider) {
-        let (auth, was_expired) = exec_provider.get_auth();
-        match auth {
-            ExecAuth::Token(_) => {} // handled below
-            ExecAuth::ClientCertKey {
-                cert_data,
-                key_data,
-                ..
-            } => {
-                if was_expired {
+    /// Make sure any exec credential plugin has provided current credentials. If the plugin returns
+    /// a client certificate, our clients need to be rebuilt to use it whenever it changes.
+    fn handle_exec_provider(&self) -> Result<(), ClickError> {
+        // figure out the new identity first, as we can't replace self.auth while it's borrowed
+        let id = match &*self.auth.borrow() {
+            Some(UserAuth::ExecProvider(exec_provider)) => match exec_provider.get_auth()? {
+                (
+                    ExecAuth::ClientCertKey {
+                        cert_data,
+                        key_data,
+                    },
+                    true,
+                ) => {
                     let pkcs12 = Context::use_pkcs12(&self.endpoint);
-                    let id =
-                        get_id_from_data(key_data.into_bytes(), cert_data.into_bytes(), pkcs12)
-                            .unwrap(); // TODO: Handle error
-                    let auth = self.auth.take();
-                    let (new_client, new_auth) = Context::get_client(
-                        &self.endpoint,
-                        self.root_ca.clone(),
-                        auth.clone(),
-                        Some(id.clone()),
-                        self.connect_timeout_secs,
-                        self.read_timeout_secs,
-                    );
-                    let (new_log_client, _) = Context::get_client(
-                        &self.endpoint,
-                        self.root_ca.clone(),
-                        auth,
-                        Some(id),
-                        u32::MAX,
-                        u32::MAX,
-                    );
-                    *self.client.borrow_mut() = new_client;
-                    *self.log_client.borrow_mut() = new_log_client;
-                    *self.auth.borrow_mut() = new_auth;
+                    Some(get_id_from_data(
+                        key_data.into_bytes(),
+                        cert_data.into_bytes(),
+                        pkcs12,
+                    )?)
                 }
-            }
+                _ => None, // tokens are handled when building the request
+            },
+            _ => None,
+        };
+        if let Some(id) = id {
+            let auth = self.auth.take();
+            let (new_client, new_auth) = Context::get_client(
+                &self.endpoint,
+                self.root_ca.clone(),
+                auth.clone(),
+                Some(id.clone()),
+                self.connect_timeout_secs,
+                self.read_timeout_secs,
+            );
+            let (new_log_client, _) = Context::get_client(
+                &self.endpoint,
+                self.root_ca.clone(),
+                auth,
+                Some(id),
+                u32::MAX,
+                u32::MAX,
+            );
+            *self.client.borrow_mut() = new_client;
+            *self.log_client.borrow_mut() = new_log_client;
+            *self.auth.borrow_mut() = new_auth;
         }
+        Ok(())
     }
 
-    pub fn execute(
+    /// Add whatever auth we have to req
+    fn add_auth(
         &self,
-        k8sreq: http::Request<Vec<u8>>,
-    ) -> Result<http::Response<Bytes>, ClickError> {
-        let (parts, body) = k8sreq.into_parts();
-
-        let url = self.endpoint.join(&parts.uri.to_string())?;
-
-        if let Some(UserAuth::ExecProvider(ref exec_provider)) = *self.auth.borrow() {
-            self.handle_exec_provider(exec_provider);
-        }
-
-        let req = match parts.method {
-            http::method::Method::GET => self.client.borrow().get(url),
-            http::method::Method::POST => self.client.borrow().post(url),
-            http::method::Method::DELETE => self.client.borrow().delete(url),
-            _ => unimplemented!(),
-        };
-
-        let req = req.headers(parts.headers).body(body);
-        let req = match &*self.auth.borrow() {
+        req: reqwest::blocking::RequestBuilder,
+    ) -> Result<reqwest::blocking::RequestBuilder, ClickError> {
+        Ok(match &*self.auth.borrow() {
             Some(auth) => match auth {
-                UserAuth::AuthProvider(provider) => match provider.ensure_token() {
-                    Some(token) => req.bearer_auth(token),
-                    None => {
-                        print_token_err();
-                        req
+                UserAuth::AuthProvider(provider) => {
+                    match provider.ensure_token(self.auth_refresh.get()) {
+                        Some(token) => req.bearer_auth(token),
+                        None => {
+                            print_token_err();
+                            req
+                        }
                     }
-                },
+                }
                 UserAuth::ExecProvider(ref exec_provider) => {
-                    let (auth, _) = exec_provider.get_auth();
+                    let (auth, _) = exec_provider.get_auth()?;
                     match auth {
                         ExecAuth::Token(token) => req.bearer_auth(token),
-                        ExecAuth::ClientCertKey { .. } => req, // handled above
+                        ExecAuth::ClientCertKey { .. } => req, // handled by handle_exec_provider
                     }
                 }
                 UserAuth::Token(token) => req.bearer_auth(token),
@@ -343,8 +525,132 @@ impl Context {
                 _ => req,
             },
             None => req,
+        })
+    }
+
+    /// Add the Impersonate-* headers to req, if we're impersonating anyone
+    fn add_impersonation(
+        &self,
+        req: reqwest::blocking::RequestBuilder,
+    ) -> reqwest::blocking::RequestBuilder {
+        match &*self.impersonate.borrow() {
+            Some(imp) => imp.groups.iter().fold(
+                req.header("Impersonate-User", imp.user.as_str()),
+                |req, group| req.header("Impersonate-Group", group.as_str()),
+            ),
+            None => req,
+        }
+    }
+
+    /// If we're using an exec credential plugin or a refreshable auth-provider, drop the cached
+    /// credentials so they're fetched again on the next request. Returns true if there was anything
+    /// to invalidate.
+    fn invalidate_auth(&self) -> bool {
+        match &*self.auth.borrow() {
+            Some(UserAuth::ExecProvider(exec_provider)) => {
+                exec_provider.invalidate();
+                true
+            }
+            Some(UserAuth::AuthProvider(provider)) if self.auth_refresh.get() => {
+                provider.invalidate();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn send(
+        &self,
+        parts: &http::request::Parts,
+        url: Url,
+        body: Vec<u8>,
+    ) -> Result<reqwest::blocking::Response, ClickError> {
+        self.handle_exec_provider()?;
+
+        let req = match parts.method {
+            http::method::Method::GET => self.client.borrow().get(url),
+            http::method::Method::POST => self.client.borrow().post(url),
+            http::method::Method::DELETE => self.client.borrow().delete(url),
+            http::method::Method::PATCH => self.client.borrow().patch(url),
+            _ => unimplemented!(),
+        };
+
+        let req = req.headers(parts.headers.clone()).body(body);
+        let req = self.add_impersonation(self.add_auth(req)?).build()?;
+        self.timed_send(&self.client.borrow(), req)
+    }
+
+    /// Run send, retrying it with backoff if the request is a GET (so it's safe to repeat) and it
+    /// fails with a connection error, a timeout, a 429, or a 5xx. If every attempt fails, the error
+    /// says how many attempts were made.
+    fn with_retries<F>(
+        &self,
+        method: &http::Method,
+        mut send: F,
+    ) -> Result<reqwest::blocking::Response, ClickError>
+    where
+        F: FnMut() -> Result<reqwest::blocking::Response, ClickError>,
+    {
+        // a mutation may have happened even if we got an error, so never repeat those
+        let attempts = if method == http::Method::GET {
+            self.retries.get().saturating_add(1)
+        } else {
+            1
         };
-        let resp = req.send()?;
+        let mut attempt = 1;
+        loop {
+            let result = send();
+            let delay = match result {
+                Ok(ref resp) if retry::retryable_status(resp.status().as_u16()) => resp
+                    .headers()
+                    .get(http::header::RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| retry::retry_after(v, Utc::now()))
+                    .unwrap_or_else(|| retry::backoff(attempt)),
+                Err(ClickError::Reqwest(ref e, _)) if retry::retryable_error(e) => {
+                    retry::backoff(attempt)
+                }
+                _ => return result,
+            };
+            if attempt >= attempts {
+                if attempt == 1 {
+                    return result;
+                }
+                let error = match result {
+                    Ok(resp) => {
+                        let status = resp.status();
+                        let body = resp.bytes().unwrap_or_default();
+                        ClickError::from_status(status.as_u16(), &body).unwrap_or_else(|| {
+                            ClickError::CommandError(format!("Server responded with {}", status))
+                        })
+                    }
+                    Err(e) => e,
+                };
+                return Err(ClickError::RetriesExhausted {
+                    attempts: attempt,
+                    error: Box::new(error),
+                });
+            }
+            thread::sleep(delay);
+            attempt += 1;
+        }
+    }
+
+    pub fn execute(
+        &self,
+        k8sreq: http::Request<Vec<u8>>,
+    ) -> Result<http::Response<Bytes>, ClickError> {
+        let (parts, body) = k8sreq.into_parts();
+
+        let url = self.endpoint.join(&parts.uri.to_string())?;
+
+        let send = || self.send(&parts, url.clone(), body.clone());
+        let mut resp = self.with_retries(&parts.method, send)?;
+        if resp.status() == http::StatusCode::UNAUTHORIZED && self.invalidate_auth() {
+            // credentials can be revoked before they expire, or have an expiry we can't see, so
+            // get fresh ones and try once more
+            resp = self.with_retries(&parts.method, send)?;
+        }
         let stat = resp.status();
         let bytes = resp.bytes()?;
 
@@ -355,7 +661,8 @@ impl Context {
     }
 
     // execute a request and return the reqwest response. this implements io::Read so it can be used
-    // for streaming operations like logs
+    // for streaming operations like logs. watches come through here too, and are always json
+    // whatever the wire_format, since protobuf watch events are framed differently
     pub fn execute_reader(
         &self,
         k8sreq: http::Request<Vec<u8>>,
@@ -365,47 +672,28 @@ impl Context {
 
         let url = self.endpoint.join(&parts.uri.to_string())?;
 
-        if let Some(UserAuth::ExecProvider(ref exec_provider)) = *self.auth.borrow() {
-            self.handle_exec_provider(exec_provider);
-        }
+        self.handle_exec_provider()?;
 
-        let req = match parts.method {
-            http::method::Method::GET => self.log_client.borrow().get(url),
-            http::method::Method::POST => self.log_client.borrow().post(url),
-            http::method::Method::DELETE => self.log_client.borrow().delete(url),
-            _ => unimplemented!(),
-        };
+        let send = || {
+            let req = match parts.method {
+                http::method::Method::GET => self.log_client.borrow().get(url.clone()),
+                http::method::Method::POST => self.log_client.borrow().post(url.clone()),
+                http::method::Method::DELETE => self.log_client.borrow().delete(url.clone()),
+                http::method::Method::PATCH => self.log_client.borrow().patch(url.clone()),
+                _ => unimplemented!(),
+            };
 
-        let req = req.body(body);
-        let req = match &*self.auth.borrow() {
-            Some(auth) => match auth {
-                UserAuth::AuthProvider(provider) => match provider.ensure_token() {
-                    Some(token) => req.bearer_auth(token),
-                    None => {
-                        print_token_err();
-                        req
-                    }
-                },
-                UserAuth::ExecProvider(ref exec_provider) => {
-                    let (auth, _) = exec_provider.get_auth();
-                    match auth {
-                        ExecAuth::Token(token) => req.bearer_auth(token),
-                        ExecAuth::ClientCertKey { .. } => req, // handled above
-                    }
-                }
-                UserAuth::Token(token) => req.bearer_auth(token),
-                UserAuth::UserPass(user, pass) => req.basic_auth(user, Some(pass)),
-                _ => req,
-            },
-            None => req,
-        };
+            let req = self.add_impersonation(self.add_auth(req.body(body.clone()))?);
 
-        let req = match timeout {
-            Some(timeout) => req.timeout(timeout),
-            None => req, // log_client above already has a super long timeout
-        };
+            let req = match timeout {
+                Some(timeout) => req.timeout(timeout),
+                None => req, // log_client above already has a super long timeout
+            };
 
-        let resp = req.send()?;
+            self.timed_send(&self.log_client.borrow(), req.build()?)
+        };
+        // only the request is retried, once the response starts streaming it's up to the caller
+        let resp = self.with_retries(&parts.method, send)?;
 
         if resp.status().is_success() {
             Ok(resp)
@@ -414,8 +702,12 @@ impl Context {
                 Ok(_) => panic!("status was not success, but error_for_status returned Ok"),
                 Err(e) => e,
             };
-            let body = resp.json()?;
-            Err(ClickError::Reqwest(err, Some(body)))
+            let status = resp.status().as_u16();
+            let body = resp.bytes()?;
+            match ClickError::from_status(status, &body) {
+                Some(api_err) => Err(api_err),
+                None => Err(ClickError::Reqwest(err, serde_json::from_slice(&body).ok())),
+            }
         }
     }
 
@@ -425,6 +717,7 @@ impl Context {
     ) -> Result<T, ClickError> {
         let response = self.execute(k8sreq)?;
         let status_code: http::StatusCode = response.status();
+        check_status(&response)?;
         match k8s_openapi::Response::try_from_parts(status_code, response.body()) {
             Ok((res, _)) => Ok(res),
             // Need more response data. We're blocking, so this is a hard error
@@ -432,33 +725,36 @@ impl Context {
         }
     }
 
-    pub fn execute_list<T: ListableResource + for<'de> Deserialize<'de> + Debug>(
+    /// Get a list. When wire_format is protobuf and T is a type we can decode from protobuf, the
+    /// list is asked for as protobuf, and decoded from json if that's what the server sends anyway.
+    pub fn execute_list<T: ListableResource + for<'de> Deserialize<'de> + Debug + 'static>(
         &self,
-        k8sreq: http::Request<Vec<u8>>,
+        mut k8sreq: http::Request<Vec<u8>>,
     ) -> Result<List<T>, ClickError> {
+        if self.wire_format.get() != WireFormat::Protobuf || !protobuf::decodes::<T>() {
+            return self.execute_list_with_body(k8sreq).map(|(list, _)| list);
+        }
+        k8sreq.headers_mut().insert(
+            http::header::ACCEPT,
+            http::HeaderValue::from_static(protobuf::ACCEPT),
+        );
         let response = self.execute(k8sreq)?;
-        let status_code: http::StatusCode = response.status();
-
-        let res_list: List<T> =
-            match k8s_openapi::Response::try_from_parts(status_code, response.body()) {
-                // Successful response (HTTP 200 and parsed successfully)
-                Ok((k8s_openapi::ListResponse::Ok(res_list), _)) => res_list,
-
-                // Some unexpected response
-                // (not HTTP 200, but still parsed successfully)
-                Ok(other) => {
-                    if status_code == http::StatusCode::UNAUTHORIZED {
-                        return Err(ClickError::Kube(ClickErrNo::Unauthorized));
-                    } else {
-                        return Err(ClickError::ParseErr(
-                            // TODO maybe a special error type for this
-                            format!("Got unexpected status {} {:?}", status_code, other),
-                        ));
-                    }
-                }
-                Err(e) => return Err(ClickError::ResponseError(e)),
-            };
+        check_status(&response)?;
+        match protobuf::decode_list(response.body()) {
+            Some(list) => list,
+            None => list_from_json(&response),
+        }
+    }
 
-        Ok(res_list)
+    /// Like execute_list, but also return the body of the response, for when the exact json the
+    /// server sent is needed. This always asks for json.
+    pub fn execute_list_with_body<T: ListableResource + for<'de> Deserialize<'de> + Debug>(
+        &self,
+        k8sreq: http::Request<Vec<u8>>,
+    ) -> Result<(List<T>, Bytes), ClickError> {
+        let response = self.execute(k8sreq)?;
+        check_status(&response)?;
+        let res_list = list_from_json(&response)?;
+        Ok((res_list, response.into_body()))
     }
 }
@@ -30,6 +30,7 @@ use crate::{
 };
 
 #[derive(Deserialize, Debug)]
+#[allow(dead_code)] // not all fields are used yet
 pub struct ColumnDefintion {
     name: String,
     #[serde(rename = "type")]
@@ -112,6 +113,8 @@ impl K8sTable {
             kobjs.push(KObj {
                 name: row.metadata.name.as_ref().unwrap().clone(),
                 namespace: row.metadata.namespace.clone(),
+                uid: row.metadata.uid.clone(),
+                context: None,
                 typ: ObjType::Crd {
                     _type: _type.to_string(),
                     group_version: group_version.to_string(),
@@ -127,6 +130,7 @@ impl K8sTable {
 #[derive(Debug)]
 pub enum GetTableResponse {
     Ok(K8sTable),
+    #[allow(dead_code)] // only read via Debug
     Other(Result<Option<serde_json::Value>, serde_json::Error>),
 }
 
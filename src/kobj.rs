@@ -14,20 +14,23 @@
 
 use crate::describe;
 use crate::error::ClickError;
+use crate::jsonpath::{self, JsonPath};
 use crate::output::ClickWriter;
-use crate::values::val_str_opt;
+use crate::theme::{self, Role};
+use crate::values::{val_str, val_str_opt};
 use crate::Env;
 
-use ansi_term::ANSIString;
-use ansi_term::Colour::{Blue, Cyan, Green, Purple, Red, Yellow};
 use clap::ArgMatches;
 use k8s_openapi::api::{
-    apps::v1 as api_apps, batch::v1 as api_batch, core::v1 as api, storage::v1 as api_storage,
+    apps::v1 as api_apps, autoscaling::v2beta2 as api_autoscaling, batch::v1 as api_batch,
+    batch::v1beta1 as api_batch_beta, core::v1 as api, rbac::v1 as api_rbac,
+    storage::v1 as api_storage,
 };
 
 use serde::ser::Serialize;
 use serde_json::Value;
 
+use std::collections::HashSet;
 use std::io::Write;
 
 #[derive(Clone, Debug, PartialEq)]
@@ -40,6 +43,7 @@ pub enum ObjType {
         group_version: String,
     },
     Node,
+    DaemonSet,
     Deployment,
     Service,
     ReplicaSet,
@@ -47,9 +51,16 @@ pub enum ObjType {
     ConfigMap,
     Secret,
     Job,
+    CronJob,
+    HorizontalPodAutoscaler,
     Namespace,
     PersistentVolume,
     StorageClass,
+    ServiceAccount,
+    Role,
+    ClusterRole,
+    RoleBinding,
+    ClusterRoleBinding,
     #[cfg(feature = "argorollouts")]
     Rollout,
 }
@@ -59,7 +70,12 @@ pub enum ObjType {
 pub struct KObj {
     pub name: String,
     pub namespace: Option<String>,
+    /// The uid of the object, if we know it
+    pub uid: Option<String>,
     pub typ: ObjType,
+    /// The context the object is in, if it was listed from a context other than the active one
+    /// (i.e. with `pods --contexts`). Selecting it switches to that context.
+    pub context: Option<String>,
 }
 
 pub struct VecWrap {
@@ -72,36 +88,152 @@ impl From<VecWrap> for Vec<KObj> {
     }
 }
 
-fn maybe_full_describe_output<T: ?Sized>(
+fn maybe_full_describe_output<T>(
     matches: &ArgMatches,
     value: &T,
     writer: &mut ClickWriter,
-) -> bool
+) -> Result<bool, ClickError>
 where
-    T: Serialize,
+    T: Serialize + ?Sized,
 {
-    if matches.is_present("json") {
+    if let Some(path) = matches.value_of("field") {
+        let path = JsonPath::parse(path)?;
+        let value = serde_json::value::to_value(value)?;
+        let prefix = if matches.is_present("with_name") {
+            format!("{}: ", val_str("/metadata/name", &value, "<Unknown>"))
+        } else {
+            String::new()
+        };
+        for found in path.find(&value)?.iter() {
+            let formatted = jsonpath::format_value(found, matches.is_present("raw"));
+            clickwriteln!(writer, "{}{}", prefix, formatted);
+        }
+        Ok(true)
+    } else if matches.is_present("json") {
         writer.pretty_color_json(value).unwrap_or(());
-        true
+        Ok(true)
     } else if matches.is_present("yaml") {
         writer.print_yaml(value).unwrap_or(());
-        true
+        Ok(true)
     } else {
-        false
+        // the global output format, i.e. -o json
+        writer.print_raw(value)
     }
 }
 
 static NOTSUPPORTED: &str = "not supported without -j or -y yet\n";
 
+lazy_static! {
+    /// Kinds that aren't namespaced, from the built-in api groups. Everything else is assumed to
+    /// live in a namespace.
+    static ref CLUSTER_SCOPED_KINDS: HashSet<&'static str> = [
+        // core
+        "ComponentStatus",
+        "Namespace",
+        "Node",
+        "PersistentVolume",
+        // admissionregistration.k8s.io
+        "MutatingWebhookConfiguration",
+        "ValidatingWebhookConfiguration",
+        // apiextensions.k8s.io
+        "CustomResourceDefinition",
+        // apiregistration.k8s.io
+        "APIService",
+        // certificates.k8s.io
+        "CertificateSigningRequest",
+        // flowcontrol.apiserver.k8s.io
+        "FlowSchema",
+        "PriorityLevelConfiguration",
+        // networking.k8s.io
+        "IngressClass",
+        // node.k8s.io
+        "RuntimeClass",
+        // policy
+        "PodSecurityPolicy",
+        // rbac.authorization.k8s.io
+        "ClusterRole",
+        "ClusterRoleBinding",
+        // scheduling.k8s.io
+        "PriorityClass",
+        // storage.k8s.io
+        "CSIDriver",
+        "CSINode",
+        "StorageClass",
+        "VolumeAttachment",
+    ]
+    .iter()
+    .copied()
+    .collect();
+}
+
+/// Is kind one of the built-in kinds that isn't namespaced
+pub fn is_cluster_scoped_kind(kind: &str) -> bool {
+    CLUSTER_SCOPED_KINDS.contains(kind)
+}
+
 impl KObj {
     pub fn from_value(value: &Value, typ: ObjType) -> Option<KObj> {
         val_str_opt("/metadata/name", value).map(|name| KObj {
             name,
             namespace: val_str_opt("/metadata/namespace", value),
+            uid: val_str_opt("/metadata/uid", value),
+            context: None,
             typ,
         })
     }
 
+    /// Build a KObj from the json for a kubernetes object, like what `kubectl get -o json` returns.
+    /// The type is figured out from `kind`, and anything we don't have a type for is treated as a
+    /// custom resource.
+    pub fn from_json(value: &Value) -> Result<KObj, ClickError> {
+        let kind = val_str_opt("/kind", value)
+            .ok_or_else(|| ClickError::ParseErr("Object json has no kind".to_string()))?;
+        let typ = match kind.as_str() {
+            "Pod" => ObjType::Pod {
+                containers: value
+                    .pointer("/spec/containers")
+                    .and_then(|conts| conts.as_array())
+                    .map(|conts| {
+                        conts
+                            .iter()
+                            .filter_map(|cont| val_str_opt("/name", cont))
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            },
+            "Node" => ObjType::Node,
+            "DaemonSet" => ObjType::DaemonSet,
+            "Deployment" => ObjType::Deployment,
+            "Service" => ObjType::Service,
+            "ReplicaSet" => ObjType::ReplicaSet,
+            "StatefulSet" => ObjType::StatefulSet,
+            "ConfigMap" => ObjType::ConfigMap,
+            "Secret" => ObjType::Secret,
+            "Job" => ObjType::Job,
+            "CronJob" => ObjType::CronJob,
+            "HorizontalPodAutoscaler" => ObjType::HorizontalPodAutoscaler,
+            "Namespace" => ObjType::Namespace,
+            "PersistentVolume" => ObjType::PersistentVolume,
+            "StorageClass" => ObjType::StorageClass,
+            "ServiceAccount" => ObjType::ServiceAccount,
+            "Role" => ObjType::Role,
+            "ClusterRole" => ObjType::ClusterRole,
+            "RoleBinding" => ObjType::RoleBinding,
+            "ClusterRoleBinding" => ObjType::ClusterRoleBinding,
+            #[cfg(feature = "argorollouts")]
+            "Rollout" => ObjType::Rollout,
+            _ => ObjType::Crd {
+                group_version: val_str_opt("/apiVersion", value).ok_or_else(|| {
+                    ClickError::ParseErr(format!("{} object json has no apiVersion", kind))
+                })?,
+                _type: kind.clone(),
+            },
+        };
+        KObj::from_value(value, typ).ok_or_else(|| {
+            ClickError::ParseErr(format!("{} object json has no metadata.name", kind))
+        })
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -111,6 +243,7 @@ impl KObj {
             ObjType::Pod { .. } => "Pod",
             ObjType::Crd { _type, .. } => _type,
             ObjType::Node => "Node",
+            ObjType::DaemonSet => "DaemonSet",
             ObjType::Deployment => "Deployment",
             ObjType::Service => "Service",
             ObjType::ReplicaSet => "ReplicaSet",
@@ -118,34 +251,21 @@ impl KObj {
             ObjType::ConfigMap => "ConfigMap",
             ObjType::Secret => "Secret",
             ObjType::Job => "Job",
+            ObjType::CronJob => "CronJob",
+            ObjType::HorizontalPodAutoscaler => "HorizontalPodAutoscaler",
             ObjType::Namespace => "Namespace",
             ObjType::PersistentVolume => "PersistentVolume",
             ObjType::StorageClass => "StorageClass",
+            ObjType::ServiceAccount => "ServiceAccount",
+            ObjType::Role => "Role",
+            ObjType::ClusterRole => "ClusterRole",
+            ObjType::RoleBinding => "RoleBinding",
+            ObjType::ClusterRoleBinding => "ClusterRoleBinding",
             #[cfg(feature = "argorollouts")]
             ObjType::Rollout => "Rollout",
         }
     }
 
-    pub fn prompt_str(&self) -> ANSIString {
-        match self.typ {
-            ObjType::Pod { .. } => Yellow.bold().paint(self.name.as_str()),
-            ObjType::Crd { .. } => Blue.bold().paint(self.name.as_str()),
-            ObjType::Node => Blue.bold().paint(self.name.as_str()),
-            ObjType::Deployment => Purple.bold().paint(self.name.as_str()),
-            ObjType::Service => Cyan.bold().paint(self.name.as_str()),
-            ObjType::ReplicaSet => Green.bold().paint(self.name.as_str()),
-            ObjType::StatefulSet => Green.bold().paint(self.name.as_str()),
-            ObjType::ConfigMap => Purple.bold().paint(self.name.as_str()),
-            ObjType::Secret => Red.bold().paint(self.name.as_str()),
-            ObjType::Job => Purple.bold().paint(self.name.as_str()),
-            ObjType::Namespace => Green.bold().paint(self.name.as_str()),
-            ObjType::PersistentVolume => Blue.bold().paint(self.name.as_str()),
-            ObjType::StorageClass => Red.bold().paint(self.name.as_str()),
-            #[cfg(feature = "argorollouts")]
-            ObjType::Rollout => Purple.bold().paint(self.name.as_str()),
-        }
-    }
-
     pub fn is(&self, typ: ObjType) -> bool {
         self.typ == typ
     }
@@ -155,14 +275,32 @@ impl KObj {
         matches!(self.typ, ObjType::Pod { .. })
     }
 
+    /// Does this kind of object live in a namespace. Custom resources not in the list of known
+    /// cluster scoped kinds are namespaced if we got a namespace for them from the api server.
+    pub fn is_namespaced(&self) -> bool {
+        if is_cluster_scoped_kind(self.type_str()) {
+            return false;
+        }
+        match self.typ {
+            ObjType::Crd { .. } => self.namespace.is_some(),
+            _ => true,
+        }
+    }
+
     // service is a bit more complex, so handle it here
-    fn service_describe(&self, matches: &ArgMatches, env: &Env, writer: &mut ClickWriter) {
+    fn service_describe(
+        &self,
+        matches: &ArgMatches,
+        env: &Env,
+        writer: &mut ClickWriter,
+    ) -> Result<(), ClickError> {
         let ns = self.namespace.as_ref().unwrap();
 
         let (request, _) =
             api::Endpoints::read_namespaced_endpoints(&self.name, ns, Default::default()).unwrap();
-        let epval = match env.run_on_context(|c| c.read(request)).unwrap() {
-            api::ReadNamespacedEndpointsResponse::Ok(resp) => {
+        // the service can still be described without its endpoints
+        let epval = match env.run_on_context(|c| c.read(request)) {
+            Ok(api::ReadNamespacedEndpointsResponse::Ok(resp)) => {
                 serde_json::value::to_value(&resp).ok()
             }
             _ => {
@@ -173,9 +311,9 @@ impl KObj {
 
         let (request, _) =
             api::Service::read_namespaced_service(&self.name, ns, Default::default()).unwrap();
-        match env.run_on_context(|c| c.read(request)).unwrap() {
+        match env.run_on_context(|c| c.read(request))? {
             api::ReadNamespacedServiceResponse::Ok(service) => {
-                if !maybe_full_describe_output(matches, &service, writer) {
+                if !maybe_full_describe_output(matches, &service, writer)? {
                     let val = serde_json::value::to_value(&service).unwrap();
                     clickwriteln!(writer, "{}", describe::describe_format_service(val, epval));
                 }
@@ -184,6 +322,31 @@ impl KObj {
                 clickwriteln!(writer, "Invalid response trying to read service info");
             }
         }
+        Ok(())
+    }
+
+    // service accounts and roles also show what they're bound to, which needs the bindings listed
+    fn rbac_describe<T: Serialize>(
+        &self,
+        value: &T,
+        desc_func: fn(Value) -> String,
+        matches: &ArgMatches,
+        env: &Env,
+        writer: &mut ClickWriter,
+    ) -> Result<(), ClickError> {
+        if maybe_full_describe_output(matches, value, writer)? {
+            return Ok(());
+        }
+        clickwriteln!(writer, "{}", desc_func(serde_json::value::to_value(value)?));
+        match crate::bindings::describe_bindings(env, self) {
+            Ok(bindings) => clickwrite!(writer, "{}", bindings),
+            Err(e) => clickwriteln!(
+                writer,
+                "{}",
+                theme::style(Role::Warning).paint(format!("Couldn't list bindings: {}", e))
+            ),
+        }
+        Ok(())
     }
 
     // crd is a bit more complex, so handle it here
@@ -195,15 +358,15 @@ impl KObj {
         env: &Env,
         writer: &mut ClickWriter,
     ) -> Result<(), ClickError> {
-        let ns = self.namespace.as_ref().unwrap();
-        let (request, _) =
-            crate::crd::read_namespaced_resource(&self.name, ns, _type, group_version)?;
-        match env
-            .run_on_context(|c| c.read::<crate::crd::ReadResourceValueResponse>(request))
-            .unwrap()
-        {
+        let ns = if self.is_namespaced() {
+            self.namespace.as_deref()
+        } else {
+            None
+        };
+        let (request, _) = crate::crd::read_resource(&self.name, ns, _type, group_version)?;
+        match env.run_on_context(|c| c.read::<crate::crd::ReadResourceValueResponse>(request))? {
             crate::crd::ReadResourceValueResponse::Ok(t) => {
-                if !maybe_full_describe_output(matches, &t, writer) {
+                if !maybe_full_describe_output(matches, &t, writer)? {
                     clickwriteln!(writer, "{} {}", self.type_str(), NOTSUPPORTED);
                 }
             }
@@ -226,21 +389,16 @@ impl KObj {
         macro_rules! do_describe {
             ($read_func:expr, $resp_typ:ty, $resp_ok:path, $custom_desc: expr) => {{
                 let (request, _) = $read_func(&self.name, Default::default())?;
-                match env
-                    .run_on_context(|c| c.read::<$resp_typ>(request))
-                    .unwrap()
-                {
-                    $resp_ok(t) => {
-                        if !maybe_full_describe_output(matches, &t, writer) {
-                            let desc_func: Option<fn(Value) -> String> = $custom_desc;
-                            match desc_func {
-                                Some(custom) => {
-                                    let val = serde_json::value::to_value(&t).unwrap();
-                                    clickwriteln!(writer, "{}", custom(val));
-                                }
-                                None => {
-                                    clickwriteln!(writer, "{} {}", self.type_str(), NOTSUPPORTED);
-                                }
+                match env.run_on_context(|c| c.read::<$resp_typ>(request))? {
+                    $resp_ok(t) if !maybe_full_describe_output(matches, &t, writer)? => {
+                        let desc_func: Option<fn(Value) -> String> = $custom_desc;
+                        match desc_func {
+                            Some(custom) => {
+                                let val = serde_json::value::to_value(&t).unwrap();
+                                clickwriteln!(writer, "{}", custom(val));
+                            }
+                            None => {
+                                clickwriteln!(writer, "{} {}", self.type_str(), NOTSUPPORTED);
                             }
                         }
                     }
@@ -253,26 +411,21 @@ impl KObj {
                 match self.namespace.as_ref() {
                     Some(ns) => {
                         let (request, _) = $read_func(&self.name, ns, Default::default())?;
-                        match env
-                            .run_on_context(|c| c.read::<$resp_typ>(request))
-                            .unwrap()
-                        {
-                            $resp_ok(t) => {
-                                if !maybe_full_describe_output(matches, &t, writer) {
-                                    let desc_func: Option<fn(Value) -> String> = $custom_desc;
-                                    match desc_func {
-                                        Some(custom) => {
-                                            let val = serde_json::value::to_value(&t).unwrap();
-                                            clickwriteln!(writer, "{}", custom(val));
-                                        }
-                                        None => {
-                                            clickwriteln!(
-                                                writer,
-                                                "{} {}",
-                                                self.type_str(),
-                                                NOTSUPPORTED
-                                            );
-                                        }
+                        match env.run_on_context(|c| c.read::<$resp_typ>(request))? {
+                            $resp_ok(t) if !maybe_full_describe_output(matches, &t, writer)? => {
+                                let desc_func: Option<fn(Value) -> String> = $custom_desc;
+                                match desc_func {
+                                    Some(custom) => {
+                                        let val = serde_json::value::to_value(&t).unwrap();
+                                        clickwriteln!(writer, "{}", custom(val));
+                                    }
+                                    None => {
+                                        clickwriteln!(
+                                            writer,
+                                            "{} {}",
+                                            self.type_str(),
+                                            NOTSUPPORTED
+                                        );
                                     }
                                 }
                             }
@@ -294,6 +447,14 @@ impl KObj {
                     None
                 );
             }
+            ObjType::DaemonSet => {
+                do_describe_with_namespace!(
+                    api_apps::DaemonSet::read_namespaced_daemon_set,
+                    api_apps::ReadNamespacedDaemonSetResponse,
+                    api_apps::ReadNamespacedDaemonSetResponse::Ok,
+                    None
+                );
+            }
             ObjType::Deployment => {
                 do_describe_with_namespace!(
                     api_apps::Deployment::read_namespaced_deployment,
@@ -310,6 +471,22 @@ impl KObj {
                     None
                 );
             }
+            ObjType::CronJob => {
+                do_describe_with_namespace!(
+                    api_batch_beta::CronJob::read_namespaced_cron_job,
+                    api_batch_beta::ReadNamespacedCronJobResponse,
+                    api_batch_beta::ReadNamespacedCronJobResponse::Ok,
+                    None
+                );
+            }
+            ObjType::HorizontalPodAutoscaler => {
+                do_describe_with_namespace!(
+                    api_autoscaling::HorizontalPodAutoscaler::read_namespaced_horizontal_pod_autoscaler,
+                    api_autoscaling::ReadNamespacedHorizontalPodAutoscalerResponse,
+                    api_autoscaling::ReadNamespacedHorizontalPodAutoscalerResponse::Ok,
+                    None
+                );
+            }
             ObjType::Namespace => {
                 do_describe!(
                     api::Namespace::read_namespace,
@@ -359,7 +536,7 @@ impl KObj {
                 );
             }
             ObjType::Service => {
-                self.service_describe(matches, env, writer);
+                self.service_describe(matches, env, writer)?;
             }
             ObjType::StatefulSet => {
                 do_describe_with_namespace!(
@@ -377,6 +554,57 @@ impl KObj {
                     None
                 );
             }
+            ObjType::ServiceAccount => {
+                let ns = self.namespace.as_deref().unwrap_or("default");
+                let (request, _) = api::ServiceAccount::read_namespaced_service_account(
+                    &self.name,
+                    ns,
+                    Default::default(),
+                )?;
+                if let api::ReadNamespacedServiceAccountResponse::Ok(sa) =
+                    env.run_on_context(|c| c.read(request))?
+                {
+                    let desc = describe::describe_format_service_account;
+                    self.rbac_describe(&sa, desc, matches, env, writer)?;
+                }
+            }
+            ObjType::Role => {
+                let ns = self.namespace.as_deref().unwrap_or("default");
+                let (request, _) =
+                    api_rbac::Role::read_namespaced_role(&self.name, ns, Default::default())?;
+                if let api_rbac::ReadNamespacedRoleResponse::Ok(role) =
+                    env.run_on_context(|c| c.read(request))?
+                {
+                    let desc = describe::describe_format_role;
+                    self.rbac_describe(&role, desc, matches, env, writer)?;
+                }
+            }
+            ObjType::ClusterRole => {
+                let (request, _) =
+                    api_rbac::ClusterRole::read_cluster_role(&self.name, Default::default())?;
+                if let api_rbac::ReadClusterRoleResponse::Ok(role) =
+                    env.run_on_context(|c| c.read(request))?
+                {
+                    let desc = describe::describe_format_role;
+                    self.rbac_describe(&role, desc, matches, env, writer)?;
+                }
+            }
+            ObjType::RoleBinding => {
+                do_describe_with_namespace!(
+                    api_rbac::RoleBinding::read_namespaced_role_binding,
+                    api_rbac::ReadNamespacedRoleBindingResponse,
+                    api_rbac::ReadNamespacedRoleBindingResponse::Ok,
+                    Some(describe::describe_format_role_binding)
+                );
+            }
+            ObjType::ClusterRoleBinding => {
+                do_describe!(
+                    api_rbac::ClusterRoleBinding::read_cluster_role_binding,
+                    api_rbac::ReadClusterRoleBindingResponse,
+                    api_rbac::ReadClusterRoleBindingResponse::Ok,
+                    Some(describe::describe_format_role_binding)
+                );
+            }
             ObjType::Crd {
                 ref _type,
                 ref group_version,
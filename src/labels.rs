@@ -0,0 +1,296 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parsing and validation of `key=value` and `key-` arguments for changing the labels or
+//! annotations of an object, and working out what they change
+
+use regex::Regex;
+
+use crate::error::ClickError;
+
+use std::collections::BTreeMap;
+
+/// Max length of the name part of a key, and of a label value
+const MAX_NAME_LEN: usize = 63;
+/// Max length of the (optional) prefix part of a key
+const MAX_PREFIX_LEN: usize = 253;
+
+lazy_static! {
+    static ref NAME_RE: Regex = Regex::new(r"^([A-Za-z0-9][-A-Za-z0-9_.]*)?[A-Za-z0-9]$").unwrap();
+    static ref PREFIX_RE: Regex =
+        Regex::new(r"^[a-z0-9]([-a-z0-9]*[a-z0-9])?(\.[a-z0-9]([-a-z0-9]*[a-z0-9])?)*$").unwrap();
+}
+
+/// Is this for labels or annotations. Keys are the same for both, but label values are restricted.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MetaKind {
+    Label,
+    Annotation,
+}
+
+impl MetaKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MetaKind::Label => "label",
+            MetaKind::Annotation => "annotation",
+        }
+    }
+}
+
+/// One requested change
+#[derive(Debug, PartialEq)]
+pub enum MetaChange {
+    Set(String, String),
+    Remove(String),
+}
+
+impl MetaChange {
+    fn key(&self) -> &str {
+        match self {
+            MetaChange::Set(key, _) => key,
+            MetaChange::Remove(key) => key,
+        }
+    }
+}
+
+/// What a change does to a single key. None means the key isn't there.
+#[derive(Debug, PartialEq)]
+pub struct KeyChange {
+    pub key: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+fn check_name(kind: MetaKind, what: &str, name: &str) -> Result<(), ClickError> {
+    if name.len() > MAX_NAME_LEN {
+        return Err(ClickError::ParseErr(format!(
+            "Invalid {} {} '{}': must be no more than {} characters",
+            kind.as_str(),
+            what,
+            name,
+            MAX_NAME_LEN
+        )));
+    }
+    if !NAME_RE.is_match(name) {
+        return Err(ClickError::ParseErr(format!(
+            "Invalid {} {} '{}': must start and end with a letter or number, and contain only \
+             letters, numbers, '-', '_', and '.'",
+            kind.as_str(),
+            what,
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// Check that key is a valid label or annotation key, which is a name with an optional dns
+/// subdomain prefix, like `app.kubernetes.io/name`
+pub fn validate_key(kind: MetaKind, key: &str) -> Result<(), ClickError> {
+    let name = match key.split_once('/') {
+        Some((prefix, name)) => {
+            if prefix.is_empty() || prefix.len() > MAX_PREFIX_LEN || !PREFIX_RE.is_match(prefix) {
+                return Err(ClickError::ParseErr(format!(
+                    "Invalid {} key '{}': the prefix must be a lowercase dns subdomain of no \
+                     more than {} characters",
+                    kind.as_str(),
+                    key,
+                    MAX_PREFIX_LEN
+                )));
+            }
+            name
+        }
+        None => key,
+    };
+    if name.is_empty() {
+        return Err(ClickError::ParseErr(format!(
+            "Invalid {} key '{}': name can't be empty",
+            kind.as_str(),
+            key
+        )));
+    }
+    check_name(kind, "key", name)
+}
+
+/// Check that value is a valid value for kind. Label values can be empty, or a name. Annotation
+/// values can be anything.
+pub fn validate_value(kind: MetaKind, value: &str) -> Result<(), ClickError> {
+    match kind {
+        MetaKind::Label if !value.is_empty() => check_name(kind, "value", value),
+        _ => Ok(()),
+    }
+}
+
+/// Parse arguments like `key=value` (set key to value) and `key-` (remove key), validating them
+pub fn parse_changes(kind: MetaKind, args: &[&str]) -> Result<Vec<MetaChange>, ClickError> {
+    let mut changes: Vec<MetaChange> = vec![];
+    for arg in args.iter() {
+        let change = match arg.split_once('=') {
+            Some((key, value)) => {
+                validate_key(kind, key)?;
+                validate_value(kind, value)?;
+                MetaChange::Set(key.to_string(), value.to_string())
+            }
+            None => match arg.strip_suffix('-') {
+                Some(key) => {
+                    validate_key(kind, key)?;
+                    MetaChange::Remove(key.to_string())
+                }
+                None => {
+                    return Err(ClickError::ParseErr(format!(
+                    "Invalid argument '{}': use key=value to set a {kind}, or key- to remove one",
+                    arg,
+                    kind = kind.as_str()
+                )))
+                }
+            },
+        };
+        if changes.iter().any(|c| c.key() == change.key()) {
+            return Err(ClickError::ParseErr(format!(
+                "{} '{}' is specified more than once",
+                crate::command::uppercase_first(kind.as_str()),
+                change.key()
+            )));
+        }
+        changes.push(change);
+    }
+    Ok(changes)
+}
+
+/// Work out what applying changes to current would do. Keys that wouldn't change aren't included.
+/// Unless overwrite is true, it's an error to change the value of a key that's already set (like
+/// kubectl).
+pub fn plan_changes(
+    current: &BTreeMap<String, String>,
+    changes: &[MetaChange],
+    overwrite: bool,
+) -> Result<Vec<KeyChange>, ClickError> {
+    let mut planned = vec![];
+    for change in changes.iter() {
+        let old = current.get(change.key());
+        let new = match change {
+            MetaChange::Set(key, value) => {
+                match old {
+                    Some(old) if old == value => continue,
+                    Some(old) if !overwrite => {
+                        return Err(ClickError::CommandError(format!(
+                            "'{}' already has a value ({}), and --overwrite is not set",
+                            key, old
+                        )));
+                    }
+                    _ => {}
+                }
+                Some(value.clone())
+            }
+            MetaChange::Remove(_) if old.is_none() => continue,
+            MetaChange::Remove(_) => None,
+        };
+        planned.push(KeyChange {
+            key: change.key().to_string(),
+            old: old.cloned(),
+            new,
+        });
+    }
+    Ok(planned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_key() {
+        assert!(validate_key(MetaKind::Label, "app").is_ok());
+        assert!(validate_key(MetaKind::Label, "app.kubernetes.io/name").is_ok());
+        assert!(validate_key(MetaKind::Label, "a_b-c.D9").is_ok());
+        assert!(validate_key(MetaKind::Annotation, "example.com/some-thing").is_ok());
+
+        assert!(validate_key(MetaKind::Label, "").is_err());
+        assert!(validate_key(MetaKind::Label, "-app").is_err());
+        assert!(validate_key(MetaKind::Label, "app-").is_err());
+        assert!(validate_key(MetaKind::Label, "a b").is_err());
+        assert!(validate_key(MetaKind::Label, "example.com/").is_err());
+        assert!(validate_key(MetaKind::Label, "/name").is_err());
+        assert!(validate_key(MetaKind::Label, "Example.com/name").is_err());
+        assert!(validate_key(MetaKind::Label, &"a".repeat(64)).is_err());
+        assert!(validate_key(MetaKind::Label, &"a".repeat(63)).is_ok());
+    }
+
+    #[test]
+    fn test_validate_value() {
+        assert!(validate_value(MetaKind::Label, "").is_ok());
+        assert!(validate_value(MetaKind::Label, "v1.2_3").is_ok());
+        assert!(validate_value(MetaKind::Label, "has space").is_err());
+        assert!(validate_value(MetaKind::Label, &"a".repeat(64)).is_err());
+        assert!(validate_value(MetaKind::Annotation, "has space, and: anything").is_ok());
+    }
+
+    #[test]
+    fn test_parse_changes() {
+        assert_eq!(
+            parse_changes(MetaKind::Label, &["app=web", "tier-", "empty="]).unwrap(),
+            vec![
+                MetaChange::Set("app".to_string(), "web".to_string()),
+                MetaChange::Remove("tier".to_string()),
+                MetaChange::Set("empty".to_string(), "".to_string()),
+            ]
+        );
+        // the '=' means this is a set, even though it ends with a '-'
+        assert_eq!(
+            parse_changes(MetaKind::Annotation, &["note=a-"]).unwrap(),
+            vec![MetaChange::Set("note".to_string(), "a-".to_string())]
+        );
+        assert!(parse_changes(MetaKind::Label, &["app"]).is_err());
+        assert!(parse_changes(MetaKind::Label, &["app=a b"]).is_err());
+        assert!(parse_changes(MetaKind::Label, &["app=x", "app-"]).is_err());
+    }
+
+    #[test]
+    fn test_plan_changes() {
+        let mut current = BTreeMap::new();
+        current.insert("app".to_string(), "web".to_string());
+        current.insert("tier".to_string(), "frontend".to_string());
+
+        let changes = parse_changes(
+            MetaKind::Label,
+            &["app=web", "tier-", "missing-", "new=yes"],
+        )
+        .unwrap();
+        assert_eq!(
+            plan_changes(&current, &changes, false).unwrap(),
+            vec![
+                KeyChange {
+                    key: "tier".to_string(),
+                    old: Some("frontend".to_string()),
+                    new: None,
+                },
+                KeyChange {
+                    key: "new".to_string(),
+                    old: None,
+                    new: Some("yes".to_string()),
+                },
+            ]
+        );
+
+        let changes = parse_changes(MetaKind::Label, &["app=api"]).unwrap();
+        assert!(plan_changes(&current, &changes, false).is_err());
+        assert_eq!(
+            plan_changes(&current, &changes, true).unwrap(),
+            vec![KeyChange {
+                key: "app".to_string(),
+                old: Some("web".to_string()),
+                new: Some("api".to_string()),
+            }]
+        );
+    }
+}
@@ -35,7 +35,9 @@ extern crate ctrlc;
 extern crate dirs;
 extern crate duct_sh;
 extern crate humantime;
+extern crate mlua;
 extern crate os_pipe;
+extern crate prost;
 extern crate regex;
 extern crate rustls;
 extern crate rustyline;
@@ -49,6 +51,7 @@ extern crate term;
 extern crate bytes;
 #[macro_use]
 extern crate k8s_openapi;
+extern crate k8s_pb;
 extern crate reqwest;
 extern crate url;
 extern crate yasna;
@@ -57,17 +60,35 @@ extern crate yasna;
 mod command;
 #[macro_use]
 mod completer;
+mod bindings;
 mod command_processor;
 mod config;
 mod crd;
+mod cron;
 mod describe;
+mod diff;
 mod env;
 mod error;
+mod fuzzy;
+mod highlight;
+mod jobs;
+mod jsonpath;
 mod k8s;
 mod k8s_table;
 mod kobj;
+mod labels;
+mod manifest;
+mod metrics;
+mod parallel;
 mod parser;
+mod plugins;
+mod protobuf;
+mod retry;
+mod script;
+mod selection;
 mod table;
+mod tar;
+mod theme;
 mod values;
 
 #[cfg(test)]
@@ -75,13 +96,16 @@ mod duct_mock;
 
 use clap::{App, Arg};
 
+use std::io::{self, BufRead, IsTerminal};
 use std::path::PathBuf;
 
 use crate::command_processor::CommandProcessor;
-use crate::config::{ClickConfig, Config};
+use crate::config::{
+    is_go_duration, parse_verbose, ClickConfig, Config, Impersonation, MAX_VERBOSE,
+};
 use crate::env::Env;
 
-use crate::output::ClickWriter;
+use crate::output::OutputFormat;
 
 fn main() {
     env_logger::init();
@@ -92,17 +116,38 @@ fn main() {
         .about("Command Line Interactive Contoller for Kubernetes")
         .arg(
             Arg::with_name("config_dir")
-                .short("c")
                 .long("config_dir")
                 .value_name("DIR")
                 .help("Specify the directory to find kubernetes and click configs")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("kubeconfig")
+                .long("kubeconfig")
+                .value_name("PATH")
+                .help(
+                    "Use the specified kubernetes config file, ignoring KUBECONFIG and the \
+                     default config",
+                )
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("exec")
                 .long("exec")
                 .value_name("COMMAND")
                 .help("Execute the specified command then exit")
+                .takes_value(true)
+                .conflicts_with("commands"),
+        )
+        .arg(
+            Arg::with_name("commands")
+                .short("c")
+                .value_name("COMMANDS")
+                .help(
+                    "Run the commands (separated by ';' or '&&') then exit, rather than starting the repl. \
+                     The exit status is non-zero if any of them fail. Commands can also be piped \
+                     to click on stdin.",
+                )
                 .takes_value(true),
         )
         .arg(
@@ -119,6 +164,99 @@ fn main() {
                 .help("Start in the specified namespace")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("dry_run")
+                .long("dry-run")
+                .help(
+                    "Print the kubectl commands that would change anything, rather than running \
+                     them (can also be changed with 'set dry-run')",
+                )
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .help(
+                    "How to format output: table, wide (tables with all extra columns), or json \
+                     or yaml (the objects as fetched, with lists printed as the api server sent \
+                     them, so the filtering and sorting options for tables don't apply)",
+                )
+                .takes_value(true)
+                .possible_values(OutputFormat::VALUES),
+        )
+        .arg(
+            Arg::with_name("no_rc")
+                .long("no-rc")
+                .help(
+                    "Don't run the commands in the rc file (~/.config/click/clickrc unless \
+                     rc_file is set) when the repl starts",
+                )
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("as")
+                .long("as")
+                .value_name("USER")
+                .help(
+                    "Send requests as this user, and run kubectl as them (can also be changed \
+                     with 'set impersonate')",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("as_group")
+                .long("as-group")
+                .value_name("GROUP")
+                .help(
+                    "Send requests as a member of this group too, can be repeated (can also be \
+                     changed with 'set impersonate_group')",
+                )
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .requires("as"),
+        )
+        .arg(
+            Arg::with_name("timeout")
+                .long("timeout")
+                .value_name("DURATION")
+                .help(
+                    "How long kubectl waits for the server before giving up, like 30s or 2m \
+                     (passed to it as --request-timeout, overriding default_timeout)",
+                )
+                .takes_value(true)
+                .validator(|v| {
+                    if is_go_duration(&v) {
+                        Ok(())
+                    } else {
+                        Err("must be a duration like 30s or 2m".to_string())
+                    }
+                }),
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .short("v")
+                .long("v")
+                .value_name("LEVEL")
+                .help(
+                    "Print each api request to stderr, with how long it took. --v=2 prints their \
+                     headers too (can also be changed with 'set verbose')",
+                )
+                .takes_value(true)
+                .min_values(0)
+                .max_values(1)
+                .validator(|v| match parse_verbose(&v) {
+                    Some(_) => Ok(()),
+                    None => Err(format!("must be a level from 0 to {}", MAX_VERBOSE)),
+                }),
+        )
+        .arg(
+            Arg::with_name("no_color")
+                .long("no-color")
+                .help("Don't color any output (this is also the default if NO_COLOR is set)")
+                .takes_value(false),
+        )
         .get_matches();
 
     let conf_dir = if let Some(dir) = matches.value_of("config_dir") {
@@ -138,7 +276,7 @@ fn main() {
 
     let mut click_path = conf_dir.clone();
     click_path.push("click.config");
-    let click_conf = match ClickConfig::from_file(click_path.as_path().to_str().unwrap()) {
+    let mut click_conf = match ClickConfig::from_file(click_path.as_path().to_str().unwrap()) {
         Ok(conf) => conf,
         Err(e) => {
             println!("Could not load click config: {}\nUsing default values.", e);
@@ -146,10 +284,32 @@ fn main() {
         }
     };
 
-    let config_paths = std::env::var_os("KUBECONFIG")
-        .map(|paths| {
-            let split_paths = std::env::split_paths(&paths);
-            split_paths.collect::<Vec<PathBuf>>()
+    // set before any contexts are made, so they all pick it up
+    if matches.is_present("verbose") {
+        click_conf.verbose = matches
+            .value_of("verbose")
+            .and_then(parse_verbose)
+            .unwrap_or(1);
+    }
+
+    if let Some(user) = matches.value_of("as") {
+        click_conf.impersonate = Some(Impersonation {
+            user: user.to_string(),
+            groups: matches
+                .values_of("as_group")
+                .map(|groups| groups.map(|g| g.to_string()).collect())
+                .unwrap_or_default(),
+        });
+    }
+
+    let config_paths = matches
+        .value_of_os("kubeconfig")
+        .map(|path| vec![PathBuf::from(path)])
+        .or_else(|| {
+            std::env::var_os("KUBECONFIG").map(|paths| {
+                let split_paths = std::env::split_paths(&paths);
+                split_paths.collect::<Vec<PathBuf>>()
+            })
         })
         .unwrap_or_else(|| {
             let mut config_path = conf_dir.clone();
@@ -177,10 +337,36 @@ fn main() {
         }
     };
 
-    let mut hist_path = conf_dir;
-    hist_path.push("click.history");
+    let hist_path = match click_conf.history_file {
+        Some(ref file) => env::expand_home(file),
+        None => conf_dir.join("click.history"),
+    };
 
     let mut env = Env::new(config, click_conf, click_path);
+    if matches.is_present("no_color") {
+        env.disable_color();
+    }
+    env.dry_run = matches.is_present("dry_run");
+    env.request_timeout = matches.value_of("timeout").map(|t| t.to_string());
+    if let Some(format) = matches.value_of("output") {
+        env.output_format = format.parse().unwrap(); // safe, validated by clap
+    }
+
+    let commands = matches
+        .value_of("commands")
+        .or_else(|| matches.value_of("exec"));
+    // without a terminal, commands are read from stdin, with no prompt or line editing
+    let batch = commands.is_some() || !io::stdin().is_terminal();
+    let mut processor = if batch {
+        CommandProcessor::new_batch(env)
+    } else {
+        CommandProcessor::new(env, hist_path)
+    };
+    if !batch && !matches.is_present("no_rc") {
+        processor.run_rc_file();
+    }
+    // the command line wins over anything the rc file set
+    let env = processor.env_mut();
     if let Some(context) = matches.value_of("context") {
         env.set_context(Some(context));
     }
@@ -188,11 +374,15 @@ fn main() {
         env.set_namespace(Some(namespace));
     }
 
-    let mut processor = CommandProcessor::new(env, hist_path);
-    if let Some(command) = matches.value_of("exec") {
-        let writer = ClickWriter::new();
-        processor.process_line(command, writer);
-    } else {
-        processor.run_repl();
+    let code = match commands {
+        Some(commands) => processor.run_commands(std::iter::once(commands.to_string())),
+        None if batch => processor.run_commands(io::stdin().lock().lines().map_while(Result::ok)),
+        None => {
+            processor.run_repl();
+            0
+        }
+    };
+    if code != 0 {
+        std::process::exit(code);
     }
 }
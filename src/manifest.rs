@@ -0,0 +1,161 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parsing manifests (files of yaml or json kubernetes objects), as passed to `apply -f`
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::error::ClickError;
+use crate::kobj::{self, KObj};
+use crate::values::val_str_opt;
+
+/// Parse text, which can hold any number of yaml documents (or a json object), into the objects
+/// it contains. Empty documents are skipped, and `kind: List` objects are replaced by their items.
+/// source is used in error messages, and is usually the file name.
+pub fn parse_manifests(source: &str, text: &str) -> Result<Vec<Value>, ClickError> {
+    let mut objects = vec![];
+    for (i, document) in serde_yaml::Deserializer::from_str(text).enumerate() {
+        let value = Value::deserialize(document)
+            .map_err(|e| ClickError::ParseErr(format!("{} (document {}): {}", source, i + 1, e)))?;
+        match value {
+            Value::Null => {}
+            Value::Object(_) if val_str_opt("/kind", &value).as_deref() == Some("List") => {
+                if let Some(Value::Array(items)) = value.get("items") {
+                    for item in items.iter() {
+                        check_object(source, i, item)?;
+                        objects.push(item.clone());
+                    }
+                }
+            }
+            _ => {
+                check_object(source, i, &value)?;
+                objects.push(value);
+            }
+        }
+    }
+    Ok(objects)
+}
+
+/// Make sure value looks like a kubernetes object we can apply
+fn check_object(source: &str, index: usize, value: &Value) -> Result<(), ClickError> {
+    if !value.is_object() {
+        return Err(ClickError::ParseErr(format!(
+            "{} (document {}): not a kubernetes object",
+            source,
+            index + 1
+        )));
+    }
+    for field in ["/apiVersion", "/kind", "/metadata/name"].iter() {
+        if val_str_opt(field, value).is_none() {
+            return Err(ClickError::ParseErr(format!(
+                "{} (document {}): object has no {}",
+                source,
+                index + 1,
+                field[1..].replace('/', ".")
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// The namespace manifest should be applied in: its own namespace if it has one, or
+/// default_namespace for namespaced kinds
+pub fn manifest_namespace(manifest: &Value, default_namespace: Option<&str>) -> Option<String> {
+    match val_str_opt("/metadata/namespace", manifest) {
+        Some(ns) => Some(ns),
+        None => match val_str_opt("/kind", manifest) {
+            Some(kind) if kobj::is_cluster_scoped_kind(&kind) => None,
+            _ => default_namespace.map(|ns| ns.to_string()),
+        },
+    }
+}
+
+/// The object a manifest applies to, in namespace
+pub fn manifest_kobj(manifest: &Value, namespace: Option<String>) -> Result<KObj, ClickError> {
+    let mut obj = KObj::from_json(manifest)?;
+    obj.namespace = namespace;
+    Ok(obj)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_manifests() {
+        let text = "\
+---
+apiVersion: v1
+kind: ConfigMap
+metadata:
+  name: one
+---
+# just a comment
+---
+apiVersion: v1
+kind: List
+items:
+- apiVersion: v1
+  kind: Secret
+  metadata:
+    name: two
+- apiVersion: apps/v1
+  kind: Deployment
+  metadata:
+    name: three
+    namespace: other
+";
+        let objects = parse_manifests("test.yaml", text).unwrap();
+        let names: Vec<String> = objects
+            .iter()
+            .map(|o| val_str_opt("/metadata/name", o).unwrap())
+            .collect();
+        assert_eq!(names, vec!["one", "two", "three"]);
+
+        let json = r#"{"apiVersion": "v1", "kind": "Pod", "metadata": {"name": "p"}}"#;
+        assert_eq!(parse_manifests("test.json", json).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_parse_manifests_errors() {
+        assert!(parse_manifests("t", "kind: ConfigMap\nmetadata:\n  name: x\n").is_err());
+        assert!(parse_manifests("t", "apiVersion: v1\nkind: ConfigMap\n").is_err());
+        assert!(parse_manifests("t", "- a\n- b\n").is_err());
+        assert!(parse_manifests("t", "a: [b\n").is_err());
+        let err = parse_manifests("f.yaml", "---\napiVersion: v1\nkind: Pod\nmetadata: {}\n")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("f.yaml (document 1)"));
+        assert!(err.contains("metadata.name"));
+    }
+
+    #[test]
+    fn test_manifest_namespace() {
+        let namespaced = serde_json::json!({"kind": "Pod", "metadata": {"name": "p"}});
+        let explicit =
+            serde_json::json!({"kind": "Pod", "metadata": {"name": "p", "namespace": "ns"}});
+        let cluster = serde_json::json!({"kind": "Namespace", "metadata": {"name": "n"}});
+        assert_eq!(
+            manifest_namespace(&namespaced, Some("default")),
+            Some("default".to_string())
+        );
+        assert_eq!(manifest_namespace(&namespaced, None), None);
+        assert_eq!(
+            manifest_namespace(&explicit, Some("default")),
+            Some("ns".to_string())
+        );
+        assert_eq!(manifest_namespace(&cluster, Some("default")), None);
+    }
+}
@@ -0,0 +1,182 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reading resource usage from the metrics api (metrics.k8s.io), which is served by metrics-server.
+//! k8s_openapi doesn't know about this api, so the types we need are defined here.
+
+use k8s_openapi::{
+    apimachinery::pkg::{api::resource::Quantity, apis::meta::v1::ObjectMeta},
+    http::{Request, StatusCode},
+    RequestError,
+};
+use serde::Deserialize;
+
+use crate::{
+    env::Env,
+    error::{ClickErrNo, ClickError},
+    table::raw_quantity,
+    values::val_str_opt,
+};
+
+const METRICS_API: &str = "/apis/metrics.k8s.io/v1beta1";
+
+/// cpu and memory usage, over the window the metrics are for
+#[derive(Debug, Default, Deserialize)]
+pub struct Usage {
+    pub cpu: Option<Quantity>,
+    pub memory: Option<Quantity>,
+}
+
+impl Usage {
+    /// cpu usage in cores
+    pub fn cpu_cores(&self) -> f64 {
+        self.cpu.as_ref().map(raw_quantity).unwrap_or(0.0)
+    }
+
+    /// memory usage in bytes
+    pub fn memory_bytes(&self) -> f64 {
+        self.memory.as_ref().map(raw_quantity).unwrap_or(0.0)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ContainerMetrics {
+    pub name: String,
+    #[serde(default)]
+    pub usage: Usage,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PodMetrics {
+    pub metadata: ObjectMeta,
+    #[serde(default)]
+    pub containers: Vec<ContainerMetrics>,
+}
+
+impl PodMetrics {
+    /// The usage of all the pod's containers added together
+    pub fn total_cpu_cores(&self) -> f64 {
+        self.containers.iter().map(|c| c.usage.cpu_cores()).sum()
+    }
+
+    pub fn total_memory_bytes(&self) -> f64 {
+        self.containers.iter().map(|c| c.usage.memory_bytes()).sum()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NodeMetrics {
+    pub metadata: ObjectMeta,
+    #[serde(default)]
+    pub usage: Usage,
+}
+
+#[derive(Debug, Deserialize)]
+struct MetricsList<T> {
+    items: Vec<T>,
+}
+
+/// Fetch a list from the metrics api. The api is provided by metrics-server, which isn't always
+/// installed, so requests failing because the api isn't there get an error saying so.
+fn list_metrics<T: for<'de> Deserialize<'de>>(env: &Env, path: &str) -> Result<Vec<T>, ClickError> {
+    let request = Request::get(format!("{}{}", METRICS_API, path))
+        .body(vec![])
+        .map_err(RequestError::Http)?;
+    let response = env.run_on_context(|c| c.execute(request))?;
+    match response.status() {
+        StatusCode::OK => {
+            let list: MetricsList<T> = serde_json::from_slice(response.body())?;
+            Ok(list.items)
+        }
+        StatusCode::NOT_FOUND => Err(ClickError::CommandError(
+            "The metrics api (metrics.k8s.io) isn't available in this cluster. It's provided by \
+             metrics-server, which doesn't seem to be installed."
+                .to_string(),
+        )),
+        StatusCode::SERVICE_UNAVAILABLE => Err(ClickError::CommandError(
+            "The metrics api (metrics.k8s.io) isn't responding. Check that metrics-server is \
+             running."
+                .to_string(),
+        )),
+        StatusCode::UNAUTHORIZED => Err(ClickError::Kube(ClickErrNo::Unauthorized)),
+        status => {
+            let message = serde_json::from_slice(response.body())
+                .ok()
+                .and_then(|body| val_str_opt("/message", &body));
+            Err(ClickError::CommandError(format!(
+                "Could not fetch metrics ({}): {}",
+                status,
+                message.unwrap_or_else(|| "unknown error".to_string())
+            )))
+        }
+    }
+}
+
+/// Usage of the pods in namespace, or in all namespaces if it's None
+pub fn pod_metrics(env: &Env, namespace: Option<&str>) -> Result<Vec<PodMetrics>, ClickError> {
+    match namespace {
+        Some(ns) => list_metrics(env, &format!("/namespaces/{}/pods", ns)),
+        None => list_metrics(env, "/pods"),
+    }
+}
+
+pub fn node_metrics(env: &Env) -> Result<Vec<NodeMetrics>, ClickError> {
+    list_metrics(env, "/nodes")
+}
+
+/// Format a number of cores like kubectl top does, in millicores
+pub fn format_cpu(cores: f64) -> String {
+    format!("{}m", (cores * 1000.0).round() as i64)
+}
+
+/// Format a number of bytes like kubectl top does, in mebibytes
+pub fn format_memory(bytes: f64) -> String {
+    format!("{}Mi", (bytes / (1024.0 * 1024.0)).round() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pod_metrics() {
+        let body = r#"{
+  "kind": "PodMetricsList",
+  "apiVersion": "metrics.k8s.io/v1beta1",
+  "items": [{
+    "metadata": {"name": "web", "namespace": "default"},
+    "timestamp": "2021-06-01T00:00:00Z",
+    "window": "30s",
+    "containers": [
+      {"name": "app", "usage": {"cpu": "250000000n", "memory": "100Mi"}},
+      {"name": "sidecar", "usage": {"cpu": "5m", "memory": "20Mi"}}
+    ]
+  }]
+}"#;
+        let list: MetricsList<PodMetrics> = serde_json::from_str(body).unwrap();
+        let pod = &list.items[0];
+        assert_eq!(pod.metadata.name.as_deref(), Some("web"));
+        assert_eq!(pod.containers.len(), 2);
+        assert_eq!(format_cpu(pod.total_cpu_cores()), "255m");
+        assert_eq!(format_memory(pod.total_memory_bytes()), "120Mi");
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(format_cpu(1.5), "1500m");
+        assert_eq!(format_cpu(0.0001), "0m");
+        assert_eq!(format_memory(1024.0 * 1024.0 * 1024.0), "1024Mi");
+        assert_eq!(format_memory(0.0), "0Mi");
+    }
+}
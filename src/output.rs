@@ -14,7 +14,7 @@
 
 /// Module to handle writing data to stdout, and/or copying/writing it
 /// to files etc
-use ansi_term::Colour::{Blue, Green};
+use ansi_term::Style;
 use duct::Handle;
 use duct_sh::sh_dangerous;
 use os_pipe::{pipe, PipeWriter};
@@ -22,11 +22,16 @@ use serde::ser::Serialize;
 use serde_json::ser::{CharEscape, Formatter, PrettyFormatter, Serializer};
 use serde_json::Error as JsonError;
 
-use std::fs::File;
+use std::fmt;
+use std::fs::{File, OpenOptions};
 use std::io;
 use std::io::{Stdout, Write};
+use std::path::PathBuf;
+use std::process::ExitStatus;
+use std::str::FromStr;
 
 use crate::error::ClickError;
+use crate::highlight;
 
 /// Ignore write errors (for now) TODO: What to do with them?
 macro_rules! clickwrite {
@@ -55,16 +60,121 @@ macro_rules! clickwriteln {
     };
 }
 
+/// How commands should format what they print
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OutputFormat {
+    /// the normal tables
+    #[default]
+    Table,
+    /// tables, with all the extra columns
+    Wide,
+    /// the raw json for whatever was fetched
+    Json,
+    /// the raw json for whatever was fetched, as yaml
+    Yaml,
+}
+
+impl OutputFormat {
+    pub const VALUES: &'static [&'static str] = &["table", "wide", "json", "yaml"];
+
+    /// Is this json or yaml, rather than a table
+    pub fn is_raw(&self) -> bool {
+        matches!(self, OutputFormat::Json | OutputFormat::Yaml)
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = ClickError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(OutputFormat::Table),
+            "wide" => Ok(OutputFormat::Wide),
+            "json" => Ok(OutputFormat::Json),
+            "yaml" => Ok(OutputFormat::Yaml),
+            _ => Err(ClickError::ParseErr(format!(
+                "Invalid output format '{}'. Possible values are: [{}]",
+                s,
+                OutputFormat::VALUES.join(", ")
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                OutputFormat::Table => "table",
+                OutputFormat::Wide => "wide",
+                OutputFormat::Json => "json",
+                OutputFormat::Yaml => "yaml",
+            }
+        )
+    }
+}
+
+/// Where we are in an ANSI escape sequence
+#[derive(Clone, Copy, PartialEq)]
+enum EscapeState {
+    None,
+    Esc,
+    Csi,
+    Charset,
+}
+
+/// Strips color and other ANSI escape sequences out of output that's not going to a terminal.
+/// Sequences can be split across writes, so this tracks where we are in one between them.
+struct AnsiStripper {
+    state: EscapeState,
+}
+
+impl AnsiStripper {
+    fn new() -> AnsiStripper {
+        AnsiStripper {
+            state: EscapeState::None,
+        }
+    }
+
+    fn strip(&mut self, buf: &[u8]) -> Vec<u8> {
+        let mut stripped = Vec::with_capacity(buf.len());
+        for &b in buf.iter() {
+            self.state = match (self.state, b) {
+                (EscapeState::None, 0x1b) => EscapeState::Esc,
+                (EscapeState::None, _) => {
+                    stripped.push(b);
+                    EscapeState::None
+                }
+                (EscapeState::Esc, b'[') => EscapeState::Csi,
+                (EscapeState::Esc, b'(') | (EscapeState::Esc, b')') => EscapeState::Charset,
+                // csi sequences end with a byte in this range
+                (EscapeState::Csi, 0x40..=0x7e) => EscapeState::None,
+                (EscapeState::Csi, _) => EscapeState::Csi,
+                (EscapeState::Esc, _) | (EscapeState::Charset, _) => EscapeState::None,
+            };
+        }
+        stripped
+    }
+}
+
+/// Remove any color or other escape sequences from s
+pub fn strip_ansi(s: &str) -> String {
+    // escape sequences are ascii, so removing them can't leave invalid utf-8 behind
+    String::from_utf8_lossy(&AnsiStripper::new().strip(s.as_bytes())).into_owned()
+}
+
 struct PipeProc {
     pipe: PipeWriter,
     expr: Handle,
 }
 
 impl PipeProc {
-    fn finish(self) -> io::Result<String> {
+    /// Close the pipe and wait for the command to exit. Its output goes straight to stdout
+    fn finish(self) -> io::Result<ExitStatus> {
         drop(self.pipe);
-        let output = self.expr.into_output()?;
-        String::from_utf8(output.stdout).map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+        self.expr.into_output().map(|output| output.status)
     }
 
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
@@ -76,83 +186,418 @@ impl PipeProc {
     }
 }
 
+/// Output held back while we find out if it fits on the screen. If it doesn't, it's sent to the
+/// pager, along with everything written after it.
+struct PendingPager {
+    command: String,
+    rows: usize,
+    cols: usize,
+    buffer: Vec<u8>,
+    /// lines and column the buffered output takes up on the screen, counting wrapped lines
+    lines: usize,
+    col: usize,
+    /// escape sequences don't take up space, so they're stripped before counting
+    stripper: AnsiStripper,
+}
+
+impl PendingPager {
+    /// Hold on to buf. Returns true if the output no longer fits on the screen (leaving a line for
+    /// the prompt)
+    fn hold(&mut self, buf: &[u8]) -> bool {
+        self.buffer.extend_from_slice(buf);
+        for c in String::from_utf8_lossy(&self.stripper.strip(buf)).chars() {
+            if c == '\n' {
+                self.lines += 1;
+                self.col = 0;
+            } else {
+                self.col += 1;
+                if self.col > self.cols {
+                    self.lines += 1;
+                    self.col = 1;
+                }
+            }
+        }
+        self.lines >= self.rows.saturating_sub(1)
+    }
+}
+
+/// A file we're redirecting output to. The file is only opened when the first output is written,
+/// so a command that fails without printing anything doesn't clobber it
+struct RedirFile {
+    path: PathBuf,
+    append: bool,
+    file: Option<File>,
+}
+
+impl RedirFile {
+    fn open(&mut self) -> io::Result<&mut File> {
+        if self.file.is_none() {
+            let file = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .append(self.append)
+                .truncate(!self.append)
+                .open(&self.path)?;
+            self.file = Some(file);
+        }
+        Ok(self.file.as_mut().unwrap()) // safe, set above
+    }
+
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.open()?.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.file.as_mut() {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
 enum WriterOutput {
     Stdout(Stdout),
+    /// output that didn't fit on the screen, going through the pager
+    Pager(Box<PipeProc>),
     #[allow(dead_code)] // used in test
     Buffer(Vec<u8>),
-    File(File),
+    File(RedirFile),
     Pipe(Box<PipeProc>),
 }
 
 pub struct ClickWriter {
     output: WriterOutput,
+    failed: bool,
+    /// set if colors should be stripped from the output
+    stripper: Option<AnsiStripper>,
+    output_format: OutputFormat,
+    pager: Option<PendingPager>,
+    /// highlight json and yaml output going to the terminal
+    syntax_highlight: bool,
+    /// how wide the terminal is, so tables can be cut down to fit it. None means tables are
+    /// printed at their full width.
+    table_width: Option<usize>,
 }
 
 impl ClickWriter {
     pub fn new() -> ClickWriter {
         ClickWriter {
             output: WriterOutput::Stdout(std::io::stdout()),
+            failed: false,
+            stripper: None,
+            output_format: OutputFormat::Table,
+            pager: None,
+            syntax_highlight: true,
+            table_width: None,
         }
     }
 
     #[allow(dead_code)] // used in test
-    pub fn with_buffer(buffer: Vec<u8>, _do_color: bool) -> ClickWriter {
-        ClickWriter {
+    pub fn with_buffer(buffer: Vec<u8>, do_color: bool) -> ClickWriter {
+        let mut writer = ClickWriter {
             output: WriterOutput::Buffer(buffer),
+            failed: false,
+            stripper: None,
+            output_format: OutputFormat::Table,
+            pager: None,
+            syntax_highlight: true,
+            table_width: None,
+        };
+        writer.set_color(do_color);
+        writer
+    }
+
+    /// A new writer for running another command, as part of the same line. Buffered writers get a
+    /// new buffer (so tests can collect the output of each command), others write to stdout.
+    pub fn sibling(&self) -> ClickWriter {
+        let mut writer = match self.output {
+            WriterOutput::Buffer(_) => ClickWriter::with_buffer(vec![], true),
+            _ => ClickWriter::new(),
+        };
+        writer.set_color(self.stripper.is_none());
+        writer
+    }
+
+    /// A writer that collects its output in a buffer, keeping or stripping color like this one.
+    /// Get what was written with finish_output.
+    pub fn buffered(&self) -> ClickWriter {
+        ClickWriter::with_buffer(vec![], self.stripper.is_none())
+    }
+
+    /// Set if color and other escape sequences should be kept in the output, or stripped out
+    pub fn set_color(&mut self, color: bool) {
+        self.stripper = if color {
+            None
+        } else {
+            Some(AnsiStripper::new())
+        };
+    }
+
+    /// Redirect output to the file at path, appending to it if append is true. The file isn't
+    /// opened until something is written, but this fails right away if its directory doesn't exist.
+    pub fn set_output_file(
+        &mut self,
+        path: &str,
+        append: bool,
+        strip_color: bool,
+    ) -> Result<(), ClickError> {
+        if path.is_empty() {
+            return Err(ClickError::CommandError(
+                "No file specified to redirect output to".to_string(),
+            ));
+        }
+        let path = PathBuf::from(path);
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            if !dir.is_dir() {
+                return Err(ClickError::CommandError(format!(
+                    "Can't redirect output to {}: directory {} does not exist",
+                    path.display(),
+                    dir.display()
+                )));
+            }
+        }
+        self.output = WriterOutput::File(RedirFile {
+            path,
+            append,
+            file: None,
+        });
+        self.set_color(!strip_color);
+        Ok(())
+    }
+
+    /// Truncate tables to fit a terminal this many columns wide, or not at all for None
+    pub fn set_table_width(&mut self, width: Option<usize>) {
+        self.table_width = width;
+    }
+
+    pub fn table_width(&self) -> Option<usize> {
+        self.table_width
+    }
+
+    /// Send output through the pager command if it's more than fits on a terminal of the specified
+    /// size. Output is held back until we know if it fits, so commands that need the user to see
+    /// what they've written so far (to answer a prompt, say) must call finish_paging first. This
+    /// only applies to output going to stdout.
+    pub fn set_pager(&mut self, command: String, rows: usize, cols: usize) {
+        if let WriterOutput::Stdout(_) = self.output {
+            self.pager = Some(PendingPager {
+                command,
+                rows,
+                cols,
+                buffer: vec![],
+                lines: 0,
+                col: 0,
+                stripper: AnsiStripper::new(),
+            });
+        }
+    }
+
+    /// The output didn't fit on the screen, so start the pager and send it everything so far. If
+    /// the pager can't be started the output goes to stdout instead.
+    fn start_pager(&mut self) -> io::Result<()> {
+        let pending = match self.pager.take() {
+            Some(pending) => pending,
+            None => return Ok(()),
+        };
+        let started = pipe().and_then(|(pipe_read, pipe_write)| {
+            sh_dangerous(&pending.command)
+                .stdin_file(pipe_read)
+                .unchecked()
+                .start()
+                .map(|handle| PipeProc {
+                    pipe: pipe_write,
+                    expr: handle,
+                })
+        });
+        match started {
+            Ok(pipe_proc) => self.output = WriterOutput::Pager(Box::new(pipe_proc)),
+            Err(e) => eprintln!("Could not start pager '{}': {}", pending.command, e),
+        }
+        self.write_all_output(&pending.buffer)
+    }
+
+    /// Stop paging the output of this command. Anything held back is written to the terminal, and
+    /// if the pager is running, this waits for the user to quit it. Call this before prompting for
+    /// input or running something that uses the terminal, like an editor.
+    pub fn finish_paging(&mut self) {
+        if let Some(pending) = self.pager.take() {
+            self.write_all_output(&pending.buffer).unwrap_or(());
+            self.flush().unwrap_or(());
+        }
+        if let WriterOutput::Pager(_) = self.output {
+            let output = std::mem::replace(&mut self.output, WriterOutput::Stdout(io::stdout()));
+            if let WriterOutput::Pager(pipe_proc) = output {
+                pipe_proc.finish().unwrap_or_default();
+            }
+        }
+    }
+
+    /// Set if json and yaml output to the terminal should be syntax highlighted
+    pub fn set_syntax_highlight(&mut self, highlight: bool) {
+        self.syntax_highlight = highlight;
+    }
+
+    /// Should json and yaml be highlighted. Output that isn't going to the terminal never is.
+    fn highlighting(&self) -> bool {
+        self.syntax_highlight
+            && matches!(
+                self.output,
+                WriterOutput::Stdout(_) | WriterOutput::Pager(_)
+            )
+    }
+
+    /// Set how commands should format their output (see OutputFormat)
+    pub fn set_output_format(&mut self, format: OutputFormat) {
+        self.output_format = format;
+    }
+
+    pub fn output_format(&self) -> OutputFormat {
+        self.output_format
+    }
+
+    /// Print value as json or yaml, according to the output format. Returns false (and prints
+    /// nothing) if the output format isn't json or yaml.
+    pub fn print_raw<T>(&mut self, value: &T) -> Result<bool, ClickError>
+    where
+        T: ?Sized + Serialize,
+    {
+        match self.output_format {
+            OutputFormat::Json => {
+                self.pretty_color_json(value)?;
+                writeln!(self).unwrap_or(());
+                Ok(true)
+            }
+            OutputFormat::Yaml => {
+                self.print_yaml(value)?;
+                Ok(true)
+            }
+            OutputFormat::Table | OutputFormat::Wide => Ok(false),
         }
     }
 
-    pub fn set_output_file(&mut self, file: File) {
-        self.output = WriterOutput::File(file);
+    /// Print a response body from the api server as it was sent if the output format is json, or
+    /// converted to yaml if it's yaml. Returns false (and prints nothing) for other formats.
+    pub fn print_raw_body(&mut self, body: &[u8]) -> Result<bool, ClickError> {
+        match self.output_format {
+            OutputFormat::Json => {
+                self.write_all(body).unwrap_or(());
+                if !body.ends_with(b"\n") {
+                    writeln!(self).unwrap_or(());
+                }
+                Ok(true)
+            }
+            OutputFormat::Yaml => {
+                // parsed as yaml, which json is, so the fields stay in the order they were sent
+                let value: serde_yaml::Value = serde_yaml::from_slice(body)?;
+                self.print_yaml(&value)?;
+                Ok(true)
+            }
+            OutputFormat::Table | OutputFormat::Wide => Ok(false),
+        }
     }
 
-    pub fn setup_pipe(&mut self, cmd: &str) -> Result<(), ClickError> {
+    /// Note that the command being run failed. If it didn't write anything, a file we're
+    /// redirecting to is then left untouched.
+    pub fn set_failed(&mut self) {
+        self.failed = true;
+    }
+
+    /// Is output going somewhere other than the terminal
+    pub fn is_redirected(&self) -> bool {
+        matches!(self.output, WriterOutput::File(_) | WriterOutput::Pipe(_))
+    }
+
+    pub fn setup_pipe(&mut self, cmd: &str, strip_color: bool) -> Result<(), ClickError> {
         let expr = sh_dangerous(cmd);
         let (pipe_read, pipe_write) = pipe()?;
-        let handle = expr.stdin_file(pipe_read).start()?;
+        // unchecked so we can report the exit code ourselves
+        let handle = expr.stdin_file(pipe_read).unchecked().start()?;
         self.output = WriterOutput::Pipe(Box::new(PipeProc {
             pipe: pipe_write,
             expr: handle,
         }));
+        // colors are usually stripped, since they confuse commands like grep
+        self.set_color(!strip_color);
         Ok(())
     }
 
-    pub fn finish_output(self) -> Option<Vec<u8>> {
+    pub fn finish_output(mut self) -> Option<Vec<u8>> {
+        if let Some(pending) = self.pager.take() {
+            self.write_all_output(&pending.buffer).unwrap_or(());
+            self.flush().unwrap_or(());
+        }
         match self.output {
+            WriterOutput::Pager(pipe_proc) => {
+                // the pager exiting early (the user quit before the end) isn't an error
+                pipe_proc.finish().unwrap_or_default();
+                None
+            }
             WriterOutput::Pipe(pipe_proc) => {
                 match pipe_proc.finish() {
-                    Ok(out) => {
-                        print!("{}", out);
-                    }
+                    Ok(status) if !status.success() => match status.code() {
+                        Some(code) => eprintln!("Command exited with code {}", code),
+                        None => eprintln!("Command was terminated by a signal"),
+                    },
+                    Ok(_) => {}
                     Err(e) => {
-                        eprint!("Failed to execute command: {}", e);
+                        eprintln!("Failed to execute command: {}", e);
+                    }
+                }
+                None
+            }
+            WriterOutput::File(mut redir) => {
+                // like the shell, an empty output still creates (or truncates) the file, but only
+                // if the command succeeded
+                if !self.failed {
+                    if let Err(e) = redir.open() {
+                        eprintln!("Can't open output file {}: {}", redir.path.display(), e);
                     }
                 }
                 None
             }
             WriterOutput::Buffer(buffer) => Some(buffer),
-            _ => None,
+            WriterOutput::Stdout(_) => None,
         }
     }
 
-    pub fn pretty_color_json<T: ?Sized>(&mut self, value: &T) -> Result<(), JsonError>
+    pub fn pretty_color_json<T>(&mut self, value: &T) -> Result<(), JsonError>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        if let WriterOutput::Stdout(_) = self.output {
+        if self.highlighting() {
             let mut ser = Serializer::with_formatter(self, PrettyColorFormatter::new());
             value.serialize(&mut ser)
+        } else if let WriterOutput::Stdout(_) | WriterOutput::Pager(_) = self.output {
+            serde_json::to_writer_pretty(self, value)
         } else {
             // don't do color if we're piping/redirecting
             serde_json::to_writer(self, value)
         }
     }
 
-    pub fn print_yaml<T: ?Sized>(&mut self, value: &T) -> Result<(), serde_yaml::Error>
+    pub fn print_yaml<T>(&mut self, value: &T) -> Result<(), serde_yaml::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        serde_yaml::to_writer(self, value)
+        if self.highlighting() {
+            let yaml = serde_yaml::to_string(value)?;
+            self.write_all(highlight::highlight_yaml(&yaml).as_bytes())
+                .unwrap_or(());
+            Ok(())
+        } else {
+            serde_yaml::to_writer(self, value)
+        }
+    }
+
+    /// Print text that's already yaml, like the output of another tool, highlighting it like
+    /// print_yaml does
+    pub fn print_yaml_text(&mut self, text: &str) {
+        if self.highlighting() {
+            self.write_all(highlight::highlight_yaml(text).as_bytes())
+                .unwrap_or(());
+        } else {
+            self.write_all(text.as_bytes()).unwrap_or(());
+        }
     }
 }
 
@@ -162,21 +607,59 @@ impl Default for ClickWriter {
     }
 }
 
-impl Write for ClickWriter {
-    fn write(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
+impl ClickWriter {
+    fn write_output(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
+        if let Some(pending) = self.pager.as_mut() {
+            if pending.hold(buf) {
+                self.start_pager()?;
+            }
+            return Ok(buf.len());
+        }
         match self.output {
             WriterOutput::Stdout(ref mut stdout) => stdout.write(buf),
+            WriterOutput::Pager(ref mut pipe_proc) => pipe_proc.write(buf),
             WriterOutput::Buffer(ref mut buffer) => buffer.write(buf),
-            WriterOutput::File(ref mut file) => file.write(buf),
+            WriterOutput::File(ref mut redir) => redir.write(buf),
             WriterOutput::Pipe(ref mut pipe_proc) => pipe_proc.write(buf),
         }
     }
 
+    fn write_all_output(&mut self, mut buf: &[u8]) -> Result<(), io::Error> {
+        while !buf.is_empty() {
+            match self.write_output(buf)? {
+                0 => return Err(io::ErrorKind::WriteZero.into()),
+                n => buf = &buf[n..],
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Write for ClickWriter {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
+        match self.stripper.as_mut() {
+            Some(stripper) => {
+                let stripped = stripper.strip(buf);
+                // write everything (not just what write takes) since we report all of buf written
+                let mut rest = &stripped[..];
+                while !rest.is_empty() {
+                    match self.write_output(rest)? {
+                        0 => return Err(io::ErrorKind::WriteZero.into()),
+                        n => rest = &rest[n..],
+                    }
+                }
+                Ok(buf.len())
+            }
+            None => self.write_output(buf),
+        }
+    }
+
     fn flush(&mut self) -> Result<(), io::Error> {
         match self.output {
             WriterOutput::Stdout(ref mut stdout) => stdout.flush(),
+            WriterOutput::Pager(ref mut pipe_proc) => pipe_proc.flush(),
             WriterOutput::Buffer(ref mut buffer) => buffer.flush(),
-            WriterOutput::File(ref mut file) => file.flush(),
+            WriterOutput::File(ref mut redir) => redir.flush(),
             WriterOutput::Pipe(ref mut pipe_proc) => pipe_proc.flush(),
         }
     }
@@ -184,7 +667,6 @@ impl Write for ClickWriter {
 
 pub struct PrettyColorFormatter<'a> {
     pretty: PrettyFormatter<'a>,
-    invalue: bool,
     iskey: bool,
 }
 
@@ -192,212 +674,249 @@ impl<'a> PrettyColorFormatter<'a> {
     pub fn new() -> PrettyColorFormatter<'a> {
         PrettyColorFormatter {
             pretty: PrettyFormatter::new(),
-            invalue: false,
             iskey: false,
         }
     }
 }
 
+/// Write whatever f writes in style
+fn write_styled<W, F>(writer: &mut W, style: Style, f: F) -> io::Result<()>
+where
+    W: ?Sized + Write,
+    F: FnOnce(&mut W) -> io::Result<()>,
+{
+    write!(writer, "{}", style.prefix()).unwrap_or(());
+    let r = f(writer);
+    write!(writer, "{}", style.suffix()).unwrap_or(());
+    r
+}
+
 impl<'a> Formatter for PrettyColorFormatter<'a> {
-    fn write_null<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
+    fn write_null<W>(&mut self, writer: &mut W) -> io::Result<()>
     where
-        W: Write,
+        W: ?Sized + Write,
     {
         self.pretty.write_null(writer)
     }
 
-    fn write_bool<W: ?Sized>(&mut self, writer: &mut W, value: bool) -> io::Result<()>
+    fn write_bool<W>(&mut self, writer: &mut W, value: bool) -> io::Result<()>
     where
-        W: Write,
+        W: ?Sized + Write,
     {
-        self.pretty.write_bool(writer, value)
+        let pretty = &mut self.pretty;
+        write_styled(writer, highlight::bool_style(), |w| {
+            pretty.write_bool(w, value)
+        })
     }
 
-    fn write_i8<W: ?Sized>(&mut self, writer: &mut W, value: i8) -> io::Result<()>
+    fn write_i8<W>(&mut self, writer: &mut W, value: i8) -> io::Result<()>
     where
-        W: Write,
+        W: ?Sized + Write,
     {
-        self.pretty.write_i8(writer, value)
+        let pretty = &mut self.pretty;
+        write_styled(writer, highlight::number_style(), |w| {
+            pretty.write_i8(w, value)
+        })
     }
 
-    fn write_i16<W: ?Sized>(&mut self, writer: &mut W, value: i16) -> io::Result<()>
+    fn write_i16<W>(&mut self, writer: &mut W, value: i16) -> io::Result<()>
     where
-        W: Write,
+        W: ?Sized + Write,
     {
-        self.pretty.write_i16(writer, value)
+        let pretty = &mut self.pretty;
+        write_styled(writer, highlight::number_style(), |w| {
+            pretty.write_i16(w, value)
+        })
     }
 
-    fn write_i32<W: ?Sized>(&mut self, writer: &mut W, value: i32) -> io::Result<()>
+    fn write_i32<W>(&mut self, writer: &mut W, value: i32) -> io::Result<()>
     where
-        W: Write,
+        W: ?Sized + Write,
     {
-        self.pretty.write_i32(writer, value)
+        let pretty = &mut self.pretty;
+        write_styled(writer, highlight::number_style(), |w| {
+            pretty.write_i32(w, value)
+        })
     }
 
-    fn write_i64<W: ?Sized>(&mut self, writer: &mut W, value: i64) -> io::Result<()>
+    fn write_i64<W>(&mut self, writer: &mut W, value: i64) -> io::Result<()>
     where
-        W: Write,
+        W: ?Sized + Write,
     {
-        self.pretty.write_i64(writer, value)
+        let pretty = &mut self.pretty;
+        write_styled(writer, highlight::number_style(), |w| {
+            pretty.write_i64(w, value)
+        })
     }
 
-    fn write_u8<W: ?Sized>(&mut self, writer: &mut W, value: u8) -> io::Result<()>
+    fn write_u8<W>(&mut self, writer: &mut W, value: u8) -> io::Result<()>
     where
-        W: Write,
+        W: ?Sized + Write,
     {
-        self.pretty.write_u8(writer, value)
+        let pretty = &mut self.pretty;
+        write_styled(writer, highlight::number_style(), |w| {
+            pretty.write_u8(w, value)
+        })
     }
 
-    fn write_u16<W: ?Sized>(&mut self, writer: &mut W, value: u16) -> io::Result<()>
+    fn write_u16<W>(&mut self, writer: &mut W, value: u16) -> io::Result<()>
     where
-        W: Write,
+        W: ?Sized + Write,
     {
-        self.pretty.write_u16(writer, value)
+        let pretty = &mut self.pretty;
+        write_styled(writer, highlight::number_style(), |w| {
+            pretty.write_u16(w, value)
+        })
     }
 
-    fn write_u32<W: ?Sized>(&mut self, writer: &mut W, value: u32) -> io::Result<()>
+    fn write_u32<W>(&mut self, writer: &mut W, value: u32) -> io::Result<()>
     where
-        W: Write,
+        W: ?Sized + Write,
     {
-        self.pretty.write_u32(writer, value)
+        let pretty = &mut self.pretty;
+        write_styled(writer, highlight::number_style(), |w| {
+            pretty.write_u32(w, value)
+        })
     }
 
-    fn write_u64<W: ?Sized>(&mut self, writer: &mut W, value: u64) -> io::Result<()>
+    fn write_u64<W>(&mut self, writer: &mut W, value: u64) -> io::Result<()>
     where
-        W: Write,
+        W: ?Sized + Write,
     {
-        self.pretty.write_u64(writer, value)
+        let pretty = &mut self.pretty;
+        write_styled(writer, highlight::number_style(), |w| {
+            pretty.write_u64(w, value)
+        })
     }
 
-    fn write_f32<W: ?Sized>(&mut self, writer: &mut W, value: f32) -> io::Result<()>
+    fn write_f32<W>(&mut self, writer: &mut W, value: f32) -> io::Result<()>
     where
-        W: Write,
+        W: ?Sized + Write,
     {
-        self.pretty.write_f32(writer, value)
+        let pretty = &mut self.pretty;
+        write_styled(writer, highlight::number_style(), |w| {
+            pretty.write_f32(w, value)
+        })
     }
 
-    fn write_f64<W: ?Sized>(&mut self, writer: &mut W, value: f64) -> io::Result<()>
+    fn write_f64<W>(&mut self, writer: &mut W, value: f64) -> io::Result<()>
     where
-        W: Write,
+        W: ?Sized + Write,
     {
-        self.pretty.write_f64(writer, value)
+        let pretty = &mut self.pretty;
+        write_styled(writer, highlight::number_style(), |w| {
+            pretty.write_f64(w, value)
+        })
     }
 
-    fn begin_string<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
+    fn begin_string<W>(&mut self, writer: &mut W) -> io::Result<()>
     where
-        W: Write,
+        W: ?Sized + Write,
     {
-        if self.invalue && !self.iskey {
-            write!(writer, "{}", Green.prefix()).unwrap_or(());
+        if !self.iskey {
+            write!(writer, "{}", highlight::string_style().prefix()).unwrap_or(());
         }
         self.pretty.begin_string(writer)
     }
 
-    fn end_string<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
+    fn end_string<W>(&mut self, writer: &mut W) -> io::Result<()>
     where
-        W: Write,
+        W: ?Sized + Write,
     {
         let r = self.pretty.end_string(writer);
-        if self.invalue && !self.iskey {
-            write!(writer, "{}", Green.suffix()).unwrap_or(());
+        if !self.iskey {
+            write!(writer, "{}", highlight::string_style().suffix()).unwrap_or(());
         }
         r
     }
 
-    fn write_string_fragment<W: ?Sized>(&mut self, writer: &mut W, fragment: &str) -> io::Result<()>
+    fn write_string_fragment<W>(&mut self, writer: &mut W, fragment: &str) -> io::Result<()>
     where
-        W: Write,
+        W: ?Sized + Write,
     {
         self.pretty.write_string_fragment(writer, fragment)
     }
 
-    fn write_char_escape<W: ?Sized>(
-        &mut self,
-        writer: &mut W,
-        char_escape: CharEscape,
-    ) -> io::Result<()>
+    fn write_char_escape<W>(&mut self, writer: &mut W, char_escape: CharEscape) -> io::Result<()>
     where
-        W: Write,
+        W: ?Sized + Write,
     {
         self.pretty.write_char_escape(writer, char_escape)
     }
 
-    fn begin_array<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
+    fn begin_array<W>(&mut self, writer: &mut W) -> io::Result<()>
     where
-        W: Write,
+        W: ?Sized + Write,
     {
         self.pretty.begin_array(writer)
     }
 
-    fn end_array<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
+    fn end_array<W>(&mut self, writer: &mut W) -> io::Result<()>
     where
-        W: Write,
+        W: ?Sized + Write,
     {
         self.pretty.end_array(writer)
     }
 
-    fn begin_array_value<W: ?Sized>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    fn begin_array_value<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
     where
-        W: Write,
+        W: ?Sized + Write,
     {
         self.pretty.begin_array_value(writer, first)
     }
 
-    fn end_array_value<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
+    fn end_array_value<W>(&mut self, writer: &mut W) -> io::Result<()>
     where
-        W: Write,
+        W: ?Sized + Write,
     {
         self.pretty.end_array_value(writer)
     }
 
-    fn begin_object<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
+    fn begin_object<W>(&mut self, writer: &mut W) -> io::Result<()>
     where
-        W: Write,
+        W: ?Sized + Write,
     {
         self.pretty.begin_object(writer)
     }
 
-    fn end_object<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
+    fn end_object<W>(&mut self, writer: &mut W) -> io::Result<()>
     where
-        W: Write,
+        W: ?Sized + Write,
     {
         self.pretty.end_object(writer)
     }
 
-    fn begin_object_key<W: ?Sized>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    fn begin_object_key<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
     where
-        W: Write,
+        W: ?Sized + Write,
     {
         self.iskey = true;
         let r = self.pretty.begin_object_key(writer, first);
-        write!(writer, "{}", Blue.bold().prefix()).unwrap_or(());
+        write!(writer, "{}", highlight::key_style().prefix()).unwrap_or(());
         r
     }
 
-    fn end_object_key<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
+    fn end_object_key<W>(&mut self, writer: &mut W) -> io::Result<()>
     where
-        W: Write,
+        W: ?Sized + Write,
     {
         let r = self.pretty.end_object_key(writer);
         self.iskey = false;
-        write!(writer, "{}", Blue.bold().suffix()).unwrap_or(());
+        write!(writer, "{}", highlight::key_style().suffix()).unwrap_or(());
         r
     }
 
-    fn begin_object_value<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
+    fn begin_object_value<W>(&mut self, writer: &mut W) -> io::Result<()>
     where
-        W: Write,
+        W: ?Sized + Write,
     {
-        let r = self.pretty.begin_object_value(writer);
-        self.invalue = true;
-        r
+        self.pretty.begin_object_value(writer)
     }
 
-    fn end_object_value<W: ?Sized>(&mut self, writer: &mut W) -> io::Result<()>
+    fn end_object_value<W>(&mut self, writer: &mut W) -> io::Result<()>
     where
-        W: Write,
+        W: ?Sized + Write,
     {
-        self.invalue = false;
         self.pretty.end_object_value(writer)
     }
 }
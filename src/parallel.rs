@@ -0,0 +1,93 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Running a batch of jobs, like requests to several namespaces or clusters, on a few threads at
+//! once.
+
+use std::sync::Mutex;
+use std::thread;
+
+/// Run f on each of jobs, with at most limit running at once, and return the results in the same
+/// order as the jobs. A limit of 0 is treated as 1. This returns once all the jobs are done.
+pub fn run_bounded<J, R, F>(jobs: Vec<J>, limit: usize, f: F) -> Vec<R>
+where
+    J: Send,
+    R: Send,
+    F: Fn(J) -> R + Sync,
+{
+    let count = jobs.len();
+    let workers = limit.max(1).min(count);
+    if workers <= 1 {
+        return jobs.into_iter().map(f).collect();
+    }
+
+    let queue = Mutex::new(jobs.into_iter().enumerate());
+    let results: Mutex<Vec<Option<R>>> = Mutex::new((0..count).map(|_| None).collect());
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                // take the lock only to grab the next job, so jobs run in parallel
+                let next = queue.lock().unwrap().next();
+                match next {
+                    Some((index, job)) => {
+                        let result = f(job);
+                        results.lock().unwrap()[index] = Some(result);
+                    }
+                    None => break,
+                }
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|r| r.unwrap()) // safe, every job has run when the scope ends
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[test]
+    fn test_run_bounded_order() {
+        let jobs: Vec<u64> = (0..20).collect();
+        // later jobs finish first, but the results still come back in order
+        let results = run_bounded(jobs, 4, |n| {
+            thread::sleep(Duration::from_millis(20 - n));
+            n * 2
+        });
+        assert_eq!(results, (0..20).map(|n| n * 2).collect::<Vec<u64>>());
+        assert!(run_bounded(Vec::<u64>::new(), 4, |n| n).is_empty());
+        assert_eq!(run_bounded(vec![1, 2, 3], 0, |n| n + 1), vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn test_run_bounded_limit() {
+        let running = AtomicUsize::new(0);
+        let most = AtomicUsize::new(0);
+        run_bounded((0..12).collect(), 3, |_: u32| {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            most.fetch_max(now, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(10));
+            running.fetch_sub(1, Ordering::SeqCst);
+        });
+        let most = most.load(Ordering::SeqCst);
+        assert!(most > 1 && most <= 3, "at most {} ran at once", most);
+    }
+}
@@ -45,7 +45,7 @@ pub struct Parser<'a> {
 }
 
 impl<'a> Parser<'a> {
-    pub fn new(cmdline: &str) -> Parser {
+    pub fn new(cmdline: &str) -> Parser<'_> {
         Parser {
             state: ParsingState::Normal,
             cmdline: cmdline.char_indices().peekable(),
@@ -183,6 +183,162 @@ pub fn try_parse_csl(line: &str) -> Option<Box<dyn Iterator<Item = usize>>> {
     Some(Box::new(ret.into_iter()))
 }
 
+/// How a command in a chain of commands on one line is joined to the one before it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChainOp {
+    /// always run this command (it's first, or follows a ';')
+    Always,
+    /// only run this command if the previous one succeeded (it follows a '&&')
+    IfOk,
+}
+
+/// Split line into the commands chained together in it, which are separated by ';' or '&&'. Quoted
+/// or escaped separators don't split commands. The commands are trimmed, and empty ones are
+/// skipped.
+pub fn split_commands(line: &str) -> Vec<(ChainOp, &str)> {
+    use self::ParsingState::*;
+
+    let mut commands = vec![];
+    let mut state = Normal;
+    let mut start = 0;
+    let mut op = ChainOp::Always;
+    let mut chars = line.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        state = match (state, c) {
+            (Normal, ';') => {
+                commands.push((op, &line[start..i]));
+                op = ChainOp::Always;
+                start = i + 1;
+                Normal
+            }
+            (Normal, '&') if chars.peek().map(|&(_, next)| next) == Some('&') => {
+                chars.next();
+                commands.push((op, &line[start..i]));
+                op = ChainOp::IfOk;
+                start = i + 2;
+                Normal
+            }
+            (Normal, '\\') => Escaped,
+            (Normal, '\'') => SingleQuoted,
+            (Normal, '"') => DoubleQuoted,
+            (Normal, _) | (Escaped, _) => Normal,
+            (SingleQuoted, '\'') => Normal,
+            (SingleQuoted, _) => SingleQuoted,
+            (DoubleQuoted, '"') => Normal,
+            (DoubleQuoted, '\\') => DoubleQuotedEscaped,
+            (DoubleQuoted, _) | (DoubleQuotedEscaped, _) => DoubleQuoted,
+        };
+    }
+    commands.push((op, &line[start..]));
+    commands
+        .into_iter()
+        .map(|(op, command)| (op, command.trim()))
+        .filter(|(_, command)| !command.is_empty())
+        .collect()
+}
+
+/// If line should be run in the background, because it starts with 'bg' or ends with a '&' (that
+/// isn't quoted, escaped, or part of a '&&'), the commands to run, without the 'bg' or '&'
+pub fn background_command(line: &str) -> Option<&str> {
+    use self::ParsingState::*;
+
+    let line = line.trim();
+    if let Some(rest) = line.strip_prefix("bg") {
+        if rest.starts_with(char::is_whitespace) {
+            return Some(rest.trim());
+        }
+    }
+    let mut state = Normal;
+    let mut last_normal_amp = None;
+    let mut prev = None;
+    for (i, c) in line.char_indices() {
+        state = match (state, c) {
+            (Normal, '&') => {
+                // a second & makes it a '&&', not a background command
+                last_normal_amp = if prev == Some('&') { None } else { Some(i) };
+                Normal
+            }
+            (Normal, '\\') => Escaped,
+            (Normal, '\'') => SingleQuoted,
+            (Normal, '"') => DoubleQuoted,
+            (Normal, _) | (Escaped, _) => Normal,
+            (SingleQuoted, '\'') => Normal,
+            (SingleQuoted, _) => SingleQuoted,
+            (DoubleQuoted, '"') => Normal,
+            (DoubleQuoted, '\\') => DoubleQuotedEscaped,
+            (DoubleQuoted, _) | (DoubleQuotedEscaped, _) => DoubleQuoted,
+        };
+        prev = Some(c);
+    }
+    match last_normal_amp {
+        Some(i) if i == line.len() - 1 => Some(line[..i].trim()),
+        _ => None,
+    }
+}
+
+/// Parse a list of indices into a list of len items, like "1,3,5-8". Each comma separated part
+/// can be a number, an inclusive range like 5-8, or a range expression like 5.. or 2..=4 (as for
+/// try_parse_range, open ranges go to the end of the list). Indices are returned in the order
+/// given, without duplicates. An error describing the problem is returned if anything doesn't
+/// parse, or is past the end of the list.
+pub fn parse_index_list(expr: &str, len: usize) -> Result<Vec<usize>, String> {
+    let check = |index: usize| {
+        if index < len {
+            Ok(index)
+        } else if len == 0 {
+            Err(format!(
+                "Index {} is out of range, the list is empty",
+                index
+            ))
+        } else {
+            Err(format!(
+                "Index {} is out of range, the list only goes up to {}",
+                index,
+                len - 1
+            ))
+        }
+    };
+    let mut indices = vec![];
+    for part in expr.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let parse = |s: &str| {
+            s.trim()
+                .parse::<usize>()
+                .map_err(|_| format!("Invalid index or range: {}", part))
+        };
+        // the first and last index of the part
+        let (first, last) = if let Some((start, end)) = part.split_once("..") {
+            let first = if start.is_empty() { 0 } else { parse(start)? };
+            let last = match end.strip_prefix('=') {
+                Some(end) => parse(end)?,
+                None if end.is_empty() => len.max(first + 1) - 1,
+                None => parse(end)?
+                    .checked_sub(1)
+                    .ok_or_else(|| format!("Empty range: {}", part))?,
+            };
+            (first, last)
+        } else if let Some((start, end)) = part.split_once('-') {
+            (parse(start)?, parse(end)?)
+        } else {
+            let index = parse(part)?;
+            (index, index)
+        };
+        if first > last {
+            return Err(format!("Empty range: {}", part));
+        }
+        check(first)?;
+        check(last)?;
+        for index in first..=last {
+            if !indices.contains(&index) {
+                indices.push(index);
+            }
+        }
+    }
+    if indices.is_empty() {
+        return Err("No indices given".to_string());
+    }
+    Ok(indices)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -213,6 +369,77 @@ mod tests {
         assert!(try_parse_csl(",1,2,").is_none());
     }
 
+    #[test]
+    fn split_commands_test() {
+        use super::ChainOp::*;
+        assert_eq!(
+            split_commands("pods -l app=foo; logs -f"),
+            vec![(Always, "pods -l app=foo"), (Always, "logs -f")]
+        );
+        assert_eq!(split_commands("contexts"), vec![(Always, "contexts")]);
+        assert_eq!(
+            split_commands(" ; a;;b ;"),
+            vec![(Always, "a"), (Always, "b")]
+        );
+        assert_eq!(
+            split_commands("namespace prod && pods -l app=api&&logs 1 --tail 50; events"),
+            vec![
+                (Always, "namespace prod"),
+                (IfOk, "pods -l app=api"),
+                (IfOk, "logs 1 --tail 50"),
+                (Always, "events")
+            ]
+        );
+        assert_eq!(
+            split_commands("exec sh -c 'a; b'; exec -- sh -c \"a && b\" d\\;e\\&&f & g"),
+            vec![
+                (Always, "exec sh -c 'a; b'"),
+                (Always, "exec -- sh -c \"a && b\" d\\;e\\&&f & g")
+            ]
+        );
+        assert!(split_commands("").is_empty());
+    }
+
+    #[test]
+    fn background_command_test() {
+        assert_eq!(background_command("logs -f &"), Some("logs -f"));
+        assert_eq!(background_command(" logs -f&  "), Some("logs -f"));
+        assert_eq!(background_command("bg logs -f"), Some("logs -f"));
+        assert_eq!(
+            background_command("bg pods && logs -f"),
+            Some("pods && logs -f")
+        );
+        assert_eq!(background_command("pods; logs -f &"), Some("pods; logs -f"));
+        assert_eq!(background_command("logs -f"), None);
+        assert_eq!(background_command("pods &&"), None);
+        assert_eq!(background_command("bgp"), None);
+        assert_eq!(background_command("exec -- echo '&'"), None);
+        assert_eq!(background_command("exec -- echo \\&"), None);
+        assert_eq!(background_command("exec -- sh -c 'a &"), None);
+        assert_eq!(background_command("exec -- a & b"), None);
+    }
+
+    #[test]
+    fn parse_index_list_test() {
+        assert_eq!(parse_index_list("3", 10), Ok(vec![3]));
+        assert_eq!(parse_index_list("1,3,5-8", 10), Ok(vec![1, 3, 5, 6, 7, 8]));
+        assert_eq!(parse_index_list(" 2, 0 ,2", 10), Ok(vec![2, 0]));
+        assert_eq!(parse_index_list("7..", 10), Ok(vec![7, 8, 9]));
+        assert_eq!(parse_index_list("..2,8..=9", 10), Ok(vec![0, 1, 8, 9]));
+        assert_eq!(parse_index_list("0-9", 10).unwrap().len(), 10);
+
+        assert!(parse_index_list("10", 10).unwrap_err().contains("up to 9"));
+        assert!(parse_index_list("5-12", 10).is_err());
+        assert!(parse_index_list("3..12", 10).is_err());
+        assert!(parse_index_list("12..", 10).is_err());
+        assert!(parse_index_list("0", 0).unwrap_err().contains("empty"));
+        assert!(parse_index_list("8-5", 10).is_err());
+        assert!(parse_index_list("3..3", 10).is_err());
+        assert!(parse_index_list("a", 10).is_err());
+        assert!(parse_index_list("1-x", 10).is_err());
+        assert!(parse_index_list(",", 10).is_err());
+    }
+
     #[test]
     fn try_parse_range_test() {
         let v: Vec<usize> = try_parse_range("1..3").unwrap().collect();
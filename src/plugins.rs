@@ -0,0 +1,266 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Plugins: executables named click-NAME in the PATH, which can be run as the command NAME, like
+//! kubectl's plugins. They're told about the active context, namespace, and selection through
+//! environment variables.
+
+use crate::env::{Env, ObjectSelection};
+use crate::error::ClickError;
+use crate::kobj::KObj;
+use crate::output::ClickWriter;
+
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const PLUGIN_PREFIX: &str = "click-";
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|meta| meta.is_file())
+}
+
+/// All the plugins in the directories of path (a PATH style list), by command name. When there's
+/// more than one plugin with the same name, the first one in path is used, like the shell would.
+pub fn find_plugins(path: Option<&OsStr>) -> BTreeMap<String, PathBuf> {
+    let mut plugins = BTreeMap::new();
+    let dirs = match path {
+        Some(path) => std::env::split_paths(path).collect(),
+        None => vec![],
+    };
+    for dir in dirs.iter() {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            let file_name = entry.file_name();
+            let name = match file_name
+                .to_str()
+                .and_then(|f| f.strip_prefix(PLUGIN_PREFIX))
+            {
+                Some(name) if !name.is_empty() => name.to_string(),
+                _ => continue,
+            };
+            let path = entry.path();
+            if !plugins.contains_key(&name) && is_executable(&path) {
+                plugins.insert(name, path);
+            }
+        }
+    }
+    plugins
+}
+
+/// The plugins in the PATH
+pub fn plugins() -> BTreeMap<String, PathBuf> {
+    find_plugins(std::env::var_os("PATH").as_deref())
+}
+
+/// The plugin to run for the command name, if there is one in the PATH
+pub fn find_plugin(name: &str) -> Option<PathBuf> {
+    if name.is_empty() || name.contains(std::path::is_separator) {
+        return None;
+    }
+    plugins().remove(name)
+}
+
+fn obj_str(obj: &KObj) -> String {
+    match obj.namespace.as_deref() {
+        Some(ns) => format!("{}/{}/{}", ns, obj.type_str().to_lowercase(), obj.name()),
+        None => format!("{}/{}", obj.type_str().to_lowercase(), obj.name()),
+    }
+}
+
+/// The environment variables a plugin is run with. Unset things are set to empty strings, so a
+/// plugin run from inside another click doesn't see the outer one's values.
+pub fn plugin_vars(
+    context: Option<&str>,
+    namespace: Option<&str>,
+    selection: &ObjectSelection,
+) -> Vec<(&'static str, String)> {
+    let mut vars = vec![
+        ("CLICK_CONTEXT", context.unwrap_or_default().to_string()),
+        ("CLICK_NAMESPACE", namespace.unwrap_or_default().to_string()),
+    ];
+    let single = match selection {
+        ObjectSelection::Single(obj) => Some(obj),
+        _ => None,
+    };
+    vars.push((
+        "CLICK_SELECTED_KIND",
+        single
+            .map(|obj| obj.type_str().to_string())
+            .unwrap_or_default(),
+    ));
+    vars.push((
+        "CLICK_SELECTED_NAME",
+        single.map(|obj| obj.name().to_string()).unwrap_or_default(),
+    ));
+    vars.push((
+        "CLICK_SELECTED_NAMESPACE",
+        single
+            .and_then(|obj| obj.namespace.clone())
+            .unwrap_or_default(),
+    ));
+    vars.push((
+        "CLICK_SELECTED_POD",
+        single
+            .filter(|obj| obj.is_pod())
+            .map(|obj| obj.name().to_string())
+            .unwrap_or_default(),
+    ));
+    // every selected object, one per line, for plugins that work on ranges
+    let selected: Vec<String> = match selection {
+        ObjectSelection::Single(obj) => vec![obj_str(obj)],
+        ObjectSelection::Range(range) => range.iter().map(obj_str).collect(),
+        ObjectSelection::None => vec![],
+    };
+    vars.push(("CLICK_SELECTED", selected.join("\n")));
+    vars
+}
+
+/// Run the plugin at path with args. Plugins get the terminal to themselves, unless click's
+/// output is redirected or piped, in which case what they print goes there instead.
+pub fn run_plugin(
+    env: &Env,
+    name: &str,
+    path: &Path,
+    args: &[&str],
+    writer: &mut ClickWriter,
+) -> Result<(), ClickError> {
+    let mut command = Command::new(path);
+    command.args(args).envs(plugin_vars(
+        env.context.as_ref().map(|c| c.name.as_str()),
+        env.namespace.as_deref(),
+        env.current_selection(),
+    ));
+    let spawn_error =
+        |e: io::Error| ClickError::CommandError(format!("Could not run plugin {}: {}", name, e));
+    let status = if writer.is_redirected() || !io::stdout().is_terminal() {
+        let output = command.output().map_err(spawn_error)?;
+        writer.write_all(&output.stdout)?;
+        io::stderr().write_all(&output.stderr)?;
+        output.status
+    } else {
+        writer.finish_paging();
+        command.status().map_err(spawn_error)?
+    };
+    if status.success() {
+        Ok(())
+    } else {
+        Err(ClickError::CommandError(format!(
+            "Plugin {} exited with code {}",
+            name,
+            status.code().unwrap_or(-1)
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kobj::ObjType;
+
+    fn pod(name: &str) -> KObj {
+        KObj {
+            name: name.to_string(),
+            namespace: Some("dev".to_string()),
+            uid: None,
+            context: None,
+            typ: ObjType::Pod { containers: vec![] },
+        }
+    }
+
+    fn var<'a>(vars: &'a [(&'static str, String)], name: &str) -> &'a str {
+        vars.iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| v.as_str())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_plugin_vars() {
+        let vars = plugin_vars(
+            Some("prod"),
+            Some("dev"),
+            &ObjectSelection::Single(pod("web-0")),
+        );
+        assert_eq!(var(&vars, "CLICK_CONTEXT"), "prod");
+        assert_eq!(var(&vars, "CLICK_NAMESPACE"), "dev");
+        assert_eq!(var(&vars, "CLICK_SELECTED_KIND"), "Pod");
+        assert_eq!(var(&vars, "CLICK_SELECTED_NAME"), "web-0");
+        assert_eq!(var(&vars, "CLICK_SELECTED_NAMESPACE"), "dev");
+        assert_eq!(var(&vars, "CLICK_SELECTED_POD"), "web-0");
+        assert_eq!(var(&vars, "CLICK_SELECTED"), "dev/pod/web-0");
+
+        let node = KObj {
+            name: "node1".to_string(),
+            namespace: None,
+            uid: None,
+            context: None,
+            typ: ObjType::Node,
+        };
+        let vars = plugin_vars(Some("prod"), None, &ObjectSelection::Single(node));
+        assert_eq!(var(&vars, "CLICK_NAMESPACE"), "");
+        assert_eq!(var(&vars, "CLICK_SELECTED_NAME"), "node1");
+        assert_eq!(var(&vars, "CLICK_SELECTED_POD"), "");
+        assert_eq!(var(&vars, "CLICK_SELECTED"), "node/node1");
+
+        let range = ObjectSelection::Range(vec![pod("web-0"), pod("web-1")]);
+        let vars = plugin_vars(None, Some("dev"), &range);
+        assert_eq!(var(&vars, "CLICK_SELECTED_NAME"), "");
+        assert_eq!(var(&vars, "CLICK_SELECTED"), "dev/pod/web-0\ndev/pod/web-1");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_plugins() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("click-plugins-test-{}", std::process::id()));
+        let first = dir.join("first");
+        let second = dir.join("second");
+        fs::create_dir_all(&first).unwrap();
+        fs::create_dir_all(&second).unwrap();
+        let make = |path: PathBuf, mode: u32| {
+            fs::write(&path, "#!/bin/sh\n").unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+        };
+        make(first.join("click-hello"), 0o755);
+        make(second.join("click-hello"), 0o755);
+        make(second.join("click-world"), 0o755);
+        make(second.join("click-notexec"), 0o644);
+        make(second.join("kubectl-nope"), 0o755);
+        make(second.join("click-"), 0o755);
+
+        let path = std::env::join_paths([&first, &dir.join("missing"), &second]).unwrap();
+        let plugins = find_plugins(Some(&path));
+        fs::remove_dir_all(&dir).unwrap();
+
+        let names: Vec<&str> = plugins.keys().map(|k| k.as_str()).collect();
+        assert_eq!(names, vec!["hello", "world"]);
+        assert_eq!(plugins["hello"], first.join("click-hello"));
+        assert!(find_plugins(None).is_empty());
+    }
+}
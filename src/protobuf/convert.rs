@@ -0,0 +1,2387 @@
+// Generated by util/gen_protobuf.py, don't edit by hand
+
+use super::*;
+
+impl FromPb<pb::api::apps::v1::ControllerRevision> for openapi::api::apps::v1::ControllerRevision {
+    fn from_pb(p: pb::api::apps::v1::ControllerRevision) -> Self {
+        Self {
+            data: opt(p.data),
+            metadata: or_default(p.metadata),
+            revision: p.revision.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::apps::v1::DaemonSet> for openapi::api::apps::v1::DaemonSet {
+    fn from_pb(p: pb::api::apps::v1::DaemonSet) -> Self {
+        Self {
+            metadata: or_default(p.metadata),
+            spec: opt(p.spec),
+            status: opt(p.status),
+        }
+    }
+}
+
+impl FromPb<pb::api::apps::v1::DaemonSetCondition> for openapi::api::apps::v1::DaemonSetCondition {
+    fn from_pb(p: pb::api::apps::v1::DaemonSetCondition) -> Self {
+        Self {
+            last_transition_time: opt(p.last_transition_time),
+            message: p.message,
+            reason: p.reason,
+            status: p.status.unwrap_or_default(),
+            type_: p.r#type.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::apps::v1::DaemonSetSpec> for openapi::api::apps::v1::DaemonSetSpec {
+    fn from_pb(p: pb::api::apps::v1::DaemonSetSpec) -> Self {
+        Self {
+            min_ready_seconds: p.min_ready_seconds,
+            revision_history_limit: p.revision_history_limit,
+            selector: or_default(p.selector),
+            template: or_default(p.template),
+            update_strategy: opt(p.update_strategy),
+        }
+    }
+}
+
+impl FromPb<pb::api::apps::v1::DaemonSetStatus> for openapi::api::apps::v1::DaemonSetStatus {
+    fn from_pb(p: pb::api::apps::v1::DaemonSetStatus) -> Self {
+        Self {
+            collision_count: p.collision_count,
+            conditions: vec(p.conditions),
+            current_number_scheduled: p.current_number_scheduled.unwrap_or_default(),
+            desired_number_scheduled: p.desired_number_scheduled.unwrap_or_default(),
+            number_available: p.number_available,
+            number_misscheduled: p.number_misscheduled.unwrap_or_default(),
+            number_ready: p.number_ready.unwrap_or_default(),
+            number_unavailable: p.number_unavailable,
+            observed_generation: p.observed_generation,
+            updated_number_scheduled: p.updated_number_scheduled,
+        }
+    }
+}
+
+impl FromPb<pb::api::apps::v1::DaemonSetUpdateStrategy>
+    for openapi::api::apps::v1::DaemonSetUpdateStrategy
+{
+    fn from_pb(p: pb::api::apps::v1::DaemonSetUpdateStrategy) -> Self {
+        Self {
+            rolling_update: opt(p.rolling_update),
+            type_: p.r#type,
+        }
+    }
+}
+
+impl FromPb<pb::api::apps::v1::Deployment> for openapi::api::apps::v1::Deployment {
+    fn from_pb(p: pb::api::apps::v1::Deployment) -> Self {
+        Self {
+            metadata: or_default(p.metadata),
+            spec: opt(p.spec),
+            status: opt(p.status),
+        }
+    }
+}
+
+impl FromPb<pb::api::apps::v1::DeploymentCondition>
+    for openapi::api::apps::v1::DeploymentCondition
+{
+    fn from_pb(p: pb::api::apps::v1::DeploymentCondition) -> Self {
+        Self {
+            last_transition_time: opt(p.last_transition_time),
+            last_update_time: opt(p.last_update_time),
+            message: p.message,
+            reason: p.reason,
+            status: p.status.unwrap_or_default(),
+            type_: p.r#type.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::apps::v1::DeploymentSpec> for openapi::api::apps::v1::DeploymentSpec {
+    fn from_pb(p: pb::api::apps::v1::DeploymentSpec) -> Self {
+        Self {
+            min_ready_seconds: p.min_ready_seconds,
+            paused: p.paused,
+            progress_deadline_seconds: p.progress_deadline_seconds,
+            replicas: p.replicas,
+            revision_history_limit: p.revision_history_limit,
+            selector: or_default(p.selector),
+            strategy: opt(p.strategy),
+            template: or_default(p.template),
+        }
+    }
+}
+
+impl FromPb<pb::api::apps::v1::DeploymentStatus> for openapi::api::apps::v1::DeploymentStatus {
+    fn from_pb(p: pb::api::apps::v1::DeploymentStatus) -> Self {
+        Self {
+            available_replicas: p.available_replicas,
+            collision_count: p.collision_count,
+            conditions: vec(p.conditions),
+            observed_generation: p.observed_generation,
+            ready_replicas: p.ready_replicas,
+            replicas: p.replicas,
+            unavailable_replicas: p.unavailable_replicas,
+            updated_replicas: p.updated_replicas,
+        }
+    }
+}
+
+impl FromPb<pb::api::apps::v1::DeploymentStrategy> for openapi::api::apps::v1::DeploymentStrategy {
+    fn from_pb(p: pb::api::apps::v1::DeploymentStrategy) -> Self {
+        Self {
+            rolling_update: opt(p.rolling_update),
+            type_: p.r#type,
+        }
+    }
+}
+
+impl FromPb<pb::api::apps::v1::ReplicaSet> for openapi::api::apps::v1::ReplicaSet {
+    fn from_pb(p: pb::api::apps::v1::ReplicaSet) -> Self {
+        Self {
+            metadata: or_default(p.metadata),
+            spec: opt(p.spec),
+            status: opt(p.status),
+        }
+    }
+}
+
+impl FromPb<pb::api::apps::v1::ReplicaSetCondition>
+    for openapi::api::apps::v1::ReplicaSetCondition
+{
+    fn from_pb(p: pb::api::apps::v1::ReplicaSetCondition) -> Self {
+        Self {
+            last_transition_time: opt(p.last_transition_time),
+            message: p.message,
+            reason: p.reason,
+            status: p.status.unwrap_or_default(),
+            type_: p.r#type.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::apps::v1::ReplicaSetSpec> for openapi::api::apps::v1::ReplicaSetSpec {
+    fn from_pb(p: pb::api::apps::v1::ReplicaSetSpec) -> Self {
+        Self {
+            min_ready_seconds: p.min_ready_seconds,
+            replicas: p.replicas,
+            selector: or_default(p.selector),
+            template: opt(p.template),
+        }
+    }
+}
+
+impl FromPb<pb::api::apps::v1::ReplicaSetStatus> for openapi::api::apps::v1::ReplicaSetStatus {
+    fn from_pb(p: pb::api::apps::v1::ReplicaSetStatus) -> Self {
+        Self {
+            available_replicas: p.available_replicas,
+            conditions: vec(p.conditions),
+            fully_labeled_replicas: p.fully_labeled_replicas,
+            observed_generation: p.observed_generation,
+            ready_replicas: p.ready_replicas,
+            replicas: p.replicas.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::apps::v1::RollingUpdateDaemonSet>
+    for openapi::api::apps::v1::RollingUpdateDaemonSet
+{
+    fn from_pb(p: pb::api::apps::v1::RollingUpdateDaemonSet) -> Self {
+        Self {
+            max_unavailable: opt(p.max_unavailable),
+        }
+    }
+}
+
+impl FromPb<pb::api::apps::v1::RollingUpdateDeployment>
+    for openapi::api::apps::v1::RollingUpdateDeployment
+{
+    fn from_pb(p: pb::api::apps::v1::RollingUpdateDeployment) -> Self {
+        Self {
+            max_surge: opt(p.max_surge),
+            max_unavailable: opt(p.max_unavailable),
+        }
+    }
+}
+
+impl FromPb<pb::api::apps::v1::RollingUpdateStatefulSetStrategy>
+    for openapi::api::apps::v1::RollingUpdateStatefulSetStrategy
+{
+    fn from_pb(p: pb::api::apps::v1::RollingUpdateStatefulSetStrategy) -> Self {
+        Self {
+            partition: p.partition,
+        }
+    }
+}
+
+impl FromPb<pb::api::apps::v1::StatefulSet> for openapi::api::apps::v1::StatefulSet {
+    fn from_pb(p: pb::api::apps::v1::StatefulSet) -> Self {
+        Self {
+            metadata: or_default(p.metadata),
+            spec: opt(p.spec),
+            status: opt(p.status),
+        }
+    }
+}
+
+impl FromPb<pb::api::apps::v1::StatefulSetCondition>
+    for openapi::api::apps::v1::StatefulSetCondition
+{
+    fn from_pb(p: pb::api::apps::v1::StatefulSetCondition) -> Self {
+        Self {
+            last_transition_time: opt(p.last_transition_time),
+            message: p.message,
+            reason: p.reason,
+            status: p.status.unwrap_or_default(),
+            type_: p.r#type.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::apps::v1::StatefulSetSpec> for openapi::api::apps::v1::StatefulSetSpec {
+    fn from_pb(p: pb::api::apps::v1::StatefulSetSpec) -> Self {
+        Self {
+            pod_management_policy: p.pod_management_policy,
+            replicas: p.replicas,
+            revision_history_limit: p.revision_history_limit,
+            selector: or_default(p.selector),
+            service_name: p.service_name.unwrap_or_default(),
+            template: or_default(p.template),
+            update_strategy: opt(p.update_strategy),
+            volume_claim_templates: vec(p.volume_claim_templates),
+        }
+    }
+}
+
+impl FromPb<pb::api::apps::v1::StatefulSetStatus> for openapi::api::apps::v1::StatefulSetStatus {
+    fn from_pb(p: pb::api::apps::v1::StatefulSetStatus) -> Self {
+        Self {
+            collision_count: p.collision_count,
+            conditions: vec(p.conditions),
+            current_replicas: p.current_replicas,
+            current_revision: p.current_revision,
+            observed_generation: p.observed_generation,
+            ready_replicas: p.ready_replicas,
+            replicas: p.replicas.unwrap_or_default(),
+            update_revision: p.update_revision,
+            updated_replicas: p.updated_replicas,
+        }
+    }
+}
+
+impl FromPb<pb::api::apps::v1::StatefulSetUpdateStrategy>
+    for openapi::api::apps::v1::StatefulSetUpdateStrategy
+{
+    fn from_pb(p: pb::api::apps::v1::StatefulSetUpdateStrategy) -> Self {
+        Self {
+            rolling_update: opt(p.rolling_update),
+            type_: p.r#type,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::Affinity> for openapi::api::core::v1::Affinity {
+    fn from_pb(p: pb::api::core::v1::Affinity) -> Self {
+        Self {
+            node_affinity: opt(p.node_affinity),
+            pod_affinity: opt(p.pod_affinity),
+            pod_anti_affinity: opt(p.pod_anti_affinity),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::AttachedVolume> for openapi::api::core::v1::AttachedVolume {
+    fn from_pb(p: pb::api::core::v1::AttachedVolume) -> Self {
+        Self {
+            device_path: p.device_path.unwrap_or_default(),
+            name: p.name.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::AwsElasticBlockStoreVolumeSource>
+    for openapi::api::core::v1::AWSElasticBlockStoreVolumeSource
+{
+    fn from_pb(p: pb::api::core::v1::AwsElasticBlockStoreVolumeSource) -> Self {
+        Self {
+            fs_type: p.fs_type,
+            partition: p.partition,
+            read_only: p.read_only,
+            volume_id: p.volume_id.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::AzureDiskVolumeSource>
+    for openapi::api::core::v1::AzureDiskVolumeSource
+{
+    fn from_pb(p: pb::api::core::v1::AzureDiskVolumeSource) -> Self {
+        Self {
+            caching_mode: p.caching_mode,
+            disk_name: p.disk_name.unwrap_or_default(),
+            disk_uri: p.disk_uri.unwrap_or_default(),
+            fs_type: p.fs_type,
+            kind: p.kind,
+            read_only: p.read_only,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::AzureFilePersistentVolumeSource>
+    for openapi::api::core::v1::AzureFilePersistentVolumeSource
+{
+    fn from_pb(p: pb::api::core::v1::AzureFilePersistentVolumeSource) -> Self {
+        Self {
+            read_only: p.read_only,
+            secret_name: p.secret_name.unwrap_or_default(),
+            secret_namespace: p.secret_namespace,
+            share_name: p.share_name.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::AzureFileVolumeSource>
+    for openapi::api::core::v1::AzureFileVolumeSource
+{
+    fn from_pb(p: pb::api::core::v1::AzureFileVolumeSource) -> Self {
+        Self {
+            read_only: p.read_only,
+            secret_name: p.secret_name.unwrap_or_default(),
+            share_name: p.share_name.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::CSIPersistentVolumeSource>
+    for openapi::api::core::v1::CSIPersistentVolumeSource
+{
+    fn from_pb(p: pb::api::core::v1::CSIPersistentVolumeSource) -> Self {
+        Self {
+            controller_publish_secret_ref: opt(p.controller_publish_secret_ref),
+            driver: p.driver.unwrap_or_default(),
+            fs_type: p.fs_type,
+            node_publish_secret_ref: opt(p.node_publish_secret_ref),
+            node_stage_secret_ref: opt(p.node_stage_secret_ref),
+            read_only: p.read_only,
+            volume_attributes: p.volume_attributes,
+            volume_handle: p.volume_handle.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::CSIVolumeSource> for openapi::api::core::v1::CSIVolumeSource {
+    fn from_pb(p: pb::api::core::v1::CSIVolumeSource) -> Self {
+        Self {
+            driver: p.driver.unwrap_or_default(),
+            fs_type: p.fs_type,
+            node_publish_secret_ref: opt(p.node_publish_secret_ref),
+            read_only: p.read_only,
+            volume_attributes: p.volume_attributes,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::Capabilities> for openapi::api::core::v1::Capabilities {
+    fn from_pb(p: pb::api::core::v1::Capabilities) -> Self {
+        Self {
+            add: p.add,
+            drop: p.drop,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::CephFsPersistentVolumeSource>
+    for openapi::api::core::v1::CephFSPersistentVolumeSource
+{
+    fn from_pb(p: pb::api::core::v1::CephFsPersistentVolumeSource) -> Self {
+        Self {
+            monitors: p.monitors,
+            path: p.path,
+            read_only: p.read_only,
+            secret_file: p.secret_file,
+            secret_ref: opt(p.secret_ref),
+            user: p.user,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::CephFsVolumeSource> for openapi::api::core::v1::CephFSVolumeSource {
+    fn from_pb(p: pb::api::core::v1::CephFsVolumeSource) -> Self {
+        Self {
+            monitors: p.monitors,
+            path: p.path,
+            read_only: p.read_only,
+            secret_file: p.secret_file,
+            secret_ref: opt(p.secret_ref),
+            user: p.user,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::CinderPersistentVolumeSource>
+    for openapi::api::core::v1::CinderPersistentVolumeSource
+{
+    fn from_pb(p: pb::api::core::v1::CinderPersistentVolumeSource) -> Self {
+        Self {
+            fs_type: p.fs_type,
+            read_only: p.read_only,
+            secret_ref: opt(p.secret_ref),
+            volume_id: p.volume_id.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::CinderVolumeSource> for openapi::api::core::v1::CinderVolumeSource {
+    fn from_pb(p: pb::api::core::v1::CinderVolumeSource) -> Self {
+        Self {
+            fs_type: p.fs_type,
+            read_only: p.read_only,
+            secret_ref: opt(p.secret_ref),
+            volume_id: p.volume_id.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ClientIpConfig> for openapi::api::core::v1::ClientIPConfig {
+    fn from_pb(p: pb::api::core::v1::ClientIpConfig) -> Self {
+        Self {
+            timeout_seconds: p.timeout_seconds,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ComponentCondition> for openapi::api::core::v1::ComponentCondition {
+    fn from_pb(p: pb::api::core::v1::ComponentCondition) -> Self {
+        Self {
+            error: p.error,
+            message: p.message,
+            status: p.status.unwrap_or_default(),
+            type_: p.r#type.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ComponentStatus> for openapi::api::core::v1::ComponentStatus {
+    fn from_pb(p: pb::api::core::v1::ComponentStatus) -> Self {
+        Self {
+            conditions: vec(p.conditions),
+            metadata: or_default(p.metadata),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ConfigMap> for openapi::api::core::v1::ConfigMap {
+    fn from_pb(p: pb::api::core::v1::ConfigMap) -> Self {
+        Self {
+            binary_data: map(p.binary_data),
+            data: p.data,
+            metadata: or_default(p.metadata),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ConfigMapEnvSource> for openapi::api::core::v1::ConfigMapEnvSource {
+    fn from_pb(p: pb::api::core::v1::ConfigMapEnvSource) -> Self {
+        let local_object_reference = p.local_object_reference.unwrap_or_default();
+        Self {
+            name: local_object_reference.name,
+            optional: p.optional,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ConfigMapKeySelector>
+    for openapi::api::core::v1::ConfigMapKeySelector
+{
+    fn from_pb(p: pb::api::core::v1::ConfigMapKeySelector) -> Self {
+        let local_object_reference = p.local_object_reference.unwrap_or_default();
+        Self {
+            key: p.key.unwrap_or_default(),
+            name: local_object_reference.name,
+            optional: p.optional,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ConfigMapNodeConfigSource>
+    for openapi::api::core::v1::ConfigMapNodeConfigSource
+{
+    fn from_pb(p: pb::api::core::v1::ConfigMapNodeConfigSource) -> Self {
+        Self {
+            kubelet_config_key: p.kubelet_config_key.unwrap_or_default(),
+            name: p.name.unwrap_or_default(),
+            namespace: p.namespace.unwrap_or_default(),
+            resource_version: p.resource_version,
+            uid: p.uid,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ConfigMapProjection>
+    for openapi::api::core::v1::ConfigMapProjection
+{
+    fn from_pb(p: pb::api::core::v1::ConfigMapProjection) -> Self {
+        let local_object_reference = p.local_object_reference.unwrap_or_default();
+        Self {
+            items: vec(p.items),
+            name: local_object_reference.name,
+            optional: p.optional,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ConfigMapVolumeSource>
+    for openapi::api::core::v1::ConfigMapVolumeSource
+{
+    fn from_pb(p: pb::api::core::v1::ConfigMapVolumeSource) -> Self {
+        let local_object_reference = p.local_object_reference.unwrap_or_default();
+        Self {
+            default_mode: p.default_mode,
+            items: vec(p.items),
+            name: local_object_reference.name,
+            optional: p.optional,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::Container> for openapi::api::core::v1::Container {
+    fn from_pb(p: pb::api::core::v1::Container) -> Self {
+        Self {
+            args: p.args,
+            command: p.command,
+            env: vec(p.env),
+            env_from: vec(p.env_from),
+            image: p.image,
+            image_pull_policy: p.image_pull_policy,
+            lifecycle: opt(p.lifecycle),
+            liveness_probe: opt(p.liveness_probe),
+            name: p.name.unwrap_or_default(),
+            ports: vec(p.ports),
+            readiness_probe: opt(p.readiness_probe),
+            resources: opt(p.resources),
+            security_context: opt(p.security_context),
+            stdin: p.stdin,
+            stdin_once: p.stdin_once,
+            termination_message_path: p.termination_message_path,
+            termination_message_policy: p.termination_message_policy,
+            tty: p.tty,
+            volume_devices: vec(p.volume_devices),
+            volume_mounts: vec(p.volume_mounts),
+            working_dir: p.working_dir,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ContainerImage> for openapi::api::core::v1::ContainerImage {
+    fn from_pb(p: pb::api::core::v1::ContainerImage) -> Self {
+        Self {
+            names: p.names,
+            size_bytes: p.size_bytes,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ContainerPort> for openapi::api::core::v1::ContainerPort {
+    fn from_pb(p: pb::api::core::v1::ContainerPort) -> Self {
+        Self {
+            container_port: p.container_port.unwrap_or_default(),
+            host_ip: p.host_ip,
+            host_port: p.host_port,
+            name: p.name,
+            protocol: p.protocol,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ContainerState> for openapi::api::core::v1::ContainerState {
+    fn from_pb(p: pb::api::core::v1::ContainerState) -> Self {
+        Self {
+            running: opt(p.running),
+            terminated: opt(p.terminated),
+            waiting: opt(p.waiting),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ContainerStateRunning>
+    for openapi::api::core::v1::ContainerStateRunning
+{
+    fn from_pb(p: pb::api::core::v1::ContainerStateRunning) -> Self {
+        Self {
+            started_at: opt(p.started_at),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ContainerStateTerminated>
+    for openapi::api::core::v1::ContainerStateTerminated
+{
+    fn from_pb(p: pb::api::core::v1::ContainerStateTerminated) -> Self {
+        Self {
+            container_id: p.container_id,
+            exit_code: p.exit_code.unwrap_or_default(),
+            finished_at: opt(p.finished_at),
+            message: p.message,
+            reason: p.reason,
+            signal: p.signal,
+            started_at: opt(p.started_at),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ContainerStateWaiting>
+    for openapi::api::core::v1::ContainerStateWaiting
+{
+    fn from_pb(p: pb::api::core::v1::ContainerStateWaiting) -> Self {
+        Self {
+            message: p.message,
+            reason: p.reason,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ContainerStatus> for openapi::api::core::v1::ContainerStatus {
+    fn from_pb(p: pb::api::core::v1::ContainerStatus) -> Self {
+        Self {
+            container_id: p.container_id,
+            image: p.image.unwrap_or_default(),
+            image_id: p.image_id.unwrap_or_default(),
+            last_state: opt(p.last_state),
+            name: p.name.unwrap_or_default(),
+            ready: p.ready.unwrap_or_default(),
+            restart_count: p.restart_count.unwrap_or_default(),
+            state: opt(p.state),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::DaemonEndpoint> for openapi::api::core::v1::DaemonEndpoint {
+    fn from_pb(p: pb::api::core::v1::DaemonEndpoint) -> Self {
+        Self {
+            port: p.port.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::DownwardAPIProjection>
+    for openapi::api::core::v1::DownwardAPIProjection
+{
+    fn from_pb(p: pb::api::core::v1::DownwardAPIProjection) -> Self {
+        Self {
+            items: vec(p.items),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::DownwardAPIVolumeFile>
+    for openapi::api::core::v1::DownwardAPIVolumeFile
+{
+    fn from_pb(p: pb::api::core::v1::DownwardAPIVolumeFile) -> Self {
+        Self {
+            field_ref: opt(p.field_ref),
+            mode: p.mode,
+            path: p.path.unwrap_or_default(),
+            resource_field_ref: opt(p.resource_field_ref),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::DownwardAPIVolumeSource>
+    for openapi::api::core::v1::DownwardAPIVolumeSource
+{
+    fn from_pb(p: pb::api::core::v1::DownwardAPIVolumeSource) -> Self {
+        Self {
+            default_mode: p.default_mode,
+            items: vec(p.items),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::EmptyDirVolumeSource>
+    for openapi::api::core::v1::EmptyDirVolumeSource
+{
+    fn from_pb(p: pb::api::core::v1::EmptyDirVolumeSource) -> Self {
+        Self {
+            medium: p.medium,
+            size_limit: opt(p.size_limit),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::EndpointAddress> for openapi::api::core::v1::EndpointAddress {
+    fn from_pb(p: pb::api::core::v1::EndpointAddress) -> Self {
+        Self {
+            hostname: p.hostname,
+            ip: p.ip.unwrap_or_default(),
+            node_name: p.node_name,
+            target_ref: opt(p.target_ref),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::EndpointPort> for openapi::api::core::v1::EndpointPort {
+    fn from_pb(p: pb::api::core::v1::EndpointPort) -> Self {
+        Self {
+            name: p.name,
+            port: p.port.unwrap_or_default(),
+            protocol: p.protocol,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::EndpointSubset> for openapi::api::core::v1::EndpointSubset {
+    fn from_pb(p: pb::api::core::v1::EndpointSubset) -> Self {
+        Self {
+            addresses: vec(p.addresses),
+            not_ready_addresses: vec(p.not_ready_addresses),
+            ports: vec(p.ports),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::Endpoints> for openapi::api::core::v1::Endpoints {
+    fn from_pb(p: pb::api::core::v1::Endpoints) -> Self {
+        Self {
+            metadata: or_default(p.metadata),
+            subsets: vec(p.subsets),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::EnvFromSource> for openapi::api::core::v1::EnvFromSource {
+    fn from_pb(p: pb::api::core::v1::EnvFromSource) -> Self {
+        Self {
+            config_map_ref: opt(p.config_map_ref),
+            prefix: p.prefix,
+            secret_ref: opt(p.secret_ref),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::EnvVar> for openapi::api::core::v1::EnvVar {
+    fn from_pb(p: pb::api::core::v1::EnvVar) -> Self {
+        Self {
+            name: p.name.unwrap_or_default(),
+            value: p.value,
+            value_from: opt(p.value_from),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::EnvVarSource> for openapi::api::core::v1::EnvVarSource {
+    fn from_pb(p: pb::api::core::v1::EnvVarSource) -> Self {
+        Self {
+            config_map_key_ref: opt(p.config_map_key_ref),
+            field_ref: opt(p.field_ref),
+            resource_field_ref: opt(p.resource_field_ref),
+            secret_key_ref: opt(p.secret_key_ref),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::Event> for openapi::api::core::v1::Event {
+    fn from_pb(p: pb::api::core::v1::Event) -> Self {
+        Self {
+            action: p.action,
+            count: p.count,
+            event_time: opt(p.event_time),
+            first_timestamp: opt(p.first_timestamp),
+            involved_object: or_default(p.involved_object),
+            last_timestamp: opt(p.last_timestamp),
+            message: p.message,
+            metadata: or_default(p.metadata),
+            reason: p.reason,
+            related: opt(p.related),
+            reporting_component: p.reporting_component,
+            reporting_instance: p.reporting_instance,
+            series: opt(p.series),
+            source: opt(p.source),
+            type_: p.r#type,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::EventSeries> for openapi::api::core::v1::EventSeries {
+    fn from_pb(p: pb::api::core::v1::EventSeries) -> Self {
+        Self {
+            count: p.count,
+            last_observed_time: opt(p.last_observed_time),
+            ..Default::default()
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::EventSource> for openapi::api::core::v1::EventSource {
+    fn from_pb(p: pb::api::core::v1::EventSource) -> Self {
+        Self {
+            component: p.component,
+            host: p.host,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ExecAction> for openapi::api::core::v1::ExecAction {
+    fn from_pb(p: pb::api::core::v1::ExecAction) -> Self {
+        Self { command: p.command }
+    }
+}
+
+impl FromPb<pb::api::core::v1::FcVolumeSource> for openapi::api::core::v1::FCVolumeSource {
+    fn from_pb(p: pb::api::core::v1::FcVolumeSource) -> Self {
+        Self {
+            fs_type: p.fs_type,
+            lun: p.lun,
+            read_only: p.read_only,
+            target_wwns: p.target_ww_ns,
+            wwids: p.wwids,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::FlexPersistentVolumeSource>
+    for openapi::api::core::v1::FlexPersistentVolumeSource
+{
+    fn from_pb(p: pb::api::core::v1::FlexPersistentVolumeSource) -> Self {
+        Self {
+            driver: p.driver.unwrap_or_default(),
+            fs_type: p.fs_type,
+            options: p.options,
+            read_only: p.read_only,
+            secret_ref: opt(p.secret_ref),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::FlexVolumeSource> for openapi::api::core::v1::FlexVolumeSource {
+    fn from_pb(p: pb::api::core::v1::FlexVolumeSource) -> Self {
+        Self {
+            driver: p.driver.unwrap_or_default(),
+            fs_type: p.fs_type,
+            options: p.options,
+            read_only: p.read_only,
+            secret_ref: opt(p.secret_ref),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::FlockerVolumeSource>
+    for openapi::api::core::v1::FlockerVolumeSource
+{
+    fn from_pb(p: pb::api::core::v1::FlockerVolumeSource) -> Self {
+        Self {
+            dataset_name: p.dataset_name,
+            dataset_uuid: p.dataset_uuid,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::GcePersistentDiskVolumeSource>
+    for openapi::api::core::v1::GCEPersistentDiskVolumeSource
+{
+    fn from_pb(p: pb::api::core::v1::GcePersistentDiskVolumeSource) -> Self {
+        Self {
+            fs_type: p.fs_type,
+            partition: p.partition,
+            pd_name: p.pd_name.unwrap_or_default(),
+            read_only: p.read_only,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::GitRepoVolumeSource>
+    for openapi::api::core::v1::GitRepoVolumeSource
+{
+    fn from_pb(p: pb::api::core::v1::GitRepoVolumeSource) -> Self {
+        Self {
+            directory: p.directory,
+            repository: p.repository.unwrap_or_default(),
+            revision: p.revision,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::GlusterfsPersistentVolumeSource>
+    for openapi::api::core::v1::GlusterfsPersistentVolumeSource
+{
+    fn from_pb(p: pb::api::core::v1::GlusterfsPersistentVolumeSource) -> Self {
+        Self {
+            endpoints: p.endpoints.unwrap_or_default(),
+            endpoints_namespace: p.endpoints_namespace,
+            path: p.path.unwrap_or_default(),
+            read_only: p.read_only,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::GlusterfsVolumeSource>
+    for openapi::api::core::v1::GlusterfsVolumeSource
+{
+    fn from_pb(p: pb::api::core::v1::GlusterfsVolumeSource) -> Self {
+        Self {
+            endpoints: p.endpoints.unwrap_or_default(),
+            path: p.path.unwrap_or_default(),
+            read_only: p.read_only,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::HostAlias> for openapi::api::core::v1::HostAlias {
+    fn from_pb(p: pb::api::core::v1::HostAlias) -> Self {
+        Self {
+            hostnames: p.hostnames,
+            ip: p.ip,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::HostPathVolumeSource>
+    for openapi::api::core::v1::HostPathVolumeSource
+{
+    fn from_pb(p: pb::api::core::v1::HostPathVolumeSource) -> Self {
+        Self {
+            path: p.path.unwrap_or_default(),
+            type_: p.r#type,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::HttpGetAction> for openapi::api::core::v1::HTTPGetAction {
+    fn from_pb(p: pb::api::core::v1::HttpGetAction) -> Self {
+        Self {
+            host: p.host,
+            http_headers: vec(p.http_headers),
+            path: p.path,
+            port: or_default(p.port),
+            scheme: p.scheme,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::HttpHeader> for openapi::api::core::v1::HTTPHeader {
+    fn from_pb(p: pb::api::core::v1::HttpHeader) -> Self {
+        Self {
+            name: p.name.unwrap_or_default(),
+            value: p.value.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::IscsiPersistentVolumeSource>
+    for openapi::api::core::v1::ISCSIPersistentVolumeSource
+{
+    fn from_pb(p: pb::api::core::v1::IscsiPersistentVolumeSource) -> Self {
+        Self {
+            chap_auth_discovery: p.chap_auth_discovery,
+            chap_auth_session: p.chap_auth_session,
+            fs_type: p.fs_type,
+            initiator_name: p.initiator_name,
+            iqn: p.iqn.unwrap_or_default(),
+            iscsi_interface: p.iscsi_interface,
+            lun: p.lun.unwrap_or_default(),
+            portals: p.portals,
+            read_only: p.read_only,
+            secret_ref: opt(p.secret_ref),
+            target_portal: p.target_portal.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::IscsiVolumeSource> for openapi::api::core::v1::ISCSIVolumeSource {
+    fn from_pb(p: pb::api::core::v1::IscsiVolumeSource) -> Self {
+        Self {
+            chap_auth_discovery: p.chap_auth_discovery,
+            chap_auth_session: p.chap_auth_session,
+            fs_type: p.fs_type,
+            initiator_name: p.initiator_name,
+            iqn: p.iqn.unwrap_or_default(),
+            iscsi_interface: p.iscsi_interface,
+            lun: p.lun.unwrap_or_default(),
+            portals: p.portals,
+            read_only: p.read_only,
+            secret_ref: opt(p.secret_ref),
+            target_portal: p.target_portal.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::KeyToPath> for openapi::api::core::v1::KeyToPath {
+    fn from_pb(p: pb::api::core::v1::KeyToPath) -> Self {
+        Self {
+            key: p.key.unwrap_or_default(),
+            mode: p.mode,
+            path: p.path.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::Lifecycle> for openapi::api::core::v1::Lifecycle {
+    fn from_pb(p: pb::api::core::v1::Lifecycle) -> Self {
+        Self {
+            post_start: opt(p.post_start),
+            pre_stop: opt(p.pre_stop),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::LifecycleHandler> for openapi::api::core::v1::Handler {
+    fn from_pb(p: pb::api::core::v1::LifecycleHandler) -> Self {
+        Self {
+            exec: opt(p.exec),
+            http_get: opt(p.http_get),
+            tcp_socket: opt(p.tcp_socket),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::LimitRange> for openapi::api::core::v1::LimitRange {
+    fn from_pb(p: pb::api::core::v1::LimitRange) -> Self {
+        Self {
+            metadata: or_default(p.metadata),
+            spec: opt(p.spec),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::LimitRangeItem> for openapi::api::core::v1::LimitRangeItem {
+    fn from_pb(p: pb::api::core::v1::LimitRangeItem) -> Self {
+        Self {
+            default: map(p.default),
+            default_request: map(p.default_request),
+            max: map(p.max),
+            max_limit_request_ratio: map(p.max_limit_request_ratio),
+            min: map(p.min),
+            type_: p.r#type,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::LimitRangeSpec> for openapi::api::core::v1::LimitRangeSpec {
+    fn from_pb(p: pb::api::core::v1::LimitRangeSpec) -> Self {
+        Self {
+            limits: vec(p.limits),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::LoadBalancerIngress>
+    for openapi::api::core::v1::LoadBalancerIngress
+{
+    fn from_pb(p: pb::api::core::v1::LoadBalancerIngress) -> Self {
+        Self {
+            hostname: p.hostname,
+            ip: p.ip,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::LoadBalancerStatus> for openapi::api::core::v1::LoadBalancerStatus {
+    fn from_pb(p: pb::api::core::v1::LoadBalancerStatus) -> Self {
+        Self {
+            ingress: vec(p.ingress),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::LocalObjectReference>
+    for openapi::api::core::v1::LocalObjectReference
+{
+    fn from_pb(p: pb::api::core::v1::LocalObjectReference) -> Self {
+        Self { name: p.name }
+    }
+}
+
+impl FromPb<pb::api::core::v1::LocalVolumeSource> for openapi::api::core::v1::LocalVolumeSource {
+    fn from_pb(p: pb::api::core::v1::LocalVolumeSource) -> Self {
+        Self {
+            fs_type: p.fs_type,
+            path: p.path.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::Namespace> for openapi::api::core::v1::Namespace {
+    fn from_pb(p: pb::api::core::v1::Namespace) -> Self {
+        Self {
+            metadata: or_default(p.metadata),
+            spec: opt(p.spec),
+            status: opt(p.status),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::NamespaceSpec> for openapi::api::core::v1::NamespaceSpec {
+    fn from_pb(p: pb::api::core::v1::NamespaceSpec) -> Self {
+        Self {
+            finalizers: p.finalizers,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::NamespaceStatus> for openapi::api::core::v1::NamespaceStatus {
+    fn from_pb(p: pb::api::core::v1::NamespaceStatus) -> Self {
+        Self { phase: p.phase }
+    }
+}
+
+impl FromPb<pb::api::core::v1::NfsVolumeSource> for openapi::api::core::v1::NFSVolumeSource {
+    fn from_pb(p: pb::api::core::v1::NfsVolumeSource) -> Self {
+        Self {
+            path: p.path.unwrap_or_default(),
+            read_only: p.read_only,
+            server: p.server.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::Node> for openapi::api::core::v1::Node {
+    fn from_pb(p: pb::api::core::v1::Node) -> Self {
+        Self {
+            metadata: or_default(p.metadata),
+            spec: opt(p.spec),
+            status: opt(p.status),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::NodeAddress> for openapi::api::core::v1::NodeAddress {
+    fn from_pb(p: pb::api::core::v1::NodeAddress) -> Self {
+        Self {
+            address: p.address.unwrap_or_default(),
+            type_: p.r#type.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::NodeAffinity> for openapi::api::core::v1::NodeAffinity {
+    fn from_pb(p: pb::api::core::v1::NodeAffinity) -> Self {
+        Self {
+            preferred_during_scheduling_ignored_during_execution: vec(
+                p.preferred_during_scheduling_ignored_during_execution
+            ),
+            required_during_scheduling_ignored_during_execution: opt(
+                p.required_during_scheduling_ignored_during_execution
+            ),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::NodeCondition> for openapi::api::core::v1::NodeCondition {
+    fn from_pb(p: pb::api::core::v1::NodeCondition) -> Self {
+        Self {
+            last_heartbeat_time: opt(p.last_heartbeat_time),
+            last_transition_time: opt(p.last_transition_time),
+            message: p.message,
+            reason: p.reason,
+            status: p.status.unwrap_or_default(),
+            type_: p.r#type.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::NodeConfigSource> for openapi::api::core::v1::NodeConfigSource {
+    fn from_pb(p: pb::api::core::v1::NodeConfigSource) -> Self {
+        Self {
+            config_map: opt(p.config_map),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::NodeConfigStatus> for openapi::api::core::v1::NodeConfigStatus {
+    fn from_pb(p: pb::api::core::v1::NodeConfigStatus) -> Self {
+        Self {
+            active: opt(p.active),
+            assigned: opt(p.assigned),
+            error: p.error,
+            last_known_good: opt(p.last_known_good),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::NodeDaemonEndpoints>
+    for openapi::api::core::v1::NodeDaemonEndpoints
+{
+    fn from_pb(p: pb::api::core::v1::NodeDaemonEndpoints) -> Self {
+        Self {
+            kubelet_endpoint: opt(p.kubelet_endpoint),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::NodeSelector> for openapi::api::core::v1::NodeSelector {
+    fn from_pb(p: pb::api::core::v1::NodeSelector) -> Self {
+        Self {
+            node_selector_terms: vec(p.node_selector_terms),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::NodeSelectorRequirement>
+    for openapi::api::core::v1::NodeSelectorRequirement
+{
+    fn from_pb(p: pb::api::core::v1::NodeSelectorRequirement) -> Self {
+        Self {
+            key: p.key.unwrap_or_default(),
+            operator: p.operator.unwrap_or_default(),
+            values: p.values,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::NodeSelectorTerm> for openapi::api::core::v1::NodeSelectorTerm {
+    fn from_pb(p: pb::api::core::v1::NodeSelectorTerm) -> Self {
+        Self {
+            match_expressions: vec(p.match_expressions),
+            match_fields: vec(p.match_fields),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::NodeSpec> for openapi::api::core::v1::NodeSpec {
+    fn from_pb(p: pb::api::core::v1::NodeSpec) -> Self {
+        Self {
+            config_source: opt(p.config_source),
+            external_id: p.external_id,
+            pod_cidr: p.pod_cidr,
+            provider_id: p.provider_id,
+            taints: vec(p.taints),
+            unschedulable: p.unschedulable,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::NodeStatus> for openapi::api::core::v1::NodeStatus {
+    fn from_pb(p: pb::api::core::v1::NodeStatus) -> Self {
+        Self {
+            addresses: vec(p.addresses),
+            allocatable: map(p.allocatable),
+            capacity: map(p.capacity),
+            conditions: vec(p.conditions),
+            config: opt(p.config),
+            daemon_endpoints: opt(p.daemon_endpoints),
+            images: vec(p.images),
+            node_info: opt(p.node_info),
+            phase: p.phase,
+            volumes_attached: vec(p.volumes_attached),
+            volumes_in_use: p.volumes_in_use,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::NodeSystemInfo> for openapi::api::core::v1::NodeSystemInfo {
+    fn from_pb(p: pb::api::core::v1::NodeSystemInfo) -> Self {
+        Self {
+            architecture: p.architecture.unwrap_or_default(),
+            boot_id: p.boot_id.unwrap_or_default(),
+            container_runtime_version: p.container_runtime_version.unwrap_or_default(),
+            kernel_version: p.kernel_version.unwrap_or_default(),
+            kube_proxy_version: p.kube_proxy_version.unwrap_or_default(),
+            kubelet_version: p.kubelet_version.unwrap_or_default(),
+            machine_id: p.machine_id.unwrap_or_default(),
+            operating_system: p.operating_system.unwrap_or_default(),
+            os_image: p.os_image.unwrap_or_default(),
+            system_uuid: p.system_uuid.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ObjectFieldSelector>
+    for openapi::api::core::v1::ObjectFieldSelector
+{
+    fn from_pb(p: pb::api::core::v1::ObjectFieldSelector) -> Self {
+        Self {
+            api_version: p.api_version,
+            field_path: p.field_path.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ObjectReference> for openapi::api::core::v1::ObjectReference {
+    fn from_pb(p: pb::api::core::v1::ObjectReference) -> Self {
+        Self {
+            api_version: p.api_version,
+            field_path: p.field_path,
+            kind: p.kind,
+            name: p.name,
+            namespace: p.namespace,
+            resource_version: p.resource_version,
+            uid: p.uid,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::PersistentVolume> for openapi::api::core::v1::PersistentVolume {
+    fn from_pb(p: pb::api::core::v1::PersistentVolume) -> Self {
+        Self {
+            metadata: or_default(p.metadata),
+            spec: opt(p.spec),
+            status: opt(p.status),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::PersistentVolumeClaim>
+    for openapi::api::core::v1::PersistentVolumeClaim
+{
+    fn from_pb(p: pb::api::core::v1::PersistentVolumeClaim) -> Self {
+        Self {
+            metadata: or_default(p.metadata),
+            spec: opt(p.spec),
+            status: opt(p.status),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::PersistentVolumeClaimCondition>
+    for openapi::api::core::v1::PersistentVolumeClaimCondition
+{
+    fn from_pb(p: pb::api::core::v1::PersistentVolumeClaimCondition) -> Self {
+        Self {
+            last_probe_time: opt(p.last_probe_time),
+            last_transition_time: opt(p.last_transition_time),
+            message: p.message,
+            reason: p.reason,
+            status: p.status.unwrap_or_default(),
+            type_: p.r#type.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::PersistentVolumeClaimSpec>
+    for openapi::api::core::v1::PersistentVolumeClaimSpec
+{
+    fn from_pb(p: pb::api::core::v1::PersistentVolumeClaimSpec) -> Self {
+        Self {
+            access_modes: p.access_modes,
+            data_source: opt(p.data_source),
+            resources: opt(p.resources),
+            selector: opt(p.selector),
+            storage_class_name: p.storage_class_name,
+            volume_mode: p.volume_mode,
+            volume_name: p.volume_name,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::PersistentVolumeClaimStatus>
+    for openapi::api::core::v1::PersistentVolumeClaimStatus
+{
+    fn from_pb(p: pb::api::core::v1::PersistentVolumeClaimStatus) -> Self {
+        Self {
+            access_modes: p.access_modes,
+            capacity: map(p.capacity),
+            conditions: vec(p.conditions),
+            phase: p.phase,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::PersistentVolumeClaimVolumeSource>
+    for openapi::api::core::v1::PersistentVolumeClaimVolumeSource
+{
+    fn from_pb(p: pb::api::core::v1::PersistentVolumeClaimVolumeSource) -> Self {
+        Self {
+            claim_name: p.claim_name.unwrap_or_default(),
+            read_only: p.read_only,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::PersistentVolumeSpec>
+    for openapi::api::core::v1::PersistentVolumeSpec
+{
+    fn from_pb(p: pb::api::core::v1::PersistentVolumeSpec) -> Self {
+        let persistent_volume_source = p.persistent_volume_source.unwrap_or_default();
+        Self {
+            access_modes: p.access_modes,
+            aws_elastic_block_store: opt(persistent_volume_source.aws_elastic_block_store),
+            azure_disk: opt(persistent_volume_source.azure_disk),
+            azure_file: opt(persistent_volume_source.azure_file),
+            capacity: map(p.capacity),
+            cephfs: opt(persistent_volume_source.cephfs),
+            cinder: opt(persistent_volume_source.cinder),
+            claim_ref: opt(p.claim_ref),
+            csi: opt(persistent_volume_source.csi),
+            fc: opt(persistent_volume_source.fc),
+            flex_volume: opt(persistent_volume_source.flex_volume),
+            flocker: opt(persistent_volume_source.flocker),
+            gce_persistent_disk: opt(persistent_volume_source.gce_persistent_disk),
+            glusterfs: opt(persistent_volume_source.glusterfs),
+            host_path: opt(persistent_volume_source.host_path),
+            iscsi: opt(persistent_volume_source.iscsi),
+            local: opt(persistent_volume_source.local),
+            mount_options: p.mount_options,
+            nfs: opt(persistent_volume_source.nfs),
+            node_affinity: opt(p.node_affinity),
+            persistent_volume_reclaim_policy: p.persistent_volume_reclaim_policy,
+            photon_persistent_disk: opt(persistent_volume_source.photon_persistent_disk),
+            portworx_volume: opt(persistent_volume_source.portworx_volume),
+            quobyte: opt(persistent_volume_source.quobyte),
+            rbd: opt(persistent_volume_source.rbd),
+            scale_io: opt(persistent_volume_source.scale_io),
+            storage_class_name: p.storage_class_name,
+            storageos: opt(persistent_volume_source.storageos),
+            volume_mode: p.volume_mode,
+            vsphere_volume: opt(persistent_volume_source.vsphere_volume),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::PersistentVolumeStatus>
+    for openapi::api::core::v1::PersistentVolumeStatus
+{
+    fn from_pb(p: pb::api::core::v1::PersistentVolumeStatus) -> Self {
+        Self {
+            message: p.message,
+            phase: p.phase,
+            reason: p.reason,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::PhotonPersistentDiskVolumeSource>
+    for openapi::api::core::v1::PhotonPersistentDiskVolumeSource
+{
+    fn from_pb(p: pb::api::core::v1::PhotonPersistentDiskVolumeSource) -> Self {
+        Self {
+            fs_type: p.fs_type,
+            pd_id: p.pd_id.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::Pod> for openapi::api::core::v1::Pod {
+    fn from_pb(p: pb::api::core::v1::Pod) -> Self {
+        Self {
+            metadata: or_default(p.metadata),
+            spec: opt(p.spec),
+            status: opt(p.status),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::PodAffinity> for openapi::api::core::v1::PodAffinity {
+    fn from_pb(p: pb::api::core::v1::PodAffinity) -> Self {
+        Self {
+            preferred_during_scheduling_ignored_during_execution: vec(
+                p.preferred_during_scheduling_ignored_during_execution
+            ),
+            required_during_scheduling_ignored_during_execution: vec(
+                p.required_during_scheduling_ignored_during_execution
+            ),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::PodAffinityTerm> for openapi::api::core::v1::PodAffinityTerm {
+    fn from_pb(p: pb::api::core::v1::PodAffinityTerm) -> Self {
+        Self {
+            label_selector: opt(p.label_selector),
+            namespaces: p.namespaces,
+            topology_key: p.topology_key.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::PodAntiAffinity> for openapi::api::core::v1::PodAntiAffinity {
+    fn from_pb(p: pb::api::core::v1::PodAntiAffinity) -> Self {
+        Self {
+            preferred_during_scheduling_ignored_during_execution: vec(
+                p.preferred_during_scheduling_ignored_during_execution
+            ),
+            required_during_scheduling_ignored_during_execution: vec(
+                p.required_during_scheduling_ignored_during_execution
+            ),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::PodCondition> for openapi::api::core::v1::PodCondition {
+    fn from_pb(p: pb::api::core::v1::PodCondition) -> Self {
+        Self {
+            last_probe_time: opt(p.last_probe_time),
+            last_transition_time: opt(p.last_transition_time),
+            message: p.message,
+            reason: p.reason,
+            status: p.status.unwrap_or_default(),
+            type_: p.r#type.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::PodDnsConfig> for openapi::api::core::v1::PodDNSConfig {
+    fn from_pb(p: pb::api::core::v1::PodDnsConfig) -> Self {
+        Self {
+            nameservers: p.nameservers,
+            options: vec(p.options),
+            searches: p.searches,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::PodDnsConfigOption> for openapi::api::core::v1::PodDNSConfigOption {
+    fn from_pb(p: pb::api::core::v1::PodDnsConfigOption) -> Self {
+        Self {
+            name: p.name,
+            value: p.value,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::PodReadinessGate> for openapi::api::core::v1::PodReadinessGate {
+    fn from_pb(p: pb::api::core::v1::PodReadinessGate) -> Self {
+        Self {
+            condition_type: p.condition_type.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::PodSecurityContext> for openapi::api::core::v1::PodSecurityContext {
+    fn from_pb(p: pb::api::core::v1::PodSecurityContext) -> Self {
+        Self {
+            fs_group: p.fs_group,
+            run_as_group: p.run_as_group,
+            run_as_non_root: p.run_as_non_root,
+            run_as_user: p.run_as_user,
+            se_linux_options: opt(p.se_linux_options),
+            supplemental_groups: p.supplemental_groups,
+            sysctls: vec(p.sysctls),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::PodSpec> for openapi::api::core::v1::PodSpec {
+    fn from_pb(p: pb::api::core::v1::PodSpec) -> Self {
+        Self {
+            active_deadline_seconds: p.active_deadline_seconds,
+            affinity: opt(p.affinity),
+            automount_service_account_token: p.automount_service_account_token,
+            containers: vec(p.containers),
+            dns_config: opt(p.dns_config),
+            dns_policy: p.dns_policy,
+            enable_service_links: p.enable_service_links,
+            host_aliases: vec(p.host_aliases),
+            host_ipc: p.host_ipc,
+            host_network: p.host_network,
+            host_pid: p.host_pid,
+            hostname: p.hostname,
+            image_pull_secrets: vec(p.image_pull_secrets),
+            init_containers: vec(p.init_containers),
+            node_name: p.node_name,
+            node_selector: p.node_selector,
+            priority: p.priority,
+            priority_class_name: p.priority_class_name,
+            readiness_gates: vec(p.readiness_gates),
+            restart_policy: p.restart_policy,
+            runtime_class_name: p.runtime_class_name,
+            scheduler_name: p.scheduler_name,
+            security_context: opt(p.security_context),
+            service_account: p.service_account,
+            service_account_name: p.service_account_name,
+            share_process_namespace: p.share_process_namespace,
+            subdomain: p.subdomain,
+            termination_grace_period_seconds: p.termination_grace_period_seconds,
+            tolerations: vec(p.tolerations),
+            volumes: vec(p.volumes),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::PodStatus> for openapi::api::core::v1::PodStatus {
+    fn from_pb(p: pb::api::core::v1::PodStatus) -> Self {
+        Self {
+            conditions: vec(p.conditions),
+            container_statuses: vec(p.container_statuses),
+            host_ip: p.host_ip,
+            init_container_statuses: vec(p.init_container_statuses),
+            message: p.message,
+            nominated_node_name: p.nominated_node_name,
+            phase: p.phase,
+            pod_ip: p.pod_ip,
+            qos_class: p.qos_class,
+            reason: p.reason,
+            start_time: opt(p.start_time),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::PodTemplate> for openapi::api::core::v1::PodTemplate {
+    fn from_pb(p: pb::api::core::v1::PodTemplate) -> Self {
+        Self {
+            metadata: or_default(p.metadata),
+            template: opt(p.template),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::PodTemplateSpec> for openapi::api::core::v1::PodTemplateSpec {
+    fn from_pb(p: pb::api::core::v1::PodTemplateSpec) -> Self {
+        Self {
+            metadata: opt(p.metadata),
+            spec: opt(p.spec),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::PortworxVolumeSource>
+    for openapi::api::core::v1::PortworxVolumeSource
+{
+    fn from_pb(p: pb::api::core::v1::PortworxVolumeSource) -> Self {
+        Self {
+            fs_type: p.fs_type,
+            read_only: p.read_only,
+            volume_id: p.volume_id.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::PreferredSchedulingTerm>
+    for openapi::api::core::v1::PreferredSchedulingTerm
+{
+    fn from_pb(p: pb::api::core::v1::PreferredSchedulingTerm) -> Self {
+        Self {
+            preference: or_default(p.preference),
+            weight: p.weight.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::Probe> for openapi::api::core::v1::Probe {
+    fn from_pb(p: pb::api::core::v1::Probe) -> Self {
+        let handler = p.handler.unwrap_or_default();
+        Self {
+            exec: opt(handler.exec),
+            failure_threshold: p.failure_threshold,
+            http_get: opt(handler.http_get),
+            initial_delay_seconds: p.initial_delay_seconds,
+            period_seconds: p.period_seconds,
+            success_threshold: p.success_threshold,
+            tcp_socket: opt(handler.tcp_socket),
+            timeout_seconds: p.timeout_seconds,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ProjectedVolumeSource>
+    for openapi::api::core::v1::ProjectedVolumeSource
+{
+    fn from_pb(p: pb::api::core::v1::ProjectedVolumeSource) -> Self {
+        Self {
+            default_mode: p.default_mode,
+            sources: vec(p.sources),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::QuobyteVolumeSource>
+    for openapi::api::core::v1::QuobyteVolumeSource
+{
+    fn from_pb(p: pb::api::core::v1::QuobyteVolumeSource) -> Self {
+        Self {
+            group: p.group,
+            read_only: p.read_only,
+            registry: p.registry.unwrap_or_default(),
+            tenant: p.tenant,
+            user: p.user,
+            volume: p.volume.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::RbdPersistentVolumeSource>
+    for openapi::api::core::v1::RBDPersistentVolumeSource
+{
+    fn from_pb(p: pb::api::core::v1::RbdPersistentVolumeSource) -> Self {
+        Self {
+            fs_type: p.fs_type,
+            image: p.image.unwrap_or_default(),
+            keyring: p.keyring,
+            monitors: p.monitors,
+            pool: p.pool,
+            read_only: p.read_only,
+            secret_ref: opt(p.secret_ref),
+            user: p.user,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::RbdVolumeSource> for openapi::api::core::v1::RBDVolumeSource {
+    fn from_pb(p: pb::api::core::v1::RbdVolumeSource) -> Self {
+        Self {
+            fs_type: p.fs_type,
+            image: p.image.unwrap_or_default(),
+            keyring: p.keyring,
+            monitors: p.monitors,
+            pool: p.pool,
+            read_only: p.read_only,
+            secret_ref: opt(p.secret_ref),
+            user: p.user,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ReplicationController>
+    for openapi::api::core::v1::ReplicationController
+{
+    fn from_pb(p: pb::api::core::v1::ReplicationController) -> Self {
+        Self {
+            metadata: or_default(p.metadata),
+            spec: opt(p.spec),
+            status: opt(p.status),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ReplicationControllerCondition>
+    for openapi::api::core::v1::ReplicationControllerCondition
+{
+    fn from_pb(p: pb::api::core::v1::ReplicationControllerCondition) -> Self {
+        Self {
+            last_transition_time: opt(p.last_transition_time),
+            message: p.message,
+            reason: p.reason,
+            status: p.status.unwrap_or_default(),
+            type_: p.r#type.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ReplicationControllerSpec>
+    for openapi::api::core::v1::ReplicationControllerSpec
+{
+    fn from_pb(p: pb::api::core::v1::ReplicationControllerSpec) -> Self {
+        Self {
+            min_ready_seconds: p.min_ready_seconds,
+            replicas: p.replicas,
+            selector: p.selector,
+            template: opt(p.template),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ReplicationControllerStatus>
+    for openapi::api::core::v1::ReplicationControllerStatus
+{
+    fn from_pb(p: pb::api::core::v1::ReplicationControllerStatus) -> Self {
+        Self {
+            available_replicas: p.available_replicas,
+            conditions: vec(p.conditions),
+            fully_labeled_replicas: p.fully_labeled_replicas,
+            observed_generation: p.observed_generation,
+            ready_replicas: p.ready_replicas,
+            replicas: p.replicas.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ResourceFieldSelector>
+    for openapi::api::core::v1::ResourceFieldSelector
+{
+    fn from_pb(p: pb::api::core::v1::ResourceFieldSelector) -> Self {
+        Self {
+            container_name: p.container_name,
+            divisor: opt(p.divisor),
+            resource: p.resource.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ResourceQuota> for openapi::api::core::v1::ResourceQuota {
+    fn from_pb(p: pb::api::core::v1::ResourceQuota) -> Self {
+        Self {
+            metadata: or_default(p.metadata),
+            spec: opt(p.spec),
+            status: opt(p.status),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ResourceQuotaSpec> for openapi::api::core::v1::ResourceQuotaSpec {
+    fn from_pb(p: pb::api::core::v1::ResourceQuotaSpec) -> Self {
+        Self {
+            hard: map(p.hard),
+            scope_selector: opt(p.scope_selector),
+            scopes: p.scopes,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ResourceQuotaStatus>
+    for openapi::api::core::v1::ResourceQuotaStatus
+{
+    fn from_pb(p: pb::api::core::v1::ResourceQuotaStatus) -> Self {
+        Self {
+            hard: map(p.hard),
+            used: map(p.used),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ResourceRequirements>
+    for openapi::api::core::v1::ResourceRequirements
+{
+    fn from_pb(p: pb::api::core::v1::ResourceRequirements) -> Self {
+        Self {
+            limits: map(p.limits),
+            requests: map(p.requests),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ScaleIoPersistentVolumeSource>
+    for openapi::api::core::v1::ScaleIOPersistentVolumeSource
+{
+    fn from_pb(p: pb::api::core::v1::ScaleIoPersistentVolumeSource) -> Self {
+        Self {
+            fs_type: p.fs_type,
+            gateway: p.gateway.unwrap_or_default(),
+            protection_domain: p.protection_domain,
+            read_only: p.read_only,
+            secret_ref: or_default(p.secret_ref),
+            ssl_enabled: p.ssl_enabled,
+            storage_mode: p.storage_mode,
+            storage_pool: p.storage_pool,
+            system: p.system.unwrap_or_default(),
+            volume_name: p.volume_name,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ScaleIoVolumeSource>
+    for openapi::api::core::v1::ScaleIOVolumeSource
+{
+    fn from_pb(p: pb::api::core::v1::ScaleIoVolumeSource) -> Self {
+        Self {
+            fs_type: p.fs_type,
+            gateway: p.gateway.unwrap_or_default(),
+            protection_domain: p.protection_domain,
+            read_only: p.read_only,
+            secret_ref: or_default(p.secret_ref),
+            ssl_enabled: p.ssl_enabled,
+            storage_mode: p.storage_mode,
+            storage_pool: p.storage_pool,
+            system: p.system.unwrap_or_default(),
+            volume_name: p.volume_name,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ScopeSelector> for openapi::api::core::v1::ScopeSelector {
+    fn from_pb(p: pb::api::core::v1::ScopeSelector) -> Self {
+        Self {
+            match_expressions: vec(p.match_expressions),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ScopedResourceSelectorRequirement>
+    for openapi::api::core::v1::ScopedResourceSelectorRequirement
+{
+    fn from_pb(p: pb::api::core::v1::ScopedResourceSelectorRequirement) -> Self {
+        Self {
+            operator: p.operator.unwrap_or_default(),
+            scope_name: p.scope_name.unwrap_or_default(),
+            values: p.values,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::SeLinuxOptions> for openapi::api::core::v1::SELinuxOptions {
+    fn from_pb(p: pb::api::core::v1::SeLinuxOptions) -> Self {
+        Self {
+            level: p.level,
+            role: p.role,
+            type_: p.r#type,
+            user: p.user,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::Secret> for openapi::api::core::v1::Secret {
+    fn from_pb(p: pb::api::core::v1::Secret) -> Self {
+        Self {
+            data: map(p.data),
+            metadata: or_default(p.metadata),
+            string_data: p.string_data,
+            type_: p.r#type,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::SecretEnvSource> for openapi::api::core::v1::SecretEnvSource {
+    fn from_pb(p: pb::api::core::v1::SecretEnvSource) -> Self {
+        let local_object_reference = p.local_object_reference.unwrap_or_default();
+        Self {
+            name: local_object_reference.name,
+            optional: p.optional,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::SecretKeySelector> for openapi::api::core::v1::SecretKeySelector {
+    fn from_pb(p: pb::api::core::v1::SecretKeySelector) -> Self {
+        let local_object_reference = p.local_object_reference.unwrap_or_default();
+        Self {
+            key: p.key.unwrap_or_default(),
+            name: local_object_reference.name,
+            optional: p.optional,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::SecretProjection> for openapi::api::core::v1::SecretProjection {
+    fn from_pb(p: pb::api::core::v1::SecretProjection) -> Self {
+        let local_object_reference = p.local_object_reference.unwrap_or_default();
+        Self {
+            items: vec(p.items),
+            name: local_object_reference.name,
+            optional: p.optional,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::SecretReference> for openapi::api::core::v1::SecretReference {
+    fn from_pb(p: pb::api::core::v1::SecretReference) -> Self {
+        Self {
+            name: p.name,
+            namespace: p.namespace,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::SecretVolumeSource> for openapi::api::core::v1::SecretVolumeSource {
+    fn from_pb(p: pb::api::core::v1::SecretVolumeSource) -> Self {
+        Self {
+            default_mode: p.default_mode,
+            items: vec(p.items),
+            optional: p.optional,
+            secret_name: p.secret_name,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::SecurityContext> for openapi::api::core::v1::SecurityContext {
+    fn from_pb(p: pb::api::core::v1::SecurityContext) -> Self {
+        Self {
+            allow_privilege_escalation: p.allow_privilege_escalation,
+            capabilities: opt(p.capabilities),
+            privileged: p.privileged,
+            proc_mount: p.proc_mount,
+            read_only_root_filesystem: p.read_only_root_filesystem,
+            run_as_group: p.run_as_group,
+            run_as_non_root: p.run_as_non_root,
+            run_as_user: p.run_as_user,
+            se_linux_options: opt(p.se_linux_options),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::Service> for openapi::api::core::v1::Service {
+    fn from_pb(p: pb::api::core::v1::Service) -> Self {
+        Self {
+            metadata: or_default(p.metadata),
+            spec: opt(p.spec),
+            status: opt(p.status),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ServiceAccount> for openapi::api::core::v1::ServiceAccount {
+    fn from_pb(p: pb::api::core::v1::ServiceAccount) -> Self {
+        Self {
+            automount_service_account_token: p.automount_service_account_token,
+            image_pull_secrets: vec(p.image_pull_secrets),
+            metadata: or_default(p.metadata),
+            secrets: vec(p.secrets),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ServiceAccountTokenProjection>
+    for openapi::api::core::v1::ServiceAccountTokenProjection
+{
+    fn from_pb(p: pb::api::core::v1::ServiceAccountTokenProjection) -> Self {
+        Self {
+            audience: p.audience,
+            expiration_seconds: p.expiration_seconds,
+            path: p.path.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ServicePort> for openapi::api::core::v1::ServicePort {
+    fn from_pb(p: pb::api::core::v1::ServicePort) -> Self {
+        Self {
+            name: p.name,
+            node_port: p.node_port,
+            port: p.port.unwrap_or_default(),
+            protocol: p.protocol,
+            target_port: opt(p.target_port),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ServiceSpec> for openapi::api::core::v1::ServiceSpec {
+    fn from_pb(p: pb::api::core::v1::ServiceSpec) -> Self {
+        Self {
+            cluster_ip: p.cluster_ip,
+            external_ips: p.external_ips,
+            external_name: p.external_name,
+            external_traffic_policy: p.external_traffic_policy,
+            health_check_node_port: p.health_check_node_port,
+            load_balancer_ip: p.load_balancer_ip,
+            load_balancer_source_ranges: p.load_balancer_source_ranges,
+            ports: vec(p.ports),
+            publish_not_ready_addresses: p.publish_not_ready_addresses,
+            selector: p.selector,
+            session_affinity: p.session_affinity,
+            session_affinity_config: opt(p.session_affinity_config),
+            type_: p.r#type,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::ServiceStatus> for openapi::api::core::v1::ServiceStatus {
+    fn from_pb(p: pb::api::core::v1::ServiceStatus) -> Self {
+        Self {
+            load_balancer: opt(p.load_balancer),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::SessionAffinityConfig>
+    for openapi::api::core::v1::SessionAffinityConfig
+{
+    fn from_pb(p: pb::api::core::v1::SessionAffinityConfig) -> Self {
+        Self {
+            client_ip: opt(p.client_ip),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::StorageOsPersistentVolumeSource>
+    for openapi::api::core::v1::StorageOSPersistentVolumeSource
+{
+    fn from_pb(p: pb::api::core::v1::StorageOsPersistentVolumeSource) -> Self {
+        Self {
+            fs_type: p.fs_type,
+            read_only: p.read_only,
+            secret_ref: opt(p.secret_ref),
+            volume_name: p.volume_name,
+            volume_namespace: p.volume_namespace,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::StorageOsVolumeSource>
+    for openapi::api::core::v1::StorageOSVolumeSource
+{
+    fn from_pb(p: pb::api::core::v1::StorageOsVolumeSource) -> Self {
+        Self {
+            fs_type: p.fs_type,
+            read_only: p.read_only,
+            secret_ref: opt(p.secret_ref),
+            volume_name: p.volume_name,
+            volume_namespace: p.volume_namespace,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::Sysctl> for openapi::api::core::v1::Sysctl {
+    fn from_pb(p: pb::api::core::v1::Sysctl) -> Self {
+        Self {
+            name: p.name.unwrap_or_default(),
+            value: p.value.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::Taint> for openapi::api::core::v1::Taint {
+    fn from_pb(p: pb::api::core::v1::Taint) -> Self {
+        Self {
+            effect: p.effect.unwrap_or_default(),
+            key: p.key.unwrap_or_default(),
+            time_added: opt(p.time_added),
+            value: p.value,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::TcpSocketAction> for openapi::api::core::v1::TCPSocketAction {
+    fn from_pb(p: pb::api::core::v1::TcpSocketAction) -> Self {
+        Self {
+            host: p.host,
+            port: or_default(p.port),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::Toleration> for openapi::api::core::v1::Toleration {
+    fn from_pb(p: pb::api::core::v1::Toleration) -> Self {
+        Self {
+            effect: p.effect,
+            key: p.key,
+            operator: p.operator,
+            toleration_seconds: p.toleration_seconds,
+            value: p.value,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::TypedLocalObjectReference>
+    for openapi::api::core::v1::TypedLocalObjectReference
+{
+    fn from_pb(p: pb::api::core::v1::TypedLocalObjectReference) -> Self {
+        Self {
+            api_group: p.api_group,
+            kind: p.kind.unwrap_or_default(),
+            name: p.name.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::Volume> for openapi::api::core::v1::Volume {
+    fn from_pb(p: pb::api::core::v1::Volume) -> Self {
+        let volume_source = p.volume_source.unwrap_or_default();
+        Self {
+            aws_elastic_block_store: opt(volume_source.aws_elastic_block_store),
+            azure_disk: opt(volume_source.azure_disk),
+            azure_file: opt(volume_source.azure_file),
+            cephfs: opt(volume_source.cephfs),
+            cinder: opt(volume_source.cinder),
+            config_map: opt(volume_source.config_map),
+            csi: opt(volume_source.csi),
+            downward_api: opt(volume_source.downward_api),
+            empty_dir: opt(volume_source.empty_dir),
+            fc: opt(volume_source.fc),
+            flex_volume: opt(volume_source.flex_volume),
+            flocker: opt(volume_source.flocker),
+            gce_persistent_disk: opt(volume_source.gce_persistent_disk),
+            git_repo: opt(volume_source.git_repo),
+            glusterfs: opt(volume_source.glusterfs),
+            host_path: opt(volume_source.host_path),
+            iscsi: opt(volume_source.iscsi),
+            name: p.name.unwrap_or_default(),
+            nfs: opt(volume_source.nfs),
+            persistent_volume_claim: opt(volume_source.persistent_volume_claim),
+            photon_persistent_disk: opt(volume_source.photon_persistent_disk),
+            portworx_volume: opt(volume_source.portworx_volume),
+            projected: opt(volume_source.projected),
+            quobyte: opt(volume_source.quobyte),
+            rbd: opt(volume_source.rbd),
+            scale_io: opt(volume_source.scale_io),
+            secret: opt(volume_source.secret),
+            storageos: opt(volume_source.storageos),
+            vsphere_volume: opt(volume_source.vsphere_volume),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::VolumeDevice> for openapi::api::core::v1::VolumeDevice {
+    fn from_pb(p: pb::api::core::v1::VolumeDevice) -> Self {
+        Self {
+            device_path: p.device_path.unwrap_or_default(),
+            name: p.name.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::VolumeMount> for openapi::api::core::v1::VolumeMount {
+    fn from_pb(p: pb::api::core::v1::VolumeMount) -> Self {
+        Self {
+            mount_path: p.mount_path.unwrap_or_default(),
+            mount_propagation: p.mount_propagation,
+            name: p.name.unwrap_or_default(),
+            read_only: p.read_only,
+            sub_path: p.sub_path,
+            sub_path_expr: p.sub_path_expr,
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::VolumeNodeAffinity> for openapi::api::core::v1::VolumeNodeAffinity {
+    fn from_pb(p: pb::api::core::v1::VolumeNodeAffinity) -> Self {
+        Self {
+            required: opt(p.required),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::VolumeProjection> for openapi::api::core::v1::VolumeProjection {
+    fn from_pb(p: pb::api::core::v1::VolumeProjection) -> Self {
+        Self {
+            config_map: opt(p.config_map),
+            downward_api: opt(p.downward_api),
+            secret: opt(p.secret),
+            service_account_token: opt(p.service_account_token),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::VolumeResourceRequirements>
+    for openapi::api::core::v1::ResourceRequirements
+{
+    fn from_pb(p: pb::api::core::v1::VolumeResourceRequirements) -> Self {
+        Self {
+            limits: map(p.limits),
+            requests: map(p.requests),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::VsphereVirtualDiskVolumeSource>
+    for openapi::api::core::v1::VsphereVirtualDiskVolumeSource
+{
+    fn from_pb(p: pb::api::core::v1::VsphereVirtualDiskVolumeSource) -> Self {
+        Self {
+            fs_type: p.fs_type,
+            storage_policy_id: p.storage_policy_id,
+            storage_policy_name: p.storage_policy_name,
+            volume_path: p.volume_path.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::api::core::v1::WeightedPodAffinityTerm>
+    for openapi::api::core::v1::WeightedPodAffinityTerm
+{
+    fn from_pb(p: pb::api::core::v1::WeightedPodAffinityTerm) -> Self {
+        Self {
+            pod_affinity_term: or_default(p.pod_affinity_term),
+            weight: p.weight.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::apimachinery::pkg::apis::meta::v1::LabelSelector>
+    for openapi::apimachinery::pkg::apis::meta::v1::LabelSelector
+{
+    fn from_pb(p: pb::apimachinery::pkg::apis::meta::v1::LabelSelector) -> Self {
+        Self {
+            match_expressions: vec(p.match_expressions),
+            match_labels: p.match_labels,
+        }
+    }
+}
+
+impl FromPb<pb::apimachinery::pkg::apis::meta::v1::LabelSelectorRequirement>
+    for openapi::apimachinery::pkg::apis::meta::v1::LabelSelectorRequirement
+{
+    fn from_pb(p: pb::apimachinery::pkg::apis::meta::v1::LabelSelectorRequirement) -> Self {
+        Self {
+            key: p.key.unwrap_or_default(),
+            operator: p.operator.unwrap_or_default(),
+            values: p.values,
+        }
+    }
+}
+
+impl FromPb<pb::apimachinery::pkg::apis::meta::v1::ListMeta>
+    for openapi::apimachinery::pkg::apis::meta::v1::ListMeta
+{
+    fn from_pb(p: pb::apimachinery::pkg::apis::meta::v1::ListMeta) -> Self {
+        Self {
+            continue_: p.r#continue,
+            resource_version: p.resource_version,
+            self_link: p.self_link,
+        }
+    }
+}
+
+impl FromPb<pb::apimachinery::pkg::apis::meta::v1::ManagedFieldsEntry>
+    for openapi::apimachinery::pkg::apis::meta::v1::ManagedFieldsEntry
+{
+    fn from_pb(p: pb::apimachinery::pkg::apis::meta::v1::ManagedFieldsEntry) -> Self {
+        Self {
+            api_version: p.api_version,
+            manager: p.manager,
+            operation: p.operation,
+            time: opt(p.time),
+            ..Default::default()
+        }
+    }
+}
+
+impl FromPb<pb::apimachinery::pkg::apis::meta::v1::ObjectMeta>
+    for openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta
+{
+    fn from_pb(p: pb::apimachinery::pkg::apis::meta::v1::ObjectMeta) -> Self {
+        Self {
+            annotations: p.annotations,
+            creation_timestamp: opt(p.creation_timestamp),
+            deletion_grace_period_seconds: p.deletion_grace_period_seconds,
+            deletion_timestamp: opt(p.deletion_timestamp),
+            finalizers: p.finalizers,
+            generate_name: p.generate_name,
+            generation: p.generation,
+            labels: p.labels,
+            managed_fields: vec(p.managed_fields),
+            name: p.name,
+            namespace: p.namespace,
+            owner_references: vec(p.owner_references),
+            resource_version: p.resource_version,
+            self_link: p.self_link,
+            uid: p.uid,
+            ..Default::default()
+        }
+    }
+}
+
+impl FromPb<pb::apimachinery::pkg::apis::meta::v1::OwnerReference>
+    for openapi::apimachinery::pkg::apis::meta::v1::OwnerReference
+{
+    fn from_pb(p: pb::apimachinery::pkg::apis::meta::v1::OwnerReference) -> Self {
+        Self {
+            api_version: p.api_version.unwrap_or_default(),
+            block_owner_deletion: p.block_owner_deletion,
+            controller: p.controller,
+            kind: p.kind.unwrap_or_default(),
+            name: p.name.unwrap_or_default(),
+            uid: p.uid.unwrap_or_default(),
+        }
+    }
+}
+
+impl FromPb<pb::apimachinery::pkg::apis::meta::v1::Status>
+    for openapi::apimachinery::pkg::apis::meta::v1::Status
+{
+    fn from_pb(p: pb::apimachinery::pkg::apis::meta::v1::Status) -> Self {
+        Self {
+            code: p.code,
+            details: opt(p.details),
+            message: p.message,
+            metadata: or_default(p.metadata),
+            reason: p.reason,
+            status: p.status,
+        }
+    }
+}
+
+impl FromPb<pb::apimachinery::pkg::apis::meta::v1::StatusCause>
+    for openapi::apimachinery::pkg::apis::meta::v1::StatusCause
+{
+    fn from_pb(p: pb::apimachinery::pkg::apis::meta::v1::StatusCause) -> Self {
+        Self {
+            field: p.field,
+            message: p.message,
+            reason: p.reason,
+        }
+    }
+}
+
+impl FromPb<pb::apimachinery::pkg::apis::meta::v1::StatusDetails>
+    for openapi::apimachinery::pkg::apis::meta::v1::StatusDetails
+{
+    fn from_pb(p: pb::apimachinery::pkg::apis::meta::v1::StatusDetails) -> Self {
+        Self {
+            causes: vec(p.causes),
+            group: p.group,
+            kind: p.kind,
+            name: p.name,
+            retry_after_seconds: p.retry_after_seconds,
+            uid: p.uid,
+        }
+    }
+}
@@ -0,0 +1,571 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Decoding lists the api server sends as protobuf (application/vnd.kubernetes.protobuf) rather
+//! than json, which is smaller to send. The k8s-pb types the protobuf decodes to are converted to
+//! the k8s-openapi types used everywhere else, by the impls generated into convert.rs by
+//! util/gen_protobuf.py.
+
+mod convert;
+
+use chrono::{DateTime, TimeZone, Utc};
+use k8s_openapi::{List, ListableResource};
+use prost::Message;
+
+use std::any::{Any, TypeId};
+use std::collections::BTreeMap;
+
+use crate::error::ClickError;
+
+use k8s_openapi as openapi;
+use k8s_pb as pb;
+
+/// The Accept header to send for lists when wire_format is protobuf. The api server only sends
+/// json for resources it can't send as protobuf (like custom resources), so json is asked for too.
+pub const ACCEPT: &str = "application/vnd.kubernetes.protobuf, application/json";
+
+/// Protobuf responses start with this, followed by a runtime.Unknown with the object in it
+const MAGIC: &[u8] = b"k8s\x00";
+
+/// Conversion from a k8s-pb type. This is a trait of our own rather than From, since neither type
+/// is ours.
+pub trait FromPb<P> {
+    fn from_pb(p: P) -> Self;
+}
+
+fn opt<P, T: FromPb<P>>(p: Option<P>) -> Option<T> {
+    p.map(T::from_pb)
+}
+
+/// protobuf has no required fields, so a missing one is the default, like it would be in go
+fn or_default<P, T: FromPb<P> + Default>(p: Option<P>) -> T {
+    p.map(T::from_pb).unwrap_or_default()
+}
+
+fn vec<P, T: FromPb<P>>(p: Vec<P>) -> Vec<T> {
+    p.into_iter().map(T::from_pb).collect()
+}
+
+fn map<P, T: FromPb<P>>(p: BTreeMap<String, P>) -> BTreeMap<String, T> {
+    p.into_iter().map(|(k, v)| (k, T::from_pb(v))).collect()
+}
+
+fn date_time(seconds: Option<i64>, nanos: Option<i32>) -> DateTime<Utc> {
+    Utc.timestamp_opt(
+        seconds.unwrap_or_default(),
+        nanos.unwrap_or_default() as u32,
+    )
+    .single()
+    .unwrap_or_else(|| Utc.timestamp(0, 0))
+}
+
+impl FromPb<pb::apimachinery::pkg::apis::meta::v1::Time>
+    for openapi::apimachinery::pkg::apis::meta::v1::Time
+{
+    fn from_pb(p: pb::apimachinery::pkg::apis::meta::v1::Time) -> Self {
+        Self(date_time(p.seconds, p.nanos))
+    }
+}
+
+impl FromPb<pb::apimachinery::pkg::apis::meta::v1::MicroTime>
+    for openapi::apimachinery::pkg::apis::meta::v1::MicroTime
+{
+    fn from_pb(p: pb::apimachinery::pkg::apis::meta::v1::MicroTime) -> Self {
+        Self(date_time(p.seconds, p.nanos))
+    }
+}
+
+impl FromPb<pb::apimachinery::pkg::api::resource::Quantity>
+    for openapi::apimachinery::pkg::api::resource::Quantity
+{
+    fn from_pb(p: pb::apimachinery::pkg::api::resource::Quantity) -> Self {
+        Self(p.string.unwrap_or_default())
+    }
+}
+
+impl FromPb<pb::apimachinery::pkg::util::intstr::IntOrString>
+    for openapi::apimachinery::pkg::util::intstr::IntOrString
+{
+    fn from_pb(p: pb::apimachinery::pkg::util::intstr::IntOrString) -> Self {
+        // type is 0 for an int, 1 for a string
+        match p.r#type {
+            Some(1) => Self::String(p.str_val.unwrap_or_default()),
+            _ => Self::Int(p.int_val.unwrap_or_default()),
+        }
+    }
+}
+
+impl FromPb<pb::apimachinery::pkg::runtime::RawExtension>
+    for openapi::apimachinery::pkg::runtime::RawExtension
+{
+    fn from_pb(p: pb::apimachinery::pkg::runtime::RawExtension) -> Self {
+        // the raw object is passed through as it was stored, which is json for everything we
+        // convert (i.e. ControllerRevision data)
+        Self(
+            p.raw
+                .and_then(|raw| serde_json::from_slice(&raw).ok())
+                .unwrap_or_default(),
+        )
+    }
+}
+
+impl FromPb<Vec<u8>> for openapi::ByteString {
+    fn from_pb(p: Vec<u8>) -> Self {
+        Self(p)
+    }
+}
+
+/// The object in a protobuf response, or None if body isn't protobuf (i.e. the server sent json)
+fn unwrap_body(body: &[u8]) -> Option<Result<Vec<u8>, ClickError>> {
+    let body = body.strip_prefix(MAGIC)?;
+    Some(
+        pb::apimachinery::pkg::runtime::Unknown::decode(body)
+            .map(|unknown| unknown.raw.unwrap_or_default())
+            .map_err(ClickError::from),
+    )
+}
+
+/// An error the api server sent as protobuf, as json, so it can be handled like any other. None
+/// if body isn't a protobuf Status.
+pub fn status_json(body: &[u8]) -> Option<Vec<u8>> {
+    let raw = unwrap_body(body)?.ok()?;
+    let status = pb::apimachinery::pkg::apis::meta::v1::Status::decode(raw.as_slice()).ok()?;
+    let status = openapi::apimachinery::pkg::apis::meta::v1::Status::from_pb(status);
+    serde_json::to_vec(&status).ok()
+}
+
+macro_rules! list_types {
+    ($($openapi:ty => $pb:ty),* $(,)?) => {
+        /// If lists of T can be decoded from protobuf
+        pub fn decodes<T: ListableResource + 'static>() -> bool {
+            let id = TypeId::of::<T>();
+            $(id == TypeId::of::<$openapi>())||*
+        }
+
+        fn decode_raw_list<T: ListableResource + 'static>(
+            raw: &[u8],
+        ) -> Option<Result<List<T>, ClickError>> {
+            let id = TypeId::of::<T>();
+            $(
+                if id == TypeId::of::<$openapi>() {
+                    return Some(<$pb>::decode(raw).map_err(ClickError::from).map(|list| {
+                        let list: Box<dyn Any> = Box::new(List::<$openapi> {
+                            items: vec(list.items),
+                            metadata: or_default(list.metadata),
+                        });
+                        // safe, checked T is $openapi above
+                        *list.downcast().unwrap()
+                    }));
+                }
+            )*
+            None
+        }
+    };
+}
+
+list_types!(
+    openapi::api::apps::v1::ControllerRevision => pb::api::apps::v1::ControllerRevisionList,
+    openapi::api::apps::v1::DaemonSet => pb::api::apps::v1::DaemonSetList,
+    openapi::api::apps::v1::Deployment => pb::api::apps::v1::DeploymentList,
+    openapi::api::apps::v1::ReplicaSet => pb::api::apps::v1::ReplicaSetList,
+    openapi::api::apps::v1::StatefulSet => pb::api::apps::v1::StatefulSetList,
+    openapi::api::core::v1::ComponentStatus => pb::api::core::v1::ComponentStatusList,
+    openapi::api::core::v1::ConfigMap => pb::api::core::v1::ConfigMapList,
+    openapi::api::core::v1::Endpoints => pb::api::core::v1::EndpointsList,
+    openapi::api::core::v1::Event => pb::api::core::v1::EventList,
+    openapi::api::core::v1::LimitRange => pb::api::core::v1::LimitRangeList,
+    openapi::api::core::v1::Namespace => pb::api::core::v1::NamespaceList,
+    openapi::api::core::v1::Node => pb::api::core::v1::NodeList,
+    openapi::api::core::v1::PersistentVolume => pb::api::core::v1::PersistentVolumeList,
+    openapi::api::core::v1::PersistentVolumeClaim => pb::api::core::v1::PersistentVolumeClaimList,
+    openapi::api::core::v1::Pod => pb::api::core::v1::PodList,
+    openapi::api::core::v1::PodTemplate => pb::api::core::v1::PodTemplateList,
+    openapi::api::core::v1::ReplicationController => pb::api::core::v1::ReplicationControllerList,
+    openapi::api::core::v1::ResourceQuota => pb::api::core::v1::ResourceQuotaList,
+    openapi::api::core::v1::Secret => pb::api::core::v1::SecretList,
+    openapi::api::core::v1::Service => pb::api::core::v1::ServiceList,
+    openapi::api::core::v1::ServiceAccount => pb::api::core::v1::ServiceAccountList,
+);
+
+/// Decode a list response. None if the body isn't protobuf, so it should be decoded as json, or
+/// if T isn't a type we can decode from protobuf.
+pub fn decode_list<T: ListableResource + 'static>(
+    body: &[u8],
+) -> Option<Result<List<T>, ClickError>> {
+    match unwrap_body(body)? {
+        Ok(raw) => decode_raw_list(&raw),
+        Err(e) => Some(Err(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::api::core::v1 as api;
+    use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+    use pb::api::core::v1 as pb_api;
+    use pb::apimachinery::pkg::apis::meta::v1 as pb_meta;
+
+    use std::time::{Duration, Instant};
+
+    fn quantities(
+        pairs: &[(&str, &str)],
+    ) -> BTreeMap<String, pb::apimachinery::pkg::api::resource::Quantity> {
+        pairs
+            .iter()
+            .map(|(k, v)| {
+                (
+                    k.to_string(),
+                    pb::apimachinery::pkg::api::resource::Quantity {
+                        string: Some(v.to_string()),
+                    },
+                )
+            })
+            .collect()
+    }
+
+    fn time(seconds: i64) -> Option<pb_meta::Time> {
+        Some(pb_meta::Time {
+            seconds: Some(seconds),
+            nanos: Some(0),
+        })
+    }
+
+    fn local_ref(name: &str) -> Option<pb_api::LocalObjectReference> {
+        Some(pb_api::LocalObjectReference {
+            name: Some(name.to_string()),
+        })
+    }
+
+    const SPEC_FIELDS: &str = r#"{"f:metadata":{"f:generateName":{},"f:labels":{".":{},"f:app":{},"f:pod-template-hash":{}},"f:ownerReferences":{".":{},"k:{\"uid\":\"0a1b2c3d-0000-4000-8000-000000000000\"}":{}}},"f:spec":{"f:containers":{"k:{\"name\":\"web\"}":{".":{},"f:env":{".":{},"k:{\"name\":\"LOG_LEVEL\"}":{".":{},"f:name":{},"f:valueFrom":{".":{},"f:configMapKeyRef":{}}}},"f:image":{},"f:imagePullPolicy":{},"f:livenessProbe":{".":{},"f:httpGet":{".":{},"f:path":{},"f:port":{},"f:scheme":{}},"f:periodSeconds":{}},"f:name":{},"f:ports":{".":{},"k:{\"containerPort\":8080,\"protocol\":\"TCP\"}":{".":{},"f:containerPort":{},"f:name":{},"f:protocol":{}}},"f:resources":{".":{},"f:limits":{".":{},"f:cpu":{},"f:memory":{}},"f:requests":{".":{},"f:cpu":{},"f:memory":{}}},"f:volumeMounts":{".":{},"k:{\"mountPath\":\"/etc/web\"}":{".":{},"f:mountPath":{},"f:name":{},"f:readOnly":{}}}}},"f:dnsPolicy":{},"f:restartPolicy":{},"f:schedulerName":{},"f:serviceAccount":{},"f:volumes":{".":{},"k:{\"name\":\"config\"}":{".":{},"f:configMap":{".":{},"f:name":{}},"f:name":{}}}}}"#;
+
+    const STATUS_FIELDS: &str = r#"{"f:status":{"f:conditions":{"k:{\"type\":\"Ready\"}":{".":{},"f:lastProbeTime":{},"f:lastTransitionTime":{},"f:status":{},"f:type":{}}},"f:containerStatuses":{},"f:hostIP":{},"f:phase":{},"f:podIP":{},"f:podIPs":{".":{},"k:{\"ip\":\"10.0.0.1\"}":{".":{},"f:ip":{}}},"f:startTime":{}}}"#;
+
+    /// A pod like a deployment would make, with a bit of everything in it
+    fn pod(i: usize) -> pb_api::Pod {
+        pb_api::Pod {
+            metadata: Some(pb_meta::ObjectMeta {
+                name: Some(format!("web-7d4b9c8f6-{:05}", i)),
+                generate_name: Some("web-7d4b9c8f6-".to_string()),
+                namespace: Some("default".to_string()),
+                uid: Some(format!("6f1c2b4e-0000-4000-8000-{:012}", i)),
+                resource_version: Some((100_000 + i).to_string()),
+                creation_timestamp: time(1_600_000_000 + i as i64),
+                labels: [("app", "web"), ("pod-template-hash", "7d4b9c8f6")]
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+                owner_references: vec![pb_meta::OwnerReference {
+                    api_version: Some("apps/v1".to_string()),
+                    kind: Some("ReplicaSet".to_string()),
+                    name: Some("web-7d4b9c8f6".to_string()),
+                    uid: Some("0a1b2c3d-0000-4000-8000-000000000000".to_string()),
+                    controller: Some(true),
+                    block_owner_deletion: Some(true),
+                }],
+                managed_fields: [
+                    ("kube-controller-manager", None, SPEC_FIELDS),
+                    ("kubelet", Some("status"), STATUS_FIELDS),
+                ]
+                .iter()
+                .map(
+                    |(manager, subresource, fields)| pb_meta::ManagedFieldsEntry {
+                        manager: Some(manager.to_string()),
+                        operation: Some("Update".to_string()),
+                        api_version: Some("v1".to_string()),
+                        time: time(1_600_000_000 + i as i64),
+                        fields_type: Some("FieldsV1".to_string()),
+                        fields_v1: Some(pb_meta::FieldsV1 {
+                            raw: Some(fields.as_bytes().to_vec()),
+                        }),
+                        subresource: subresource.map(str::to_string),
+                    },
+                )
+                .collect(),
+                ..Default::default()
+            }),
+            spec: Some(pb_api::PodSpec {
+                containers: vec![pb_api::Container {
+                    name: Some("web".to_string()),
+                    image: Some("nginx:1.21".to_string()),
+                    ports: vec![pb_api::ContainerPort {
+                        name: Some("http".to_string()),
+                        container_port: Some(8080),
+                        protocol: Some("TCP".to_string()),
+                        ..Default::default()
+                    }],
+                    env: vec![pb_api::EnvVar {
+                        name: Some("LOG_LEVEL".to_string()),
+                        value_from: Some(pb_api::EnvVarSource {
+                            config_map_key_ref: Some(pb_api::ConfigMapKeySelector {
+                                local_object_reference: local_ref("web-config"),
+                                key: Some("log-level".to_string()),
+                                optional: None,
+                            }),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    }],
+                    resources: Some(pb_api::ResourceRequirements {
+                        limits: quantities(&[("cpu", "1"), ("memory", "512Mi")]),
+                        requests: quantities(&[("cpu", "250m"), ("memory", "256Mi")]),
+                        ..Default::default()
+                    }),
+                    liveness_probe: Some(pb_api::Probe {
+                        handler: Some(pb_api::ProbeHandler {
+                            http_get: Some(pb_api::HttpGetAction {
+                                path: Some("/healthz".to_string()),
+                                port: Some(pb::apimachinery::pkg::util::intstr::IntOrString {
+                                    r#type: Some(1),
+                                    int_val: None,
+                                    str_val: Some("http".to_string()),
+                                }),
+                                ..Default::default()
+                            }),
+                            ..Default::default()
+                        }),
+                        period_seconds: Some(10),
+                        ..Default::default()
+                    }),
+                    volume_mounts: vec![pb_api::VolumeMount {
+                        name: Some("config".to_string()),
+                        mount_path: Some("/etc/web".to_string()),
+                        read_only: Some(true),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }],
+                volumes: vec![pb_api::Volume {
+                    name: Some("config".to_string()),
+                    volume_source: Some(pb_api::VolumeSource {
+                        config_map: Some(pb_api::ConfigMapVolumeSource {
+                            local_object_reference: local_ref("web-config"),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    }),
+                }],
+                node_name: Some(format!("node-{}", i % 50)),
+                service_account_name: Some("default".to_string()),
+                ..Default::default()
+            }),
+            status: Some(pb_api::PodStatus {
+                phase: Some("Running".to_string()),
+                pod_ip: Some(format!("10.0.{}.{}", i / 250, i % 250)),
+                start_time: time(1_600_000_010 + i as i64),
+                conditions: vec![pb_api::PodCondition {
+                    r#type: Some("Ready".to_string()),
+                    status: Some("True".to_string()),
+                    last_transition_time: time(1_600_000_020 + i as i64),
+                    ..Default::default()
+                }],
+                container_statuses: vec![pb_api::ContainerStatus {
+                    name: Some("web".to_string()),
+                    ready: Some(true),
+                    restart_count: Some((i % 3) as i32),
+                    image: Some("nginx:1.21".to_string()),
+                    state: Some(pb_api::ContainerState {
+                        running: Some(pb_api::ContainerStateRunning {
+                            started_at: time(1_600_000_015 + i as i64),
+                        }),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+        }
+    }
+
+    fn pod_list(count: usize) -> pb_api::PodList {
+        pb_api::PodList {
+            metadata: Some(pb_meta::ListMeta {
+                resource_version: Some("200000".to_string()),
+                r#continue: Some("next-page".to_string()),
+                ..Default::default()
+            }),
+            items: (0..count).map(pod).collect(),
+        }
+    }
+
+    /// A response body, the way the api server sends it
+    fn body<M: Message>(msg: &M) -> Vec<u8> {
+        let mut body = MAGIC.to_vec();
+        pb::apimachinery::pkg::runtime::Unknown {
+            raw: Some(msg.encode_to_vec()),
+            ..Default::default()
+        }
+        .encode(&mut body)
+        .unwrap();
+        body
+    }
+
+    #[test]
+    fn test_decode_list() {
+        let list: List<api::Pod> = decode_list(&body(&pod_list(2))).unwrap().unwrap();
+        assert_eq!(list.metadata.resource_version.as_deref(), Some("200000"));
+        assert_eq!(list.metadata.continue_.as_deref(), Some("next-page"));
+        assert_eq!(list.items.len(), 2);
+
+        let pod = &list.items[1];
+        assert_eq!(pod.metadata.name.as_deref(), Some("web-7d4b9c8f6-00001"));
+        assert_eq!(
+            pod.metadata
+                .creation_timestamp
+                .as_ref()
+                .unwrap()
+                .0
+                .timestamp(),
+            1_600_000_001
+        );
+        assert_eq!(pod.metadata.labels["app"], "web");
+        assert_eq!(pod.metadata.owner_references[0].kind, "ReplicaSet");
+
+        let spec = pod.spec.as_ref().unwrap();
+        let container = &spec.containers[0];
+        assert_eq!(container.ports[0].container_port, 8080);
+        let resources = container.resources.as_ref().unwrap();
+        assert_eq!(resources.requests["cpu"].0, "250m");
+        // fields that moved into a field of their own since 1.14
+        let probe = container.liveness_probe.as_ref().unwrap();
+        assert_eq!(
+            probe.http_get.as_ref().unwrap().port,
+            IntOrString::String("http".to_string())
+        );
+        let key_ref = container.env[0]
+            .value_from
+            .as_ref()
+            .unwrap()
+            .config_map_key_ref
+            .as_ref()
+            .unwrap();
+        assert_eq!(key_ref.name.as_deref(), Some("web-config"));
+        assert_eq!(
+            spec.volumes[0].config_map.as_ref().unwrap().name.as_deref(),
+            Some("web-config")
+        );
+
+        let status = pod.status.as_ref().unwrap();
+        assert_eq!(status.phase.as_deref(), Some("Running"));
+        assert_eq!(status.container_statuses[0].restart_count, 1);
+    }
+
+    #[test]
+    fn test_decode_secret_data() {
+        let secrets = pb_api::SecretList {
+            metadata: None,
+            items: vec![pb_api::Secret {
+                data: [("password".to_string(), b"hunter2".to_vec())]
+                    .iter()
+                    .cloned()
+                    .collect(),
+                r#type: Some("Opaque".to_string()),
+                ..Default::default()
+            }],
+        };
+        let list: List<api::Secret> = decode_list(&body(&secrets)).unwrap().unwrap();
+        assert_eq!(list.items[0].data["password"].0, b"hunter2");
+        assert_eq!(list.items[0].type_.as_deref(), Some("Opaque"));
+    }
+
+    #[test]
+    fn test_decode_fallback() {
+        assert!(decodes::<api::Pod>());
+        assert!(decodes::<k8s_openapi::api::apps::v1::Deployment>());
+        assert!(!decodes::<k8s_openapi::api::batch::v1::Job>());
+
+        // the server sends json for things it can't send as protobuf
+        let json = br#"{"kind":"PodList","apiVersion":"v1","metadata":{},"items":[]}"#;
+        assert!(decode_list::<api::Pod>(json).is_none());
+
+        // and we can't decode lists of types we don't know
+        let jobs = pb::api::batch::v1::JobList::default();
+        assert!(decode_list::<k8s_openapi::api::batch::v1::Job>(&body(&jobs)).is_none());
+
+        let bad = [MAGIC, b"not protobuf"].concat();
+        assert!(decode_list::<api::Pod>(&bad).unwrap().is_err());
+    }
+
+    #[test]
+    fn test_status_json() {
+        let status = pb_meta::Status {
+            status: Some("Failure".to_string()),
+            message: Some("pods is forbidden".to_string()),
+            reason: Some("Forbidden".to_string()),
+            code: Some(403),
+            ..Default::default()
+        };
+        let json = status_json(&body(&status)).unwrap();
+        match ClickError::from_status(403, &json) {
+            Some(ClickError::Api {
+                code,
+                reason,
+                message,
+            }) => {
+                assert_eq!(code, 403);
+                assert_eq!(reason, "Forbidden");
+                assert_eq!(message, "pods is forbidden");
+            }
+            other => panic!("expected an api error, got {:?}", other),
+        }
+        assert!(status_json(br#"{"kind":"Status"}"#).is_none());
+    }
+
+    /// Best time of a few runs of f, so allocator warm up and the like don't count
+    fn best_time<R>(f: impl Fn() -> R) -> (R, Duration) {
+        let mut best = None;
+        for _ in 0..5 {
+            let start = Instant::now();
+            let res = f();
+            let elapsed = start.elapsed();
+            if best.as_ref().is_none_or(|(_, b)| elapsed < *b) {
+                best = Some((res, elapsed));
+            }
+        }
+        best.unwrap()
+    }
+
+    /// Compare how long it takes to decode 5000 pods from protobuf and from json. This is slow in a
+    /// debug build, so run it with:
+    ///   cargo test --release decode_5k_pods -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn decode_5k_pods() {
+        let pb_body = body(&pod_list(5000));
+        // the json the server would send. 1.14 didn't have fieldsV1, so it has to be put back
+        let mut json =
+            serde_json::to_value(decode_list::<api::Pod>(&pb_body).unwrap().unwrap()).unwrap();
+        for item in json["items"].as_array_mut().unwrap() {
+            let entries = item["metadata"]["managedFields"].as_array_mut().unwrap();
+            for (entry, fields) in entries.iter_mut().zip(&[SPEC_FIELDS, STATUS_FIELDS]) {
+                entry["fieldsType"] = "FieldsV1".into();
+                entry["fieldsV1"] = serde_json::from_str(fields).unwrap();
+            }
+        }
+        let json_body = serde_json::to_vec(&json).unwrap();
+
+        let (from_json, json_time) =
+            best_time(|| serde_json::from_slice::<List<api::Pod>>(&json_body).unwrap());
+        let (from_pb, pb_time) = best_time(|| decode_list::<api::Pod>(&pb_body).unwrap().unwrap());
+
+        assert_eq!(from_pb, from_json);
+        println!(
+            "5000 pods: json {} bytes in {:?}, protobuf {} bytes in {:?}",
+            json_body.len(),
+            json_time,
+            pb_body.len(),
+            pb_time
+        );
+    }
+}
@@ -0,0 +1,122 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! When and how long to wait before retrying requests to the api server that failed for reasons
+//! that are likely to go away, like a dropped connection or an overloaded server.
+
+use chrono::{DateTime, Utc};
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Delay before the first retry, doubled for each one after that
+const BASE_DELAY: Duration = Duration::from_millis(250);
+/// Longest we'll back off for between attempts
+const MAX_DELAY: Duration = Duration::from_secs(10);
+/// Longest we'll wait when the server asks us to with Retry-After
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(30);
+
+/// Is a response with this status worth retrying. 429 means we're being rate limited, and 5xx
+/// are usually an overloaded or restarting api server.
+pub fn retryable_status(status: u16) -> bool {
+    status == 429 || (500..600).contains(&status)
+}
+
+/// Is this error from sending a request worth retrying. These are failures to connect, timeouts,
+/// and errors while sending like a connection reset.
+pub fn retryable_error(err: &reqwest::Error) -> bool {
+    err.is_connect() || err.is_timeout() || err.is_request()
+}
+
+/// How long to wait before retrying after attempt (counting from 1) failed. This is exponential
+/// backoff, with jitter so lots of clients don't retry in lockstep. jitter is in [0, 1), and
+/// scales the delay to somewhere between half and all of it.
+pub fn backoff_with_jitter(attempt: u32, jitter: f64) -> Duration {
+    let exp = BASE_DELAY
+        .checked_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .unwrap_or(MAX_DELAY)
+        .min(MAX_DELAY);
+    exp.mul_f64(0.5 + jitter.clamp(0.0, 1.0) / 2.0)
+}
+
+/// backoff_with_jitter, with jitter taken from the clock
+pub fn backoff(attempt: u32) -> Duration {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    backoff_with_jitter(attempt, f64::from(nanos % 1000) / 1000.0)
+}
+
+/// Parse a Retry-After header, which is either a number of seconds or an http date. Returns None
+/// if it can't be parsed. The wait is capped at MAX_RETRY_AFTER, so a confused server can't make
+/// us hang.
+pub fn retry_after(header: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let header = header.trim();
+    let wait = match header.parse::<u64>() {
+        Ok(secs) => Duration::from_secs(secs),
+        Err(_) => {
+            let date = DateTime::parse_from_rfc2822(header).ok()?;
+            (date.with_timezone(&Utc) - now)
+                .to_std()
+                .unwrap_or(Duration::from_secs(0)) // in the past, so go now
+        }
+    };
+    Some(wait.min(MAX_RETRY_AFTER))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_retryable_status() {
+        assert!(retryable_status(429));
+        assert!(retryable_status(500));
+        assert!(retryable_status(503));
+        assert!(!retryable_status(200));
+        assert!(!retryable_status(404));
+        assert!(!retryable_status(409));
+    }
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff_with_jitter(1, 0.0), Duration::from_millis(125));
+        assert_eq!(backoff_with_jitter(1, 1.0), Duration::from_millis(250));
+        assert_eq!(backoff_with_jitter(2, 1.0), Duration::from_millis(500));
+        assert_eq!(backoff_with_jitter(3, 0.5), Duration::from_millis(750));
+        assert_eq!(backoff_with_jitter(10, 1.0), MAX_DELAY);
+        assert_eq!(backoff_with_jitter(100, 1.0), MAX_DELAY);
+        let delay = backoff(2);
+        assert!(delay >= Duration::from_millis(250) && delay <= Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let now = Utc.ymd(2021, 6, 1).and_hms(12, 0, 0);
+        assert_eq!(retry_after("5", now), Some(Duration::from_secs(5)));
+        assert_eq!(retry_after(" 0 ", now), Some(Duration::from_secs(0)));
+        assert_eq!(retry_after("3600", now), Some(MAX_RETRY_AFTER));
+        assert_eq!(
+            retry_after("Tue, 01 Jun 2021 12:00:08 GMT", now),
+            Some(Duration::from_secs(8))
+        );
+        assert_eq!(
+            retry_after("Tue, 01 Jun 2021 11:00:00 GMT", now),
+            Some(Duration::from_secs(0))
+        );
+        assert_eq!(retry_after("soon", now), None);
+    }
+}
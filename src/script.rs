@@ -0,0 +1,182 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Support for Lua scripts run with the script command. The command processor runs them, as they
+//! run other commands, and this has what it needs to turn what scripts pass into command lines,
+//! and click's state into Lua tables. See SCRIPTHELP in command_processor.rs for what scripts see.
+
+use mlua::{Lua, Table, Value, Variadic};
+
+use crate::command::shell_quote;
+use crate::env::{Env, ObjectSelection};
+use crate::kobj::KObj;
+
+/// The name of the function for command in the click table. Lua names can't have a -, so i.e.
+/// network-policies is click.network_policies.
+pub fn lua_name(command: &str) -> String {
+    command.replace('-', "_")
+}
+
+/// The words of a command line for the arguments a script passed to a command's function.
+/// Strings and numbers are words as they are. In a table, string keys are options, put before
+/// the table's list part, which is positional arguments. A one letter key is a short option, true
+/// is a flag, and a list gives the option once for each value: {"app", l="x=y", follow=true}
+/// is `-l x=y --follow app`.
+pub fn command_words(args: Variadic<Value>) -> mlua::Result<Vec<String>> {
+    let mut words = vec![];
+    for arg in args.into_iter() {
+        match arg {
+            Value::Table(table) => table_words(&table, &mut words)?,
+            other => words.push(value_word(&other)?),
+        }
+    }
+    Ok(words)
+}
+
+fn value_word(value: &Value) -> mlua::Result<String> {
+    match value {
+        Value::String(s) => Ok(s.to_str()?.to_string()),
+        Value::Integer(i) => Ok(i.to_string()),
+        Value::Number(n) => Ok(n.to_string()),
+        Value::Boolean(b) => Ok(b.to_string()),
+        other => Err(mlua::Error::RuntimeError(format!(
+            "a {} can't be a command argument",
+            other.type_name()
+        ))),
+    }
+}
+
+fn table_words(table: &Table, words: &mut Vec<String>) -> mlua::Result<()> {
+    let mut options = vec![];
+    for pair in table.pairs::<Value, Value>() {
+        if let (Value::String(key), value) = pair? {
+            options.push((key.to_str()?.to_string(), value));
+        }
+    }
+    // table order is arbitrary, so sort to always build the same line
+    options.sort_by(|a, b| a.0.cmp(&b.0));
+    for (key, value) in options.into_iter() {
+        let option = if key.chars().count() == 1 {
+            format!("-{}", key)
+        } else {
+            format!("--{}", key.replace('_', "-"))
+        };
+        match value {
+            Value::Boolean(true) => words.push(option),
+            Value::Boolean(false) | Value::Nil => {}
+            Value::Table(values) => {
+                for value in values.sequence_values::<Value>() {
+                    words.push(option.clone());
+                    words.push(value_word(&value?)?);
+                }
+            }
+            other => {
+                words.push(option);
+                words.push(value_word(&other)?);
+            }
+        }
+    }
+    for value in table.sequence_values::<Value>() {
+        words.push(value_word(&value?)?);
+    }
+    Ok(())
+}
+
+/// The line that runs command with words, which are quoted so they reach it unchanged
+pub fn script_line(command: &str, words: &[String]) -> String {
+    let mut line = command.to_string();
+    for word in words.iter() {
+        line.push(' ');
+        line.push_str(&shell_quote(word));
+    }
+    line
+}
+
+fn obj_table(lua: &Lua, obj: &KObj) -> mlua::Result<Table> {
+    let table = lua.create_table()?;
+    table.set("name", obj.name())?;
+    table.set("namespace", obj.namespace.as_deref())?;
+    table.set("kind", obj.type_str())?;
+    table.set("uid", obj.uid.as_deref())?;
+    table.set("context", obj.context.as_deref())?;
+    Ok(table)
+}
+
+/// The click.env table: the context and namespace, the selected objects, and the objects last
+/// listed, in the order they were numbered (so last[1] is what '0' selects)
+pub fn env_table(lua: &Lua, env: &Env) -> mlua::Result<Table> {
+    let table = lua.create_table()?;
+    table.set("context", env.context.as_ref().map(|c| c.name.as_str()))?;
+    table.set("namespace", env.namespace.as_deref())?;
+    let selection = lua.create_table()?;
+    match env.current_selection() {
+        ObjectSelection::Single(obj) => selection.push(obj_table(lua, obj)?)?,
+        ObjectSelection::Range(range) => {
+            for obj in range.iter() {
+                selection.push(obj_table(lua, obj)?)?;
+            }
+        }
+        ObjectSelection::None => {}
+    }
+    table.set("selection", selection)?;
+    let last = lua.create_table()?;
+    for i in 0..env.last_objs_len().unwrap_or(0) {
+        if let Some(obj) = env.item_at(i) {
+            last.push(obj_table(lua, obj)?)?;
+        }
+    }
+    table.set("last", last)?;
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(lua: &Lua, code: &str) -> Vec<String> {
+        let args: Variadic<Value> = lua.load(code).eval().unwrap();
+        command_words(args).unwrap()
+    }
+
+    #[test]
+    fn test_command_words() {
+        let lua = Lua::new();
+        assert_eq!(words(&lua, r#"return "app", 10"#), vec!["app", "10"]);
+        assert_eq!(
+            words(
+                &lua,
+                r#"return {"app", l = "x=y", follow = true, quiet = false, since_time = "1h"}"#
+            ),
+            vec!["--follow", "-l", "x=y", "--since-time", "1h", "app"]
+        );
+        assert_eq!(
+            words(&lua, r#"return {namespaces = {"a", "b"}}"#),
+            vec!["--namespaces", "a", "--namespaces", "b"]
+        );
+        let args: Variadic<Value> = lua.load("return function() end").eval().unwrap();
+        assert!(command_words(args).is_err());
+    }
+
+    #[test]
+    fn test_script_line() {
+        assert_eq!(
+            script_line(
+                "patch",
+                &["{\"spec\":{\"replicas\":2}}".to_string(), "x".to_string()]
+            ),
+            "patch '{\"spec\":{\"replicas\":2}}' x"
+        );
+        assert_eq!(script_line("pods", &[]), "pods");
+    }
+}
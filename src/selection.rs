@@ -0,0 +1,186 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The file format for saved selections (see the save and load commands). A saved selection looks
+//! like:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "context": "prod",
+//!   "objects": [
+//!     {"kind": "Pod", "metadata": {"name": "web-0", "namespace": "default", "uid": "..."}}
+//!   ]
+//! }
+//! ```
+//!
+//! Each object is a trimmed down kubernetes object, so the format doesn't depend on how click
+//! represents objects internally. Custom resources also have an apiVersion, and pods list their
+//! containers under spec.containers. Files with a newer version than we know about are refused,
+//! rather than being half understood.
+
+use serde_json::{json, Map, Value};
+
+use crate::error::ClickError;
+use crate::kobj::{KObj, ObjType};
+use crate::values::val_str_opt;
+
+pub const SELECTION_VERSION: u64 = 1;
+
+/// The json for a single object in a saved selection
+fn object_json(obj: &KObj) -> Value {
+    let mut metadata = Map::new();
+    metadata.insert("name".to_string(), json!(obj.name()));
+    if let Some(ns) = obj.namespace.as_ref() {
+        metadata.insert("namespace".to_string(), json!(ns));
+    }
+    if let Some(uid) = obj.uid.as_ref() {
+        metadata.insert("uid".to_string(), json!(uid));
+    }
+    let mut value = json!({
+        "kind": obj.type_str(),
+        "metadata": metadata,
+    });
+    match &obj.typ {
+        ObjType::Pod { containers } => {
+            let containers: Vec<Value> = containers.iter().map(|c| json!({ "name": c })).collect();
+            value["spec"] = json!({ "containers": containers });
+        }
+        ObjType::Crd { group_version, .. } => value["apiVersion"] = json!(group_version),
+        _ => {}
+    }
+    value
+}
+
+/// The saved form of objs, selected in context
+pub fn selection_to_json(objs: &[KObj], context: Option<&str>) -> Value {
+    let objects: Vec<Value> = objs.iter().map(object_json).collect();
+    json!({
+        "version": SELECTION_VERSION,
+        "context": context,
+        "objects": objects,
+    })
+}
+
+/// A selection read back from a file
+#[derive(Debug)]
+pub struct SavedSelection {
+    /// the context the selection was saved in, if one was active
+    pub context: Option<String>,
+    pub objects: Vec<KObj>,
+}
+
+/// Read a saved selection. source is used in error messages, and is usually the file name.
+pub fn selection_from_json(source: &str, value: &Value) -> Result<SavedSelection, ClickError> {
+    let err = |msg: String| ClickError::ParseErr(format!("{}: {}", source, msg));
+    let version = value
+        .get("version")
+        .and_then(|v| v.as_u64())
+        .ok_or_else(|| err("not a saved selection (no version)".to_string()))?;
+    if version > SELECTION_VERSION {
+        return Err(err(format!(
+            "saved by a newer click (format version {}, this click reads up to {})",
+            version, SELECTION_VERSION
+        )));
+    }
+    let objects = value
+        .get("objects")
+        .and_then(|objs| objs.as_array())
+        .ok_or_else(|| err("no objects".to_string()))?
+        .iter()
+        .enumerate()
+        .map(|(i, obj)| KObj::from_json(obj).map_err(|e| err(format!("object {}: {}", i, e))))
+        .collect::<Result<Vec<KObj>, ClickError>>()?;
+    Ok(SavedSelection {
+        context: val_str_opt("/context", value),
+        objects,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn objs() -> Vec<KObj> {
+        vec![
+            KObj {
+                name: "web-0".to_string(),
+                namespace: Some("default".to_string()),
+                uid: Some("1234".to_string()),
+                context: None,
+                typ: ObjType::Pod {
+                    containers: vec!["app".to_string(), "sidecar".to_string()],
+                },
+            },
+            KObj {
+                name: "node1".to_string(),
+                namespace: None,
+                uid: None,
+                context: None,
+                typ: ObjType::Node,
+            },
+            KObj {
+                name: "cert".to_string(),
+                namespace: Some("default".to_string()),
+                uid: None,
+                context: None,
+                typ: ObjType::Crd {
+                    _type: "Certificate".to_string(),
+                    group_version: "cert-manager.io/v1".to_string(),
+                },
+            },
+        ]
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let value = selection_to_json(&objs(), Some("prod"));
+        let saved = selection_from_json("f", &value).unwrap();
+        assert_eq!(saved.context.as_deref(), Some("prod"));
+        assert_eq!(saved.objects, objs());
+    }
+
+    #[test]
+    fn test_format() {
+        // the format is read by other versions of click, so it must not change by accident
+        let value = selection_to_json(&objs()[..2], None);
+        assert_eq!(
+            value,
+            json!({
+                "version": 1,
+                "context": null,
+                "objects": [
+                    {
+                        "kind": "Pod",
+                        "metadata": {"name": "web-0", "namespace": "default", "uid": "1234"},
+                        "spec": {"containers": [{"name": "app"}, {"name": "sidecar"}]}
+                    },
+                    {"kind": "Node", "metadata": {"name": "node1"}}
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_bad_files() {
+        assert!(selection_from_json("f", &json!({"objects": []})).is_err());
+        let newer = json!({"version": 2, "objects": []});
+        assert!(selection_from_json("f", &newer)
+            .unwrap_err()
+            .to_string()
+            .contains("newer click"));
+        let bad_obj = json!({"version": 1, "objects": [{"kind": "Pod", "metadata": {}}]});
+        assert!(selection_from_json("f", &bad_obj).is_err());
+    }
+}
@@ -17,6 +17,7 @@ use crate::command::time_since;
 /// Stuff to handle outputting a table of resources, including
 /// applying filters and sorting
 use crate::output::ClickWriter;
+use crate::theme::{self, Role};
 
 use chrono::{DateTime, Duration, Utc};
 use clap::ArgMatches;
@@ -28,6 +29,7 @@ use regex::Regex;
 
 use std::borrow::Cow;
 use std::cmp::{Ordering, PartialEq, PartialOrd};
+use std::fmt;
 use std::io::Write;
 
 lazy_static! {
@@ -38,6 +40,14 @@ lazy_static! {
         )
         .padding(1, 1)
         .build();
+    /// TBLFMT without the bottom line, for tables that carry on in a following print
+    pub static ref TBLFMT_OPEN: format::TableFormat = format::FormatBuilder::new()
+        .separators(
+            &[format::LinePosition::Title],
+            format::LineSeparator::new('-', '+', '+', '+')
+        )
+        .padding(1, 1)
+        .build();
 }
 
 #[derive(Debug)]
@@ -54,6 +64,8 @@ enum CellSpecTxt<'a> {
 pub struct CellSpec<'a> {
     txt: CellSpecTxt<'a>,
     pub style: Option<&'a str>,
+    /// What the cell means, so it can be styled by the theme. Takes precedence over style.
+    pub role: Option<Role>,
     pub align: Option<format::Alignment>,
 }
 
@@ -62,6 +74,7 @@ impl<'a> CellSpec<'a> {
         CellSpec {
             txt: CellSpecTxt::Index,
             style: None,
+            role: None,
             align: None,
         }
     }
@@ -70,14 +83,25 @@ impl<'a> CellSpec<'a> {
         CellSpec {
             txt: CellSpecTxt::Int(num),
             style: None,
+            role: None,
             align: None,
         }
     }
 
-    pub fn with_style(txt: Cow<'a, str>, style: &'a str) -> CellSpec<'a> {
+    pub fn _with_style(txt: Cow<'a, str>, style: &'a str) -> CellSpec<'a> {
         CellSpec {
             txt: CellSpecTxt::Str(txt),
             style: Some(style),
+            role: None,
+            align: None,
+        }
+    }
+
+    pub fn with_role(txt: Cow<'a, str>, role: Role) -> CellSpec<'a> {
+        CellSpec {
+            txt: CellSpecTxt::Str(txt),
+            style: None,
+            role: Some(role),
             align: None,
         }
     }
@@ -86,33 +110,36 @@ impl<'a> CellSpec<'a> {
         CellSpec {
             txt: CellSpecTxt::Str(txt),
             style: None,
+            role: None,
             align: Some(align),
         }
     }
 
     pub fn to_cell(&self, index: usize) -> Cell {
-        let mut cell = match &self.txt {
-            CellSpecTxt::DateTime(datetime) => Cell::new(&format_duration(time_since(*datetime))),
-            CellSpecTxt::Duration(duration) => Cell::new(&format_duration(*duration)),
-            CellSpecTxt::Index => {
-                let mut c = Cell::new(format!("{}", index).as_str());
-                c.align(format::Alignment::RIGHT);
-                c
-            }
-            CellSpecTxt::Int(num) => {
-                let mut c = Cell::new(format!("{}", num).as_str());
-                c.align(format::Alignment::RIGHT);
-                c
-            }
-            CellSpecTxt::Quantity(quant) => Cell::new(&quant.0),
-            CellSpecTxt::Str(s) => Cell::new(s),
-        };
+        match &self.txt {
+            CellSpecTxt::Index => self.cell_with_text(&format!("{}", index)),
+            CellSpecTxt::Str(s) => self.cell_with_text(s),
+            _ => self.cell_with_text(&self.to_string()),
+        }
+    }
+
+    /// A cell showing text, aligned and styled as this spec says
+    fn cell_with_text(&self, text: &str) -> Cell {
+        let mut cell = Cell::new(text);
+        if let CellSpecTxt::Index | CellSpecTxt::Int(_) = self.txt {
+            cell.align(format::Alignment::RIGHT);
+        }
 
         if let Some(a) = self.align {
             cell.align(a);
         }
 
-        if let Some(style) = self.style {
+        if let Some(role) = self.role {
+            for attr in theme::cell_attrs(role) {
+                cell.style(attr);
+            }
+            cell
+        } else if let Some(style) = self.style {
             cell.style_spec(style)
         } else {
             cell
@@ -129,15 +156,17 @@ impl<'a> CellSpec<'a> {
     }
 }
 
-impl<'a> ToString for CellSpec<'a> {
-    fn to_string(&self) -> String {
+impl<'a> fmt::Display for CellSpec<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match &self.txt {
-            CellSpecTxt::DateTime(datetime) => format_duration(time_since(*datetime)),
-            CellSpecTxt::Duration(duration) => format_duration(*duration),
-            CellSpecTxt::Index => "[index]".to_string(),
-            CellSpecTxt::Int(num) => format!("{}", num),
-            CellSpecTxt::Quantity(quant) => quant.0.clone(),
-            CellSpecTxt::Str(s) => s.to_string(),
+            CellSpecTxt::DateTime(datetime) => {
+                write!(f, "{}", format_duration(time_since(*datetime)))
+            }
+            CellSpecTxt::Duration(duration) => write!(f, "{}", format_duration(*duration)),
+            CellSpecTxt::Index => write!(f, "[index]"),
+            CellSpecTxt::Int(num) => write!(f, "{}", num),
+            CellSpecTxt::Quantity(quant) => write!(f, "{}", quant.0),
+            CellSpecTxt::Str(s) => write!(f, "{}", s),
         }
     }
 }
@@ -147,6 +176,7 @@ impl<'a> From<&'a str> for CellSpec<'a> {
         CellSpec {
             txt: CellSpecTxt::Str(Cow::Borrowed(s)),
             style: None,
+            role: None,
             align: None,
         }
     }
@@ -157,6 +187,7 @@ impl<'a> From<Cow<'a, str>> for CellSpec<'a> {
         CellSpec {
             txt: CellSpecTxt::Str(c),
             style: None,
+            role: None,
             align: None,
         }
     }
@@ -167,6 +198,7 @@ impl<'a> From<String> for CellSpec<'a> {
         CellSpec {
             txt: CellSpecTxt::Str(Cow::Owned(s)),
             style: None,
+            role: None,
             align: None,
         }
     }
@@ -195,6 +227,7 @@ impl<'a> From<Quantity> for CellSpec<'a> {
         CellSpec {
             txt: CellSpecTxt::Quantity(quant),
             style: None,
+            role: None,
             align: None,
         }
     }
@@ -205,6 +238,7 @@ impl<'a> From<Duration> for CellSpec<'a> {
         CellSpec {
             txt: CellSpecTxt::Duration(duration),
             style: None,
+            role: None,
             align: None,
         }
     }
@@ -215,6 +249,7 @@ impl<'a> From<DateTime<Utc>> for CellSpec<'a> {
         CellSpec {
             txt: CellSpecTxt::DateTime(dt),
             style: None,
+            role: None,
             align: None,
         }
     }
@@ -248,25 +283,22 @@ impl<'a> Eq for CellSpec<'a> {}
 
 impl<'a> PartialOrd for CellSpec<'a> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        match (&self.txt, &other.txt) {
-            (CellSpecTxt::DateTime(dt1), CellSpecTxt::DateTime(dt2)) => dt1.partial_cmp(dt2),
-            (CellSpecTxt::Index, CellSpecTxt::Index) => Some(Ordering::Equal),
-            (CellSpecTxt::Str(st), CellSpecTxt::Str(ot)) => st.partial_cmp(ot),
-            (CellSpecTxt::Int(num1), CellSpecTxt::Int(num2)) => num1.partial_cmp(num2),
-            (CellSpecTxt::Duration(dur1), CellSpecTxt::Duration(dur2)) => dur1.partial_cmp(dur2),
-            (CellSpecTxt::Quantity(quant1), CellSpecTxt::Quantity(quant2)) => {
-                raw_quantity(quant1).partial_cmp(&raw_quantity(quant2))
-            }
-            _ => None,
-        }
+        Some(self.cmp(other))
     }
 }
 
 impl<'a> Ord for CellSpec<'a> {
     fn cmp(&self, other: &Self) -> Ordering {
-        match self.partial_cmp(other) {
-            Some(o) => o,
-            None => Ordering::Equal,
+        match (&self.txt, &other.txt) {
+            (CellSpecTxt::DateTime(dt1), CellSpecTxt::DateTime(dt2)) => dt1.cmp(dt2),
+            (CellSpecTxt::Index, CellSpecTxt::Index) => Ordering::Equal,
+            (CellSpecTxt::Str(st), CellSpecTxt::Str(ot)) => st.cmp(ot),
+            (CellSpecTxt::Int(num1), CellSpecTxt::Int(num2)) => num1.cmp(num2),
+            (CellSpecTxt::Duration(dur1), CellSpecTxt::Duration(dur2)) => dur1.cmp(dur2),
+            (CellSpecTxt::Quantity(quant1), CellSpecTxt::Quantity(quant2)) => raw_quantity(quant1)
+                .partial_cmp(&raw_quantity(quant2))
+                .unwrap_or(Ordering::Equal),
+            _ => Ordering::Equal,
         }
     }
 }
@@ -287,7 +319,7 @@ pub fn raw_quantity(quantity: &Quantity) -> f64 {
     }
 
     // find location of first non-digit
-    let mut split = match chars.position(|c| !c.is_digit(10)) {
+    let mut split = match chars.position(|c| !c.is_ascii_digit()) {
         Some(pos) => pos,
         None => {
             // no non digit, just parse as a raw number, set split to end of string
@@ -316,7 +348,7 @@ pub fn raw_quantity(quantity: &Quantity) -> f64 {
 
     if suffix.len() > 1 && (suffix.starts_with('e') || suffix.starts_with('E')) {
         // our suffix has more than one char and starts with e/E, so it should be a decimal exponent
-        match (&suffix[1..]).parse::<u32>() {
+        match suffix[1..].parse::<u32>() {
             Ok(exp) => {
                 let famt = (amt * base10.pow(exp)) as f64;
                 if has_neg {
@@ -334,11 +366,16 @@ pub fn raw_quantity(quantity: &Quantity) -> f64 {
 
     let bytes = match suffix {
         "" => amt,
-        "m" => {
-            // this is the only branch that could actually produce a fraction, so we handle it
-            // specially
+        "m" | "u" | "n" => {
+            // these are the only branches that could actually produce a fraction, so we handle
+            // them specially. the metrics api reports cpu usage in nanocores, i.e. "1234567n".
+            let exp = match suffix {
+                "m" => 3,
+                "u" => 6,
+                _ => 9,
+            };
             let famt = amt as f64;
-            let famt = famt / (base10.pow(3) as f64);
+            let famt = famt / (base10.pow(exp) as f64);
             if has_neg {
                 return -famt;
             } else {
@@ -383,6 +420,67 @@ pub fn get_regex(matches: &ArgMatches) -> Result<Option<Regex>, String> {
     }
 }
 
+/// Columns aren't truncated to narrower than this (or their title), even if the table doesn't fit
+const MIN_TRUNCATED_WIDTH: usize = 8;
+
+/// The widest each column of a table can be for it to fit in width, given how wide its titles and
+/// its columns are, or None if it fits already. The widest columns are cut down first. Columns
+/// aren't cut narrower than their titles or MIN_TRUNCATED_WIDTH, so a table with many columns can
+/// still be too wide.
+fn fit_columns(title_widths: &[usize], widths: &[usize], width: usize) -> Option<Vec<usize>> {
+    // TBLFMT pads each column with a space on each side, and has no separators between them
+    let fits = |caps: &[usize]| caps.iter().sum::<usize>() + 2 * caps.len() <= width;
+    if fits(widths) {
+        return None;
+    }
+    let floor = |i: usize| {
+        title_widths
+            .get(i)
+            .copied()
+            .unwrap_or(0)
+            .max(MIN_TRUNCATED_WIDTH)
+            .min(widths[i])
+    };
+    let widest = widths.iter().copied().max().unwrap_or(0);
+    let mut caps = widths.to_vec();
+    for cap in (MIN_TRUNCATED_WIDTH..widest).rev() {
+        caps = widths
+            .iter()
+            .enumerate()
+            .map(|(i, w)| (*w).min(cap).max(floor(i)))
+            .collect();
+        if fits(&caps) {
+            break;
+        }
+    }
+    Some(caps)
+}
+
+/// How many characters the longest line in cell is
+fn cell_width(cell: &Cell) -> usize {
+    cell.get_content()
+        .lines()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0)
+}
+
+/// Cut each line of text down to at most max characters, ending the ones that are cut with '…'
+fn truncate(text: &str, max: usize) -> String {
+    text.lines()
+        .map(|line| {
+            if line.chars().count() > max {
+                let mut cut: String = line.chars().take(max.saturating_sub(1)).collect();
+                cut.push('…');
+                cut
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
 fn term_print_table<T: Write>(table: &Table, writer: &mut T) -> bool {
     match term::TerminfoTerminal::new(writer) {
         Some(ref mut term) => {
@@ -402,13 +500,62 @@ pub fn print_filled_table(table: &mut Table, writer: &mut ClickWriter) {
 
 #[allow(clippy::ptr_arg)]
 pub fn print_table(titles: Row, specs: Vec<Vec<CellSpec<'_>>>, writer: &mut ClickWriter) {
+    print_table_page(Some(titles), specs, 0, true, writer);
+}
+
+/// Print part of a table whose rows arrive a page at a time. Titles are given for the first page
+/// only, and the bottom line is only printed after the last one. Rows are numbered starting at
+/// first_index, so the numbers carry on from the previous pages.
+pub fn print_table_page(
+    titles: Option<Row>,
+    specs: Vec<Vec<CellSpec<'_>>>,
+    first_index: usize,
+    last: bool,
+    writer: &mut ClickWriter,
+) {
+    let mut rows: Vec<Vec<Cell>> = specs
+        .iter()
+        .enumerate()
+        .map(|(index, t_spec)| {
+            t_spec
+                .iter()
+                .map(|spec| spec.to_cell(first_index + index))
+                .collect()
+        })
+        .collect();
+    if let Some(width) = writer.table_width() {
+        let title_widths: Vec<usize> = titles
+            .as_ref()
+            .map(|titles| titles.iter().map(cell_width).collect())
+            .unwrap_or_default();
+        let mut widths = title_widths.clone();
+        for row in rows.iter() {
+            if widths.len() < row.len() {
+                widths.resize(row.len(), 0);
+            }
+            for (i, cell) in row.iter().enumerate() {
+                widths[i] = widths[i].max(cell_width(cell));
+            }
+        }
+        if let Some(caps) = fit_columns(&title_widths, &widths, width) {
+            for (row, t_spec) in rows.iter_mut().zip(specs.iter()) {
+                for ((cell, spec), cap) in row.iter_mut().zip(t_spec.iter()).zip(caps.iter()) {
+                    if cell_width(cell) > *cap {
+                        *cell = spec.cell_with_text(&truncate(&cell.get_content(), *cap));
+                    }
+                }
+            }
+        }
+    }
+
     let mut table = Table::new();
-    table.set_titles(titles);
-    for (index, t_spec) in specs.iter().enumerate() {
-        let row_vec: Vec<Cell> = t_spec.iter().map(|spec| spec.to_cell(index)).collect();
+    if let Some(titles) = titles {
+        table.set_titles(titles);
+    }
+    for row_vec in rows.into_iter() {
         table.add_row(Row::new(row_vec));
     }
-    table.set_format(*TBLFMT);
+    table.set_format(if last { *TBLFMT } else { *TBLFMT_OPEN });
     if !term_print_table(&table, writer) {
         table.print(writer).unwrap_or(0);
     }
@@ -416,13 +563,50 @@ pub fn print_table(titles: Row, specs: Vec<Vec<CellSpec<'_>>>, writer: &mut Clic
 
 #[cfg(test)]
 mod tests {
-    use crate::table::raw_quantity;
+    use crate::table::{fit_columns, raw_quantity, truncate};
     use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
 
+    #[test]
+    fn test_fit_columns() {
+        // 4 + 30 + 5, plus padding, is 45
+        assert_eq!(fit_columns(&[4, 4, 5], &[4, 30, 5], 45), None);
+        assert_eq!(
+            fit_columns(&[4, 4, 5], &[4, 30, 5], 35),
+            Some(vec![4, 20, 5])
+        );
+        // the widest is cut down first, then both
+        assert_eq!(
+            fit_columns(&[4, 4, 5], &[4, 30, 20], 50),
+            Some(vec![4, 20, 20])
+        );
+        assert_eq!(
+            fit_columns(&[4, 4, 5], &[4, 30, 20], 40),
+            Some(vec![4, 15, 15])
+        );
+        // never narrower than the title or MIN_TRUNCATED_WIDTH
+        assert_eq!(
+            fit_columns(&[4, 12, 4], &[4, 30, 20], 10),
+            Some(vec![4, 12, 8])
+        );
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("web-7d4b9c", 20), "web-7d4b9c");
+        assert_eq!(truncate("web-7d4b9c", 10), "web-7d4b9c");
+        assert_eq!(truncate("web-7d4b9c", 8), "web-7d4…");
+        assert_eq!(
+            truncate("app=web\nenvironment=prod", 8),
+            "app=web\nenviron…"
+        );
+    }
+
     #[test]
     fn test_raw_quantity() {
         assert_eq!(raw_quantity(&Quantity("1500m".to_string())), 1.5);
         assert_eq!(raw_quantity(&Quantity("-1500m".to_string())), -1.5);
+        assert_eq!(raw_quantity(&Quantity("250000000n".to_string())), 0.25);
+        assert_eq!(raw_quantity(&Quantity("1500u".to_string())), 0.0015);
         assert_eq!(raw_quantity(&Quantity("1Ki".to_string())), 1024.0);
         assert_eq!(raw_quantity(&Quantity("2Gi".to_string())), 2147483648.0);
         assert_eq!(raw_quantity(&Quantity("12e6".to_string())), 12000000.0);
@@ -0,0 +1,516 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Just enough of the tar format to copy files to and from containers. We write plain ustar, with
+//! gnu long name entries for paths that don't fit, and read what gnu tar and busybox tar produce
+//! (ustar, gnu long names, and pax path headers).
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+const BLOCK: usize = 512;
+const NAME_LEN: usize = 100;
+const GNU_LONG_NAME: &str = "././@LongLink";
+
+/// What's been copied so far
+#[derive(Debug, Default, PartialEq)]
+pub struct Progress {
+    pub files: u64,
+    pub bytes: u64,
+}
+
+/// The total size of the regular files under path, for reporting progress
+pub fn total_size(path: &Path) -> io::Result<u64> {
+    let meta = fs::symlink_metadata(path)?;
+    if meta.is_dir() {
+        let mut total = 0;
+        for entry in fs::read_dir(path)? {
+            total += total_size(&entry?.path())?;
+        }
+        Ok(total)
+    } else if meta.is_file() {
+        Ok(meta.len())
+    } else {
+        Ok(0)
+    }
+}
+
+#[cfg(unix)]
+fn file_mode(meta: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    meta.permissions().mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+fn file_mode(meta: &fs::Metadata) -> u32 {
+    if meta.is_dir() {
+        0o755
+    } else if meta.permissions().readonly() {
+        0o444
+    } else {
+        0o644
+    }
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> io::Result<()> {
+    Ok(())
+}
+
+/// Write num as a zero padded, nul terminated octal number into field. Numbers too big for that
+/// are written in the gnu base-256 format.
+fn write_number(field: &mut [u8], num: u64) {
+    let digits = field.len() - 1;
+    if num < 8u64.pow(digits as u32) {
+        let octal = format!("{:0width$o}", num, width = digits);
+        field[..digits].copy_from_slice(octal.as_bytes());
+        field[digits] = 0;
+    } else {
+        for (i, byte) in field.iter_mut().rev().enumerate() {
+            *byte = if i < 8 { (num >> (i * 8)) as u8 } else { 0 };
+        }
+        field[0] |= 0x80;
+    }
+}
+
+fn read_number(field: &[u8]) -> io::Result<u64> {
+    if field.first().is_some_and(|b| b & 0x80 != 0) {
+        let mut num = u64::from(field[0] & 0x7f);
+        for byte in field[1..].iter() {
+            num = (num << 8) | u64::from(*byte);
+        }
+        return Ok(num);
+    }
+    let text: String = field
+        .iter()
+        .take_while(|b| **b != 0)
+        .map(|b| *b as char)
+        .collect();
+    let text = text.trim();
+    if text.is_empty() {
+        Ok(0)
+    } else {
+        u64::from_str_radix(text, 8).map_err(|_| invalid(format!("bad number in header: {}", text)))
+    }
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// A nul terminated string field
+fn read_string(field: &[u8]) -> String {
+    let end = field.iter().position(|b| *b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+fn header(name: &str, mode: u32, size: u64, typeflag: u8, link: &str) -> [u8; BLOCK] {
+    let mut block = [0u8; BLOCK];
+    let name = name.as_bytes();
+    block[..name.len().min(NAME_LEN)].copy_from_slice(&name[..name.len().min(NAME_LEN)]);
+    write_number(&mut block[100..108], u64::from(mode));
+    write_number(&mut block[108..116], 0); // uid
+    write_number(&mut block[116..124], 0); // gid
+    write_number(&mut block[124..136], size);
+    write_number(&mut block[136..148], 0); // mtime
+    block[156] = typeflag;
+    let link = link.as_bytes();
+    block[157..157 + link.len().min(NAME_LEN)].copy_from_slice(&link[..link.len().min(NAME_LEN)]);
+    block[257..263].copy_from_slice(b"ustar\0");
+    block[263..265].copy_from_slice(b"00");
+    // the checksum is computed with the checksum field set to spaces
+    block[148..156].copy_from_slice(b"        ");
+    let sum: u64 = block.iter().map(|b| u64::from(*b)).sum();
+    let checksum = format!("{:06o}\0 ", sum);
+    block[148..156].copy_from_slice(checksum.as_bytes());
+    block
+}
+
+fn write_padding<W: Write>(out: &mut W, size: u64) -> io::Result<()> {
+    let rem = (size % BLOCK as u64) as usize;
+    if rem != 0 {
+        out.write_all(&[0u8; BLOCK][..BLOCK - rem])?;
+    }
+    Ok(())
+}
+
+/// Write a header for name, preceded by gnu long name/link entries if they're too long
+fn write_header<W: Write>(
+    out: &mut W,
+    name: &str,
+    mode: u32,
+    size: u64,
+    typeflag: u8,
+    link: &str,
+) -> io::Result<()> {
+    for (long, flag) in [(name, b'L'), (link, b'K')].iter() {
+        if long.len() > NAME_LEN {
+            let data = format!("{}\0", long);
+            out.write_all(&header(GNU_LONG_NAME, 0, data.len() as u64, *flag, ""))?;
+            out.write_all(data.as_bytes())?;
+            write_padding(out, data.len() as u64)?;
+        }
+    }
+    out.write_all(&header(name, mode, size, typeflag, link))
+}
+
+/// Add path (a file, directory, or symlink) to the archive as name. Directories are added
+/// recursively. progress is called after each file is written.
+pub fn append_path<W: Write, F: FnMut(&Progress)>(
+    out: &mut W,
+    path: &Path,
+    name: &str,
+    progress: &mut Progress,
+    on_progress: &mut F,
+) -> io::Result<()> {
+    let meta = fs::symlink_metadata(path)?;
+    let mode = file_mode(&meta);
+    if meta.file_type().is_symlink() {
+        let target = fs::read_link(path)?;
+        write_header(out, name, 0o777, 0, b'2', &target.to_string_lossy())?;
+    } else if meta.is_dir() {
+        write_header(out, &format!("{}/", name), mode, 0, b'5', "")?;
+        let mut entries: Vec<PathBuf> = fs::read_dir(path)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<_, _>>()?;
+        entries.sort();
+        for entry in entries.iter() {
+            let file_name = entry.file_name().unwrap().to_string_lossy(); // safe, from read_dir
+            let entry_name = format!("{}/{}", name, file_name);
+            append_path(out, entry, &entry_name, progress, on_progress)?;
+        }
+    } else if meta.is_file() {
+        let size = meta.len();
+        write_header(out, name, mode, size, b'0', "")?;
+        let mut file = fs::File::open(path)?;
+        let mut buf = vec![0u8; 64 * 1024];
+        let mut written = 0;
+        while written < size {
+            let max = buf.len().min((size - written) as usize);
+            let n = file.read(&mut buf[..max])?;
+            if n == 0 {
+                return Err(invalid(format!("{} shrank while copying", path.display())));
+            }
+            out.write_all(&buf[..n])?;
+            written += n as u64;
+            progress.bytes += n as u64;
+        }
+        write_padding(out, size)?;
+        progress.files += 1;
+        on_progress(progress);
+    }
+    // sockets, devices, and the like aren't copied
+    Ok(())
+}
+
+/// Mark the end of the archive
+pub fn finish<W: Write>(out: &mut W) -> io::Result<()> {
+    out.write_all(&[0u8; BLOCK * 2])?;
+    out.flush()
+}
+
+/// An entry that wasn't extracted, and why
+#[derive(Debug, PartialEq)]
+pub struct Skipped {
+    pub path: String,
+    pub reason: String,
+}
+
+/// Turn an archive path into a relative path under the archive's top level entry, which is
+/// renamed to dest. Returns None for paths that aren't under top, or that would escape it.
+fn dest_path(dest: &Path, top: &str, name: &str) -> Option<PathBuf> {
+    let name = name.trim_start_matches("./").trim_start_matches('/');
+    let rest = name.strip_prefix(top)?;
+    if !(rest.is_empty() || rest.starts_with('/')) {
+        return None;
+    }
+    let rest = Path::new(rest.trim_start_matches('/'));
+    if rest.as_os_str().is_empty() {
+        return Some(dest.to_path_buf());
+    }
+    if rest
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return None;
+    }
+    Some(dest.join(rest))
+}
+
+fn read_block<R: Read>(input: &mut R, block: &mut [u8; BLOCK]) -> io::Result<bool> {
+    let mut read = 0;
+    while read < BLOCK {
+        let n = input.read(&mut block[read..])?;
+        if n == 0 {
+            if read == 0 {
+                return Ok(false);
+            }
+            return Err(invalid(
+                "archive ended in the middle of a header".to_string(),
+            ));
+        }
+        read += n;
+    }
+    Ok(true)
+}
+
+fn read_data<R: Read>(input: &mut R, size: u64) -> io::Result<Vec<u8>> {
+    let mut data = vec![0u8; size as usize];
+    input.read_exact(&mut data)?;
+    skip_padding(input, size)?;
+    Ok(data)
+}
+
+fn skip_padding<R: Read>(input: &mut R, size: u64) -> io::Result<()> {
+    let rem = (size % BLOCK as u64) as usize;
+    if rem != 0 {
+        let mut pad = [0u8; BLOCK];
+        input.read_exact(&mut pad[..BLOCK - rem])?;
+    }
+    Ok(())
+}
+
+/// Get the path out of pax extended header records, which look like "<len> path=<value>\n"
+fn pax_path(data: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(data);
+    text.lines()
+        .filter_map(|line| line.split_once(' '))
+        .filter_map(|(_, record)| record.split_once('='))
+        .find(|(key, _)| *key == "path")
+        .map(|(_, value)| value.to_string())
+}
+
+/// Extract an archive whose entries are all under top (i.e. as made by `tar cf - -C dir top`),
+/// with top renamed to dest. File modes are preserved. Like kubectl, symlinks and hard links aren't
+/// extracted, and neither is anything that would end up outside dest; these are returned as
+/// skipped. progress is called after each file is written.
+pub fn extract<R: Read, F: FnMut(&Progress)>(
+    mut input: R,
+    dest: &Path,
+    top: &str,
+    on_progress: &mut F,
+) -> io::Result<(Progress, Vec<Skipped>)> {
+    let mut progress = Progress::default();
+    let mut skipped = vec![];
+    let mut long_name: Option<String> = None;
+    let mut block = [0u8; BLOCK];
+    // directory modes are set at the end, in case they don't allow writing into them
+    let mut dir_modes = vec![];
+    while read_block(&mut input, &mut block)? {
+        if block.iter().all(|b| *b == 0) {
+            break;
+        }
+        let size = read_number(&block[124..136])?;
+        let mode = read_number(&block[100..108])? as u32 & 0o7777;
+        let typeflag = block[156];
+        let name = match long_name.take() {
+            Some(name) => name,
+            None => {
+                let prefix = read_string(&block[345..500]);
+                let name = read_string(&block[..NAME_LEN]);
+                if &block[257..262] == b"ustar" && !prefix.is_empty() {
+                    format!("{}/{}", prefix, name)
+                } else {
+                    name
+                }
+            }
+        };
+        match typeflag {
+            b'L' => {
+                let data = read_data(&mut input, size)?;
+                long_name = Some(read_string(&data));
+                continue;
+            }
+            b'x' => {
+                let data = read_data(&mut input, size)?;
+                long_name = pax_path(&data);
+                continue;
+            }
+            b'K' | b'g' => {
+                read_data(&mut input, size)?;
+                continue;
+            }
+            _ => {}
+        }
+        let path = match dest_path(dest, top, name.trim_end_matches('/')) {
+            Some(path) => path,
+            None => {
+                skipped.push(Skipped {
+                    path: name,
+                    reason: "it is outside the directory being copied".to_string(),
+                });
+                io::copy(&mut (&mut input).take(size), &mut io::sink())?;
+                skip_padding(&mut input, size)?;
+                continue;
+            }
+        };
+        match typeflag {
+            b'5' => {
+                fs::create_dir_all(&path)?;
+                dir_modes.push((path, mode));
+            }
+            b'0' | b'\0' | b'7' => {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                let mut file = fs::File::create(&path)?;
+                let copied = io::copy(&mut (&mut input).take(size), &mut file)?;
+                if copied != size {
+                    return Err(invalid(format!("archive ended in the middle of {}", name)));
+                }
+                skip_padding(&mut input, size)?;
+                set_mode(&path, mode)?;
+                progress.files += 1;
+                progress.bytes += size;
+                on_progress(&progress);
+            }
+            _ => {
+                let reason = match typeflag {
+                    b'2' => format!(
+                        "symlinks aren't copied (it points to {})",
+                        read_string(&block[157..257])
+                    ),
+                    b'1' => "hard links aren't copied".to_string(),
+                    _ => "it isn't a regular file or directory".to_string(),
+                };
+                skipped.push(Skipped { path: name, reason });
+                io::copy(&mut (&mut input).take(size), &mut io::sink())?;
+                skip_padding(&mut input, size)?;
+            }
+        }
+    }
+    for (path, mode) in dir_modes.into_iter().rev() {
+        set_mode(&path, mode)?;
+    }
+    Ok((progress, skipped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_numbers() {
+        let mut field = [0u8; 12];
+        write_number(&mut field, 0o644);
+        assert_eq!(&field, b"00000000644\0");
+        assert_eq!(read_number(&field).unwrap(), 0o644);
+
+        // too big for 11 octal digits
+        let big = 10 * 1024 * 1024 * 1024;
+        write_number(&mut field, big);
+        assert_eq!(field[0], 0x80);
+        assert_eq!(read_number(&field).unwrap(), big);
+
+        assert_eq!(read_number(b"   644 \0").unwrap(), 0o644);
+        assert!(read_number(b"abc\0").is_err());
+    }
+
+    #[test]
+    fn test_dest_path() {
+        let dest = Path::new("/local/out");
+        assert_eq!(
+            dest_path(dest, "top", "top"),
+            Some(PathBuf::from("/local/out"))
+        );
+        assert_eq!(
+            dest_path(dest, "top", "./top/a/b"),
+            Some(PathBuf::from("/local/out/a/b"))
+        );
+        assert_eq!(dest_path(dest, "top", "topper/a"), None);
+        assert_eq!(dest_path(dest, "top", "other/a"), None);
+        assert_eq!(dest_path(dest, "top", "top/../../etc/passwd"), None);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let src = TempDir::new("click-tar-src").unwrap();
+        let root = src.path().join("data");
+        let long_dir = "d".repeat(120);
+        fs::create_dir_all(root.join("sub").join(&long_dir)).unwrap();
+        fs::write(root.join("a.txt"), b"hello").unwrap();
+        fs::write(root.join("sub").join("big"), vec![7u8; 70000]).unwrap();
+        fs::write(root.join("sub").join(&long_dir).join("f"), b"deep").unwrap();
+        set_mode(&root.join("a.txt"), 0o751).unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("a.txt", root.join("link")).unwrap();
+        assert_eq!(total_size(&root).unwrap(), 5 + 70000 + 4);
+
+        let mut archive = vec![];
+        let mut progress = Progress::default();
+        append_path(&mut archive, &root, "data", &mut progress, &mut |_| {}).unwrap();
+        finish(&mut archive).unwrap();
+        assert_eq!(progress.files, 3);
+        assert_eq!(archive.len() % BLOCK, 0);
+
+        let dst = TempDir::new("click-tar-dst").unwrap();
+        let out = dst.path().join("renamed");
+        let mut seen = 0;
+        let (progress, skipped) = extract(&archive[..], &out, "data", &mut |_| seen += 1).unwrap();
+        assert_eq!(
+            progress,
+            Progress {
+                files: 3,
+                bytes: 70009
+            }
+        );
+        assert_eq!(seen, 3);
+        assert_eq!(fs::read(out.join("a.txt")).unwrap(), b"hello");
+        assert_eq!(fs::read(out.join("sub").join("big")).unwrap().len(), 70000);
+        assert_eq!(
+            fs::read(out.join("sub").join(&long_dir).join("f")).unwrap(),
+            b"deep"
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(out.join("a.txt"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o751);
+            assert_eq!(skipped.len(), 1);
+            assert_eq!(skipped[0].path, "data/link");
+        }
+    }
+
+    #[test]
+    fn test_extract_single_file() {
+        let src = TempDir::new("click-tar-src").unwrap();
+        let file = src.path().join("notes");
+        fs::write(&file, b"some notes").unwrap();
+        let mut archive = vec![];
+        let mut progress = Progress::default();
+        append_path(&mut archive, &file, "notes", &mut progress, &mut |_| {}).unwrap();
+        // an entry that tries to escape
+        write_header(&mut archive, "notes/../../evil", 0o644, 0, b'0', "").unwrap();
+        finish(&mut archive).unwrap();
+
+        let dst = TempDir::new("click-tar-dst").unwrap();
+        let out = dst.path().join("copy");
+        let (progress, skipped) = extract(&archive[..], &out, "notes", &mut |_| {}).unwrap();
+        assert_eq!(progress.files, 1);
+        assert_eq!(fs::read(&out).unwrap(), b"some notes");
+        assert_eq!(skipped.len(), 1);
+        assert!(!dst.path().join("evil").exists());
+    }
+}
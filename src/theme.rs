@@ -0,0 +1,341 @@
+// Copyright 2021 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Color themes. Output is colored by what it means (a good status, a warning, etc.), and the
+//! theme decides what that looks like, so click is readable on both dark and light terminals.
+
+use ansi_term::{Colour, Style};
+use prettytable::{color, Attr};
+
+use crate::error::ClickError;
+
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+/// The things we color
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Role {
+    /// Section headers, like in help output
+    Header,
+    /// Statuses that are fine, like a Running pod
+    GoodStatus,
+    /// Statuses that are broken, like a Failed job
+    BadStatus,
+    /// Things worth noticing
+    Warning,
+    /// Errors from commands that failed
+    Error,
+    PromptContext,
+    PromptNamespace,
+    /// The selected object(s), in the prompt and wherever else it's highlighted
+    Selection,
+}
+
+pub const ROLES: &[Role] = &[
+    Role::Header,
+    Role::GoodStatus,
+    Role::BadStatus,
+    Role::Warning,
+    Role::Error,
+    Role::PromptContext,
+    Role::PromptNamespace,
+    Role::Selection,
+];
+
+pub const THEME_NAMES: &[&str] = &["dark", "light", "solarized", "gruvbox", "colorblind"];
+
+impl Role {
+    /// The name used for this role in overrides, i.e. in 'set theme.bad-status red'
+    pub fn name(&self) -> &'static str {
+        match self {
+            Role::Header => "header",
+            Role::GoodStatus => "good-status",
+            Role::BadStatus => "bad-status",
+            Role::Warning => "warning",
+            Role::Error => "error",
+            Role::PromptContext => "prompt-context",
+            Role::PromptNamespace => "prompt-namespace",
+            Role::Selection => "selection",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Role> {
+        ROLES.iter().find(|role| role.name() == name).copied()
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Theme {
+    styles: [Style; 8],
+}
+
+fn builtin(name: &str) -> Option<Theme> {
+    // in ROLES order
+    let styles = match name {
+        "dark" => [
+            Colour::Yellow.normal(),
+            Colour::Green.normal(),
+            Colour::Red.normal(),
+            Colour::Yellow.normal(),
+            Colour::Red.bold(),
+            Colour::Red.bold(),
+            Colour::Green.bold(),
+            Colour::Yellow.bold(),
+        ],
+        // darker colors that still show up on a white background
+        "light" => [
+            Colour::Blue.normal(),
+            Colour::Fixed(28).normal(),
+            Colour::Fixed(124).normal(),
+            Colour::Fixed(130).normal(),
+            Colour::Fixed(124).bold(),
+            Colour::Fixed(124).bold(),
+            Colour::Fixed(28).bold(),
+            Colour::Fixed(18).bold(),
+        ],
+        // the accent colors from the solarized palette
+        "solarized" => [
+            Colour::Fixed(33).normal(),
+            Colour::Fixed(64).normal(),
+            Colour::Fixed(160).normal(),
+            Colour::Fixed(136).normal(),
+            Colour::Fixed(160).bold(),
+            Colour::Fixed(160).bold(),
+            Colour::Fixed(64).bold(),
+            Colour::Fixed(37).bold(),
+        ],
+        // the warm colors of the gruvbox palette, for dark backgrounds
+        "gruvbox" => [
+            Colour::Fixed(214).normal(),
+            Colour::Fixed(142).normal(),
+            Colour::Fixed(167).normal(),
+            Colour::Fixed(208).normal(),
+            Colour::Fixed(167).bold(),
+            Colour::Fixed(167).bold(),
+            Colour::Fixed(142).bold(),
+            Colour::Fixed(214).bold(),
+        ],
+        // blue/orange rather than green/red, so good and bad can be told apart
+        "colorblind" => [
+            Colour::Cyan.normal(),
+            Colour::Fixed(33).normal(),
+            Colour::Fixed(208).bold(),
+            Colour::Yellow.normal(),
+            Colour::Fixed(208).bold(),
+            Colour::Fixed(208).bold(),
+            Colour::Fixed(33).bold(),
+            Colour::Yellow.bold(),
+        ],
+        _ => return None,
+    };
+    Some(Theme { styles })
+}
+
+fn parse_colour(name: &str) -> Option<Colour> {
+    match name {
+        "black" => Some(Colour::Black),
+        "red" => Some(Colour::Red),
+        "green" => Some(Colour::Green),
+        "yellow" => Some(Colour::Yellow),
+        "blue" => Some(Colour::Blue),
+        "purple" | "magenta" => Some(Colour::Purple),
+        "cyan" => Some(Colour::Cyan),
+        "white" => Some(Colour::White),
+        _ => name.parse::<u8>().ok().map(Colour::Fixed),
+    }
+}
+
+/// Parse a style like "red bold" or "208 on-black underline". Colors are the eight basic color
+/// names, or a number from the 256 color palette. "default" is no style at all.
+pub fn parse_style(spec: &str) -> Result<Style, ClickError> {
+    let mut style = Style::new();
+    for word in spec.split_whitespace() {
+        let word = word.to_lowercase();
+        style = match word.as_str() {
+            "default" | "none" | "plain" => style,
+            "bold" => style.bold(),
+            "dim" | "dimmed" => style.dimmed(),
+            "italic" => style.italic(),
+            "underline" => style.underline(),
+            _ => {
+                if let Some(bg) = word
+                    .strip_prefix("on-")
+                    .or_else(|| word.strip_prefix("on_"))
+                {
+                    match parse_colour(bg) {
+                        Some(colour) => style.on(colour),
+                        None => return Err(style_err(spec, bg)),
+                    }
+                } else {
+                    match parse_colour(&word) {
+                        Some(colour) => style.fg(colour),
+                        None => return Err(style_err(spec, &word)),
+                    }
+                }
+            }
+        };
+    }
+    Ok(style)
+}
+
+fn style_err(spec: &str, word: &str) -> ClickError {
+    ClickError::ParseErr(format!(
+        "Invalid style '{}': don't know what '{}' is. Use color names (red, green, ...), numbers \
+         from 0-255, on-<color> for a background, and bold, dim, italic, or underline",
+        spec, word
+    ))
+}
+
+impl Theme {
+    /// Get the named built-in theme with overrides (a map of role name -> style spec) applied
+    pub fn new(name: &str, overrides: &BTreeMap<String, String>) -> Result<Theme, ClickError> {
+        let mut theme = builtin(name).ok_or_else(|| {
+            ClickError::CommandError(format!(
+                "Unknown theme '{}'. Available themes are: {}",
+                name,
+                THEME_NAMES.join(", ")
+            ))
+        })?;
+        for (role_name, spec) in overrides.iter() {
+            let role = Role::from_name(role_name).ok_or_else(|| {
+                ClickError::CommandError(format!("Unknown theme role '{}'", role_name))
+            })?;
+            theme.styles[role.index()] = parse_style(spec)?;
+        }
+        Ok(theme)
+    }
+
+    pub fn style(&self, role: Role) -> Style {
+        self.styles[role.index()]
+    }
+
+    /// The style for role as attributes for a prettytable cell
+    pub fn cell_attrs(&self, role: Role) -> Vec<Attr> {
+        let style = self.style(role);
+        let mut attrs = vec![];
+        if let Some(colour) = style.foreground.and_then(term_colour) {
+            attrs.push(Attr::ForegroundColor(colour));
+        }
+        if let Some(colour) = style.background.and_then(term_colour) {
+            attrs.push(Attr::BackgroundColor(colour));
+        }
+        if style.is_bold {
+            attrs.push(Attr::Bold);
+        }
+        if style.is_dimmed {
+            attrs.push(Attr::Dim);
+        }
+        if style.is_italic {
+            attrs.push(Attr::Italic(true));
+        }
+        if style.is_underline {
+            attrs.push(Attr::Underline(true));
+        }
+        attrs
+    }
+}
+
+fn term_colour(colour: Colour) -> Option<color::Color> {
+    match colour {
+        Colour::Black => Some(color::BLACK),
+        Colour::Red => Some(color::RED),
+        Colour::Green => Some(color::GREEN),
+        Colour::Yellow => Some(color::YELLOW),
+        Colour::Blue => Some(color::BLUE),
+        Colour::Purple => Some(color::MAGENTA),
+        Colour::Cyan => Some(color::CYAN),
+        Colour::White => Some(color::WHITE),
+        Colour::Fixed(n) => Some(n as color::Color),
+        Colour::RGB(..) => None,
+    }
+}
+
+lazy_static! {
+    // The env owns the theme setting, but a lot of output (i.e. table cells) is built without
+    // access to the env, so the active theme is kept here
+    static ref ACTIVE: RwLock<Theme> = RwLock::new(builtin("dark").unwrap());
+}
+
+pub fn set_active(theme: Theme) {
+    *ACTIVE.write().unwrap() = theme;
+}
+
+/// The style for role in the active theme
+pub fn style(role: Role) -> Style {
+    ACTIVE.read().unwrap().style(role)
+}
+
+/// The cell attributes for role in the active theme
+pub fn cell_attrs(role: Role) -> Vec<Attr> {
+    ACTIVE.read().unwrap().cell_attrs(role)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_style() {
+        assert_eq!(parse_style("red bold").unwrap(), Colour::Red.bold());
+        assert_eq!(parse_style("Red").unwrap(), Colour::Red.normal());
+        assert_eq!(
+            parse_style("208 on-black underline").unwrap(),
+            Colour::Fixed(208).on(Colour::Black).underline()
+        );
+        assert_eq!(parse_style("default").unwrap(), Style::new());
+        assert!(parse_style("reddish").is_err());
+        assert!(parse_style("on-256").is_err());
+    }
+
+    #[test]
+    fn test_themes() {
+        let overrides = BTreeMap::new();
+        for name in THEME_NAMES.iter() {
+            assert!(Theme::new(name, &overrides).is_ok());
+        }
+        assert!(Theme::new("neon", &overrides).is_err());
+        assert_eq!(
+            Theme::new("gruvbox", &overrides)
+                .unwrap()
+                .style(Role::Error),
+            Colour::Fixed(167).bold()
+        );
+
+        let mut overrides = BTreeMap::new();
+        overrides.insert("bad-status".to_string(), "purple bold".to_string());
+        let theme = Theme::new("light", &overrides).unwrap();
+        assert_eq!(theme.style(Role::BadStatus), Colour::Purple.bold());
+        assert_eq!(theme.style(Role::Header), Colour::Blue.normal());
+        assert_eq!(
+            theme.cell_attrs(Role::BadStatus),
+            vec![Attr::ForegroundColor(color::MAGENTA), Attr::Bold]
+        );
+
+        overrides.insert("nope".to_string(), "red".to_string());
+        assert!(Theme::new("dark", &overrides).is_err());
+    }
+
+    #[test]
+    fn test_roles() {
+        assert_eq!(ROLES.len(), 8);
+        for (i, role) in ROLES.iter().enumerate() {
+            assert_eq!(role.index(), i);
+            assert_eq!(Role::from_name(role.name()), Some(*role));
+        }
+    }
+}
@@ -0,0 +1,306 @@
+#!/usr/bin/env python3
+#
+# Generate src/protobuf/convert.rs, which converts the types in k8s-pb (decoded from protobuf api
+# responses) to the k8s-openapi types click uses everywhere else.
+#
+# Usage: util/gen_protobuf.py PATH_TO_K8S_OPENAPI PATH_TO_K8S_PB > src/protobuf/convert.rs
+#        rustfmt src/protobuf/convert.rs
+#
+# where the paths are the crates' source directories, i.e. ~/.cargo/registry/src/*/k8s-openapi-0.12.0
+# and ~/.cargo/registry/src/*/k8s-pb-0.9.0. Fields are matched by name. A field k8s-openapi has but
+# k8s-pb doesn't (because it was removed from the api since 1.14) is left at its default, and is
+# listed on stderr so renames can be spotted and added to INLINED below.
+
+import os
+import re
+import sys
+
+OPENAPI_VERSION = "v1_14"
+
+# The list types to convert, as (module, kind). Everything they reference is converted too.
+ROOTS = [
+    ("api::apps::v1", "ControllerRevision"),
+    ("api::apps::v1", "DaemonSet"),
+    ("api::apps::v1", "Deployment"),
+    ("api::apps::v1", "ReplicaSet"),
+    ("api::apps::v1", "StatefulSet"),
+    ("api::core::v1", "ComponentStatus"),
+    ("api::core::v1", "ConfigMap"),
+    ("api::core::v1", "Endpoints"),
+    ("api::core::v1", "Event"),
+    ("api::core::v1", "LimitRange"),
+    ("api::core::v1", "Namespace"),
+    ("api::core::v1", "Node"),
+    ("api::core::v1", "PersistentVolume"),
+    ("api::core::v1", "PersistentVolumeClaim"),
+    ("api::core::v1", "Pod"),
+    ("api::core::v1", "PodTemplate"),
+    ("api::core::v1", "ReplicationController"),
+    ("api::core::v1", "ResourceQuota"),
+    ("api::core::v1", "Secret"),
+    ("api::core::v1", "Service"),
+    ("api::core::v1", "ServiceAccount"),
+    ("apimachinery::pkg::apis::meta::v1", "ListMeta"),
+    # errors come back as a Status
+    ("apimachinery::pkg::apis::meta::v1", "Status"),
+]
+
+# Types converted by hand in src/protobuf/mod.rs
+HANDWRITTEN = {
+    ("apimachinery::pkg::api::resource", "Quantity"),
+    ("apimachinery::pkg::apis::meta::v1", "MicroTime"),
+    ("apimachinery::pkg::apis::meta::v1", "Time"),
+    ("apimachinery::pkg::runtime", "RawExtension"),
+    ("apimachinery::pkg::util::intstr", "IntOrString"),
+    ("", "ByteString"),
+}
+
+# Types whose fields have since been moved into a field of their own in k8s-pb, as
+# (module, kind) -> the k8s-pb field the rest of the fields are found in
+INLINED = {
+    ("api::core::v1", "ConfigMapEnvSource"): "local_object_reference",
+    ("api::core::v1", "ConfigMapKeySelector"): "local_object_reference",
+    ("api::core::v1", "ConfigMapProjection"): "local_object_reference",
+    ("api::core::v1", "ConfigMapVolumeSource"): "local_object_reference",
+    ("api::core::v1", "PersistentVolumeSpec"): "persistent_volume_source",
+    ("api::core::v1", "Probe"): "handler",
+    ("api::core::v1", "SecretEnvSource"): "local_object_reference",
+    ("api::core::v1", "SecretKeySelector"): "local_object_reference",
+    ("api::core::v1", "SecretProjection"): "local_object_reference",
+    ("api::core::v1", "Volume"): "volume_source",
+}
+
+# Fields k8s-pb names differently, as (module, kind, field) -> the k8s-pb field
+RENAMED = {
+    ("api::core::v1", "FCVolumeSource", "target_wwns"): "target_ww_ns",
+}
+
+PRIMITIVES = {"String", "bool", "i32", "i64", "f64", "u8"}
+
+KEYWORDS = {"type", "continue", "ref", "as", "in", "match", "self", "struct", "use", "where"}
+
+
+def parse_type(s):
+    """Parse a rust type into (name, [args]), dropping paths from std types"""
+    s = s.strip()
+    m = re.match(r"^([^<]+)<(.*)>$", s, re.S)
+    if not m:
+        return (s, [])
+    name, inner = m.group(1).strip(), m.group(2)
+    args, depth, cur = [], 0, ""
+    for c in inner:
+        if c == "<":
+            depth += 1
+        elif c == ">":
+            depth -= 1
+        if c == "," and depth == 0:
+            args.append(cur)
+            cur = ""
+        else:
+            cur += c
+    if cur.strip():
+        args.append(cur)
+    return (name, [parse_type(a) for a in args])
+
+
+def simple_name(name):
+    return name.split("::")[-1]
+
+
+def parse_fields(body):
+    """Parse `pub name: Type,` fields out of a struct body, where Type might span lines"""
+    body = re.sub(r"^\s*(///|//|#\[).*$", "", body, flags=re.M)
+    fields = []
+    for m in re.finditer(r"pub ([a-z0-9_#]+):\s*(.*?),\s*(?=pub |$)", body, re.S):
+        name = m.group(1).replace("r#", "")
+        fields.append((name, parse_type(" ".join(m.group(2).split()))))
+    return fields
+
+
+def load_openapi(root):
+    """(module, name) -> [(field, type)] for every struct k8s-openapi generates from a definition"""
+    structs = {}
+    src = os.path.join(root, "src", OPENAPI_VERSION)
+    for dirpath, _, files in os.walk(src):
+        module = "::".join(os.path.relpath(dirpath, src).split(os.sep))
+        for f in files:
+            text = open(os.path.join(dirpath, f)).read()
+            if not text.startswith("// Generated from definition"):
+                continue
+            m = re.search(r"^pub struct (\w+) \{\n(.*?)^\}", text, re.S | re.M)
+            if m:
+                structs[(module, m.group(1))] = [
+                    (name.rstrip("_"), name, ty) for name, ty in parse_fields(m.group(2))
+                ]
+    return structs
+
+
+def load_pb(root):
+    """(module, name) -> {field: type} for every message in k8s-pb"""
+    structs = {}
+    src = os.path.join(root, "src")
+    for dirpath, _, files in os.walk(src):
+        if "mod.rs" not in files:
+            continue
+        module = "::".join(os.path.relpath(dirpath, src).split(os.sep))
+        text = open(os.path.join(dirpath, "mod.rs")).read()
+        for m in re.finditer(r"^pub struct (\w+) \{\n(.*?)^\}", text, re.S | re.M):
+            structs[(module, m.group(1))] = dict(parse_fields(m.group(2)))
+    return structs
+
+
+def openapi_ref(ty):
+    """The (module, name) an openapi type refers to"""
+    name = ty[0]
+    if name.startswith("crate::"):
+        parts = name[len("crate::"):].split("::")
+        return ("::".join(parts[:-1]), parts[-1])
+    return None
+
+
+def pb_ref(module, ty):
+    """The (module, name) a k8s-pb type refers to, resolving super:: from module"""
+    name = ty[0]
+    if name.startswith("::"):
+        return None
+    parts = name.split("::")
+    if len(parts) == 1:
+        return (module, name)
+    base = module.split("::")
+    while parts[0] == "super":
+        parts.pop(0)
+        base.pop()
+    return ("::".join(base + parts[:-1]), parts[-1])
+
+
+def rust_path(krate, ref):
+    module, name = ref
+    return "{}::{}::{}".format(krate, module, name) if module else "{}::{}".format(krate, name)
+
+
+class Generator:
+    def __init__(self, openapi, pb):
+        self.openapi = openapi
+        self.pb = pb
+        self.pending = []
+        self.done = set()
+        self.out = []
+        self.missing = []
+
+    def want(self, oref, pref):
+        if oref in HANDWRITTEN or (oref, pref) in self.done:
+            return
+        self.done.add((oref, pref))
+        self.pending.append((oref, pref))
+
+    def leaf(self, omod, oty, pmod, pty):
+        """Note that a conversion from pty to oty is needed, if it's one we generate"""
+        if oty[0] in PRIMITIVES:
+            return
+        oref = openapi_ref(oty)
+        if oref is None:
+            raise Exception("Don't know how to convert to {}".format(oty))
+        if oref in HANDWRITTEN:
+            return
+        pref = pb_ref(pmod, pty)
+        if pref is None or pref not in self.pb:
+            raise Exception("No protobuf type for {} from {}".format(oref, pty))
+        self.want(oref, pref)
+
+    def expr(self, omod, oty, pmod, pty, value):
+        """An expression converting value, of k8s-pb type pty, to k8s-openapi type oty"""
+        oname, pname = simple_name(oty[0]), simple_name(pty[0])
+        if oname == "Option" and pname == "Option":
+            inner = oty[1][0]
+            if simple_name(inner[0]) == "Vec" and pty[1][0][0] == "::prost::alloc::vec::Vec":
+                # bytes
+                if inner[1][0][0] != "u8":
+                    raise Exception("bytes for {}".format(oty))
+                return value
+            self.leaf(omod, inner, pmod, pty[1][0])
+            if inner[0] in PRIMITIVES:
+                return value
+            return "opt({})".format(value)
+        if oname == "Vec" and pname == "Vec":
+            self.leaf(omod, oty[1][0], pmod, pty[1][0])
+            if oty[1][0][0] in PRIMITIVES:
+                return value
+            return "vec({})".format(value)
+        if oname == "BTreeMap" and pname == "BTreeMap":
+            self.leaf(omod, oty[1][1], pmod, pty[1][1])
+            if oty[1][1][0] in PRIMITIVES:
+                return value
+            return "map({})".format(value)
+        if pname == "Option":
+            self.leaf(omod, oty, pmod, pty[1][0])
+            if oty[0] in PRIMITIVES:
+                return "{}.unwrap_or_default()".format(value)
+            return "or_default({})".format(value)
+        raise Exception("Can't convert {} to {}".format(pty, oty))
+
+    def generate(self, oref, pref):
+        ofields = self.openapi[oref]
+        pfields = self.pb[pref]
+        inline = INLINED.get(oref)
+        lines = []
+        fields = []
+        missing = []
+        if inline:
+            lines.append("        let {0} = p.{0}.unwrap_or_default();".format(inline))
+            inner_ref = pb_ref(pref[0], pfields[inline][1][0])
+            inner_fields = self.pb[inner_ref]
+        for name, rust_name, oty in ofields:
+            pname = RENAMED.get(oref + (name,), name)
+            if pname in pfields:
+                value = "p.{}".format("r#" + pname if pname in KEYWORDS else pname)
+                pty, pmod = pfields[pname], pref[0]
+            elif inline and pname in inner_fields:
+                value = "{}.{}".format(inline, pname)
+                pty, pmod = inner_fields[pname], inner_ref[0]
+            else:
+                missing.append(name)
+                continue
+            fields.append(
+                "            {}: {},".format(rust_name, self.expr(oref[0], oty, pmod, pty, value))
+            )
+        if missing:
+            self.missing.append("{}: {}".format(oref[1], ", ".join(missing)))
+            fields.append("            ..Default::default()")
+        arg = "p" if len(fields) > len(missing and [1]) else "_p"
+        self.out.append(
+            "impl FromPb<{}> for {} {{\n    fn from_pb({}: {}) -> Self {{\n{}{}        Self {{\n{}\n        }}\n    }}\n}}\n".format(
+                rust_path("pb", pref),
+                rust_path("openapi", oref),
+                arg,
+                rust_path("pb", pref),
+                "\n".join(lines),
+                "\n" if lines else "",
+                "\n".join(fields),
+            )
+        )
+
+    def run(self):
+        for oref in ROOTS:
+            self.want(oref, oref)
+        while self.pending:
+            oref, pref = self.pending.pop(0)
+            self.generate(oref, pref)
+        self.out.sort()
+
+
+def main():
+    if len(sys.argv) != 3:
+        sys.exit("usage: {} PATH_TO_K8S_OPENAPI PATH_TO_K8S_PB".format(sys.argv[0]))
+    gen = Generator(load_openapi(sys.argv[1]), load_pb(sys.argv[2]))
+    gen.run()
+    for missing in gen.missing:
+        print("not in k8s-pb, left at default: {}".format(missing), file=sys.stderr)
+    print("// Generated by util/gen_protobuf.py, don't edit by hand")
+    print()
+    print("use super::*;")
+    print()
+    print("\n".join(gen.out).rstrip())
+
+
+if __name__ == "__main__":
+    main()